@@ -539,6 +539,23 @@ impl<Window: WindowMethods> IOCompositor<Window> {
                 self.pending_paint_metrics.insert(pipeline_id, epoch);
             }
 
+            (Msg::WebDriverMouseButtonEvent(mouse_event_type, mouse_button, x, y),
+             ShutdownState::NotShuttingDown) => {
+                let dppx = self.page_zoom * self.hidpi_factor();
+                let point = TypedPoint2D::<f32, CSSPixel>::new(x, y) * dppx;
+                self.on_mouse_window_event_class(match mouse_event_type {
+                    MouseEventType::Click => MouseWindowEvent::Click(mouse_button, point),
+                    MouseEventType::MouseDown => MouseWindowEvent::MouseDown(mouse_button, point),
+                    MouseEventType::MouseUp => MouseWindowEvent::MouseUp(mouse_button, point),
+                });
+            }
+
+            (Msg::WebDriverMouseMoveEvent(x, y), ShutdownState::NotShuttingDown) => {
+                let dppx = self.page_zoom * self.hidpi_factor();
+                let point = TypedPoint2D::<f32, CSSPixel>::new(x, y) * dppx;
+                self.on_mouse_window_move_event_class(point);
+            }
+
             // When we are shutting_down, we need to avoid performing operations
             // such as Paint that may crash because we have begun tearing down
             // the rest of our resources.
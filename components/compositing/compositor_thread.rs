@@ -14,6 +14,7 @@ use net_traits::image::base::Image;
 use profile_traits::mem;
 use profile_traits::time;
 use script_traits::{AnimationState, ConstellationMsg, EventResult, LoadData};
+use script_traits::{MouseButton, MouseEventType};
 use servo_url::ServoUrl;
 use std::fmt::{Debug, Error, Formatter};
 use std::sync::mpsc::{Receiver, Sender};
@@ -138,6 +139,8 @@ pub enum EmbedderMsg {
     NewFavicon(TopLevelBrowsingContextId, ServoUrl),
     /// <head> tag finished parsing
     HeadParsed(TopLevelBrowsingContextId),
+    /// The selected text of a document changed, so it can be copied by the embedder.
+    SelectionChanged(TopLevelBrowsingContextId, String),
     /// The history state has changed.
     HistoryChanged(TopLevelBrowsingContextId, Vec<LoadData>, usize),
     /// Enter or exit fullscreen
@@ -193,7 +196,10 @@ pub enum Msg {
     PendingPaintMetric(PipelineId, Epoch),
     /// The load of a page has completed
     LoadComplete(TopLevelBrowsingContextId),
-
+    /// WebDriver mouse button event, at a point in CSS pixels.
+    WebDriverMouseButtonEvent(MouseEventType, MouseButton, f32, f32),
+    /// WebDriver mouse move event, to a point in CSS pixels.
+    WebDriverMouseMoveEvent(f32, f32),
 }
 
 impl Debug for Msg {
@@ -214,6 +220,8 @@ impl Debug for Msg {
             Msg::Dispatch(..) => write!(f, "Dispatch"),
             Msg::PendingPaintMetric(..) => write!(f, "PendingPaintMetric"),
             Msg::LoadComplete(..) => write!(f, "LoadComplete"),
+            Msg::WebDriverMouseButtonEvent(..) => write!(f, "WebDriverMouseButtonEvent"),
+            Msg::WebDriverMouseMoveEvent(..) => write!(f, "WebDriverMouseMoveEvent"),
         }
     }
 }
@@ -233,6 +241,7 @@ impl Debug for EmbedderMsg {
             EmbedderMsg::SetCursor(..) => write!(f, "SetCursor"),
             EmbedderMsg::NewFavicon(..) => write!(f, "NewFavicon"),
             EmbedderMsg::HeadParsed(..) => write!(f, "HeadParsed"),
+            EmbedderMsg::SelectionChanged(..) => write!(f, "SelectionChanged"),
             EmbedderMsg::HistoryChanged(..) => write!(f, "HistoryChanged"),
             EmbedderMsg::SetFullscreenState(..) => write!(f, "SetFullscreenState"),
             EmbedderMsg::LoadStart(..) => write!(f, "LoadStart"),
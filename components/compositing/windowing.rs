@@ -86,6 +86,8 @@ pub enum WindowEvent {
     SelectBrowser(TopLevelBrowsingContextId),
     /// Toggles a debug flag in WebRender
     ToggleWebRenderDebug(WebRenderDebugOption),
+    /// Save the network activity recorded so far as an HTTP Archive, if it is being recorded.
+    SaveHar,
 }
 
 impl Debug for WindowEvent {
@@ -111,6 +113,7 @@ impl Debug for WindowEvent {
             WindowEvent::CloseBrowser(..) => write!(f, "CloseBrowser"),
             WindowEvent::SelectBrowser(..) => write!(f, "SelectBrowser"),
             WindowEvent::ToggleWebRenderDebug(..) => write!(f, "ToggleWebRenderDebug"),
+            WindowEvent::SaveHar => write!(f, "SaveHar"),
         }
     }
 }
@@ -192,4 +195,8 @@ pub trait WindowMethods {
     /// will want to avoid blocking on UI events, and just
     /// run the event loop at the vsync interval.
     fn set_animation_state(&self, _state: AnimationState) {}
+
+    /// Called when the selected text of a document changed. Embedders that
+    /// implement their own copy commands should keep track of it.
+    fn selection_changed(&self, _ctx: TopLevelBrowsingContextId, _text: String) {}
 }
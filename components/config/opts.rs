@@ -120,13 +120,17 @@ pub struct Opts {
     pub profile_heartbeats: bool,
 
     /// `None` to disable debugger or `Some` with a port number to start a server to listen to
-    /// remote Firefox debugger connections.
+    /// remote Chrome DevTools Protocol clients.
     pub debugger_port: Option<u16>,
 
     /// `None` to disable devtools or `Some` with a port number to start a server to listen to
     /// remote Firefox devtools connections.
     pub devtools_port: Option<u16>,
 
+    /// `None` to not record network activity or `Some` with the path of an HTTP Archive to
+    /// save it to, on demand and at exit.
+    pub har_file: Option<String>,
+
     /// `None` to disable WebDriver or `Some` with a port number to start a server to listen to
     /// remote WebDriver commands.
     pub webdriver_port: Option<u16>,
@@ -431,6 +435,8 @@ pub enum OutputOptions {
     /// Database connection config (hostname, name, user, pass)
     DB(ServoUrl, Option<String>, Option<String>, Option<String>),
     FileName(String),
+    /// A JSON file in the Chrome Trace Event format, with a track per thread.
+    ChromeTrace(String),
     Stdout(f64),
 }
 
@@ -523,6 +529,7 @@ pub fn default_opts() -> Opts {
         trace_layout: false,
         debugger_port: None,
         devtools_port: None,
+        har_file: None,
         webdriver_port: None,
         initial_window_size: TypedSize2D::new(1024, 740),
         user_agent: default_user_agent_string(DEFAULT_USER_AGENT).into(),
@@ -569,9 +576,10 @@ pub fn from_cmdline_args(args: &[String]) -> ArgumentParsingResult {
     opts.optopt("o", "output", "Output file", "output.png");
     opts.optopt("s", "size", "Size of tiles", "512");
     opts.optopt("", "device-pixel-ratio", "Device pixels per px", "");
-    opts.optflagopt("p", "profile", "Time profiler flag and either a TSV output filename \
+    opts.optflagopt("p", "profile", "Time profiler flag and either a TSV output filename, \
+        a JSON output filename for a trace in the Chrome Trace Event format \
         OR an interval for output to Stdout (blank for Stdout with interval of 5s)", "10 \
-        OR time.tsv");
+        OR time.tsv OR trace.json");
     opts.optflagopt("", "profiler-trace-path",
                     "Path to dump a self-contained HTML timeline of profiler traces",
                     "");
@@ -588,6 +596,7 @@ pub fn from_cmdline_args(args: &[String]) -> ArgumentParsingResult {
     opts.optflag("F", "soft-fail", "Display about:failure on thread failure instead of exiting");
     opts.optflagopt("", "remote-debugging-port", "Start remote debugger server on port", "2794");
     opts.optflagopt("", "devtools", "Start remote devtools server on port", "6000");
+    opts.optopt("", "har-file", "Record network activity to an HTTP Archive at the given path", "servo.har");
     opts.optflagopt("", "webdriver", "Start remote WebDriver server on port", "7000");
     opts.optopt("", "resolution", "Set window resolution.", "1024x740");
     opts.optopt("u",
@@ -694,6 +703,7 @@ pub fn from_cmdline_args(args: &[String]) -> ArgumentParsingResult {
                         Ok(url) => Some(OutputOptions::DB(url, opt_match.opt_str("profiler-db-name"),
                                                           opt_match.opt_str("profiler-db-user"),
                                                           opt_match.opt_str("profiler-db-pass"))),
+                        Err(_) if argument.ends_with(".json") => Some(OutputOptions::ChromeTrace(argument)),
                         Err(_) => Some(OutputOptions::FileName(argument)),
                     }
                 }
@@ -821,6 +831,7 @@ pub fn from_cmdline_args(args: &[String]) -> ArgumentParsingResult {
         trace_layout: debug_options.trace_layout,
         debugger_port: debugger_port,
         devtools_port: devtools_port,
+        har_file: opt_match.opt_str("har-file"),
         webdriver_port: webdriver_port,
         initial_window_size: initial_window_size,
         user_agent: user_agent,
@@ -120,6 +120,7 @@ use msg::constellation_msg::{BrowsingContextId, TopLevelBrowsingContextId, Frame
 use msg::constellation_msg::{Key, KeyModifiers, KeyState};
 use msg::constellation_msg::{PipelineNamespace, PipelineNamespaceId, TraversalDirection};
 use net_traits::{self, IpcSend, FetchResponseMsg, ResourceThreads};
+use net_traits::indexeddb_thread::IndexedDBThreadMsg;
 use net_traits::pub_domains::reg_host;
 use net_traits::request::RequestInit;
 use net_traits::storage_thread::{StorageThreadMsg, StorageType};
@@ -135,6 +136,7 @@ use script_traits::{LayoutMsg as FromLayoutMsg, ScriptMsg as FromScriptMsg, Scri
 use script_traits::{LogEntry, ScriptToConstellationChan, ServiceWorkerMsg, webdriver_msg};
 use script_traits::{MozBrowserErrorType, MozBrowserEvent, WebDriverCommandMsg, WindowSizeData};
 use script_traits::{SWManagerMsg, ScopeThings, UpdatePipelineIdReason, WindowSizeType};
+use script_traits::{SimpleDialog, SimpleDialogResponse};
 use serde::{Deserialize, Serialize};
 use servo_config::opts;
 use servo_config::prefs::PREFS;
@@ -384,10 +386,22 @@ pub struct InitialConstellationState {
     pub supports_clipboard: bool,
 }
 
+/// A simple dialog that script is blocked on, waiting for webdriver to answer it.
+struct PendingSimpleDialog {
+    /// The dialog that was opened.
+    dialog: SimpleDialog,
+    /// The text sent to a prompt, returned if it is accepted.
+    text: Option<String>,
+    /// The channel to answer the dialog on.
+    sender: IpcSender<SimpleDialogResponse>,
+}
+
 /// Data needed for webdriver
 struct WebDriverData {
     load_channel: Option<(PipelineId, IpcSender<webdriver_msg::LoadStatus>)>,
     resize_channel: Option<IpcSender<WindowSizeData>>,
+    /// The simple dialogs waiting for webdriver to answer them, per top-level browsing context.
+    simple_dialogs: HashMap<TopLevelBrowsingContextId, PendingSimpleDialog>,
 }
 
 impl WebDriverData {
@@ -395,6 +409,7 @@ impl WebDriverData {
         WebDriverData {
             load_channel: None,
             resize_channel: None,
+            simple_dialogs: HashMap::new(),
         }
     }
 }
@@ -1051,9 +1066,9 @@ impl<Message, LTF, STF> Constellation<Message, LTF, STF>
                 debug!("constellation got CloseBrowser message");
                 self.handle_close_top_level_browsing_context(top_level_browsing_context_id);
             }
-            // Send frame tree to WebRender. Make it visible.
+            // Send frame tree to WebRender. Make it visible, and give it focus.
             FromCompositorMsg::SelectBrowser(top_level_browsing_context_id) => {
-                self.send_frame_tree(top_level_browsing_context_id);
+                self.handle_select_top_level_browsing_context(top_level_browsing_context_id);
             }
             // Handle a forward or back request
             FromCompositorMsg::TraverseHistory(top_level_browsing_context_id, direction) => {
@@ -1241,13 +1256,17 @@ impl<Message, LTF, STF> Constellation<Message, LTF, STF>
                 debug!("constellation got NodeStatus message");
                 self.embedder_proxy.send(EmbedderMsg::Status(source_top_ctx_id, message));
             }
+            FromScriptMsg::SelectionChanged(text) => {
+                debug!("constellation got SelectionChanged message");
+                self.embedder_proxy.send(EmbedderMsg::SelectionChanged(source_top_ctx_id, text));
+            }
             FromScriptMsg::SetDocumentState(state) => {
                 debug!("constellation got SetDocumentState message");
                 self.document_states.insert(source_pipeline_id, state);
             }
-            FromScriptMsg::Alert(message, sender) => {
-                debug!("constellation got Alert message");
-                self.handle_alert(source_top_ctx_id, message, sender);
+            FromScriptMsg::ShowSimpleDialog(dialog, sender) => {
+                debug!("constellation got ShowSimpleDialog message");
+                self.handle_show_simple_dialog(source_top_ctx_id, dialog, sender);
             }
             FromScriptMsg::GetClientWindow(send) => {
                 self.embedder_proxy.send(EmbedderMsg::GetClientWindow(source_top_ctx_id, send));
@@ -1416,16 +1435,13 @@ impl<Message, LTF, STF> Constellation<Message, LTF, STF>
         // Channels to receive signals when threads are done exiting.
         let (core_sender, core_receiver) = ipc::channel().expect("Failed to create IPC channel!");
         let (storage_sender, storage_receiver) = ipc::channel().expect("Failed to create IPC channel!");
+        let (indexeddb_sender, indexeddb_receiver) = ipc::channel().expect("Failed to create IPC channel!");
 
         debug!("Exiting core resource threads.");
         if let Err(e) = self.public_resource_threads.send(net_traits::CoreResourceMsg::Exit(core_sender)) {
             warn!("Exit resource thread failed ({})", e);
         }
 
-        if let Some(ref chan) = self.debugger_chan {
-            debugger::shutdown_server(chan);
-        }
-
         if let Some(ref chan) = self.devtools_chan {
             debug!("Exiting devtools.");
             let msg = DevtoolsControlMsg::FromChrome(ChromeToDevtoolsControlMsg::ServerExitMsg);
@@ -1434,10 +1450,17 @@ impl<Message, LTF, STF> Constellation<Message, LTF, STF>
             }
         }
 
+        if let Some(ref chan) = self.debugger_chan {
+            debugger::shutdown_server(chan);
+        }
+
         debug!("Exiting storage resource threads.");
         if let Err(e) = self.public_resource_threads.send(StorageThreadMsg::Exit(storage_sender)) {
             warn!("Exit storage thread failed ({})", e);
         }
+        if let Err(e) = self.public_resource_threads.send(IndexedDBThreadMsg::Exit(indexeddb_sender)) {
+            warn!("Exit IndexedDB thread failed ({})", e);
+        }
 
         debug!("Exiting bluetooth thread.");
         if let Err(e) = self.bluetooth_thread.send(BluetoothRequest::Exit) {
@@ -1478,6 +1501,9 @@ impl<Message, LTF, STF> Constellation<Message, LTF, STF>
         if let Err(e) = storage_receiver.recv() {
             warn!("Exit storage thread failed ({})", e);
         }
+        if let Err(e) = indexeddb_receiver.recv() {
+            warn!("Exit IndexedDB thread failed ({})", e);
+        }
 
         debug!("Asking compositor to complete shutdown.");
         self.compositor_proxy.send(ToCompositorMsg::ShutdownComplete);
@@ -1486,6 +1512,12 @@ impl<Message, LTF, STF> Constellation<Message, LTF, STF>
     fn handle_pipeline_exited(&mut self, pipeline_id: PipelineId) {
         debug!("Pipeline {:?} exited.", pipeline_id);
         self.pipelines.remove(&pipeline_id);
+        if let Some(ref chan) = self.devtools_chan {
+            let msg = DevtoolsControlMsg::FromChrome(ChromeToDevtoolsControlMsg::PipelineClosed(pipeline_id));
+            if let Err(e) = chan.send(msg) {
+                warn!("Sending pipeline closure to devtools failed ({})", e);
+            }
+        }
     }
 
     fn handle_send_error(&mut self, pipeline_id: PipelineId, err: IpcError) {
@@ -1636,8 +1668,20 @@ impl<Message, LTF, STF> Constellation<Message, LTF, STF>
         });
     }
 
+    fn handle_select_top_level_browsing_context(&mut self, top_level_browsing_context_id: TopLevelBrowsingContextId) {
+        let browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
+        match self.browsing_contexts.get(&browsing_context_id) {
+            Some(browsing_context) => self.focus_pipeline_id = Some(browsing_context.pipeline_id),
+            None => return warn!("Selected unknown top-level browsing context {}.", top_level_browsing_context_id),
+        }
+        self.send_frame_tree(top_level_browsing_context_id);
+    }
+
     fn handle_close_top_level_browsing_context(&mut self, top_level_browsing_context_id: TopLevelBrowsingContextId) {
         let browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
+        if let Some(pending) = self.webdriver.simple_dialogs.remove(&top_level_browsing_context_id) {
+            let _ = pending.sender.send(SimpleDialogResponse::Dismiss);
+        }
         self.close_browsing_context(browsing_context_id, ExitPipelineMode::Normal);
     }
 
@@ -1838,34 +1882,56 @@ impl<Message, LTF, STF> Constellation<Message, LTF, STF>
         }
     }
 
-    fn handle_alert(&mut self,
-                    top_level_browsing_context_id: TopLevelBrowsingContextId,
-                    message: String,
-                    sender: IpcSender<bool>) {
+    fn handle_show_simple_dialog(&mut self,
+                                 top_level_browsing_context_id: TopLevelBrowsingContextId,
+                                 dialog: SimpleDialog,
+                                 sender: IpcSender<SimpleDialogResponse>) {
+        // When webdriver is in control, the dialog stays open until webdriver answers it.
+        if opts::get().webdriver_port.is_some() {
+            let pending = PendingSimpleDialog {
+                dialog: dialog,
+                text: None,
+                sender: sender,
+            };
+            if let Some(previous) = self.webdriver.simple_dialogs.insert(top_level_browsing_context_id, pending) {
+                // Only one dialog can be open at a time; dismiss any leftover one.
+                let _ = previous.sender.send(SimpleDialogResponse::Dismiss);
+            }
+            return;
+        }
+
         let browser_pipeline_id = self.browsing_contexts.get(&BrowsingContextId::from(top_level_browsing_context_id))
             .and_then(|browsing_context| self.pipelines.get(&browsing_context.pipeline_id))
             .and_then(|pipeline| pipeline.parent_info)
             .map(|(browser_pipeline_id, _)| browser_pipeline_id);
         let mozbrowser_modal_prompt = PREFS.is_mozbrowser_enabled() && browser_pipeline_id.is_some();
 
-        if mozbrowser_modal_prompt {
+        let response = if mozbrowser_modal_prompt {
             // https://developer.mozilla.org/en-US/docs/Web/Events/mozbrowsershowmodalprompt
-            let prompt_type = String::from("alert");
-            let title = String::from("Alert");
-            let return_value = String::from("");
+            let (prompt_type, title, message, return_value) = match dialog {
+                SimpleDialog::Alert(message) =>
+                    (String::from("alert"), String::from("Alert"), message, String::from("")),
+                SimpleDialog::Confirm(message) =>
+                    (String::from("confirm"), String::from("Confirm"), message, String::from("")),
+                SimpleDialog::Prompt(message, default) =>
+                    (String::from("prompt"), String::from("Prompt"), message, default),
+            };
             let event = MozBrowserEvent::ShowModalPrompt(prompt_type, title, message, return_value);
             match browser_pipeline_id.and_then(|id| self.pipelines.get(&id)) {
-                None => warn!("Alert sent after browser pipeline closure."),
+                None => warn!("Simple dialog sent after browser pipeline closure."),
                 Some(pipeline) => pipeline.trigger_mozbrowser_event(Some(top_level_browsing_context_id), event),
             }
-        }
+            SimpleDialogResponse::Dismiss
+        } else {
+            SimpleDialogResponse::Display
+        };
 
-        let result = sender.send(!mozbrowser_modal_prompt);
+        let result = sender.send(response);
         if let Err(e) = result {
             let ctx_id = BrowsingContextId::from(top_level_browsing_context_id);
             let pipeline_id = match self.browsing_contexts.get(&ctx_id) {
                 Some(ctx) => ctx.pipeline_id,
-                None => return warn!("Alert sent for unknown browsing context."),
+                None => return warn!("Simple dialog sent for unknown browsing context."),
             };
             self.handle_send_error(pipeline_id, e);
         }
@@ -2260,6 +2326,17 @@ impl<Message, LTF, STF> Constellation<Message, LTF, STF>
         }
     }
 
+    /// Whether the browsing context belongs to the given top-level browsing context, and that
+    /// is the one the compositor is showing, so that events it hit-tests can reach it.
+    fn is_displayed(&self,
+                    top_level_browsing_context_id: TopLevelBrowsingContextId,
+                    browsing_context_id: BrowsingContextId)
+                    -> bool {
+        self.active_browser_id == Some(top_level_browsing_context_id) &&
+            self.browsing_contexts.get(&browsing_context_id)
+                .map_or(false, |browsing_context| browsing_context.top_level_id == top_level_browsing_context_id)
+    }
+
     fn handle_webdriver_msg(&mut self, msg: WebDriverCommandMsg) {
         // Find the script channel for the given parent pipeline,
         // and pass the event to that script thread.
@@ -2267,6 +2344,24 @@ impl<Message, LTF, STF> Constellation<Message, LTF, STF>
             WebDriverCommandMsg::GetWindowSize(_, reply) => {
                let _ = reply.send(self.window_size);
             },
+            WebDriverCommandMsg::GetWindowRect(top_level_browsing_context_id, reply) => {
+                self.embedder_proxy.send(EmbedderMsg::GetClientWindow(top_level_browsing_context_id, reply));
+            },
+            WebDriverCommandMsg::SetWindowPosition(top_level_browsing_context_id, point) => {
+                self.embedder_proxy.send(EmbedderMsg::MoveTo(top_level_browsing_context_id, point));
+            },
+            WebDriverCommandMsg::GetScreenAvailSize(top_level_browsing_context_id, reply) => {
+                self.embedder_proxy.send(EmbedderMsg::GetScreenAvailSize(top_level_browsing_context_id, reply));
+            },
+            WebDriverCommandMsg::GetTopLevelBrowsingContexts(reply) => {
+                let mut top_level_browsing_context_ids: Vec<TopLevelBrowsingContextId> = self.browsing_contexts
+                    .values()
+                    .filter(|browsing_context| browsing_context.id == browsing_context.top_level_id)
+                    .map(|browsing_context| browsing_context.top_level_id)
+                    .collect();
+                top_level_browsing_context_ids.sort();
+                let _ = reply.send(top_level_browsing_context_ids);
+            },
             WebDriverCommandMsg::SetWindowSize(top_level_browsing_context_id, size, reply) => {
                 self.webdriver.resize_channel = Some(reply);
                 self.embedder_proxy.send(EmbedderMsg::ResizeTo(top_level_browsing_context_id, size));
@@ -2313,6 +2408,49 @@ impl<Message, LTF, STF> Constellation<Message, LTF, STF>
                     }
                 }
             },
+            WebDriverCommandMsg::MouseButtonAction(top_level_browsing_context_id, browsing_context_id,
+                                                   mouse_event_type, mouse_button, x, y) => {
+                if !self.is_displayed(top_level_browsing_context_id, browsing_context_id) {
+                    return warn!("Browsing context {} MouseButtonAction while not displayed.", browsing_context_id);
+                }
+                self.compositor_proxy.send(ToCompositorMsg::WebDriverMouseButtonEvent(mouse_event_type,
+                                                                                      mouse_button,
+                                                                                      x, y));
+            },
+            WebDriverCommandMsg::MouseMoveAction(top_level_browsing_context_id, browsing_context_id, x, y) => {
+                if !self.is_displayed(top_level_browsing_context_id, browsing_context_id) {
+                    return warn!("Browsing context {} MouseMoveAction while not displayed.", browsing_context_id);
+                }
+                self.compositor_proxy.send(ToCompositorMsg::WebDriverMouseMoveEvent(x, y));
+            },
+            WebDriverCommandMsg::GetSimpleDialog(top_level_browsing_context_id, reply) => {
+                let dialog = self.webdriver.simple_dialogs.get(&top_level_browsing_context_id)
+                    .map(|pending| pending.dialog.clone());
+                let _ = reply.send(dialog);
+            },
+            WebDriverCommandMsg::SetSimpleDialogText(top_level_browsing_context_id, text) => {
+                if let Some(pending) = self.webdriver.simple_dialogs.get_mut(&top_level_browsing_context_id) {
+                    pending.text = Some(text);
+                }
+            },
+            WebDriverCommandMsg::CloseSimpleDialog(top_level_browsing_context_id, accept) => {
+                let pending = match self.webdriver.simple_dialogs.remove(&top_level_browsing_context_id) {
+                    Some(pending) => pending,
+                    None => return warn!("No simple dialog open in {}.", top_level_browsing_context_id),
+                };
+                let response = if accept {
+                    let value = match pending.dialog {
+                        SimpleDialog::Prompt(_, default) => pending.text.unwrap_or(default),
+                        _ => String::new(),
+                    };
+                    SimpleDialogResponse::Accept(value)
+                } else {
+                    SimpleDialogResponse::Dismiss
+                };
+                if let Err(e) = pending.sender.send(response) {
+                    warn!("Failed to answer simple dialog ({}).", e);
+                }
+            },
             WebDriverCommandMsg::TakeScreenshot(_, reply) => {
                 self.compositor_proxy.send(ToCompositorMsg::CreatePng(reply));
             },
@@ -0,0 +1,454 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A connection to a protocol client, and the methods it can call.
+
+use {Client, State};
+use devtools_traits::{DevtoolScriptControlMsg, EvaluateJSReply, Modification, NodeInfo};
+use ipc_channel::ipc::{self, IpcSender};
+use msg::constellation_msg::PipelineId;
+use serde_json::{self, Map, Value};
+use std::collections::HashMap;
+use std::str;
+use std::sync::{Arc, Mutex};
+use ws::{self, CloseCode, Handler, Handshake, Request, Response};
+
+/// The error codes of JSON-RPC, which the protocol uses.
+const INVALID_PARAMS: i64 = -32602;
+const METHOD_NOT_FOUND: i64 = -32601;
+const SERVER_ERROR: i64 = -32000;
+
+/// Prefix of the paths that clients connect to to debug a given page.
+const TARGET_PATH: &'static str = "/devtools/page/";
+
+/// The origins of the devtools front-ends that may connect.
+const ALLOWED_ORIGINS: &'static [&'static str] = &["devtools://devtools", "chrome-devtools://devtools"];
+
+struct ProtocolError {
+    code: i64,
+    message: String,
+}
+
+impl ProtocolError {
+    fn new(code: i64, message: &str) -> ProtocolError {
+        ProtocolError {
+            code: code,
+            message: message.to_owned(),
+        }
+    }
+
+    fn invalid_params() -> ProtocolError {
+        ProtocolError::new(INVALID_PARAMS, "Invalid parameters")
+    }
+
+    /// The error for a request the page's script thread can't answer, because the page was
+    /// closed.
+    fn page_went_away() -> ProtocolError {
+        ProtocolError::new(SERVER_ERROR, "The page went away")
+    }
+}
+
+type MethodResult = Result<Value, ProtocolError>;
+
+/// A node the client knows about, by its protocol id.
+struct KnownNode {
+    unique_id: String,
+    attributes: Vec<(String, String)>,
+}
+
+pub struct Connection {
+    sender: ws::Sender,
+    state: Arc<Mutex<State>>,
+    /// The page being debugged, or `None` to follow the page that was loaded last.
+    pipeline: Option<PipelineId>,
+    node_ids: HashMap<String, u64>,
+    nodes: HashMap<u64, KnownNode>,
+    next_node_id: u64,
+}
+
+impl Connection {
+    pub(crate) fn new(sender: ws::Sender, state: Arc<Mutex<State>>) -> Connection {
+        Connection {
+            sender: sender,
+            state: state,
+            pipeline: None,
+            node_ids: HashMap::new(),
+            nodes: HashMap::new(),
+            next_node_id: 1,
+        }
+    }
+
+    /// The page being debugged, and the channel to its script thread.
+    fn target(&self) -> Result<(PipelineId, IpcSender<DevtoolScriptControlMsg>), ProtocolError> {
+        let state = self.state.lock().unwrap();
+        state.target(self.pipeline)
+             .map(|target| (target.pipeline, target.script_chan.clone()))
+             .ok_or(ProtocolError::new(SERVER_ERROR, "No page to debug"))
+    }
+
+    fn update_client<F: FnOnce(&mut Client)>(&self, update: F) {
+        if let Some(client) = self.state.lock().unwrap().clients.get_mut(&self.sender.token()) {
+            update(client);
+        }
+    }
+
+    fn send_event(&self, method: &str, params: Value) -> ws::Result<()> {
+        self.sender.send(json!({ "method": method, "params": params }).to_string())
+    }
+
+    fn handle_method(&mut self, method: &str, params: &Map<String, Value>) -> MethodResult {
+        match method {
+            "Target.getTargets" => {
+                let state = self.state.lock().unwrap();
+                let infos: Vec<Value> = state.targets.iter().map(|target| json!({
+                    "targetId": target.pipeline.to_string(),
+                    "type": "page",
+                    "title": target.title,
+                    "url": target.url,
+                    "attached": false,
+                })).collect();
+                Ok(json!({ "targetInfos": infos }))
+            }
+
+            "Runtime.enable" => {
+                let (pipeline, script_chan) = self.target()?;
+                script_chan.send(DevtoolScriptControlMsg::WantsLiveNotifications(pipeline, true))
+                           .map_err(|_| ProtocolError::page_went_away())?;
+                self.update_client(|client| client.runtime_enabled = true);
+                let url = self.state.lock().unwrap().target(Some(pipeline)).map(|target| target.url.clone());
+                let _ = self.send_event("Runtime.executionContextCreated", json!({
+                    "context": { "id": 1, "origin": url, "name": "" },
+                }));
+                Ok(json!({}))
+            }
+
+            "Runtime.disable" => {
+                self.update_client(|client| client.runtime_enabled = false);
+                Ok(json!({}))
+            }
+
+            "Runtime.evaluate" => {
+                let expression = string_param(params, "expression")?;
+                let result = self.evaluate(expression)?;
+                Ok(json!({ "result": remote_object(result) }))
+            }
+
+            "Page.enable" | "Page.disable" | "DOM.enable" | "DOM.disable" => Ok(json!({})),
+
+            // There is no message to navigate a page, so this is done from script.
+            "Page.navigate" => {
+                let url = string_param(params, "url")?;
+                let expression = format!("location.assign({})", Value::String(url));
+                self.evaluate(expression)?;
+                let (pipeline, _) = self.target()?;
+                Ok(json!({ "frameId": pipeline.to_string() }))
+            }
+
+            "Page.reload" => {
+                let (pipeline, script_chan) = self.target()?;
+                script_chan.send(DevtoolScriptControlMsg::Reload(pipeline))
+                           .map_err(|_| ProtocolError::page_went_away())?;
+                Ok(json!({}))
+            }
+
+            "DOM.getDocument" => {
+                let depth = params.get("depth").and_then(|depth| depth.as_i64()).unwrap_or(1);
+                let (pipeline, script_chan) = self.target()?;
+                let (tx, rx) = ipc::channel().map_err(|_| ProtocolError::page_went_away())?;
+                script_chan.send(DevtoolScriptControlMsg::GetRootNode(pipeline, tx))
+                           .map_err(|_| ProtocolError::page_went_away())?;
+                let root = rx.recv().map_err(|_| ProtocolError::page_went_away())?
+                             .ok_or(ProtocolError::new(SERVER_ERROR, "No document"))?;
+                let root = self.node(&script_chan, pipeline, root, depth)?;
+                Ok(json!({ "root": root }))
+            }
+
+            "DOM.requestChildNodes" => {
+                let node_id = u64_param(params, "nodeId")?;
+                let depth = params.get("depth").and_then(|depth| depth.as_i64()).unwrap_or(1);
+                let unique_id = self.known_node(node_id)?.unique_id.clone();
+                let (pipeline, script_chan) = self.target()?;
+                let children = self.children(&script_chan, pipeline, unique_id, depth)?;
+                let _ = self.send_event("DOM.setChildNodes", json!({
+                    "parentId": node_id,
+                    "nodes": children,
+                }));
+                Ok(json!({}))
+            }
+
+            "DOM.getAttributes" => {
+                let node = self.known_node(u64_param(params, "nodeId")?)?;
+                Ok(json!({ "attributes": flatten_attributes(&node.attributes) }))
+            }
+
+            "DOM.setAttributeValue" | "DOM.removeAttribute" => {
+                let node_id = u64_param(params, "nodeId")?;
+                let name = string_param(params, "name")?;
+                let value = if method == "DOM.setAttributeValue" {
+                    Some(string_param(params, "value")?)
+                } else {
+                    None
+                };
+                let (pipeline, script_chan) = self.target()?;
+                let node = self.nodes.get_mut(&node_id).ok_or(ProtocolError::new(SERVER_ERROR, "Unknown node"))?;
+                node.attributes.retain(|&(ref attribute, _)| *attribute != name);
+                if let Some(ref value) = value {
+                    node.attributes.push((name.clone(), value.clone()));
+                }
+                let modification = Modification {
+                    attributeName: name,
+                    newValue: value,
+                };
+                script_chan.send(DevtoolScriptControlMsg::ModifyAttribute(pipeline,
+                                                                          node.unique_id.clone(),
+                                                                          vec![modification]))
+                           .map_err(|_| ProtocolError::page_went_away())?;
+                Ok(json!({}))
+            }
+
+            "Network.enable" => {
+                self.update_client(|client| client.network_enabled = true);
+                Ok(json!({}))
+            }
+
+            "Network.disable" => {
+                self.update_client(|client| client.network_enabled = false);
+                Ok(json!({}))
+            }
+
+            "Network.getResponseBody" => {
+                let request_id = string_param(params, "requestId")?;
+                let state = self.state.lock().unwrap();
+                let body = state.requests.get(&request_id)
+                                         .and_then(|request| request.body.as_ref())
+                                         .ok_or(ProtocolError::new(SERVER_ERROR, "No body for this request"))?;
+                Ok(json!({
+                    "body": String::from_utf8_lossy(body),
+                    "base64Encoded": false,
+                }))
+            }
+
+            _ => Err(ProtocolError::new(METHOD_NOT_FOUND, &format!("'{}' wasn't found", method))),
+        }
+    }
+
+    fn evaluate(&self, expression: String) -> Result<EvaluateJSReply, ProtocolError> {
+        let (pipeline, script_chan) = self.target()?;
+        let (tx, rx) = ipc::channel().map_err(|_| ProtocolError::page_went_away())?;
+        script_chan.send(DevtoolScriptControlMsg::EvaluateJS(pipeline, expression, tx))
+                   .map_err(|_| ProtocolError::page_went_away())?;
+        rx.recv().map_err(|_| ProtocolError::page_went_away())
+    }
+
+    fn known_node(&self, node_id: u64) -> Result<&KnownNode, ProtocolError> {
+        self.nodes.get(&node_id).ok_or(ProtocolError::new(SERVER_ERROR, "Unknown node"))
+    }
+
+    /// The protocol form of the given node, with its descendants down to the given depth
+    /// (-1 for all of them).
+    fn node(&mut self,
+            script_chan: &IpcSender<DevtoolScriptControlMsg>,
+            pipeline: PipelineId,
+            info: NodeInfo,
+            depth: i64) -> MethodResult {
+        let attributes: Vec<(String, String)> =
+            info.attrs.iter().map(|attr| (attr.name.clone(), attr.value.clone())).collect();
+        let node_id = match self.node_ids.get(&info.uniqueId) {
+            Some(&node_id) => node_id,
+            None => {
+                let node_id = self.next_node_id;
+                self.next_node_id += 1;
+                self.node_ids.insert(info.uniqueId.clone(), node_id);
+                node_id
+            }
+        };
+        self.nodes.insert(node_id, KnownNode {
+            unique_id: info.uniqueId.clone(),
+            attributes: attributes.clone(),
+        });
+
+        let local_name = if info.nodeType == 1 { info.nodeName.to_lowercase() } else { String::new() };
+        let mut node = json!({
+            "nodeId": node_id,
+            "backendNodeId": node_id,
+            "nodeType": info.nodeType,
+            "nodeName": info.nodeName,
+            "localName": local_name,
+            "nodeValue": info.shortValue,
+            "childNodeCount": info.numChildren,
+        });
+        {
+            let node = node.as_object_mut().unwrap();
+            match info.nodeType {
+                // Elements.
+                1 => {
+                    node.insert("attributes".to_owned(), flatten_attributes(&attributes));
+                }
+                // Documents.
+                9 => {
+                    node.insert("documentURL".to_owned(), Value::String(info.baseURI.clone()));
+                    node.insert("baseURL".to_owned(), Value::String(info.baseURI.clone()));
+                }
+                // Doctypes.
+                10 => {
+                    node.insert("publicId".to_owned(), Value::String(info.publicId.clone()));
+                    node.insert("systemId".to_owned(), Value::String(info.systemId.clone()));
+                }
+                _ => {}
+            }
+            if depth != 0 && info.numChildren > 0 {
+                let children = self.children(script_chan, pipeline, info.uniqueId, depth)?;
+                node.insert("children".to_owned(), children);
+            }
+        }
+        Ok(node)
+    }
+
+    fn children(&mut self,
+                script_chan: &IpcSender<DevtoolScriptControlMsg>,
+                pipeline: PipelineId,
+                unique_id: String,
+                depth: i64) -> MethodResult {
+        let (tx, rx) = ipc::channel().map_err(|_| ProtocolError::page_went_away())?;
+        script_chan.send(DevtoolScriptControlMsg::GetChildren(pipeline, unique_id, tx))
+                   .map_err(|_| ProtocolError::page_went_away())?;
+        let children = rx.recv().map_err(|_| ProtocolError::page_went_away())?
+                         .ok_or(ProtocolError::new(SERVER_ERROR, "Unknown node"))?;
+        let mut nodes = vec![];
+        for child in children {
+            nodes.push(self.node(script_chan, pipeline, child, depth - 1)?);
+        }
+        Ok(Value::Array(nodes))
+    }
+}
+
+fn string_param(params: &Map<String, Value>, name: &str) -> Result<String, ProtocolError> {
+    params.get(name).and_then(|value| value.as_str()).map(|value| value.to_owned())
+          .ok_or(ProtocolError::invalid_params())
+}
+
+fn u64_param(params: &Map<String, Value>, name: &str) -> Result<u64, ProtocolError> {
+    params.get(name).and_then(|value| value.as_u64()).ok_or(ProtocolError::invalid_params())
+}
+
+/// Attributes are sent as a flat list of names and values.
+fn flatten_attributes(attributes: &[(String, String)]) -> Value {
+    Value::Array(attributes.iter().flat_map(|&(ref name, ref value)| {
+        vec![Value::String(name.clone()), Value::String(value.clone())]
+    }).collect())
+}
+
+/// The protocol form of a value from script.
+pub fn remote_object(value: EvaluateJSReply) -> Value {
+    match value {
+        EvaluateJSReply::VoidValue => json!({ "type": "undefined" }),
+        EvaluateJSReply::NullValue => json!({ "type": "object", "subtype": "null", "value": null }),
+        EvaluateJSReply::BooleanValue(value) => json!({ "type": "boolean", "value": value }),
+        EvaluateJSReply::NumberValue(value) => {
+            let unserializable = if value.is_nan() {
+                "NaN"
+            } else if value.is_infinite() {
+                if value < 0. { "-Infinity" } else { "Infinity" }
+            } else if value == 0. && value.is_sign_negative() {
+                "-0"
+            } else {
+                return json!({ "type": "number", "value": value, "description": value.to_string() });
+            };
+            json!({ "type": "number", "unserializableValue": unserializable, "description": unserializable })
+        }
+        EvaluateJSReply::StringValue(value) => json!({ "type": "string", "value": value }),
+        EvaluateJSReply::ActorValue { class, uuid } => json!({
+            "type": "object",
+            "className": class,
+            "description": class,
+            "objectId": uuid,
+        }),
+    }
+}
+
+/// Whether a handshake may come from the given `Origin`. Web pages send their own origin, and
+/// must not be able to connect, so only clients that send none and the devtools front-ends of
+/// browsers are allowed.
+pub fn is_allowed_origin(origin: Option<&[u8]>) -> bool {
+    match origin {
+        None => true,
+        Some(origin) => ALLOWED_ORIGINS.iter().any(|allowed| allowed.as_bytes() == origin),
+    }
+}
+
+/// Whether a handshake was sent to the server by the given `Host`, which is only listening on the
+/// loopback interface. This stops pages from reaching it through DNS rebinding.
+pub fn is_local_host(host: Option<&[u8]>) -> bool {
+    let host = match host.and_then(|host| str::from_utf8(host).ok()) {
+        Some(host) => host,
+        None => return false,
+    };
+    let hostname = if host.starts_with('[') {
+        host.find(']').map_or(host, |end| &host[..end + 1])
+    } else {
+        host.split(':').next().unwrap_or(host)
+    };
+    hostname.eq_ignore_ascii_case("localhost") || hostname == "127.0.0.1" || hostname == "[::1]"
+}
+
+impl Handler for Connection {
+    fn on_request(&mut self, request: &Request) -> ws::Result<Response> {
+        let origin = request.header("Origin").map(|origin| &origin[..]);
+        let host = request.header("Host").map(|host| &host[..]);
+        if !is_allowed_origin(origin) || !is_local_host(host) {
+            warn!("Refused a debugger connection from {:?} to {:?}.",
+                  origin.map(String::from_utf8_lossy), host.map(String::from_utf8_lossy));
+            return Ok(Response::new(403, "Forbidden", b"Not allowed to debug this browser".to_vec()));
+        }
+        if request.resource().starts_with(TARGET_PATH) {
+            let id = &request.resource()[TARGET_PATH.len()..];
+            let state = self.state.lock().unwrap();
+            match state.targets.iter().find(|target| target.pipeline.to_string() == id) {
+                Some(target) => self.pipeline = Some(target.pipeline),
+                None => return Ok(Response::new(404, "Not Found", b"No such page".to_vec())),
+            }
+        }
+        Response::from_request(request)
+    }
+
+    fn on_open(&mut self, _: Handshake) -> ws::Result<()> {
+        debug!("Connection opened.");
+        let mut state = self.state.lock().unwrap();
+        state.network_listeners.add();
+        state.clients.insert(self.sender.token(), Client {
+            sender: self.sender.clone(),
+            pipeline: self.pipeline,
+            runtime_enabled: false,
+            network_enabled: false,
+        });
+        Ok(())
+    }
+
+    fn on_close(&mut self, _: CloseCode, _: &str) {
+        debug!("Connection closed.");
+        let mut state = self.state.lock().unwrap();
+        if state.clients.remove(&self.sender.token()).is_some() {
+            state.network_listeners.remove();
+        }
+    }
+
+    fn on_message(&mut self, message: ws::Message) -> ws::Result<()> {
+        let message = message.as_text().ok().and_then(|text| serde_json::from_str::<Map<String, Value>>(text).ok());
+        let message = match message {
+            Some(message) => message,
+            None => return self.sender.send(json!({
+                "error": { "code": INVALID_PARAMS, "message": "Message must be a JSON object" },
+            }).to_string()),
+        };
+        let id = message.get("id").cloned().unwrap_or(Value::Null);
+        let method = message.get("method").and_then(|method| method.as_str()).unwrap_or("");
+        let empty = Map::new();
+        let params = message.get("params").and_then(|params| params.as_object()).unwrap_or(&empty);
+        let response = match self.handle_method(method, params) {
+            Ok(result) => json!({ "id": id, "result": result }),
+            Err(error) => json!({ "id": id, "error": { "code": error.code, "message": error.message } }),
+        };
+        self.sender.send(response.to_string())
+    }
+}
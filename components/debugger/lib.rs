@@ -2,46 +2,327 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+//! A remote debugging server speaking a subset of the [Chrome DevTools Protocol]
+//! (https://chromedevtools.github.io/devtools-protocol/) over websockets: the Runtime, Page, DOM
+//! and Network domains, mapped onto the messages the Firefox devtools server uses.
+//!
+//! The server sees every message script and net send to devtools, and passes them on to the
+//! Firefox devtools server if there is one. Clients connect to `/devtools/page/<target>` to debug
+//! a given page, or to any other path to debug the page that was loaded last.
+
+extern crate devtools_traits;
+extern crate hyper;
+extern crate ipc_channel;
 #[macro_use]
 extern crate log;
+extern crate msg;
+#[macro_use]
+extern crate serde_json;
+extern crate time;
 extern crate ws;
 
+mod connection;
+
+pub use connection::{is_allowed_origin, is_local_host, remote_object};
+
+use connection::Connection;
+use devtools_traits::{ChromeToDevtoolsControlMsg, ConsoleMessage, DevtoolScriptControlMsg};
+use devtools_traits::{DevtoolsControlMsg, LogLevel, NetworkEvent, NetworkListeners, ScriptToDevtoolsControlMsg};
+use ipc_channel::ipc::IpcSender;
+use msg::constellation_msg::PipelineId;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
 use std::sync::mpsc;
 use std::sync::mpsc::channel;
 use std::thread;
-use ws::{Builder, CloseCode, Handler, Handshake};
+use ws::Builder;
+use ws::util::Token;
 
 enum Message {
     ShutdownServer,
 }
 
-pub struct Sender(mpsc::Sender<Message>);
+pub struct Sender {
+    sender: mpsc::Sender<Message>,
+    devtools_sender: mpsc::Sender<DevtoolsControlMsg>,
+}
+
+impl Sender {
+    /// The channel that script and net should send their devtools messages to for this server
+    /// to see them.
+    pub fn devtools_chan(&self) -> mpsc::Sender<DevtoolsControlMsg> {
+        self.devtools_sender.clone()
+    }
+}
+
+/// A page that can be debugged.
+struct Target {
+    pipeline: PipelineId,
+    title: String,
+    url: String,
+    script_chan: IpcSender<DevtoolScriptControlMsg>,
+}
 
-struct Connection {
-    sender: ws::Sender
+/// A connected client, and the domains it enabled events for.
+struct Client {
+    sender: ws::Sender,
+    /// The page being debugged, or `None` to follow the page that was loaded last.
+    pipeline: Option<PipelineId>,
+    runtime_enabled: bool,
+    network_enabled: bool,
 }
 
-impl Handler for Connection {
-    fn on_open(&mut self, _: Handshake) -> ws::Result<()> {
-        debug!("Connection opened.");
-        Ok(())
+/// The number of requests whose bodies are kept for `Network.getResponseBody`.
+const MAX_REQUESTS: usize = 500;
+
+/// A request seen by the Network domain.
+struct NetworkRequest {
+    pipeline: PipelineId,
+    url: String,
+    body: Option<Vec<u8>>,
+}
+
+/// The state shared between the connections and the thread watching devtools messages.
+struct State {
+    /// The pages that can be debugged, in the order they were loaded.
+    targets: Vec<Target>,
+    clients: HashMap<Token, Client>,
+    requests: HashMap<String, NetworkRequest>,
+    /// The ids of `requests`, oldest first, so that the oldest ones can be forgotten.
+    request_order: VecDeque<String>,
+    /// Shared with the resource threads, which only send response bodies while a client is connected.
+    network_listeners: NetworkListeners,
+}
+
+impl State {
+    fn target(&self, pipeline: Option<PipelineId>) -> Option<&Target> {
+        match pipeline {
+            Some(pipeline) => self.targets.iter().find(|target| target.pipeline == pipeline),
+            None => self.targets.last(),
+        }
     }
 
-    fn on_close(&mut self, _: CloseCode, _: &str) {
-        debug!("Connection closed.");
+    /// Send the given event to the clients debugging the given page that enabled it.
+    fn broadcast<F>(&self, pipeline: PipelineId, enabled: F, method: &str, params: Value)
+        where F: Fn(&Client) -> bool
+    {
+        let event = json!({ "method": method, "params": params }).to_string();
+        let latest = self.targets.last().map(|target| target.pipeline);
+        for client in self.clients.values() {
+            if enabled(client) && client.pipeline.or(latest) == Some(pipeline) {
+                let _ = client.sender.send(event.clone());
+            }
+        }
     }
 
-    fn on_message(&mut self, message: ws::Message) -> ws::Result<()> {
-        self.sender.send(message)
+    fn handle_devtools_message(&mut self, message: &DevtoolsControlMsg) {
+        match *message {
+            DevtoolsControlMsg::FromScript(ScriptToDevtoolsControlMsg::NewGlobal((pipeline, None),
+                                                                                 ref script_chan,
+                                                                                 ref page_info)) => {
+                self.targets.retain(|target| target.pipeline != pipeline);
+                self.targets.push(Target {
+                    pipeline: pipeline,
+                    title: page_info.title.clone(),
+                    url: page_info.url.as_str().to_owned(),
+                    script_chan: script_chan.clone(),
+                });
+            }
+            DevtoolsControlMsg::FromScript(ScriptToDevtoolsControlMsg::ConsoleAPI(pipeline, ref message, None)) => {
+                self.broadcast(pipeline, |client| client.runtime_enabled,
+                               "Runtime.consoleAPICalled", console_api_called(message));
+            }
+            DevtoolsControlMsg::FromChrome(ChromeToDevtoolsControlMsg::NetworkEvent(ref request_id,
+                                                                                    ref event)) => {
+                self.handle_network_event(request_id, event);
+            }
+            DevtoolsControlMsg::FromChrome(ChromeToDevtoolsControlMsg::PipelineClosed(pipeline)) => {
+                self.targets.retain(|target| target.pipeline != pipeline);
+                let requests = &mut self.requests;
+                requests.retain(|_, request| request.pipeline != pipeline);
+                self.request_order.retain(|request_id| requests.contains_key(request_id));
+            }
+            _ => {}
+        }
     }
+
+    fn handle_network_event(&mut self, request_id: &str, event: &NetworkEvent) {
+        let timestamp = time::precise_time_s();
+        match *event {
+            NetworkEvent::HttpRequest(ref request) => {
+                if self.requests.len() >= MAX_REQUESTS {
+                    if let Some(oldest) = self.request_order.pop_front() {
+                        self.requests.remove(&oldest);
+                    }
+                }
+                let previous = self.requests.insert(request_id.to_owned(), NetworkRequest {
+                    pipeline: request.pipeline_id,
+                    url: request.url.as_str().to_owned(),
+                    body: None,
+                });
+                if previous.is_none() {
+                    self.request_order.push_back(request_id.to_owned());
+                }
+                let params = json!({
+                    "requestId": request_id,
+                    "loaderId": "",
+                    "documentURL": request.url.as_str(),
+                    "request": {
+                        "url": request.url.as_str(),
+                        "method": request.method.to_string(),
+                        "headers": headers_to_json(request.headers.iter()),
+                    },
+                    "timestamp": timestamp,
+                    "wallTime": request.startedDateTime.to_timespec().sec,
+                    "type": if request.is_xhr { "XHR" } else { "Other" },
+                });
+                self.broadcast(request.pipeline_id, |client| client.network_enabled,
+                               "Network.requestWillBeSent", params);
+            }
+            NetworkEvent::HttpResponse(ref response) => {
+                let url = self.requests.get(request_id).map_or(String::new(), |request| request.url.clone());
+                let (status, status_text) = match response.status {
+                    Some((code, ref text)) => (code, String::from_utf8_lossy(text).into_owned()),
+                    None => (0, String::new()),
+                };
+                let headers = response.headers.as_ref().map_or(json!({}), |headers| headers_to_json(headers.iter()));
+                let params = json!({
+                    "requestId": request_id,
+                    "loaderId": "",
+                    "timestamp": timestamp,
+                    "type": "Other",
+                    "response": {
+                        "url": url,
+                        "status": status,
+                        "statusText": status_text,
+                        "headers": headers,
+                        "mimeType": response.headers.as_ref()
+                                                    .and_then(|headers| headers.get_raw("content-type"))
+                                                    .and_then(|values| values.first())
+                                                    .map_or(String::new(),
+                                                            |value| String::from_utf8_lossy(value).into_owned()),
+                    },
+                });
+                self.broadcast(response.pipeline_id, |client| client.network_enabled,
+                               "Network.responseReceived", params);
+            }
+            NetworkEvent::ResponseBodyChunk(pipeline_id, ref chunk) => {
+                if let Some(request) = self.requests.get_mut(request_id) {
+                    request.body.get_or_insert(vec![]).extend_from_slice(chunk);
+                }
+                let params = json!({
+                    "requestId": request_id,
+                    "timestamp": timestamp,
+                    "dataLength": chunk.len(),
+                    "encodedDataLength": chunk.len(),
+                });
+                self.broadcast(pipeline_id, |client| client.network_enabled, "Network.dataReceived", params);
+            }
+            NetworkEvent::ResponseEnd(pipeline_id, _) => {
+                let length = self.requests.get_mut(request_id).map_or(0, |request| {
+                    request.body.get_or_insert(vec![]).len()
+                });
+                let params = json!({
+                    "requestId": request_id,
+                    "timestamp": timestamp,
+                    "encodedDataLength": length,
+                });
+                self.broadcast(pipeline_id, |client| client.network_enabled, "Network.loadingFinished", params);
+            }
+        }
+    }
+}
+
+fn headers_to_json<'a, I: Iterator<Item = hyper::header::HeaderView<'a>>>(headers: I) -> Value {
+    let mut map = serde_json::Map::new();
+    for header in headers {
+        map.insert(header.name().to_owned(), Value::String(header.value_string()));
+    }
+    Value::Object(map)
+}
+
+fn console_api_called(message: &ConsoleMessage) -> Value {
+    let args = if message.arguments.is_empty() {
+        vec![json!({ "type": "string", "value": message.message })]
+    } else {
+        message.arguments.iter().map(|argument| remote_object(argument.value.clone())).collect()
+    };
+    let call_frames: Vec<Value> = match message.stacktrace {
+        Some(ref frames) => frames.iter().map(|frame| json!({
+            "functionName": frame.callee.clone().unwrap_or_default(),
+            "scriptId": "",
+            "url": frame.url,
+            "lineNumber": frame.line,
+            "columnNumber": frame.column,
+        })).collect(),
+        None => vec![json!({
+            "functionName": "",
+            "scriptId": "",
+            "url": message.filename,
+            "lineNumber": message.lineNumber,
+            "columnNumber": message.columnNumber,
+        })],
+    };
+    json!({
+        "type": match message.logLevel {
+            LogLevel::Log => "log",
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warning",
+            LogLevel::Error => "error",
+            LogLevel::Trace => "trace",
+            LogLevel::Dir => "dir",
+            LogLevel::Table => "table",
+            LogLevel::Assert => "assert",
+            LogLevel::Group => "startGroup",
+            LogLevel::GroupCollapsed => "startGroupCollapsed",
+            LogLevel::GroupEnd => "endGroup",
+            LogLevel::Count => "count",
+            LogLevel::Time | LogLevel::TimeLog => "log",
+            LogLevel::TimeEnd => "timeEnd",
+        },
+        "args": args,
+        "executionContextId": 1,
+        "timestamp": time::precise_time_s() * 1000.,
+        "stackTrace": {
+            "callFrames": call_frames,
+        },
+    })
 }
 
-pub fn start_server(port: u16) -> Sender {
+/// Start the server on the given port. The devtools messages it sees are passed on to
+/// `devtools_chan`, if any. Connected clients are counted in `network_listeners`.
+pub fn start_server(port: u16,
+                    devtools_chan: Option<mpsc::Sender<DevtoolsControlMsg>>,
+                    network_listeners: NetworkListeners)
+                    -> Sender {
     debug!("Starting server.");
     let (sender, receiver) = channel();
+    let (devtools_sender, devtools_receiver) = channel();
+    let state = Arc::new(Mutex::new(State {
+        targets: vec![],
+        clients: HashMap::new(),
+        requests: HashMap::new(),
+        request_order: VecDeque::new(),
+        network_listeners: network_listeners,
+    }));
+
+    let devtools_state = state.clone();
+    thread::Builder::new().name("debugger-devtools".to_owned()).spawn(move || {
+        while let Ok(message) = devtools_receiver.recv() {
+            devtools_state.lock().unwrap().handle_devtools_message(&message);
+            if let Some(ref chan) = devtools_chan {
+                if chan.send(message).is_err() {
+                    warn!("Failed to pass message on to devtools.");
+                }
+            }
+        }
+    }).expect("Thread spawning failed");
+
     thread::Builder::new().name("debugger".to_owned()).spawn(move || {
-        let socket = Builder::new().build(|sender: ws::Sender| {
-            Connection { sender: sender }
+        let socket = Builder::new().build(move |sender: ws::Sender| {
+            Connection::new(sender, state.clone())
         }).unwrap();
         let sender = socket.broadcaster();
         thread::Builder::new().name("debugger-websocket".to_owned()).spawn(move || {
@@ -56,13 +337,15 @@ pub fn start_server(port: u16) -> Sender {
         }
         sender.shutdown().unwrap();
     }).expect("Thread spawning failed");
-    Sender(sender)
+    Sender {
+        sender: sender,
+        devtools_sender: devtools_sender,
+    }
 }
 
 pub fn shutdown_server(sender: &Sender) {
     debug!("Shutting down server.");
-    let &Sender(ref sender) = sender;
-    if let Err(_) = sender.send(Message::ShutdownServer) {
+    if let Err(_) = sender.sender.send(Message::ShutdownServer) {
         warn!("Failed to shut down server.");
     }
 }
@@ -8,15 +8,13 @@
 //! inspection, JS evaluation, autocompletion) in Servo.
 
 use actor::{Actor, ActorMessageStatus, ActorRegistry};
-use actors::object::ObjectActor;
-use devtools_traits::{CachedConsoleMessageTypes, DevtoolScriptControlMsg};
+use actors::object::value_to_grip;
+use devtools_traits::{BlockedUrls, CachedConsoleMessageTypes, DevtoolScriptControlMsg};
 use devtools_traits::CachedConsoleMessage;
-use devtools_traits::EvaluateJSReply::{ActorValue, BooleanValue, StringValue};
-use devtools_traits::EvaluateJSReply::{NullValue, NumberValue, VoidValue};
 use ipc_channel::ipc::{self, IpcSender};
 use msg::constellation_msg::PipelineId;
 use protocol::JsonPacketStream;
-use serde_json::{self, Map, Number, Value};
+use serde_json::{self, Map, Value};
 use std::cell::RefCell;
 use std::net::TcpStream;
 
@@ -82,11 +80,24 @@ struct SetPreferencesReply {
     updated: Vec<String>,
 }
 
+#[derive(Serialize)]
+struct BlockedUrlsReply {
+    from: String,
+    urls: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct BlockingReply {
+    from: String,
+}
+
 pub struct ConsoleActor {
     pub name: String,
     pub pipeline: PipelineId,
     pub script_chan: IpcSender<DevtoolScriptControlMsg>,
     pub streams: RefCell<Vec<TcpStream>>,
+    /// The URLs that requests are blocked to, which all tabs share.
+    pub blocked_urls: BlockedUrls,
 }
 
 impl Actor for ConsoleActor {
@@ -178,55 +189,7 @@ impl Actor for ConsoleActor {
                 self.script_chan.send(DevtoolScriptControlMsg::EvaluateJS(
                     self.pipeline, input.clone(), chan)).unwrap();
 
-                //TODO: extract conversion into protocol module or some other useful place
-                let result = match port.recv().map_err(|_| ())? {
-                    VoidValue => {
-                        let mut m = Map::new();
-                        m.insert("type".to_owned(), Value::String("undefined".to_owned()));
-                        Value::Object(m)
-                    }
-                    NullValue => {
-                        let mut m = Map::new();
-                        m.insert("type".to_owned(), Value::String("null".to_owned()));
-                        Value::Object(m)
-                    }
-                    BooleanValue(val) => Value::Bool(val),
-                    NumberValue(val) => {
-                        if val.is_nan() {
-                            let mut m = Map::new();
-                            m.insert("type".to_owned(), Value::String("NaN".to_owned()));
-                            Value::Object(m)
-                        } else if val.is_infinite() {
-                            let mut m = Map::new();
-                            if val < 0. {
-                                m.insert("type".to_owned(), Value::String("-Infinity".to_owned()));
-                            } else {
-                                m.insert("type".to_owned(), Value::String("Infinity".to_owned()));
-                            }
-                            Value::Object(m)
-                        } else if val == 0. && val.is_sign_negative() {
-                            let mut m = Map::new();
-                            m.insert("type".to_owned(), Value::String("-0".to_owned()));
-                            Value::Object(m)
-                        } else {
-                            Value::Number(Number::from_f64(val).unwrap())
-                        }
-                    }
-                    StringValue(s) => Value::String(s),
-                    ActorValue { class, uuid } => {
-                        //TODO: make initial ActorValue message include these properties?
-                        let mut m = Map::new();
-                        let actor = ObjectActor::new(registry, uuid);
-
-                        m.insert("type".to_owned(), Value::String("object".to_owned()));
-                        m.insert("class".to_owned(), Value::String(class));
-                        m.insert("actor".to_owned(), Value::String(actor));
-                        m.insert("extensible".to_owned(), Value::Bool(true));
-                        m.insert("frozen".to_owned(), Value::Bool(false));
-                        m.insert("sealed".to_owned(), Value::Bool(false));
-                        Value::Object(m)
-                    }
-                };
+                let result = value_to_grip(registry, port.recv().map_err(|_| ())?);
 
                 //TODO: catch and return exception values from JS evaluation
                 let msg = EvaluateJSReply {
@@ -251,6 +214,37 @@ impl Actor for ConsoleActor {
                 ActorMessageStatus::Processed
             }
 
+            "getBlockedUrls" => {
+                let msg = BlockedUrlsReply {
+                    from: self.name(),
+                    urls: self.blocked_urls.get(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            }
+
+            "setBlockedUrls" => {
+                let urls = msg.get("urls").and_then(|urls| urls.as_array()).ok_or(())?;
+                self.blocked_urls.set(urls.iter().filter_map(|url| url.as_str()).map(str::to_owned).collect());
+                stream.write_json_packet(&BlockingReply { from: self.name() });
+                ActorMessageStatus::Processed
+            }
+
+            "blockRequest" | "unblockRequest" => {
+                let url = msg.get("filter")
+                             .and_then(|filter| filter.get("url"))
+                             .and_then(|url| url.as_str())
+                             .ok_or(())?;
+                let mut urls = self.blocked_urls.get();
+                urls.retain(|blocked| blocked != url);
+                if msg_type == "blockRequest" {
+                    urls.push(url.to_owned());
+                }
+                self.blocked_urls.set(urls);
+                stream.write_json_packet(&BlockingReply { from: self.name() });
+                ActorMessageStatus::Processed
+            }
+
             _ => ActorMessageStatus::Ignored
         })
     }
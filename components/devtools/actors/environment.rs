@@ -0,0 +1,106 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Liberally derived from the [Firefox JS implementation]
+//! (http://mxr.mozilla.org/mozilla-central/source/devtools/server/actors/environment.js).
+//! A scope of a paused frame, and the variables bound in it.
+
+use actor::{Actor, ActorMessageStatus, ActorRegistry};
+use actors::object::value_to_grip;
+use devtools_traits::ScopeInfo;
+use protocol::JsonPacketStream;
+use serde_json::{Map, Value};
+use std::net::TcpStream;
+
+#[derive(Serialize)]
+struct FunctionMsg {
+    name: String,
+}
+
+#[derive(Serialize)]
+pub struct EnvironmentForm {
+    actor: String,
+    #[serde(rename = "type")]
+    type_: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    function: Option<FunctionMsg>,
+    bindings: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent: Option<Box<EnvironmentForm>>,
+}
+
+#[derive(Serialize)]
+struct BindingsReply {
+    from: String,
+    bindings: Value,
+}
+
+pub struct EnvironmentActor {
+    name: String,
+    bindings: Value,
+}
+
+impl EnvironmentActor {
+    /// Create the actors for a chain of scopes, innermost first, and return the form of the
+    /// innermost one.
+    pub fn new_chain(registry: &ActorRegistry, scopes: Vec<ScopeInfo>) -> Option<EnvironmentForm> {
+        scopes.into_iter().rev().fold(None, |parent, scope| {
+            let mut variables = Map::new();
+            for binding in scope.bindings {
+                let mut descriptor = Map::new();
+                descriptor.insert("value".to_owned(), value_to_grip(registry, binding.value));
+                variables.insert(binding.name, Value::Object(descriptor));
+            }
+            let mut bindings = Map::new();
+            bindings.insert("arguments".to_owned(), Value::Array(vec![]));
+            bindings.insert("variables".to_owned(), Value::Object(variables));
+            let bindings = Value::Object(bindings);
+
+            let actor = EnvironmentActor {
+                name: registry.new_name("environment"),
+                bindings: bindings.clone(),
+            };
+            // Declarative environments are those of functions and blocks.
+            let type_ = match (&*scope.type_, &scope.callee) {
+                ("declarative", &Some(_)) => "function",
+                ("declarative", &None) => "block",
+                (type_, _) => type_,
+            }.to_owned();
+            let form = EnvironmentForm {
+                actor: actor.name(),
+                type_: type_,
+                function: scope.callee.map(|name| FunctionMsg { name: name }),
+                bindings: bindings,
+                parent: parent.map(Box::new),
+            };
+            registry.register_later(Box::new(actor));
+            Some(form)
+        })
+    }
+}
+
+impl Actor for EnvironmentActor {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn handle_message(&self,
+                      _registry: &ActorRegistry,
+                      msg_type: &str,
+                      _msg: &Map<String, Value>,
+                      stream: &mut TcpStream) -> Result<ActorMessageStatus, ()> {
+        Ok(match msg_type {
+            "bindings" => {
+                let msg = BindingsReply {
+                    from: self.name(),
+                    bindings: self.bindings.clone(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            }
+
+            _ => ActorMessageStatus::Ignored,
+        })
+    }
+}
@@ -6,9 +6,13 @@
 //! (http://mxr.mozilla.org/mozilla-central/source/toolkit/devtools/server/actors/inspector.js).
 
 use actor::{Actor, ActorMessageStatus, ActorRegistry};
-use devtools_traits::{ComputedNodeLayout, DevtoolScriptControlMsg, NodeInfo};
-use devtools_traits::DevtoolScriptControlMsg::{GetChildren, GetDocumentElement, GetRootNode};
-use devtools_traits::DevtoolScriptControlMsg::{GetLayout, ModifyAttribute};
+use actors::stylesheets::{StyleSheetActor, StyleSheetForm, get_style_sheets};
+use devtools_traits::{ComputedNodeLayout, DevtoolScriptControlMsg, NodeInfo, NodeMutation};
+use devtools_traits::{RuleInfo, RuleLocation, RuleModification};
+use devtools_traits::DevtoolScriptControlMsg::{EditTagName, GetAppliedRules, GetChildren, GetDocumentElement};
+use devtools_traits::DevtoolScriptControlMsg::{GetLayout, GetRootNode, InsertBefore, ModifyAttribute, ModifyRule};
+use devtools_traits::DevtoolScriptControlMsg::{RemoveNode, SetOuterHTML, SetRuleText};
+use devtools_traits::DevtoolScriptControlMsg::WantsMutationNotifications;
 use ipc_channel::ipc::{self, IpcSender};
 use msg::constellation_msg::PipelineId;
 use protocol::JsonPacketStream;
@@ -237,10 +241,14 @@ impl NodeInfoToProtocol for NodeInfo {
     }
 }
 
-struct WalkerActor {
+pub struct WalkerActor {
     name: String,
     script_chan: IpcSender<DevtoolScriptControlMsg>,
     pipeline: PipelineId,
+    /// The connections that are told when the DOM tree changes.
+    streams: RefCell<Vec<TcpStream>>,
+    /// The changes to the DOM tree that were not retrieved with `getMutations` yet.
+    mutations: RefCell<Vec<NodeMutation>>,
 }
 
 #[derive(Serialize)]
@@ -267,6 +275,128 @@ struct ChildrenReply {
     from: String,
 }
 
+#[derive(Serialize)]
+struct EditReply {
+    from: String,
+}
+
+#[derive(Serialize)]
+struct RemoveNodeReply {
+    from: String,
+    nextSibling: Option<NodeActorMsg>,
+}
+
+#[derive(Serialize)]
+struct NewMutationsMsg {
+    from: String,
+    #[serde(rename = "type")]
+    type_: String,
+}
+
+#[derive(Serialize)]
+struct GetMutationsReply {
+    from: String,
+    mutations: Vec<MutationMsg>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type")]
+enum MutationMsg {
+    #[serde(rename = "attributes")]
+    Attributes {
+        target: String,
+        attributeName: String,
+        attributeNamespace: Option<String>,
+        newValue: Option<String>,
+    },
+    #[serde(rename = "childList")]
+    ChildList {
+        target: String,
+        added: Vec<NodeActorMsg>,
+        removed: Vec<String>,
+        numChildren: usize,
+    },
+}
+
+impl WalkerActor {
+    fn new(name: String, script_chan: IpcSender<DevtoolScriptControlMsg>, pipeline: PipelineId) -> WalkerActor {
+        WalkerActor {
+            name: name,
+            script_chan: script_chan,
+            pipeline: pipeline,
+            streams: RefCell::new(vec![]),
+            mutations: RefCell::new(vec![]),
+        }
+    }
+
+    /// Queue changes to the DOM tree, and tell the connections there are some to retrieve.
+    pub fn new_mutations(&self, mutations: Vec<NodeMutation>) {
+        let was_empty = {
+            let mut pending = self.mutations.borrow_mut();
+            let was_empty = pending.is_empty();
+            pending.extend(mutations);
+            was_empty
+        };
+        if !was_empty {
+            return;
+        }
+        let msg = NewMutationsMsg {
+            from: self.name(),
+            type_: "newMutations".to_owned(),
+        };
+        for stream in &mut *self.streams.borrow_mut() {
+            stream.write_json_packet(&msg);
+        }
+    }
+
+    /// The form of a change, or `None` if it is about a node the client does not know of.
+    fn encode_mutation(&self, registry: &ActorRegistry, mutation: NodeMutation) -> Option<MutationMsg> {
+        match mutation {
+            NodeMutation::Attribute { target, name, namespace, value } => {
+                if !registry.script_actor_registered(target.clone()) {
+                    return None;
+                }
+                Some(MutationMsg::Attributes {
+                    target: registry.script_to_actor(target),
+                    attributeName: name,
+                    attributeNamespace: namespace,
+                    newValue: value,
+                })
+            }
+            NodeMutation::ChildList { target, added, removed, numChildren } => {
+                if !registry.script_actor_registered(target.clone()) {
+                    return None;
+                }
+                Some(MutationMsg::ChildList {
+                    target: registry.script_to_actor(target),
+                    added: added.into_iter().map(|node| {
+                        node.encode(registry, true, self.script_chan.clone(), self.pipeline)
+                    }).collect(),
+                    removed: removed.into_iter()
+                                    .filter(|node| registry.script_actor_registered(node.clone()))
+                                    .map(|node| registry.script_to_actor(node))
+                                    .collect(),
+                    numChildren: numChildren,
+                })
+            }
+        }
+    }
+
+    /// The id script knows the node of the given actor by.
+    fn node_id(registry: &ActorRegistry, msg: &Map<String, Value>, key: &str) -> Result<String, ()> {
+        let actor = msg.get(key).and_then(|actor| actor.as_str()).ok_or(())?;
+        Ok(registry.actor_to_script(actor.to_owned()))
+    }
+
+    fn reply_edited(&self, done: bool, stream: &mut TcpStream) -> Result<ActorMessageStatus, ()> {
+        if !done {
+            return Err(());
+        }
+        stream.write_json_packet(&EditReply { from: self.name() });
+        Ok(ActorMessageStatus::Processed)
+    }
+}
+
 impl Actor for WalkerActor {
     fn name(&self) -> String {
         self.name.clone()
@@ -308,6 +438,65 @@ impl Actor for WalkerActor {
                 ActorMessageStatus::Processed
             }
 
+            "getMutations" => {
+                let mutations = if msg.get("cleanup").and_then(|cleanup| cleanup.as_bool()).unwrap_or(true) {
+                    self.mutations.borrow_mut().drain(..).collect()
+                } else {
+                    self.mutations.borrow().clone()
+                };
+                let msg = GetMutationsReply {
+                    from: self.name(),
+                    mutations: mutations.into_iter()
+                                        .filter_map(|mutation| self.encode_mutation(registry, mutation))
+                                        .collect(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            }
+
+            "setOuterHTML" => {
+                let node = WalkerActor::node_id(registry, msg, "node")?;
+                let value = msg.get("value").and_then(|value| value.as_str()).ok_or(())?;
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan.send(SetOuterHTML(self.pipeline, node, value.to_owned(), tx)).unwrap();
+                self.reply_edited(rx.recv().map_err(|_| ())?, stream)?
+            }
+
+            "removeNode" => {
+                let node = WalkerActor::node_id(registry, msg, "node")?;
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan.send(RemoveNode(self.pipeline, node, tx)).unwrap();
+                let next_sibling = rx.recv().map_err(|_| ())??;
+                let msg = RemoveNodeReply {
+                    from: self.name(),
+                    nextSibling: next_sibling.map(|sibling| {
+                        sibling.encode(registry, true, self.script_chan.clone(), self.pipeline)
+                    }),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            }
+
+            "insertBefore" => {
+                let node = WalkerActor::node_id(registry, msg, "node")?;
+                let parent = WalkerActor::node_id(registry, msg, "parent")?;
+                let sibling = match msg.get("sibling") {
+                    Some(&Value::String(_)) => Some(WalkerActor::node_id(registry, msg, "sibling")?),
+                    _ => None,
+                };
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan.send(InsertBefore(self.pipeline, node, parent, sibling, tx)).unwrap();
+                self.reply_edited(rx.recv().map_err(|_| ())?, stream)?
+            }
+
+            "editTagName" => {
+                let node = WalkerActor::node_id(registry, msg, "node")?;
+                let tag_name = msg.get("tagName").and_then(|tag_name| tag_name.as_str()).ok_or(())?;
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan.send(EditTagName(self.pipeline, node, tag_name.to_owned(), tx)).unwrap();
+                self.reply_edited(rx.recv().map_err(|_| ())?, stream)?
+            }
+
             "children" => {
                 let target = msg.get("node").unwrap().as_str().unwrap();
                 let (tx, rx) = ipc::channel().unwrap();
@@ -355,7 +544,7 @@ struct PageStyleActor {
 struct GetAppliedReply {
     entries: Vec<AppliedEntry>,
     rules: Vec<AppliedRule>,
-    sheets: Vec<AppliedSheet>,
+    sheets: Vec<StyleSheetForm>,
     from: String,
 }
 
@@ -377,26 +566,27 @@ struct AppliedEntry {
 struct AppliedRule {
     actor: String,
     #[serde(rename = "type")]
-    type_: String,
-    href: String,
+    type_: u32,
+    href: Option<String>,
     cssText: String,
+    selectors: Vec<String>,
     line: u32,
     column: u32,
-    parentStyleSheet: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parentStyleSheet: Option<String>,
 }
 
 #[derive(Serialize)]
-struct AppliedSheet {
-    actor: String,
-    href: String,
-    nodeHref: String,
-    disabled: bool,
-    title: String,
-    system: bool,
-    styleSheetIndex: isize,
-    ruleCount: usize,
+struct ModifyRuleReply {
+    from: String,
+    rule: AppliedRule,
 }
 
+/// The `CSSRule` type of style rules.
+const STYLE_RULE: u32 = 1;
+/// The type Firefox gives to the style attribute of an element.
+const ELEMENT_STYLE: u32 = 100;
+
 #[derive(Serialize)]
 struct GetLayoutReply {
     from: String,
@@ -454,11 +644,52 @@ impl Actor for PageStyleActor {
                       stream: &mut TcpStream) -> Result<ActorMessageStatus, ()> {
         Ok(match msg_type {
             "getApplied" => {
-                //TODO: query script for relevant applied styles to node (msg.node)
+                let target = msg.get("node").and_then(|node| node.as_str()).ok_or(())?;
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan.send(GetAppliedRules(self.pipeline,
+                                                      registry.actor_to_script(target.to_owned()),
+                                                      tx))
+                                .unwrap();
+                let applied = rx.recv().map_err(|_| ())?.ok_or(())?;
+
+                // Only the sheets the applied rules are in are sent along.
+                let mut sheets: Vec<StyleSheetForm> = vec![];
+                let mut sheet_actors = vec![];
+                for info in get_style_sheets(&self.script_chan, self.pipeline)? {
+                    let index = info.index;
+                    let used = applied.iter().any(|rule| match rule.location {
+                        RuleLocation::StyleSheet(sheet, _) => sheet == index,
+                        RuleLocation::ElementStyle(_) => false,
+                    });
+                    if used {
+                        let form = StyleSheetActor::encode(registry, self.script_chan.clone(), self.pipeline, info);
+                        sheet_actors.push((index, form.actor.clone(), form.href.clone()));
+                        sheets.push(form);
+                    }
+                }
+
+                let mut entries = vec![];
+                let mut rules = vec![];
+                for rule in applied {
+                    let sheet = match rule.location {
+                        RuleLocation::StyleSheet(index, _) => sheet_actors.iter().find(|sheet| sheet.0 == index),
+                        RuleLocation::ElementStyle(_) => None,
+                    };
+                    let sheet = sheet.map(|&(_, ref actor, ref href)| (actor.clone(), href.clone()));
+                    let form = StyleRuleActor::encode(registry, self.script_chan.clone(), self.pipeline, rule, sheet);
+                    entries.push(AppliedEntry {
+                        rule: form.actor.clone(),
+                        pseudoElement: Value::Null,
+                        isSystem: false,
+                        matchedSelectors: form.selectors.clone(),
+                    });
+                    rules.push(form);
+                }
+
                 let msg = GetAppliedReply {
-                    entries: vec!(),
-                    rules: vec!(),
-                    sheets: vec!(),
+                    entries: entries,
+                    rules: rules,
+                    sheets: sheets,
                     from: self.name(),
                 };
                 stream.write_json_packet(&msg);
@@ -538,6 +769,138 @@ impl Actor for PageStyleActor {
     }
 }
 
+/// A CSS rule, or the style attribute of an element, whose declarations can be edited.
+struct StyleRuleActor {
+    name: String,
+    script_chan: IpcSender<DevtoolScriptControlMsg>,
+    pipeline: PipelineId,
+    location: RuleLocation,
+}
+
+impl StyleRuleActor {
+    /// The form of the given rule, creating its actor if there is none yet. `sheet` is the
+    /// actor and URL of the style sheet the rule is in, if any.
+    fn encode(registry: &ActorRegistry,
+              script_chan: IpcSender<DevtoolScriptControlMsg>,
+              pipeline: PipelineId,
+              rule: RuleInfo,
+              sheet: Option<(String, Option<String>)>) -> AppliedRule {
+        let script_id = match rule.location {
+            RuleLocation::StyleSheet(sheet, index) => format!("rule-{}-{}-{}", pipeline, sheet, index),
+            RuleLocation::ElementStyle(ref node) => format!("rule-{}-{}", pipeline, node),
+        };
+        let actor = if registry.script_actor_registered(script_id.clone()) {
+            registry.script_to_actor(script_id)
+        } else {
+            let actor = StyleRuleActor {
+                name: registry.new_name("domstylerule"),
+                script_chan: script_chan,
+                pipeline: pipeline,
+                location: rule.location.clone(),
+            };
+            let name = actor.name();
+            registry.register_script_actor(script_id, name.clone());
+            registry.register_later(Box::new(actor));
+            name
+        };
+
+        let (parent, href) = match sheet {
+            Some((actor, href)) => (Some(actor), href),
+            None => (None, None),
+        };
+        AppliedRule {
+            actor: actor,
+            type_: match rule.location {
+                RuleLocation::StyleSheet(..) => STYLE_RULE,
+                RuleLocation::ElementStyle(_) => ELEMENT_STYLE,
+            },
+            href: href,
+            cssText: rule.cssText,
+            selectors: if rule.selector.is_empty() {
+                vec![]
+            } else {
+                rule.selector.split(',').map(|selector| selector.trim().to_owned()).collect()
+            },
+            line: 0,
+            column: 0,
+            parentStyleSheet: parent,
+        }
+    }
+
+    /// Reply with the form of the rule after it was edited.
+    fn reply(&self,
+             registry: &ActorRegistry,
+             rule: Option<RuleInfo>,
+             stream: &mut TcpStream) -> Result<ActorMessageStatus, ()> {
+        let rule = rule.ok_or(())?;
+        let sheet = match self.location {
+            RuleLocation::StyleSheet(index, _) => {
+                get_style_sheets(&self.script_chan, self.pipeline)?
+                    .into_iter()
+                    .find(|info| info.index == index)
+                    .map(|info| StyleSheetActor::encode(registry, self.script_chan.clone(), self.pipeline, info))
+                    .map(|form| (form.actor, form.href))
+            }
+            RuleLocation::ElementStyle(_) => None,
+        };
+        let msg = ModifyRuleReply {
+            from: self.name(),
+            rule: StyleRuleActor::encode(registry, self.script_chan.clone(), self.pipeline, rule, sheet),
+        };
+        stream.write_json_packet(&msg);
+        Ok(ActorMessageStatus::Processed)
+    }
+}
+
+impl Actor for StyleRuleActor {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn handle_message(&self,
+                      registry: &ActorRegistry,
+                      msg_type: &str,
+                      msg: &Map<String, Value>,
+                      stream: &mut TcpStream) -> Result<ActorMessageStatus, ()> {
+        match msg_type {
+            "modifyProperties" => {
+                let modifications = msg.get("modifications").and_then(|mods| mods.as_array()).ok_or(())?;
+                let modifications = modifications.iter().filter_map(|modification| {
+                    let name = modification.get("name").and_then(|name| name.as_str())?;
+                    let value = match modification.get("type").and_then(|type_| type_.as_str()) {
+                        Some("set") => Some(modification.get("value").and_then(|value| value.as_str())?.to_owned()),
+                        Some("remove") => None,
+                        _ => return None,
+                    };
+                    Some(RuleModification {
+                        name: name.to_owned(),
+                        value: value,
+                        priority: modification.get("priority")
+                                              .and_then(|priority| priority.as_str())
+                                              .unwrap_or("")
+                                              .to_owned(),
+                    })
+                }).collect();
+
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan.send(ModifyRule(self.pipeline, self.location.clone(), modifications, tx))
+                                .unwrap();
+                self.reply(registry, rx.recv().map_err(|_| ())?, stream)
+            }
+
+            "setRuleText" => {
+                let text = msg.get("newText").and_then(|text| text.as_str()).ok_or(())?;
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan.send(SetRuleText(self.pipeline, self.location.clone(), text.to_owned(), tx))
+                                .unwrap();
+                self.reply(registry, rx.recv().map_err(|_| ())?, stream)
+            }
+
+            _ => Ok(ActorMessageStatus::Ignored),
+        }
+    }
+}
+
 impl Actor for InspectorActor {
     fn name(&self) -> String {
         self.name.clone()
@@ -551,11 +914,9 @@ impl Actor for InspectorActor {
         Ok(match msg_type {
             "getWalker" => {
                 if self.walker.borrow().is_none() {
-                    let walker = WalkerActor {
-                        name: registry.new_name("walker"),
-                        script_chan: self.script_chan.clone(),
-                        pipeline: self.pipeline,
-                    };
+                    let walker = WalkerActor::new(registry.new_name("walker"), self.script_chan.clone(), self.pipeline);
+                    walker.streams.borrow_mut().push(stream.try_clone().unwrap());
+                    self.script_chan.send(WantsMutationNotifications(self.pipeline, true)).unwrap();
                     let mut walker_name = self.walker.borrow_mut();
                     *walker_name = Some(walker.name());
                     registry.register_later(Box::new(walker));
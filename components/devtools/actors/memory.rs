@@ -2,25 +2,66 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+//! Liberally derived from the [Firefox JS implementation]
+//! (http://mxr.mozilla.org/mozilla-central/source/devtools/server/actors/memory.js).
+//! Measures the memory used by a tab, from the reports of the memory profiler.
+
 use actor::{Actor, ActorMessageStatus, ActorRegistry};
-use serde_json::{Map, Value};
+use ipc_channel::ipc;
+use profile_traits::mem::{ProfilerChan, ProfilerMsg, Report};
+use protocol::JsonPacketStream;
+use serde_json::{self, Map, Value};
 use std::net::TcpStream;
+use time::precise_time_ns;
 
-#[derive(Serialize)]
+#[derive(Default, Serialize)]
 pub struct TimelineMemoryReply {
     jsObjectSize: u64,
     jsStringSize: u64,
     jsOtherSize: u64,
     domSize: u64,
     styleSize: u64,
+    layoutSize: u64,
     otherSize: u64,
     totalSize: u64,
     jsMilliseconds: f64,
     nonJSMilliseconds: f64,
 }
 
+impl TimelineMemoryReply {
+    /// Count a report, whose path is relative to the page it is about.
+    fn add(&mut self, path: &[String], size: u64) {
+        let path: Vec<&str> = path.iter().map(|segment| &**segment).collect();
+        // Strings live in the GC heap too, and are not measured apart from objects.
+        if path == ["js", "gc-heap", "used"] {
+            self.jsObjectSize += size;
+        } else if path.first() == Some(&"js") {
+            self.jsOtherSize += size;
+        } else if path == ["dom-tree"] {
+            self.domSize += size;
+        } else if path == ["layout-thread", "stylist"] {
+            self.styleSize += size;
+        } else if path.first() == Some(&"layout-thread") {
+            self.layoutSize += size;
+        } else {
+            self.otherSize += size;
+        }
+        self.totalSize += size;
+    }
+}
+
+#[derive(Serialize)]
+struct AttachReply {
+    from: String,
+    #[serde(rename = "type")]
+    type_: String,
+}
+
 pub struct MemoryActor {
     pub name: String,
+    /// The URL of the page of the tab, which the reports about it are filed under.
+    url: String,
+    mem_profiler_chan: ProfilerChan,
 }
 
 impl Actor for MemoryActor {
@@ -30,37 +71,64 @@ impl Actor for MemoryActor {
 
     fn handle_message(&self,
                       _registry: &ActorRegistry,
-                      _msg_type: &str,
+                      msg_type: &str,
                       _msg: &Map<String, Value>,
-                      _stream: &mut TcpStream) -> Result<ActorMessageStatus, ()> {
-        Ok(ActorMessageStatus::Ignored)
+                      stream: &mut TcpStream) -> Result<ActorMessageStatus, ()> {
+        Ok(match msg_type {
+            "attach" | "detach" => {
+                let msg = AttachReply {
+                    from: self.name(),
+                    type_: if msg_type == "attach" { "attached" } else { "detached" }.to_owned(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            }
+
+            "measure" => {
+                let mut msg = match serde_json::to_value(self.measure()) {
+                    Ok(Value::Object(msg)) => msg,
+                    _ => return Err(()),
+                };
+                msg.insert("from".to_owned(), Value::String(self.name()));
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            }
+
+            _ => ActorMessageStatus::Ignored,
+        })
     }
 }
 
 impl MemoryActor {
-    /// return name of actor
-    pub fn create(registry: &ActorRegistry) -> String {
-        let actor_name = registry.new_name("memory");
-        let actor = MemoryActor {
-            name: actor_name.clone()
-        };
+    pub fn new(name: String, url: String, mem_profiler_chan: ProfilerChan) -> MemoryActor {
+        MemoryActor {
+            name: name,
+            url: url,
+            mem_profiler_chan: mem_profiler_chan,
+        }
+    }
 
-        registry.register_later(Box::new(actor));
-        actor_name
+    /// Whether the given report is about the page of the tab. Reports about pages start with
+    /// a `url(...)` segment, which lists every page the measured structure is shared by.
+    fn is_about_page(&self, report: &Report) -> bool {
+        report.path.first().map_or(false, |segment| {
+            segment.starts_with("url(") && segment.ends_with(")") &&
+                segment[4..segment.len() - 1].split(", ").any(|url| url == self.url)
+        })
     }
 
     pub fn measure(&self) -> TimelineMemoryReply {
-        //TODO:
-        TimelineMemoryReply {
-            jsObjectSize: 1,
-            jsStringSize: 1,
-            jsOtherSize: 1,
-            domSize: 1,
-            styleSize: 1,
-            otherSize: 1,
-            totalSize: 1,
-            jsMilliseconds: 1.1,
-            nonJSMilliseconds: 1.1,
+        let start = precise_time_ns();
+        let (tx, rx) = ipc::channel().unwrap();
+        self.mem_profiler_chan.send(ProfilerMsg::CollectReports(tx));
+        let reports = rx.recv().unwrap_or(vec![]);
+
+        let mut measurement = TimelineMemoryReply::default();
+        for report in reports.iter().filter(|report| self.is_about_page(report)) {
+            measurement.add(&report.path[1..], report.size as u64);
         }
+        // The reports are collected all together, so the time it takes is not split.
+        measurement.nonJSMilliseconds = (precise_time_ns() - start) as f64 / 1000000.;
+        measurement
     }
 }
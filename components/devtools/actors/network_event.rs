@@ -7,6 +7,8 @@
 //! Handles interaction with the remote web console on network events (HTTP requests, responses) in Servo.
 
 use actor::{Actor, ActorMessageStatus, ActorRegistry};
+use base64;
+use devtools_traits::{HttpTimings, SecurityInfo as DevtoolsSecurityInfo};
 use devtools_traits::HttpRequest as DevtoolsHttpRequest;
 use devtools_traits::HttpResponse as DevtoolsHttpResponse;
 use hyper::header::{ContentType, Cookie};
@@ -17,9 +19,13 @@ use protocol::JsonPacketStream;
 use serde_json::{Map, Value};
 use std::borrow::Cow;
 use std::net::TcpStream;
+use std::str;
 use time;
 use time::Tm;
 
+/// The largest response body that is kept for the client to inspect, as in Firefox.
+const RESPONSE_BODY_LIMIT: usize = 1024 * 1024;
+
 struct HttpRequest {
     url: String,
     method: Method,
@@ -27,20 +33,23 @@ struct HttpRequest {
     body: Option<Vec<u8>>,
     startedDateTime: Tm,
     timeStamp: i64,
-    connect_time: u64,
-    send_time: u64,
 }
 
 struct HttpResponse {
     headers: Option<Headers>,
     status: Option<RawStatus>,
-    body: Option<Vec<u8>>
+    /// The body received so far, unless it was discarded for being too large.
+    body: Option<Vec<u8>>,
+    body_size: usize,
+    body_discarded: bool,
+    security_info: Option<DevtoolsSecurityInfo>,
 }
 
 pub struct NetworkEventActor {
     pub name: String,
     request: HttpRequest,
     response: HttpResponse,
+    timings: HttpTimings,
     is_xhr: bool,
 }
 
@@ -120,10 +129,20 @@ struct GetResponseHeadersReply {
     rawHeaders: String
 }
 
+#[derive(Serialize)]
+struct ResponseContent {
+    mimeType: String,
+    text: String,
+    size: usize,
+    /// How the text is encoded, if the body is not UTF-8 text.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<String>,
+}
+
 #[derive(Serialize)]
 struct GetResponseContentReply {
     from: String,
-    content: Option<Vec<u8>>,
+    content: Option<ResponseContent>,
     contentDiscarded: bool,
 }
 
@@ -148,12 +167,13 @@ struct GetResponseCookiesReply {
 
 #[derive(Serialize)]
 struct Timings {
-    blocked: u32,
-    dns: u32,
+    blocked: u64,
+    dns: u64,
     connect: u64,
+    ssl: u64,
     send: u64,
-    wait: u32,
-    receive: u32,
+    wait: u64,
+    receive: u64,
 }
 
 #[derive(Serialize)]
@@ -163,9 +183,40 @@ struct GetEventTimingsReply {
     totalTime: u64,
 }
 
+#[derive(Serialize)]
+struct CertificateName {
+    commonName: String,
+}
+
+#[derive(Serialize)]
+struct CertificateValidity {
+    start: String,
+    end: String,
+}
+
+#[derive(Serialize)]
+struct CertificateFingerprint {
+    sha256: String,
+}
+
+#[derive(Serialize)]
+struct Certificate {
+    subject: CertificateName,
+    validity: CertificateValidity,
+    fingerprint: CertificateFingerprint,
+}
+
 #[derive(Serialize)]
 struct SecurityInfo {
     state: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    protocolVersion: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cipherSuite: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keyLength: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cert: Option<Certificate>,
 }
 
 #[derive(Serialize)]
@@ -277,40 +328,61 @@ impl Actor for NetworkEventActor {
                 ActorMessageStatus::Processed
             }
             "getResponseContent" => {
+                let content = self.response.body.as_ref().map(|body| {
+                    let (text, encoding) = match str::from_utf8(body) {
+                        Ok(text) => (text.to_owned(), None),
+                        Err(_) => (base64::encode(body), Some("base64".to_owned())),
+                    };
+                    ResponseContent {
+                        mimeType: self.mime_type(),
+                        text: text,
+                        size: self.response.body_size,
+                        encoding: encoding,
+                    }
+                });
                 let msg = GetResponseContentReply {
                     from: self.name(),
-                    content: self.response.body.clone(),
-                    contentDiscarded: self.response.body.is_none(),
+                    content: content,
+                    contentDiscarded: self.response.body_discarded,
                 };
                 stream.write_json_packet(&msg);
                 ActorMessageStatus::Processed
             }
             "getEventTimings" => {
-                // TODO: This is a fake timings msg
-                let timingsObj = Timings {
-                    blocked: 0,
-                    dns: 0,
-                    connect: self.request.connect_time,
-                    send: self.request.send_time,
-                    wait: 0,
-                    receive: 0,
+                let timings = Timings {
+                    blocked: self.timings.blocked,
+                    dns: self.timings.dns,
+                    connect: self.timings.connect,
+                    ssl: self.timings.tls,
+                    send: self.timings.send,
+                    wait: self.timings.wait,
+                    receive: self.timings.receive,
                 };
-                let total = timingsObj.connect + timingsObj.send;
-                // TODO: Send the correct values for all these fields.
                 let msg = GetEventTimingsReply {
                     from: self.name(),
-                    timings: timingsObj,
-                    totalTime: total,
+                    timings: timings,
+                    totalTime: self.total_time(),
                 };
                 stream.write_json_packet(&msg);
                 ActorMessageStatus::Processed
             }
             "getSecurityInfo" => {
-                // TODO: Send the correct values for securityInfo.
+                let info = self.response.security_info.as_ref();
                 let msg = GetSecurityInfoReply {
                     from: self.name(),
                     securityInfo: SecurityInfo {
-                        state: "insecure".to_owned()
+                        state: self.security_state().to_owned(),
+                        protocolVersion: info.map(|info| info.protocol_version.clone()),
+                        cipherSuite: info.map(|info| info.cipher_suite.clone()),
+                        keyLength: info.and_then(|info| info.key_length),
+                        cert: info.and_then(|info| info.certificate.as_ref()).map(|certificate| Certificate {
+                            subject: CertificateName { commonName: certificate.subject.clone() },
+                            validity: CertificateValidity {
+                                start: certificate.valid_from.clone(),
+                                end: certificate.valid_until.clone(),
+                            },
+                            fingerprint: CertificateFingerprint { sha256: certificate.sha256_fingerprint.clone() },
+                        }),
                     },
                 };
                 stream.write_json_packet(&msg);
@@ -332,14 +404,16 @@ impl NetworkEventActor {
                 body: None,
                 startedDateTime: time::now(),
                 timeStamp: time::get_time().sec,
-                send_time: 0,
-                connect_time: 0,
             },
             response: HttpResponse {
                 headers: None,
                 status: None,
                 body: None,
+                body_size: 0,
+                body_discarded: false,
+                security_info: None,
             },
+            timings: HttpTimings::default(),
             is_xhr: false,
         }
     }
@@ -351,8 +425,9 @@ impl NetworkEventActor {
         self.request.body = request.body;
         self.request.startedDateTime = request.startedDateTime;
         self.request.timeStamp = request.timeStamp;
-        self.request.connect_time = request.connect_time;
-        self.request.send_time = request.send_time;
+        // The full timings are only known once the response has been received.
+        self.timings.connect = request.connect_time;
+        self.timings.send = request.send_time;
         self.is_xhr = request.is_xhr;
     }
 
@@ -363,6 +438,41 @@ impl NetworkEventActor {
             RawStatus(s, Cow::from(status_text))
         });
         self.response.body = response.body.clone();
+        self.response.security_info = response.security_info;
+    }
+
+    pub fn add_body_chunk(&mut self, chunk: &[u8]) {
+        self.response.body_size += chunk.len();
+        if self.response.body_size > RESPONSE_BODY_LIMIT {
+            self.response.body = None;
+            self.response.body_discarded = true;
+        } else {
+            self.response.body.get_or_insert(vec![]).extend_from_slice(chunk);
+        }
+    }
+
+    /// Record the timings of the request, which are known once the whole response was received.
+    pub fn set_timings(&mut self, timings: HttpTimings) {
+        self.timings = timings;
+        if !self.response.body_discarded {
+            self.response.body.get_or_insert(vec![]);
+        }
+    }
+
+    /// The state of the connection the response was received over, as the client reports it.
+    pub fn security_state(&self) -> &'static str {
+        if self.response.security_info.is_some() || self.request.url.starts_with("https:") {
+            "secure"
+        } else {
+            "insecure"
+        }
+    }
+
+    fn mime_type(&self) -> String {
+        match self.response.headers.as_ref().and_then(|headers| headers.get::<ContentType>()) {
+            Some(&ContentType(ref mime)) => mime.to_string(),
+            None => "".to_owned(),
+        }
     }
 
     pub fn event_actor(&self) -> EventActor {
@@ -397,19 +507,11 @@ impl NetworkEventActor {
     }
 
     pub fn response_content(&self) -> ResponseContentMsg {
-        let mut mString = "".to_owned();
-        if let Some(ref headers) = self.response.headers {
-            mString = match headers.get() {
-                Some(&ContentType(ref mime)) => mime.to_string(),
-                None => "".to_owned()
-            };
-        }
-        // TODO: Set correct values when response's body is sent to the devtools in http_loader.
         ResponseContentMsg {
-            mimeType: mString,
-            contentSize: 0,
-            transferredSize: 0,
-            discardResponseBody: true,
+            mimeType: self.mime_type(),
+            contentSize: self.response.body_size as u32,
+            transferredSize: self.response.body_size as u32,
+            discardResponseBody: self.response.body_discarded,
         }
     }
 
@@ -464,6 +566,6 @@ impl NetworkEventActor {
     }
 
     pub fn total_time(&self) -> u64 {
-        self.request.connect_time + self.request.send_time
+        self.timings.total()
     }
 }
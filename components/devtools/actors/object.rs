@@ -3,7 +3,10 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use actor::{Actor, ActorMessageStatus, ActorRegistry};
-use serde_json::{Map, Value};
+use devtools_traits::{ConsoleArgument, EvaluateJSReply};
+use devtools_traits::EvaluateJSReply::{ActorValue, BooleanValue, StringValue};
+use devtools_traits::EvaluateJSReply::{NullValue, NumberValue, VoidValue};
+use serde_json::{Map, Number, Value};
 use std::net::TcpStream;
 
 pub struct ObjectActor {
@@ -42,3 +45,95 @@ impl ObjectActor {
         }
     }
 }
+
+/// Convert a value from script into the grip that represents it in the remote protocol,
+/// creating an object actor for it if it is an object.
+pub fn value_to_grip(registry: &ActorRegistry, value: EvaluateJSReply) -> Value {
+    match value {
+        VoidValue => {
+            let mut m = Map::new();
+            m.insert("type".to_owned(), Value::String("undefined".to_owned()));
+            Value::Object(m)
+        }
+        NullValue => {
+            let mut m = Map::new();
+            m.insert("type".to_owned(), Value::String("null".to_owned()));
+            Value::Object(m)
+        }
+        BooleanValue(val) => Value::Bool(val),
+        NumberValue(val) => {
+            if val.is_nan() {
+                let mut m = Map::new();
+                m.insert("type".to_owned(), Value::String("NaN".to_owned()));
+                Value::Object(m)
+            } else if val.is_infinite() {
+                let mut m = Map::new();
+                if val < 0. {
+                    m.insert("type".to_owned(), Value::String("-Infinity".to_owned()));
+                } else {
+                    m.insert("type".to_owned(), Value::String("Infinity".to_owned()));
+                }
+                Value::Object(m)
+            } else if val == 0. && val.is_sign_negative() {
+                let mut m = Map::new();
+                m.insert("type".to_owned(), Value::String("-0".to_owned()));
+                Value::Object(m)
+            } else {
+                Value::Number(Number::from_f64(val).unwrap())
+            }
+        }
+        StringValue(s) => Value::String(s),
+        ActorValue { class, uuid } => {
+            //TODO: make initial ActorValue message include these properties?
+            let mut m = Map::new();
+            let actor = ObjectActor::new(registry, uuid);
+
+            m.insert("type".to_owned(), Value::String("object".to_owned()));
+            m.insert("class".to_owned(), Value::String(class));
+            m.insert("actor".to_owned(), Value::String(actor));
+            m.insert("extensible".to_owned(), Value::Bool(true));
+            m.insert("frozen".to_owned(), Value::Bool(false));
+            m.insert("sealed".to_owned(), Value::Bool(false));
+            Value::Object(m)
+        }
+    }
+}
+
+/// Convert a value passed to the console API into its grip, with the preview script made of
+/// it if it is an object.
+pub fn console_argument_to_grip(registry: &ActorRegistry, argument: ConsoleArgument) -> Value {
+    let is_array = match argument.value {
+        ActorValue { ref class, .. } => class == "Array",
+        _ => false,
+    };
+    let mut grip = value_to_grip(registry, argument.value);
+    if let (Some(properties), Some(grip)) = (argument.preview, grip.as_object_mut()) {
+        let mut preview = Map::new();
+        let length = Value::Number(Number::from(properties.len()));
+        if is_array {
+            let items = properties.into_iter().map(|(_, value)| console_argument_to_grip(registry, value)).collect();
+            preview.insert("kind".to_owned(), Value::String("ArrayLike".to_owned()));
+            preview.insert("length".to_owned(), length);
+            preview.insert("items".to_owned(), Value::Array(items));
+        } else {
+            let own_properties = properties.into_iter().map(|(name, value)| {
+                let mut descriptor = Map::new();
+                descriptor.insert("configurable".to_owned(), Value::Bool(true));
+                descriptor.insert("enumerable".to_owned(), Value::Bool(true));
+                if value.is_accessor {
+                    // The getter is not described, which is shown as just "Getter".
+                    descriptor.insert("get".to_owned(), value_to_grip(registry, VoidValue));
+                } else {
+                    descriptor.insert("writable".to_owned(), Value::Bool(true));
+                    descriptor.insert("value".to_owned(), console_argument_to_grip(registry, value));
+                }
+                (name, Value::Object(descriptor))
+            }).collect();
+            preview.insert("kind".to_owned(), Value::String("Object".to_owned()));
+            preview.insert("ownProperties".to_owned(), Value::Object(own_properties));
+            preview.insert("ownPropertiesLength".to_owned(), length);
+        }
+        grip.insert("preview".to_owned(), Value::Object(preview));
+    }
+    grip
+}
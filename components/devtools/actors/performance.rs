@@ -2,13 +2,49 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+//! Liberally derived from the [Firefox JS implementation]
+//! (http://mxr.mozilla.org/mozilla-central/source/devtools/server/actors/performance.js).
+//! Records the time a tab spends in layout, styling, script and painting, from the reports of
+//! the time profiler, and samples the stack of its scripts meanwhile.
+
 use actor::{Actor, ActorMessageStatus, ActorRegistry};
+use actors::timeline::{HighResolutionStamp, TimelineMarkerReply};
+use devtools_traits::{DevtoolScriptControlMsg, FrameInfo, PreciseTime};
+use ipc_channel::ipc::{self, IpcSender};
+use msg::constellation_msg::PipelineId;
+use profile_traits::time::{ProfilerCategory, ProfilerChan, ProfilerMsg, ProfilerSample};
 use protocol::{ActorDescription, JsonPacketStream, Method};
 use serde_json::{Map, Value};
-use std::net::TcpStream;
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::mem;
+use std::net::{SocketAddr, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
 
 pub struct PerformanceActor {
     name: String,
+    pipeline: PipelineId,
+    /// The URL of the page of the tab, which the time profiler files the reports about it under.
+    url: String,
+    script_chan: IpcSender<DevtoolScriptControlMsg>,
+    time_profiler_chan: ProfilerChan,
+    /// The recording in progress, if any.
+    recording: RefCell<Option<Recording>>,
+}
+
+struct Recording {
+    /// The form of the recording as it started.
+    form: RecordingForm,
+    /// The sections reported by the time profiler since the recording started.
+    samples: Arc<Mutex<Vec<ProfilerSample>>>,
+    /// Whether to keep on collecting the sections reported by the time profiler.
+    is_recording: Arc<AtomicBool>,
+    start: PreciseTime,
+    /// The address of the client that started the recording, if known.
+    client: Option<SocketAddr>,
 }
 
 #[derive(Serialize)]
@@ -46,15 +82,139 @@ struct SuccessMsg {
 #[derive(Serialize)]
 enum Error {}
 
-impl Actor for PerformanceActor {
+#[derive(Serialize)]
+struct RecordingReply<'a> {
+    from: String,
+    value: &'a RecordingForm,
+}
+
+#[derive(Serialize)]
+struct IsRecordingReply {
+    from: String,
+    value: bool,
+}
+
+#[derive(Clone, Serialize)]
+pub struct RecordingForm {
+    actor: String,
+    configuration: Map<String, Value>,
+    startTime: HighResolutionStamp,
+    recording: bool,
+    completed: bool,
+    duration: Option<HighResolutionStamp>,
+    markers: Vec<TimelineMarkerReply>,
+    /// The markers, with those that happened within others nested in them.
+    waterfall: Vec<WaterfallNode>,
+    profile: Profile,
+}
+
+#[derive(Clone, Serialize)]
+struct WaterfallNode {
+    name: String,
+    start: HighResolutionStamp,
+    end: HighResolutionStamp,
+    children: Vec<WaterfallNode>,
+}
+
+#[derive(Clone, Default, Serialize)]
+struct Profile {
+    samples: Vec<ProfileSample>,
+}
+
+/// The stack of the scripts of the tab at some point of the recording.
+#[derive(Clone, Serialize)]
+struct ProfileSample {
+    time: HighResolutionStamp,
+    frames: Vec<FrameInfo>,
+}
+
+/// A section of time the tab spent doing something the performance panel shows.
+struct Section {
+    name: &'static str,
+    start: u64,
+    end: u64,
+}
+
+/// The name of the markers the performance panel shows for the sections of the given category,
+/// if it shows them.
+fn marker_name(category: ProfilerCategory) -> Option<&'static str> {
+    match category {
+        ProfilerCategory::LayoutPerform => Some("Reflow"),
+        ProfilerCategory::LayoutStyleRecalc => Some("Styles"),
+        ProfilerCategory::ScriptEvaluate => Some("Javascript"),
+        ProfilerCategory::ScriptParseHTML => Some("Parse HTML"),
+        ProfilerCategory::ScriptParseXML => Some("Parse XML"),
+        ProfilerCategory::Painting |
+        ProfilerCategory::PaintingPerTile |
+        ProfilerCategory::PaintingPrepBuff => Some("Paint"),
+        ProfilerCategory::Compositing => Some("Composite"),
+        _ => None,
+    }
+}
+
+/// Nest the sections that happened within others in them, as the waterfall of the performance
+/// panel shows them.
+fn waterfall(sections: &[Section], start_stamp: PreciseTime) -> Vec<WaterfallNode> {
+    let mut sections: Vec<&Section> = sections.iter().collect();
+    // Outer sections come before the sections they contain.
+    sections.sort_by_key(|section| (section.start, Reverse(section.end)));
+
+    fn close(open: &mut Vec<(u64, WaterfallNode)>, roots: &mut Vec<WaterfallNode>) {
+        let (_, node) = open.pop().unwrap();
+        match open.last_mut() {
+            Some(&mut (_, ref mut parent)) => parent.children.push(node),
+            None => roots.push(node),
+        }
+    }
+
+    let mut roots = vec![];
+    let mut open: Vec<(u64, WaterfallNode)> = vec![];
+    for section in sections {
+        while open.last().map_or(false, |&(end, _)| end < section.end) {
+            close(&mut open, &mut roots);
+        }
+        open.push((section.end, WaterfallNode {
+            name: section.name.to_owned(),
+            start: HighResolutionStamp::new(start_stamp, PreciseTime::from_ns(section.start)),
+            end: HighResolutionStamp::new(start_stamp, PreciseTime::from_ns(section.end)),
+            children: vec![],
+        }));
+    }
+    while !open.is_empty() {
+        close(&mut open, &mut roots);
+    }
+    roots
+}
+
+/// A recording of the performance of a tab, which is complete once its form has a duration.
+pub struct PerformanceRecordingActor {
+    name: String,
+    form: RefCell<RecordingForm>,
+}
+
+impl Actor for PerformanceRecordingActor {
     fn name(&self) -> String {
         self.name.clone()
     }
 
     fn handle_message(&self,
                       _registry: &ActorRegistry,
-                      msg_type: &str,
+                      _msg_type: &str,
                       _msg: &Map<String, Value>,
+                      _stream: &mut TcpStream) -> Result<ActorMessageStatus, ()> {
+        Ok(ActorMessageStatus::Ignored)
+    }
+}
+
+impl Actor for PerformanceActor {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn handle_message(&self,
+                      registry: &ActorRegistry,
+                      msg_type: &str,
+                      msg: &Map<String, Value>,
                       stream: &mut TcpStream) -> Result<ActorMessageStatus, ()> {
         Ok(match msg_type {
             "connect" => {
@@ -84,16 +244,173 @@ impl Actor for PerformanceActor {
                 stream.write_json_packet(&msg);
                 ActorMessageStatus::Processed
             }
+            "startRecording" => {
+                if self.recording.borrow().is_none() {
+                    let configuration = msg.get("options")
+                                           .and_then(|options| options.as_object())
+                                           .cloned()
+                                           .unwrap_or_default();
+                    let recording = self.start_recording(registry, configuration, stream.peer_addr().ok());
+                    *self.recording.borrow_mut() = Some(recording);
+                }
+                let recording = self.recording.borrow();
+                stream.write_json_packet(&RecordingReply {
+                    from: self.name(),
+                    value: &recording.as_ref().unwrap().form,
+                });
+                ActorMessageStatus::Processed
+            }
+            "stopRecording" => {
+                let recording = match self.recording.borrow_mut().take() {
+                    Some(recording) => recording,
+                    None => return Err(()),
+                };
+                let form = self.stop_recording(registry, recording)?;
+                stream.write_json_packet(&RecordingReply {
+                    from: self.name(),
+                    value: &form,
+                });
+                ActorMessageStatus::Processed
+            }
+            "isRecording" => {
+                let msg = IsRecordingReply {
+                    from: self.name(),
+                    value: self.recording.borrow().is_some(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            }
             _ => ActorMessageStatus::Ignored,
         })
     }
 }
 
 impl PerformanceActor {
-    pub fn new(name: String) -> PerformanceActor {
+    pub fn new(name: String,
+               pipeline: PipelineId,
+               url: String,
+               script_chan: IpcSender<DevtoolScriptControlMsg>,
+               time_profiler_chan: ProfilerChan) -> PerformanceActor {
         PerformanceActor {
             name: name,
+            pipeline: pipeline,
+            url: url,
+            script_chan: script_chan,
+            time_profiler_chan: time_profiler_chan,
+            recording: RefCell::new(None),
+        }
+    }
+
+    /// Stop the recording of the client at `peer`, whose connection closed, so that script is
+    /// not sampled for a recording that nobody will stop.
+    pub fn connection_closed(&self, peer: &SocketAddr) {
+        let recording = match self.recording.borrow_mut().take() {
+            Some(recording) => recording,
+            None => return,
+        };
+        if recording.client.as_ref() != Some(peer) {
+            *self.recording.borrow_mut() = Some(recording);
+            return;
         }
+        recording.is_recording.store(false, Ordering::SeqCst);
+        let (tx, _) = ipc::channel().unwrap();
+        let _ = self.script_chan.send(DevtoolScriptControlMsg::StopJsSampling(self.pipeline, tx));
+    }
+
+    fn start_recording(&self,
+                       registry: &ActorRegistry,
+                       configuration: Map<String, Value>,
+                       client: Option<SocketAddr>)
+                       -> Recording {
+        let samples = Arc::new(Mutex::new(vec![]));
+        let is_recording = Arc::new(AtomicBool::new(true));
+        let (sender, receiver) = ipc::channel().unwrap();
+        self.time_profiler_chan.send(ProfilerMsg::Listen(sender));
+        {
+            let samples = samples.clone();
+            let is_recording = is_recording.clone();
+            thread::Builder::new().name("PerformanceRecording".to_owned()).spawn(move || {
+                // The time profiler forgets about the receiver once it hangs up.
+                while let Ok(sample) = receiver.recv() {
+                    if !is_recording.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    samples.lock().unwrap().push(sample);
+                }
+            }).expect("Thread spawning failed");
+        }
+        self.script_chan.send(DevtoolScriptControlMsg::StartJsSampling(self.pipeline)).unwrap();
+
+        let start = PreciseTime::now();
+        let form = RecordingForm {
+            actor: registry.new_name("performance-recording"),
+            configuration: configuration,
+            startTime: HighResolutionStamp::new(registry.start_stamp(), start),
+            recording: true,
+            completed: false,
+            duration: None,
+            markers: vec![],
+            waterfall: vec![],
+            profile: Profile::default(),
+        };
+        registry.register_later(Box::new(PerformanceRecordingActor {
+            name: form.actor.clone(),
+            form: RefCell::new(form.clone()),
+        }));
+        Recording {
+            form: form,
+            samples: samples,
+            is_recording: is_recording,
+            start: start,
+            client: client,
+        }
+    }
+
+    fn stop_recording(&self, registry: &ActorRegistry, recording: Recording) -> Result<RecordingForm, ()> {
+        recording.is_recording.store(false, Ordering::SeqCst);
+        let samples = mem::replace(&mut *recording.samples.lock().unwrap(), vec![]);
+
+        let (tx, rx) = ipc::channel().unwrap();
+        self.script_chan.send(DevtoolScriptControlMsg::StopJsSampling(self.pipeline, tx)).unwrap();
+        let stacks = rx.recv().map_err(|_| ())?;
+
+        // Layout files its reports under the URL of the page, but script evaluation files them
+        // under the URL of the script.
+        let (tx, rx) = ipc::channel().unwrap();
+        self.script_chan.send(DevtoolScriptControlMsg::GetSources(self.pipeline, tx)).unwrap();
+        let mut urls: HashSet<String> = rx.recv().map_err(|_| ())?.into_iter().map(|source| source.url).collect();
+        urls.insert(self.url.clone());
+
+        let sections: Vec<Section> = samples.into_iter().filter_map(|sample| {
+            // Reports about no page in particular, like compositing, are about every page.
+            let is_about_page = sample.metadata.as_ref().map_or(true, |metadata| urls.contains(&metadata.url));
+            match marker_name(sample.category) {
+                Some(name) if is_about_page => Some(Section {
+                    name: name,
+                    start: sample.start,
+                    end: sample.end,
+                }),
+                _ => None,
+            }
+        }).collect();
+
+        let start_stamp = registry.start_stamp();
+        let stamp = |ns| HighResolutionStamp::new(start_stamp, PreciseTime::from_ns(ns));
+        let mut form = recording.form;
+        form.recording = false;
+        form.completed = true;
+        form.duration = Some(HighResolutionStamp::new(recording.start, PreciseTime::now()));
+        form.markers = sections.iter().map(|section| {
+            TimelineMarkerReply::new(section.name.to_owned(), stamp(section.start), stamp(section.end))
+        }).collect();
+        form.waterfall = waterfall(&sections, start_stamp);
+        form.profile.samples = stacks.into_iter().map(|sample| ProfileSample {
+            time: stamp(sample.time),
+            frames: sample.frames,
+        }).collect();
+
+        *registry.find::<PerformanceRecordingActor>(&form.actor).form.borrow_mut() = form.clone();
+        Ok(form)
     }
 
     pub fn description() -> ActorDescription {
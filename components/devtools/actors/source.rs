@@ -0,0 +1,169 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Liberally derived from the [Firefox JS implementation]
+//! (http://mxr.mozilla.org/mozilla-central/source/devtools/server/actors/source.js).
+//! A script of a page, as shown by the debugger, and the breakpoints set in it.
+
+use actor::{Actor, ActorMessageStatus, ActorRegistry};
+use devtools_traits::{DevtoolScriptControlMsg, SourceInfo};
+use ipc_channel::ipc::{self, IpcSender};
+use msg::constellation_msg::PipelineId;
+use protocol::JsonPacketStream;
+use serde_json::{Map, Value};
+use std::net::TcpStream;
+
+#[derive(Serialize)]
+pub struct SourceForm {
+    actor: String,
+    url: String,
+    isBlackBoxed: bool,
+    isPrettyPrinted: bool,
+    introductionType: String,
+}
+
+#[derive(Serialize)]
+struct SourceReply {
+    from: String,
+    source: String,
+    contentType: String,
+}
+
+#[derive(Serialize)]
+struct LocationMsg {
+    line: u32,
+}
+
+#[derive(Serialize)]
+struct SetBreakpointReply {
+    from: String,
+    actor: String,
+    isPending: bool,
+    actualLocation: LocationMsg,
+}
+
+#[derive(Serialize)]
+struct DeleteReply {
+    from: String,
+}
+
+pub struct SourceActor {
+    name: String,
+    info: SourceInfo,
+    script_chan: IpcSender<DevtoolScriptControlMsg>,
+    pipeline: PipelineId,
+}
+
+impl SourceActor {
+    pub fn new(name: String,
+               info: SourceInfo,
+               script_chan: IpcSender<DevtoolScriptControlMsg>,
+               pipeline: PipelineId) -> SourceActor {
+        SourceActor {
+            name: name,
+            info: info,
+            script_chan: script_chan,
+            pipeline: pipeline,
+        }
+    }
+
+    pub fn encodable(&self) -> SourceForm {
+        SourceForm {
+            actor: self.name(),
+            url: self.info.url.clone(),
+            isBlackBoxed: false,
+            isPrettyPrinted: false,
+            introductionType: if self.info.startLine > 1 { "scriptElement" } else { "srcScript" }.to_owned(),
+        }
+    }
+}
+
+impl Actor for SourceActor {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn handle_message(&self,
+                      registry: &ActorRegistry,
+                      msg_type: &str,
+                      msg: &Map<String, Value>,
+                      stream: &mut TcpStream) -> Result<ActorMessageStatus, ()> {
+        Ok(match msg_type {
+            "source" => {
+                let msg = SourceReply {
+                    from: self.name(),
+                    source: self.info.text.clone(),
+                    contentType: "text/javascript".to_owned(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            }
+
+            "setBreakpoint" => {
+                let line = msg.get("location")
+                              .and_then(|location| location.get("line"))
+                              .and_then(|line| line.as_u64())
+                              .ok_or(())? as u32;
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan.send(DevtoolScriptControlMsg::SetBreakpoint(self.pipeline,
+                                                                             self.info.url.clone(),
+                                                                             line,
+                                                                             tx)).unwrap();
+                let found = rx.recv().map_err(|_| ())?;
+
+                let breakpoint = BreakpointActor {
+                    name: registry.new_name("breakpoint"),
+                    url: self.info.url.clone(),
+                    line: line,
+                    script_chan: self.script_chan.clone(),
+                    pipeline: self.pipeline,
+                };
+                let msg = SetBreakpointReply {
+                    from: self.name(),
+                    actor: breakpoint.name(),
+                    isPending: !found,
+                    actualLocation: LocationMsg { line: line },
+                };
+                registry.register_later(Box::new(breakpoint));
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            }
+
+            _ => ActorMessageStatus::Ignored,
+        })
+    }
+}
+
+pub struct BreakpointActor {
+    name: String,
+    url: String,
+    line: u32,
+    script_chan: IpcSender<DevtoolScriptControlMsg>,
+    pipeline: PipelineId,
+}
+
+impl Actor for BreakpointActor {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn handle_message(&self,
+                      registry: &ActorRegistry,
+                      msg_type: &str,
+                      _msg: &Map<String, Value>,
+                      stream: &mut TcpStream) -> Result<ActorMessageStatus, ()> {
+        Ok(match msg_type {
+            "delete" => {
+                self.script_chan.send(DevtoolScriptControlMsg::RemoveBreakpoint(self.pipeline,
+                                                                                self.url.clone(),
+                                                                                self.line)).unwrap();
+                stream.write_json_packet(&DeleteReply { from: self.name() });
+                registry.drop_actor_later(self.name());
+                ActorMessageStatus::Processed
+            }
+
+            _ => ActorMessageStatus::Ignored,
+        })
+    }
+}
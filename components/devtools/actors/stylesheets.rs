@@ -0,0 +1,185 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Liberally derived from the [Firefox JS implementation]
+//! (http://mxr.mozilla.org/mozilla-central/source/devtools/server/actors/stylesheets.js).
+//! Lists the style sheets of a tab for the style editor, which can read and disable them.
+
+use actor::{Actor, ActorMessageStatus, ActorRegistry};
+use devtools_traits::{DevtoolScriptControlMsg, StyleSheetInfo};
+use ipc_channel::ipc::{self, IpcSender};
+use msg::constellation_msg::PipelineId;
+use protocol::JsonPacketStream;
+use serde_json::{Map, Value};
+use std::net::TcpStream;
+
+#[derive(Serialize)]
+pub struct StyleSheetForm {
+    pub actor: String,
+    pub href: Option<String>,
+    nodeHref: String,
+    disabled: bool,
+    title: String,
+    system: bool,
+    styleSheetIndex: isize,
+    ruleCount: usize,
+}
+
+#[derive(Serialize)]
+struct GetStyleSheetsReply {
+    from: String,
+    styleSheets: Vec<StyleSheetForm>,
+}
+
+#[derive(Serialize)]
+struct GetTextReply {
+    from: String,
+    text: String,
+}
+
+#[derive(Serialize)]
+struct ToggleDisabledReply {
+    from: String,
+    disabled: bool,
+}
+
+/// Retrieve the style sheets of the given pipeline.
+pub fn get_style_sheets(script_chan: &IpcSender<DevtoolScriptControlMsg>,
+                        pipeline: PipelineId) -> Result<Vec<StyleSheetInfo>, ()> {
+    let (tx, rx) = ipc::channel().unwrap();
+    script_chan.send(DevtoolScriptControlMsg::GetStyleSheets(pipeline, tx)).unwrap();
+    rx.recv().map_err(|_| ())
+}
+
+pub struct StyleSheetsActor {
+    name: String,
+    script_chan: IpcSender<DevtoolScriptControlMsg>,
+    pipeline: PipelineId,
+}
+
+impl StyleSheetsActor {
+    pub fn new(name: String,
+               script_chan: IpcSender<DevtoolScriptControlMsg>,
+               pipeline: PipelineId) -> StyleSheetsActor {
+        StyleSheetsActor {
+            name: name,
+            script_chan: script_chan,
+            pipeline: pipeline,
+        }
+    }
+}
+
+impl Actor for StyleSheetsActor {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn handle_message(&self,
+                      registry: &ActorRegistry,
+                      msg_type: &str,
+                      _msg: &Map<String, Value>,
+                      stream: &mut TcpStream) -> Result<ActorMessageStatus, ()> {
+        Ok(match msg_type {
+            "getStyleSheets" => {
+                let sheets = get_style_sheets(&self.script_chan, self.pipeline)?;
+                let msg = GetStyleSheetsReply {
+                    from: self.name(),
+                    styleSheets: sheets.into_iter().map(|info| {
+                        StyleSheetActor::encode(registry, self.script_chan.clone(), self.pipeline, info)
+                    }).collect(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            }
+
+            _ => ActorMessageStatus::Ignored,
+        })
+    }
+}
+
+pub struct StyleSheetActor {
+    name: String,
+    script_chan: IpcSender<DevtoolScriptControlMsg>,
+    pipeline: PipelineId,
+    index: usize,
+}
+
+impl StyleSheetActor {
+    /// The form of the given style sheet, creating its actor if there is none yet.
+    pub fn encode(registry: &ActorRegistry,
+                  script_chan: IpcSender<DevtoolScriptControlMsg>,
+                  pipeline: PipelineId,
+                  info: StyleSheetInfo) -> StyleSheetForm {
+        let script_id = format!("stylesheet-{}-{}", pipeline, info.index);
+        let actor = if registry.script_actor_registered(script_id.clone()) {
+            registry.script_to_actor(script_id)
+        } else {
+            let actor = StyleSheetActor {
+                name: registry.new_name("stylesheet"),
+                script_chan: script_chan,
+                pipeline: pipeline,
+                index: info.index,
+            };
+            let name = actor.name();
+            registry.register_script_actor(script_id, name.clone());
+            registry.register_later(Box::new(actor));
+            name
+        };
+
+        StyleSheetForm {
+            actor: actor,
+            href: info.href,
+            nodeHref: info.nodeHref,
+            disabled: info.disabled,
+            title: info.title.unwrap_or_default(),
+            system: false,
+            styleSheetIndex: info.index as isize,
+            ruleCount: info.ruleCount,
+        }
+    }
+}
+
+impl Actor for StyleSheetActor {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn handle_message(&self,
+                      _registry: &ActorRegistry,
+                      msg_type: &str,
+                      _msg: &Map<String, Value>,
+                      stream: &mut TcpStream) -> Result<ActorMessageStatus, ()> {
+        Ok(match msg_type {
+            "getText" => {
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan.send(DevtoolScriptControlMsg::GetStyleSheetText(self.pipeline, self.index, tx))
+                                .unwrap();
+                let msg = GetTextReply {
+                    from: self.name(),
+                    text: rx.recv().map_err(|_| ())?.ok_or(())?,
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            }
+
+            "toggleDisabled" => {
+                let disabled = get_style_sheets(&self.script_chan, self.pipeline)?
+                    .into_iter()
+                    .find(|info| info.index == self.index)
+                    .map(|info| !info.disabled)
+                    .ok_or(())?;
+                self.script_chan.send(
+                    DevtoolScriptControlMsg::SetStyleSheetDisabled(self.pipeline, self.index, disabled)).unwrap();
+                let msg = ToggleDisabledReply {
+                    from: self.name(),
+                    disabled: disabled,
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            }
+
+            _ => ActorMessageStatus::Ignored,
+        })
+    }
+}
@@ -65,6 +65,8 @@ pub struct TabActorMsg {
     timelineActor: String,
     profilerActor: String,
     performanceActor: String,
+    memoryActor: String,
+    styleSheetsActor: String,
 }
 
 pub struct TabActor {
@@ -77,6 +79,8 @@ pub struct TabActor {
     pub profiler: String,
     pub performance: String,
     pub thread: String,
+    pub memory: String,
+    pub style_sheets: String,
 }
 
 impl Actor for TabActor {
@@ -164,6 +168,8 @@ impl TabActor {
             timelineActor: self.timeline.clone(),
             profilerActor: self.profiler.clone(),
             performanceActor: self.performance.clone(),
+            memoryActor: self.memory.clone(),
+            styleSheetsActor: self.style_sheets.clone(),
         }
     }
 }
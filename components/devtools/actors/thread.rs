@@ -2,10 +2,21 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+//! Liberally derived from the [Firefox JS implementation]
+//! (http://mxr.mozilla.org/mozilla-central/source/devtools/server/actors/script.js).
+//! The debugger of a tab: lists its scripts, and pauses, steps and inspects their execution.
+
 use actor::{Actor, ActorMessageStatus, ActorRegistry};
+use actors::environment::{EnvironmentActor, EnvironmentForm};
+use actors::source::{SourceActor, SourceForm};
+use devtools_traits::{DevtoolScriptControlMsg, FrameInfo, PauseInfo, StepType};
+use ipc_channel::ipc::{self, IpcSender};
+use msg::constellation_msg::PipelineId;
 use protocol::JsonPacketStream;
 use serde_json::{Map, Value};
-use std::net::TcpStream;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::net::{SocketAddr, TcpStream};
 
 #[derive(Serialize)]
 struct ThreadAttachedReply {
@@ -26,6 +37,16 @@ struct WhyMsg {
     type_: String,
 }
 
+#[derive(Serialize)]
+struct ThreadPausedMsg {
+    from: String,
+    #[serde(rename = "type")]
+    type_: String,
+    actor: String,
+    frame: FrameForm,
+    why: WhyMsg,
+}
+
 #[derive(Serialize)]
 struct ThreadResumedReply {
     from: String,
@@ -33,6 +54,18 @@ struct ThreadResumedReply {
     type_: String,
 }
 
+#[derive(Serialize)]
+struct ThreadInterruptReply {
+    from: String,
+}
+
+#[derive(Serialize)]
+struct ThreadDetachedReply {
+    from: String,
+    #[serde(rename = "type")]
+    type_: String,
+}
+
 #[derive(Serialize)]
 struct ReconfigureReply {
     from: String
@@ -41,22 +74,110 @@ struct ReconfigureReply {
 #[derive(Serialize)]
 struct SourcesReply {
     from: String,
-    sources: Vec<Source>,
+    sources: Vec<SourceForm>,
+}
+
+#[derive(Serialize)]
+struct FramesReply {
+    from: String,
+    frames: Vec<FrameForm>,
+}
+
+#[derive(Serialize)]
+struct FrameForm {
+    actor: String,
+    depth: u32,
+    #[serde(rename = "type")]
+    type_: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    callee: Option<String>,
+    #[serde(rename = "where")]
+    where_: WhereMsg,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    environment: Option<EnvironmentForm>,
 }
 
 #[derive(Serialize)]
-enum Source {}
+struct WhereMsg {
+    url: String,
+    line: u32,
+    column: u32,
+}
 
 pub struct ThreadActor {
     name: String,
+    script_chan: IpcSender<DevtoolScriptControlMsg>,
+    pipeline: PipelineId,
+    /// The connections that attached to this thread, with the address of their client, which
+    /// are told when it pauses.
+    streams: RefCell<Vec<(SocketAddr, TcpStream)>>,
+    /// Whether the script of this thread is paused, waiting to be resumed.
+    paused: Cell<bool>,
+    /// The source actors created so far, by URL and starting line.
+    sources: RefCell<HashMap<(String, u32), String>>,
 }
 
 impl ThreadActor {
-    pub fn new(name: String) -> ThreadActor {
+    pub fn new(name: String,
+               script_chan: IpcSender<DevtoolScriptControlMsg>,
+               pipeline: PipelineId) -> ThreadActor {
         ThreadActor {
             name: name,
+            script_chan: script_chan,
+            pipeline: pipeline,
+            streams: RefCell::new(vec![]),
+            paused: Cell::new(false),
+            sources: RefCell::new(HashMap::new()),
         }
     }
+
+    /// Tell the attached connections that the script of this thread paused.
+    pub fn paused(&self, registry: &ActorRegistry, info: PauseInfo) {
+        let msg = ThreadPausedMsg {
+            from: self.name(),
+            type_: "paused".to_owned(),
+            actor: registry.new_name("pause"),
+            frame: frame_form(registry, info.frame, None),
+            why: WhyMsg { type_: info.why },
+        };
+        self.paused.set(true);
+        for &mut (_, ref mut stream) in &mut *self.streams.borrow_mut() {
+            stream.write_json_packet(&msg);
+        }
+    }
+
+    /// Forget the connection of the client at `peer`, which closed. If it was the last one
+    /// attached while the script is paused, nobody is left to resume it, so resume it now.
+    pub fn connection_closed(&self, peer: &SocketAddr) {
+        let mut streams = self.streams.borrow_mut();
+        let count = streams.len();
+        streams.retain(|&(ref address, _)| address != peer);
+        if streams.len() < count && streams.is_empty() && self.paused.get() {
+            if self.resume(None).is_err() {
+                debug!("the script of {} is gone, so it cannot be resumed", self.name);
+            }
+        }
+    }
+
+    fn resume(&self, step: Option<StepType>) -> Result<(), ()> {
+        self.paused.set(false);
+        self.script_chan.send(DevtoolScriptControlMsg::Resume(self.pipeline, step)).map_err(|_| ())
+    }
+}
+
+fn frame_form(registry: &ActorRegistry, frame: FrameInfo, environment: Option<EnvironmentForm>) -> FrameForm {
+    FrameForm {
+        actor: registry.new_name("frame"),
+        depth: frame.depth,
+        type_: frame.type_,
+        callee: frame.callee,
+        where_: WhereMsg {
+            url: frame.url,
+            line: frame.line,
+            column: frame.column,
+        },
+        environment: environment,
+    }
 }
 
 impl Actor for ThreadActor {
@@ -67,7 +188,7 @@ impl Actor for ThreadActor {
     fn handle_message(&self,
                       registry: &ActorRegistry,
                       msg_type: &str,
-                      _msg: &Map<String, Value>,
+                      msg: &Map<String, Value>,
                       stream: &mut TcpStream) -> Result<ActorMessageStatus, ()> {
         Ok(match msg_type {
             "attach" => {
@@ -78,11 +199,23 @@ impl Actor for ThreadActor {
                     poppedFrames: vec![],
                     why: WhyMsg { type_: "attached".to_owned() },
                 };
+                if let (Ok(peer), Ok(clone)) = (stream.peer_addr(), stream.try_clone()) {
+                    self.streams.borrow_mut().push((peer, clone));
+                }
                 stream.write_json_packet(&msg);
                 ActorMessageStatus::Processed
             },
 
             "resume" => {
+                let step = match msg.get("resumeLimit")
+                                    .and_then(|limit| limit.get("type"))
+                                    .and_then(|type_| type_.as_str()) {
+                    Some("step") => Some(StepType::In),
+                    Some("next") => Some(StepType::Over),
+                    Some("finish") => Some(StepType::Out),
+                    _ => None,
+                };
+                self.resume(step)?;
                 let msg = ThreadResumedReply {
                     from: self.name(),
                     type_: "resumed".to_owned(),
@@ -91,15 +224,86 @@ impl Actor for ThreadActor {
                 ActorMessageStatus::Processed
             },
 
+            // The script pauses once it next runs, which is reported like any other pause.
+            "interrupt" => {
+                self.script_chan.send(DevtoolScriptControlMsg::Interrupt(self.pipeline)).unwrap();
+                stream.write_json_packet(&ThreadInterruptReply { from: self.name() });
+                ActorMessageStatus::Processed
+            },
+
+            "detach" => {
+                self.streams.borrow_mut().clear();
+                self.resume(None)?;
+                let msg = ThreadDetachedReply {
+                    from: self.name(),
+                    type_: "detached".to_owned(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
             "reconfigure" => {
                 stream.write_json_packet(&ReconfigureReply { from: self.name() });
                 ActorMessageStatus::Processed
             }
 
+            "frames" => {
+                let start = msg.get("start").and_then(|start| start.as_u64()).unwrap_or(0) as usize;
+                let count = msg.get("count").and_then(|count| count.as_u64()).map(|count| count as usize);
+
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan.send(DevtoolScriptControlMsg::GetFrames(self.pipeline, tx)).unwrap();
+                let frames = rx.recv().map_err(|_| ())?;
+                let count = count.unwrap_or(frames.len());
+
+                let mut forms = vec![];
+                for frame in frames.into_iter().skip(start).take(count) {
+                    let (tx, rx) = ipc::channel().unwrap();
+                    self.script_chan.send(
+                        DevtoolScriptControlMsg::GetEnvironment(self.pipeline, frame.depth, tx)).unwrap();
+                    let environment = rx.recv().map_err(|_| ())?
+                                        .and_then(|scopes| EnvironmentActor::new_chain(registry, scopes));
+                    forms.push(frame_form(registry, frame, environment));
+                }
+
+                let msg = FramesReply {
+                    from: self.name(),
+                    frames: forms,
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            }
+
             "sources" => {
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan.send(DevtoolScriptControlMsg::GetSources(self.pipeline, tx)).unwrap();
+                let infos = rx.recv().map_err(|_| ())?;
+
+                let mut sources = vec![];
+                let mut known = self.sources.borrow_mut();
+                let mut added = HashSet::new();
+                for info in infos {
+                    let key = (info.url.clone(), info.startLine);
+                    if let Some(name) = known.get(&key) {
+                        // Actors created by this request are not registered yet, and are listed already.
+                        if !added.contains(&key) {
+                            sources.push(registry.find::<SourceActor>(name).encodable());
+                        }
+                        continue;
+                    }
+                    let actor = SourceActor::new(registry.new_name("source"),
+                                                 info,
+                                                 self.script_chan.clone(),
+                                                 self.pipeline);
+                    sources.push(actor.encodable());
+                    known.insert(key.clone(), actor.name());
+                    added.insert(key);
+                    registry.register_later(Box::new(actor));
+                }
+
                 let msg = SourcesReply {
                     from: self.name(),
-                    sources: vec![],
+                    sources: sources,
                 };
                 stream.write_json_packet(&msg);
                 ActorMessageStatus::Processed
@@ -28,6 +28,8 @@ pub struct TimelineActor {
     stream: RefCell<Option<TcpStream>>,
 
     framerate_actor: RefCell<Option<String>>,
+    /// The memory actor of the tab, which measures memory while recording with memory.
+    memory: String,
     memory_actor: RefCell<Option<String>>,
 }
 
@@ -59,8 +61,8 @@ struct StopReply {
     value: HighResolutionStamp,
 }
 
-#[derive(Serialize)]
-struct TimelineMarkerReply {
+#[derive(Clone, Serialize)]
+pub struct TimelineMarkerReply {
     name: String,
     start: HighResolutionStamp,
     end: HighResolutionStamp,
@@ -68,6 +70,18 @@ struct TimelineMarkerReply {
     endStack: Option<Vec<()>>,
 }
 
+impl TimelineMarkerReply {
+    pub fn new(name: String, start: HighResolutionStamp, end: HighResolutionStamp) -> TimelineMarkerReply {
+        TimelineMarkerReply {
+            name: name,
+            start: start,
+            end: end,
+            stack: None,
+            endStack: None,
+        }
+    }
+}
+
 #[derive(Serialize)]
 struct MarkersEmitterReply {
     #[serde(rename = "type")]
@@ -99,6 +113,7 @@ struct FramerateEmitterReply {
 /// with accuracy to microsecond that shows how much time has passed since
 /// actor registry inited
 /// analog https://w3c.github.io/hr-time/#sec-DOMHighResTimeStamp
+#[derive(Clone, Copy)]
 pub struct HighResolutionStamp(f64);
 
 impl HighResolutionStamp {
@@ -124,7 +139,8 @@ static DEFAULT_TIMELINE_DATA_PULL_TIMEOUT: u64 = 200; //ms
 impl TimelineActor {
     pub fn new(name: String,
                pipeline: PipelineId,
-               script_sender: IpcSender<DevtoolScriptControlMsg>) -> TimelineActor {
+               script_sender: IpcSender<DevtoolScriptControlMsg>,
+               memory: String) -> TimelineActor {
         let marker_types = vec!(TimelineMarkerType::Reflow,
                                 TimelineMarkerType::DOMEvent);
 
@@ -137,6 +153,7 @@ impl TimelineActor {
             stream: RefCell::new(None),
 
             framerate_actor: RefCell::new(None),
+            memory: memory,
             memory_actor: RefCell::new(None),
         }
     }
@@ -190,7 +207,7 @@ impl Actor for TimelineActor {
                 // init memory actor
                 if let Some(with_memory) = msg.get("withMemory") {
                     if let Some(true) = with_memory.as_bool() {
-                        *self.memory_actor.borrow_mut() = Some(MemoryActor::create(registry));
+                        *self.memory_actor.borrow_mut() = Some(self.memory.clone());
                     }
                 }
 
@@ -234,9 +251,7 @@ impl Actor for TimelineActor {
                     registry.drop_actor_later(actor_name.clone());
                 }
 
-                if let Some(ref actor_name) = *self.memory_actor.borrow() {
-                    registry.drop_actor_later(actor_name.clone());
-                }
+                self.memory_actor.borrow_mut().take();
 
                 **self.is_recording.lock().as_mut().unwrap() = false;
                 self.stream.borrow_mut().take();
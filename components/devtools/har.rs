@@ -0,0 +1,462 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Records the network activity of every page as an [HTTP Archive]
+//! (https://w3c.github.io/web-performance/specs/HAR/Overview.html), from the network events
+//! net sends to devtools. Each pipeline is a page of the archive, which is written to a file
+//! on demand and at exit.
+
+use base64;
+use devtools_traits::{ChromeToDevtoolsControlMsg, DevtoolsControlMsg, HttpRequest, HttpResponse};
+use devtools_traits::{HttpTimings, NetworkEvent, NetworkListeners, ScriptToDevtoolsControlMsg};
+use hyper::header::{ContentType, ETag, Headers, Location};
+use msg::constellation_msg::PipelineId;
+use serde_json;
+use std::collections::{HashMap, VecDeque};
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::str;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{Sender, channel};
+use std::thread;
+use time::{self, Tm};
+
+/// The largest response body whose text is kept in the archive.
+const RESPONSE_BODY_LIMIT: usize = 1024 * 1024;
+
+/// The most requests kept in the archive. The oldest ones are dropped to make room for
+/// new ones, which bounds the memory the recorder uses over a long session.
+const REQUEST_LIMIT: usize = 2000;
+
+#[derive(Serialize)]
+struct Har {
+    log: Log,
+}
+
+#[derive(Serialize)]
+struct Log {
+    version: &'static str,
+    creator: Creator,
+    pages: Vec<Page>,
+    entries: Vec<Entry>,
+}
+
+#[derive(Serialize)]
+struct Creator {
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Clone, Serialize)]
+struct Page {
+    startedDateTime: String,
+    id: String,
+    title: String,
+    pageTimings: PageTimings,
+}
+
+/// The load timings of a page, which are not known.
+#[derive(Clone, Serialize)]
+struct PageTimings {
+    onContentLoad: i64,
+    onLoad: i64,
+}
+
+#[derive(Serialize)]
+struct Entry {
+    pageref: String,
+    startedDateTime: String,
+    time: u64,
+    request: Request,
+    response: Response,
+    cache: Cache,
+    timings: Timings,
+}
+
+#[derive(Clone, Serialize)]
+struct NameValue {
+    name: String,
+    value: String,
+}
+
+#[derive(Clone, Serialize)]
+struct Request {
+    method: String,
+    url: String,
+    httpVersion: &'static str,
+    cookies: Vec<NameValue>,
+    headers: Vec<NameValue>,
+    queryString: Vec<NameValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    postData: Option<PostData>,
+    headersSize: i64,
+    bodySize: i64,
+}
+
+#[derive(Clone, Serialize)]
+struct PostData {
+    mimeType: String,
+    text: String,
+}
+
+#[derive(Clone, Serialize)]
+struct Response {
+    status: u16,
+    statusText: String,
+    httpVersion: &'static str,
+    cookies: Vec<NameValue>,
+    headers: Vec<NameValue>,
+    content: Content,
+    redirectURL: String,
+    headersSize: i64,
+    bodySize: i64,
+}
+
+#[derive(Clone, Serialize)]
+struct Content {
+    size: usize,
+    mimeType: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<String>,
+}
+
+/// What the cache held for the request, which is only known for requests answered from
+/// the HTTP cache.
+#[derive(Clone, Default, Serialize)]
+struct Cache {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    beforeRequest: Option<CacheEntry>,
+}
+
+#[derive(Clone, Serialize)]
+struct CacheEntry {
+    lastAccess: String,
+    eTag: String,
+    hitCount: u64,
+}
+
+/// The phases of a request, where the time spent in TLS negotiation is part of connecting.
+#[derive(Serialize)]
+struct Timings {
+    blocked: u64,
+    dns: u64,
+    connect: u64,
+    send: u64,
+    wait: u64,
+    receive: u64,
+    ssl: u64,
+}
+
+fn name_values(headers: &Headers) -> Vec<NameValue> {
+    headers.iter().map(|header| NameValue {
+        name: header.name().to_owned(),
+        value: header.value_string(),
+    }).collect()
+}
+
+/// The cookies set by the given `Cookie` or `Set-Cookie` headers, ignoring their attributes.
+fn cookies(headers: &Headers, name: &str) -> Vec<NameValue> {
+    let values = match headers.get_raw(name) {
+        Some(values) => values,
+        None => return vec![],
+    };
+    let separator = if name == "Cookie" { ';' } else { '\n' };
+    values.iter()
+          .flat_map(|value| String::from_utf8_lossy(value).split(separator).map(str::to_owned).collect::<Vec<_>>())
+          .filter_map(|cookie| {
+              let pair = cookie.split(';').next().unwrap_or("");
+              let mut parts = pair.splitn(2, '=');
+              let name = parts.next().unwrap_or("").trim();
+              if name.is_empty() {
+                  return None;
+              }
+              Some(NameValue {
+                  name: name.to_owned(),
+                  value: parts.next().unwrap_or("").trim().to_owned(),
+              })
+          })
+          .collect()
+}
+
+fn mime_type(headers: &Headers) -> String {
+    headers.get::<ContentType>().map_or(String::new(), |&ContentType(ref mime)| mime.to_string())
+}
+
+fn date_time(tm: &Tm) -> String {
+    format!("{}", tm.to_utc().rfc3339())
+}
+
+/// A request, and what has been received of its response so far.
+struct RecordedRequest {
+    id: String,
+    pipeline: PipelineId,
+    started: Tm,
+    request: Request,
+    response: Option<Response>,
+    body: Vec<u8>,
+    body_size: usize,
+    timings: Option<HttpTimings>,
+    cache: Cache,
+}
+
+impl RecordedRequest {
+    fn new(id: &str, request: &HttpRequest) -> RecordedRequest {
+        let post_data = request.body.as_ref().map(|body| PostData {
+            mimeType: mime_type(&request.headers),
+            text: String::from_utf8_lossy(body).into_owned(),
+        });
+        RecordedRequest {
+            id: id.to_owned(),
+            pipeline: request.pipeline_id,
+            started: request.startedDateTime,
+            request: Request {
+                method: request.method.to_string(),
+                url: request.url.as_str().to_owned(),
+                httpVersion: "HTTP/1.1",
+                cookies: cookies(&request.headers, "Cookie"),
+                headers: name_values(&request.headers),
+                queryString: request.url.as_url().query_pairs().map(|(name, value)| NameValue {
+                    name: name.into_owned(),
+                    value: value.into_owned(),
+                }).collect(),
+                postData: post_data,
+                headersSize: -1,
+                bodySize: request.body.as_ref().map_or(0, |body| body.len() as i64),
+            },
+            response: None,
+            body: vec![],
+            body_size: 0,
+            timings: None,
+            cache: Cache::default(),
+        }
+    }
+
+    fn add_response(&mut self, response: &HttpResponse) {
+        let headers = response.headers.clone().unwrap_or_else(Headers::new);
+        let (status, status_text) = match response.status {
+            Some((code, ref text)) => (code, String::from_utf8_lossy(text).into_owned()),
+            None => (0, String::new()),
+        };
+        self.response = Some(Response {
+            status: status,
+            statusText: status_text,
+            httpVersion: "HTTP/1.1",
+            cookies: cookies(&headers, "Set-Cookie"),
+            headers: name_values(&headers),
+            content: Content {
+                size: 0,
+                mimeType: mime_type(&headers),
+                text: None,
+                encoding: None,
+            },
+            redirectURL: headers.get::<Location>().map_or(String::new(), |location| location.to_string()),
+            headersSize: -1,
+            bodySize: -1,
+        });
+    }
+
+    fn add_body_chunk(&mut self, chunk: &[u8]) {
+        self.body_size += chunk.len();
+        if self.body_size <= RESPONSE_BODY_LIMIT {
+            self.body.extend_from_slice(chunk);
+        } else {
+            self.body = vec![];
+        }
+    }
+
+    /// The entry of the request, if its response has been received.
+    fn entry(&self) -> Option<Entry> {
+        let mut response = self.response.clone()?;
+        response.content.size = self.body_size;
+        response.bodySize = self.body_size as i64;
+        if self.body_size <= RESPONSE_BODY_LIMIT {
+            match str::from_utf8(&self.body) {
+                Ok(text) => response.content.text = Some(text.to_owned()),
+                Err(_) => {
+                    response.content.text = Some(base64::encode(&self.body));
+                    response.content.encoding = Some("base64".to_owned());
+                }
+            }
+        }
+        let timings = self.timings.clone().unwrap_or_default();
+        Some(Entry {
+            pageref: self.pipeline.to_string(),
+            startedDateTime: date_time(&self.started),
+            time: timings.total(),
+            request: self.request.clone(),
+            response: response,
+            cache: self.cache.clone(),
+            timings: Timings {
+                blocked: timings.blocked,
+                dns: timings.dns,
+                connect: timings.connect + timings.tls,
+                send: timings.send,
+                wait: timings.wait,
+                receive: timings.receive,
+                ssl: timings.tls,
+            },
+        })
+    }
+}
+
+/// The network activity recorded so far.
+#[derive(Default)]
+struct Recording {
+    pages: Vec<Page>,
+    requests: VecDeque<RecordedRequest>,
+    /// How many of the oldest requests were dropped to stay within `REQUEST_LIMIT`.
+    dropped_requests: usize,
+    /// The index of each request among all the requests recorded, including those dropped
+    /// since, by the id net gave it.
+    request_indices: HashMap<String, usize>,
+    /// When each URL was last requested, and how many times it was answered from the cache.
+    accesses: HashMap<String, (Tm, u64)>,
+}
+
+impl Recording {
+    fn handle_devtools_message(&mut self, message: &DevtoolsControlMsg) {
+        match *message {
+            DevtoolsControlMsg::FromScript(ScriptToDevtoolsControlMsg::NewGlobal((pipeline, None),
+                                                                                 _,
+                                                                                 ref page_info)) => {
+                let id = pipeline.to_string();
+                self.pages.retain(|page| page.id != id);
+                self.pages.push(Page {
+                    startedDateTime: date_time(&time::now()),
+                    id: id,
+                    title: page_info.title.clone(),
+                    pageTimings: PageTimings {
+                        onContentLoad: -1,
+                        onLoad: -1,
+                    },
+                });
+            }
+            DevtoolsControlMsg::FromChrome(ChromeToDevtoolsControlMsg::NetworkEvent(ref request_id, ref event)) => {
+                self.handle_network_event(request_id, event);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_network_event(&mut self, request_id: &str, event: &NetworkEvent) {
+        if let NetworkEvent::HttpRequest(ref request) = *event {
+            if self.requests.len() == REQUEST_LIMIT {
+                if let Some(dropped) = self.requests.pop_front() {
+                    self.request_indices.remove(&dropped.id);
+                    self.dropped_requests += 1;
+                }
+            }
+            let index = self.dropped_requests + self.requests.len();
+            self.request_indices.insert(request_id.to_owned(), index);
+            self.requests.push_back(RecordedRequest::new(request_id, request));
+            return;
+        }
+        let request = match self.request_indices.get(request_id) {
+            Some(&index) => &mut self.requests[index - self.dropped_requests],
+            None => return,
+        };
+        match *event {
+            NetworkEvent::HttpRequest(_) => {}
+            NetworkEvent::HttpResponse(ref response) => {
+                request.add_response(response);
+                let (last_access, hit_count) = self.accesses.get(&request.request.url)
+                    .cloned()
+                    .unwrap_or((request.started, 0));
+                let hit_count = if response.from_cache {
+                    let etag = response.headers.as_ref().and_then(|headers| headers.get::<ETag>());
+                    request.cache.beforeRequest = Some(CacheEntry {
+                        lastAccess: date_time(&last_access),
+                        eTag: etag.map_or(String::new(), |etag| etag.to_string()),
+                        hitCount: hit_count + 1,
+                    });
+                    hit_count + 1
+                } else {
+                    hit_count
+                };
+                self.accesses.insert(request.request.url.clone(), (request.started, hit_count));
+            }
+            NetworkEvent::ResponseBodyChunk(_, ref chunk) => request.add_body_chunk(chunk),
+            NetworkEvent::ResponseEnd(_, ref timings) => request.timings = Some(timings.clone()),
+        }
+    }
+
+    fn har(&self) -> Har {
+        Har {
+            log: Log {
+                version: "1.2",
+                creator: Creator {
+                    name: "Servo",
+                    version: env!("CARGO_PKG_VERSION"),
+                },
+                pages: self.pages.clone(),
+                entries: self.requests.iter().filter_map(RecordedRequest::entry).collect(),
+            },
+        }
+    }
+}
+
+/// Records the network activity that net reports to devtools, to save it as an HTTP Archive.
+pub struct HarRecorder {
+    path: PathBuf,
+    recording: Arc<Mutex<Recording>>,
+    devtools_sender: Sender<DevtoolsControlMsg>,
+}
+
+impl HarRecorder {
+    /// Start recording to an archive at the given path. The devtools messages the recorder sees
+    /// are passed on to `devtools_chan`, if any.
+    pub fn start(path: PathBuf,
+                 devtools_chan: Option<Sender<DevtoolsControlMsg>>,
+                 network_listeners: &NetworkListeners)
+                 -> HarRecorder {
+        // The recorder wants every response body, for as long as it runs.
+        network_listeners.add();
+        let (devtools_sender, devtools_receiver) = channel();
+        let recording = Arc::new(Mutex::new(Recording::default()));
+
+        let thread_recording = recording.clone();
+        thread::Builder::new().name("HarRecorder".to_owned()).spawn(move || {
+            while let Ok(message) = devtools_receiver.recv() {
+                thread_recording.lock().unwrap().handle_devtools_message(&message);
+                if let Some(ref chan) = devtools_chan {
+                    if chan.send(message).is_err() {
+                        warn!("Failed to pass message on to devtools.");
+                    }
+                }
+            }
+        }).expect("Thread spawning failed");
+
+        HarRecorder {
+            path: path,
+            recording: recording,
+            devtools_sender: devtools_sender,
+        }
+    }
+
+    /// The channel that script and net should send their devtools messages to for the recorder
+    /// to see them.
+    pub fn devtools_chan(&self) -> Sender<DevtoolsControlMsg> {
+        self.devtools_sender.clone()
+    }
+
+    /// Write out everything recorded so far, replacing the archive written before, if any.
+    pub fn save(&self) -> io::Result<()> {
+        let har = self.recording.lock().unwrap().har();
+        // Write to a temporary file first, so that the archive is never left half written.
+        let mut temporary_name = self.path.file_name().map_or(OsString::new(), |name| name.to_owned());
+        temporary_name.push(".tmp");
+        let temporary_path = self.path.with_file_name(temporary_name);
+        {
+            let mut file = File::create(&temporary_path)?;
+            serde_json::to_writer_pretty(&mut file, &har).map_err(io::Error::from)?;
+            writeln!(file, "")?;
+            file.sync_all()?;
+        }
+        fs::rename(&temporary_path, &self.path)
+    }
+}
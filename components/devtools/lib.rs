@@ -13,12 +13,14 @@
 #![allow(non_snake_case)]
 #![deny(unsafe_code)]
 
+extern crate base64;
 extern crate devtools_traits;
 extern crate hyper;
 extern crate ipc_channel;
 #[macro_use]
 extern crate log;
 extern crate msg;
+extern crate profile_traits;
 #[macro_use] extern crate serde;
 extern crate serde_json;
 extern crate time;
@@ -26,21 +28,28 @@ extern crate time;
 use actor::{Actor, ActorRegistry};
 use actors::console::ConsoleActor;
 use actors::framerate::FramerateActor;
-use actors::inspector::InspectorActor;
+use actors::inspector::{InspectorActor, WalkerActor};
+use actors::memory::MemoryActor;
 use actors::network_event::{EventActor, NetworkEventActor, ResponseStartMsg};
+use actors::object::console_argument_to_grip;
 use actors::performance::PerformanceActor;
 use actors::profiler::ProfilerActor;
 use actors::root::RootActor;
+use actors::stylesheets::StyleSheetsActor;
 use actors::tab::TabActor;
 use actors::thread::ThreadActor;
 use actors::timeline::TimelineActor;
 use actors::worker::WorkerActor;
-use devtools_traits::{ChromeToDevtoolsControlMsg, ConsoleMessage, DevtoolsControlMsg};
-use devtools_traits::{DevtoolScriptControlMsg, DevtoolsPageInfo, LogLevel, NetworkEvent};
-use devtools_traits::{ScriptToDevtoolsControlMsg, WorkerId};
+use devtools_traits::{BlockedUrls, ChromeToDevtoolsControlMsg, ConsoleArgument, ConsoleCounter};
+use devtools_traits::{ConsoleMessage, ConsoleTimer, DevtoolScriptControlMsg, DevtoolsControlMsg};
+use devtools_traits::{DevtoolsPageInfo, EvaluateJSReply, LogLevel, NetworkEvent, NetworkListeners};
+use devtools_traits::{NodeMutation, PauseInfo, ScriptToDevtoolsControlMsg, WorkerId};
 use ipc_channel::ipc::IpcSender;
 use msg::constellation_msg::PipelineId;
+use profile_traits::mem::ProfilerChan;
+use profile_traits::time::ProfilerChan as TimeProfilerChan;
 use protocol::JsonPacketStream;
+use serde_json::Value;
 use std::borrow::ToOwned;
 use std::cell::RefCell;
 use std::collections::HashMap;
@@ -55,6 +64,7 @@ mod actor;
 /// Corresponds to http://mxr.mozilla.org/mozilla-central/source/toolkit/devtools/server/actors/
 mod actors {
     pub mod console;
+    pub mod environment;
     pub mod framerate;
     pub mod inspector;
     pub mod memory;
@@ -63,11 +73,14 @@ mod actors {
     pub mod performance;
     pub mod profiler;
     pub mod root;
+    pub mod source;
+    pub mod stylesheets;
     pub mod tab;
     pub mod thread;
     pub mod timeline;
     pub mod worker;
 }
+pub mod har;
 mod protocol;
 
 #[derive(Serialize)]
@@ -82,10 +95,26 @@ struct ConsoleAPICall {
 struct ConsoleMsg {
     level: String,
     timeStamp: u64,
-    arguments: Vec<String>,
+    arguments: Vec<Value>,
     filename: String,
     lineNumber: usize,
     columnNumber: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    groupName: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    counter: Option<ConsoleCounter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timer: Option<ConsoleTimer>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stacktrace: Option<Vec<StackFrame>>,
+}
+
+#[derive(Serialize)]
+struct StackFrame {
+    filename: String,
+    functionName: String,
+    lineNumber: u32,
+    columnNumber: u32,
 }
 
 #[derive(Serialize)]
@@ -124,12 +153,19 @@ struct ResponseStartUpdateMsg {
 }
 
 /// Spin up a devtools server that listens for connections on the specified port.
-pub fn start_server(port: u16) -> Sender<DevtoolsControlMsg> {
+/// The resource threads block requests to `blocked_urls`, which clients edit through the console,
+/// and connected clients are counted in `network_listeners`.
+pub fn start_server(port: u16,
+                    mem_profiler_chan: ProfilerChan,
+                    time_profiler_chan: TimeProfilerChan,
+                    blocked_urls: BlockedUrls,
+                    network_listeners: NetworkListeners) -> Sender<DevtoolsControlMsg> {
     let (sender, receiver) = channel();
     {
         let sender = sender.clone();
         thread::Builder::new().name("Devtools".to_owned()).spawn(move || {
-            run_server(sender, receiver, port)
+            run_server(sender, receiver, port, mem_profiler_chan, time_profiler_chan, blocked_urls,
+                       network_listeners)
         }).expect("Thread spawning failed");
     }
     sender
@@ -137,7 +173,11 @@ pub fn start_server(port: u16) -> Sender<DevtoolsControlMsg> {
 
 fn run_server(sender: Sender<DevtoolsControlMsg>,
               receiver: Receiver<DevtoolsControlMsg>,
-              port: u16) {
+              port: u16,
+              mem_profiler_chan: ProfilerChan,
+              time_profiler_chan: TimeProfilerChan,
+              blocked_urls: BlockedUrls,
+              network_listeners: NetworkListeners) {
     let listener = TcpListener::bind(&("127.0.0.1", port)).unwrap();
 
     let mut registry = ActorRegistry::new();
@@ -161,7 +201,11 @@ fn run_server(sender: Sender<DevtoolsControlMsg>,
 
     /// Process the input from a single devtools client until EOF.
     fn handle_client(actors: Arc<Mutex<ActorRegistry>>, mut stream: TcpStream) {
-        debug!("connection established to {}", stream.peer_addr().unwrap());
+        let peer = match stream.peer_addr() {
+            Ok(peer) => peer,
+            Err(_) => return,
+        };
+        debug!("connection established to {}", peer);
         {
             let actors = actors.lock().unwrap();
             let msg = actors.find::<RootActor>("root").encodable();
@@ -188,6 +232,15 @@ fn run_server(sender: Sender<DevtoolsControlMsg>,
                 }
             }
         }
+
+        // Scripts paused by this client would otherwise wait for it forever, and its recordings
+        // would keep on sampling script.
+        let actors = actors.lock().unwrap();
+        for tab in &actors.find::<RootActor>("root").tabs {
+            let tab = actors.find::<TabActor>(tab);
+            actors.find::<ThreadActor>(&tab.thread).connection_closed(&peer);
+            actors.find::<PerformanceActor>(&tab.performance).connection_closed(&peer);
+        }
     }
 
     fn handle_framerate_tick(actors: Arc<Mutex<ActorRegistry>>, actor_name: String, tick: f64) {
@@ -204,18 +257,22 @@ fn run_server(sender: Sender<DevtoolsControlMsg>,
                          script_sender: IpcSender<DevtoolScriptControlMsg>,
                          actor_pipelines: &mut HashMap<PipelineId, String>,
                          actor_workers: &mut HashMap<(PipelineId, WorkerId), String>,
-                         page_info: DevtoolsPageInfo) {
+                         page_info: DevtoolsPageInfo,
+                         mem_profiler_chan: ProfilerChan,
+                         time_profiler_chan: TimeProfilerChan,
+                         blocked_urls: BlockedUrls) {
         let mut actors = actors.lock().unwrap();
 
         let (pipeline, worker_id) = ids;
 
         //TODO: move all this actor creation into a constructor method on TabActor
-        let (tab, console, inspector, timeline, profiler, performance, thread) = {
+        let (tab, console, inspector, timeline, profiler, performance, thread, memory, style_sheets) = {
             let console = ConsoleActor {
                 name: actors.new_name("console"),
                 script_chan: script_sender.clone(),
                 pipeline: pipeline,
                 streams: RefCell::new(Vec::new()),
+                blocked_urls: blocked_urls,
             };
             let inspector = InspectorActor {
                 name: actors.new_name("inspector"),
@@ -226,16 +283,25 @@ fn run_server(sender: Sender<DevtoolsControlMsg>,
                 pipeline: pipeline,
             };
 
+            let DevtoolsPageInfo { title, url } = page_info;
+            let memory = MemoryActor::new(actors.new_name("memory"), url.as_str().to_owned(), mem_profiler_chan);
+
             let timeline = TimelineActor::new(actors.new_name("timeline"),
                                               pipeline,
-                                              script_sender);
+                                              script_sender.clone(),
+                                              memory.name());
 
             let profiler = ProfilerActor::new(actors.new_name("profiler"));
-            let performance = PerformanceActor::new(actors.new_name("performance"));
+            let performance = PerformanceActor::new(actors.new_name("performance"),
+                                                    pipeline,
+                                                    url.as_str().to_owned(),
+                                                    script_sender.clone(),
+                                                    time_profiler_chan);
 
-            let thread = ThreadActor::new(actors.new_name("context"));
+            let style_sheets = StyleSheetsActor::new(actors.new_name("stylesheets"), script_sender.clone(), pipeline);
+
+            let thread = ThreadActor::new(actors.new_name("context"), script_sender, pipeline);
 
-            let DevtoolsPageInfo { title, url } = page_info;
             let tab = TabActor {
                 name: actors.new_name("tab"),
                 title: String::from(title),
@@ -246,12 +312,14 @@ fn run_server(sender: Sender<DevtoolsControlMsg>,
                 profiler: profiler.name(),
                 performance: performance.name(),
                 thread: thread.name(),
+                memory: memory.name(),
+                style_sheets: style_sheets.name(),
             };
 
             let root = actors.find_mut::<RootActor>("root");
             root.tabs.push(tab.name.clone());
 
-            (tab, console, inspector, timeline, profiler, performance, thread)
+            (tab, console, inspector, timeline, profiler, performance, thread, memory, style_sheets)
         };
 
         if let Some(id) = worker_id {
@@ -272,6 +340,38 @@ fn run_server(sender: Sender<DevtoolsControlMsg>,
         actors.register(Box::new(profiler));
         actors.register(Box::new(performance));
         actors.register(Box::new(thread));
+        actors.register(Box::new(memory));
+        actors.register(Box::new(style_sheets));
+    }
+
+    fn handle_paused(actors: Arc<Mutex<ActorRegistry>>,
+                     id: PipelineId,
+                     info: PauseInfo,
+                     actor_pipelines: &HashMap<PipelineId, String>) {
+        let actors = actors.lock().unwrap();
+        let tab_actor_name = match actor_pipelines.get(&id) {
+            Some(name) => name,
+            None => return,
+        };
+        let thread_actor_name = actors.find::<TabActor>(tab_actor_name).thread.clone();
+        actors.find::<ThreadActor>(&thread_actor_name).paused(&*actors, info);
+    }
+
+    fn handle_mutations(actors: Arc<Mutex<ActorRegistry>>,
+                        id: PipelineId,
+                        mutations: Vec<NodeMutation>,
+                        actor_pipelines: &HashMap<PipelineId, String>) {
+        let actors = actors.lock().unwrap();
+        let tab_actor_name = match actor_pipelines.get(&id) {
+            Some(name) => name,
+            None => return,
+        };
+        let inspector_actor_name = actors.find::<TabActor>(tab_actor_name).inspector.clone();
+        let walker_actor_name = match *actors.find::<InspectorActor>(&inspector_actor_name).walker.borrow() {
+            Some(ref name) => name.clone(),
+            None => return,
+        };
+        actors.find::<WalkerActor>(&walker_actor_name).new_mutations(mutations);
     }
 
     fn handle_console_message(actors: Arc<Mutex<ActorRegistry>>,
@@ -287,22 +387,50 @@ fn run_server(sender: Sender<DevtoolsControlMsg>,
         };
         let actors = actors.lock().unwrap();
         let console_actor = actors.find::<ConsoleActor>(&console_actor_name);
+        let level = match console_message.logLevel {
+            LogLevel::Log => "log",
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warn",
+            LogLevel::Error => "error",
+            LogLevel::Trace => "trace",
+            LogLevel::Dir => "dir",
+            LogLevel::Table => "table",
+            LogLevel::Assert => "assert",
+            LogLevel::Group => "group",
+            LogLevel::GroupCollapsed => "groupCollapsed",
+            LogLevel::GroupEnd => "groupEnd",
+            LogLevel::Count => "count",
+            LogLevel::Time => "time",
+            LogLevel::TimeLog => "timeLog",
+            LogLevel::TimeEnd => "timeEnd",
+        };
+        let group_name = match console_message.logLevel {
+            LogLevel::Group | LogLevel::GroupCollapsed => Some(console_message.message.clone()),
+            _ => None,
+        };
+        let stacktrace = console_message.stacktrace.map(|frames| frames.into_iter().map(|frame| StackFrame {
+            filename: frame.url,
+            functionName: frame.callee.unwrap_or_default(),
+            lineNumber: frame.line,
+            columnNumber: frame.column,
+        }).collect());
         let msg = ConsoleAPICall {
             from: console_actor.name.clone(),
             type_: "consoleAPICall".to_owned(),
             message: ConsoleMsg {
-                level: match console_message.logLevel {
-                    LogLevel::Debug => "debug",
-                    LogLevel::Info => "info",
-                    LogLevel::Warn => "warn",
-                    LogLevel::Error => "error",
-                    _ => "log"
-                }.to_owned(),
+                level: level.to_owned(),
                 timeStamp: precise_time_ns(),
-                arguments: vec!(console_message.message),
+                arguments: console_message.arguments.into_iter().map(|argument| {
+                    console_argument_to_grip(&actors, argument)
+                }).collect(),
                 filename: console_message.filename,
                 lineNumber: console_message.lineNumber,
                 columnNumber: console_message.columnNumber,
+                groupName: group_name,
+                counter: console_message.counter,
+                timer: console_message.timer,
+                stacktrace: stacktrace,
             },
         };
         for stream in &mut *console_actor.streams.borrow_mut() {
@@ -391,13 +519,14 @@ fn run_server(sender: Sender<DevtoolsControlMsg>,
                 for stream in &mut connections {
                     stream.write_json_packet(&msg);
                 }
+
                 let msg = NetworkEventUpdateMsg {
                     from: netevent_actor_name.clone(),
                     type_: "networkEventUpdate".to_owned(),
-                    updateType: "eventTimings".to_owned(),
+                    updateType: "securityInfo".to_owned(),
                 };
-                let extra = EventTimingsUpdateMsg {
-                    totalTime: actor.total_time(),
+                let extra = SecurityInfoUpdateMsg {
+                    state: actor.security_state().to_owned(),
                 };
                 for stream in &mut connections {
                     stream.write_merged_json_packet(&msg, &extra);
@@ -406,40 +535,46 @@ fn run_server(sender: Sender<DevtoolsControlMsg>,
                 let msg = NetworkEventUpdateMsg {
                     from: netevent_actor_name.clone(),
                     type_: "networkEventUpdate".to_owned(),
-                    updateType: "securityInfo".to_owned(),
-                };
-                let extra = SecurityInfoUpdateMsg {
-                    state: "insecure".to_owned(),
+                    updateType: "responseCookies".to_owned(),
                 };
                 for stream in &mut connections {
-                    stream.write_merged_json_packet(&msg, &extra);
+                    stream.write_merged_json_packet(&msg, &actor.response_cookies());
                 }
 
                 let msg = NetworkEventUpdateMsg {
                     from: netevent_actor_name.clone(),
                     type_: "networkEventUpdate".to_owned(),
-                    updateType: "responseContent".to_owned(),
+                    updateType: "responseHeaders".to_owned(),
                 };
                 for stream in &mut connections {
-                    stream.write_merged_json_packet(&msg, &actor.response_content());
+                    stream.write_merged_json_packet(&msg, &actor.response_headers());
                 }
+            }
+            NetworkEvent::ResponseBodyChunk(_, chunk) => {
+                actor.add_body_chunk(&chunk);
+            }
+            NetworkEvent::ResponseEnd(_, timings) => {
+                actor.set_timings(timings);
 
                 let msg = NetworkEventUpdateMsg {
                     from: netevent_actor_name.clone(),
                     type_: "networkEventUpdate".to_owned(),
-                    updateType: "responseCookies".to_owned(),
+                    updateType: "eventTimings".to_owned(),
+                };
+                let extra = EventTimingsUpdateMsg {
+                    totalTime: actor.total_time(),
                 };
                 for stream in &mut connections {
-                    stream.write_merged_json_packet(&msg, &actor.response_cookies());
+                    stream.write_merged_json_packet(&msg, &extra);
                 }
 
                 let msg = NetworkEventUpdateMsg {
                     from: netevent_actor_name.clone(),
                     type_: "networkEventUpdate".to_owned(),
-                    updateType: "responseHeaders".to_owned(),
+                    updateType: "responseContent".to_owned(),
                 };
                 for stream in &mut connections {
-                    stream.write_merged_json_packet(&msg, &actor.response_headers());
+                    stream.write_merged_json_packet(&msg, &actor.response_content());
                 }
             }
         }
@@ -480,9 +615,12 @@ fn run_server(sender: Sender<DevtoolsControlMsg>,
         match msg {
             DevtoolsControlMsg::FromChrome(ChromeToDevtoolsControlMsg::AddClient(stream)) => {
                 let actors = actors.clone();
+                let network_listeners = network_listeners.clone();
                 accepted_connections.push(stream.try_clone().unwrap());
                 thread::Builder::new().name("DevtoolsClientHandler".to_owned()).spawn(move || {
-                    handle_client(actors, stream.try_clone().unwrap())
+                    network_listeners.add();
+                    handle_client(actors, stream.try_clone().unwrap());
+                    network_listeners.remove();
                 }).expect("Thread spawning failed");
             }
             DevtoolsControlMsg::FromScript(ScriptToDevtoolsControlMsg::FramerateTick(
@@ -491,22 +629,35 @@ fn run_server(sender: Sender<DevtoolsControlMsg>,
             DevtoolsControlMsg::FromScript(ScriptToDevtoolsControlMsg::NewGlobal(
                         ids, script_sender, pageinfo)) =>
                 handle_new_global(actors.clone(), ids, script_sender, &mut actor_pipelines,
-                                  &mut actor_workers, pageinfo),
+                                  &mut actor_workers, pageinfo, mem_profiler_chan.clone(), time_profiler_chan.clone(),
+                                  blocked_urls.clone()),
             DevtoolsControlMsg::FromScript(ScriptToDevtoolsControlMsg::ConsoleAPI(
                         id,
                         console_message,
                         worker_id)) =>
                 handle_console_message(actors.clone(), id, worker_id, console_message,
                                        &actor_pipelines, &actor_workers),
+            DevtoolsControlMsg::FromScript(ScriptToDevtoolsControlMsg::Paused(id, info)) =>
+                handle_paused(actors.clone(), id, info, &actor_pipelines),
+            DevtoolsControlMsg::FromScript(ScriptToDevtoolsControlMsg::Mutations(id, mutations)) =>
+                handle_mutations(actors.clone(), id, mutations, &actor_pipelines),
             DevtoolsControlMsg::FromScript(ScriptToDevtoolsControlMsg::ReportCSSError(
                         id,
                         css_error)) => {
                 let console_message =  ConsoleMessage {
-                    message: css_error.msg,
+                    message: css_error.msg.clone(),
                     logLevel: LogLevel::Warn,
                     filename: css_error.filename,
                     lineNumber: css_error.line as usize,
                     columnNumber: css_error.column as usize,
+                    arguments: vec![ConsoleArgument {
+                        value: EvaluateJSReply::StringValue(css_error.msg),
+                        preview: None,
+                        is_accessor: false,
+                    }],
+                    counter: None,
+                    timer: None,
+                    stacktrace: None,
                 };
                 handle_console_message(actors.clone(), id, None, console_message,
                                        &actor_pipelines, &actor_workers)
@@ -519,13 +670,12 @@ fn run_server(sender: Sender<DevtoolsControlMsg>,
                     connections.push(stream.try_clone().unwrap());
                 }
 
-                let pipeline_id = match network_event {
-                    NetworkEvent::HttpResponse(ref response) => response.pipeline_id,
-                    NetworkEvent::HttpRequest(ref request) => request.pipeline_id,
-                };
+                let pipeline_id = network_event.pipeline_id();
                 handle_network_event(actors.clone(), connections, &actor_pipelines, &mut actor_requests,
                                      &actor_workers, pipeline_id, request_id, network_event);
             },
+            // The actors of closed pipelines stay around for the clients that still refer to them.
+            DevtoolsControlMsg::FromChrome(ChromeToDevtoolsControlMsg::PipelineClosed(_)) => {}
             DevtoolsControlMsg::FromChrome(ChromeToDevtoolsControlMsg::ServerExitMsg) => break
         }
     }
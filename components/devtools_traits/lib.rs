@@ -29,6 +29,8 @@ use ipc_channel::ipc::IpcSender;
 use msg::constellation_msg::PipelineId;
 use servo_url::ServoUrl;
 use std::net::TcpStream;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use time::Duration;
 use time::Tm;
 
@@ -68,6 +70,8 @@ pub enum ChromeToDevtoolsControlMsg {
     /// A network event occurred (request, reply, etc.). The actor with the
     /// provided name should be notified.
     NetworkEvent(String, NetworkEvent),
+    /// The given pipeline exited, and can no longer be debugged.
+    PipelineClosed(PipelineId),
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -86,11 +90,18 @@ pub enum ScriptToDevtoolsControlMsg {
 
     /// Report a CSS parse error for the given pipeline
     ReportCSSError(PipelineId, CSSError),
+
+    /// Script execution in the given pipeline paused in the debugger, and waits for a
+    /// `DevtoolScriptControlMsg::Resume` message.
+    Paused(PipelineId, PauseInfo),
+
+    /// The DOM tree of the given pipeline changed, while devtools follow it.
+    Mutations(PipelineId, Vec<NodeMutation>),
 }
 
 /// Serialized JS return values
 /// TODO: generalize this beyond the EvaluateJS message?
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum EvaluateJSReply {
     VoidValue,
     NullValue,
@@ -100,14 +111,14 @@ pub enum EvaluateJSReply {
     ActorValue { class: String, uuid: String },
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct AttrInfo {
     pub namespace: String,
     pub name: String,
     pub value: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct NodeInfo {
     pub uniqueId: String,
     pub baseURI: String,
@@ -129,6 +140,25 @@ pub struct NodeInfo {
     pub incompleteValue: bool,
 }
 
+/// A change to the DOM tree of a page.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum NodeMutation {
+    /// An attribute of the node with the given id was set, or removed if its value is `None`.
+    Attribute {
+        target: String,
+        name: String,
+        namespace: Option<String>,
+        value: Option<String>,
+    },
+    /// Children of the node with the given id were added or removed.
+    ChildList {
+        target: String,
+        added: Vec<NodeInfo>,
+        removed: Vec<String>,
+        numChildren: usize,
+    },
+}
+
 pub struct StartedTimelineMarker {
     name: String,
     start_time: PreciseTime,
@@ -206,6 +236,18 @@ pub enum DevtoolScriptControlMsg {
     ModifyAttribute(PipelineId, String, Vec<Modification>),
     /// Request live console messages for a given pipeline (true if desired, false otherwise).
     WantsLiveNotifications(PipelineId, bool),
+    /// Request notifications of the changes to the DOM tree of a given pipeline (true if
+    /// desired, false otherwise).
+    WantsMutationNotifications(PipelineId, bool),
+    /// Replace a given node with the nodes parsed from the given markup.
+    SetOuterHTML(PipelineId, String, String, IpcSender<bool>),
+    /// Remove a given node from its parent, replying with its next sibling.
+    RemoveNode(PipelineId, String, IpcSender<Result<Option<NodeInfo>, ()>>),
+    /// Insert a given node in a given parent, before a given sibling or last.
+    InsertBefore(PipelineId, String, String, Option<String>, IpcSender<bool>),
+    /// Replace a given element with an element with the same attributes and children, and the
+    /// given tag name.
+    EditTagName(PipelineId, String, String, IpcSender<bool>),
     /// Request live notifications for a given set of timeline events for a given pipeline.
     SetTimelineMarkers(PipelineId, Vec<TimelineMarkerType>, IpcSender<Option<TimelineMarker>>),
     /// Withdraw request for live timeline notifications for a given pipeline.
@@ -215,6 +257,155 @@ pub enum DevtoolScriptControlMsg {
     RequestAnimationFrame(PipelineId, String),
     /// Direct the given pipeline to reload the current page.
     Reload(PipelineId),
+    /// Retrieve the scripts that were run in the given pipeline.
+    GetSources(PipelineId, IpcSender<Vec<SourceInfo>>),
+    /// Set a breakpoint on the given line of the script with the given URL, replying
+    /// whether there was code on that line to break on.
+    SetBreakpoint(PipelineId, String, u32, IpcSender<bool>),
+    /// Remove the breakpoint on the given line of the script with the given URL.
+    RemoveBreakpoint(PipelineId, String, u32),
+    /// Pause as soon as the next script runs in the given pipeline.
+    Interrupt(PipelineId),
+    /// Resume the paused script, optionally pausing again after the given step.
+    Resume(PipelineId, Option<StepType>),
+    /// Retrieve the stack of the paused script, newest frame first.
+    GetFrames(PipelineId, IpcSender<Vec<FrameInfo>>),
+    /// Retrieve the scopes of the frame at the given depth in the stack of the paused script,
+    /// innermost scope first.
+    GetEnvironment(PipelineId, u32, IpcSender<Option<Vec<ScopeInfo>>>),
+    /// Retrieve the style sheets of the document of the given pipeline.
+    GetStyleSheets(PipelineId, IpcSender<Vec<StyleSheetInfo>>),
+    /// Retrieve the text of the style sheet with the given index.
+    GetStyleSheetText(PipelineId, usize, IpcSender<Option<String>>),
+    /// Enable or disable the style sheet with the given index.
+    SetStyleSheetDisabled(PipelineId, usize, bool),
+    /// Retrieve the CSS rules that apply to the given node, most important first.
+    GetAppliedRules(PipelineId, String, IpcSender<Option<Vec<RuleInfo>>>),
+    /// Update the declarations of a CSS rule with a list of modifications, replying with the
+    /// updated rule.
+    ModifyRule(PipelineId, RuleLocation, Vec<RuleModification>, IpcSender<Option<RuleInfo>>),
+    /// Replace the declarations of a CSS rule, replying with the updated rule.
+    SetRuleText(PipelineId, RuleLocation, String, IpcSender<Option<RuleInfo>>),
+    /// Start sampling the stack of the scripts of the given pipeline at a regular interval.
+    StartJsSampling(PipelineId),
+    /// Stop sampling the stack of the scripts of the given pipeline, replying with the samples
+    /// taken since sampling started.
+    StopJsSampling(PipelineId, IpcSender<Vec<JsStackSample>>),
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StyleSheetInfo {
+    /// The index of the sheet among the style sheets of the document.
+    pub index: usize,
+    pub href: Option<String>,
+    /// The URL of the document the sheet belongs to.
+    pub nodeHref: String,
+    pub title: Option<String>,
+    pub disabled: bool,
+    pub ruleCount: usize,
+}
+
+/// Where a CSS rule is.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum RuleLocation {
+    /// The rule with the given index in the style sheet with the given index.
+    StyleSheet(usize, usize),
+    /// The style attribute of the node with the given id.
+    ElementStyle(String),
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RuleInfo {
+    pub location: RuleLocation,
+    /// The selector of the rule, empty for the style attribute of an element.
+    pub selector: String,
+    /// The text of the declarations of the rule.
+    pub cssText: String,
+    pub declarations: Vec<DeclarationInfo>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DeclarationInfo {
+    pub name: String,
+    pub value: String,
+    pub priority: String,
+}
+
+/// A change to a declaration of a CSS rule. A value of `None` removes the declaration.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RuleModification {
+    pub name: String,
+    pub value: Option<String>,
+    pub priority: String,
+}
+
+/// A script that was run in a global.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SourceInfo {
+    pub url: String,
+    pub text: String,
+    /// The line of the document the script starts at, for inline scripts.
+    pub startLine: u32,
+}
+
+/// How far to run a paused script before pausing again.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum StepType {
+    /// Pause at the next line, entering called functions.
+    In,
+    /// Pause at the next line of the current function.
+    Over,
+    /// Pause once the current function returns.
+    Out,
+}
+
+/// Why and where script execution paused.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PauseInfo {
+    /// One of `breakpoint`, `debuggerStatement`, `interrupted` or `resumeLimit`.
+    pub why: String,
+    pub frame: FrameInfo,
+}
+
+/// The stack of a running script at some point in time.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct JsStackSample {
+    /// When the sample was taken, in nanoseconds.
+    pub time: u64,
+    /// The frames of the stack, newest first.
+    pub frames: Vec<FrameInfo>,
+}
+
+/// A frame in the stack of a paused or sampled script.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FrameInfo {
+    pub depth: u32,
+    /// One of `call`, `eval`, `global` or `module`.
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// The name of the called function, if any.
+    pub callee: Option<String>,
+    pub url: String,
+    pub line: u32,
+    pub column: u32,
+}
+
+/// A scope of a frame in the stack of a paused script.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ScopeInfo {
+    /// One of `declarative`, `object` or `with`.
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// The function this is the scope of, if any.
+    pub callee: Option<String>,
+    pub bindings: Vec<BindingInfo>,
+}
+
+/// A variable bound in a scope.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BindingInfo {
+    pub name: String,
+    pub value: EvaluateJSReply,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -223,6 +414,7 @@ pub struct Modification {
     pub newValue: Option<String>,
 }
 
+/// The console method a message comes from, which decides how devtools render it.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum LogLevel {
     Log,
@@ -230,15 +422,61 @@ pub enum LogLevel {
     Info,
     Warn,
     Error,
+    Trace,
+    Dir,
+    Table,
+    Assert,
+    Group,
+    GroupCollapsed,
+    GroupEnd,
+    Count,
+    Time,
+    TimeLog,
+    TimeEnd,
+}
+
+/// A value passed to a console method.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ConsoleArgument {
+    pub value: EvaluateJSReply,
+    /// The own enumerable properties of an object, for devtools to show it without asking
+    /// script. The properties of `console.table` and `console.dir` objects have their own
+    /// preview too.
+    pub preview: Option<Vec<(String, ConsoleArgument)>>,
+    /// Whether this is an accessor property in the preview of an object, which is not read,
+    /// rather than a value.
+    pub is_accessor: bool,
+}
+
+/// A `count` call, with the count of its label afterwards.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ConsoleCounter {
+    pub label: String,
+    pub count: u32,
+}
+
+/// A `time`, `timeLog` or `timeEnd` call, with the milliseconds since the timer started for
+/// the latter two.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ConsoleTimer {
+    pub name: String,
+    pub duration: Option<u64>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ConsoleMessage {
+    /// The text of the message, as printed on stdout.
     pub message: String,
     pub logLevel: LogLevel,
     pub filename: String,
     pub lineNumber: usize,
     pub columnNumber: usize,
+    /// The values the console method was called with, which `message` formats.
+    pub arguments: Vec<ConsoleArgument>,
+    pub counter: Option<ConsoleCounter>,
+    pub timer: Option<ConsoleTimer>,
+    /// The stack of the caller, newest frame first, for `trace` and failed `assert` calls.
+    pub stacktrace: Option<Vec<FrameInfo>>,
 }
 
 bitflags! {
@@ -306,12 +544,120 @@ pub struct HttpResponse {
     pub status: Option<(u16, Vec<u8>)>,
     pub body: Option<Vec<u8>>,
     pub pipeline_id: PipelineId,
+    /// The parameters of the TLS connection the response was received over, if any.
+    pub security_info: Option<SecurityInfo>,
+    /// Whether the response was taken from the HTTP cache without going to the network.
+    pub from_cache: bool,
+}
+
+/// The parameters negotiated by a TLS connection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SecurityInfo {
+    pub protocol_version: String,
+    pub cipher_suite: String,
+    pub key_length: Option<i32>,
+    pub certificate: Option<CertificateInfo>,
+}
+
+/// The certificate a server presented.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    pub valid_from: String,
+    pub valid_until: String,
+    /// The SHA-256 fingerprint, as colon-separated hexadecimal bytes.
+    pub sha256_fingerprint: String,
+}
+
+/// How long each phase of an HTTP request took, in milliseconds. The DNS, connect and TLS
+/// phases are zero when an existing connection was reused.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HttpTimings {
+    pub blocked: u64,
+    pub dns: u64,
+    pub connect: u64,
+    pub tls: u64,
+    pub send: u64,
+    pub wait: u64,
+    pub receive: u64,
+}
+
+impl HttpTimings {
+    pub fn total(&self) -> u64 {
+        self.blocked + self.dns + self.connect + self.tls + self.send + self.wait + self.receive
+    }
 }
 
 #[derive(Debug)]
 pub enum NetworkEvent {
     HttpRequest(HttpRequest),
     HttpResponse(HttpResponse),
+    /// A chunk of the body of the response, sent as it is received.
+    ResponseBodyChunk(PipelineId, Vec<u8>),
+    /// The body of the response has been received completely.
+    ResponseEnd(PipelineId, HttpTimings),
+}
+
+impl NetworkEvent {
+    pub fn pipeline_id(&self) -> PipelineId {
+        match *self {
+            NetworkEvent::HttpRequest(ref request) => request.pipeline_id,
+            NetworkEvent::HttpResponse(ref response) => response.pipeline_id,
+            NetworkEvent::ResponseBodyChunk(pipeline_id, _) |
+            NetworkEvent::ResponseEnd(pipeline_id, _) => pipeline_id,
+        }
+    }
+}
+
+/// The number of clients that want to be sent response bodies, which the devtools server, the
+/// debugger and the HAR recorder share with the resource threads. Response bodies are only
+/// copied to devtools while there is at least one.
+#[derive(Clone, Debug, Default)]
+pub struct NetworkListeners(Arc<AtomicUsize>);
+
+impl NetworkListeners {
+    pub fn add(&self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn remove(&self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    pub fn any(&self) -> bool {
+        self.0.load(Ordering::SeqCst) > 0
+    }
+}
+
+/// The URLs the network monitor blocks requests to, which the devtools server shares with the
+/// resource threads. Patterns match anywhere in a URL, and may contain `*` wildcards.
+#[derive(Clone, Debug, Default)]
+pub struct BlockedUrls(Arc<RwLock<Vec<String>>>);
+
+impl BlockedUrls {
+    pub fn get(&self) -> Vec<String> {
+        self.0.read().unwrap().clone()
+    }
+
+    pub fn set(&self, patterns: Vec<String>) {
+        *self.0.write().unwrap() = patterns;
+    }
+
+    pub fn is_blocked(&self, url: &ServoUrl) -> bool {
+        self.0.read().unwrap().iter().any(|pattern| {
+            let mut rest = url.as_str();
+            !pattern.is_empty() && pattern.split('*').all(|part| {
+                match rest.find(part) {
+                    Some(index) => {
+                        rest = &rest[index + part.len()..];
+                        true
+                    }
+                    None => false,
+                }
+            })
+        })
+    }
 }
 
 impl TimelineMarker {
@@ -350,6 +696,11 @@ impl PreciseTime {
         PreciseTime(time::precise_time_ns())
     }
 
+    /// The time of the given `time::precise_time_ns` timestamp.
+    pub fn from_ns(ns: u64) -> PreciseTime {
+        PreciseTime(ns)
+    }
+
     pub fn to(&self, later: PreciseTime) -> Duration {
         Duration::nanoseconds((later.0 - self.0) as i64)
     }
@@ -122,6 +122,36 @@ impl DisplayList {
         None
     }
 
+    /// Returns the byte offset, within the text of `node` before white space was collapsed, of
+    /// the character closest to `point`, which is in the coordinate space of the display list.
+    ///
+    /// Points below the last line of text map to the end of the text.
+    ///
+    /// TODO: This assumes that the text is laid out horizontally.
+    pub fn text_offset_at_point(&self, node: OpaqueNode, point: &Point2D<Au>) -> Option<usize> {
+        let mut offset_after_last_item = None;
+        for item in &self.list {
+            let text = match *item {
+                DisplayItem::Text(ref text) if item.base().metadata.node == node => text,
+                _ => continue,
+            };
+            if point.y < item.base().bounds.max_y() {
+                let advance = point.x - text.baseline_origin.x;
+                let glyph_count = text.text_run.range_index_of_advance(&text.range, advance);
+                let item_start = text.range.begin().to_usize();
+                let item_end = text.range.end().to_usize();
+                let offset = text.text_run.text[item_start..item_end]
+                    .char_indices()
+                    .nth(glyph_count)
+                    .map_or(item_end, |(index, _)| item_start + index);
+                return text.text_run.original_byte_index(&text.range, ByteIndex(offset as isize));
+            }
+            offset_after_last_item = text.text_run.original_byte_index(&text.range, text.range.end());
+        }
+
+        offset_after_last_item
+    }
+
     pub fn print(&self) {
         let mut print_tree = PrintTree::new("Display List".to_owned());
         self.print_with_tree(&mut print_tree);
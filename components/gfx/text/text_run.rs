@@ -35,6 +35,9 @@ pub struct TextRun {
     pub glyphs: Arc<Vec<GlyphRun>>,
     pub bidi_level: bidi::Level,
     pub extra_word_spacing: Au,
+    /// The byte index in `text` of each character, paired with the byte index of the
+    /// character it was made from in the text of its node, before white space was collapsed.
+    pub original_offsets: Arc<Vec<(usize, usize)>>,
 }
 
 impl Drop for TextRun {
@@ -191,6 +194,7 @@ impl<'a> TextRun {
             glyphs: Arc::new(glyphs),
             bidi_level: bidi_level,
             extra_word_spacing: Au(0),
+            original_offsets: Arc::new(vec![]),
         }
     }
 
@@ -305,6 +309,27 @@ impl<'a> TextRun {
         })
     }
 
+    /// Maps a byte index in `range`, whose characters were all made from the text of the
+    /// same node, to a byte index in that text.
+    pub fn original_byte_index(&self, range: &Range<ByteIndex>, index: ByteIndex) -> Option<usize> {
+        if range.is_empty() {
+            return None;
+        }
+        // A character starting at the end of the range belongs to whatever follows it.
+        let limit = if index < range.end() { index } else { range.end() - ByteIndex(1) };
+        let offsets = &self.original_offsets;
+        let position = match offsets.binary_search_by_key(&limit.to_usize(), |&(run_index, _)| run_index) {
+            Ok(position) => position,
+            Err(0) => return None,
+            Err(position) => position - 1,
+        };
+        let (run_index, original_index) = self.original_offsets[position];
+        if run_index < range.begin().to_usize() {
+            return None;
+        }
+        Some(original_index + index.to_usize() - run_index)
+    }
+
     /// Returns the index in the range of the first glyph advancing over given advance
     pub fn range_index_of_advance(&self, range: &Range<ByteIndex>, advance: Au) -> usize {
         // TODO(Issue #199): alter advance direction for RTL
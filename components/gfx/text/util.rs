@@ -17,24 +17,25 @@ pub enum CompressionMode {
 // * Issue #113: consider incoming text state (arabic, etc)
 //               and propagate outgoing text state (dual of above)
 //
-// * Issue #114: record skipped and kept chars for mapping original to new text
-//
 // * Untracked: various edge cases for bidi, CJK, etc.
+//
+// Each character kept in `output_text` gets an entry in `original_offsets`, which maps
+// its byte index in `output_text` to the byte index in `text` of the character it was
+// made from.
 pub fn transform_text(text: &str,
                       mode: CompressionMode,
                       incoming_whitespace: bool,
-                      output_text: &mut String)
+                      output_text: &mut String,
+                      original_offsets: &mut Vec<(usize, usize)>)
                       -> bool {
     let out_whitespace = match mode {
         CompressionMode::CompressNone | CompressionMode::DiscardNewline => {
-            for ch in text.chars() {
-                if is_discardable_char(ch, mode) {
-                    // TODO: record skipped char
-                } else {
-                    // TODO: record kept char
+            for (index, ch) in text.char_indices() {
+                if !is_discardable_char(ch, mode) {
                     if ch == '\t' {
                         // TODO: set "has tab" flag
                     }
+                    original_offsets.push((output_text.len(), index));
                     output_text.push(ch);
                 }
             }
@@ -43,7 +44,7 @@ pub fn transform_text(text: &str,
 
         CompressionMode::CompressWhitespace | CompressionMode::CompressWhitespaceNewline => {
             let mut in_whitespace: bool = incoming_whitespace;
-            for ch in text.chars() {
+            for (index, ch) in text.char_indices() {
                 // TODO: discard newlines between CJK chars
                 let mut next_in_whitespace: bool = is_in_whitespace(ch, mode);
 
@@ -51,16 +52,13 @@ pub fn transform_text(text: &str,
                     if is_always_discardable_char(ch) {
                         // revert whitespace setting, since this char was discarded
                         next_in_whitespace = in_whitespace;
-                        // TODO: record skipped char
                     } else {
-                        // TODO: record kept char
+                        original_offsets.push((output_text.len(), index));
                         output_text.push(ch);
                     }
                 } else { /* next_in_whitespace; possibly add a space char */
-                    if in_whitespace {
-                        // TODO: record skipped char
-                    } else {
-                        // TODO: record kept char
+                    if !in_whitespace {
+                        original_offsets.push((output_text.len(), index));
                         output_text.push(' ');
                     }
                 }
@@ -1443,7 +1443,8 @@ impl BlockFlow {
             display::T::table_row_group |
             display::T::table |
             display::T::inline_block |
-            display::T::flex => {
+            display::T::flex |
+            display::T::grid => {
                 FormattingContextType::Other
             }
             _ if style.get_box().overflow_x != overflow_x::T::visible ||
@@ -1562,7 +1563,8 @@ impl BlockFlow {
 
     fn is_inline_block_or_inline_flex(&self) -> bool {
         self.fragment.style().get_box().display == display::T::inline_block ||
-        self.fragment.style().get_box().display == display::T::inline_flex
+        self.fragment.style().get_box().display == display::T::inline_flex ||
+        self.fragment.style().get_box().display == display::T::inline_grid
     }
 
     /// Computes the content portion (only) of the intrinsic inline sizes of this flow. This is
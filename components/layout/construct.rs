@@ -25,9 +25,11 @@ use flow_ref::FlowRef;
 use fragment::{CanvasFragmentInfo, ImageFragmentInfo, InlineAbsoluteFragmentInfo, SvgFragmentInfo};
 use fragment::{Fragment, GeneratedContentInfo, IframeFragmentInfo, FragmentFlags};
 use fragment::{InlineAbsoluteHypotheticalFragmentInfo, TableColumnFragmentInfo};
-use fragment::{InlineBlockFragmentInfo, SpecificFragmentInfo, UnscannedTextFragmentInfo};
+use fragment::{InlineBlockFragmentInfo, InputWidgetFragmentInfo, SpecificFragmentInfo};
+use fragment::UnscannedTextFragmentInfo;
 use fragment::WhitespaceStrippingResult;
 use gfx::display_list::OpaqueNode;
+use grid::GridFlow;
 use inline::{InlineFlow, InlineFragmentNodeInfo, InlineFragmentNodeFlags};
 use linked_list::prepend_from;
 use list_item::{ListItemFlow, ListStyleTypeContent};
@@ -637,7 +639,8 @@ impl<'a, ConcreteThreadSafeLayoutNode: ThreadSafeLayoutNode>
     ///
     /// * Generated content gets the initial content specified by the `content` attribute of the
     ///   CSS.
-    /// * `<input>` and `<textarea>` elements get their content.
+    /// * `<input>` and `<textarea>` elements get their content, or a widget for `<input>`
+    ///   types such as `range` and `color`.
     ///
     /// FIXME(pcwalton): It is not clear to me that there isn't a cleaner way to handle
     /// `<textarea>`.
@@ -661,12 +664,26 @@ impl<'a, ConcreteThreadSafeLayoutNode: ThreadSafeLayoutNode>
             let mut style = node.style(context);
             style = context.stylist.style_for_anonymous(
                 &context.guards, &PseudoElement::ServoText, &style);
-            if node_is_input_or_text_area {
-                style = context.stylist.style_for_anonymous(
-                    &context.guards, &PseudoElement::ServoInputText, &style)
-            }
 
-            self.create_fragments_for_node_text_content(&mut fragments, node, &style)
+            // Inputs that are painted as widgets show the widget in place of their value.
+            let widget = if node_is_input_or_text_area { node.input_widget() } else { None };
+            if let Some(widget) = widget {
+                let info = InputWidgetFragmentInfo::new(widget);
+                fragments.fragments.push_back(Fragment::from_opaque_node_and_style(
+                        node.opaque(),
+                        node.get_pseudo_element_type().strip(),
+                        style,
+                        node.selected_style(),
+                        node.restyle_damage(),
+                        SpecificFragmentInfo::InputWidget(info)));
+            } else {
+                if node_is_input_or_text_area {
+                    style = context.stylist.style_for_anonymous(
+                        &context.guards, &PseudoElement::ServoInputText, &style)
+                }
+
+                self.create_fragments_for_node_text_content(&mut fragments, node, &style)
+            }
         }
         self.build_flow_for_block_starting_with_fragments(flow, node, fragments)
     }
@@ -924,13 +941,15 @@ impl<'a, ConcreteThreadSafeLayoutNode: ThreadSafeLayoutNode>
         ConstructionResult::ConstructionItem(construction_item)
     }
 
-    /// Build the fragment for an inline-block or inline-flex, based on the `display` flag
+    /// Build the fragment for an inline-block, inline-flex or inline-grid, based on the `display`
+    /// flag
     fn build_fragment_for_inline_block_or_inline_flex(&mut self, node: &ConcreteThreadSafeLayoutNode,
                                                       display: display::T) -> ConstructionResult {
         let block_flow_result = match display {
             display::T::inline_block => self.build_flow_for_block(node, None),
             display::T::inline_flex => self.build_flow_for_flex(node, None),
-            _ => panic!("The flag should be inline-block or inline-flex")
+            display::T::inline_grid => self.build_flow_for_grid(node, None),
+            _ => panic!("The flag should be inline-block, inline-flex or inline-grid")
         };
         let (block_flow, abs_descendants) = match block_flow_result {
             ConstructionResult::Flow(block_flow, abs_descendants) => (block_flow, abs_descendants),
@@ -1312,6 +1331,16 @@ impl<'a, ConcreteThreadSafeLayoutNode: ThreadSafeLayoutNode>
         self.build_flow_for_block_like(flow, node)
     }
 
+    /// Builds a flow for a node with 'display: grid'.
+    fn build_flow_for_grid(&mut self,
+                           node: &ConcreteThreadSafeLayoutNode,
+                           float_kind: Option<FloatKind>)
+                           -> ConstructionResult {
+        let fragment = self.build_fragment_for_block(node);
+        let flow = FlowRef::new(Arc::new(GridFlow::from_fragment(fragment, float_kind)));
+        self.build_flow_for_block_like(flow, node)
+    }
+
     /// Attempts to perform incremental repair to account for recent changes to this node. This
     /// can fail and return false, indicating that flows will need to be reconstructed.
     ///
@@ -1597,6 +1626,19 @@ impl<'a, ConcreteThreadSafeLayoutNode> PostorderNodeMutTraversal<ConcreteThreadS
                 self.set_flow_construction_result(node, construction_result)
             }
 
+            // Grid items contribute grid flow construction results.
+            (display::T::grid, float_value, _) => {
+                let float_kind = FloatKind::from_property(float_value);
+                let construction_result = self.build_flow_for_grid(node, float_kind);
+                self.set_flow_construction_result(node, construction_result)
+            }
+
+            (display::T::inline_grid, _, _) => {
+                let construction_result = self.build_fragment_for_inline_block_or_inline_flex(node,
+                                                                                              display::T::inline_grid);
+                self.set_flow_construction_result(node, construction_result)
+            }
+
             // Block flows that are not floated contribute block flow construction results.
             //
             // TODO(pcwalton): Make this only trigger for blocks and handle the other `display`
@@ -1914,7 +1956,7 @@ impl Legalizer {
     ///
     /// This method attempts to create anonymous blocks in between `parent` and `child` if and only
     /// if those blocks will only ever have `child` as their sole child. At present, this is only
-    /// true for anonymous block children of flex flows.
+    /// true for anonymous block children of flex and grid flows.
     fn try_to_add_child(&mut self, context: &SharedStyleContext, parent: &mut FlowRef, child: &mut FlowRef)
                         -> bool {
         let parent = self.stack.last_mut().unwrap_or(parent);
@@ -1983,6 +2025,37 @@ impl Legalizer {
                 true
             }
 
+            // Grid items are laid out like the items of a row flex container: their grid container
+            // sets their inline size and margins.
+            (FlowClass::Grid, FlowClass::Inline) => {
+                flow::mut_base(FlowRef::deref_mut(child)).flags.insert(FlowFlags::MARGINS_CANNOT_COLLAPSE);
+                let mut block_wrapper =
+                    Legalizer::create_anonymous_flow(context,
+                                                     parent,
+                                                     &[PseudoElement::ServoAnonymousBlock],
+                                                     SpecificFragmentInfo::Generic,
+                                                     BlockFlow::from_fragment);
+                {
+                    let block = FlowRef::deref_mut(&mut block_wrapper).as_mut_block();
+                    block.base.flags.insert(FlowFlags::MARGINS_CANNOT_COLLAPSE);
+                    block.fragment.flags.insert(FragmentFlags::IS_INLINE_FLEX_ITEM);
+                }
+                block_wrapper.add_new_child((*child).clone());
+                block_wrapper.finish();
+                parent.add_new_child(block_wrapper);
+                true
+            }
+
+            (FlowClass::Grid, _) => {
+                {
+                    let block = FlowRef::deref_mut(child).as_mut_block();
+                    block.base.flags.insert(FlowFlags::MARGINS_CANNOT_COLLAPSE);
+                    block.fragment.flags.insert(FragmentFlags::IS_INLINE_FLEX_ITEM);
+                }
+                parent.add_new_child((*child).clone());
+                true
+            }
+
             _ => {
                 parent.add_new_child((*child).clone());
                 true
@@ -33,6 +33,7 @@ use gfx::display_list::{RadialGradientDisplayItem, SolidColorDisplayItem, Stacki
 use gfx::display_list::{StackingContextType, StickyFrameData, TextDisplayItem, TextOrientation};
 use gfx::display_list::WebRenderImageInfo;
 use gfx_traits::{combine_id_with_fragment_type, FragmentType, StackingContextId};
+use grid::GridFlow;
 use inline::{InlineFragmentNodeFlags, InlineFlow};
 use ipc_channel::ipc;
 use list_item::ListItemFlow;
@@ -41,6 +42,7 @@ use msg::constellation_msg::{BrowsingContextId, PipelineId};
 use net_traits::image::base::PixelFormat;
 use net_traits::image_cache::UsePlaceholder;
 use range::Range;
+use script_layout_interface::HTMLInputWidget;
 use script_layout_interface::wrapper_traits::PseudoElementType;
 use servo_config::opts;
 use servo_geometry::max_rect;
@@ -450,6 +452,12 @@ impl<'a> DisplayListBuildState<'a> {
 /// The logical width of an insertion point: at the moment, a one-pixel-wide line.
 const INSERTION_POINT_LOGICAL_WIDTH: Au = Au(1 * AU_PER_PX);
 
+/// The height of the track of a range widget.
+const RANGE_TRACK_HEIGHT: Au = Au(4 * AU_PER_PX);
+
+/// The width of the thumb of a range widget.
+const RANGE_THUMB_WIDTH: Au = Au(8 * AU_PER_PX);
+
 pub enum IdType {
     StackingContext,
     OverflowClip,
@@ -608,6 +616,13 @@ pub trait FragmentDisplayListBuilding {
                                             text_shadows: &[SimpleShadow],
                                             clip: &Rect<Au>);
 
+    /// Creates the display items that paint an `<input>` widget into its content box.
+    fn build_display_list_for_input_widget(&self,
+                                           state: &mut DisplayListBuildState,
+                                           widget: &HTMLInputWidget,
+                                           stacking_relative_content_box: &Rect<Au>,
+                                           clip: &Rect<Au>);
+
     /// Creates the display item for a text decoration: underline, overline, or line-through.
     fn build_display_list_for_text_decoration(&self,
                                               state: &mut DisplayListBuildState,
@@ -2086,6 +2101,12 @@ impl FragmentDisplayListBuilding for Fragment {
                                                              clip);
                 }
             }
+            SpecificFragmentInfo::InputWidget(ref widget_fragment_info) => {
+                self.build_display_list_for_input_widget(state,
+                                                         &widget_fragment_info.widget,
+                                                         &stacking_relative_content_box,
+                                                         clip);
+            }
             SpecificFragmentInfo::Iframe(ref fragment_info) => {
                 if !stacking_relative_content_box.is_empty() {
                     let browsing_context_id = match fragment_info.browsing_context_id {
@@ -2366,6 +2387,45 @@ impl FragmentDisplayListBuilding for Fragment {
         }
     }
 
+    fn build_display_list_for_input_widget(&self,
+                                           state: &mut DisplayListBuildState,
+                                           widget: &HTMLInputWidget,
+                                           stacking_relative_content_box: &Rect<Au>,
+                                           clip: &Rect<Au>) {
+        let content_box = stacking_relative_content_box;
+        let parts = match *widget {
+            HTMLInputWidget::Range(fraction) => {
+                // A horizontal track, with the thumb placed along it.
+                let track_height = cmp::min(RANGE_TRACK_HEIGHT, content_box.size.height);
+                let track = Rect::new(
+                    Point2D::new(content_box.origin.x,
+                                 content_box.origin.y + (content_box.size.height - track_height) / 2),
+                    Size2D::new(content_box.size.width, track_height));
+
+                let thumb_width = cmp::min(RANGE_THUMB_WIDTH, content_box.size.width);
+                let thumb_offset = (content_box.size.width - thumb_width).scale_by(fraction);
+                let thumb = Rect::new(Point2D::new(content_box.origin.x + thumb_offset, content_box.origin.y),
+                                      Size2D::new(thumb_width, content_box.size.height));
+
+                vec![(track, ColorF::rgb(187, 187, 187)), (thumb, ColorF::rgb(102, 102, 102))]
+            }
+            HTMLInputWidget::Color(color) => vec![(*content_box, color.to_gfx_color())],
+        };
+
+        for (bounds, color) in parts {
+            let base = state.create_base_display_item(
+                &bounds,
+                LocalClip::from(clip.to_rectf()),
+                self.node,
+                self.style.get_cursor(Cursor::Default),
+                DisplayListSection::Content);
+            state.add_display_item(DisplayItem::SolidColor(Box::new(SolidColorDisplayItem {
+                base: base,
+                color: color,
+            })));
+        }
+    }
+
     fn build_display_list_for_text_decoration(&self,
                                               state: &mut DisplayListBuildState,
                                               color: &RGBA,
@@ -3144,6 +3204,17 @@ impl FlexFlowDisplayListBuilding for FlexFlow {
     }
 }
 
+pub trait GridFlowDisplayListBuilding {
+    fn build_display_list_for_grid(&mut self, state: &mut DisplayListBuildState);
+}
+
+impl GridFlowDisplayListBuilding for GridFlow {
+    fn build_display_list_for_grid(&mut self, state: &mut DisplayListBuildState) {
+        // Draw the rest of the block.
+        self.as_mut_block().build_display_list_for_block(state, BorderPaintingMode::Separate)
+    }
+}
+
 trait BaseFlowDisplayListBuilding {
     fn build_display_items_for_debugging_tint(&self,
                                               state: &mut DisplayListBuildState,
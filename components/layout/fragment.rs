@@ -31,7 +31,7 @@ use net_traits::image::base::{Image, ImageMetadata};
 use net_traits::image_cache::{ImageOrMetadataAvailable, UsePlaceholder};
 use range::*;
 use script_layout_interface::{HTMLCanvasData, HTMLCanvasDataSource};
-use script_layout_interface::SVGSVGData;
+use script_layout_interface::{HTMLInputWidget, SVGSVGData};
 use script_layout_interface::wrapper_traits::{PseudoElementType, ThreadSafeLayoutElement, ThreadSafeLayoutNode};
 use serde::ser::{Serialize, SerializeStruct, Serializer};
 use servo_url::ServoUrl;
@@ -66,6 +66,14 @@ static FONT_SUPERSCRIPT_OFFSET_RATIO: f32 = 0.34;
 static DEFAULT_REPLACED_WIDTH: i32 = 300;
 static DEFAULT_REPLACED_HEIGHT: i32 = 150;
 
+/// The default size of `<input type=range>` widgets, in CSS pixels.
+static DEFAULT_RANGE_WIDGET_WIDTH: i32 = 129;
+static DEFAULT_RANGE_WIDGET_HEIGHT: i32 = 16;
+
+/// The default size of `<input type=color>` widgets, in CSS pixels.
+static DEFAULT_COLOR_WIDGET_WIDTH: i32 = 44;
+static DEFAULT_COLOR_WIDGET_HEIGHT: i32 = 19;
+
 /// Fragments (`struct Fragment`) are the leaves of the layout tree. They cannot position
 /// themselves. In general, fragments do not have a simple correspondence with CSS fragments in the
 /// specification:
@@ -169,6 +177,7 @@ pub enum SpecificFragmentInfo {
     Image(Box<ImageFragmentInfo>),
     Canvas(Box<CanvasFragmentInfo>),
     Svg(Box<SvgFragmentInfo>),
+    InputWidget(InputWidgetFragmentInfo),
 
     /// A hypothetical box (see CSS 2.1 § 10.3.7) for an absolutely-positioned block that was
     /// declared with `display: inline;`.
@@ -207,6 +216,7 @@ impl SpecificFragmentInfo {
                 SpecificFragmentInfo::Image(_) |
                 SpecificFragmentInfo::ScannedText(_) |
                 SpecificFragmentInfo::Svg(_) |
+                SpecificFragmentInfo::InputWidget(_) |
                 SpecificFragmentInfo::Table |
                 SpecificFragmentInfo::TableCell |
                 SpecificFragmentInfo::TableColumn(_) |
@@ -239,6 +249,7 @@ impl SpecificFragmentInfo {
             SpecificFragmentInfo::InlineBlock(_) => "SpecificFragmentInfo::InlineBlock",
             SpecificFragmentInfo::ScannedText(_) => "SpecificFragmentInfo::ScannedText",
             SpecificFragmentInfo::Svg(_) => "SpecificFragmentInfo::Svg",
+            SpecificFragmentInfo::InputWidget(_) => "SpecificFragmentInfo::InputWidget",
             SpecificFragmentInfo::Table => "SpecificFragmentInfo::Table",
             SpecificFragmentInfo::TableCell => "SpecificFragmentInfo::TableCell",
             SpecificFragmentInfo::TableColumn(_) => "SpecificFragmentInfo::TableColumn",
@@ -371,6 +382,29 @@ impl SvgFragmentInfo {
     }
 }
 
+/// A fragment that represents an `<input>` element that is painted as a widget instead of
+/// as text, such as a range control or a color well.
+#[derive(Clone)]
+pub struct InputWidgetFragmentInfo {
+    pub widget: HTMLInputWidget,
+    pub dom_width: Au,
+    pub dom_height: Au,
+}
+
+impl InputWidgetFragmentInfo {
+    pub fn new(widget: HTMLInputWidget) -> InputWidgetFragmentInfo {
+        let (width, height) = match widget {
+            HTMLInputWidget::Range(_) => (DEFAULT_RANGE_WIDGET_WIDTH, DEFAULT_RANGE_WIDGET_HEIGHT),
+            HTMLInputWidget::Color(_) => (DEFAULT_COLOR_WIDGET_WIDTH, DEFAULT_COLOR_WIDGET_HEIGHT),
+        };
+        InputWidgetFragmentInfo {
+            widget: widget,
+            dom_width: Au::from_px(width),
+            dom_height: Au::from_px(height),
+        }
+    }
+}
+
 
 /// A fragment that represents a replaced content image and its accompanying borders, shadows, etc.
 #[derive(Clone)]
@@ -621,6 +655,10 @@ pub struct UnscannedTextFragmentInfo {
 
     /// The selected text range.  An empty range represents the insertion point.
     pub selection: Option<Range<ByteIndex>>,
+
+    /// The byte offset of the text within the text of the node, for fragments that were split
+    /// off the end of another.
+    pub node_offset: usize,
 }
 
 impl UnscannedTextFragmentInfo {
@@ -630,6 +668,7 @@ impl UnscannedTextFragmentInfo {
         UnscannedTextFragmentInfo {
             text: text.into_boxed_str(),
             selection: selection,
+            node_offset: 0,
         }
     }
 }
@@ -854,7 +893,8 @@ impl Fragment {
             SpecificFragmentInfo::Image(_) |
             SpecificFragmentInfo::InlineAbsolute(_) |
             SpecificFragmentInfo::Multicol |
-            SpecificFragmentInfo::Svg(_) => {
+            SpecificFragmentInfo::Svg(_) |
+            SpecificFragmentInfo::InputWidget(_) => {
                 QuantitiesIncludedInIntrinsicInlineSizes::all()
             }
             SpecificFragmentInfo::Table => {
@@ -991,6 +1031,7 @@ impl Fragment {
             }
             SpecificFragmentInfo::Canvas(ref info) => info.dom_width,
             SpecificFragmentInfo::Svg(ref info) => info.dom_width,
+            SpecificFragmentInfo::InputWidget(ref info) => info.dom_width,
             // Note: Currently for replaced element with no intrinsic size,
             // this function simply returns the default object size. As long as
             // these elements do not have intrinsic aspect ratio this should be
@@ -1014,6 +1055,7 @@ impl Fragment {
             }
             SpecificFragmentInfo::Canvas(ref info) => info.dom_height,
             SpecificFragmentInfo::Svg(ref info) => info.dom_height,
+            SpecificFragmentInfo::InputWidget(ref info) => info.dom_height,
             SpecificFragmentInfo::Iframe(_) => Au::from_px(DEFAULT_REPLACED_HEIGHT),
             _ => panic!("Trying to get intrinsic height on non-replaced element!")
         }
@@ -1507,7 +1549,8 @@ impl Fragment {
             SpecificFragmentInfo::Image(_) |
             SpecificFragmentInfo::Canvas(_) |
             SpecificFragmentInfo::Iframe(_) |
-            SpecificFragmentInfo::Svg(_) => {
+            SpecificFragmentInfo::Svg(_) |
+            SpecificFragmentInfo::InputWidget(_) => {
                 let mut inline_size = match self.style.content_inline_size() {
                     LengthOrPercentageOrAuto::Auto |
                     LengthOrPercentageOrAuto::Percentage(_) => {
@@ -1949,7 +1992,8 @@ impl Fragment {
             SpecificFragmentInfo::InlineAbsolute(_) |
             SpecificFragmentInfo::ScannedText(_) |
             SpecificFragmentInfo::TruncatedFragment(_) |
-            SpecificFragmentInfo::Svg(_) => {}
+            SpecificFragmentInfo::Svg(_) |
+            SpecificFragmentInfo::InputWidget(_) => {}
         };
 
         match self.specific {
@@ -2035,7 +2079,8 @@ impl Fragment {
             SpecificFragmentInfo::InlineAbsolute(_) |
             SpecificFragmentInfo::ScannedText(_) |
             SpecificFragmentInfo::TruncatedFragment(_) |
-            SpecificFragmentInfo::Svg(_) => {}
+            SpecificFragmentInfo::Svg(_) |
+            SpecificFragmentInfo::InputWidget(_) => {}
         }
 
         match self.specific {
@@ -2086,7 +2131,8 @@ impl Fragment {
             SpecificFragmentInfo::Iframe(_) |
             SpecificFragmentInfo::Canvas(_) |
             SpecificFragmentInfo::Image(_) |
-            SpecificFragmentInfo::Svg(_) => true,
+            SpecificFragmentInfo::Svg(_) |
+            SpecificFragmentInfo::InputWidget(_) => true,
             _ => false
         }
     }
@@ -2116,6 +2162,7 @@ impl Fragment {
         let inline_metrics = match self.specific {
             SpecificFragmentInfo::Canvas(_) | SpecificFragmentInfo::Iframe(_) |
             SpecificFragmentInfo::Image(_) | SpecificFragmentInfo::Svg(_) |
+            SpecificFragmentInfo::InputWidget(_) |
             SpecificFragmentInfo::Generic | SpecificFragmentInfo::GeneratedContent(_) => {
                 let ascent = self.border_box.size.block + self.margin.block_end;
                 InlineMetrics {
@@ -2413,6 +2460,7 @@ impl Fragment {
             SpecificFragmentInfo::Image(_) |
             SpecificFragmentInfo::ScannedText(_) |
             SpecificFragmentInfo::Svg(_) |
+            SpecificFragmentInfo::InputWidget(_) |
             SpecificFragmentInfo::Table |
             SpecificFragmentInfo::TableCell |
             SpecificFragmentInfo::TableColumn(_) |
@@ -2565,7 +2613,7 @@ impl Fragment {
         }
 
         match self.style().get_box().display {
-            display::T::flex => self.style().get_position().z_index.integer_or(0),
+            display::T::flex | display::T::grid => self.style().get_position().z_index.integer_or(0),
             _ => 0,
         }
     }
@@ -2888,6 +2936,7 @@ impl Fragment {
             SpecificFragmentInfo::ScannedText(_) |
             SpecificFragmentInfo::TruncatedFragment(_) |
             SpecificFragmentInfo::Svg(_) |
+            SpecificFragmentInfo::InputWidget(_) |
             SpecificFragmentInfo::UnscannedText(_) => true
         }
     }
@@ -3112,8 +3161,8 @@ impl Overflow {
 
 bitflags! {
     pub struct FragmentFlags: u8 {
-        // TODO(stshine): find a better name since these flags can also be used for grid item.
-        /// Whether this fragment represents a child in a row flex container.
+        // TODO(stshine): find a better name since these flags are also used for grid items.
+        /// Whether this fragment represents a child in a row flex container or a grid container.
         const IS_INLINE_FLEX_ITEM = 0b0000_0001;
         /// Whether this fragment represents a child in a column flex container.
         const IS_BLOCK_FLEX_ITEM = 0b0000_0010;
@@ -0,0 +1,1496 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Layout for elements with a CSS `display` property of `grid` or `inline-grid`.
+//!
+//! https://drafts.csswg.org/css-grid/
+
+#![deny(unsafe_code)]
+
+use app_units::Au;
+use block::{AbsoluteAssignBSizesTraversal, BlockFlow};
+use context::LayoutContext;
+use display_list_builder::{DisplayListBuildState, GridFlowDisplayListBuilding};
+use display_list_builder::StackingContextCollectionState;
+use euclid::Point2D;
+use floats::FloatKind;
+use flow;
+use flow::{Flow, FlowClass, FlowFlags, OpaqueFlow};
+use fragment::{Fragment, FragmentBorderBoxIterator, Overflow};
+use incremental::RelayoutMode;
+use layout_debug;
+use model::{AdjoiningMargins, CollapsibleMargins, MaybeAuto, SizeConstraint};
+use sequential;
+use servo_atoms::Atom;
+use std::cmp::{max, min};
+use std::ops::Range;
+use style::computed_values::{align_content, align_items, align_self, justify_content};
+use style::computed_values::grid_auto_flow::AutoFlow;
+use style::logical_geometry::{Direction, LogicalSize};
+use style::properties::ComputedValues;
+use style::properties::longhands::grid_template_areas::TemplateAreas;
+use style::servo::restyle_damage::ServoRestyleDamage;
+use style::values::{CustomIdent, Either};
+use style::values::computed::{GridLine, GridTemplateComponent, LengthOrPercentage};
+use style::values::computed::{LengthOrPercentageOrAuto, TrackSize};
+use style::values::generics::grid::{GridTemplateComponent as GenericGridTemplateComponent, RepeatCount};
+use style::values::generics::grid::{TrackBreadth as GenericTrackBreadth, TrackKeyword, TrackListType};
+use style::values::generics::grid::{TrackListValue, TrackRepeat, TrackSize as GenericTrackSize};
+
+/// Resolves a length or percentage against the given basis, or returns `None` if it is a
+/// percentage of an indefinite size.
+fn used_length(length: &LengthOrPercentage, basis: Option<Au>) -> Option<Au> {
+    match *length {
+        LengthOrPercentage::Length(length) => Some(Au::from(length)),
+        LengthOrPercentage::Percentage(percent) => basis.map(|basis| basis.scale_by(percent.0)),
+        LengthOrPercentage::Calc(calc) => calc.to_used_value(basis),
+    }
+}
+
+/// A track sizing function, with its lengths resolved.
+///
+/// https://drafts.csswg.org/css-grid/#track-sizing-function
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+enum TrackBreadth {
+    Fixed(Au),
+    MinContent,
+    MaxContent,
+    Auto,
+    /// A flexible size, in `fr` units.
+    Flex(f32),
+    /// The maximum of a `fit-content()` track, with its limit.
+    FitContent(Au),
+}
+
+impl TrackBreadth {
+    fn new(breadth: &GenericTrackBreadth<LengthOrPercentage>, basis: Option<Au>) -> TrackBreadth {
+        match *breadth {
+            GenericTrackBreadth::Breadth(ref length) => {
+                // Percentages of an indefinite size are treated as `auto`.
+                used_length(length, basis).map_or(TrackBreadth::Auto, TrackBreadth::Fixed)
+            }
+            GenericTrackBreadth::Fr(factor) => TrackBreadth::Flex(factor),
+            GenericTrackBreadth::Keyword(TrackKeyword::Auto) => TrackBreadth::Auto,
+            GenericTrackBreadth::Keyword(TrackKeyword::MinContent) => TrackBreadth::MinContent,
+            GenericTrackBreadth::Keyword(TrackKeyword::MaxContent) => TrackBreadth::MaxContent,
+        }
+    }
+
+    fn is_intrinsic(&self) -> bool {
+        match *self {
+            TrackBreadth::MinContent | TrackBreadth::MaxContent |
+            TrackBreadth::Auto | TrackBreadth::FitContent(_) => true,
+            TrackBreadth::Fixed(_) | TrackBreadth::Flex(_) => false,
+        }
+    }
+}
+
+/// The size of a `<track-size>`, if it can be known without laying out the grid items.
+fn fixed_track_size(size: &TrackSize, basis: Option<Au>) -> Option<Au> {
+    let fixed = |breadth: &GenericTrackBreadth<LengthOrPercentage>| {
+        match TrackBreadth::new(breadth, basis) {
+            TrackBreadth::Fixed(length) => Some(length),
+            _ => None,
+        }
+    };
+    match *size {
+        GenericTrackSize::Breadth(ref breadth) => fixed(breadth),
+        GenericTrackSize::Minmax(ref min, ref max) => fixed(max).or_else(|| fixed(min)),
+        GenericTrackSize::FitContent(_) => None,
+    }
+}
+
+/// A row or column of the grid.
+#[derive(Clone, Debug, Serialize)]
+struct Track {
+    /// The min track sizing function.
+    min_sizing: TrackBreadth,
+    /// The max track sizing function.
+    max_sizing: TrackBreadth,
+    /// The used size of the track, once it has been sized.
+    base_size: Au,
+    /// The size the track may grow to while intrinsic sizes are resolved, or `None` if there is
+    /// no such limit.
+    growth_limit: Option<Au>,
+    /// True if this is an empty track from an `auto-fit` repetition, which collapses.
+    collapsed: bool,
+    /// The offset of the start of this track from the content edge of the grid container.
+    position: Au,
+}
+
+impl Track {
+    fn new(size: &TrackSize, basis: Option<Au>) -> Track {
+        let (min_sizing, max_sizing) = match *size {
+            GenericTrackSize::Breadth(ref breadth) => {
+                match TrackBreadth::new(breadth, basis) {
+                    // A lone flexible size is treated as `minmax(auto, <flex>)`.
+                    flex @ TrackBreadth::Flex(_) => (TrackBreadth::Auto, flex),
+                    breadth => (breadth, breadth),
+                }
+            }
+            GenericTrackSize::Minmax(ref min, ref max) => {
+                let min = match TrackBreadth::new(min, basis) {
+                    TrackBreadth::Flex(_) => TrackBreadth::Auto,
+                    min => min,
+                };
+                (min, TrackBreadth::new(max, basis))
+            }
+            GenericTrackSize::FitContent(ref limit) => {
+                (TrackBreadth::Auto,
+                 used_length(limit, basis).map_or(TrackBreadth::MaxContent, TrackBreadth::FitContent))
+            }
+        };
+        Track {
+            min_sizing: min_sizing,
+            max_sizing: max_sizing,
+            base_size: Au(0),
+            growth_limit: None,
+            collapsed: false,
+            position: Au(0),
+        }
+    }
+
+    fn flex_factor(&self) -> Option<f32> {
+        match self.max_sizing {
+            TrackBreadth::Flex(factor) if !self.collapsed => Some(factor),
+            _ => None,
+        }
+    }
+
+    fn end(&self) -> Au {
+        self.position + self.base_size
+    }
+}
+
+/// The space tracks are sized in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum AvailableSpace {
+    Definite(Au),
+    MinContent,
+    MaxContent,
+}
+
+/// The sizes an item contributes to the tracks it spans, including its margins.
+struct Contribution {
+    span: Range<usize>,
+    min_content: Au,
+    max_content: Au,
+}
+
+/// The size of the gaps between the given tracks, which collapse along with empty `auto-fit`
+/// tracks.
+fn gaps_size(tracks: &[Track], gap: Au) -> Au {
+    match tracks.iter().filter(|track| !track.collapsed).count() as i32 {
+        0 => Au(0),
+        count => gap * (count - 1),
+    }
+}
+
+/// The size of the given tracks, including the gaps between them.
+fn total_size(tracks: &[Track], gap: Au) -> Au {
+    tracks.iter().fold(gaps_size(tracks, gap), |total, track| total + track.base_size)
+}
+
+/// Grows the base sizes (or the growth limits) of the given tracks that satisfy `eligible` so
+/// that together they are at least `size`, sharing the extra space equally.
+fn distribute_extra_space<F>(tracks: &mut [Track], span: Range<usize>, size: Au, eligible: F, growth_limits: bool)
+                             where F: Fn(&Track) -> bool {
+    let current = |track: &Track| {
+        if growth_limits { track.growth_limit.unwrap_or(track.base_size) } else { track.base_size }
+    };
+    let spanned_size = tracks[span.clone()].iter().fold(Au(0), |total, track| total + current(track));
+    let count = tracks[span.clone()].iter().filter(|track| eligible(track)).count() as i32;
+    if size <= spanned_size || count == 0 {
+        return
+    }
+    let share = (size - spanned_size) / count;
+    for track in tracks[span].iter_mut().filter(|track| eligible(track)) {
+        if growth_limits {
+            track.growth_limit = Some(current(track) + share);
+        } else {
+            track.base_size += share;
+        }
+    }
+}
+
+/// Finds the size of `1fr` when the flexible tracks in `span` share `space` with the other tracks
+/// in it.
+///
+/// https://drafts.csswg.org/css-grid/#algo-find-fr-size
+fn find_fr_size(tracks: &[Track], span: Range<usize>, space: Au) -> Au {
+    let mut inflexible = vec![false; tracks.len()];
+    loop {
+        let mut leftover_space = space;
+        let mut flex_factor_sum = 0.;
+        for (index, track) in tracks[span.clone()].iter().enumerate() {
+            match track.flex_factor() {
+                Some(factor) if !inflexible[span.start + index] => flex_factor_sum += factor,
+                _ => leftover_space -= track.base_size,
+            }
+        }
+        let fr_size = max(leftover_space, Au(0)).scale_by(1. / flex_factor_sum.max(1.));
+
+        // Tracks whose base size is larger than their share of the space are treated as
+        // inflexible, and the space is shared again.
+        let mut done = true;
+        for (index, track) in tracks[span.clone()].iter().enumerate() {
+            if let Some(factor) = track.flex_factor() {
+                if !inflexible[span.start + index] && fr_size.scale_by(factor) < track.base_size {
+                    inflexible[span.start + index] = true;
+                    done = false;
+                }
+            }
+        }
+        if done {
+            return fr_size
+        }
+    }
+}
+
+/// Sizes the tracks of one axis of a grid from the contributions of the items in them.
+///
+/// https://drafts.csswg.org/css-grid/#algo-track-sizing
+fn size_tracks(tracks: &mut [Track],
+               contributions: &[Contribution],
+               gap: Au,
+               available: AvailableSpace,
+               stretch: bool) {
+    // Initialize each track's base size and growth limit.
+    for track in tracks.iter_mut() {
+        track.base_size = match track.min_sizing {
+            TrackBreadth::Fixed(length) => length,
+            _ => Au(0),
+        };
+        track.growth_limit = match track.max_sizing {
+            TrackBreadth::Fixed(length) => Some(max(length, track.base_size)),
+            _ => None,
+        };
+        if track.collapsed {
+            track.growth_limit = Some(track.base_size);
+        }
+    }
+
+    // Resolve intrinsic track sizes, from items that span a single track first.
+    for contribution in contributions.iter().filter(|contribution| contribution.span.len() == 1) {
+        let track = &mut tracks[contribution.span.start];
+        match track.min_sizing {
+            TrackBreadth::MinContent => {
+                track.base_size = max(track.base_size, contribution.min_content);
+            }
+            TrackBreadth::MaxContent => {
+                track.base_size = max(track.base_size, contribution.max_content);
+            }
+            TrackBreadth::Auto if available == AvailableSpace::MaxContent => {
+                track.base_size = max(track.base_size, contribution.max_content);
+            }
+            TrackBreadth::Auto => {
+                track.base_size = max(track.base_size, contribution.min_content);
+            }
+            _ => {}
+        }
+        let limit = match track.max_sizing {
+            TrackBreadth::MinContent => contribution.min_content,
+            TrackBreadth::MaxContent | TrackBreadth::Auto => contribution.max_content,
+            TrackBreadth::FitContent(limit) => {
+                max(contribution.min_content, min(contribution.max_content, limit))
+            }
+            TrackBreadth::Fixed(_) | TrackBreadth::Flex(_) => continue,
+        };
+        track.growth_limit = Some(max(track.growth_limit.unwrap_or(Au(0)), limit));
+    }
+
+    // Then from items that span several tracks, none of them flexible, from the smallest span up.
+    let mut spanning: Vec<&Contribution> = contributions.iter().filter(|contribution| {
+        contribution.span.len() > 1 &&
+        tracks[contribution.span.clone()].iter().all(|track| track.flex_factor().is_none())
+    }).collect();
+    spanning.sort_by_key(|contribution| contribution.span.len());
+    for contribution in spanning {
+        let gaps = gap * (contribution.span.len() as i32 - 1);
+        let min_size = if available == AvailableSpace::MaxContent {
+            contribution.max_content
+        } else {
+            contribution.min_content
+        };
+        distribute_extra_space(tracks, contribution.span.clone(), min_size - gaps,
+                               |track| track.min_sizing.is_intrinsic(), false);
+        distribute_extra_space(tracks, contribution.span.clone(), contribution.max_content - gaps,
+                               |track| track.max_sizing.is_intrinsic(), true);
+    }
+
+    for track in tracks.iter_mut() {
+        let growth_limit = max(track.growth_limit.unwrap_or(track.base_size), track.base_size);
+        track.growth_limit = Some(growth_limit);
+    }
+
+    // Maximize the tracks, growing them to their growth limits with the free space.
+    match available {
+        AvailableSpace::Definite(size) => {
+            let mut free_space = size - total_size(tracks, gap);
+            while free_space > Au(0) {
+                let count = tracks.iter().filter(|track| Some(track.base_size) < track.growth_limit).count();
+                if count == 0 {
+                    break
+                }
+                let share = free_space / count as i32;
+                if share == Au(0) {
+                    break
+                }
+                for track in tracks.iter_mut() {
+                    if let Some(growth_limit) = track.growth_limit {
+                        let growth = min(share, max(growth_limit - track.base_size, Au(0)));
+                        track.base_size += growth;
+                        free_space -= growth;
+                    }
+                }
+            }
+        }
+        AvailableSpace::MaxContent => {
+            for track in tracks.iter_mut() {
+                track.base_size = track.growth_limit.unwrap_or(track.base_size);
+            }
+        }
+        AvailableSpace::MinContent => {}
+    }
+
+    // Expand the flexible tracks. Under a min-content constraint their fraction is zero.
+    if tracks.iter().any(|track| track.flex_factor().is_some()) {
+        let fr_size = match available {
+            AvailableSpace::Definite(size) => {
+                Some(find_fr_size(tracks, 0..tracks.len(), size - gaps_size(tracks, gap)))
+            }
+            AvailableSpace::MaxContent => {
+                let mut fr_size = Au(0);
+                for track in tracks.iter() {
+                    if let Some(factor) = track.flex_factor() {
+                        fr_size = max(fr_size, track.base_size.scale_by(1. / factor.max(1.)));
+                    }
+                }
+                for contribution in contributions {
+                    if tracks[contribution.span.clone()].iter().any(|track| track.flex_factor().is_some()) {
+                        let gaps = gap * (contribution.span.len() as i32 - 1);
+                        fr_size = max(fr_size, find_fr_size(tracks,
+                                                            contribution.span.clone(),
+                                                            contribution.max_content - gaps));
+                    }
+                }
+                Some(fr_size)
+            }
+            AvailableSpace::MinContent => None,
+        };
+        if let Some(fr_size) = fr_size {
+            for track in tracks.iter_mut() {
+                if let Some(factor) = track.flex_factor() {
+                    track.base_size = max(track.base_size, fr_size.scale_by(factor));
+                }
+            }
+        }
+    }
+
+    // Stretch the `auto` tracks into whatever space is left.
+    if let AvailableSpace::Definite(size) = available {
+        let free_space = size - total_size(tracks, gap);
+        let count = tracks.iter().filter(|track| {
+            !track.collapsed && track.max_sizing == TrackBreadth::Auto
+        }).count() as i32;
+        if stretch && free_space > Au(0) && count > 0 {
+            for track in tracks.iter_mut() {
+                if !track.collapsed && track.max_sizing == TrackBreadth::Auto {
+                    track.base_size += free_space / count;
+                }
+            }
+        }
+    }
+}
+
+/// How tracks are distributed in the free space of a grid container, from `justify-content` or
+/// `align-content`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ContentDistribution {
+    Start,
+    End,
+    Center,
+    SpaceBetween,
+    SpaceAround,
+    Stretch,
+}
+
+impl ContentDistribution {
+    fn from_justify_content(justify_content: justify_content::T) -> ContentDistribution {
+        match justify_content {
+            // Servo has no `normal`, which behaves as `stretch` in a grid container, so the initial
+            // value stands in for it.
+            justify_content::T::flex_start | justify_content::T::stretch => ContentDistribution::Stretch,
+            justify_content::T::flex_end => ContentDistribution::End,
+            justify_content::T::center => ContentDistribution::Center,
+            justify_content::T::space_between => ContentDistribution::SpaceBetween,
+            justify_content::T::space_around => ContentDistribution::SpaceAround,
+        }
+    }
+
+    fn from_align_content(align_content: align_content::T) -> ContentDistribution {
+        match align_content {
+            align_content::T::stretch => ContentDistribution::Stretch,
+            align_content::T::flex_start => ContentDistribution::Start,
+            align_content::T::flex_end => ContentDistribution::End,
+            align_content::T::center => ContentDistribution::Center,
+            align_content::T::space_between => ContentDistribution::SpaceBetween,
+            align_content::T::space_around => ContentDistribution::SpaceAround,
+        }
+    }
+}
+
+/// Sets the positions of sized tracks, distributing the free space of the container between
+/// them.
+fn position_tracks(tracks: &mut [Track], gap: Au, free_space: Au, distribution: ContentDistribution) {
+    let count = tracks.iter().filter(|track| !track.collapsed).count() as i32;
+    let (mut position, interval) = if free_space <= Au(0) || count == 0 {
+        (Au(0), Au(0))
+    } else {
+        match distribution {
+            ContentDistribution::Start | ContentDistribution::Stretch => (Au(0), Au(0)),
+            ContentDistribution::End => (free_space, Au(0)),
+            ContentDistribution::Center => (free_space / 2, Au(0)),
+            ContentDistribution::SpaceBetween if count == 1 => (Au(0), Au(0)),
+            ContentDistribution::SpaceBetween => (Au(0), free_space / (count - 1)),
+            ContentDistribution::SpaceAround => (free_space / (count * 2), free_space / count),
+        }
+    };
+    let mut first = true;
+    for track in tracks.iter_mut() {
+        if !track.collapsed {
+            if !first {
+                position += gap + interval;
+            }
+            first = false;
+        }
+        track.position = position;
+        if !track.collapsed {
+            position += track.base_size;
+        }
+    }
+}
+
+/// Lines are clamped to this many lines before and after the start of the explicit grid, which
+/// also limits the number of explicit tracks, so that huge line numbers, spans and repetitions do
+/// not make huge grids.
+///
+/// https://drafts.csswg.org/css-grid/#overlarge-grids
+const MAX_LINE: i32 = 10000;
+
+fn clamp_line(line: i32) -> i32 {
+    max(-MAX_LINE, min(line, MAX_LINE))
+}
+
+/// The explicit tracks of one axis of a grid, and the names of the lines between them.
+struct ExplicitGrid {
+    /// The sizes of the explicit tracks.
+    sizes: Vec<TrackSize>,
+    /// The names of the lines, with their index. Lines are numbered from 0.
+    line_names: Vec<(Atom, i32)>,
+    /// The tracks that were repeated by `repeat(auto-fit, ...)`.
+    auto_fit: Option<Range<usize>>,
+}
+
+impl ExplicitGrid {
+    /// Expands a `grid-template-rows` or `grid-template-columns` value into tracks, with
+    /// `auto-fill` and `auto-fit` repetitions repeated as many times as fit in `available`.
+    fn new(template: &GridTemplateComponent,
+           areas: Option<&TemplateAreas>,
+           direction: Direction,
+           auto_size: &TrackSize,
+           available: Option<Au>,
+           gap: Au)
+           -> ExplicitGrid {
+        let mut grid = ExplicitGrid {
+            sizes: vec![],
+            line_names: vec![],
+            auto_fit: None,
+        };
+
+        if let GenericGridTemplateComponent::TrackList(ref list) = *template {
+            let repeat_index = match list.list_type {
+                TrackListType::Auto(index) => Some(index as usize),
+                TrackListType::Normal | TrackListType::Explicit => None,
+            };
+            for index in 0..(list.values.len() + 1) {
+                if let Some(names) = list.line_names.get(index) {
+                    grid.add_line_names(names);
+                }
+                if repeat_index == Some(index) {
+                    if let Some(ref repeat) = list.auto_repeat {
+                        let sizes = list.values.iter().filter_map(|value| match *value {
+                            TrackListValue::TrackSize(ref size) => Some(size),
+                            TrackListValue::TrackRepeat(_) => None,
+                        });
+                        let count = ExplicitGrid::repetitions(repeat, sizes, available, gap);
+                        let start = grid.sizes.len();
+                        for _ in 0..count {
+                            for (size, names) in repeat.track_sizes.iter().zip(repeat.line_names.iter()) {
+                                grid.add_line_names(names);
+                                grid.sizes.push(size.clone());
+                            }
+                        }
+                        if let Some(names) = repeat.line_names.get(repeat.track_sizes.len()) {
+                            grid.add_line_names(names);
+                        }
+                        if repeat.count == RepeatCount::AutoFit {
+                            grid.auto_fit = Some(start..grid.sizes.len());
+                        }
+                    }
+                }
+                if let Some(&TrackListValue::TrackSize(ref size)) = list.values.get(index) {
+                    grid.sizes.push(size.clone());
+                }
+            }
+        }
+
+        // Named areas imply lines named after them, and may make the explicit grid larger.
+        if let Some(areas) = areas {
+            let track_count = match direction {
+                Direction::Inline => areas.width as usize,
+                Direction::Block => areas.strings.len(),
+            };
+            for area in areas.areas.iter() {
+                let lines = match direction {
+                    Direction::Inline => &area.columns,
+                    Direction::Block => &area.rows,
+                };
+                grid.line_names.push((Atom::from(format!("{}-start", area.name)), lines.start as i32 - 1));
+                grid.line_names.push((Atom::from(format!("{}-end", area.name)), lines.end as i32 - 1));
+            }
+            while grid.sizes.len() < track_count {
+                grid.sizes.push(auto_size.clone());
+            }
+        }
+        grid.sizes.truncate(MAX_LINE as usize);
+        grid.line_names.sort_by_key(|&(_, line)| line);
+        grid
+    }
+
+    fn add_line_names(&mut self, names: &[CustomIdent]) {
+        let line = self.sizes.len() as i32;
+        self.line_names.extend(names.iter().map(|name| (name.0.clone(), line)));
+    }
+
+    /// The number of times an `auto-fill` or `auto-fit` repetition is repeated: as many times as
+    /// fit in the available space alongside the other tracks, and at least once.
+    ///
+    /// https://drafts.csswg.org/css-grid/#auto-repeat
+    fn repetitions<'a, I>(repeat: &TrackRepeat<LengthOrPercentage, i32>,
+                          other_sizes: I,
+                          available: Option<Au>,
+                          gap: Au)
+                          -> i32
+                          where I: Iterator<Item=&'a TrackSize> {
+        if let RepeatCount::Number(count) = repeat.count {
+            return max(min(count, MAX_LINE), 1)
+        }
+        let available = match available {
+            Some(available) => available,
+            None => return 1,
+        };
+        let mut other_count = 0;
+        let mut other_size = Au(0);
+        for size in other_sizes {
+            other_count += 1;
+            other_size += fixed_track_size(size, Some(available)).unwrap_or(Au(0));
+        }
+        let repeat_size = repeat.track_sizes.iter().fold(Au(0), |total, size| {
+            total + fixed_track_size(size, Some(available)).unwrap_or(Au(0))
+        }) + gap * repeat.track_sizes.len() as i32;
+        if repeat_size <= Au(0) {
+            return 1
+        }
+        let space = available - other_size - gap * (other_count - 1);
+        max(min(space.0 / repeat_size.0, MAX_LINE), 1)
+    }
+
+    fn track_count(&self) -> i32 {
+        self.sizes.len() as i32
+    }
+
+    /// The indices of the lines with the given name.
+    fn lines_named(&self, name: &Atom) -> Vec<i32> {
+        let mut lines: Vec<i32> =
+            self.line_names.iter().filter(|&&(ref line_name, _)| line_name == name).map(|&(_, line)| line).collect();
+        lines.dedup();
+        lines
+    }
+
+    /// The `nth` line with the given name, counting from the end if it is negative. If there are
+    /// not enough lines with that name, the implicit lines on that side count as having it.
+    fn nth_line_named(&self, name: &Atom, nth: i32) -> i32 {
+        let lines = self.lines_named(name);
+        let count = lines.len() as i32;
+        if nth > 0 {
+            if nth <= count { lines[nth as usize - 1] } else { self.track_count().saturating_add(nth - count) }
+        } else {
+            if nth >= -count { lines[(count + nth) as usize] } else { nth + count }
+        }
+    }
+
+    /// Resolves a `grid-*-start` or `grid-*-end` value that is a line, rather than a span or
+    /// `auto`.
+    fn resolve_line(&self, line: &GridLine, is_start: bool) -> i32 {
+        clamp_line(match (&line.ident, line.line_num) {
+            (&Some(ref name), Some(nth)) => self.nth_line_named(&name.0, nth),
+            (&Some(ref name), None) => {
+                // A name matches the edge of a named area first.
+                let suffix = if is_start { "-start" } else { "-end" };
+                let area_line = Atom::from(format!("{}{}", name.0, suffix));
+                match self.lines_named(&area_line).first() {
+                    Some(&line) => line,
+                    None => self.nth_line_named(&name.0, 1),
+                }
+            }
+            (&None, Some(nth)) if nth > 0 => nth - 1,
+            (&None, Some(nth)) => self.track_count().saturating_add(1).saturating_add(nth),
+            (&None, None) => 0,
+        })
+    }
+
+    /// Resolves a span from the given line, searching forwards or backwards.
+    fn resolve_span(&self, from: i32, span: &GridLine, forwards: bool) -> i32 {
+        // No span can cross more than all the lines there are room for.
+        let nth = min(max(span.line_num.unwrap_or(1), 1), 2 * MAX_LINE);
+        let name = match span.ident {
+            Some(ref name) => &name.0,
+            None => return clamp_line(if forwards { from.saturating_add(nth) } else { from.saturating_sub(nth) }),
+        };
+        let lines = self.lines_named(name);
+        if forwards {
+            let lines: Vec<i32> = lines.into_iter().filter(|&line| line > from).collect();
+            match lines.get(nth as usize - 1) {
+                Some(&line) => line,
+                None => clamp_line(max(from, self.track_count()).saturating_add(nth - lines.len() as i32)),
+            }
+        } else {
+            let lines: Vec<i32> = lines.into_iter().rev().filter(|&line| line < from).collect();
+            match lines.get(nth as usize - 1) {
+                Some(&line) => line,
+                None => clamp_line(min(from, 0).saturating_sub(nth - lines.len() as i32)),
+            }
+        }
+    }
+
+    /// Resolves the placement of an item in this axis from its `grid-*-start` and `grid-*-end`.
+    ///
+    /// https://drafts.csswg.org/css-grid/#line-placement
+    fn resolve_placement(&self, start: &GridLine, end: &GridLine) -> LinePlacement {
+        let is_line = |line: &GridLine| !line.is_auto() && !line.is_span;
+        match (is_line(start), is_line(end)) {
+            (true, true) => {
+                let (start, end) = (self.resolve_line(start, true), self.resolve_line(end, false));
+                if start == end {
+                    LinePlacement::definite(start, start + 1)
+                } else {
+                    LinePlacement::definite(min(start, end), max(start, end))
+                }
+            }
+            (true, false) => {
+                let start_line = self.resolve_line(start, true);
+                let end_line = if end.is_span { self.resolve_span(start_line, end, true) } else { start_line + 1 };
+                LinePlacement::definite(start_line, end_line)
+            }
+            (false, true) => {
+                let end_line = self.resolve_line(end, false);
+                let start_line = if start.is_span { self.resolve_span(end_line, start, false) } else { end_line - 1 };
+                LinePlacement::definite(start_line, end_line)
+            }
+            (false, false) => {
+                // Only the start span counts if both are spans, and a span of named lines is a
+                // span of one track for auto-placement.
+                let span = if start.is_span { start } else { end };
+                match (span.is_span, &span.ident) {
+                    (true, &None) => LinePlacement::Auto(min(max(span.line_num.unwrap_or(1), 1), MAX_LINE)),
+                    _ => LinePlacement::Auto(1),
+                }
+            }
+        }
+    }
+}
+
+/// The placement of an item in one axis, before auto-placement.
+#[derive(Clone, Copy, Debug)]
+enum LinePlacement {
+    /// The start and end lines of the item, which may be before or after the explicit grid.
+    Definite(i32, i32),
+    /// The span of an item that is auto-placed in this axis.
+    Auto(i32),
+}
+
+impl LinePlacement {
+    /// A placement between the given lines, moved or shrunk to keep at least one track between
+    /// them within the limits of the grid.
+    fn definite(start: i32, end: i32) -> LinePlacement {
+        let start = max(-MAX_LINE, min(start, MAX_LINE - 1));
+        LinePlacement::Definite(start, max(start + 1, min(end, MAX_LINE)))
+    }
+}
+
+/// The cells of the grid that are taken by the items placed so far, in the axis along which
+/// auto-placement fills the grid ("minor") and the one in which it adds tracks ("major").
+///
+/// Each major track keeps the ranges of minor tracks taken in it rather than one flag per cell,
+/// so that items spanning many tracks in both axes stay cheap.
+struct Occupancy {
+    minor_count: usize,
+    taken: Vec<Vec<Range<usize>>>,
+}
+
+impl Occupancy {
+    fn is_free(&self, major: &Range<usize>, minor: &Range<usize>) -> bool {
+        if minor.end > self.minor_count {
+            return false
+        }
+        let lines = min(major.start, self.taken.len())..min(major.end, self.taken.len());
+        self.taken[lines].iter().all(|line| {
+            line.iter().all(|taken| taken.end <= minor.start || minor.end <= taken.start)
+        })
+    }
+
+    fn occupy(&mut self, major: &Range<usize>, minor: &Range<usize>) {
+        self.minor_count = max(self.minor_count, minor.end);
+        while self.taken.len() < major.end {
+            self.taken.push(vec![]);
+        }
+        for line in &mut self.taken[major.clone()] {
+            line.push(minor.clone());
+        }
+    }
+}
+
+/// An item to be placed, in the axes of auto-placement.
+struct Placement {
+    major: LinePlacement,
+    minor: LinePlacement,
+}
+
+/// Places every item in the grid, following `grid-auto-flow` for the ones that are not fully
+/// placed by their lines. Returns the track ranges of the items in (major, minor) order, with
+/// track 0 being the first of the implicit grid, and the offset of the explicit grid in each axis.
+///
+/// https://drafts.csswg.org/css-grid/#auto-placement-algo
+fn auto_place(placements: &[Placement],
+              explicit_counts: (usize, usize),
+              dense: bool)
+              -> (Vec<(Range<usize>, Range<usize>)>, (usize, usize)) {
+    // Lines before the explicit grid add implicit tracks at its start.
+    let offset = |axis: fn(&Placement) -> LinePlacement| {
+        placements.iter().fold(0, |offset, placement| match axis(placement) {
+            LinePlacement::Definite(start, _) => max(offset, -start),
+            LinePlacement::Auto(_) => offset,
+        }) as usize
+    };
+    let major_offset = offset(|placement: &Placement| placement.major);
+    let minor_offset = offset(|placement: &Placement| placement.minor);
+    let shift = |start: i32, end: i32, offset: usize| {
+        ((start + offset as i32) as usize)..((end + offset as i32) as usize)
+    };
+    // Auto-placed items that would go past the last line the grid may have are kept within it.
+    let limits = (major_offset + MAX_LINE as usize, minor_offset + MAX_LINE as usize);
+    let clamp = |range: Range<usize>, limit: usize| {
+        let start = min(range.start, limit - 1);
+        start..max(start + 1, min(range.end, limit))
+    };
+
+    let mut minor_count = minor_offset + explicit_counts.1;
+    for placement in placements {
+        minor_count = max(minor_count, match placement.minor {
+            LinePlacement::Definite(start, end) => shift(start, end, minor_offset).end,
+            LinePlacement::Auto(span) => span as usize,
+        });
+    }
+    let mut occupancy = Occupancy {
+        minor_count: minor_count,
+        taken: vec![],
+    };
+    let mut areas: Vec<Option<(Range<usize>, Range<usize>)>> = vec![None; placements.len()];
+
+    // Place the items with a definite position in both axes.
+    for (index, placement) in placements.iter().enumerate() {
+        if let (LinePlacement::Definite(major_start, major_end), LinePlacement::Definite(minor_start, minor_end)) =
+                (placement.major, placement.minor) {
+            let area = (shift(major_start, major_end, major_offset), shift(minor_start, minor_end, minor_offset));
+            occupancy.occupy(&area.0, &area.1);
+            areas[index] = Some(area);
+        }
+    }
+
+    // Place the items locked to a major track.
+    let mut cursors: Vec<(usize, usize)> = vec![];
+    for (index, placement) in placements.iter().enumerate() {
+        if let (LinePlacement::Definite(major_start, major_end), LinePlacement::Auto(span)) =
+                (placement.major, placement.minor) {
+            let major = shift(major_start, major_end, major_offset);
+            let mut minor_start = if dense {
+                0
+            } else {
+                cursors.iter().find(|&&(line, _)| line == major.start).map_or(0, |&(_, cursor)| cursor)
+            };
+            while !occupancy.is_free(&major, &(minor_start..(minor_start + span as usize))) &&
+                  minor_start + span as usize <= occupancy.minor_count {
+                minor_start += 1;
+            }
+            let minor = clamp(minor_start..(minor_start + span as usize), limits.1);
+            occupancy.occupy(&major, &minor);
+            cursors.retain(|&(line, _)| line != major.start);
+            cursors.push((major.start, minor.end));
+            areas[index] = Some((major, minor));
+        }
+    }
+
+    // Place the rest of the items.
+    let mut cursor = (0, 0);
+    for (index, placement) in placements.iter().enumerate() {
+        if areas[index].is_some() {
+            continue
+        }
+        let major_span = match placement.major {
+            LinePlacement::Auto(span) => span as usize,
+            LinePlacement::Definite(..) => unreachable!(),
+        };
+        if dense {
+            cursor = (0, 0);
+        }
+        let area = match placement.minor {
+            LinePlacement::Definite(start, end) => {
+                let minor = shift(start, end, minor_offset);
+                if minor.start < cursor.1 {
+                    cursor.0 += 1;
+                }
+                cursor.1 = minor.start;
+                while !occupancy.is_free(&(cursor.0..(cursor.0 + major_span)), &minor) {
+                    cursor.0 += 1;
+                }
+                (cursor.0..(cursor.0 + major_span), minor)
+            }
+            LinePlacement::Auto(minor_span) => {
+                let minor_span = minor_span as usize;
+                loop {
+                    let major = cursor.0..(cursor.0 + major_span);
+                    while cursor.1 + minor_span <= occupancy.minor_count &&
+                          !occupancy.is_free(&major, &(cursor.1..(cursor.1 + minor_span))) {
+                        cursor.1 += 1;
+                    }
+                    if cursor.1 + minor_span <= occupancy.minor_count {
+                        break
+                    }
+                    cursor = (cursor.0 + 1, 0);
+                }
+                (cursor.0..(cursor.0 + major_span), cursor.1..(cursor.1 + minor_span))
+            }
+        };
+        let area = (clamp(area.0, limits.0), clamp(area.1, limits.1));
+        occupancy.occupy(&area.0, &area.1);
+        areas[index] = Some(area);
+    }
+
+    (areas.into_iter().map(|area| area.unwrap()).collect(), (major_offset, minor_offset))
+}
+
+/// A child of a grid container, placed in its grid.
+#[derive(Debug, Serialize)]
+struct GridItem {
+    /// The index of the actual flow in our child list.
+    index: usize,
+    /// The 'order' property of this item.
+    order: i32,
+    /// The columns the item spans.
+    columns: Range<usize>,
+    /// The rows the item spans.
+    rows: Range<usize>,
+}
+
+/// How a grid item is aligned in the block axis of its grid area.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SelfAlignment {
+    Stretch,
+    Start,
+    End,
+    Center,
+}
+
+impl SelfAlignment {
+    fn new(container_style: &ComputedValues, item_style: &ComputedValues) -> SelfAlignment {
+        match item_style.get_position().align_self {
+            align_self::T::stretch => SelfAlignment::Stretch,
+            align_self::T::flex_end => SelfAlignment::End,
+            align_self::T::center => SelfAlignment::Center,
+            // TODO: support baseline alignment.
+            align_self::T::flex_start | align_self::T::baseline => SelfAlignment::Start,
+            align_self::T::auto => match container_style.get_position().align_items {
+                align_items::T::stretch => SelfAlignment::Stretch,
+                align_items::T::flex_end => SelfAlignment::End,
+                align_items::T::center => SelfAlignment::Center,
+                align_items::T::flex_start | align_items::T::baseline => SelfAlignment::Start,
+            },
+        }
+    }
+}
+
+/// Stretches an item that has been laid out to `size` in the block axis of its grid area, which
+/// is `area_size` tall, and lays its contents out again as if that size had been specified, so
+/// that they resolve percentages against it.
+///
+/// https://drafts.csswg.org/css-align/#valdef-align-self-stretch
+fn stretch_item(flow: &mut Flow, size: Au, area_size: Au, layout_context: &LayoutContext) {
+    let content_size = {
+        let block = flow.as_mut_block();
+        block.base.block_container_explicit_block_size = Some(area_size);
+        let box_border = block.fragment.border_padding.block_start_end();
+        max(size - box_border, Au(0))
+    };
+    for child in flow::mut_base(flow).children.iter_mut() {
+        if flow::base(child).flags.contains(FlowFlags::IS_ABSOLUTELY_POSITIONED) {
+            continue
+        }
+        flow::mut_base(child).block_container_explicit_block_size = Some(content_size);
+        sequential::reflow(child, layout_context, RelayoutMode::Force);
+    }
+    flow.assign_block_size(layout_context);
+
+    let block = flow.as_mut_block();
+    block.base.position.size.block = size;
+    block.fragment.border_box.size.block = size;
+}
+
+#[allow(unsafe_code)]
+unsafe impl ::flow::HasBaseFlow for GridFlow {}
+
+/// A block with the CSS `display` property equal to `grid` or `inline-grid`.
+#[derive(Debug, Serialize)]
+#[repr(C)]
+pub struct GridFlow {
+    /// Data common to all block flows.
+    block_flow: BlockFlow,
+    /// The items of the grid, sorted by the 'order' property.
+    items: Vec<GridItem>,
+    /// The tracks in the inline axis.
+    columns: Vec<Track>,
+    /// The tracks in the block axis.
+    rows: Vec<Track>,
+    /// The used 'grid-column-gap'.
+    column_gap: Au,
+    /// The used 'grid-row-gap'.
+    row_gap: Au,
+}
+
+impl GridFlow {
+    pub fn from_fragment(fragment: Fragment, flotation: Option<FloatKind>) -> GridFlow {
+        GridFlow {
+            block_flow: BlockFlow::from_fragment_and_float_kind(fragment, flotation),
+            items: Vec::new(),
+            columns: Vec::new(),
+            rows: Vec::new(),
+            column_gap: Au(0),
+            row_gap: Au(0),
+        }
+    }
+
+    /// Places the items in the grid and creates its tracks, given the content size of the
+    /// container in each axis, if it is definite.
+    fn place_items(&mut self, inline_size: Option<Au>, block_size: Option<Au>) {
+        let style = self.block_flow.fragment.style.clone();
+        let position = style.get_position();
+        self.column_gap = position.grid_column_gap.to_used_value(inline_size.unwrap_or(Au(0)));
+        self.row_gap = position.grid_row_gap.to_used_value(block_size.unwrap_or(Au(0)));
+
+        let areas = match position.grid_template_areas {
+            Either::First(ref areas) => Some(areas),
+            Either::Second(_) => None,
+        };
+        let columns = ExplicitGrid::new(&position.grid_template_columns, areas, Direction::Inline,
+                                        &position.grid_auto_columns, inline_size, self.column_gap);
+        let rows = ExplicitGrid::new(&position.grid_template_rows, areas, Direction::Block,
+                                     &position.grid_auto_rows, block_size, self.row_gap);
+
+        // Grid items are placed in order-modified document order.
+        let mut items: Vec<(GridItem, LinePlacement, LinePlacement)> =
+            self.block_flow
+                .base
+                .children
+                .iter()
+                .enumerate()
+                .filter(|&(_, flow)| {
+                    !flow.as_block().base.flags.contains(FlowFlags::IS_ABSOLUTELY_POSITIONED)
+                })
+                .map(|(index, flow)| {
+                    let item_position = flow.as_block().fragment.style.get_position();
+                    let item = GridItem {
+                        index: index,
+                        order: item_position.order,
+                        columns: 0..0,
+                        rows: 0..0,
+                    };
+                    (item,
+                     columns.resolve_placement(&item_position.grid_column_start, &item_position.grid_column_end),
+                     rows.resolve_placement(&item_position.grid_row_start, &item_position.grid_row_end))
+                })
+                .collect();
+        items.sort_by_key(|&(ref item, _, _)| item.order);
+
+        let row_flow = position.grid_auto_flow.autoflow == AutoFlow::Row;
+        let placements: Vec<Placement> = items.iter().map(|&(_, column, row)| {
+            if row_flow {
+                Placement { major: row, minor: column }
+            } else {
+                Placement { major: column, minor: row }
+            }
+        }).collect();
+        let explicit_counts = if row_flow {
+            (rows.sizes.len(), columns.sizes.len())
+        } else {
+            (columns.sizes.len(), rows.sizes.len())
+        };
+        let (item_areas, offsets) = auto_place(&placements, explicit_counts, position.grid_auto_flow.dense);
+        let (column_offset, row_offset) = if row_flow { (offsets.1, offsets.0) } else { offsets };
+
+        self.items = items.into_iter().zip(item_areas.into_iter()).map(|((mut item, _, _), (major, minor))| {
+            if row_flow {
+                item.rows = major;
+                item.columns = minor;
+            } else {
+                item.columns = major;
+                item.rows = minor;
+            }
+            item
+        }).collect();
+
+        let column_count = self.items.iter().fold(column_offset + columns.sizes.len(), |count, item| {
+            max(count, item.columns.end)
+        });
+        let row_count = self.items.iter().fold(row_offset + rows.sizes.len(), |count, item| {
+            max(count, item.rows.end)
+        });
+        self.columns = GridFlow::create_tracks(&columns, column_offset, column_count,
+                                               &position.grid_auto_columns, inline_size);
+        self.rows = GridFlow::create_tracks(&rows, row_offset, row_count, &position.grid_auto_rows, block_size);
+
+        // Empty tracks from an `auto-fit` repetition collapse.
+        if let Some(ref auto_fit) = columns.auto_fit {
+            for index in auto_fit.clone() {
+                let index = index + column_offset;
+                self.columns[index].collapsed = !self.items.iter().any(|item| item.columns.start <= index &&
+                                                                               index < item.columns.end);
+            }
+        }
+        if let Some(ref auto_fit) = rows.auto_fit {
+            for index in auto_fit.clone() {
+                let index = index + row_offset;
+                self.rows[index].collapsed = !self.items.iter().any(|item| item.rows.start <= index &&
+                                                                            index < item.rows.end);
+            }
+        }
+    }
+
+    /// Creates the tracks of one axis, which are the explicit ones starting at `offset` and
+    /// implicit ones sized by `grid-auto-rows` or `grid-auto-columns` around them.
+    fn create_tracks(explicit: &ExplicitGrid,
+                     offset: usize,
+                     count: usize,
+                     auto_size: &TrackSize,
+                     basis: Option<Au>)
+                     -> Vec<Track> {
+        // Items are kept within the lines `auto_place` allows, so this is all the tracks they need.
+        let count = min(count, offset + MAX_LINE as usize);
+        (0..count).map(|index| {
+            let size = if index >= offset && index - offset < explicit.sizes.len() {
+                &explicit.sizes[index - offset]
+            } else {
+                auto_size
+            };
+            Track::new(size, basis)
+        }).collect()
+    }
+
+    /// The sizes the items contribute to the columns they span.
+    fn inline_contributions(&self) -> Vec<Contribution> {
+        let children: Vec<&Flow> = self.block_flow.base.children.iter().collect();
+        self.items.iter().map(|item| {
+            let sizes = &flow::base(children[item.index]).intrinsic_inline_sizes;
+            Contribution {
+                span: item.columns.clone(),
+                min_content: sizes.minimum_inline_size,
+                max_content: sizes.preferred_inline_size,
+            }
+        }).collect()
+    }
+
+    /// The sizes the items contribute to the rows they span, once they have been laid out.
+    fn block_contributions(&self) -> Vec<Contribution> {
+        let children: Vec<&Flow> = self.block_flow.base.children.iter().collect();
+        self.items.iter().map(|item| {
+            let fragment = &children[item.index].as_block().fragment;
+            let size = fragment.border_box.size.block + fragment.margin.block_start_end();
+            Contribution {
+                span: item.rows.clone(),
+                min_content: size,
+                max_content: size,
+            }
+        }).collect()
+    }
+
+    /// The size of the given rows, if it is known before the items are laid out.
+    fn fixed_rows_size(&self, rows: Range<usize>) -> Option<Au> {
+        let mut size = self.row_gap * (rows.len() as i32 - 1);
+        for track in &self.rows[rows] {
+            match (track.min_sizing, track.max_sizing) {
+                (TrackBreadth::Fixed(min_size), TrackBreadth::Fixed(max_size)) if min_size >= max_size => {
+                    size += min_size;
+                }
+                _ => return None,
+            }
+        }
+        Some(size)
+    }
+
+    /// The content block size of the grid container, if it is definite.
+    fn explicit_content_block_size(&self, layout_context: &LayoutContext) -> Option<Au> {
+        let box_border = self.block_flow.fragment.box_sizing_boundary(Direction::Block);
+        let parent_container_size =
+            self.block_flow.explicit_block_containing_size(layout_context.shared_context());
+        // https://drafts.csswg.org/css-ui-3/#box-sizing
+        self.block_flow
+            .explicit_block_size(parent_container_size)
+            .map(|x| max(x - box_border, Au(0)))
+    }
+
+    /// Lays out the items in the inline axis, in the columns of their grid areas.
+    fn assign_item_inline_sizes(&mut self, inline_start_content_edge: Au) {
+        let container_mode = self.block_flow.base.block_container_writing_mode;
+        let containing_block_text_align =
+            self.block_flow.fragment.style().get_inheritedtext().text_align;
+        let rows_sizes: Vec<Option<Au>> =
+            self.items.iter().map(|item| self.fixed_rows_size(item.rows.clone())).collect();
+
+        let mut children = self.block_flow.base.children.random_access_mut();
+        for (item, rows_size) in self.items.iter().zip(rows_sizes.into_iter()) {
+            let area_start = self.columns[item.columns.start].position;
+            let area_size = self.columns[item.columns.end - 1].end() - area_start;
+
+            let block = children.get(item.index).as_mut_block();
+            block.base.block_container_writing_mode = container_mode;
+            block.base.block_container_inline_size = area_size;
+            block.base.block_container_explicit_block_size = rows_size;
+            // Per CSS 2.1 § 16.3.1, text alignment propagates to all children in flow.
+            //
+            // TODO(#2265, pcwalton): Do this in the cascade instead.
+            block.base.flags.set_text_align(containing_block_text_align);
+
+            // These methods compute auto margins to zero length, which is corrected below.
+            block.fragment.compute_border_and_padding(area_size);
+            block.fragment.compute_inline_direction_margins(area_size);
+            block.fragment.compute_block_direction_margins(area_size);
+
+            // There is no 'justify-self' in Servo, so items with an auto inline size stretch to
+            // fill their area, and the others are aligned by their auto margins.
+            let style = block.fragment.style.clone();
+            let border_padding = block.fragment.border_padding.inline_start_end();
+            let inline_size = match MaybeAuto::from_style(style.content_inline_size(), area_size) {
+                MaybeAuto::Specified(size) => {
+                    let constraint = SizeConstraint::new(Some(area_size),
+                                                         style.min_inline_size(),
+                                                         style.max_inline_size(),
+                                                         None);
+                    let content_size = constraint.clamp(size) - block.fragment.box_sizing_boundary(Direction::Inline);
+                    max(content_size, Au(0)) + border_padding
+                }
+                MaybeAuto::Auto => {
+                    max(area_size - block.fragment.margin.inline_start_end(), border_padding)
+                }
+            };
+            let margin = style.logical_margin();
+            let free_space = max(area_size - inline_size - block.fragment.margin.inline_start_end(), Au(0));
+            match (margin.inline_start, margin.inline_end) {
+                (LengthOrPercentageOrAuto::Auto, LengthOrPercentageOrAuto::Auto) => {
+                    block.fragment.margin.inline_start = free_space / 2;
+                    block.fragment.margin.inline_end = free_space - free_space / 2;
+                }
+                (LengthOrPercentageOrAuto::Auto, _) => block.fragment.margin.inline_start = free_space,
+                (_, LengthOrPercentageOrAuto::Auto) => block.fragment.margin.inline_end = free_space,
+                _ => {}
+            }
+
+            block.fragment.border_box.start.i = block.fragment.margin.inline_start;
+            block.fragment.border_box.size.inline = inline_size;
+            block.base.position.start.i = inline_start_content_edge + area_start;
+            block.base.position.size.inline = inline_size + block.fragment.margin.inline_start_end();
+        }
+    }
+
+    /// Sizes the rows and aligns the items, which have been laid out, in them.
+    fn assign_item_block_positions(&mut self, layout_context: &LayoutContext) {
+        let explicit_content_size = self.explicit_content_block_size(layout_context);
+        let contributions = self.block_contributions();
+        let row_gap = self.row_gap;
+        let content_size = match explicit_content_size {
+            Some(size) => {
+                size_tracks(&mut self.rows, &contributions, row_gap, AvailableSpace::Definite(size), true);
+                size
+            }
+            None => {
+                size_tracks(&mut self.rows, &contributions, row_gap, AvailableSpace::MaxContent, false);
+                let size = total_size(&self.rows, row_gap);
+                let style = self.block_flow.fragment.style();
+                let box_border = self.block_flow.fragment.box_sizing_boundary(Direction::Block);
+                let constraint = SizeConstraint::new(self.block_flow.base.block_container_explicit_block_size,
+                                                     style.min_block_size(),
+                                                     style.max_block_size(),
+                                                     Some(box_border));
+                let clamped_size = constraint.clamp(size);
+                if clamped_size != size {
+                    size_tracks(&mut self.rows, &contributions, row_gap,
+                                AvailableSpace::Definite(clamped_size), true);
+                }
+                clamped_size
+            }
+        };
+        let align_content = self.block_flow.fragment.style().get_position().align_content;
+        let free_space = content_size - total_size(&self.rows, row_gap);
+        position_tracks(&mut self.rows, row_gap, free_space, ContentDistribution::from_align_content(align_content));
+
+        let block_start_content_edge = self.block_flow.fragment.border_padding.block_start;
+        let container_style = self.block_flow.fragment.style.clone();
+        let mut children = self.block_flow.base.children.random_access_mut();
+        for item in &self.items {
+            let area_start = self.rows[item.rows.start].position;
+            let area_size = self.rows[item.rows.end - 1].end() - area_start;
+
+            let flow = children.get(item.index);
+            let stretched_size = {
+                let block = flow.as_block();
+                let alignment = SelfAlignment::new(&container_style, block.fragment.style());
+                let margin = block.fragment.style().logical_margin();
+                let is_stretched = alignment == SelfAlignment::Stretch &&
+                    block.fragment.style().content_block_size() == LengthOrPercentageOrAuto::Auto &&
+                    margin.block_start != LengthOrPercentageOrAuto::Auto &&
+                    margin.block_end != LengthOrPercentageOrAuto::Auto;
+                if is_stretched {
+                    Some(max(area_size - block.fragment.margin.block_start_end(), Au(0)))
+                } else {
+                    None
+                }
+            };
+            if let Some(size) = stretched_size {
+                stretch_item(flow, size, area_size, layout_context);
+            }
+
+            let block = flow.as_mut_block();
+            let margin = block.fragment.style().logical_margin();
+            let free_space = area_size - block.base.position.size.block - block.fragment.margin.block_start_end();
+            let alignment = SelfAlignment::new(&container_style, block.fragment.style());
+            let auto_margins = (margin.block_start == LengthOrPercentageOrAuto::Auto,
+                                margin.block_end == LengthOrPercentageOrAuto::Auto);
+
+            let offset = if auto_margins.0 || auto_margins.1 {
+                let free_space = max(free_space, Au(0));
+                match auto_margins {
+                    (true, true) => {
+                        block.fragment.margin.block_start = free_space / 2;
+                        block.fragment.margin.block_end = free_space - free_space / 2;
+                    }
+                    (true, false) => block.fragment.margin.block_start = free_space,
+                    _ => block.fragment.margin.block_end = free_space,
+                }
+                Au(0)
+            } else if stretched_size.is_some() {
+                Au(0)
+            } else {
+                match alignment {
+                    SelfAlignment::End => free_space,
+                    SelfAlignment::Center => free_space / 2,
+                    SelfAlignment::Start | SelfAlignment::Stretch => Au(0),
+                }
+            };
+            block.base.position.start.b =
+                block_start_content_edge + area_start + block.fragment.margin.block_start + offset;
+        }
+
+        let total_block_size = content_size + self.block_flow.fragment.border_padding.block_start_end();
+        self.block_flow.fragment.border_box.size.block = total_block_size;
+        self.block_flow.base.position.size.block = total_block_size;
+    }
+}
+
+impl Flow for GridFlow {
+    fn class(&self) -> FlowClass {
+        FlowClass::Grid
+    }
+
+    fn as_mut_grid(&mut self) -> &mut GridFlow {
+        self
+    }
+
+    fn as_grid(&self) -> &GridFlow {
+        self
+    }
+
+    fn as_block(&self) -> &BlockFlow {
+        &self.block_flow
+    }
+
+    fn as_mut_block(&mut self) -> &mut BlockFlow {
+        &mut self.block_flow
+    }
+
+    fn mark_as_root(&mut self) {
+        self.block_flow.mark_as_root();
+    }
+
+    fn bubble_inline_sizes(&mut self) {
+        let _scope = layout_debug_scope!("grid::bubble_inline_sizes {:x}",
+                                         self.block_flow.base.debug_id());
+
+        // The intrinsic sizes of the container come from placing its items with only the
+        // container's fixed sizes known, and sizing the columns under min-content and
+        // max-content constraints.
+        let (inline_size, block_size) = {
+            let style = self.block_flow.fragment.style();
+            let fixed = |size: LengthOrPercentageOrAuto| match size {
+                LengthOrPercentageOrAuto::Length(length) => Some(Au::from(length)),
+                _ => None,
+            };
+            (fixed(style.content_inline_size()), fixed(style.content_block_size()))
+        };
+        self.place_items(inline_size, block_size);
+
+        let mut computation = self.block_flow.fragment.compute_intrinsic_inline_sizes();
+        if inline_size.is_none() {
+            let contributions = self.inline_contributions();
+            let column_gap = self.column_gap;
+            size_tracks(&mut self.columns, &contributions, column_gap, AvailableSpace::MinContent, false);
+            computation.content_intrinsic_sizes.minimum_inline_size =
+                max(computation.content_intrinsic_sizes.minimum_inline_size,
+                    total_size(&self.columns, column_gap));
+            size_tracks(&mut self.columns, &contributions, column_gap, AvailableSpace::MaxContent, false);
+            computation.content_intrinsic_sizes.preferred_inline_size =
+                max(computation.content_intrinsic_sizes.preferred_inline_size,
+                    total_size(&self.columns, column_gap));
+        }
+        self.block_flow.base.intrinsic_inline_sizes = computation.finish();
+    }
+
+    fn assign_inline_sizes(&mut self, layout_context: &LayoutContext) {
+        let _scope = layout_debug_scope!("grid::assign_inline_sizes {:x}", self.block_flow.base.debug_id());
+        debug!("assign_inline_sizes");
+
+        if !self.block_flow.base.restyle_damage.intersects(ServoRestyleDamage::REFLOW_OUT_OF_FLOW |
+                                                           ServoRestyleDamage::REFLOW) {
+            return
+        }
+
+        self.block_flow.initialize_container_size_for_root(layout_context.shared_context());
+
+        // Our inline-size was set to the inline-size of the containing block by the flow's parent.
+        // Now compute the real value.
+        let containing_block_inline_size = self.block_flow.base.block_container_inline_size;
+        self.block_flow.compute_used_inline_size(layout_context.shared_context(),
+                                                 containing_block_inline_size);
+        if self.block_flow.base.flags.is_float() {
+            self.block_flow.float.as_mut().unwrap().containing_inline_size = containing_block_inline_size
+        }
+
+        // Move in from the inline-start border edge.
+        let inline_start_content_edge = self.block_flow.fragment.border_box.start.i +
+            self.block_flow.fragment.border_padding.inline_start;
+        let padding_and_borders = self.block_flow.fragment.border_padding.inline_start_end();
+        let content_inline_size = self.block_flow.fragment.border_box.size.inline - padding_and_borders;
+        let explicit_content_size = self.explicit_content_block_size(layout_context);
+
+        // Place the items again now that the size of the container is known, since it decides
+        // how many tracks `auto-fill` and `auto-fit` repetitions make.
+        self.place_items(Some(content_inline_size), explicit_content_size);
+
+        let contributions = self.inline_contributions();
+        let column_gap = self.column_gap;
+        let justify_content = self.block_flow.fragment.style().get_position().justify_content;
+        let distribution = ContentDistribution::from_justify_content(justify_content);
+        size_tracks(&mut self.columns,
+                    &contributions,
+                    column_gap,
+                    AvailableSpace::Definite(content_inline_size),
+                    distribution == ContentDistribution::Stretch);
+        let free_space = content_inline_size - total_size(&self.columns, column_gap);
+        position_tracks(&mut self.columns, column_gap, free_space, distribution);
+
+        self.assign_item_inline_sizes(inline_start_content_edge);
+    }
+
+    fn assign_block_size(&mut self, layout_context: &LayoutContext) {
+        let _scope = layout_debug_scope!("grid::assign_block_size {:x}", self.block_flow.base.debug_id());
+
+        self.assign_item_block_positions(layout_context);
+
+        // The margins of a grid container do not collapse with the margins of its contents.
+        let block_start = AdjoiningMargins::from_margin(self.block_flow.fragment.margin.block_start);
+        let block_end = AdjoiningMargins::from_margin(self.block_flow.fragment.margin.block_end);
+        self.block_flow.base.collapsible_margins = CollapsibleMargins::Collapse(block_start, block_end);
+
+        // TODO: assign proper static position for absolute descendants.
+        if (&*self as &Flow).contains_roots_of_absolute_flow_tree() {
+            // Assign block-sizes for all flows in this absolute flow tree.
+            // This is preorder because the block-size of an absolute flow may depend on
+            // the block-size of its containing block, which may also be an absolute flow.
+            let assign_abs_b_sizes = AbsoluteAssignBSizesTraversal(layout_context.shared_context());
+            assign_abs_b_sizes.traverse_absolute_flows(&mut *self);
+        }
+    }
+
+    fn compute_stacking_relative_position(&mut self, layout_context: &LayoutContext) {
+        self.block_flow.compute_stacking_relative_position(layout_context)
+    }
+
+    fn place_float_if_applicable<'a>(&mut self) {
+        self.block_flow.place_float_if_applicable()
+    }
+
+    fn update_late_computed_inline_position_if_necessary(&mut self, inline_position: Au) {
+        self.block_flow.update_late_computed_inline_position_if_necessary(inline_position)
+    }
+
+    fn update_late_computed_block_position_if_necessary(&mut self, block_position: Au) {
+        self.block_flow.update_late_computed_block_position_if_necessary(block_position)
+    }
+
+    fn build_display_list(&mut self, state: &mut DisplayListBuildState) {
+        self.build_display_list_for_grid(state);
+    }
+
+    fn collect_stacking_contexts(&mut self, state: &mut StackingContextCollectionState) {
+        self.block_flow.collect_stacking_contexts(state);
+    }
+
+    fn repair_style(&mut self, new_style: &::ServoArc<ComputedValues>) {
+        self.block_flow.repair_style(new_style)
+    }
+
+    fn compute_overflow(&self) -> Overflow {
+        self.block_flow.compute_overflow()
+    }
+
+    fn contains_roots_of_absolute_flow_tree(&self) -> bool {
+        self.block_flow.contains_roots_of_absolute_flow_tree()
+    }
+
+    fn is_absolute_containing_block(&self) -> bool {
+        self.block_flow.is_absolute_containing_block()
+    }
+
+    fn generated_containing_block_size(&self, flow: OpaqueFlow) -> LogicalSize<Au> {
+        self.block_flow.generated_containing_block_size(flow)
+    }
+
+    fn iterate_through_fragment_border_boxes(&self,
+                                             iterator: &mut FragmentBorderBoxIterator,
+                                             level: i32,
+                                             stacking_context_position: &Point2D<Au>) {
+        self.block_flow.iterate_through_fragment_border_boxes(iterator, level, stacking_context_position);
+    }
+
+    fn mutate_fragments(&mut self, mutator: &mut FnMut(&mut Fragment)) {
+        self.block_flow.mutate_fragments(mutator);
+    }
+}
@@ -1193,6 +1193,7 @@ impl InlineFlow {
                 (display::T::inline, VerticalAlign::Top) |
                 (display::T::block, VerticalAlign::Top) |
                 (display::T::inline_flex, VerticalAlign::Top) |
+                (display::T::inline_grid, VerticalAlign::Top) |
                 (display::T::inline_block, VerticalAlign::Top) if
                         inline_metrics.space_above_baseline >= Au(0) => {
                     *largest_block_size_for_top_fragments = max(
@@ -1202,6 +1203,7 @@ impl InlineFlow {
                 (display::T::inline, VerticalAlign::Bottom) |
                 (display::T::block, VerticalAlign::Bottom) |
                 (display::T::inline_flex, VerticalAlign::Bottom) |
+                (display::T::inline_grid, VerticalAlign::Bottom) |
                 (display::T::inline_block, VerticalAlign::Bottom) if
                         inline_metrics.space_below_baseline >= Au(0) => {
                     *largest_block_size_for_bottom_fragments = max(
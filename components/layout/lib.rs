@@ -59,6 +59,7 @@ mod flow_list;
 pub mod flow_ref;
 mod fragment;
 mod generated_content;
+mod grid;
 pub mod incremental;
 mod inline;
 mod linked_list;
@@ -182,10 +182,12 @@ impl TextRunScanner {
                 let mut mapping = RunMapping::new(&run_info_list[..], fragment_index);
                 let text;
                 let selection;
+                let node_offset;
                 match in_fragment.specific {
                     SpecificFragmentInfo::UnscannedText(ref text_fragment_info) => {
                         text = &text_fragment_info.text;
                         selection = text_fragment_info.selection;
+                        node_offset = text_fragment_info.node_offset;
                     }
                     _ => panic!("Expected an unscanned text fragment!"),
                 };
@@ -244,6 +246,7 @@ impl TextRunScanner {
                             mapping.flush(&mut mappings,
                                           &mut run_info,
                                           &**text,
+                                          node_offset,
                                           compression,
                                           text_transform,
                                           &mut last_whitespace,
@@ -273,6 +276,7 @@ impl TextRunScanner {
                 mapping.flush(&mut mappings,
                               &mut run_info,
                               &**text,
+                              node_offset,
                               compression,
                               text_transform,
                               &mut last_whitespace,
@@ -315,11 +319,10 @@ impl TextRunScanner {
                     options.flags.insert(ShapingFlags::RTL_FLAG);
                 }
                 let mut font = fontgroup.fonts.get(run_info.font_index).unwrap().borrow_mut();
+                let mut run = TextRun::new(&mut *font, run_info.text, &options, run_info.bidi_level);
+                run.original_offsets = Arc::new(run_info.original_offsets);
                 ScannedTextRun {
-                    run: Arc::new(TextRun::new(&mut *font,
-                                               run_info.text,
-                                               &options,
-                                               run_info.bidi_level)),
+                    run: Arc::new(run),
                     insertion_point: run_info.insertion_point,
                 }
             }).collect::<Vec<_>>()
@@ -464,6 +467,7 @@ fn split_first_fragment_at_newline_if_necessary(fragments: &mut LinkedList<Fragm
         let first_fragment = fragments.front_mut().unwrap();
         let string_before;
         let selection_before;
+        let node_offset_before;
         {
             if !first_fragment.white_space().preserve_newlines() {
                 return;
@@ -487,6 +491,8 @@ fn split_first_fragment_at_newline_if_necessary(fragments: &mut LinkedList<Fragm
                 unscanned_text_fragment_info.text[..(position + 1)].to_owned();
             unscanned_text_fragment_info.text =
                 unscanned_text_fragment_info.text[(position + 1)..].to_owned().into_boxed_str();
+            node_offset_before = unscanned_text_fragment_info.node_offset;
+            unscanned_text_fragment_info.node_offset += string_before.len();
             let offset = ByteIndex(string_before.len() as isize);
             match unscanned_text_fragment_info.selection {
                 Some(ref mut selection) if selection.begin() >= offset => {
@@ -506,11 +512,11 @@ fn split_first_fragment_at_newline_if_necessary(fragments: &mut LinkedList<Fragm
                 }
             };
         }
+        let mut info_before = UnscannedTextFragmentInfo::new(string_before, selection_before);
+        info_before.node_offset = node_offset_before;
         first_fragment.transform(
             first_fragment.border_box.size,
-            SpecificFragmentInfo::UnscannedText(Box::new(
-                UnscannedTextFragmentInfo::new(string_before, selection_before)
-            ))
+            SpecificFragmentInfo::UnscannedText(Box::new(info_before))
         )
     };
 
@@ -529,6 +535,9 @@ struct RunInfo {
     bidi_level: bidi::Level,
     /// The Unicode script property of this text run.
     script: Script,
+    /// The byte index in `text` of each character, paired with the byte index of the
+    /// character it was made from in the text of its node.
+    original_offsets: Vec<(usize, usize)>,
 }
 
 impl RunInfo {
@@ -539,6 +548,7 @@ impl RunInfo {
             font_index: 0,
             bidi_level: bidi::Level::ltr(),
             script: Script::Common,
+            original_offsets: Vec::new(),
         }
     }
 
@@ -596,6 +606,7 @@ impl RunMapping {
              mappings: &mut Vec<RunMapping>,
              run_info: &mut RunInfo,
              text: &str,
+             node_offset: usize,
              compression: CompressionMode,
              text_transform: text_transform::T,
              last_whitespace: &mut bool,
@@ -603,13 +614,19 @@ impl RunMapping {
              end_position: usize) {
         let was_empty = *start_position == end_position;
         let old_byte_length = run_info.text.len();
+        let old_offset_count = run_info.original_offsets.len();
         *last_whitespace = util::transform_text(&text[(*start_position)..end_position],
                                                 compression,
                                                 *last_whitespace,
-                                                &mut run_info.text);
+                                                &mut run_info.text,
+                                                &mut run_info.original_offsets);
+        for offset in &mut run_info.original_offsets[old_offset_count..] {
+            offset.1 += node_offset + *start_position;
+        }
 
         // Account for `text-transform`. (Confusingly, this is not handled in "text
         // transformation" above, but we follow Gecko in the naming.)
+        // FIXME: The original offsets are off when this changes the length of the text.
         let is_first_run = *start_position == 0;
         apply_style_transform_if_necessary(&mut run_info.text, old_byte_length, text_transform,
                                            *last_whitespace, is_first_run);
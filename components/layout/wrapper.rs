@@ -35,7 +35,7 @@ use data::{LayoutData, LayoutDataFlags, StyleAndLayoutData};
 use script_layout_interface::wrapper_traits::{ThreadSafeLayoutElement, ThreadSafeLayoutNode};
 use script_layout_interface::wrapper_traits::GetLayoutData;
 use style::computed_values::content::{self, ContentItem};
-use style::dom::{NodeInfo, TNode};
+use style::dom::{NodeInfo, TElement, TNode};
 use style::selector_parser::RestyleDamage;
 
 pub trait LayoutNodeLayoutData {
@@ -130,11 +130,10 @@ impl<T: ThreadSafeLayoutNode> ThreadSafeLayoutNodeHelpers for T {
         // escape and never access its descendants.
         let mut node = unsafe { self.unsafe_get() };
 
-        // If this is a text node, use the parent element, since that's what
-        // controls our style.
+        // If this is a text node, use the parent element in the flat tree,
+        // since that's what controls our style.
         if node.is_text_node() {
-            node = node.parent_node().unwrap();
-            debug_assert!(node.is_element());
+            node = node.traversal_parent().unwrap().as_node();
         }
 
         let damage = {
@@ -39,12 +39,13 @@ use msg::constellation_msg::{BrowsingContextId, PipelineId};
 use nonzero::NonZero;
 use range::Range;
 use script::layout_exports::{CharacterDataTypeId, ElementTypeId, HTMLElementTypeId, NodeTypeId};
-use script::layout_exports::{Document, Element, Node, Text};
+use script::layout_exports::{Document, Element, Node, ShadowRoot, Text};
 use script::layout_exports::{LayoutCharacterDataHelpers, LayoutDocumentHelpers};
 use script::layout_exports::{LayoutElementHelpers, LayoutNodeHelpers, LayoutDom, RawLayoutElementHelpers};
+use script::layout_exports::LayoutShadowRootHelpers;
 use script::layout_exports::NodeFlags;
 use script::layout_exports::PendingRestyle;
-use script_layout_interface::{HTMLCanvasData, LayoutNodeType, SVGSVGData, TrustedNodeAddress};
+use script_layout_interface::{HTMLCanvasData, HTMLInputWidget, LayoutNodeType, SVGSVGData, TrustedNodeAddress};
 use script_layout_interface::{OpaqueStyleAndLayoutData, StyleData};
 use script_layout_interface::wrapper_traits::{DangerousThreadSafeLayoutNode, GetLayoutData, LayoutNode};
 use script_layout_interface::wrapper_traits::{PseudoElementType, ThreadSafeLayoutElement, ThreadSafeLayoutNode};
@@ -66,15 +67,18 @@ use style::attr::AttrValue;
 use style::computed_values::display;
 use style::context::SharedStyleContext;
 use style::data::ElementData;
-use style::dom::{DomChildren, LayoutIterator, NodeInfo, OpaqueNode};
+use style::dom::{LayoutIterator, NodeInfo, OpaqueNode};
 use style::dom::{TDocument, TElement, TNode};
 use style::element_state::*;
 use style::font_metrics::ServoMetricsProvider;
+use style::media_queries::Device;
 use style::properties::{ComputedValues, PropertyDeclarationBlock};
 use style::selector_parser::{AttrValue as SelectorAttrValue, NonTSPseudoClass, PseudoClassStringArg};
 use style::selector_parser::{PseudoElement, SelectorImpl, extended_filtering};
 use style::shared_lock::{SharedRwLock as StyleSharedRwLock, Locked as StyleLocked};
+use style::shared_lock::SharedRwLockReadGuard;
 use style::str::is_whitespace;
+use style::stylist::CascadeData;
 
 pub unsafe fn drop_style_and_layout_data(data: OpaqueStyleAndLayoutData) {
     let ptr = data.ptr.get() as *mut StyleData;
@@ -190,7 +194,9 @@ impl<'ln> TNode for ServoLayoutNode<'ln> {
     }
 
     fn traversal_parent(&self) -> Option<ServoLayoutElement<'ln>> {
-        self.parent_element()
+        unsafe {
+            self.node.flat_tree_parent_ref().and_then(as_element)
+        }
     }
 
     fn opaque(&self) -> OpaqueNode {
@@ -222,6 +228,39 @@ impl<'ln> TNode for ServoLayoutNode<'ln> {
     }
 }
 
+impl<'ln> ServoLayoutNode<'ln> {
+    /// Returns the root of the shadow tree this node is in, if any.
+    fn containing_shadow_root(&self) -> Option<LayoutDom<ShadowRoot>> {
+        unsafe {
+            if !self.node.get_flag(NodeFlags::IS_IN_SHADOW_TREE) {
+                return None;
+            }
+            let mut root = self.node;
+            while let Some(parent) = root.parent_node_ref() {
+                root = parent;
+            }
+            root.downcast()
+        }
+    }
+}
+
+/// An iterator over the children of a node in the flat tree, that is, the
+/// children of its shadow root if it's a shadow host, with slots replaced by
+/// the nodes assigned to them.
+pub struct ServoFlatTreeChildren<'ln>(Option<ServoLayoutNode<'ln>>);
+
+impl<'ln> Iterator for ServoFlatTreeChildren<'ln> {
+    type Item = ServoLayoutNode<'ln>;
+
+    fn next(&mut self) -> Option<ServoLayoutNode<'ln>> {
+        let current = self.0.take()?;
+        self.0 = unsafe {
+            current.node.flat_tree_next_sibling_ref().map(|node| current.new_with_this_lifetime(&node))
+        };
+        Some(current)
+    }
+}
+
 impl<'ln> LayoutNode for ServoLayoutNode<'ln> {
     type ConcreteThreadSafeLayoutNode = ServoThreadSafeLayoutNode<'ln>;
 
@@ -332,6 +371,25 @@ impl<'ld> ServoLayoutDocument<'ld> {
         unsafe { self.document.style_shared_lock() }
     }
 
+    /// Flushes the stylesheets of the shadow trees in this document, and
+    /// returns the hosts of those whose stylesheets changed.
+    pub fn flush_shadow_root_stylesheets(
+        &self,
+        device: &Device,
+        quirks_mode: QuirksMode,
+        guard: &SharedRwLockReadGuard,
+    ) -> Vec<ServoLayoutElement<'ld>> {
+        unsafe {
+            self.document.shadow_roots_for_layout().into_iter().filter_map(|shadow_root| {
+                if shadow_root.flush_stylesheets_for_layout(device, quirks_mode, guard) {
+                    Some(ServoLayoutElement::from_layout_js(shadow_root.get_host_for_layout()))
+                } else {
+                    None
+                }
+            }).collect()
+        }
+    }
+
     pub fn from_layout_js(doc: LayoutDom<Document>) -> ServoLayoutDocument<'ld> {
         ServoLayoutDocument {
             document: doc,
@@ -359,7 +417,7 @@ impl<'le> fmt::Debug for ServoLayoutElement<'le> {
 
 impl<'le> TElement for ServoLayoutElement<'le> {
     type ConcreteNode = ServoLayoutNode<'le>;
-    type TraversalChildrenIterator = DomChildren<Self::ConcreteNode>;
+    type TraversalChildrenIterator = ServoFlatTreeChildren<'le>;
 
     type FontMetricsProvider = ServoMetricsProvider;
 
@@ -368,7 +426,31 @@ impl<'le> TElement for ServoLayoutElement<'le> {
     }
 
     fn traversal_children(&self) -> LayoutIterator<Self::TraversalChildrenIterator> {
-        LayoutIterator(self.as_node().dom_children())
+        let node = self.as_node();
+        let first_child = unsafe {
+            node.node.flat_tree_first_child_ref().map(|child| node.new_with_this_lifetime(&child))
+        };
+        LayoutIterator(ServoFlatTreeChildren(first_child))
+    }
+
+    fn inheritance_parent(&self) -> Option<Self> {
+        self.traversal_parent()
+    }
+
+    fn style_scope(&self) -> ServoLayoutNode<'le> {
+        match self.as_node().containing_shadow_root() {
+            Some(shadow_root) => ServoLayoutNode::from_layout_js(shadow_root.upcast()),
+            None => self.as_node().owner_doc().as_node(),
+        }
+    }
+
+    fn shadow_root_cascade_data<'a>(&self) -> Option<&'a CascadeData>
+    where
+        Self: 'a,
+    {
+        unsafe {
+            self.element.get_shadow_root_for_layout().map(|shadow_root| shadow_root.get_style_data_for_layout())
+        }
     }
 
     fn style_attribute(&self) -> Option<ArcBorrow<StyleLocked<PropertyDeclarationBlock>>> {
@@ -592,9 +674,18 @@ impl<'le> ServoLayoutElement<'le> {
         self.as_node().node.set_flag(NodeFlags::HAS_SNAPSHOT, true);
     }
 
-    pub unsafe fn note_dirty_descendant(&self) {
+    /// Whether the rules of a shadow tree may apply to this element, either
+    /// because it's in a shadow tree, is a shadow host, or is assigned to a
+    /// slot.
+    pub fn may_match_shadow_tree_rules(&self) -> bool {
         use ::selectors::Element;
 
+        self.containing_shadow_host().is_some() ||
+            self.assigned_slot().is_some() ||
+            unsafe { self.element.get_shadow_root_for_layout().is_some() }
+    }
+
+    pub unsafe fn note_dirty_descendant(&self) {
         let mut current = Some(*self);
         while let Some(el) = current {
             // FIXME(bholley): Ideally we'd have the invariant that any element
@@ -603,7 +694,7 @@ impl<'le> ServoLayoutElement<'le> {
             // we get that wrong.  I have in-flight patches to fix all this
             // stuff up, so we just always propagate this bit for now.
             el.set_dirty_descendants();
-            current = el.parent_element();
+            current = el.traversal_parent();
         }
     }
 }
@@ -755,7 +846,9 @@ impl<'le> ::selectors::Element for ServoLayoutElement<'le> {
             NonTSPseudoClass::Indeterminate |
             NonTSPseudoClass::ReadWrite |
             NonTSPseudoClass::PlaceholderShown |
-            NonTSPseudoClass::Target =>
+            NonTSPseudoClass::Target |
+            NonTSPseudoClass::Valid |
+            NonTSPseudoClass::Invalid =>
                 self.element.get_state_for_layout().contains(pseudo_class.state_flag())
         }
     }
@@ -799,6 +892,24 @@ impl<'le> ::selectors::Element for ServoLayoutElement<'le> {
 
         self.as_node().owner_doc().is_html_document()
     }
+
+    fn parent_node_is_shadow_root(&self) -> bool {
+        unsafe {
+            self.element.upcast().parent_node_ref().map_or(false, |parent| parent.downcast::<ShadowRoot>().is_some())
+        }
+    }
+
+    fn containing_shadow_host(&self) -> Option<Self> {
+        self.as_node().containing_shadow_root().map(|shadow_root| unsafe {
+            ServoLayoutElement::from_layout_js(shadow_root.get_host_for_layout())
+        })
+    }
+
+    fn assigned_slot(&self) -> Option<Self> {
+        unsafe {
+            self.element.upcast().assigned_slot_ref().and_then(as_element)
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -820,11 +931,11 @@ impl<'a> PartialEq for ServoThreadSafeLayoutNode<'a> {
 
 impl<'ln> DangerousThreadSafeLayoutNode for ServoThreadSafeLayoutNode<'ln> {
     unsafe fn dangerous_first_child(&self) -> Option<Self> {
-            self.get_jsmanaged().first_child_ref()
+            self.get_jsmanaged().flat_tree_first_child_ref()
                 .map(|node| self.new_with_this_lifetime(&node))
     }
     unsafe fn dangerous_next_sibling(&self) -> Option<Self> {
-            self.get_jsmanaged().next_sibling_ref()
+            self.get_jsmanaged().flat_tree_next_sibling_ref()
                 .map(|node| self.new_with_this_lifetime(&node))
     }
 }
@@ -881,7 +992,7 @@ impl<'ln> ThreadSafeLayoutNode for ServoThreadSafeLayoutNode<'ln> {
     }
 
     fn parent_style(&self) -> Arc<ComputedValues> {
-        let parent = self.node.parent_node().unwrap().as_element().unwrap();
+        let parent = self.node.traversal_parent().unwrap();
         let parent_data = parent.get_data().unwrap().borrow();
         parent_data.styles.primary().clone()
     }
@@ -963,6 +1074,11 @@ impl<'ln> ThreadSafeLayoutNode for ServoThreadSafeLayoutNode<'ln> {
         this.svg_data()
     }
 
+    fn input_widget(&self) -> Option<HTMLInputWidget> {
+        let this = unsafe { self.get_jsmanaged() };
+        this.input_widget()
+    }
+
     // Can return None if the iframe has no nested browsing context
     fn iframe_browsing_context_id(&self) -> Option<BrowsingContextId> {
         let this = unsafe { self.get_jsmanaged() };
@@ -1108,7 +1108,7 @@ impl LayoutThread {
                     ReflowGoal::MarginStyleQuery(_) => {
                         rw_data.margin_style_response = MarginStyleResponse::empty();
                     },
-                    ReflowGoal::TextIndexQuery(..) => {
+                    ReflowGoal::TextIndexQuery(..) | ReflowGoal::TextOffsetQuery(..) => {
                         rw_data.text_index_response = TextIndexResponse(None);
                     }
                     ReflowGoal::Full | ReflowGoal:: TickAnimations => {}
@@ -1197,6 +1197,20 @@ impl LayoutThread {
             }
 
             self.stylist.flush(&guards, Some(element));
+
+            // There's no invalidation for the stylesheets of shadow trees, so
+            // restyle the whole flat tree of the hosts whose sheets changed.
+            let changed_hosts = document.flush_shadow_root_stylesheets(
+                self.stylist.device(),
+                document.quirks_mode(),
+                &author_guard,
+            );
+            for host in changed_hosts {
+                if let Some(mut data) = host.mutate_data() {
+                    data.hint.insert(RestyleHint::restyle_subtree());
+                }
+                unsafe { host.note_dirty_descendant() };
+            }
         }
 
         if viewport_size_changed {
@@ -1220,7 +1234,7 @@ impl LayoutThread {
             // Propagate the descendant bit up the ancestors. Do this before
             // the restyle calculation so that we can also do it for new
             // unstyled nodes, which the descendants bit helps us find.
-            if let Some(parent) = el.parent_element() {
+            if let Some(parent) = el.traversal_parent() {
                 unsafe { parent.note_dirty_descendant() };
             }
 
@@ -1243,6 +1257,10 @@ impl LayoutThread {
 
             // Stash the data on the element for processing by the style system.
             style_data.hint.insert(restyle.hint.into());
+            if el.has_snapshot() && el.may_match_shadow_tree_rules() {
+                // Snapshots are only checked against the document rules.
+                style_data.hint.insert(RestyleHint::restyle_subtree());
+            }
             style_data.damage = restyle.damage;
             debug!("Noting restyle for {:?}: {:?}", el, style_data);
         }
@@ -1372,6 +1390,17 @@ impl LayoutThread {
                     .text_index(opaque_node, &point_in_node)
                 );
             },
+            ReflowGoal::TextOffsetQuery(node, point) => {
+                let node = unsafe { ServoLayoutNode::new(&node) };
+                let opaque_node = node.opaque();
+                let point = Point2D::new(Au::from_f32_px(point.x), Au::from_f32_px(point.y));
+                rw_data.text_index_response = TextIndexResponse(
+                    rw_data.display_list
+                    .as_ref()
+                    .expect("Tried to hit test with no display list")
+                    .text_offset_at_point(opaque_node, &point)
+                );
+            },
             ReflowGoal::NodeGeometryQuery(node) => {
                 let node = unsafe { ServoLayoutNode::new(&node) };
                 rw_data.client_rect_response = process_node_geometry_request(node, root_flow);
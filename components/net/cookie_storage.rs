@@ -101,6 +101,11 @@ impl CookieStorage {
             cookie.creation_time = old_cookie.creation_time;
         }
 
+        // A cookie that has already expired only evicts the one it replaces.
+        if is_cookie_expired(&cookie) {
+            return;
+        }
+
         // Step 12
         let domain = reg_host(&cookie.cookie.domain().as_ref().unwrap_or(&""));
         let cookies = self.cookies_map.entry(domain).or_insert(vec![]);
@@ -4,7 +4,7 @@
 
 use blob_loader::load_blob_sync;
 use data_loader::decode;
-use devtools_traits::DevtoolsControlMsg;
+use devtools_traits::{BlockedUrls, DevtoolsControlMsg, NetworkListeners};
 use fetch::cors_cache::CorsCache;
 use filemanager_thread::FileManager;
 use http_loader::{HttpState, determine_request_referrer, http_fetch};
@@ -17,6 +17,7 @@ use hyper::mime::{Mime, SubLevel, TopLevel};
 use hyper::status::StatusCode;
 use mime_guess::guess_mime_type;
 use net_traits::{FetchTaskTarget, NetworkError, ReferrerPolicy};
+use net_traits::csp::CheckResult;
 use net_traits::request::{CredentialsMode, Destination, Referrer, Request, RequestMode};
 use net_traits::request::{ResponseTainting, Origin, Window};
 use net_traits::response::{Response, ResponseBody, ResponseType};
@@ -42,6 +43,10 @@ pub struct FetchContext {
     pub state: Arc<HttpState>,
     pub user_agent: Cow<'static, str>,
     pub devtools_chan: Option<Sender<DevtoolsControlMsg>>,
+    /// The URLs that devtools block requests to.
+    pub blocked_urls: BlockedUrls,
+    /// The devtools clients that want to be sent response bodies.
+    pub network_listeners: NetworkListeners,
     pub filemanager: FileManager,
 }
 
@@ -112,7 +117,8 @@ pub fn main_fetch(request: &mut Request,
     }
 
     // Step 3.
-    // TODO: handle content security policy violations.
+    // Violations of report-only policies are reported along with those of
+    // enforced policies in step 5.
 
     // Step 4.
     // TODO: handle upgrade to a potentially secure URL.
@@ -122,7 +128,12 @@ pub fn main_fetch(request: &mut Request,
         response = Some(Response::network_error(NetworkError::Internal("Request attempted on bad port".into())));
     }
     // TODO: handle blocking as mixed content.
-    // TODO: handle blocking by content security policy.
+    if should_be_blocked_by_csp(request, target) {
+        response = Some(Response::network_error(NetworkError::Internal("Blocked by Content Security Policy".into())));
+    }
+    if context.blocked_urls.is_blocked(&request.current_url()) {
+        response = Some(Response::network_error(NetworkError::Internal("Blocked by devtools".into())));
+    }
 
     // Step 6
     // TODO: handle request's client's referrer policy.
@@ -633,6 +644,18 @@ fn should_be_blocked_due_to_mime_type(destination: Destination, response_headers
     }
 }
 
+/// <https://w3c.github.io/webappsec-csp/#should-block-request>
+fn should_be_blocked_by_csp(request: &Request, target: Target) -> bool {
+    if request.csp_list.is_empty() {
+        return false;
+    }
+    let (result, violations) = request.csp_list.should_request_be_blocked(request);
+    if !violations.is_empty() {
+        target.process_csp_violations(request, violations);
+    }
+    result == CheckResult::Blocked
+}
+
 /// <https://fetch.spec.whatwg.org/#block-bad-port>
 pub fn should_be_blocked_due_to_bad_port(url: &ServoUrl) -> bool {
     // Step 1 is not applicable, this function just takes the URL directly.
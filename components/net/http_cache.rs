@@ -0,0 +1,1071 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#![deny(missing_docs)]
+
+//! A memory cache implementing the logic specified in <http://tools.ietf.org/html/rfc7234>
+//! and <http://tools.ietf.org/html/rfc7232>.
+//!
+//! The cache can be backed by a directory on disk, in which case responses survive restarts.
+//! Bodies are written to one file each, and an index file describes every stored response.
+//! All files are written to a temporary file first and renamed into place, so a crash never
+//! leaves a partially written file behind. The writing happens on a dedicated thread, as soon
+//! as a response is complete, so the cache lock is never held while waiting for the disk.
+
+use hyper::header::{CacheControl, CacheDirective, Date, ETag, Expires};
+use hyper::header::{Headers, IfModifiedSince, IfNoneMatch, LastModified, Location, Pragma, Vary};
+use hyper::method::Method;
+use hyper::status::StatusCode;
+use net_traits::request::Request;
+use net_traits::response::{CacheState, HttpsState, Response, ResponseBody};
+use serde_json;
+use servo_config::prefs::PREFS;
+use servo_url::ServoUrl;
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::iter;
+use std::str;
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::thread;
+use time;
+use time::{Duration, Timespec, Tm};
+use uuid::Uuid;
+
+/// The size of the cache, in bytes, if the `network.http-cache.max-size-bytes` pref is not set.
+const DEFAULT_MAX_SIZE: usize = 50 * 1024 * 1024;
+
+/// The name of the directory holding the cache, inside the config directory.
+const CACHE_DIRECTORY: &'static str = "http_cache";
+
+/// The name of the index file, inside the cache directory.
+const INDEX_FILE: &'static str = "index.json";
+
+/// The version of the on-disk format, bumped whenever the index can't be read by older code.
+const INDEX_VERSION: u32 = 1;
+
+/// Request headers carrying credentials, which are never written to disk.
+const CREDENTIAL_HEADERS: &'static [&'static str] = &["authorization", "cookie", "proxy-authorization"];
+
+
+/// The key used to differentiate requests in the cache.
+#[derive(Clone, Eq, Hash, PartialEq)]
+pub struct CacheKey {
+    url: ServoUrl
+}
+
+impl CacheKey {
+    fn new(request: &Request) -> CacheKey {
+        CacheKey::from_servo_url(&request.current_url())
+    }
+
+    fn from_servo_url(servo_url: &ServoUrl) -> CacheKey {
+        // Fragments are never sent to the server, so they can't influence the response.
+        let mut url = servo_url.clone();
+        url.as_mut_url().set_fragment(None);
+        CacheKey {
+            url: url
+        }
+    }
+
+    /// Retrieve the URL associated with this key
+    pub fn url(&self) -> ServoUrl {
+        self.url.clone()
+    }
+}
+
+/// A complete cached resource.
+#[derive(Clone)]
+struct CachedResource {
+    /// A unique identifier, also used to name the file holding the body on disk.
+    id: String,
+    /// The request headers named by the Vary header of the response, needed to match later requests.
+    request_headers: Headers,
+    /// The complete body, or `Empty` while it is still on disk or being received.
+    body: Arc<Mutex<ResponseBody>>,
+    /// The body of the network response while it is being received. The resource can only be
+    /// used once the whole body is there, at which point it is copied to `body`.
+    pending_body: Option<Arc<Mutex<ResponseBody>>>,
+    metadata: CachedMetadata,
+    location_url: Option<Result<ServoUrl, String>>,
+    https_state: HttpsState,
+    status: Option<StatusCode>,
+    raw_status: Option<(u16, Vec<u8>)>,
+    url_list: Vec<ServoUrl>,
+    /// The time at which the response was received, or last validated.
+    response_time: Tm,
+    /// <https://tools.ietf.org/html/rfc7234#section-4.2.3>, "corrected_initial_age".
+    initial_age: Duration,
+    /// Whether the body has been written to disk.
+    persisted: bool,
+    /// The size of the body on disk, used while the body itself has not been read back.
+    disk_size: usize,
+    /// The value of the cache's use counter the last time this resource was used,
+    /// for least-recently-used eviction.
+    last_used: u64,
+}
+
+impl CachedResource {
+    /// The number of bytes the body of this resource accounts for in the cache.
+    fn size(&self) -> usize {
+        let body = match self.pending_body {
+            Some(ref pending_body) => pending_body.lock().unwrap(),
+            None => self.body.lock().unwrap(),
+        };
+        match *body {
+            ResponseBody::Done(ref body) | ResponseBody::Receiving(ref body) => body.len(),
+            ResponseBody::Empty => self.disk_size,
+        }
+    }
+
+    /// Whether this resource is waiting for the given body to be received.
+    fn is_waiting_for(&self, body: &Arc<Mutex<ResponseBody>>) -> bool {
+        self.pending_body.as_ref().map_or(false, |pending_body| Arc::ptr_eq(pending_body, body))
+    }
+
+    /// Whether matching this resource depends on the credentials of the request.
+    /// Those are never written to disk, so neither is the resource.
+    fn varies_on_credentials(&self) -> bool {
+        CREDENTIAL_HEADERS.iter().any(|name| self.request_headers.get_raw(name).is_some())
+    }
+}
+
+/// Metadata about a loaded resource, such as is obtained from HTTP headers.
+#[derive(Clone)]
+struct CachedMetadata {
+    /// Final URL after redirects.
+    final_url: ServoUrl,
+    /// Headers
+    headers: Headers,
+}
+
+/// Wrapper around a cached response, including information on re-validation needs
+pub struct CachedResponse {
+    /// The response constructed from the cached resource
+    pub response: Response,
+    /// The revalidation flag for the stored response
+    pub needs_validation: bool
+}
+
+/// A memory cache, optionally persisted to disk.
+pub struct HttpCache {
+    /// cached responses.
+    entries: HashMap<CacheKey, Vec<CachedResource>>,
+    /// The directory the cache is persisted to, if any.
+    directory: Option<PathBuf>,
+    /// The number of body bytes above which least recently used responses are evicted.
+    max_size: usize,
+    /// Incremented every time a resource is stored or used.
+    use_counter: u64,
+    /// The channel to the thread writing the cache to disk, if it is backed by a directory.
+    writer: Option<Mutex<Sender<DiskOperation>>>,
+}
+
+/// Work for the thread writing the cache to disk.
+enum DiskOperation {
+    /// Write the body of the resource with the given id.
+    WriteBody(String, Vec<u8>),
+    /// Replace the index, and remove the bodies it no longer refers to.
+    WriteIndex(StoredIndex),
+    /// Remove every file of the cache.
+    Clear,
+    /// Reply once every operation sent before is done.
+    Sync(Sender<()>),
+}
+
+/// The on-disk representation of a cached resource, without its body.
+#[derive(Deserialize, Serialize)]
+struct StoredResource {
+    id: String,
+    url: ServoUrl,
+    #[serde(deserialize_with = "::hyper_serde::deserialize",
+            serialize_with = "::hyper_serde::serialize")]
+    request_headers: Headers,
+    final_url: ServoUrl,
+    #[serde(deserialize_with = "::hyper_serde::deserialize",
+            serialize_with = "::hyper_serde::serialize")]
+    headers: Headers,
+    location_url: Option<Result<ServoUrl, String>>,
+    https_state: HttpsState,
+    raw_status: Option<(u16, Vec<u8>)>,
+    url_list: Vec<ServoUrl>,
+    /// Seconds since the epoch.
+    response_time: i64,
+    /// In seconds.
+    initial_age: i64,
+    body_size: usize,
+}
+
+/// The on-disk index of the cache, ordered from least to most recently used.
+#[derive(Deserialize, Serialize)]
+struct StoredIndex {
+    version: u32,
+    resources: Vec<StoredResource>,
+}
+
+/// Write `data` to `path` without ever leaving a partially written file at `path`.
+fn write_atomically(path: &Path, data: &[u8]) -> io::Result<()> {
+    let temporary_path = path.with_extension("tmp");
+    {
+        let mut file = File::create(&temporary_path)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+    }
+    fs::rename(&temporary_path, path)
+}
+
+fn body_file_name(id: &str) -> String {
+    format!("{}.body", id)
+}
+
+/// Read back the body of a persisted resource, if it isn't in memory yet.
+/// Returns false if the body is missing from disk.
+fn load_body_from_disk(directory: &Path, cached_resource: &CachedResource) -> bool {
+    if !cached_resource.persisted || *cached_resource.body.lock().unwrap() != ResponseBody::Empty {
+        return true;
+    }
+    let path = directory.join(body_file_name(&cached_resource.id));
+    let mut bytes = vec![];
+    match File::open(&path).and_then(|mut file| file.read_to_end(&mut bytes)) {
+        Ok(_) => {
+            // Another consumer may have read the body meanwhile, the bytes are the same.
+            let mut body = cached_resource.body.lock().unwrap();
+            if *body == ResponseBody::Empty {
+                *body = ResponseBody::Done(bytes);
+            }
+            true
+        },
+        Err(e) => {
+            warn!("couldn't read cached body {}: {}", path.display(), e);
+            false
+        },
+    }
+}
+
+
+/// Determine if a given response is cacheable based on the initial metadata received.
+/// Based on <https://tools.ietf.org/html/rfc7234#section-3>
+fn response_is_cacheable(request: &Request, response: &Response) -> bool {
+    // Only responses to GET requests are stored, the other methods
+    // either are never cacheable or would require special key handling.
+    if request.method != Method::Get {
+        return false;
+    }
+    if response.is_network_error() {
+        return false;
+    }
+    let status = match response.raw_status {
+        Some((status, _)) => status,
+        None => return false,
+    };
+    // 206 and 304 responses only make sense when combined with a stored response.
+    if status == 206 || status == 304 {
+        return false;
+    }
+    let request_directives = request.headers.get::<CacheControl>();
+    if request_directives.map_or(false, |directives| directives.contains(&CacheDirective::NoStore)) {
+        return false;
+    }
+    let directives = response.headers.get::<CacheControl>();
+    if directives.map_or(false, |directives| directives.contains(&CacheDirective::NoStore)) {
+        return false;
+    }
+    if let Some(&Vary::Any) = response.headers.get::<Vary>() {
+        // A Vary header of "*" always fails to match.
+        return false;
+    }
+    if request.headers.get_raw("authorization").is_some() {
+        // https://tools.ietf.org/html/rfc7234#section-3.2
+        let allowed = directives.map_or(false, |directives| {
+            directives.iter().any(|directive| match *directive {
+                CacheDirective::Public |
+                CacheDirective::MustRevalidate |
+                CacheDirective::SMaxAge(_) => true,
+                _ => false,
+            })
+        });
+        if !allowed {
+            return false;
+        }
+    }
+    // A resource which is stale from the start and can't be revalidated is pointless to store.
+    let has_validator = response.headers.has::<ETag>() || response.headers.has::<LastModified>();
+    has_validator || get_response_expiry(response) > Duration::seconds(0)
+}
+
+/// Whether a response with this status never has a body.
+/// <https://fetch.spec.whatwg.org/#null-body-status>
+fn is_null_body_status(response: &Response) -> bool {
+    match response.raw_status {
+        Some((101, _)) | Some((204, _)) | Some((205, _)) | Some((304, _)) => true,
+        _ => false,
+    }
+}
+
+/// Calculating Age, the "corrected_initial_age" of a response requested at `request_time`
+/// and received at `response_time`.
+/// <https://tools.ietf.org/html/rfc7234#section-4.2.3>
+fn calculate_response_age(response: &Response, request_time: Tm, response_time: Tm) -> Duration {
+    let zero = Duration::seconds(0);
+    let age_value = response.headers.get_raw("age")
+        .and_then(|values| values.last())
+        .and_then(|value| str::from_utf8(value).ok())
+        .and_then(|value| value.trim().parse::<i64>().ok())
+        .map(Duration::seconds)
+        .unwrap_or(zero);
+    let apparent_age = match response.headers.get::<Date>() {
+        Some(&Date(ref date)) => cmp::max(zero, response_time - date.0),
+        None => zero,
+    };
+    let response_delay = cmp::max(zero, response_time - request_time);
+    let corrected_age_value = age_value + response_delay;
+    cmp::max(apparent_age, corrected_age_value)
+}
+
+/// Determine the expiry date from relevant headers,
+/// or uses a heuristic if none are present.
+/// <https://tools.ietf.org/html/rfc7234#section-4.2.1>
+fn get_response_expiry(response: &Response) -> Duration {
+    // Calculating Freshness Lifetime <https://tools.ietf.org/html/rfc7234#section-4.2.1>
+    if let Some(&CacheControl(ref directives)) = response.headers.get::<CacheControl>() {
+        let has_no_cache_directive = directives.iter().any(|directive| {
+            CacheDirective::NoCache == *directive
+        });
+        if has_no_cache_directive {
+            // Requires validation on first use.
+            return Duration::seconds(0i64);
+        } else {
+            for directive in directives {
+                // s-maxage only applies to shared caches, which we are not.
+                if let CacheDirective::MaxAge(secs) = *directive {
+                    return Duration::seconds(secs as i64);
+                }
+            }
+        }
+    }
+    let date = match response.headers.get::<Date>() {
+        Some(&Date(ref date)) => date.0,
+        None => time::now_utc(),
+    };
+    match response.headers.get::<Expires>() {
+        Some(&Expires(ref expires)) => {
+            let lifetime = expires.0 - date;
+            return if lifetime > Duration::seconds(0) { lifetime } else { Duration::seconds(0) };
+        },
+        None => {
+            if response.headers.get_raw("expires").is_some() {
+                // Malformed Expires header, shouldn't be used to construct a valid response.
+                return Duration::seconds(0i64);
+            }
+        },
+    }
+    // Calculating Heuristic Freshness
+    // <https://tools.ietf.org/html/rfc7234#section-4.2.2>
+    if let Some((ref code, _)) = response.raw_status {
+        // <https://tools.ietf.org/html/rfc7234#section-5.5.4>
+        // Since presently we do not generate a Warning header field with a 113 warn-code,
+        // a heuristic freshness lifetime must not exceed 24 hours, so that no response
+        // older than that is ever served as fresh.
+        let max_heuristic = Duration::hours(24);
+        let heuristic_freshness = if let Some(&LastModified(ref last_modified)) =
+            response.headers.get::<LastModified>() {
+            // If the response has a Last-Modified header field,
+            // caches are encouraged to use a heuristic expiration value
+            // that is no more than some fraction of the interval since that time.
+            // A typical setting of this fraction might be 10%.
+            let last_modified = date - last_modified.0;
+            let raw_heuristic_calc = last_modified / 10;
+            let result = if raw_heuristic_calc < max_heuristic {
+                raw_heuristic_calc
+            } else {
+                max_heuristic
+            };
+            result
+        } else {
+            max_heuristic
+        };
+        match *code {
+            200 | 203 | 204 | 300 | 301 | 404 | 405 | 410 | 414 | 501 => {
+                // Status codes that are cacheable by default can use heuristics.
+                if response.headers.has::<LastModified>() {
+                    return heuristic_freshness;
+                }
+            },
+            _ => {
+                // Other status codes can only use heuristic freshness if the public cache directive is present.
+                if let Some(&CacheControl(ref directives)) = response.headers.get::<CacheControl>() {
+                    let has_public_directive = directives.iter().any(|directive| {
+                        CacheDirective::Public == *directive
+                    });
+                    if has_public_directive && response.headers.has::<LastModified>() {
+                        return heuristic_freshness;
+                    }
+                }
+            },
+        }
+    }
+    // Requires validation upon first use as default.
+    Duration::seconds(0i64)
+}
+
+/// Request Cache-Control Directives
+/// <https://tools.ietf.org/html/rfc7234#section-5.2.1>
+fn get_expiry_adjustment_from_request_headers(request: &Request, expires: Duration) -> Duration {
+    let directives = match request.headers.get::<CacheControl>() {
+        Some(&CacheControl(ref directives)) => directives,
+        None => {
+            // <https://tools.ietf.org/html/rfc7234#section-5.4>
+            if let Some(&Pragma::NoCache) = request.headers.get::<Pragma>() {
+                return Duration::seconds(0i64);
+            }
+            return expires;
+        },
+    };
+    for directive in directives {
+        match *directive {
+            CacheDirective::MaxStale(secs) => return expires + Duration::seconds(secs as i64),
+            CacheDirective::MaxAge(secs) => {
+                let max_age = Duration::seconds(secs as i64);
+                if expires > max_age {
+                    return max_age;
+                }
+                return expires;
+            },
+            CacheDirective::MinFresh(secs) => {
+                let min_fresh = Duration::seconds(secs as i64);
+                if expires < min_fresh {
+                    return Duration::seconds(0i64);
+                }
+                return expires - min_fresh;
+            },
+            CacheDirective::NoCache | CacheDirective::NoStore => return Duration::seconds(0i64),
+            _ => {},
+        }
+    }
+    expires
+}
+
+/// Create a CachedResponse from a request and a CachedResource.
+fn create_cached_response(request: &Request, cached_resource: &CachedResource) -> Option<CachedResponse> {
+    if cached_resource.pending_body.is_some() {
+        // A response which is still being received is not yet usable by other consumers.
+        return None;
+    }
+    let body = match *cached_resource.body.lock().unwrap() {
+        ResponseBody::Done(ref body) => body.clone(),
+        ResponseBody::Empty | ResponseBody::Receiving(_) => return None,
+    };
+    let mut response = Response::new(cached_resource.metadata.final_url.clone());
+    response.headers = cached_resource.metadata.headers.clone();
+    // Every consumer gets its own copy of the body,
+    // since fetch is free to modify the body of the response it returns.
+    response.body = Arc::new(Mutex::new(ResponseBody::Done(body)));
+    response.location_url = cached_resource.location_url.clone();
+    response.status = cached_resource.status.clone();
+    response.raw_status = cached_resource.raw_status.clone();
+    response.url_list = cached_resource.url_list.clone();
+    response.https_state = cached_resource.https_state.clone();
+    response.referrer = request.referrer.to_url().cloned();
+    response.referrer_policy = request.referrer_policy.clone();
+    response.cache_state = CacheState::Local;
+    // The freshness lifetime is that of the stored response, before its Age header is updated.
+    let expires = get_expiry_adjustment_from_request_headers(request, get_response_expiry(&response));
+    // <https://tools.ietf.org/html/rfc7234#section-4.2.3>
+    let resident_time = time::now_utc() - cached_resource.response_time;
+    let current_age = cached_resource.initial_age + resident_time;
+    response.headers.set_raw("Age", vec![current_age.num_seconds().to_string().into_bytes()]);
+    let has_expired = expires <= current_age;
+    Some(CachedResponse { response: response, needs_validation: has_expired })
+}
+
+/// Whether the stored request headers are a match for the new request,
+/// for every header named by the Vary header of the stored response.
+/// <https://tools.ietf.org/html/rfc7234#section-4.1>
+fn vary_headers_match(request: &Request, cached_resource: &CachedResource) -> bool {
+    match cached_resource.metadata.headers.get::<Vary>() {
+        Some(&Vary::Any) => false,
+        Some(&Vary::Items(ref names)) => {
+            names.iter().all(|name| {
+                let stored = cached_resource.request_headers.get_raw(name);
+                let current = request.headers.get_raw(name);
+                stored == current
+            })
+        },
+        None => true,
+    }
+}
+
+/// The request headers named by the Vary header of `response`,
+/// which are all that is needed to tell whether a later request matches.
+fn vary_request_headers(request: &Request, response: &Response) -> Headers {
+    let mut headers = Headers::new();
+    if let Some(&Vary::Items(ref names)) = response.headers.get::<Vary>() {
+        for name in names {
+            if let Some(values) = request.headers.get_raw(name) {
+                headers.set_raw((**name).clone(), values.to_vec());
+            }
+        }
+    }
+    headers
+}
+
+/// Whether the given stored resource can be updated by a 304 response carrying `headers`.
+/// <https://tools.ietf.org/html/rfc7234#section-4.3.4>
+fn validators_match(headers: &Headers, cached_resource: &CachedResource) -> bool {
+    let cached_headers = &cached_resource.metadata.headers;
+    if let Some(&ETag(ref tag)) = headers.get::<ETag>() {
+        return match cached_headers.get::<ETag>() {
+            Some(&ETag(ref cached_tag)) => tag.weak_eq(cached_tag),
+            None => false,
+        };
+    }
+    if let Some(&LastModified(ref date)) = headers.get::<LastModified>() {
+        return match cached_headers.get::<LastModified>() {
+            Some(&LastModified(ref cached_date)) => date == cached_date,
+            None => false,
+        };
+    }
+    true
+}
+
+/// Set the conditional request headers needed to revalidate `cached_response`.
+/// <https://tools.ietf.org/html/rfc7232#section-3>
+/// Returns false if the stored response carries no validator.
+pub fn set_validators(cached_response: &Response, headers: &mut Headers) -> bool {
+    let mut has_validator = false;
+    if let Some(&ETag(ref tag)) = cached_response.headers.get::<ETag>() {
+        headers.set(IfNoneMatch::Items(vec![tag.clone()]));
+        has_validator = true;
+    }
+    if let Some(&LastModified(ref date)) = cached_response.headers.get::<LastModified>() {
+        headers.set(IfModifiedSince(date.clone()));
+        has_validator = true;
+    }
+    has_validator
+}
+
+impl HttpCache {
+    /// Create a new memory cache instance.
+    pub fn new() -> HttpCache {
+        let max_size = PREFS.get("network.http-cache.max-size-bytes").as_u64()
+                            .map_or(DEFAULT_MAX_SIZE, |size| size as usize);
+        HttpCache::with_max_size(max_size)
+    }
+
+    /// Create a new memory cache instance holding at most `max_size` bytes of bodies.
+    pub fn with_max_size(max_size: usize) -> HttpCache {
+        HttpCache {
+            entries: HashMap::new(),
+            directory: None,
+            max_size: max_size,
+            use_counter: 0,
+            writer: None,
+        }
+    }
+
+    /// Create a cache persisted in `config_dir`, populated with the responses stored there.
+    /// Bodies are only read from disk when a response is used.
+    pub fn new_with_disk(config_dir: &Path) -> HttpCache {
+        let mut cache = HttpCache::new();
+        let directory = config_dir.join(CACHE_DIRECTORY);
+        let index_path = directory.join(INDEX_FILE);
+        let index = File::open(&index_path).map_err(|e| e.to_string()).and_then(|file| {
+            serde_json::from_reader::<_, StoredIndex>(file).map_err(|e| e.to_string())
+        });
+        let index = match index {
+            Ok(ref index) if index.version != INDEX_VERSION => {
+                warn!("ignoring http cache index with unknown version {}", index.version);
+                None
+            },
+            Ok(index) => Some(index),
+            Err(e) => {
+                debug!("couldn't read http cache index {}: {}", index_path.display(), e);
+                None
+            },
+        };
+        if let Some(index) = index {
+            for stored_resource in index.resources {
+                if !directory.join(body_file_name(&stored_resource.id)).is_file() {
+                    continue;
+                }
+                cache.use_counter += 1;
+                let status = stored_resource.raw_status.as_ref().map(|&(code, _)| StatusCode::from_u16(code));
+                let cached_resource = CachedResource {
+                    id: stored_resource.id,
+                    request_headers: stored_resource.request_headers,
+                    body: Arc::new(Mutex::new(ResponseBody::Empty)),
+                    pending_body: None,
+                    metadata: CachedMetadata {
+                        final_url: stored_resource.final_url,
+                        headers: stored_resource.headers,
+                    },
+                    location_url: stored_resource.location_url,
+                    https_state: stored_resource.https_state,
+                    status: status,
+                    raw_status: stored_resource.raw_status,
+                    url_list: stored_resource.url_list,
+                    response_time: time::at_utc(Timespec::new(stored_resource.response_time, 0)),
+                    initial_age: Duration::seconds(stored_resource.initial_age),
+                    persisted: true,
+                    disk_size: stored_resource.body_size,
+                    last_used: cache.use_counter,
+                };
+                let key = CacheKey::from_servo_url(&stored_resource.url);
+                cache.entries.entry(key).or_insert(vec![]).push(cached_resource);
+            }
+        }
+        // Get rid of leftovers of interrupted writes, and of bodies the index doesn't refer to.
+        let live_files: HashSet<String> = cache.entries.values()
+            .flat_map(|cached_resources| cached_resources.iter())
+            .map(|cached_resource| body_file_name(&cached_resource.id))
+            .collect();
+        remove_stale_files(&directory, &live_files);
+        let (sender, receiver) = channel();
+        let writer_directory = directory.clone();
+        let spawned = thread::Builder::new().name("HttpCacheWriter".to_owned()).spawn(move || {
+            run_disk_writer(&writer_directory, receiver)
+        });
+        match spawned {
+            Ok(_) => cache.writer = Some(Mutex::new(sender)),
+            Err(e) => warn!("couldn't start the http cache writer, the cache won't be persisted: {}", e),
+        }
+        cache.directory = Some(directory);
+        // The budget may have been lowered since the cache was written.
+        cache.evict();
+        cache.persist();
+        cache
+    }
+
+    /// Constructing Responses from Caches.
+    /// <https://tools.ietf.org/html/rfc7234#section-4>
+    ///
+    /// Bodies which are only on disk are read without holding the lock on the cache.
+    pub fn construct_response(cache: &RwLock<HttpCache>, request: &Request) -> Option<CachedResponse> {
+        // TODO: generate warning headers as appropriate <https://tools.ietf.org/html/rfc7234#section-5.5>
+        if request.method != Method::Get {
+            // Only Get requests are cached, avoid a url based match for others.
+            return None;
+        }
+        let entry_key = CacheKey::new(request);
+        let (directory, candidates) = {
+            let cache = cache.read().unwrap();
+            let candidates: Vec<CachedResource> = match cache.entries.get(&entry_key) {
+                // Prefer the most recently stored matching response.
+                Some(resources) => resources.iter().rev().filter(|cached_resource| {
+                    cached_resource.pending_body.is_none() && vary_headers_match(request, cached_resource)
+                }).cloned().collect(),
+                None => return None,
+            };
+            (cache.directory.clone(), candidates)
+        };
+        let mut missing = vec![];
+        let mut found = None;
+        for candidate in candidates {
+            if let Some(ref directory) = directory {
+                if !load_body_from_disk(directory, &candidate) {
+                    // Forget about responses whose body went missing from disk.
+                    missing.push(candidate.id);
+                    continue;
+                }
+            }
+            if let Some(cached_response) = create_cached_response(request, &candidate) {
+                found = Some((candidate.id, cached_response));
+                break;
+            }
+        }
+        let mut cache = cache.write().unwrap();
+        for id in missing {
+            cache.remove(&entry_key, &id);
+        }
+        found.map(|(id, cached_response)| {
+            cache.mark_used(&entry_key, &id);
+            cached_response
+        })
+    }
+
+    /// Forget about the resource with the given id.
+    fn remove(&mut self, key: &CacheKey, id: &str) {
+        let is_empty = match self.entries.get_mut(key) {
+            Some(cached_resources) => {
+                cached_resources.retain(|cached_resource| cached_resource.id != id);
+                cached_resources.is_empty()
+            },
+            None => false,
+        };
+        if is_empty {
+            self.entries.remove(key);
+        }
+    }
+
+    /// Record that the resource with the given id was just used, for least-recently-used eviction.
+    fn mark_used(&mut self, key: &CacheKey, id: &str) {
+        self.use_counter += 1;
+        let use_counter = self.use_counter;
+        if let Some(cached_resources) = self.entries.get_mut(key) {
+            for cached_resource in cached_resources.iter_mut() {
+                if cached_resource.id == id {
+                    cached_resource.last_used = use_counter;
+                }
+            }
+        }
+    }
+
+    /// Freshening Stored Responses upon Validation, with a 304 response to a request sent
+    /// at `request_time`.
+    /// <https://tools.ietf.org/html/rfc7234#section-4.3.4>
+    pub fn refresh(&mut self, request: &Request, response: &Response, request_time: Tm) -> Option<Response> {
+        assert_eq!(response.status, Some(StatusCode::NotModified));
+        let entry_key = CacheKey::new(request);
+        self.use_counter += 1;
+        let use_counter = self.use_counter;
+        let now = time::now_utc();
+        let initial_age = calculate_response_age(response, request_time, now);
+        let constructed_response = match self.entries.get_mut(&entry_key) {
+            Some(resources) => resources.iter_mut().rev().find(|cached_resource| {
+                vary_headers_match(request, cached_resource) &&
+                validators_match(&response.headers, cached_resource)
+            }).and_then(|cached_resource| {
+                // Use the stored response, updated with the new headers.
+                cached_resource.metadata.headers.extend(response.headers.iter());
+                cached_resource.response_time = now;
+                cached_resource.initial_age = initial_age;
+                cached_resource.last_used = use_counter;
+                create_cached_response(request, cached_resource)
+            }),
+            None => return None,
+        };
+        self.persist();
+        constructed_response.map(|cached_response| {
+            let mut constructed_response = cached_response.response;
+            constructed_response.cache_state = CacheState::Validated;
+            constructed_response
+        })
+    }
+
+    /// Invalidation.
+    /// <https://tools.ietf.org/html/rfc7234#section-4.4>
+    pub fn invalidate(&mut self, request: &Request, response: &Response) {
+        let current_url = request.current_url();
+        let mut urls = vec![current_url.clone()];
+        if let Some(&Location(ref location)) = response.headers.get::<Location>() {
+            if let Ok(url) = ServoUrl::parse_with_base(Some(&current_url), location) {
+                urls.push(url);
+            }
+        }
+        let content_location = response.headers.get_raw("content-location")
+            .and_then(|values| values.last())
+            .and_then(|value| str::from_utf8(value).ok());
+        if let Some(content_location) = content_location {
+            if let Ok(url) = ServoUrl::parse_with_base(Some(&current_url), content_location) {
+                urls.push(url);
+            }
+        }
+        for url in urls {
+            // A cache must not invalidate a URL from a different origin.
+            if url.origin() != current_url.origin() {
+                continue;
+            }
+            self.entries.remove(&CacheKey::from_servo_url(&url));
+        }
+        self.persist();
+    }
+
+    /// Storing Responses in Caches, for a request sent at `request_time`.
+    /// <https://tools.ietf.org/html/rfc7234#section-3>
+    ///
+    /// A response whose body is still being received is only used once `body_finished`
+    /// reports that the whole body arrived.
+    pub fn store(&mut self, request: &Request, response: &Response, request_time: Tm) {
+        if !response_is_cacheable(request, response) {
+            return;
+        }
+        let url = match response.url() {
+            Some(url) => url.clone(),
+            None => return,
+        };
+        let (body, pending_body) = if is_null_body_status(response) {
+            // Fetch empties the body of these responses, which never have one anyway.
+            (ResponseBody::Done(vec![]), None)
+        } else {
+            let body = response.body.lock().unwrap();
+            // The body is marked as aborted before it is done, so this is up to date once it is.
+            let aborted = response.aborted.load(Ordering::SeqCst);
+            let bodies = match *body {
+                _ if aborted => return,
+                ResponseBody::Done(ref body) => (ResponseBody::Done(body.clone()), None),
+                ResponseBody::Empty | ResponseBody::Receiving(_) => {
+                    (ResponseBody::Empty, Some(response.body.clone()))
+                },
+            };
+            bodies
+        };
+        let entry_key = CacheKey::new(request);
+        let response_time = time::now_utc();
+        self.use_counter += 1;
+        let is_complete = pending_body.is_none();
+        let entry_resource = CachedResource {
+            id: Uuid::new_v4().simple().to_string(),
+            request_headers: vary_request_headers(request, response),
+            body: Arc::new(Mutex::new(body)),
+            pending_body: pending_body,
+            metadata: CachedMetadata {
+                final_url: url,
+                headers: response.headers.clone(),
+            },
+            location_url: response.location_url.clone(),
+            https_state: response.https_state.clone(),
+            status: response.status.clone(),
+            raw_status: response.raw_status.clone(),
+            url_list: response.url_list.clone(),
+            response_time: response_time,
+            initial_age: calculate_response_age(response, request_time, response_time),
+            persisted: false,
+            disk_size: 0,
+            last_used: self.use_counter,
+        };
+        {
+            let entry = self.entries.entry(entry_key).or_insert(vec![]);
+            // A new response replaces any stored response for the same variant.
+            entry.retain(|cached_resource| !vary_headers_match(request, cached_resource));
+            entry.push(entry_resource);
+        }
+        // Bodies still being received are accounted for, and persisted, by `body_finished`.
+        if is_complete {
+            self.evict();
+        }
+        self.persist();
+    }
+
+    /// Called once the body of a network response has been received, or failed to be.
+    /// The responses stored while that body was being received become usable, unless the
+    /// body is incomplete, in which case they are forgotten.
+    pub fn body_finished(&mut self, body: &Arc<Mutex<ResponseBody>>, aborted: bool) {
+        let complete_body = match *body.lock().unwrap() {
+            ResponseBody::Done(ref bytes) if !aborted => Some(bytes.clone()),
+            _ => None,
+        };
+        for cached_resources in self.entries.values_mut() {
+            match complete_body {
+                Some(ref bytes) => {
+                    for cached_resource in cached_resources.iter_mut() {
+                        if cached_resource.is_waiting_for(body) {
+                            cached_resource.body = Arc::new(Mutex::new(ResponseBody::Done(bytes.clone())));
+                            cached_resource.pending_body = None;
+                        }
+                    }
+                },
+                None => cached_resources.retain(|cached_resource| !cached_resource.is_waiting_for(body)),
+            }
+        }
+        self.entries.retain(|_, cached_resources| !cached_resources.is_empty());
+        // Only now is the size of the new responses known.
+        self.evict();
+        self.persist();
+    }
+
+    /// Evict the least recently used responses until the cache fits in its byte budget.
+    /// Bodies which are still being received count with the bytes received so far.
+    pub fn evict(&mut self) {
+        let mut total_size = 0;
+        let mut resources = vec![];
+        for (key, cached_resources) in self.entries.iter() {
+            for cached_resource in cached_resources {
+                let size = cached_resource.size();
+                total_size += size;
+                resources.push((cached_resource.last_used, key.clone(), cached_resource.id.clone(), size));
+            }
+        }
+        if total_size <= self.max_size {
+            return;
+        }
+        resources.sort_by_key(|&(last_used, _, _, _)| last_used);
+        for (_, key, id, size) in resources {
+            if total_size <= self.max_size {
+                break;
+            }
+            self.remove(&key, &id);
+            total_size -= size;
+        }
+    }
+
+    /// Remove every stored response, from memory and from disk.
+    /// Returns once the files are removed.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.send_to_writer(DiskOperation::Clear);
+        self.sync();
+    }
+
+    /// Write every complete response to disk, along with the index describing them.
+    /// Returns once everything is written.
+    pub fn flush(&mut self) {
+        self.persist();
+        self.sync();
+    }
+
+    /// Hand the bodies not yet on disk, and the index describing every stored response,
+    /// to the writer thread. Does nothing for a cache which is not backed by a directory.
+    fn persist(&mut self) {
+        if self.writer.is_none() {
+            return;
+        }
+        let mut operations = vec![];
+        let mut stored_resources = vec![];
+        for (key, cached_resources) in self.entries.iter_mut() {
+            for cached_resource in cached_resources.iter_mut() {
+                // Only complete responses are persisted.
+                if cached_resource.pending_body.is_some() || cached_resource.varies_on_credentials() {
+                    continue;
+                }
+                if !cached_resource.persisted {
+                    let body = match *cached_resource.body.lock().unwrap() {
+                        ResponseBody::Done(ref body) => body.clone(),
+                        ResponseBody::Empty | ResponseBody::Receiving(_) => continue,
+                    };
+                    cached_resource.persisted = true;
+                    cached_resource.disk_size = body.len();
+                    operations.push(DiskOperation::WriteBody(cached_resource.id.clone(), body));
+                }
+                stored_resources.push((cached_resource.last_used, StoredResource {
+                    id: cached_resource.id.clone(),
+                    url: key.url(),
+                    request_headers: cached_resource.request_headers.clone(),
+                    final_url: cached_resource.metadata.final_url.clone(),
+                    headers: cached_resource.metadata.headers.clone(),
+                    location_url: cached_resource.location_url.clone(),
+                    https_state: cached_resource.https_state,
+                    raw_status: cached_resource.raw_status.clone(),
+                    url_list: cached_resource.url_list.clone(),
+                    response_time: cached_resource.response_time.to_timespec().sec,
+                    initial_age: cached_resource.initial_age.num_seconds(),
+                    body_size: cached_resource.disk_size,
+                }));
+            }
+        }
+        stored_resources.sort_by_key(|&(last_used, _)| last_used);
+        operations.push(DiskOperation::WriteIndex(StoredIndex {
+            version: INDEX_VERSION,
+            resources: stored_resources.into_iter().map(|(_, stored_resource)| stored_resource).collect(),
+        }));
+        for operation in operations {
+            self.send_to_writer(operation);
+        }
+    }
+
+    /// Wait for the writer thread to be done with every operation sent so far.
+    fn sync(&self) {
+        let (sender, receiver) = channel();
+        if self.send_to_writer(DiskOperation::Sync(sender)) {
+            let _ = receiver.recv();
+        }
+    }
+
+    /// Returns false if there is no writer thread, or it is gone.
+    fn send_to_writer(&self, operation: DiskOperation) -> bool {
+        match self.writer {
+            Some(ref writer) => writer.lock().unwrap().send(operation).is_ok(),
+            None => false,
+        }
+    }
+}
+
+/// The loop of the thread writing the cache to disk.
+fn run_disk_writer(directory: &Path, receiver: Receiver<DiskOperation>) {
+    while let Ok(operation) = receiver.recv() {
+        // Indexes are complete snapshots, so only the last one of a batch needs to be written.
+        let mut index = None;
+        // The bodies written since the snapshot in `index` was taken, which it doesn't know about yet.
+        let mut new_bodies = HashSet::new();
+        for operation in iter::once(operation).chain(receiver.try_iter()) {
+            match operation {
+                DiskOperation::WriteBody(id, body) => {
+                    if let Err(e) = fs::create_dir_all(directory) {
+                        warn!("couldn't create http cache at {}: {}", directory.display(), e);
+                        continue;
+                    }
+                    let path = directory.join(body_file_name(&id));
+                    if let Err(e) = write_atomically(&path, &body) {
+                        warn!("couldn't write cached body {}: {}", path.display(), e);
+                    }
+                    new_bodies.insert(body_file_name(&id));
+                },
+                DiskOperation::WriteIndex(new_index) => {
+                    index = Some(new_index);
+                    new_bodies.clear();
+                },
+                DiskOperation::Clear => {
+                    index = None;
+                    new_bodies.clear();
+                    if let Err(e) = fs::remove_dir_all(directory) {
+                        if e.kind() != io::ErrorKind::NotFound {
+                            warn!("couldn't remove http cache at {}: {}", directory.display(), e);
+                        }
+                    }
+                },
+                DiskOperation::Sync(sender) => {
+                    if let Some(index) = index.take() {
+                        write_index(directory, index, &new_bodies);
+                    }
+                    let _ = sender.send(());
+                },
+            }
+        }
+        if let Some(index) = index {
+            write_index(directory, index, &new_bodies);
+        }
+    }
+}
+
+/// Write the index of the cache, then remove the bodies it no longer refers to,
+/// except for `new_bodies`, which a later index will refer to.
+fn write_index(directory: &Path, index: StoredIndex, new_bodies: &HashSet<String>) {
+    if let Err(e) = fs::create_dir_all(directory) {
+        warn!("couldn't create http cache at {}: {}", directory.display(), e);
+        return;
+    }
+    let json = match serde_json::to_vec(&index) {
+        Ok(json) => json,
+        Err(e) => {
+            warn!("couldn't serialize http cache index: {}", e);
+            return;
+        },
+    };
+    let index_path = directory.join(INDEX_FILE);
+    if let Err(e) = write_atomically(&index_path, &json) {
+        warn!("couldn't write http cache index {}: {}", index_path.display(), e);
+        return;
+    }
+    // Only remove stale bodies once the index no longer refers to them.
+    let mut live_files: HashSet<String> = index.resources.iter()
+        .map(|stored_resource| body_file_name(&stored_resource.id))
+        .collect();
+    live_files.extend(new_bodies.iter().cloned());
+    remove_stale_files(directory, &live_files);
+}
+
+/// Remove every file in the cache directory which is neither the index
+/// nor the body of a live resource, including leftovers of interrupted writes.
+fn remove_stale_files(directory: &Path, live_files: &HashSet<String>) {
+    let dir_entries = match fs::read_dir(directory) {
+        Ok(dir_entries) => dir_entries,
+        Err(_) => return,
+    };
+    for dir_entry in dir_entries {
+        let dir_entry = match dir_entry {
+            Ok(dir_entry) => dir_entry,
+            Err(_) => continue,
+        };
+        let file_name = dir_entry.file_name().to_string_lossy().into_owned();
+        if file_name == INDEX_FILE || live_files.contains(&file_name) {
+            continue;
+        }
+        if let Err(e) = fs::remove_file(dir_entry.path()) {
+            warn!("couldn't remove stale http cache file {}: {}", file_name, e);
+        }
+    }
+}
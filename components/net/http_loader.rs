@@ -3,16 +3,19 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use brotli::Decompressor;
-use connector::{Connector, create_http_connector};
+use connector::{Connector, Proxy, ProxyAuthCache, ProxyConfig, ProxyConnector};
+use connector::{create_http_connector, create_proxy_connector, take_connection_info};
+use connector::take_tunnel_needed_credentials;
 use cookie;
 use cookie_storage::CookieStorage;
 use devtools_traits::{ChromeToDevtoolsControlMsg, DevtoolsControlMsg, HttpRequest as DevtoolsHttpRequest};
-use devtools_traits::{HttpResponse as DevtoolsHttpResponse, NetworkEvent};
+use devtools_traits::{HttpResponse as DevtoolsHttpResponse, HttpTimings, NetworkEvent, SecurityInfo};
 use fetch::cors_cache::CorsCache;
 use fetch::methods::{Data, DoneChannel, FetchContext, Target};
 use fetch::methods::{is_cors_safelisted_request_header, is_cors_safelisted_method, main_fetch};
 use flate2::read::{DeflateDecoder, GzDecoder};
 use hsts::HstsList;
+use http_cache::{self, HttpCache};
 use hyper::Error as HttpError;
 use hyper::LanguageTag;
 use hyper::client::{Pool, Request as HyperRequest, Response as HyperResponse};
@@ -26,7 +29,10 @@ use hyper::header::{Host, Origin as HyperOrigin, IfMatch, IfRange};
 use hyper::header::{IfUnmodifiedSince, IfModifiedSince, IfNoneMatch, Location};
 use hyper::header::{Pragma, Quality, QualityItem, Referer, SetCookie};
 use hyper::header::{UserAgent, q, qitem};
+use hyper::http::HttpMessage;
+use hyper::http::h1::Http11Message;
 use hyper::method::Method;
+use hyper::net::{Fresh, NetworkConnector};
 use hyper::status::StatusCode;
 use hyper_openssl::OpensslClient;
 use hyper_serde::Serde;
@@ -37,19 +43,23 @@ use net_traits::request::{CacheMode, CredentialsMode, Destination, Origin};
 use net_traits::request::{RedirectMode, Referrer, Request, RequestMode};
 use net_traits::request::{ResponseTainting, ServiceWorkersMode};
 use net_traits::response::{HttpsState, Response, ResponseBody, ResponseType};
-use resource_thread::AuthCache;
+use resource_thread::{AuthCache, AuthCacheEntry};
+use servo_config::opts;
 use servo_url::{ImmutableOrigin, ServoUrl};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::io::{self, Read, Write};
 use std::iter::FromIterator;
 use std::mem;
 use std::ops::Deref;
-use std::sync::RwLock;
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::Ordering;
 use std::sync::mpsc::{channel, Sender};
 use std::thread;
 use time;
 use time::Tm;
+#[cfg(any(target_os = "macos", target_os = "linux", target_os = "windows"))]
+use tinyfiledialogs;
 use unicase::UniCase;
 use uuid;
 
@@ -70,23 +80,84 @@ pub struct HttpState {
     pub hsts_list: RwLock<HstsList>,
     pub cookie_jar: RwLock<CookieStorage>,
     pub auth_cache: RwLock<AuthCache>,
+    pub http_cache: RwLock<HttpCache>,
+    pub proxy_config: RwLock<ProxyConfig>,
+    /// The credentials the user gave to proxies, shared with the connectors in `proxy_connectors`.
+    pub proxy_auth_cache: Arc<RwLock<ProxyAuthCache>>,
+    /// The pool of connections through each proxy used so far, along with that proxy.
+    pub proxy_connectors: Mutex<HashMap<String, (Proxy, Arc<Pool<ProxyConnector>>)>>,
+    pub proxy_credentials_prompt: &'static ProxyCredentialsPrompt,
     pub ssl_client: OpensslClient,
     pub connector: Pool<Connector>,
 }
 
 impl HttpState {
-    pub fn new(ssl_client: OpensslClient) -> HttpState {
+    pub fn new(ssl_client: OpensslClient,
+               proxy_config: ProxyConfig,
+               proxy_credentials_prompt: &'static ProxyCredentialsPrompt)
+               -> HttpState {
         HttpState {
             hsts_list: RwLock::new(HstsList::new()),
             cookie_jar: RwLock::new(CookieStorage::new(150)),
             auth_cache: RwLock::new(AuthCache::new()),
+            http_cache: RwLock::new(HttpCache::new()),
+            proxy_config: RwLock::new(proxy_config),
+            proxy_auth_cache: Arc::new(RwLock::new(ProxyAuthCache::new())),
+            proxy_connectors: Mutex::new(HashMap::new()),
+            proxy_credentials_prompt: proxy_credentials_prompt,
             ssl_client: ssl_client.clone(),
             connector: create_http_connector(ssl_client),
         }
     }
+
+    /// The pool of connections through `proxy`, created the first time it is used.
+    fn proxy_connector(&self, proxy: &Proxy) -> Arc<Pool<ProxyConnector>> {
+        let mut proxy_connectors = self.proxy_connectors.lock().unwrap();
+        if let Some(&(ref pooled_proxy, ref connector)) = proxy_connectors.get(&proxy.key()) {
+            // The credentials in the proxy settings may have changed.
+            if pooled_proxy == proxy {
+                return connector.clone();
+            }
+        }
+        let connector = Arc::new(create_proxy_connector(proxy.clone(),
+                                                        self.proxy_auth_cache.clone(),
+                                                        self.ssl_client.clone()));
+        proxy_connectors.insert(proxy.key(), (proxy.clone(), connector.clone()));
+        connector
+    }
+}
+
+/// The provider of the dialog asking the user for the credentials of a proxy.
+/// For example, tests can answer without showing any UI.
+pub trait ProxyCredentialsPrompt where Self: Sync {
+    fn prompt_for_credentials(&self, proxy: &Proxy) -> Option<AuthCacheEntry>;
 }
 
-fn precise_time_ms() -> u64 {
+pub struct TFDProxyCredentialsPrompt;
+
+impl ProxyCredentialsPrompt for TFDProxyCredentialsPrompt {
+    #[cfg(any(target_os = "macos", target_os = "linux", target_os = "windows"))]
+    fn prompt_for_credentials(&self, proxy: &Proxy) -> Option<AuthCacheEntry> {
+        if opts::get().headless {
+            return None;
+        }
+
+        let message = format!("The proxy {}:{} requires a user name and password.", proxy.host, proxy.port);
+        let user_name = tinyfiledialogs::input_box("Proxy authentication", &message, "")?;
+        let password = tinyfiledialogs::password_box("Proxy authentication", "Password:")?;
+        Some(AuthCacheEntry {
+            user_name: user_name,
+            password: password,
+        })
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+    fn prompt_for_credentials(&self, _proxy: &Proxy) -> Option<AuthCacheEntry> {
+        None
+    }
+}
+
+pub fn precise_time_ms() -> u64 {
     time::precise_time_ns() / (1000 * 1000)
 }
 
@@ -374,14 +445,62 @@ fn send_response_to_devtools(devtools_chan: &Sender<DevtoolsControlMsg>,
                              request_id: String,
                              headers: Option<Headers>,
                              status: Option<(u16, Vec<u8>)>,
-                             pipeline_id: PipelineId) {
-    let response = DevtoolsHttpResponse { headers: headers, status: status, body: None, pipeline_id: pipeline_id };
-    let net_event_response = NetworkEvent::HttpResponse(response);
+                             pipeline_id: PipelineId,
+                             security_info: Option<SecurityInfo>,
+                             from_cache: bool) {
+    let response = DevtoolsHttpResponse {
+        headers: headers,
+        status: status,
+        body: None,
+        pipeline_id: pipeline_id,
+        security_info: security_info,
+        from_cache: from_cache,
+    };
+    send_network_event_to_devtools(devtools_chan, request_id, NetworkEvent::HttpResponse(response));
+}
 
-    let msg = ChromeToDevtoolsControlMsg::NetworkEvent(request_id, net_event_response);
+/// Tell devtools about a request that was answered from the HTTP cache, as if the whole
+/// response had been received at once.
+fn send_cached_response_to_devtools(context: &FetchContext, request: &Request, response: &Response) {
+    let (devtools_chan, pipeline_id) = match (context.devtools_chan.as_ref(), request.pipeline_id) {
+        (Some(devtools_chan), Some(pipeline_id)) => (devtools_chan, pipeline_id),
+        _ => return,
+    };
+    let request_id = uuid::Uuid::new_v4().simple().to_string();
+    let is_xhr = request.destination == Destination::None;
+    let msg = prepare_devtools_request(request_id.clone(), request.current_url(), request.method.clone(),
+                                       request.headers.clone(), request.body.clone(), pipeline_id,
+                                       time::now(), 0, 0, is_xhr);
+    send_request_to_devtools(msg, devtools_chan);
+    send_response_to_devtools(devtools_chan, request_id.clone(), Some(response.headers.clone()),
+                              response.raw_status.clone(), pipeline_id, None, true);
+    if context.network_listeners.any() {
+        if let ResponseBody::Done(ref body) = *response.body.lock().unwrap() {
+            send_network_event_to_devtools(devtools_chan, request_id.clone(),
+                                           NetworkEvent::ResponseBodyChunk(pipeline_id, body.clone()));
+        }
+    }
+    send_network_event_to_devtools(devtools_chan, request_id,
+                                   NetworkEvent::ResponseEnd(pipeline_id, HttpTimings::default()));
+}
+
+fn send_network_event_to_devtools(devtools_chan: &Sender<DevtoolsControlMsg>,
+                                  request_id: String,
+                                  net_event: NetworkEvent) {
+    let msg = ChromeToDevtoolsControlMsg::NetworkEvent(request_id, net_event);
     let _ = devtools_chan.send(DevtoolsControlMsg::FromChrome(msg));
 }
 
+/// What devtools are told about a request once its response arrives.
+struct DevtoolsRequestInfo {
+    request: ChromeToDevtoolsControlMsg,
+    pipeline_id: PipelineId,
+    /// The timings of the request, but for the time it takes to receive the body.
+    timings: HttpTimings,
+    /// The parameters of the TLS session, if the request opened a secure connection.
+    security_info: Option<SecurityInfo>,
+}
+
 fn auth_from_cache(auth_cache: &RwLock<AuthCache>, origin: &ImmutableOrigin) -> Option<Basic> {
     if let Some(ref auth_entry) = auth_cache.read().unwrap().entries.get(&origin.ascii_serialization()) {
         let user_name = auth_entry.user_name.clone();
@@ -392,7 +511,23 @@ fn auth_from_cache(auth_cache: &RwLock<AuthCache>, origin: &ImmutableOrigin) ->
     }
 }
 
+/// Create a request which is sent through `proxy_connector`.
+fn proxied_request(method: &Method,
+                   url: &ServoUrl,
+                   proxy_connector: &Pool<ProxyConnector>)
+                   -> Result<HyperRequest<Fresh>, HttpError> {
+    let host = url.host_str().unwrap_or("");
+    let port = url.port_or_known_default().unwrap_or(80);
+    let stream = proxy_connector.connect(host, port, url.scheme())?;
+    let mut message = Http11Message::with_stream(Box::new(stream));
+    // Requests which aren't tunnelled are sent to the proxy itself,
+    // and name their whole URL as request target.
+    message.set_proxied(url.scheme() == "http");
+    HyperRequest::with_message(method.clone(), url.clone().into_url(), Box::new(message))
+}
+
 fn obtain_response(connector: &Pool<Connector>,
+                   proxy_connector: Option<&Pool<ProxyConnector>>,
                    url: &ServoUrl,
                    method: &Method,
                    request_headers: &Headers,
@@ -402,7 +537,7 @@ fn obtain_response(connector: &Pool<Connector>,
                    iters: u32,
                    request_id: Option<&str>,
                    is_xhr: bool)
-                   -> Result<(WrappedHttpResponse, Option<ChromeToDevtoolsControlMsg>), NetworkError> {
+                   -> Result<(WrappedHttpResponse, Option<DevtoolsRequestInfo>), NetworkError> {
     let null_data = None;
 
     // loop trying connections in connection pool
@@ -441,11 +576,17 @@ fn obtain_response(connector: &Pool<Connector>,
             info!("{:?}", data);
         }
 
+        // Forget about connections that were opened for earlier requests of this thread.
+        take_connection_info();
+
         let connect_start = precise_time_ms();
 
-        let request = HyperRequest::with_connector(method.clone(),
-                                                   url.clone().into_url(),
-                                                   &*connector);
+        let request = match proxy_connector {
+            Some(proxy_connector) => proxied_request(method, url, proxy_connector),
+            None => HyperRequest::with_connector(method.clone(),
+                                                 url.clone().into_url(),
+                                                 &*connector),
+        };
         let mut request = match request {
             Ok(request) => request,
             Err(e) => return Err(NetworkError::from_hyper_error(&url, e)),
@@ -453,6 +594,7 @@ fn obtain_response(connector: &Pool<Connector>,
         *request.headers_mut() = headers.clone();
 
         let connect_end = precise_time_ms();
+        let connection_info = take_connection_info().unwrap_or_default();
 
         let send_start = precise_time_ms();
 
@@ -467,6 +609,8 @@ fn obtain_response(connector: &Pool<Connector>,
             }
         }
 
+        let send_end = precise_time_ms();
+
         let response = match request_writer.send() {
             Ok(w) => w,
             Err(HttpError::Io(ref io_error))
@@ -478,15 +622,30 @@ fn obtain_response(connector: &Pool<Connector>,
             Err(e) => return Err(NetworkError::Internal(e.description().to_owned())),
         };
 
-        let send_end = precise_time_ms();
+        let wait_end = precise_time_ms();
 
-        let msg = if let Some(request_id) = request_id {
+        let devtools_info = if let Some(request_id) = request_id {
             if let Some(pipeline_id) = *pipeline_id {
-                Some(prepare_devtools_request(
-                    request_id.into(),
-                    url.clone(), method.clone(), headers,
-                    request_body.clone(), pipeline_id, time::now(),
-                    connect_end - connect_start, send_end - send_start, is_xhr))
+                let connection_time = connection_info.dns_time + connection_info.connect_time +
+                                      connection_info.tls_time;
+                Some(DevtoolsRequestInfo {
+                    request: prepare_devtools_request(
+                        request_id.into(),
+                        url.clone(), method.clone(), headers,
+                        request_body.clone(), pipeline_id, time::now(),
+                        connect_end - connect_start, send_end - send_start, is_xhr),
+                    pipeline_id: pipeline_id,
+                    timings: HttpTimings {
+                        blocked: (connect_end - connect_start).saturating_sub(connection_time),
+                        dns: connection_info.dns_time,
+                        connect: connection_info.connect_time,
+                        tls: connection_info.tls_time,
+                        send: send_end - send_start,
+                        wait: wait_end - send_end,
+                        receive: 0,
+                    },
+                    security_info: connection_info.security_info,
+                })
             } else {
                 debug!("Not notifying devtools (no pipeline_id)");
                 None
@@ -496,7 +655,7 @@ fn obtain_response(connector: &Pool<Connector>,
             None
         };
 
-        return Ok((WrappedHttpResponse { response: response }, msg));
+        return Ok((WrappedHttpResponse { response: response }, devtools_info));
     }
 }
 
@@ -886,7 +1045,16 @@ fn http_network_or_cache_fetch(request: &mut Request,
     }
 
     // Step 18
-    // TODO If there’s a proxy-authentication entry, use it as appropriate.
+    // Requests tunnelled through CONNECT send the credentials with the CONNECT
+    // request instead, so that they never reach the origin; see http_network_fetch.
+    if current_url.scheme() == "http" {
+        let proxy_authorization = context.state.proxy_config.read().unwrap()
+            .proxy_for_url(&current_url)
+            .and_then(|proxy| context.state.proxy_auth_cache.read().unwrap().authorization(proxy));
+        if let Some(proxy_authorization) = proxy_authorization {
+            http_request.headers.set_raw("Proxy-Authorization", vec![proxy_authorization.into_bytes()]);
+        }
+    }
 
     // Step 19
     let mut response: Option<Response> = None;
@@ -895,34 +1063,33 @@ fn http_network_or_cache_fetch(request: &mut Request,
     let mut revalidating_flag = false;
 
     // Step 21
-    // TODO have a HTTP cache to check for a completed response
-    let complete_http_response_from_cache: Option<Response> = None;
     if http_request.cache_mode != CacheMode::NoStore &&
-       http_request.cache_mode != CacheMode::Reload &&
-       complete_http_response_from_cache.is_some() {
-        // TODO Substep 1 and 2. Select a response from HTTP cache.
-
-        // Substep 3
-        if let Some(ref response) = response {
-            revalidating_flag = response_needs_revalidation(&response);
-        };
+       http_request.cache_mode != CacheMode::Reload {
+        // Substep 1 and 2. Select a response from HTTP cache.
+        let cached_response = HttpCache::construct_response(&context.state.http_cache, &http_request);
+        if let Some(cached_response) = cached_response {
+            // Substep 3
+            revalidating_flag = cached_response.needs_validation ||
+                                http_request.cache_mode == CacheMode::NoCache;
 
-        // Substep 4
-        if http_request.cache_mode == CacheMode::ForceCache ||
-           http_request.cache_mode == CacheMode::OnlyIfCached {
-            // TODO pull response from HTTP cache
-            // response = http_request
-        }
+            // Substep 4
+            if http_request.cache_mode == CacheMode::ForceCache ||
+               http_request.cache_mode == CacheMode::OnlyIfCached {
+                revalidating_flag = false;
+            }
 
-        if revalidating_flag {
-            // Substep 5
-            // TODO set If-None-Match and If-Modified-Since according to cached
-            //      response headers.
-        } else {
-            // Substep 6
-            // TODO pull response from HTTP cache
-            // response = http_request
-            // response.cache_state = CacheState::Local;
+            if revalidating_flag {
+                // Substep 5
+                if !http_cache::set_validators(&cached_response.response, &mut http_request.headers) {
+                    // A stored response without validators can't be revalidated,
+                    // so the resource is fetched again in full.
+                    revalidating_flag = false;
+                }
+            } else {
+                // Substep 6
+                send_cached_response_to_devtools(context, &http_request, &cached_response.response);
+                response = Some(cached_response.response);
+            }
         }
     }
 
@@ -934,25 +1101,39 @@ fn http_network_or_cache_fetch(request: &mut Request,
                 NetworkError::Internal("Couldn't find response in cache".into()))
         }
         // Substep 2
+        let request_time = time::now_utc();
         let forward_response = http_network_fetch(http_request, credentials_flag,
                                                   done_chan, context);
         // Substep 3
         if let Some((200...399, _)) = forward_response.raw_status {
             if !http_request.method.safe() {
-                // TODO Invalidate HTTP cache response
+                if let Ok(mut http_cache) = context.state.http_cache.write() {
+                    http_cache.invalidate(&http_request, &forward_response);
+                }
             }
         }
         // Substep 4
         if revalidating_flag && forward_response.status.map_or(false, |s| s == StatusCode::NotModified) {
-            // TODO update forward_response headers with cached response headers
+            if let Ok(mut http_cache) = context.state.http_cache.write() {
+                response = http_cache.refresh(&http_request, &forward_response, request_time);
+            }
+            if response.is_some() {
+                // The stored body replaces the empty body of the 304 response,
+                // so there is nothing to wait for on the network.
+                *done_chan = None;
+            }
         }
 
         // Substep 5
         if response.is_none() {
+            // Subsubstep 2
+            if http_request.cache_mode != CacheMode::NoStore {
+                if let Ok(mut http_cache) = context.state.http_cache.write() {
+                    http_cache.store(&http_request, &forward_response, request_time);
+                }
+            }
             // Subsubstep 1
             response = Some(forward_response);
-            // Subsubstep 2
-            // TODO: store http_request and forward_response in cache
         }
     }
 
@@ -988,7 +1169,8 @@ fn http_network_or_cache_fetch(request: &mut Request,
     // Step 24
     if let Some(StatusCode::ProxyAuthenticationRequired) = response.status {
         // Step 1
-        if request_has_no_window {
+        // Requests without a pipeline, such as those of the browser itself, have no window.
+        if http_request.pipeline_id.is_none() {
             return Response::network_error(NetworkError::Internal("Can't find Window object".into()));
         }
 
@@ -996,16 +1178,21 @@ fn http_network_or_cache_fetch(request: &mut Request,
         // TODO: Spec says requires testing on Proxy-Authenticate headers
 
         // Step 3
-        // FIXME: Prompt the user for proxy authentication credentials
-
-        // Wrong, but will have to do until we are able to prompt the user
-        // otherwise this creates an infinite loop
-        // We basically pretend that the user declined to enter credentials
-        return response;
+        let proxy = context.state.proxy_config.read().unwrap().proxy_for_url(&current_url).cloned();
+        let proxy = match proxy {
+            Some(proxy) => proxy,
+            // Not from a proxy we know of, so there is nothing to authenticate to.
+            None => return response,
+        };
+        match context.state.proxy_credentials_prompt.prompt_for_credentials(&proxy) {
+            Some(credentials) => context.state.proxy_auth_cache.write().unwrap().insert(&proxy, credentials),
+            // The user declined to enter credentials.
+            None => return response,
+        }
 
         // Step 4
-        // return http_network_or_cache_fetch(request, authentication_fetch_flag,
-        //                                    cors_flag, done_chan, context);
+        return http_network_or_cache_fetch(http_request, authentication_fetch_flag,
+                                           cors_flag, done_chan, context);
     }
 
     // Step 25
@@ -1046,7 +1233,13 @@ fn http_network_fetch(request: &Request,
     // do not. Once we support other kinds of fetches we'll need to be more fine grained here
     // since things like image fetches are classified differently by devtools
     let is_xhr = request.destination == Destination::None;
+    let proxy_connector = context.state.proxy_config.read().unwrap().proxy_for_url(&url).map(|proxy| {
+        context.state.proxy_connector(proxy)
+    });
+    // Forget about tunnels that were refused to earlier requests of this thread.
+    take_tunnel_needed_credentials();
     let wrapped_response = obtain_response(&context.state.connector,
+                                           proxy_connector.as_ref().map(Deref::deref),
                                            &url,
                                            &request.method,
                                            &request.headers,
@@ -1054,9 +1247,16 @@ fn http_network_fetch(request: &Request,
                                            &request.pipeline_id, request.redirect_count + 1,
                                            request_id.as_ref().map(Deref::deref), is_xhr);
 
-    let pipeline_id = request.pipeline_id;
-    let (res, msg) = match wrapped_response {
+    let (res, devtools_info) = match wrapped_response {
         Ok(wrapped_response) => wrapped_response,
+        Err(_) if take_tunnel_needed_credentials() => {
+            // The proxy wants credentials before opening a tunnel, which is handled
+            // like the same answer from a proxy that requests are sent to directly.
+            let mut response = Response::new(url.clone());
+            response.status = Some(StatusCode::ProxyAuthenticationRequired);
+            response.raw_status = Some((407, b"Proxy Authentication Required".to_vec()));
+            return response;
+        },
         Err(error) => return Response::network_error(error),
     };
 
@@ -1076,6 +1276,8 @@ fn http_network_fetch(request: &Request,
     response.referrer_policy = request.referrer_policy.clone();
 
     let res_body = response.body.clone();
+    let res_aborted = response.aborted.clone();
+    let http_state = context.state.clone();
 
     // We're about to spawn a thread to be waited on here
     let (done_sender, done_receiver) = channel();
@@ -1085,38 +1287,62 @@ fn http_network_fetch(request: &Request,
         FetchMetadata::Filtered { unsafe_, .. } => unsafe_
     };
     let devtools_sender = context.devtools_chan.clone();
+    let network_listeners = context.network_listeners.clone();
     let meta_status = meta.status.clone();
     let meta_headers = meta.headers.clone();
     thread::Builder::new().name(format!("fetch worker thread")).spawn(move || {
         match StreamedResponse::from_http_response(res) {
             Ok(mut res) => {
                 *res_body.lock().unwrap() = ResponseBody::Receiving(vec![]);
-
-                if let Some(ref sender) = devtools_sender {
-                    if let Some(m) = msg {
-                        send_request_to_devtools(m, &sender);
-                    }
-
-                    // --- Tell devtools that we got a response
-                    // Send an HttpResponse message to devtools with the corresponding request_id
-                    if let Some(pipeline_id) = pipeline_id {
+                let receive_start = precise_time_ms();
+
+                // Tell devtools about the request, and that we got a response to it. They
+                // are then sent the body as it is received.
+                let devtools = match (devtools_sender, devtools_info) {
+                    (Some(sender), Some(info)) => {
+                        let request_id = request_id.unwrap();
+                        let pipeline_id = info.pipeline_id;
+                        send_request_to_devtools(info.request, &sender);
                         send_response_to_devtools(
-                            &sender, request_id.unwrap(),
+                            &sender, request_id.clone(),
                             meta_headers.map(Serde::into_inner),
                             meta_status,
-                            pipeline_id);
+                            pipeline_id,
+                            info.security_info,
+                            false);
+                        Some((sender, request_id, pipeline_id, info.timings))
                     }
-                }
+                    _ => None,
+                };
 
                 loop {
                     match read_block(&mut res) {
                         Ok(Data::Payload(chunk)) => {
                             if let ResponseBody::Receiving(ref mut body) = *res_body.lock().unwrap() {
                                 body.extend_from_slice(&chunk);
+                                // Only copy the body when a client is there to look at it.
+                                if let Some((ref sender, ref request_id, pipeline_id, _)) = devtools {
+                                    if network_listeners.any() {
+                                        send_network_event_to_devtools(
+                                            sender, request_id.clone(),
+                                            NetworkEvent::ResponseBodyChunk(pipeline_id, chunk.clone()));
+                                    }
+                                }
                                 let _ = done_sender.send(Data::Payload(chunk));
                             }
                         },
-                        Ok(Data::Done) | Err(_) => {
+                        end => {
+                            if end.is_err() {
+                                res_aborted.store(true, Ordering::SeqCst);
+                            }
+                            if let Some((ref sender, ref request_id, pipeline_id, ref timings)) = devtools {
+                                let timings = HttpTimings {
+                                    receive: precise_time_ms() - receive_start,
+                                    .. timings.clone()
+                                };
+                                send_network_event_to_devtools(sender, request_id.clone(),
+                                                               NetworkEvent::ResponseEnd(pipeline_id, timings));
+                            }
                             let mut body = res_body.lock().unwrap();
                             let completed_body = match *body {
                                 ResponseBody::Receiving(ref mut body) => {
@@ -1125,6 +1351,11 @@ fn http_network_fetch(request: &Request,
                                 _ => vec![],
                             };
                             *body = ResponseBody::Done(completed_body);
+                            drop(body);
+                            // The cache may be waiting for the body before it can use the response.
+                            if let Ok(mut http_cache) = http_state.http_cache.write() {
+                                http_cache.body_finished(&res_body, end.is_err());
+                            }
                             let _ = done_sender.send(Data::Done);
                             break;
                         }
@@ -1133,7 +1364,11 @@ fn http_network_fetch(request: &Request,
             }
             Err(_) => {
                 // XXXManishearth we should propagate this error somehow
+                res_aborted.store(true, Ordering::SeqCst);
                 *res_body.lock().unwrap() = ResponseBody::Done(vec![]);
+                if let Ok(mut http_cache) = http_state.http_cache.write() {
+                    http_cache.body_finished(&res_body, true);
+                }
                 let _ = done_sender.send(Data::Done);
             }
         }
@@ -1169,9 +1404,8 @@ fn http_network_fetch(request: &Request,
     // TODO this step isn't possible yet (CSP)
 
     // Step 14
-    if !response.is_network_error() && request.cache_mode != CacheMode::NoStore {
-        // TODO update response in the HTTP cache for request
-    }
+    // The response is stored in the HTTP cache by http_network_or_cache_fetch,
+    // which also has the stored response needed to handle a 304.
 
     // TODO this step isn't possible yet
     // Step 15
@@ -1368,11 +1602,6 @@ fn is_no_store_cache(headers: &Headers) -> bool {
     headers.has::<IfRange>()
 }
 
-fn response_needs_revalidation(_response: &Response) -> bool {
-    // TODO this function
-    false
-}
-
 /// <https://fetch.spec.whatwg.org/#redirect-status>
 pub fn is_redirect_status(status: StatusCode) -> bool {
     match status {
@@ -0,0 +1,1075 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The IndexedDB storage thread.
+//!
+//! Databases are kept in memory, and each origin's databases are written to their own file
+//! under `<config dir>/indexeddb` whenever a transaction that modified them commits.
+//! Transactions on a database are run in creation order; read-only transactions may run
+//! alongside each other, anything else runs on its own. Requests to open a database wait in
+//! the same queue as its transactions, so that the version they see is the one they upgrade.
+//! A transaction that can modify the database keeps a copy of it to revert to if it is aborted.
+
+use base64;
+use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
+use net_traits::indexeddb_thread::{DatabaseInfo, IndexInfo, IndexedDBConnectionEvent, IndexedDBCursorDirection};
+use net_traits::indexeddb_thread::{IndexedDBError, IndexedDBKey, IndexedDBKeyRange, IndexedDBOpenEvent};
+use net_traits::indexeddb_thread::{IndexedDBOperation, IndexedDBRecord, IndexedDBReply, IndexedDBResult};
+use net_traits::indexeddb_thread::{IndexedDBSource, IndexedDBThreadMsg, IndexedDBTransactionMode, ObjectStoreInfo};
+use resource_thread;
+use serde_json;
+use servo_url::ImmutableOrigin;
+use std::borrow::ToOwned;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::collections::Bound;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::thread;
+
+/// <https://w3c.github.io/IndexedDB/#key-generator-construct>
+const MAX_GENERATED_KEY: u64 = 1 << 53;
+
+pub trait IndexedDBThreadFactory {
+    fn new(config_dir: Option<PathBuf>) -> Self;
+}
+
+impl IndexedDBThreadFactory for IpcSender<IndexedDBThreadMsg> {
+    /// Create an IndexedDB storage thread
+    fn new(config_dir: Option<PathBuf>) -> IpcSender<IndexedDBThreadMsg> {
+        let (chan, port) = ipc::channel().unwrap();
+        thread::Builder::new().name("IndexedDBManager".to_owned()).spawn(move || {
+            IndexedDBManager::new(port, config_dir).start();
+        }).expect("Thread spawning failed");
+        chan
+    }
+}
+
+#[derive(Clone)]
+struct Record {
+    value: Vec<u8>,
+    /// The keys of this record in each index of its object store
+    index_keys: HashMap<String, Vec<IndexedDBKey>>,
+}
+
+/// The primary keys of the records of an index, by index key
+type Index = BTreeMap<IndexedDBKey, BTreeSet<IndexedDBKey>>;
+
+#[derive(Clone)]
+struct ObjectStore {
+    info: ObjectStoreInfo,
+    /// The next key handed out by the key generator
+    current_number: u64,
+    records: BTreeMap<IndexedDBKey, Record>,
+    indexes: HashMap<String, Index>,
+}
+
+impl ObjectStore {
+    fn new(info: ObjectStoreInfo) -> ObjectStore {
+        let indexes = info.indexes.iter().map(|index| (index.name.clone(), Index::new())).collect();
+        ObjectStore {
+            info: info,
+            current_number: 1,
+            records: BTreeMap::new(),
+            indexes: indexes,
+        }
+    }
+
+    fn index_info(&self, name: &str) -> Option<&IndexInfo> {
+        self.info.indexes.iter().find(|index| index.name == name)
+    }
+
+    fn insert_record(&mut self, key: IndexedDBKey, record: Record) {
+        self.remove_record(&key);
+        for (name, keys) in &record.index_keys {
+            if let Some(index) = self.indexes.get_mut(name) {
+                for index_key in keys {
+                    index.entry(index_key.clone()).or_insert_with(BTreeSet::new).insert(key.clone());
+                }
+            }
+        }
+        self.records.insert(key, record);
+    }
+
+    fn remove_record(&mut self, key: &IndexedDBKey) -> bool {
+        let record = match self.records.remove(key) {
+            Some(record) => record,
+            None => return false,
+        };
+        for (name, keys) in record.index_keys {
+            if let Some(index) = self.indexes.get_mut(&name) {
+                for index_key in keys {
+                    let now_empty = match index.get_mut(&index_key) {
+                        Some(primary_keys) => {
+                            primary_keys.remove(key);
+                            primary_keys.is_empty()
+                        },
+                        None => false,
+                    };
+                    if now_empty {
+                        index.remove(&index_key);
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Whether storing `keys` in the unique index `name` for the record `primary_key` would
+    /// clash with another record.
+    fn violates_unique_index(&self, name: &str, keys: &[IndexedDBKey], primary_key: &IndexedDBKey) -> bool {
+        let unique = self.index_info(name).map_or(false, |index| index.unique);
+        let index = match self.indexes.get(name) {
+            Some(index) if unique => index,
+            _ => return false,
+        };
+        keys.iter().any(|key| {
+            index.get(key).map_or(false, |primary_keys| primary_keys.iter().any(|other| other != primary_key))
+        })
+    }
+
+    /// The (key, primary key) pairs of a source, in ascending order
+    fn entries(&self, index: Option<&str>, range: &IndexedDBKeyRange) -> Vec<(IndexedDBKey, IndexedDBKey)> {
+        match index {
+            Some(name) => {
+                self.indexes.get(name).map_or(vec![], |index| {
+                    index.range(bounds(range)).flat_map(|(key, primary_keys)| {
+                        primary_keys.iter().map(move |primary_key| (key.clone(), primary_key.clone()))
+                    }).collect()
+                })
+            },
+            None => {
+                self.records.range(bounds(range)).map(|(key, _)| (key.clone(), key.clone())).collect()
+            },
+        }
+    }
+
+    fn record(&self, key: IndexedDBKey, primary_key: IndexedDBKey) -> Option<IndexedDBRecord> {
+        let value = match self.records.get(&primary_key) {
+            Some(record) => record.value.clone(),
+            None => return None,
+        };
+        Some(IndexedDBRecord {
+            key: key,
+            primary_key: primary_key,
+            value: value,
+        })
+    }
+}
+
+fn bounds(range: &IndexedDBKeyRange) -> (Bound<&IndexedDBKey>, Bound<&IndexedDBKey>) {
+    let lower = match range.lower {
+        Some(ref key) if range.lower_open => Bound::Excluded(key),
+        Some(ref key) => Bound::Included(key),
+        None => Bound::Unbounded,
+    };
+    let upper = match range.upper {
+        Some(ref key) if range.upper_open => Bound::Excluded(key),
+        Some(ref key) => Bound::Included(key),
+        None => Bound::Unbounded,
+    };
+    (lower, upper)
+}
+
+#[derive(Clone)]
+struct Database {
+    version: u64,
+    object_stores: BTreeMap<String, ObjectStore>,
+}
+
+impl Database {
+    fn info(&self) -> DatabaseInfo {
+        DatabaseInfo {
+            version: self.version,
+            object_stores: self.object_stores.values().map(|store| store.info.clone()).collect(),
+        }
+    }
+}
+
+/// The on-disk representation of the databases of an origin.
+#[derive(Default, Deserialize, Serialize)]
+struct StoredOrigin {
+    databases: Vec<StoredDatabase>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct StoredDatabase {
+    name: String,
+    version: u64,
+    object_stores: Vec<StoredObjectStore>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct StoredObjectStore {
+    info: ObjectStoreInfo,
+    current_number: u64,
+    /// (primary key, base64 encoded value, index keys)
+    records: Vec<(IndexedDBKey, String, Vec<(String, Vec<IndexedDBKey>)>)>,
+}
+
+impl StoredOrigin {
+    fn from_databases(databases: &HashMap<String, Database>) -> StoredOrigin {
+        StoredOrigin {
+            databases: databases.iter().map(|(name, database)| StoredDatabase {
+                name: name.clone(),
+                version: database.version,
+                object_stores: database.object_stores.values().map(|store| StoredObjectStore {
+                    info: store.info.clone(),
+                    current_number: store.current_number,
+                    records: store.records.iter().map(|(key, record)| {
+                        (key.clone(),
+                         base64::encode(&record.value),
+                         record.index_keys.iter().map(|(name, keys)| (name.clone(), keys.clone())).collect())
+                    }).collect(),
+                }).collect(),
+            }).collect(),
+        }
+    }
+
+    fn into_databases(self) -> HashMap<String, Database> {
+        self.databases.into_iter().map(|stored| {
+            let object_stores = stored.object_stores.into_iter().map(|stored_store| {
+                let mut store = ObjectStore::new(stored_store.info);
+                store.current_number = stored_store.current_number;
+                for (key, value, index_keys) in stored_store.records {
+                    let value = match base64::decode(&value) {
+                        Ok(value) => value,
+                        Err(_) => {
+                            warn!("Dropping corrupted IndexedDB record");
+                            continue;
+                        }
+                    };
+                    store.insert_record(key, Record {
+                        value: value,
+                        index_keys: index_keys.into_iter().collect(),
+                    });
+                }
+                (store.info.name.clone(), store)
+            }).collect();
+            (stored.name, Database {
+                version: stored.version,
+                object_stores: object_stores,
+            })
+        }).collect()
+    }
+}
+
+/// <https://w3c.github.io/IndexedDB/#connection>
+struct Connection {
+    origin: String,
+    database: String,
+    events: IpcSender<IndexedDBConnectionEvent>,
+}
+
+/// <https://w3c.github.io/IndexedDB/#open-a-database>
+struct OpenRequest {
+    sender: IpcSender<IndexedDBOpenEvent>,
+    version: Option<u64>,
+    /// Where the events of the connection are sent once it is open
+    events: IpcSender<IndexedDBConnectionEvent>,
+    /// The connections that were sent a versionchange event and did not handle it yet, or
+    /// None if no event was sent yet
+    notified: Option<HashSet<u64>>,
+    blocked_sent: bool,
+}
+
+struct Transaction {
+    origin: String,
+    database: String,
+    mode: IndexedDBTransactionMode,
+    /// The open request this queue entry stands for, until it is resolved. An open request
+    /// that upgrades the database becomes the version change transaction.
+    open: Option<OpenRequest>,
+    started: bool,
+    /// Operations received before the transaction could start
+    pending: VecDeque<(IpcSender<IndexedDBResult<IndexedDBReply>>, IndexedDBOperation)>,
+    /// Set when a commit was requested before the transaction could start
+    pending_commit: Option<IpcSender<IndexedDBResult<()>>>,
+    /// The database as it was when the transaction started, or None if the
+    /// transaction created it
+    snapshot: Option<Option<Database>>,
+}
+
+struct IndexedDBManager {
+    port: IpcReceiver<IndexedDBThreadMsg>,
+    config_dir: Option<PathBuf>,
+    /// The databases of each origin that was accessed so far, keyed by the origin's
+    /// serialization and then by name
+    origins: HashMap<String, HashMap<String, Database>>,
+    transactions: HashMap<u64, Transaction>,
+    /// The transactions of each database, by (origin, name), in creation order
+    queues: HashMap<(String, String), Vec<u64>>,
+    next_transaction_id: u64,
+    connections: HashMap<u64, Connection>,
+    next_connection_id: u64,
+}
+
+impl IndexedDBManager {
+    fn new(port: IpcReceiver<IndexedDBThreadMsg>, config_dir: Option<PathBuf>) -> IndexedDBManager {
+        IndexedDBManager {
+            port: port,
+            config_dir: config_dir.map(|dir| dir.join("indexeddb")),
+            origins: HashMap::new(),
+            transactions: HashMap::new(),
+            queues: HashMap::new(),
+            next_transaction_id: 1,
+            connections: HashMap::new(),
+            next_connection_id: 1,
+        }
+    }
+
+    fn start(&mut self) {
+        loop {
+            match self.port.recv().unwrap() {
+                IndexedDBThreadMsg::Open(sender, origin, name, version, events) => {
+                    let request = OpenRequest {
+                        sender: sender,
+                        version: version,
+                        events: events,
+                        notified: None,
+                        blocked_sent: false,
+                    };
+                    self.queue_transaction(&origin, name, IndexedDBTransactionMode::VersionChange, Some(request));
+                }
+                IndexedDBThreadMsg::CloseConnection(connection) => {
+                    if let Some(connection) = self.connections.remove(&connection) {
+                        self.start_transactions(connection.origin, connection.database);
+                    }
+                }
+                IndexedDBThreadMsg::VersionChangeFired(connection) => {
+                    self.version_change_fired(connection);
+                }
+                IndexedDBThreadMsg::Databases(sender, origin) => {
+                    let mut databases: Vec<(String, u64)> = self.databases(&origin).iter()
+                        .map(|(name, database)| (name.clone(), database.version))
+                        .collect();
+                    databases.sort();
+                    let _ = sender.send(databases);
+                }
+                IndexedDBThreadMsg::DeleteDatabase(sender, origin, name) => {
+                    let version = self.databases(&origin).remove(&name).map_or(0, |database| database.version);
+                    if version != 0 {
+                        let origin = origin.ascii_serialization();
+                        self.write_origin(&origin);
+                        for (&id, connection) in &self.connections {
+                            if connection.origin == origin && connection.database == name {
+                                let _ = connection.events.send(IndexedDBConnectionEvent::VersionChange {
+                                    connection: id,
+                                    old_version: version,
+                                    new_version: None,
+                                });
+                            }
+                        }
+                    }
+                    let _ = sender.send(version);
+                }
+                IndexedDBThreadMsg::CreateTransaction(sender, origin, name, mode) => {
+                    let id = self.queue_transaction(&origin, name, mode, None);
+                    let _ = sender.send(id);
+                }
+                IndexedDBThreadMsg::Operation(sender, id, operation) => {
+                    self.handle_operation(sender, id, operation);
+                }
+                IndexedDBThreadMsg::Commit(sender, id) => {
+                    self.commit(sender, id);
+                }
+                IndexedDBThreadMsg::Abort(sender, id) => {
+                    self.abort(id);
+                    let _ = sender.send(());
+                }
+                IndexedDBThreadMsg::Exit(sender) => {
+                    let _ = sender.send(());
+                    break
+                }
+            }
+        }
+    }
+
+    /// The databases of an origin, read from disk the first time the origin is accessed
+    fn databases(&mut self, origin: &ImmutableOrigin) -> &mut HashMap<String, Database> {
+        let key = origin.ascii_serialization();
+        if !self.origins.contains_key(&key) {
+            let mut stored = StoredOrigin::default();
+            if let Some(ref config_dir) = self.config_dir {
+                if config_dir.join(origin_file_name(&key)).exists() {
+                    resource_thread::read_json_from_file(&mut stored, config_dir, &origin_file_name(&key));
+                }
+            }
+            self.origins.insert(key.clone(), stored.into_databases());
+        }
+        self.origins.get_mut(&key).unwrap()
+    }
+
+    fn write_origin(&self, origin: &str) {
+        let config_dir = match self.config_dir {
+            Some(ref config_dir) => config_dir,
+            None => return,
+        };
+        let databases = match self.origins.get(origin) {
+            Some(databases) => databases,
+            None => return,
+        };
+        if let Err(error) = fs::create_dir_all(config_dir) {
+            warn!("Could not create the IndexedDB directory: {}", error);
+            return;
+        }
+        let json = match serde_json::to_string(&StoredOrigin::from_databases(databases)) {
+            Ok(json) => json,
+            Err(error) => {
+                warn!("Could not serialize IndexedDB databases: {}", error);
+                return;
+            },
+        };
+        // Write to a temporary file first, so that the file is never left half written.
+        let path = config_dir.join(origin_file_name(origin));
+        let temporary_path = path.with_extension("json.tmp");
+        let written = File::create(&temporary_path).and_then(|mut file| {
+            file.write_all(json.as_bytes())?;
+            file.sync_all()
+        }).and_then(|_| fs::rename(&temporary_path, &path));
+        if let Err(error) = written {
+            warn!("Could not write IndexedDB databases to {}: {}", path.display(), error);
+            let _ = fs::remove_file(&temporary_path);
+        }
+    }
+
+    /// Adds a transaction, or an open request, to the queue of a database
+    fn queue_transaction(&mut self,
+                         origin: &ImmutableOrigin,
+                         name: String,
+                         mode: IndexedDBTransactionMode,
+                         open: Option<OpenRequest>)
+                         -> u64 {
+        // Make sure the origin's databases are loaded before the transaction runs.
+        self.databases(origin);
+        let id = self.next_transaction_id;
+        self.next_transaction_id += 1;
+        let origin = origin.ascii_serialization();
+        self.queues.entry((origin.clone(), name.clone())).or_insert(vec![]).push(id);
+        self.transactions.insert(id, Transaction {
+            origin: origin.clone(),
+            database: name.clone(),
+            mode: mode,
+            open: open,
+            started: false,
+            pending: VecDeque::new(),
+            pending_commit: None,
+            snapshot: None,
+        });
+        self.start_transactions(origin, name);
+        id
+    }
+
+    /// Starts every transaction of a database that no longer has to wait for another one
+    fn start_transactions(&mut self, origin: String, name: String) {
+        let queue = match self.queues.get(&(origin.clone(), name.clone())) {
+            Some(queue) => queue.clone(),
+            None => return,
+        };
+        for (position, id) in queue.iter().enumerate() {
+            let can_start = {
+                let mode = self.transactions[id].mode;
+                position == 0 || (mode == IndexedDBTransactionMode::ReadOnly &&
+                                  queue[..position].iter().all(|earlier| {
+                                      self.transactions[earlier].mode == IndexedDBTransactionMode::ReadOnly
+                                  }))
+            };
+            if !can_start {
+                break;
+            }
+            if !self.transactions[id].started {
+                self.start_transaction(*id);
+                if !self.transactions.contains_key(id) {
+                    // Finishing the transaction already started the ones after it.
+                    return;
+                }
+            }
+        }
+    }
+
+    fn start_transaction(&mut self, id: u64) {
+        let open = self.transactions.get_mut(&id).unwrap().open.take();
+        if let Some(request) = open {
+            return self.process_open(id, request);
+        }
+        let (origin, name, mode) = {
+            let transaction = self.transactions.get_mut(&id).unwrap();
+            transaction.started = true;
+            (transaction.origin.clone(), transaction.database.clone(), transaction.mode)
+        };
+        if mode != IndexedDBTransactionMode::ReadOnly {
+            let snapshot = self.origins[&origin].get(&name).cloned();
+            self.transactions.get_mut(&id).unwrap().snapshot = Some(snapshot);
+        }
+        self.run_pending(id);
+    }
+
+    /// Runs the operations and the commit of a transaction that were received before it started
+    fn run_pending(&mut self, id: u64) {
+        loop {
+            let next = self.transactions.get_mut(&id).and_then(|transaction| transaction.pending.pop_front());
+            match next {
+                Some((sender, operation)) => self.handle_operation(sender, id, operation),
+                None => break,
+            }
+        }
+        let pending_commit = self.transactions.get_mut(&id).and_then(|transaction| transaction.pending_commit.take());
+        if let Some(sender) = pending_commit {
+            self.commit(sender, id);
+        }
+    }
+
+    /// Steps 5 and on of <https://w3c.github.io/IndexedDB/#open-a-database>, run once the
+    /// open request is at the front of the database's queue. The queue entry is either
+    /// finished, turned into the version change transaction, or left waiting for other
+    /// connections to close.
+    fn process_open(&mut self, id: u64, mut request: OpenRequest) {
+        let (origin, name) = {
+            let transaction = &self.transactions[&id];
+            (transaction.origin.clone(), transaction.database.clone())
+        };
+        let (current_version, info) = match self.origins[&origin].get(&name) {
+            Some(database) => (database.version, database.info()),
+            None => (0, DatabaseInfo::default()),
+        };
+        let version = request.version.unwrap_or(if current_version == 0 { 1 } else { current_version });
+        if version < current_version {
+            let _ = request.sender.send(IndexedDBOpenEvent::Error(IndexedDBError::Version));
+            return self.finish_transaction(id, origin, name);
+        }
+
+        if version > current_version {
+            // https://w3c.github.io/IndexedDB/#upgrade-a-database, steps 1-4 of
+            // https://w3c.github.io/IndexedDB/#open-a-database's step 10.
+            let open_connections: Vec<u64> = self.connections.iter()
+                .filter(|&(_, connection)| connection.origin == origin && connection.database == name)
+                .map(|(&connection, _)| connection)
+                .collect();
+            if !open_connections.is_empty() {
+                if request.notified.is_none() {
+                    for &connection in &open_connections {
+                        let _ = self.connections[&connection].events.send(IndexedDBConnectionEvent::VersionChange {
+                            connection: connection,
+                            old_version: current_version,
+                            new_version: Some(version),
+                        });
+                    }
+                    request.notified = Some(open_connections.iter().cloned().collect());
+                }
+                let all_fired = {
+                    let notified = request.notified.as_mut().unwrap();
+                    notified.retain(|connection| open_connections.contains(connection));
+                    notified.is_empty()
+                };
+                if all_fired && !request.blocked_sent {
+                    request.blocked_sent = true;
+                    let _ = request.sender.send(IndexedDBOpenEvent::Blocked(current_version, version));
+                }
+                self.transactions.get_mut(&id).unwrap().open = Some(request);
+                return;
+            }
+        }
+
+        let connection = self.next_connection_id;
+        self.next_connection_id += 1;
+        self.connections.insert(connection, Connection {
+            origin: origin.clone(),
+            database: name.clone(),
+            events: request.events,
+        });
+
+        if version == current_version {
+            let _ = request.sender.send(IndexedDBOpenEvent::Opened(connection, info));
+            return self.finish_transaction(id, origin, name);
+        }
+
+        // The open request becomes the version change transaction, which creates the
+        // database if needed and sets its version.
+        let snapshot = {
+            let databases = self.origins.get_mut(&origin).unwrap();
+            let snapshot = databases.get(&name).cloned();
+            databases.entry(name.clone()).or_insert(Database {
+                version: 0,
+                object_stores: BTreeMap::new(),
+            }).version = version;
+            snapshot
+        };
+        {
+            let transaction = self.transactions.get_mut(&id).unwrap();
+            transaction.started = true;
+            transaction.snapshot = Some(snapshot);
+        }
+        let _ = request.sender.send(IndexedDBOpenEvent::Upgrade {
+            connection: connection,
+            transaction: id,
+            version: version,
+            info: info,
+        });
+        self.run_pending(id);
+    }
+
+    /// Resumes the open request that was waiting for a connection to handle its
+    /// versionchange event.
+    fn version_change_fired(&mut self, connection: u64) {
+        let waiting = self.transactions.values_mut().find(|transaction| {
+            transaction.open.as_ref()
+                .and_then(|request| request.notified.as_ref())
+                .map_or(false, |notified| notified.contains(&connection))
+        }).map(|transaction| {
+            transaction.open.as_mut().unwrap().notified.as_mut().unwrap().remove(&connection);
+            (transaction.origin.clone(), transaction.database.clone())
+        });
+        if let Some((origin, name)) = waiting {
+            self.start_transactions(origin, name);
+        }
+    }
+
+    fn handle_operation(&mut self,
+                        sender: IpcSender<IndexedDBResult<IndexedDBReply>>,
+                        id: u64,
+                        operation: IndexedDBOperation) {
+        let (origin, name) = match self.transactions.get_mut(&id) {
+            Some(transaction) => {
+                if !transaction.started {
+                    transaction.pending.push_back((sender, operation));
+                    return;
+                }
+                (transaction.origin.clone(), transaction.database.clone())
+            },
+            None => {
+                let _ = sender.send(Err(IndexedDBError::Abort));
+                return;
+            },
+        };
+        let result = match self.origins.get_mut(&origin).and_then(|databases| databases.get_mut(&name)) {
+            Some(database) => run_operation(database, operation),
+            None => Err(IndexedDBError::Abort),
+        };
+        let _ = sender.send(result);
+    }
+
+    fn commit(&mut self, sender: IpcSender<IndexedDBResult<()>>, id: u64) {
+        let (origin, name, modified) = match self.transactions.get_mut(&id) {
+            Some(transaction) => {
+                if !transaction.started {
+                    transaction.pending_commit = Some(sender);
+                    return;
+                }
+                (transaction.origin.clone(), transaction.database.clone(), transaction.snapshot.is_some())
+            },
+            None => {
+                let _ = sender.send(Err(IndexedDBError::Abort));
+                return;
+            },
+        };
+        if modified {
+            self.write_origin(&origin);
+        }
+        self.finish_transaction(id, origin, name);
+        let _ = sender.send(Ok(()));
+    }
+
+    fn abort(&mut self, id: u64) {
+        let (origin, name, snapshot) = match self.transactions.get_mut(&id) {
+            Some(transaction) => {
+                for (sender, _) in transaction.pending.drain(..) {
+                    let _ = sender.send(Err(IndexedDBError::Abort));
+                }
+                if let Some(sender) = transaction.pending_commit.take() {
+                    let _ = sender.send(Err(IndexedDBError::Abort));
+                }
+                (transaction.origin.clone(), transaction.database.clone(), transaction.snapshot.take())
+            },
+            None => return,
+        };
+        if let (Some(snapshot), Some(databases)) = (snapshot, self.origins.get_mut(&origin)) {
+            match snapshot {
+                Some(database) => databases.insert(name.clone(), database),
+                None => databases.remove(&name),
+            };
+        }
+        self.finish_transaction(id, origin, name);
+    }
+
+    fn finish_transaction(&mut self, id: u64, origin: String, name: String) {
+        self.transactions.remove(&id);
+        let key = (origin.clone(), name.clone());
+        let empty = match self.queues.get_mut(&key) {
+            Some(queue) => {
+                queue.retain(|&other| other != id);
+                queue.is_empty()
+            },
+            None => false,
+        };
+        if empty {
+            self.queues.remove(&key);
+        } else {
+            self.start_transactions(origin, name);
+        }
+    }
+}
+
+/// The name of the file an origin's databases are written to
+fn origin_file_name(origin: &str) -> String {
+    let hex: Vec<String> = origin.bytes().map(|byte| format!("{:02x}", byte)).collect();
+    format!("{}.json", hex.concat())
+}
+
+fn run_operation(database: &mut Database, operation: IndexedDBOperation) -> IndexedDBResult<IndexedDBReply> {
+    match operation {
+        IndexedDBOperation::CreateObjectStore(info) => {
+            if database.object_stores.contains_key(&info.name) {
+                return Err(IndexedDBError::Constraint);
+            }
+            database.object_stores.insert(info.name.clone(), ObjectStore::new(info));
+            Ok(IndexedDBReply::None)
+        },
+        IndexedDBOperation::DeleteObjectStore(name) => {
+            match database.object_stores.remove(&name) {
+                Some(_) => Ok(IndexedDBReply::None),
+                None => Err(IndexedDBError::NotFound),
+            }
+        },
+        IndexedDBOperation::CreateIndex(store_name, info, entries) => {
+            let store = object_store(database, &store_name)?;
+            if store.index_info(&info.name).is_some() {
+                return Err(IndexedDBError::Constraint);
+            }
+            let name = info.name.clone();
+            store.info.indexes.push(info);
+            store.indexes.insert(name.clone(), Index::new());
+            for (primary_key, keys) in entries {
+                if store.violates_unique_index(&name, &keys, &primary_key) {
+                    remove_index(store, &name);
+                    return Err(IndexedDBError::Constraint);
+                }
+                if let Some(mut record) = store.records.get(&primary_key).cloned() {
+                    // Records stored after script read the existing ones already have their keys.
+                    if !record.index_keys.contains_key(&name) {
+                        record.index_keys.insert(name.clone(), keys);
+                        store.insert_record(primary_key, record);
+                    }
+                }
+            }
+            Ok(IndexedDBReply::None)
+        },
+        IndexedDBOperation::DeleteIndex(store_name, name) => {
+            let store = object_store(database, &store_name)?;
+            if store.index_info(&name).is_none() {
+                return Err(IndexedDBError::NotFound);
+            }
+            remove_index(store, &name);
+            Ok(IndexedDBReply::None)
+        },
+        IndexedDBOperation::Put { object_store: store_name, key, value, index_keys, no_overwrite } => {
+            let store = object_store(database, &store_name)?;
+            // https://w3c.github.io/IndexedDB/#store-a-record-into-an-object-store
+            let key = match key {
+                Some(key) => {
+                    if store.info.auto_increment {
+                        if let IndexedDBKey::Number(number) = key {
+                            // https://w3c.github.io/IndexedDB/#possibly-update-the-key-generator
+                            let number = number.min(MAX_GENERATED_KEY as f64).floor();
+                            if number >= store.current_number as f64 {
+                                store.current_number = number as u64 + 1;
+                            }
+                        }
+                    }
+                    key
+                },
+                None => {
+                    // https://w3c.github.io/IndexedDB/#generate-a-key
+                    if !store.info.auto_increment {
+                        return Err(IndexedDBError::Data);
+                    }
+                    if store.current_number > MAX_GENERATED_KEY {
+                        return Err(IndexedDBError::Constraint);
+                    }
+                    let key = IndexedDBKey::Number(store.current_number as f64);
+                    store.current_number += 1;
+                    key
+                },
+            };
+            if no_overwrite && store.records.contains_key(&key) {
+                return Err(IndexedDBError::Constraint);
+            }
+            if index_keys.iter().any(|&(ref name, ref keys)| store.violates_unique_index(name, keys, &key)) {
+                return Err(IndexedDBError::Constraint);
+            }
+            store.insert_record(key.clone(), Record {
+                value: value,
+                index_keys: index_keys.into_iter().collect(),
+            });
+            Ok(IndexedDBReply::Key(key))
+        },
+        IndexedDBOperation::Get(source, range) => {
+            let store = object_store(database, &source.object_store)?;
+            let record = store.entries(source.index.as_ref().map(|name| &**name), &range)
+                              .into_iter()
+                              .next()
+                              .and_then(|(key, primary_key)| store.record(key, primary_key));
+            Ok(IndexedDBReply::Record(record))
+        },
+        IndexedDBOperation::GetAll(source, range, count) => {
+            let store = object_store(database, &source.object_store)?;
+            let count = match count {
+                Some(count) if count > 0 => count as usize,
+                _ => usize::max_value(),
+            };
+            let records = store.entries(source.index.as_ref().map(|name| &**name), &range)
+                               .into_iter()
+                               .take(count)
+                               .filter_map(|(key, primary_key)| store.record(key, primary_key))
+                               .collect();
+            Ok(IndexedDBReply::Records(records))
+        },
+        IndexedDBOperation::Count(source, range) => {
+            let store = object_store(database, &source.object_store)?;
+            let count = store.entries(source.index.as_ref().map(|name| &**name), &range).len();
+            Ok(IndexedDBReply::Count(count as u64))
+        },
+        IndexedDBOperation::Delete(store_name, range) => {
+            let store = object_store(database, &store_name)?;
+            let keys: Vec<IndexedDBKey> = store.records.range(bounds(&range)).map(|(key, _)| key.clone()).collect();
+            for key in keys {
+                store.remove_record(&key);
+            }
+            Ok(IndexedDBReply::None)
+        },
+        IndexedDBOperation::Clear(store_name) => {
+            let store = object_store(database, &store_name)?;
+            store.records.clear();
+            for index in store.indexes.values_mut() {
+                index.clear();
+            }
+            Ok(IndexedDBReply::None)
+        },
+        IndexedDBOperation::Iterate { source, range, direction, position, key, primary_key, count } => {
+            let store = object_store(database, &source.object_store)?;
+            let found = match source.index {
+                Some(ref name) => match store.indexes.get(name) {
+                    Some(index) => iterate(index, &range, direction, position, key, primary_key, count),
+                    None => return Err(IndexedDBError::NotFound),
+                },
+                None => iterate(&store.records, &range, direction, position, key, primary_key, count),
+            };
+            Ok(IndexedDBReply::Record(found.and_then(|(key, primary_key)| store.record(key, primary_key))))
+        },
+    }
+}
+
+fn object_store<'a>(database: &'a mut Database, name: &str) -> IndexedDBResult<&'a mut ObjectStore> {
+    database.object_stores.get_mut(name).ok_or(IndexedDBError::NotFound)
+}
+
+fn remove_index(store: &mut ObjectStore, name: &str) {
+    store.info.indexes.retain(|index| index.name != name);
+    store.indexes.remove(name);
+    for record in store.records.values_mut() {
+        record.index_keys.remove(name);
+    }
+}
+
+/// The records a cursor iterates over, by key
+trait CursorSource {
+    /// The keys of the source in the given bounds
+    fn keys<'a>(&'a self, lower: Bound<&IndexedDBKey>, upper: Bound<&IndexedDBKey>)
+                -> Box<DoubleEndedIterator<Item = &'a IndexedDBKey> + 'a>;
+
+    /// The lowest, or else highest, primary key of the records with the given key, in the
+    /// given bounds
+    fn primary_key(&self,
+                   key: &IndexedDBKey,
+                   lower: Bound<&IndexedDBKey>,
+                   upper: Bound<&IndexedDBKey>,
+                   lowest: bool)
+                   -> Option<IndexedDBKey>;
+}
+
+impl CursorSource for BTreeMap<IndexedDBKey, Record> {
+    fn keys<'a>(&'a self, lower: Bound<&IndexedDBKey>, upper: Bound<&IndexedDBKey>)
+                -> Box<DoubleEndedIterator<Item = &'a IndexedDBKey> + 'a> {
+        if is_empty_range(lower, upper) {
+            return Box::new(None::<&IndexedDBKey>.into_iter());
+        }
+        Box::new(self.range((lower, upper)).map(|(key, _)| key))
+    }
+
+    fn primary_key(&self,
+                   key: &IndexedDBKey,
+                   lower: Bound<&IndexedDBKey>,
+                   upper: Bound<&IndexedDBKey>,
+                   _lowest: bool)
+                   -> Option<IndexedDBKey> {
+        // The primary key of a record in an object store is its key.
+        if is_empty_range(lower, upper) || !contains(lower, upper, key) {
+            return None;
+        }
+        Some(key.clone())
+    }
+}
+
+impl CursorSource for Index {
+    fn keys<'a>(&'a self, lower: Bound<&IndexedDBKey>, upper: Bound<&IndexedDBKey>)
+                -> Box<DoubleEndedIterator<Item = &'a IndexedDBKey> + 'a> {
+        if is_empty_range(lower, upper) {
+            return Box::new(None::<&IndexedDBKey>.into_iter());
+        }
+        Box::new(self.range((lower, upper)).map(|(key, _)| key))
+    }
+
+    fn primary_key(&self,
+                   key: &IndexedDBKey,
+                   lower: Bound<&IndexedDBKey>,
+                   upper: Bound<&IndexedDBKey>,
+                   lowest: bool)
+                   -> Option<IndexedDBKey> {
+        let primary_keys = match self.get(key) {
+            Some(primary_keys) if !is_empty_range(lower, upper) => primary_keys,
+            _ => return None,
+        };
+        let mut primary_keys = primary_keys.range((lower, upper));
+        let primary_key = if lowest { primary_keys.next() } else { primary_keys.next_back() };
+        primary_key.cloned()
+    }
+}
+
+/// Whether no key is in the given bounds, which `BTreeMap::range` panics on
+fn is_empty_range(lower: Bound<&IndexedDBKey>, upper: Bound<&IndexedDBKey>) -> bool {
+    match (lower, upper) {
+        (Bound::Included(lower), Bound::Included(upper)) => lower > upper,
+        (Bound::Included(lower), Bound::Excluded(upper)) |
+        (Bound::Excluded(lower), Bound::Included(upper)) |
+        (Bound::Excluded(lower), Bound::Excluded(upper)) => lower >= upper,
+        _ => false,
+    }
+}
+
+fn contains(lower: Bound<&IndexedDBKey>, upper: Bound<&IndexedDBKey>, key: &IndexedDBKey) -> bool {
+    let above_lower = match lower {
+        Bound::Included(lower) => key >= lower,
+        Bound::Excluded(lower) => key > lower,
+        Bound::Unbounded => true,
+    };
+    let below_upper = match upper {
+        Bound::Included(upper) => key <= upper,
+        Bound::Excluded(upper) => key < upper,
+        Bound::Unbounded => true,
+    };
+    above_lower && below_upper
+}
+
+/// The higher of two lower bounds
+fn max_lower<'a>(first: Bound<&'a IndexedDBKey>, second: Bound<&'a IndexedDBKey>) -> Bound<&'a IndexedDBKey> {
+    match (first, second) {
+        (Bound::Unbounded, bound) | (bound, Bound::Unbounded) => bound,
+        (Bound::Included(first), Bound::Included(second)) => Bound::Included(first.max(second)),
+        (Bound::Excluded(first), Bound::Excluded(second)) => Bound::Excluded(first.max(second)),
+        (Bound::Included(included), Bound::Excluded(excluded)) |
+        (Bound::Excluded(excluded), Bound::Included(included)) => {
+            if included > excluded { Bound::Included(included) } else { Bound::Excluded(excluded) }
+        },
+    }
+}
+
+/// The lower of two upper bounds
+fn min_upper<'a>(first: Bound<&'a IndexedDBKey>, second: Bound<&'a IndexedDBKey>) -> Bound<&'a IndexedDBKey> {
+    match (first, second) {
+        (Bound::Unbounded, bound) | (bound, Bound::Unbounded) => bound,
+        (Bound::Included(first), Bound::Included(second)) => Bound::Included(first.min(second)),
+        (Bound::Excluded(first), Bound::Excluded(second)) => Bound::Excluded(first.min(second)),
+        (Bound::Included(included), Bound::Excluded(excluded)) |
+        (Bound::Excluded(excluded), Bound::Included(included)) => {
+            if included < excluded { Bound::Included(included) } else { Bound::Excluded(excluded) }
+        },
+    }
+}
+
+/// <https://w3c.github.io/IndexedDB/#iterate-a-cursor>
+///
+/// Each step looks up the next record from the cursor's position in the ordered keys of
+/// its source, rather than going through the records in its range.
+fn iterate<S: CursorSource>(source: &S,
+                            range: &IndexedDBKeyRange,
+                            direction: IndexedDBCursorDirection,
+                            mut position: Option<(IndexedDBKey, IndexedDBKey)>,
+                            mut key: Option<IndexedDBKey>,
+                            mut primary_key: Option<IndexedDBKey>,
+                            count: u32)
+                            -> Option<(IndexedDBKey, IndexedDBKey)> {
+    let forward = match direction {
+        IndexedDBCursorDirection::Next | IndexedDBCursorDirection::NextUnique => true,
+        IndexedDBCursorDirection::Prev | IndexedDBCursorDirection::PrevUnique => false,
+    };
+    let unique = match direction {
+        IndexedDBCursorDirection::NextUnique | IndexedDBCursorDirection::PrevUnique => true,
+        IndexedDBCursorDirection::Next | IndexedDBCursorDirection::Prev => false,
+    };
+    let (range_lower, range_upper) = bounds(range);
+    for _ in 0..count.max(1) {
+        let found = {
+            // The keys the found record's key can have: in range, past the position, and
+            // reaching the key given to `continue`.
+            let position_bound = match position {
+                Some((ref position_key, _)) if unique => Bound::Excluded(position_key),
+                Some((ref position_key, _)) => Bound::Included(position_key),
+                None => Bound::Unbounded,
+            };
+            let key_bound = match key {
+                Some(ref key) => Bound::Included(key),
+                None => Bound::Unbounded,
+            };
+            let (lower, upper) = if forward {
+                (max_lower(max_lower(range_lower, position_bound), key_bound), range_upper)
+            } else {
+                (range_lower, min_upper(min_upper(range_upper, position_bound), key_bound))
+            };
+
+            // The primary keys the found record can have when it has the position's key, or
+            // the key given to `continuePrimaryKey`.
+            let primary_key_bounds = |entry_key: &IndexedDBKey| {
+                let position_bound = match position {
+                    Some((ref position_key, ref position_primary_key)) if !unique && entry_key == position_key => {
+                        Bound::Excluded(position_primary_key)
+                    },
+                    _ => Bound::Unbounded,
+                };
+                let key_bound = match (&key, &primary_key) {
+                    (&Some(ref key), &Some(ref primary_key)) if entry_key == key => Bound::Included(primary_key),
+                    _ => Bound::Unbounded,
+                };
+                if forward {
+                    (max_lower(position_bound, key_bound), Bound::Unbounded)
+                } else {
+                    (Bound::Unbounded, min_upper(position_bound, key_bound))
+                }
+            };
+
+            let mut keys = source.keys(lower, upper);
+            let mut found = None;
+            loop {
+                let next = if forward { keys.next() } else { keys.next_back() };
+                let entry_key = match next {
+                    Some(entry_key) => entry_key,
+                    None => break,
+                };
+                let (primary_lower, primary_upper) = primary_key_bounds(entry_key);
+                // A reverse unique cursor moves to the lowest primary key of the next key.
+                let lowest = forward || unique;
+                if let Some(entry_primary_key) = source.primary_key(entry_key, primary_lower, primary_upper, lowest) {
+                    found = Some((entry_key.clone(), entry_primary_key));
+                    break;
+                }
+            }
+            found
+        };
+        match found {
+            Some(ref entry) => position = Some(entry.clone()),
+            None => return None,
+        }
+        key = None;
+        primary_key = None;
+    }
+    position
+}
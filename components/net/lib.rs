@@ -48,8 +48,10 @@ mod data_loader;
 pub mod filemanager_thread;
 mod hosts;
 pub mod hsts;
+pub mod http_cache;
 pub mod http_loader;
 pub mod image_cache;
+mod indexeddb_thread;
 pub mod mime_classifier;
 pub mod resource_thread;
 mod storage_thread;
@@ -64,6 +66,6 @@ pub mod fetch {
 /// A module for re-exports of items used in unit tests.
 pub mod test {
     pub use chrome_loader::resolve_chrome_url;
-    pub use http_loader::HttpState;
+    pub use http_loader::{HttpState, ProxyCredentialsPrompt};
     pub use hosts::{replace_host_table, parse_hostsfile};
 }
@@ -3,23 +3,26 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 //! A thread that takes a URL and streams back the binary data.
-use connector::{create_http_connector, create_ssl_client};
+use connector::{ProxyAuthCache, ProxyConfig, create_http_connector, create_ssl_client};
 use cookie;
 use cookie_rs;
 use cookie_storage::CookieStorage;
-use devtools_traits::DevtoolsControlMsg;
+use devtools_traits::{BlockedUrls, DevtoolsControlMsg, NetworkListeners};
 use fetch::cors_cache::CorsCache;
 use fetch::methods::{FetchContext, fetch};
 use filemanager_thread::{FileManager, TFDProvider};
 use hsts::HstsList;
-use http_loader::{HttpState, http_redirect_fetch};
+use http_cache::HttpCache;
+use http_loader::{HttpState, TFDProxyCredentialsPrompt, http_redirect_fetch};
 use hyper_serde::Serde;
+use indexeddb_thread::IndexedDBThreadFactory;
 use ipc_channel::ipc::{self, IpcReceiver, IpcReceiverSet, IpcSender};
 use net_traits::{CookieSource, CoreResourceThread};
 use net_traits::{CoreResourceMsg, FetchChannels, FetchResponseMsg};
 use net_traits::{CustomResponseMediator, ResourceId};
 use net_traits::{ResourceThreads, WebSocketDomAction, WebSocketNetworkEvent};
 use net_traits::request::{Request, RequestInit};
+use net_traits::indexeddb_thread::IndexedDBThreadMsg;
 use net_traits::response::{Response, ResponseInit};
 use net_traits::storage_thread::StorageThreadMsg;
 use profile_traits::time::ProfilerChan;
@@ -35,34 +38,42 @@ use std::fs::File;
 use std::io::prelude::*;
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use std::sync::mpsc::Sender;
 use std::thread;
 use storage_thread::StorageThreadFactory;
 use websocket_loader;
 
 const TFD_PROVIDER: &'static TFDProvider = &TFDProvider;
+const TFD_CREDENTIALS_PROMPT: &'static TFDProxyCredentialsPrompt = &TFDProxyCredentialsPrompt;
 
 /// Returns a tuple of (public, private) senders to the new threads.
 pub fn new_resource_threads(user_agent: Cow<'static, str>,
                             devtools_chan: Option<Sender<DevtoolsControlMsg>>,
+                            blocked_urls: BlockedUrls,
+                            network_listeners: NetworkListeners,
                             profiler_chan: ProfilerChan,
                             config_dir: Option<PathBuf>)
                             -> (ResourceThreads, ResourceThreads) {
     let (public_core, private_core) = new_core_resource_thread(
         user_agent,
         devtools_chan,
+        blocked_urls,
+        network_listeners,
         profiler_chan,
         config_dir.clone());
-    let storage: IpcSender<StorageThreadMsg> = StorageThreadFactory::new(config_dir);
-    (ResourceThreads::new(public_core, storage.clone()),
-     ResourceThreads::new(private_core, storage))
+    let storage: IpcSender<StorageThreadMsg> = StorageThreadFactory::new(config_dir.clone());
+    let indexeddb: IpcSender<IndexedDBThreadMsg> = IndexedDBThreadFactory::new(config_dir);
+    (ResourceThreads::new(public_core, storage.clone(), indexeddb.clone()),
+     ResourceThreads::new(private_core, storage, indexeddb))
 }
 
 
 /// Create a CoreResourceThread
 pub fn new_core_resource_thread(user_agent: Cow<'static, str>,
                                 devtools_chan: Option<Sender<DevtoolsControlMsg>>,
+                                blocked_urls: BlockedUrls,
+                                network_listeners: NetworkListeners,
                                 profiler_chan: ProfilerChan,
                                 config_dir: Option<PathBuf>)
                                 -> (CoreResourceThread, CoreResourceThread) {
@@ -70,7 +81,7 @@ pub fn new_core_resource_thread(user_agent: Cow<'static, str>,
     let (private_setup_chan, private_setup_port) = ipc::channel().unwrap();
     thread::Builder::new().name("ResourceManager".to_owned()).spawn(move || {
         let resource_manager = CoreResourceManager::new(
-            user_agent, devtools_chan, profiler_chan
+            user_agent, devtools_chan, blocked_urls, network_listeners, profiler_chan
         );
 
         let mut channel_manager = ResourceChannelManager {
@@ -92,11 +103,14 @@ fn create_http_states(config_dir: Option<&Path>) -> (Arc<HttpState>, Arc<HttpSta
     let mut hsts_list = HstsList::from_servo_preload();
     let mut auth_cache = AuthCache::new();
     let mut cookie_jar = CookieStorage::new(150);
+    let mut http_cache = HttpCache::new();
     if let Some(config_dir) = config_dir {
         read_json_from_file(&mut auth_cache, config_dir, "auth_cache.json");
         read_json_from_file(&mut hsts_list, config_dir, "hsts_list.json");
         read_json_from_file(&mut cookie_jar, config_dir, "cookie_jar.json");
+        http_cache = HttpCache::new_with_disk(config_dir);
     }
+    let proxy_config = ProxyConfig::from_prefs_and_env();
 
     let ca_file = match opts::get().certificate_path {
         Some(ref path) => PathBuf::from(path),
@@ -110,12 +124,17 @@ fn create_http_states(config_dir: Option<&Path>) -> (Arc<HttpState>, Arc<HttpSta
         cookie_jar: RwLock::new(cookie_jar),
         auth_cache: RwLock::new(auth_cache),
         hsts_list: RwLock::new(hsts_list),
+        http_cache: RwLock::new(http_cache),
+        proxy_config: RwLock::new(proxy_config.clone()),
+        proxy_auth_cache: Arc::new(RwLock::new(ProxyAuthCache::new())),
+        proxy_connectors: Mutex::new(HashMap::new()),
+        proxy_credentials_prompt: TFD_CREDENTIALS_PROMPT,
         ssl_client: ssl_client.clone(),
         connector: create_http_connector(ssl_client),
     };
 
     let private_ssl_client = create_ssl_client(&ca_file);
-    let private_http_state = HttpState::new(private_ssl_client);
+    let private_http_state = HttpState::new(private_ssl_client, proxy_config, TFD_CREDENTIALS_PROMPT);
 
     (Arc::new(http_state), Arc::new(private_http_state))
 }
@@ -193,6 +212,13 @@ impl ResourceChannelManager {
             CoreResourceMsg::Synchronize(sender) => {
                 let _ = sender.send(());
             }
+            CoreResourceMsg::ClearCache(sender) => {
+                match http_state.http_cache.write() {
+                    Ok(mut http_cache) => http_cache.clear(),
+                    Err(_) => warn!("Error clearing http cache"),
+                }
+                let _ = sender.send(());
+            }
             CoreResourceMsg::ToFileManager(msg) => self.resource_manager.filemanager.handle(msg, TFD_PROVIDER),
             CoreResourceMsg::Exit(sender) => {
                 if let Some(ref config_dir) = self.config_dir {
@@ -208,6 +234,10 @@ impl ResourceChannelManager {
                         Ok(hsts) => write_json_to_file(&*hsts, config_dir, "hsts_list.json"),
                         Err(_) => warn!("Error writing hsts list to disk"),
                     }
+                    match http_state.http_cache.write() {
+                        Ok(mut http_cache) => http_cache.flush(),
+                        Err(_) => warn!("Error writing http cache to disk"),
+                    }
                 }
                 let _ = sender.send(());
                 return false;
@@ -273,7 +303,7 @@ pub fn write_json_to_file<T>(data: &T, config_dir: &Path, filename: &str)
     }
 }
 
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Clone, Deserialize, PartialEq, Serialize)]
 pub struct AuthCacheEntry {
     pub user_name: String,
     pub password: String,
@@ -297,6 +327,8 @@ pub struct AuthCache {
 pub struct CoreResourceManager {
     user_agent: Cow<'static, str>,
     devtools_chan: Option<Sender<DevtoolsControlMsg>>,
+    blocked_urls: BlockedUrls,
+    network_listeners: NetworkListeners,
     swmanager_chan: Option<IpcSender<CustomResponseMediator>>,
     filemanager: FileManager,
     cancel_load_map: HashMap<ResourceId, Sender<()>>,
@@ -305,10 +337,14 @@ pub struct CoreResourceManager {
 impl CoreResourceManager {
     pub fn new(user_agent: Cow<'static, str>,
                devtools_channel: Option<Sender<DevtoolsControlMsg>>,
+               blocked_urls: BlockedUrls,
+               network_listeners: NetworkListeners,
                _profiler_chan: ProfilerChan) -> CoreResourceManager {
         CoreResourceManager {
             user_agent: user_agent,
             devtools_chan: devtools_channel,
+            blocked_urls: blocked_urls,
+            network_listeners: network_listeners,
             swmanager_chan: None,
             filemanager: FileManager::new(),
             cancel_load_map: HashMap::new(),
@@ -333,6 +369,8 @@ impl CoreResourceManager {
         let http_state = http_state.clone();
         let ua = self.user_agent.clone();
         let dc = self.devtools_chan.clone();
+        let blocked_urls = self.blocked_urls.clone();
+        let network_listeners = self.network_listeners.clone();
         let filemanager = self.filemanager.clone();
 
         thread::Builder::new().name(format!("fetch thread for {}", req_init.url)).spawn(move || {
@@ -345,6 +383,8 @@ impl CoreResourceManager {
                 state: http_state,
                 user_agent: ua,
                 devtools_chan: dc,
+                blocked_urls: blocked_urls,
+                network_listeners: network_listeners,
                 filemanager: filemanager,
             };
 
@@ -2,18 +2,30 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
+use ipc_channel::ipc::{self, IpcSender};
+use ipc_channel::router::ROUTER;
 use net_traits::storage_thread::{StorageThreadMsg, StorageType};
 use resource_thread;
+use serde_json;
 use servo_url::ServoUrl;
 use std::borrow::ToOwned;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
 use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
 use std::thread;
+use std::time::{Duration, Instant};
 
 const QUOTA_SIZE_LIMIT: usize = 5 * 1024 * 1024;
 
+/// How long local storage changes are held in memory before being written to disk,
+/// so that a burst of `setItem` calls results in a single write.
+const WRITE_DELAY_MS: u64 = 1000;
+
+const LOCAL_DATA_FILE: &'static str = "local_data.json";
+
 pub trait StorageThreadFactory {
     fn new(config_dir: Option<PathBuf>) -> Self;
 }
@@ -21,7 +33,8 @@ pub trait StorageThreadFactory {
 impl StorageThreadFactory for IpcSender<StorageThreadMsg> {
     /// Create a storage thread
     fn new(config_dir: Option<PathBuf>) -> IpcSender<StorageThreadMsg> {
-        let (chan, port) = ipc::channel().unwrap();
+        let (chan, ipc_port) = ipc::channel().unwrap();
+        let port = ROUTER.route_ipc_receiver_to_new_mpsc_receiver(ipc_port);
         thread::Builder::new().name("StorageManager".to_owned()).spawn(move || {
             StorageManager::new(port, config_dir).start();
         }).expect("Thread spawning failed");
@@ -30,25 +43,33 @@ impl StorageThreadFactory for IpcSender<StorageThreadMsg> {
 }
 
 struct StorageManager {
-    port: IpcReceiver<StorageThreadMsg>,
+    port: Receiver<StorageThreadMsg>,
     session_data: HashMap<String, (usize, BTreeMap<String, String>)>,
     local_data: HashMap<String, (usize, BTreeMap<String, String>)>,
     config_dir: Option<PathBuf>,
+    /// The time by which pending local storage changes must be written to disk,
+    /// or None if the on-disk copy is up to date.
+    write_deadline: Option<Instant>,
 }
 
 impl StorageManager {
-    fn new(port: IpcReceiver<StorageThreadMsg>,
+    fn new(port: Receiver<StorageThreadMsg>,
            config_dir: Option<PathBuf>)
            -> StorageManager {
-        let mut local_data = HashMap::new();
+        let mut local_data: HashMap<String, (usize, BTreeMap<String, String>)> = HashMap::new();
         if let Some(ref config_dir) = config_dir {
-            resource_thread::read_json_from_file(&mut local_data, config_dir, "local_data.json");
+            resource_thread::read_json_from_file(&mut local_data, config_dir, LOCAL_DATA_FILE);
+        }
+        // Don't trust the stored sizes, since the quota check relies on them.
+        for &mut (ref mut total, ref entry) in local_data.values_mut() {
+            *total = entry.iter().map(|(name, value)| name.as_bytes().len() + value.as_bytes().len()).sum();
         }
         StorageManager {
             port: port,
             session_data: HashMap::new(),
             local_data: local_data,
             config_dir: config_dir,
+            write_deadline: None,
         }
     }
 }
@@ -56,7 +77,28 @@ impl StorageManager {
 impl StorageManager {
     fn start(&mut self) {
         loop {
-            match self.port.recv().unwrap() {
+            let msg = match self.write_deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if deadline <= now {
+                        self.write_local_data();
+                        continue;
+                    }
+                    match self.port.recv_timeout(deadline - now) {
+                        Ok(msg) => msg,
+                        Err(RecvTimeoutError::Timeout) => continue,
+                        Err(RecvTimeoutError::Disconnected) => {
+                            self.write_local_data();
+                            break
+                        }
+                    }
+                }
+                None => match self.port.recv() {
+                    Ok(msg) => msg,
+                    Err(_) => break,
+                },
+            };
+            match msg {
                 StorageThreadMsg::Length(sender, url, storage_type) => {
                     self.length(sender, url, storage_type)
                 }
@@ -78,10 +120,14 @@ impl StorageManager {
                 StorageThreadMsg::Clear(sender, url, storage_type) => {
                     self.clear(sender, url, storage_type)
                 }
+                StorageThreadMsg::Origins(sender, storage_type) => {
+                    self.origins(sender, storage_type)
+                }
+                StorageThreadMsg::ClearOrigin(sender, origin, storage_type) => {
+                    self.clear_origin(sender, origin, storage_type)
+                }
                 StorageThreadMsg::Exit(sender) => {
-                    if let Some(ref config_dir) = self.config_dir {
-                        resource_thread::write_json_to_file(&self.local_data, config_dir, "local_data.json");
-                    }
+                    self.write_local_data();
                     let _ = sender.send(());
                     break
                 }
@@ -89,6 +135,42 @@ impl StorageManager {
         }
     }
 
+    /// Schedule a write of the local storage data, unless one is already pending.
+    fn local_data_changed(&mut self, storage_type: StorageType) {
+        if let StorageType::Session = storage_type {
+            return;
+        }
+        if self.config_dir.is_some() && self.write_deadline.is_none() {
+            self.write_deadline = Some(Instant::now() + Duration::from_millis(WRITE_DELAY_MS));
+        }
+    }
+
+    fn write_local_data(&mut self) {
+        self.write_deadline = None;
+        let config_dir = match self.config_dir {
+            Some(ref config_dir) => config_dir,
+            None => return,
+        };
+        let json = match serde_json::to_string_pretty(&self.local_data) {
+            Ok(json) => json,
+            Err(error) => {
+                warn!("Could not serialize local storage data: {}", error);
+                return;
+            },
+        };
+        // Write to a temporary file first, so that the file is never left half written.
+        let path = config_dir.join(LOCAL_DATA_FILE);
+        let temporary_path = path.with_extension("json.tmp");
+        let written = File::create(&temporary_path).and_then(|mut file| {
+            file.write_all(json.as_bytes())?;
+            file.sync_all()
+        }).and_then(|_| fs::rename(&temporary_path, &path));
+        if let Err(error) = written {
+            warn!("Could not write local storage data to {}: {}", path.display(), error);
+            let _ = fs::remove_file(&temporary_path);
+        }
+    }
+
     fn select_data(&self, storage_type: StorageType)
                    -> &HashMap<String, (usize, BTreeMap<String, String>)> {
         match storage_type {
@@ -159,33 +241,38 @@ impl StorageManager {
             }
         };
 
-        let data = self.select_data_mut(storage_type);
-        if !data.contains_key(&origin) {
-            data.insert(origin.clone(), (0, BTreeMap::new()));
-        }
-
-        let message = data.get_mut(&origin).map(|&mut (ref mut total, ref mut entry)| {
-            let mut new_total_size = this_storage_size + value.as_bytes().len();
-            if let Some(old_value) = entry.get(&name) {
-                new_total_size -= old_value.as_bytes().len();
-            } else {
-                new_total_size += name.as_bytes().len();
-            }
-
-            if (new_total_size + other_storage_size) > QUOTA_SIZE_LIMIT {
-                return Err(());
+        let message = {
+            let data = self.select_data_mut(storage_type);
+            if !data.contains_key(&origin) {
+                data.insert(origin.clone(), (0, BTreeMap::new()));
             }
 
-            let message = entry.insert(name.clone(), value.clone()).map_or(
-                Ok((true, None)),
-                |old| if old == value {
-                    Ok((false, None))
+            data.get_mut(&origin).map(|&mut (ref mut total, ref mut entry)| {
+                let mut new_total_size = this_storage_size + value.as_bytes().len();
+                if let Some(old_value) = entry.get(&name) {
+                    new_total_size -= old_value.as_bytes().len();
                 } else {
-                    Ok((true, Some(old)))
-                });
-            *total = new_total_size;
-            message
-        }).unwrap();
+                    new_total_size += name.as_bytes().len();
+                }
+
+                if (new_total_size + other_storage_size) > QUOTA_SIZE_LIMIT {
+                    return Err(());
+                }
+
+                let message = entry.insert(name.clone(), value.clone()).map_or(
+                    Ok((true, None)),
+                    |old| if old == value {
+                        Ok((false, None))
+                    } else {
+                        Ok((true, Some(old)))
+                    });
+                *total = new_total_size;
+                message
+            }).unwrap()
+        };
+        if let Ok((true, _)) = message {
+            self.local_data_changed(storage_type);
+        }
         sender.send(message).unwrap();
     }
 
@@ -208,28 +295,61 @@ impl StorageManager {
                    storage_type: StorageType,
                    name: String) {
         let origin = self.origin_as_string(url);
-        let data = self.select_data_mut(storage_type);
-        let old_value = data.get_mut(&origin).and_then(|&mut (ref mut total, ref mut entry)| {
-            entry.remove(&name).and_then(|old| {
-                *total -= name.as_bytes().len() + old.as_bytes().len();
-                Some(old)
+        let old_value = {
+            let data = self.select_data_mut(storage_type);
+            data.get_mut(&origin).and_then(|&mut (ref mut total, ref mut entry)| {
+                entry.remove(&name).and_then(|old| {
+                    *total -= name.as_bytes().len() + old.as_bytes().len();
+                    Some(old)
+                })
             })
-        });
+        };
+        if old_value.is_some() {
+            self.local_data_changed(storage_type);
+        }
         sender.send(old_value).unwrap();
     }
 
     fn clear(&mut self, sender: IpcSender<bool>, url: ServoUrl, storage_type: StorageType) {
         let origin = self.origin_as_string(url);
-        let data = self.select_data_mut(storage_type);
-        sender.send(data.get_mut(&origin)
-                    .map_or(false, |&mut (ref mut total, ref mut entry)| {
-                        if !entry.is_empty() {
-                            entry.clear();
-                            *total = 0;
-                            true
-                        } else {
-                            false
-                        }})).unwrap();
+        let cleared = {
+            let data = self.select_data_mut(storage_type);
+            data.get_mut(&origin)
+                .map_or(false, |&mut (ref mut total, ref mut entry)| {
+                    if !entry.is_empty() {
+                        entry.clear();
+                        *total = 0;
+                        true
+                    } else {
+                        false
+                    }})
+        };
+        if cleared {
+            self.local_data_changed(storage_type);
+        }
+        sender.send(cleared).unwrap();
+    }
+
+    /// Sends every origin that has stored data, along with the number of bytes it uses
+    fn origins(&self, sender: IpcSender<Vec<(String, usize)>>, storage_type: StorageType) {
+        let data = self.select_data(storage_type);
+        let mut origins: Vec<(String, usize)> = data.iter()
+            .filter(|&(_, &(_, ref entry))| !entry.is_empty())
+            .map(|(origin, &(total, _))| (origin.clone(), total))
+            .collect();
+        origins.sort();
+        sender.send(origins).unwrap();
+    }
+
+    /// Removes all of the data stored by the given serialized origin, and sends
+    /// whether there was any
+    fn clear_origin(&mut self, sender: IpcSender<bool>, origin: String, storage_type: StorageType) {
+        let removed = self.select_data_mut(storage_type).remove(&origin)
+                          .map_or(false, |(_, entry)| !entry.is_empty());
+        if removed {
+            self.local_data_changed(storage_type);
+        }
+        sender.send(removed).unwrap();
     }
 
     fn origin_as_string(&self, url: ServoUrl) -> String {
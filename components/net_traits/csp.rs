@@ -0,0 +1,653 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Parsing and enforcement of [Content Security Policies][csp].
+//!
+//! Policies are parsed from `Content-Security-Policy` and
+//! `Content-Security-Policy-Report-Only` headers, or from
+//! `<meta http-equiv>` elements, into a `CspList`. The network stack uses it
+//! to block subresource fetches; the script thread uses it to block inline
+//! scripts and styles and string evaluation. Both report the violations they
+//! find back to the document, which dispatches events and sends reports.
+//!
+//! [csp]: https://w3c.github.io/webappsec-csp/
+
+use base64;
+use hyper::header::Headers;
+use openssl::hash::{MessageDigest, hash2};
+use request::{Destination, Origin, Request};
+use serde_json;
+use servo_url::{ImmutableOrigin, ServoUrl};
+use std::str;
+
+/// <https://w3c.github.io/webappsec-csp/#policy-disposition>
+#[derive(Clone, Copy, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum PolicyDisposition {
+    Enforce,
+    Report,
+}
+
+/// <https://w3c.github.io/webappsec-csp/#policy-source>
+#[derive(Clone, Copy, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum PolicySource {
+    Header,
+    Meta,
+}
+
+/// <https://w3c.github.io/webappsec-csp/#directives>
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct Directive {
+    /// The directive name, lowercased.
+    pub name: String,
+    /// The source expressions or other tokens that follow the name.
+    pub value: Vec<String>,
+}
+
+/// <https://w3c.github.io/webappsec-csp/#content-security-policy-object>
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct Policy {
+    pub directives: Vec<Directive>,
+    pub disposition: PolicyDisposition,
+    pub source: PolicySource,
+    /// The serialized policy, as it was delivered.
+    pub text: String,
+}
+
+/// The outcome of checking something against a `CspList`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CheckResult {
+    Allowed,
+    Blocked,
+}
+
+/// The kind of inline content being checked.
+///
+/// <https://w3c.github.io/webappsec-csp/#should-block-inline>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InlineCheckType {
+    /// An inline `<script>` element.
+    Script,
+    /// An event handler content attribute.
+    ScriptAttribute,
+    /// An inline `<style>` element.
+    Style,
+    /// A `style` content attribute.
+    StyleAttribute,
+    /// A `javascript:` URL being navigated to.
+    Navigation,
+}
+
+impl InlineCheckType {
+    fn effective_directive(&self) -> &'static str {
+        match *self {
+            InlineCheckType::Script | InlineCheckType::Navigation => "script-src-elem",
+            InlineCheckType::ScriptAttribute => "script-src-attr",
+            InlineCheckType::Style => "style-src-elem",
+            InlineCheckType::StyleAttribute => "style-src-attr",
+        }
+    }
+
+    /// Whether nonces and hashes apply, rather than only hashes in the presence of `'unsafe-hashes'`.
+    fn allows_nonces(&self) -> bool {
+        *self == InlineCheckType::Script || *self == InlineCheckType::Style
+    }
+}
+
+/// What a violation was caused by.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ViolationResource {
+    Inline,
+    Eval,
+    Url(ServoUrl),
+}
+
+/// <https://w3c.github.io/webappsec-csp/#violation>
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Violation {
+    pub resource: ViolationResource,
+    /// The effective directive that was violated.
+    pub directive: String,
+    /// The serialized policy that was violated.
+    pub policy: String,
+    pub disposition: PolicyDisposition,
+    /// The unresolved values of the policy's `report-uri` directive.
+    pub report_uris: Vec<String>,
+    /// The first 40 characters of the offending inline content, if the
+    /// violated directive contains `'report-sample'`.
+    pub sample: Option<String>,
+}
+
+#[derive(Serialize)]
+struct CspReport<'a> {
+    #[serde(rename = "csp-report")]
+    body: CspReportBody<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct CspReportBody<'a> {
+    document_uri: String,
+    referrer: &'a str,
+    blocked_uri: String,
+    effective_directive: &'a str,
+    violated_directive: &'a str,
+    original_policy: &'a str,
+    disposition: &'static str,
+    status_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    script_sample: Option<&'a str>,
+}
+
+impl Violation {
+    /// <https://w3c.github.io/webappsec-csp/#dom-securitypolicyviolationevent-blockeduri>
+    pub fn blocked_uri(&self) -> String {
+        match self.resource {
+            ViolationResource::Inline => "inline".to_owned(),
+            ViolationResource::Eval => "eval".to_owned(),
+            ViolationResource::Url(ref url) => strip_url_for_report(url),
+        }
+    }
+
+    pub fn disposition_str(&self) -> &'static str {
+        match self.disposition {
+            PolicyDisposition::Enforce => "enforce",
+            PolicyDisposition::Report => "report",
+        }
+    }
+
+    /// The JSON body POSTed to the policy's `report-uri` endpoints.
+    ///
+    /// <https://w3c.github.io/webappsec-csp/#deprecated-serialize-violation>
+    pub fn report_body(&self, document_url: &ServoUrl, referrer: &str, status_code: u16) -> Vec<u8> {
+        let report = CspReport {
+            body: CspReportBody {
+                document_uri: strip_url_for_report(document_url),
+                referrer: referrer,
+                blocked_uri: self.blocked_uri(),
+                effective_directive: &self.directive,
+                violated_directive: &self.directive,
+                original_policy: &self.policy,
+                disposition: self.disposition_str(),
+                status_code: status_code,
+                script_sample: self.sample.as_ref().map(|sample| &**sample),
+            },
+        };
+        serde_json::to_vec(&report).unwrap_or_default()
+    }
+}
+
+/// <https://w3c.github.io/webappsec-csp/#strip-url-for-use-in-reports>
+fn strip_url_for_report(url: &ServoUrl) -> String {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+    url.into_string()
+}
+
+/// <https://w3c.github.io/webappsec-csp/#csp-list>
+#[derive(Clone, Debug, Default, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct CspList(pub Vec<Policy>);
+
+/// Directives that are ignored when delivered through a `<meta>` element.
+///
+/// <https://w3c.github.io/webappsec-csp/#meta-element>
+const HEADER_ONLY_DIRECTIVES: &'static [&'static str] = &["frame-ancestors", "report-uri", "sandbox"];
+
+/// Maximum length of a violation sample.
+const SAMPLE_LENGTH: usize = 40;
+
+impl CspList {
+    pub fn new() -> CspList {
+        CspList(vec![])
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn append(&mut self, other: CspList) {
+        self.0.extend(other.0)
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#parse-serialized-policy-list>
+    pub fn parse(list: &str, source: PolicySource, disposition: PolicyDisposition) -> CspList {
+        CspList(list.split(',')
+                    .filter_map(|policy| parse_policy(policy, source, disposition))
+                    .collect())
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#parse-response-csp>
+    pub fn from_headers(headers: &Headers) -> CspList {
+        let mut list = CspList::new();
+        let header_names = [
+            ("content-security-policy", PolicyDisposition::Enforce),
+            ("content-security-policy-report-only", PolicyDisposition::Report),
+        ];
+        for &(name, disposition) in header_names.iter() {
+            let values = match headers.get_raw(name) {
+                Some(values) => values,
+                None => continue,
+            };
+            for value in values.iter().filter_map(|value| str::from_utf8(value).ok()) {
+                list.append(CspList::parse(value, PolicySource::Header, disposition));
+            }
+        }
+        list
+    }
+
+    /// The policies of a worker whose script was delivered with `headers` from `url`.
+    /// Workers with a local script inherit the policies of their creator, `creator`.
+    ///
+    /// <https://w3c.github.io/webappsec-csp/#initialize-global-object-csp>
+    pub fn for_worker(creator: &CspList, url: &ServoUrl, headers: Option<&Headers>) -> CspList {
+        match url.scheme() {
+            "about" | "blob" | "data" => creator.clone(),
+            _ => headers.map_or(CspList::new(), CspList::from_headers),
+        }
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#should-block-request>
+    pub fn should_request_be_blocked(&self, request: &Request) -> (CheckResult, Vec<Violation>) {
+        let directive = match effective_directive_for_destination(request.destination) {
+            Some(directive) => directive,
+            None => return (CheckResult::Allowed, vec![]),
+        };
+        let origin = match request.origin {
+            Origin::Origin(ref origin) => origin,
+            Origin::Client => return (CheckResult::Allowed, vec![]),
+        };
+        let url = request.current_url();
+        let nonce = &request.cryptographic_nonce_metadata;
+        self.check(directive, None, |source_list| {
+            if !nonce.is_empty() && source_list_contains_nonce(source_list, nonce) {
+                return true;
+            }
+            source_list_matches_url(source_list, &url, origin, request.redirect_count)
+        }, || ViolationResource::Url(url.clone()))
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#should-block-inline>
+    pub fn should_inline_be_blocked(&self,
+                                    check_type: InlineCheckType,
+                                    nonce: Option<&str>,
+                                    source: &str)
+                                    -> (CheckResult, Vec<Violation>) {
+        let nonce = if check_type.allows_nonces() { nonce.unwrap_or("") } else { "" };
+        self.check(check_type.effective_directive(), Some(source), |source_list| {
+            if !nonce.is_empty() && source_list_contains_nonce(source_list, nonce) {
+                return true;
+            }
+            let allows_hashes = check_type.allows_nonces() ||
+                source_list.iter().any(|expression| expression.eq_ignore_ascii_case("'unsafe-hashes'"));
+            if allows_hashes && source_list_contains_hash(source_list, source) {
+                return true;
+            }
+            source_list_allows_all_inline(source_list, check_type)
+        }, || ViolationResource::Inline)
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#can-compile-strings>
+    pub fn is_js_evaluation_allowed(&self) -> (CheckResult, Vec<Violation>) {
+        self.check("script-src", None, |source_list| {
+            source_list.iter().any(|expression| expression.eq_ignore_ascii_case("'unsafe-eval'"))
+        }, || ViolationResource::Eval)
+    }
+
+    /// Runs `allows` against the source list of the directive governing
+    /// `effective_directive` in every policy, collecting a violation for
+    /// every policy that does not allow it.
+    fn check<F, R>(&self,
+                   effective_directive: &str,
+                   sample: Option<&str>,
+                   allows: F,
+                   resource: R)
+                   -> (CheckResult, Vec<Violation>)
+        where F: Fn(&[String]) -> bool,
+              R: Fn() -> ViolationResource,
+    {
+        let mut result = CheckResult::Allowed;
+        let mut violations = vec![];
+        for policy in &self.0 {
+            let directive = match policy.governing_directive(effective_directive) {
+                Some(directive) => directive,
+                None => continue,
+            };
+            if allows(&directive.value[..]) {
+                continue;
+            }
+            if policy.disposition == PolicyDisposition::Enforce {
+                result = CheckResult::Blocked;
+            }
+            let wants_sample = directive.value.iter().any(|v| v.eq_ignore_ascii_case("'report-sample'"));
+            violations.push(Violation {
+                resource: resource(),
+                directive: effective_directive.to_owned(),
+                policy: policy.text.clone(),
+                disposition: policy.disposition,
+                report_uris: policy.report_uris(),
+                sample: if wants_sample {
+                    sample.map(|sample| sample.chars().take(SAMPLE_LENGTH).collect())
+                } else {
+                    None
+                },
+            });
+        }
+        (result, violations)
+    }
+}
+
+impl Policy {
+    fn directive(&self, name: &str) -> Option<&Directive> {
+        self.directives.iter().find(|directive| directive.name == name)
+    }
+
+    /// The first directive present in this policy from the
+    /// [fallback list](https://w3c.github.io/webappsec-csp/#directive-fallback-list)
+    /// of `effective_directive`.
+    fn governing_directive(&self, effective_directive: &str) -> Option<&Directive> {
+        directive_fallback_list(effective_directive).iter()
+                                                    .filter_map(|name| self.directive(name))
+                                                    .next()
+    }
+
+    fn report_uris(&self) -> Vec<String> {
+        self.directive("report-uri").map_or(vec![], |directive| directive.value.clone())
+    }
+}
+
+/// <https://w3c.github.io/webappsec-csp/#parse-serialized-policy>
+fn parse_policy(serialized: &str, source: PolicySource, disposition: PolicyDisposition) -> Option<Policy> {
+    let mut directives: Vec<Directive> = vec![];
+    for token in serialized.split(';') {
+        let mut parts = token.split(is_ascii_whitespace).filter(|part| !part.is_empty());
+        let name = match parts.next() {
+            Some(name) => name.to_ascii_lowercase(),
+            None => continue,
+        };
+        if !name.chars().all(|c| is_ascii_alphanumeric(c) || c == '-') {
+            continue;
+        }
+        if source == PolicySource::Meta && HEADER_ONLY_DIRECTIVES.contains(&&*name) {
+            continue;
+        }
+        // Only the first occurrence of a directive is honoured.
+        if directives.iter().any(|directive| directive.name == name) {
+            continue;
+        }
+        directives.push(Directive {
+            name: name,
+            value: parts.map(str::to_owned).collect(),
+        });
+    }
+    if directives.is_empty() {
+        return None;
+    }
+    Some(Policy {
+        directives: directives,
+        disposition: disposition,
+        source: source,
+        text: serialized.trim_matches(is_ascii_whitespace).to_owned(),
+    })
+}
+
+fn is_ascii_whitespace(c: char) -> bool {
+    c == ' ' || c == '\t' || c == '\n' || c == '\x0C' || c == '\r'
+}
+
+fn is_ascii_alphanumeric(c: char) -> bool {
+    (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || (c >= '0' && c <= '9')
+}
+
+/// <https://w3c.github.io/webappsec-csp/#effective-directive-for-a-request>
+fn effective_directive_for_destination(destination: Destination) -> Option<&'static str> {
+    match destination {
+        Destination::None => Some("connect-src"),
+        Destination::Manifest => Some("manifest-src"),
+        Destination::Object | Destination::Embed => Some("object-src"),
+        Destination::Font => Some("font-src"),
+        Destination::Image => Some("img-src"),
+        Destination::Audio | Destination::Track | Destination::Video => Some("media-src"),
+        Destination::Script | Destination::Xslt => Some("script-src-elem"),
+        Destination::ServiceWorker | Destination::SharedWorker | Destination::Worker => Some("worker-src"),
+        Destination::Style => Some("style-src-elem"),
+        // Navigations are governed by frame-src and navigate-to, which are
+        // checked by whoever starts the navigation.
+        Destination::Document | Destination::Report => None,
+    }
+}
+
+/// <https://w3c.github.io/webappsec-csp/#directive-fallback-list>
+fn directive_fallback_list(directive: &str) -> &'static [&'static str] {
+    match directive {
+        "script-src-elem" => &["script-src-elem", "script-src", "default-src"],
+        "script-src-attr" => &["script-src-attr", "script-src", "default-src"],
+        "style-src-elem" => &["style-src-elem", "style-src", "default-src"],
+        "style-src-attr" => &["style-src-attr", "style-src", "default-src"],
+        "worker-src" => &["worker-src", "child-src", "script-src", "default-src"],
+        "connect-src" => &["connect-src", "default-src"],
+        "manifest-src" => &["manifest-src", "default-src"],
+        "object-src" => &["object-src", "default-src"],
+        "frame-src" => &["frame-src", "child-src", "default-src"],
+        "media-src" => &["media-src", "default-src"],
+        "font-src" => &["font-src", "default-src"],
+        "img-src" => &["img-src", "default-src"],
+        "script-src" => &["script-src", "default-src"],
+        "style-src" => &["style-src", "default-src"],
+        "child-src" => &["child-src", "default-src"],
+        _ => &[],
+    }
+}
+
+fn source_list_contains_nonce(source_list: &[String], nonce: &str) -> bool {
+    source_list.iter().any(|expression| {
+        expression.len() > "'nonce-'".len() &&
+        expression.to_ascii_lowercase().starts_with("'nonce-") &&
+        expression.ends_with('\'') &&
+        &expression["'nonce-".len()..expression.len() - 1] == nonce
+    })
+}
+
+fn is_hash_source(expression: &str) -> bool {
+    let lower = expression.to_ascii_lowercase();
+    (lower.starts_with("'sha256-") || lower.starts_with("'sha384-") || lower.starts_with("'sha512-")) &&
+        lower.ends_with('\'')
+}
+
+/// The digest algorithm of a hash source, and its base64-encoded value.
+fn parse_hash_source(expression: &str) -> Option<(MessageDigest, &str)> {
+    if !is_hash_source(expression) {
+        return None;
+    }
+    // All the algorithm prefixes, such as `'sha256-`, have the same length.
+    let value = &expression["'sha256-".len()..expression.len() - 1];
+    let digest = match &*expression[1..7].to_ascii_lowercase() {
+        "sha256" => MessageDigest::sha256(),
+        "sha384" => MessageDigest::sha384(),
+        _ => MessageDigest::sha512(),
+    };
+    Some((digest, value))
+}
+
+/// <https://w3c.github.io/webappsec-csp/#match-element-to-source-list> step 5.
+fn source_list_contains_hash(source_list: &[String], source: &str) -> bool {
+    source_list.iter().filter_map(|expression| parse_hash_source(expression)).any(|(digest, value)| {
+        let actual = match hash2(digest, source.as_bytes()) {
+            Ok(actual) => base64::encode(&actual),
+            Err(_) => return false,
+        };
+        // The base64url alphabet is accepted as well.
+        actual == value.replace('-', "+").replace('_', "/")
+    })
+}
+
+/// <https://w3c.github.io/webappsec-csp/#allow-all-inline>
+fn source_list_allows_all_inline(source_list: &[String], check_type: InlineCheckType) -> bool {
+    let mut allow_inline = false;
+    for expression in source_list {
+        let lower = expression.to_ascii_lowercase();
+        if lower.starts_with("'nonce-") || is_hash_source(&lower) {
+            return false;
+        }
+        if lower == "'strict-dynamic'" &&
+           (check_type == InlineCheckType::Script || check_type == InlineCheckType::ScriptAttribute) {
+            return false;
+        }
+        if lower == "'unsafe-inline'" {
+            allow_inline = true;
+        }
+    }
+    allow_inline
+}
+
+/// <https://w3c.github.io/webappsec-csp/#match-url-to-source-list>
+fn source_list_matches_url(source_list: &[String], url: &ServoUrl, origin: &ImmutableOrigin,
+                           redirect_count: u32) -> bool {
+    source_list.iter().any(|expression| expression_matches_url(expression, url, origin, redirect_count))
+}
+
+/// <https://w3c.github.io/webappsec-csp/#match-url-to-source-expression>
+fn expression_matches_url(expression: &str, url: &ServoUrl, origin: &ImmutableOrigin,
+                          redirect_count: u32) -> bool {
+    let origin_scheme = origin.scheme().unwrap_or("");
+
+    // Step 1.
+    if expression == "*" {
+        return is_network_scheme(url.scheme()) || url.scheme() == origin_scheme;
+    }
+
+    // Step 2.
+    if expression.ends_with(':') && is_scheme(&expression[..expression.len() - 1]) {
+        return scheme_part_matches(&expression[..expression.len() - 1], url.scheme());
+    }
+
+    // Step 4.
+    if expression.eq_ignore_ascii_case("'self'") {
+        return self_matches(url, origin);
+    }
+
+    // Keyword, nonce and hash sources never match URLs.
+    if expression.starts_with('\'') {
+        return false;
+    }
+
+    // Step 3.
+    let (scheme, rest) = match expression.find("://") {
+        Some(index) => (Some(&expression[..index]), &expression[index + 3..]),
+        None => (None, expression),
+    };
+    match scheme {
+        Some(scheme) => {
+            if !is_scheme(scheme) || !scheme_part_matches(scheme, url.scheme()) {
+                return false;
+            }
+        },
+        None => {
+            if !scheme_part_matches(origin_scheme, url.scheme()) {
+                return false;
+            }
+        },
+    }
+
+    let host_end = rest.find(|c: char| c == ':' || c == '/').unwrap_or(rest.len());
+    let (host, rest) = rest.split_at(host_end);
+    let url_host = match url.host_str() {
+        Some(url_host) => url_host,
+        None => return false,
+    };
+    if !host_part_matches(host, url_host) {
+        return false;
+    }
+
+    let (port, path) = if rest.starts_with(':') {
+        let path_start = rest.find('/').unwrap_or(rest.len());
+        (Some(&rest[1..path_start]), &rest[path_start..])
+    } else {
+        (None, rest)
+    };
+    if !port_part_matches(port, url) {
+        return false;
+    }
+
+    // Paths are ignored after a redirect, so as not to leak the path of
+    // the redirect target to the page.
+    if !path.is_empty() && redirect_count == 0 {
+        return path_part_matches(path, url.path());
+    }
+    true
+}
+
+fn is_scheme(scheme: &str) -> bool {
+    let mut chars = scheme.chars();
+    chars.next().map_or(false, |c| is_ascii_alphanumeric(c) && !c.is_digit(10)) &&
+        chars.all(|c| is_ascii_alphanumeric(c) || c == '+' || c == '-' || c == '.')
+}
+
+fn is_network_scheme(scheme: &str) -> bool {
+    scheme == "http" || scheme == "https" || scheme == "ws" || scheme == "wss"
+}
+
+/// <https://w3c.github.io/webappsec-csp/#match-schemes>
+fn scheme_part_matches(expression: &str, scheme: &str) -> bool {
+    let expression = expression.to_ascii_lowercase();
+    expression == scheme ||
+        (expression == "http" && scheme == "https") ||
+        (expression == "ws" && (scheme == "wss" || scheme == "http" || scheme == "https")) ||
+        (expression == "wss" && scheme == "https")
+}
+
+/// <https://w3c.github.io/webappsec-csp/#match-hosts>
+fn host_part_matches(expression: &str, host: &str) -> bool {
+    if expression == "*" {
+        return true;
+    }
+    if expression.starts_with("*.") {
+        let suffix = &expression[1..];
+        return host.len() > suffix.len() &&
+            host[host.len() - suffix.len()..].eq_ignore_ascii_case(suffix);
+    }
+    expression.eq_ignore_ascii_case(host)
+}
+
+/// <https://w3c.github.io/webappsec-csp/#match-ports>
+fn port_part_matches(expression: Option<&str>, url: &ServoUrl) -> bool {
+    let url_port = url.port_or_known_default();
+    match expression {
+        None => url.port().is_none(),
+        Some("*") => true,
+        Some(port) => match port.parse::<u16>() {
+            Ok(port) => Some(port) == url_port || (port == 80 && url_port == Some(443)),
+            Err(_) => false,
+        },
+    }
+}
+
+/// <https://w3c.github.io/webappsec-csp/#match-paths>
+fn path_part_matches(expression: &str, path: &str) -> bool {
+    if expression == "/" {
+        return true;
+    }
+    if expression.ends_with('/') {
+        return path.starts_with(expression);
+    }
+    expression == path
+}
+
+/// <https://w3c.github.io/webappsec-csp/#match-url-to-source-expression> step 4.
+fn self_matches(url: &ServoUrl, origin: &ImmutableOrigin) -> bool {
+    if url.origin() == *origin {
+        return true;
+    }
+    let (origin_scheme, origin_host) = match (origin.scheme(), origin.host()) {
+        (Some(scheme), Some(host)) => (scheme, host.to_string()),
+        _ => return false,
+    };
+    if url.host_str() != Some(&*origin_host) {
+        return false;
+    }
+    let upgrade = (origin_scheme == "http" && (url.scheme() == "https" || url.scheme() == "ws" ||
+                                               url.scheme() == "wss")) ||
+        (origin_scheme == "https" && url.scheme() == "wss");
+    upgrade && (url.port().is_none() || url.port() == origin.port())
+}
@@ -0,0 +1,318 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Messages and data types shared between script and the IndexedDB storage thread.
+//!
+//! Values are stored as opaque structured clone buffers; anything that needs to look
+//! inside a value (key paths, index keys) is computed by script before the value is sent.
+
+use ipc_channel::ipc::IpcSender;
+use servo_url::ImmutableOrigin;
+use std::cmp::Ordering;
+
+/// <https://w3c.github.io/IndexedDB/#key-construct>
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
+pub enum IndexedDBKey {
+    Number(f64),
+    Date(f64),
+    String(String),
+    Binary(Vec<u8>),
+    Array(Vec<IndexedDBKey>),
+}
+
+impl IndexedDBKey {
+    fn type_rank(&self) -> u8 {
+        match *self {
+            IndexedDBKey::Number(_) => 0,
+            IndexedDBKey::Date(_) => 1,
+            IndexedDBKey::String(_) => 2,
+            IndexedDBKey::Binary(_) => 3,
+            IndexedDBKey::Array(_) => 4,
+        }
+    }
+}
+
+/// <https://w3c.github.io/IndexedDB/#compare-two-keys>
+impl Ord for IndexedDBKey {
+    fn cmp(&self, other: &IndexedDBKey) -> Ordering {
+        match (self, other) {
+            (&IndexedDBKey::Number(a), &IndexedDBKey::Number(b)) |
+            (&IndexedDBKey::Date(a), &IndexedDBKey::Date(b)) => {
+                // Keys are never NaN, see `convert a value to a key`.
+                a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+            },
+            (&IndexedDBKey::String(ref a), &IndexedDBKey::String(ref b)) => {
+                // Strings are compared by code units.
+                a.encode_utf16().cmp(b.encode_utf16())
+            },
+            (&IndexedDBKey::Binary(ref a), &IndexedDBKey::Binary(ref b)) => a.cmp(b),
+            (&IndexedDBKey::Array(ref a), &IndexedDBKey::Array(ref b)) => a.cmp(b),
+            _ => self.type_rank().cmp(&other.type_rank()),
+        }
+    }
+}
+
+impl PartialOrd for IndexedDBKey {
+    fn partial_cmp(&self, other: &IndexedDBKey) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for IndexedDBKey {
+    fn eq(&self, other: &IndexedDBKey) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for IndexedDBKey {}
+
+/// <https://w3c.github.io/IndexedDB/#key-range-construct>
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct IndexedDBKeyRange {
+    pub lower: Option<IndexedDBKey>,
+    pub upper: Option<IndexedDBKey>,
+    pub lower_open: bool,
+    pub upper_open: bool,
+}
+
+impl IndexedDBKeyRange {
+    /// A range that contains every key
+    pub fn unbounded() -> IndexedDBKeyRange {
+        IndexedDBKeyRange {
+            lower: None,
+            upper: None,
+            lower_open: false,
+            upper_open: false,
+        }
+    }
+
+    /// A range that only contains the given key
+    pub fn only(key: IndexedDBKey) -> IndexedDBKeyRange {
+        IndexedDBKeyRange {
+            lower: Some(key.clone()),
+            upper: Some(key),
+            lower_open: false,
+            upper_open: false,
+        }
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#in>
+    pub fn contains(&self, key: &IndexedDBKey) -> bool {
+        let above_lower = match self.lower {
+            Some(ref lower) if self.lower_open => key > lower,
+            Some(ref lower) => key >= lower,
+            None => true,
+        };
+        let below_upper = match self.upper {
+            Some(ref upper) if self.upper_open => key < upper,
+            Some(ref upper) => key <= upper,
+            None => true,
+        };
+        above_lower && below_upper
+    }
+}
+
+/// <https://w3c.github.io/IndexedDB/#key-path-construct>
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum KeyPath {
+    String(String),
+    Sequence(Vec<String>),
+}
+
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct IndexInfo {
+    pub name: String,
+    pub key_path: KeyPath,
+    pub unique: bool,
+    pub multi_entry: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct ObjectStoreInfo {
+    pub name: String,
+    pub key_path: Option<KeyPath>,
+    pub auto_increment: bool,
+    pub indexes: Vec<IndexInfo>,
+}
+
+/// The schema of a database, as seen by a new connection
+#[derive(Clone, Debug, Default, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct DatabaseInfo {
+    pub version: u64,
+    pub object_stores: Vec<ObjectStoreInfo>,
+}
+
+impl DatabaseInfo {
+    pub fn object_store(&self, name: &str) -> Option<&ObjectStoreInfo> {
+        self.object_stores.iter().find(|store| store.name == name)
+    }
+
+    pub fn object_store_mut(&mut self, name: &str) -> Option<&mut ObjectStoreInfo> {
+        self.object_stores.iter_mut().find(|store| store.name == name)
+    }
+}
+
+/// <https://w3c.github.io/IndexedDB/#transaction-mode>
+#[derive(Clone, Copy, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum IndexedDBTransactionMode {
+    ReadOnly,
+    ReadWrite,
+    VersionChange,
+}
+
+/// <https://w3c.github.io/IndexedDB/#cursor-direction>
+#[derive(Clone, Copy, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum IndexedDBCursorDirection {
+    Next,
+    NextUnique,
+    Prev,
+    PrevUnique,
+}
+
+/// A stored value, along with the key it was found under.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct IndexedDBRecord {
+    /// The index key if the record was found through an index, the primary key otherwise
+    pub key: IndexedDBKey,
+    pub primary_key: IndexedDBKey,
+    /// The structured clone of the value
+    pub value: Vec<u8>,
+}
+
+/// Errors reported by the storage thread, named after the DOMException they become.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum IndexedDBError {
+    Constraint,
+    Data,
+    NotFound,
+    Version,
+    Abort,
+}
+
+pub type IndexedDBResult<T> = Result<T, IndexedDBError>;
+
+/// The source of the records a request operates on
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct IndexedDBSource {
+    pub object_store: String,
+    pub index: Option<String>,
+}
+
+/// An operation run in the scope of a transaction
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum IndexedDBOperation {
+    CreateObjectStore(ObjectStoreInfo),
+    DeleteObjectStore(String),
+    /// Creates an index, along with the index keys script extracted from every existing record
+    CreateIndex(String, IndexInfo, Vec<(IndexedDBKey, Vec<IndexedDBKey>)>),
+    DeleteIndex(String, String),
+    /// Stores a value. A missing key is taken from the store's key generator. The index keys
+    /// are the keys script extracted from the value for each index of the store.
+    Put {
+        object_store: String,
+        key: Option<IndexedDBKey>,
+        value: Vec<u8>,
+        index_keys: Vec<(String, Vec<IndexedDBKey>)>,
+        no_overwrite: bool,
+    },
+    /// Replies with the first record in range
+    Get(IndexedDBSource, IndexedDBKeyRange),
+    /// Replies with up to `count` records in range
+    GetAll(IndexedDBSource, IndexedDBKeyRange, Option<u32>),
+    Count(IndexedDBSource, IndexedDBKeyRange),
+    Delete(String, IndexedDBKeyRange),
+    Clear(String),
+    /// Replies with the next record a cursor moves to. `position` is the cursor's current
+    /// (key, primary key), `key` and `primary_key` are the targets given to `continue` and
+    /// `continuePrimaryKey`, and `count` is the number of records to advance by.
+    Iterate {
+        source: IndexedDBSource,
+        range: IndexedDBKeyRange,
+        direction: IndexedDBCursorDirection,
+        position: Option<(IndexedDBKey, IndexedDBKey)>,
+        key: Option<IndexedDBKey>,
+        primary_key: Option<IndexedDBKey>,
+        count: u32,
+    },
+}
+
+/// The reply to an `IndexedDBOperation`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum IndexedDBReply {
+    None,
+    Key(IndexedDBKey),
+    Record(Option<IndexedDBRecord>),
+    Records(Vec<IndexedDBRecord>),
+    Count(u64),
+}
+
+/// What happens to a request to open a database, sent as it progresses
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum IndexedDBOpenEvent {
+    /// Other connections to the database did not close after being sent a versionchange
+    /// event, with the old and new versions
+    Blocked(u64, u64),
+    /// The database is open at its current version, as the given connection
+    Opened(u64, DatabaseInfo),
+    /// The database is being upgraded to `version` by the version change transaction
+    /// `transaction`. `info` is the schema of the database before the upgrade.
+    Upgrade {
+        connection: u64,
+        transaction: u64,
+        version: u64,
+        info: DatabaseInfo,
+    },
+    Error(IndexedDBError),
+}
+
+/// An event sent to an open connection
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum IndexedDBConnectionEvent {
+    /// Another connection wants to upgrade the database to the given version, or to delete
+    /// it. The connection should be closed, after which the storage thread is sent a
+    /// `VersionChangeFired` message either way.
+    VersionChange {
+        connection: u64,
+        old_version: u64,
+        new_version: Option<u64>,
+    },
+}
+
+#[derive(Deserialize, Serialize)]
+pub enum IndexedDBThreadMsg {
+    /// Opens a database, creating or upgrading it to the given version if needed. Opening
+    /// waits for the transactions and open requests queued before it, and for other
+    /// connections to close if the database is upgraded. The events of the new connection
+    /// are sent on the second sender.
+    Open(IpcSender<IndexedDBOpenEvent>, ImmutableOrigin, String, Option<u64>, IpcSender<IndexedDBConnectionEvent>),
+
+    /// Closes a connection
+    CloseConnection(u64),
+
+    /// Tells that a connection handled the versionchange event it was sent
+    VersionChangeFired(u64),
+
+    /// Replies with the name and version of every database of an origin
+    Databases(IpcSender<Vec<(String, u64)>>, ImmutableOrigin),
+
+    /// Deletes a database, replying with its version, or 0 if it did not exist
+    DeleteDatabase(IpcSender<u64>, ImmutableOrigin, String),
+
+    /// Creates a transaction on a database, replying with its id. Operations of a transaction
+    /// only run once no conflicting transaction on the same database is running. Version
+    /// change transactions are only created by `Open`.
+    CreateTransaction(IpcSender<u64>, ImmutableOrigin, String, IndexedDBTransactionMode),
+
+    /// Runs an operation in a transaction
+    Operation(IpcSender<IndexedDBResult<IndexedDBReply>>, u64, IndexedDBOperation),
+
+    /// Commits a transaction once its operations have run, writing the database to disk
+    Commit(IpcSender<IndexedDBResult<()>>, u64),
+
+    /// Aborts a transaction, reverting every change it made
+    Abort(IpcSender<()>, u64),
+
+    /// send a reply when done cleaning up thread resources and then shut it down
+    Exit(IpcSender<()>),
+}
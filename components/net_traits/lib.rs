@@ -5,6 +5,7 @@
 
 #![deny(unsafe_code)]
 
+extern crate base64;
 extern crate cookie as cookie_rs;
 extern crate hyper;
 extern crate hyper_serde;
@@ -16,7 +17,9 @@ extern crate ipc_channel;
 #[macro_use] extern crate malloc_size_of_derive;
 extern crate msg;
 extern crate num_traits;
+extern crate openssl;
 #[macro_use] extern crate serde;
+extern crate serde_json;
 extern crate servo_config;
 extern crate servo_url;
 extern crate url;
@@ -24,6 +27,7 @@ extern crate uuid;
 extern crate webrender_api;
 
 use cookie_rs::Cookie;
+use csp::Violation;
 use filemanager_thread::FileManagerThreadMsg;
 use hyper::Error as HyperError;
 use hyper::header::{ContentType, Headers, ReferrerPolicy as ReferrerPolicyHeader};
@@ -36,12 +40,15 @@ use ipc_channel::router::ROUTER;
 use request::{Request, RequestInit};
 use response::{HttpsState, Response, ResponseInit};
 use servo_url::ServoUrl;
+use indexeddb_thread::IndexedDBThreadMsg;
 use std::error::Error;
 use storage_thread::StorageThreadMsg;
 
 pub mod blob_url_store;
+pub mod csp;
 pub mod filemanager_thread;
 pub mod image_cache;
+pub mod indexeddb_thread;
 pub mod net_error_list;
 pub mod pub_domains;
 pub mod request;
@@ -155,6 +162,7 @@ pub enum FetchResponseMsg {
     ProcessResponse(Result<FetchMetadata, NetworkError>),
     ProcessResponseChunk(Vec<u8>),
     ProcessResponseEOF(Result<(), NetworkError>),
+    ProcessCspViolations(Vec<Violation>),
 }
 
 pub trait FetchTaskTarget {
@@ -180,6 +188,11 @@ pub trait FetchTaskTarget {
     ///
     /// Fired when the response is fully fetched
     fn process_response_eof(&mut self, response: &Response);
+
+    /// <https://w3c.github.io/webappsec-csp/#report-violation>
+    ///
+    /// Fired when the request violates one of its Content Security Policies
+    fn process_csp_violations(&mut self, request: &Request, violations: Vec<Violation>);
 }
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -205,6 +218,10 @@ pub trait FetchResponseListener {
     fn process_response(&mut self, metadata: Result<FetchMetadata, NetworkError>);
     fn process_response_chunk(&mut self, chunk: Vec<u8>);
     fn process_response_eof(&mut self, response: Result<(), NetworkError>);
+
+    /// Violations of the fetching document's Content Security Policy are
+    /// ignored unless the listener knows which document to report them to.
+    fn process_csp_violations(&mut self, _violations: Vec<Violation>) {}
 }
 
 impl FetchTaskTarget for IpcSender<FetchResponseMsg> {
@@ -231,6 +248,10 @@ impl FetchTaskTarget for IpcSender<FetchResponseMsg> {
             let _ = self.send(FetchResponseMsg::ProcessResponseEOF(Ok(())));
         }
     }
+
+    fn process_csp_violations(&mut self, _: &Request, violations: Vec<Violation>) {
+        let _ = self.send(FetchResponseMsg::ProcessCspViolations(violations));
+    }
 }
 
 
@@ -247,6 +268,7 @@ impl<T: FetchResponseListener> Action<T> for FetchResponseMsg {
             FetchResponseMsg::ProcessResponse(meta) => listener.process_response(meta),
             FetchResponseMsg::ProcessResponseChunk(data) => listener.process_response_chunk(data),
             FetchResponseMsg::ProcessResponseEOF(data) => listener.process_response_eof(data),
+            FetchResponseMsg::ProcessCspViolations(violations) => listener.process_csp_violations(violations),
         }
     }
 }
@@ -277,13 +299,18 @@ pub trait IpcSend<T>
 pub struct ResourceThreads {
     core_thread: CoreResourceThread,
     storage_thread: IpcSender<StorageThreadMsg>,
+    indexeddb_thread: IpcSender<IndexedDBThreadMsg>,
 }
 
 impl ResourceThreads {
-    pub fn new(c: CoreResourceThread, s: IpcSender<StorageThreadMsg>) -> ResourceThreads {
+    pub fn new(c: CoreResourceThread,
+               s: IpcSender<StorageThreadMsg>,
+               i: IpcSender<IndexedDBThreadMsg>)
+               -> ResourceThreads {
         ResourceThreads {
             core_thread: c,
             storage_thread: s,
+            indexeddb_thread: i,
         }
     }
 }
@@ -308,6 +335,16 @@ impl IpcSend<StorageThreadMsg> for ResourceThreads {
     }
 }
 
+impl IpcSend<IndexedDBThreadMsg> for ResourceThreads {
+    fn send(&self, msg: IndexedDBThreadMsg) -> IpcSendResult {
+        self.indexeddb_thread.send(msg)
+    }
+
+    fn sender(&self) -> IpcSender<IndexedDBThreadMsg> {
+        self.indexeddb_thread.clone()
+    }
+}
+
 // Ignore the sub-fields
 malloc_size_of_is_0!(ResourceThreads);
 
@@ -366,6 +403,8 @@ pub enum CoreResourceMsg {
     Cancel(ResourceId),
     /// Synchronization message solely for knowing the state of the ResourceChannelManager loop
     Synchronize(IpcSender<()>),
+    /// Remove every response from the HTTP cache, in memory and on disk, replying once done
+    ClearCache(IpcSender<()>),
     /// Send the network sender in constellation to CoreResourceThread
     NetworkMediator(IpcSender<CustomResponseMediator>),
     /// Message forwarded to file manager's handler
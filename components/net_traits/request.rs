@@ -3,6 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use ReferrerPolicy;
+use csp::CspList;
 use hyper::header::Headers;
 use hyper::method::Method;
 use msg::constellation_msg::PipelineId;
@@ -164,8 +165,10 @@ pub struct RequestInit {
     pub pipeline_id: Option<PipelineId>,
     pub redirect_mode: RedirectMode,
     pub integrity_metadata: String,
+    pub cryptographic_nonce_metadata: String,
     // to keep track of redirects
     pub url_list: Vec<ServoUrl>,
+    pub csp_list: CspList,
 }
 
 impl Default for RequestInit {
@@ -190,7 +193,9 @@ impl Default for RequestInit {
             pipeline_id: None,
             redirect_mode: RedirectMode::Follow,
             integrity_metadata: "".to_owned(),
+            cryptographic_nonce_metadata: "".to_owned(),
             url_list: vec![],
+            csp_list: CspList::new(),
         }
     }
 }
@@ -248,6 +253,8 @@ pub struct Request {
     pub redirect_mode: RedirectMode,
     /// <https://fetch.spec.whatwg.org/#concept-request-integrity-metadata>
     pub integrity_metadata: String,
+    /// <https://fetch.spec.whatwg.org/#concept-request-nonce-metadata>
+    pub cryptographic_nonce_metadata: String,
     // Use the last method on url_list to act as spec current url field, and
     // first method to act as spec url field
     /// <https://fetch.spec.whatwg.org/#concept-request-url-list>
@@ -256,6 +263,11 @@ pub struct Request {
     pub redirect_count: u32,
     /// <https://fetch.spec.whatwg.org/#concept-request-response-tainting>
     pub response_tainting: ResponseTainting,
+    /// The CSP list of the request's client, used to decide whether
+    /// the request should be blocked.
+    ///
+    /// <https://w3c.github.io/webappsec-csp/#should-block-request>
+    pub csp_list: CspList,
 }
 
 impl Request {
@@ -287,9 +299,11 @@ impl Request {
             cache_mode: CacheMode::Default,
             redirect_mode: RedirectMode::Follow,
             integrity_metadata: String::new(),
+            cryptographic_nonce_metadata: String::new(),
             url_list: vec![url],
             redirect_count: 0,
             response_tainting: ResponseTainting::Basic,
+            csp_list: CspList::new(),
         }
     }
 
@@ -324,6 +338,8 @@ impl Request {
         req.redirect_count = url_list.len() as u32 - 1;
         req.url_list = url_list;
         req.integrity_metadata = init.integrity_metadata;
+        req.cryptographic_nonce_metadata = init.cryptographic_nonce_metadata;
+        req.csp_list = init.csp_list;
         req
     }
 
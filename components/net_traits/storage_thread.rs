@@ -35,6 +35,13 @@ pub enum StorageThreadMsg {
     /// clears the associated storage data by removing all the key/value pairs
     Clear(IpcSender<bool>, ServoUrl, StorageType),
 
+    /// gets the serialized origins that have data in the given storage, along with the number
+    /// of bytes each of them uses
+    Origins(IpcSender<Vec<(String, usize)>>, StorageType),
+
+    /// removes all the data stored by the given serialized origin
+    ClearOrigin(IpcSender<bool>, String, StorageType),
+
     /// send a reply when done cleaning up thread resources and then shut it down
     Exit(IpcSender<()>),
 }
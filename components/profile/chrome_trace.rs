@@ -0,0 +1,126 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A module for writing time profiler traces in the [Chrome Trace Event format]
+//! (https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU/), which
+//! chrome://tracing and Perfetto can load.
+
+use profile_traits::time::{ProfilerCategory, ProfilerThread, TimerMetadata, TimerMetadataFrameType};
+use profile_traits::time::TimerMetadataReflowType;
+use serde_json::{self, Map, Value};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use time::category_name;
+
+/// A timed section of a thread.
+struct Span {
+    category: ProfilerCategory,
+    metadata: Option<TimerMetadata>,
+    start: u64,
+    end: u64,
+}
+
+/// The traces of every thread, which are written out together once profiling ends, as the
+/// sections a thread reports end before the sections they are nested in.
+#[derive(Default)]
+pub struct ChromeTrace {
+    /// The name and spans of each thread, with the threads in the order they were seen.
+    threads: Vec<(Option<String>, Vec<Span>)>,
+    /// The index in `threads` of each thread id. Sections reported without a thread share a
+    /// track.
+    thread_indices: HashMap<Option<usize>, usize>,
+}
+
+impl ChromeTrace {
+    pub fn new() -> ChromeTrace {
+        ChromeTrace::default()
+    }
+
+    /// Record a section of the given thread.
+    pub fn add(&mut self,
+               thread: Option<ProfilerThread>,
+               category: &(ProfilerCategory, Option<TimerMetadata>),
+               time: (u64, u64)) {
+        let (id, name) = match thread {
+            Some(thread) => (Some(thread.id), thread.name),
+            None => (None, None),
+        };
+        let threads = &mut self.threads;
+        let index = *self.thread_indices.entry(id).or_insert_with(|| {
+            threads.push((name, vec![]));
+            threads.len() - 1
+        });
+        threads[index].1.push(Span {
+            category: category.0,
+            metadata: category.1.clone(),
+            start: time.0,
+            end: time.1,
+        });
+    }
+
+    /// The trace events, with a `thread_name` metadata event for each thread and a begin and an
+    /// end event for each section. The events of a thread are in the order they happened, so that
+    /// sections nest. Threads without a name are called "Unnamed".
+    pub fn events(&self) -> Vec<Value> {
+        let origin = self.threads.iter()
+                                 .flat_map(|&(_, ref spans)| spans.iter().map(|span| span.start))
+                                 .min()
+                                 .unwrap_or(0);
+        let mut events = vec![];
+        for (tid, &(ref name, ref spans)) in self.threads.iter().enumerate() {
+            events.push(json!({
+                "name": "thread_name",
+                "ph": "M",
+                "pid": 0,
+                "tid": tid,
+                "args": { "name": name.as_ref().map_or("Unnamed", |name| &**name) },
+            }));
+
+            // Sections that end when another starts end first, outer sections begin first and
+            // inner sections end first.
+            let mut boundaries = vec![];
+            for span in spans {
+                boundaries.push(((span.start, 1, Reverse(span.end)), "B", span));
+                boundaries.push(((span.end, 0, Reverse(span.start)), "E", span));
+            }
+            boundaries.sort_by_key(|&(key, _, _)| key);
+
+            for ((timestamp, _, _), phase, span) in boundaries {
+                let mut event = Map::new();
+                event.insert("name".to_owned(), Value::from(category_name(&span.category)));
+                event.insert("cat".to_owned(), Value::from("servo"));
+                event.insert("ph".to_owned(), Value::from(phase));
+                event.insert("ts".to_owned(), Value::from((timestamp - origin) as f64 / 1000.));
+                event.insert("pid".to_owned(), Value::from(0));
+                event.insert("tid".to_owned(), Value::from(tid));
+                if let (Some(metadata), "B") = (span.metadata.as_ref(), phase) {
+                    event.insert("args".to_owned(), json!({
+                        "url": metadata.url,
+                        "iframe": metadata.iframe == TimerMetadataFrameType::IFrame,
+                        "incremental": metadata.incremental == TimerMetadataReflowType::Incremental,
+                    }));
+                }
+                events.push(Value::Object(event));
+            }
+        }
+        events
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let trace = json!({
+            "traceEvents": self.events(),
+            "displayTimeUnit": "ms",
+        });
+        serde_json::to_writer(&mut *writer, &trace).map_err(io::Error::from)?;
+        writeln!(writer, "")
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        self.write_to(&mut file)
+    }
+}
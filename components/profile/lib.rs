@@ -20,12 +20,14 @@ extern crate profile_traits;
 extern crate regex;
 #[macro_use]
 extern crate serde;
+#[macro_use]
 extern crate serde_json;
 extern crate servo_config;
 #[cfg(target_os = "macos")]
 extern crate task_info;
 extern crate time as std_time;
 
+pub mod chrome_trace;
 #[allow(unsafe_code)]
 mod heartbeats;
 #[allow(unsafe_code)]
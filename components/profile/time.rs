@@ -4,13 +4,14 @@
 
 //! Timing functions.
 
+use chrome_trace::ChromeTrace;
 use heartbeats;
 use influent::client::{Client, Credentials};
 use influent::create_client;
 use influent::measurement::{Measurement, Value};
-use ipc_channel::ipc::{self, IpcReceiver};
+use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
 use profile_traits::energy::{energy_interval_ms, read_energy_uj};
-use profile_traits::time::{ProfilerCategory, ProfilerChan, ProfilerMsg, TimerMetadata};
+use profile_traits::time::{ProfilerCategory, ProfilerChan, ProfilerMsg, ProfilerSample, TimerMetadata};
 use profile_traits::time::{TimerMetadataFrameType, TimerMetadataReflowType};
 use servo_config::opts::OutputOptions;
 use std::{f64, thread, u32, u64};
@@ -104,63 +105,67 @@ impl Formattable for ProfilerCategory {
             ProfilerCategory::LayoutTextShaping => "| + ",
             _ => ""
         };
-        let name = match *self {
-            ProfilerCategory::Compositing => "Compositing",
-            ProfilerCategory::LayoutPerform => "Layout",
-            ProfilerCategory::LayoutStyleRecalc => "Style Recalc",
-            ProfilerCategory::LayoutTextShaping => "Text Shaping",
-            ProfilerCategory::LayoutRestyleDamagePropagation => "Restyle Damage Propagation",
-            ProfilerCategory::LayoutNonIncrementalReset => "Non-incremental reset (temporary)",
-            ProfilerCategory::LayoutSelectorMatch => "Selector Matching",
-            ProfilerCategory::LayoutTreeBuilder => "Tree Building",
-            ProfilerCategory::LayoutDamagePropagate => "Damage Propagation",
-            ProfilerCategory::LayoutDisplayListSorting => "Sorting Display List",
-            ProfilerCategory::LayoutGeneratedContent => "Generated Content Resolution",
-            ProfilerCategory::LayoutFloatPlacementSpeculation => "Float Placement Speculation",
-            ProfilerCategory::LayoutMain => "Primary Layout Pass",
-            ProfilerCategory::LayoutStoreOverflow => "Store Overflow",
-            ProfilerCategory::LayoutParallelWarmup => "Parallel Warmup",
-            ProfilerCategory::LayoutDispListBuild => "Display List Construction",
-            ProfilerCategory::NetHTTPRequestResponse => "Network HTTP Request/Response",
-            ProfilerCategory::PaintingPerTile => "Painting Per Tile",
-            ProfilerCategory::PaintingPrepBuff => "Buffer Prep",
-            ProfilerCategory::Painting => "Painting",
-            ProfilerCategory::ImageDecoding => "Image Decoding",
-            ProfilerCategory::ImageSaving => "Image Saving",
-            ProfilerCategory::ScriptAttachLayout => "Script Attach Layout",
-            ProfilerCategory::ScriptConstellationMsg => "Script Constellation Msg",
-            ProfilerCategory::ScriptDevtoolsMsg => "Script Devtools Msg",
-            ProfilerCategory::ScriptDocumentEvent => "Script Document Event",
-            ProfilerCategory::ScriptDomEvent => "Script Dom Event",
-            ProfilerCategory::ScriptEvaluate => "Script JS Evaluate",
-            ProfilerCategory::ScriptFileRead => "Script File Read",
-            ProfilerCategory::ScriptImageCacheMsg => "Script Image Cache Msg",
-            ProfilerCategory::ScriptInputEvent => "Script Input Event",
-            ProfilerCategory::ScriptNetworkEvent => "Script Network Event",
-            ProfilerCategory::ScriptParseHTML => "Script Parse HTML",
-            ProfilerCategory::ScriptParseXML => "Script Parse XML",
-            ProfilerCategory::ScriptPlannedNavigation => "Script Planned Navigation",
-            ProfilerCategory::ScriptResize => "Script Resize",
-            ProfilerCategory::ScriptEvent => "Script Event",
-            ProfilerCategory::ScriptUpdateReplacedElement => "Script Update Replaced Element",
-            ProfilerCategory::ScriptSetScrollState => "Script Set Scroll State",
-            ProfilerCategory::ScriptSetViewport => "Script Set Viewport",
-            ProfilerCategory::ScriptTimerEvent => "Script Timer Event",
-            ProfilerCategory::ScriptStylesheetLoad => "Script Stylesheet Load",
-            ProfilerCategory::ScriptWebSocketEvent => "Script Web Socket Event",
-            ProfilerCategory::ScriptWorkerEvent => "Script Worker Event",
-            ProfilerCategory::ScriptServiceWorkerEvent => "Script Service Worker Event",
-            ProfilerCategory::ScriptEnterFullscreen => "Script Enter Fullscreen",
-            ProfilerCategory::ScriptExitFullscreen => "Script Exit Fullscreen",
-            ProfilerCategory::ScriptWebVREvent => "Script WebVR Event",
-            ProfilerCategory::ScriptWorkletEvent => "Script Worklet Event",
-            ProfilerCategory::ScriptPerformanceEvent => "Script Performance Event",
-            ProfilerCategory::TimeToFirstPaint => "Time To First Paint",
-            ProfilerCategory::TimeToFirstContentfulPaint => "Time To First Contentful Paint",
-            ProfilerCategory::TimeToInteractive => "Time to Interactive",
-            ProfilerCategory::ApplicationHeartbeat => "Application Heartbeat",
-        };
-        format!("{}{}", padding, name)
+        format!("{}{}", padding, category_name(self))
+    }
+}
+
+/// The name of a category, as printed in profiles.
+pub fn category_name(category: &ProfilerCategory) -> &'static str {
+    match *category {
+        ProfilerCategory::Compositing => "Compositing",
+        ProfilerCategory::LayoutPerform => "Layout",
+        ProfilerCategory::LayoutStyleRecalc => "Style Recalc",
+        ProfilerCategory::LayoutTextShaping => "Text Shaping",
+        ProfilerCategory::LayoutRestyleDamagePropagation => "Restyle Damage Propagation",
+        ProfilerCategory::LayoutNonIncrementalReset => "Non-incremental reset (temporary)",
+        ProfilerCategory::LayoutSelectorMatch => "Selector Matching",
+        ProfilerCategory::LayoutTreeBuilder => "Tree Building",
+        ProfilerCategory::LayoutDamagePropagate => "Damage Propagation",
+        ProfilerCategory::LayoutDisplayListSorting => "Sorting Display List",
+        ProfilerCategory::LayoutGeneratedContent => "Generated Content Resolution",
+        ProfilerCategory::LayoutFloatPlacementSpeculation => "Float Placement Speculation",
+        ProfilerCategory::LayoutMain => "Primary Layout Pass",
+        ProfilerCategory::LayoutStoreOverflow => "Store Overflow",
+        ProfilerCategory::LayoutParallelWarmup => "Parallel Warmup",
+        ProfilerCategory::LayoutDispListBuild => "Display List Construction",
+        ProfilerCategory::NetHTTPRequestResponse => "Network HTTP Request/Response",
+        ProfilerCategory::PaintingPerTile => "Painting Per Tile",
+        ProfilerCategory::PaintingPrepBuff => "Buffer Prep",
+        ProfilerCategory::Painting => "Painting",
+        ProfilerCategory::ImageDecoding => "Image Decoding",
+        ProfilerCategory::ImageSaving => "Image Saving",
+        ProfilerCategory::ScriptAttachLayout => "Script Attach Layout",
+        ProfilerCategory::ScriptConstellationMsg => "Script Constellation Msg",
+        ProfilerCategory::ScriptDevtoolsMsg => "Script Devtools Msg",
+        ProfilerCategory::ScriptDocumentEvent => "Script Document Event",
+        ProfilerCategory::ScriptDomEvent => "Script Dom Event",
+        ProfilerCategory::ScriptEvaluate => "Script JS Evaluate",
+        ProfilerCategory::ScriptFileRead => "Script File Read",
+        ProfilerCategory::ScriptImageCacheMsg => "Script Image Cache Msg",
+        ProfilerCategory::ScriptInputEvent => "Script Input Event",
+        ProfilerCategory::ScriptNetworkEvent => "Script Network Event",
+        ProfilerCategory::ScriptParseHTML => "Script Parse HTML",
+        ProfilerCategory::ScriptParseXML => "Script Parse XML",
+        ProfilerCategory::ScriptPlannedNavigation => "Script Planned Navigation",
+        ProfilerCategory::ScriptResize => "Script Resize",
+        ProfilerCategory::ScriptEvent => "Script Event",
+        ProfilerCategory::ScriptUpdateReplacedElement => "Script Update Replaced Element",
+        ProfilerCategory::ScriptSetScrollState => "Script Set Scroll State",
+        ProfilerCategory::ScriptSetViewport => "Script Set Viewport",
+        ProfilerCategory::ScriptTimerEvent => "Script Timer Event",
+        ProfilerCategory::ScriptStylesheetLoad => "Script Stylesheet Load",
+        ProfilerCategory::ScriptWebSocketEvent => "Script Web Socket Event",
+        ProfilerCategory::ScriptWorkerEvent => "Script Worker Event",
+        ProfilerCategory::ScriptServiceWorkerEvent => "Script Service Worker Event",
+        ProfilerCategory::ScriptEnterFullscreen => "Script Enter Fullscreen",
+        ProfilerCategory::ScriptExitFullscreen => "Script Exit Fullscreen",
+        ProfilerCategory::ScriptWebVREvent => "Script WebVR Event",
+        ProfilerCategory::ScriptWorkletEvent => "Script Worklet Event",
+        ProfilerCategory::ScriptPerformanceEvent => "Script Performance Event",
+        ProfilerCategory::TimeToFirstPaint => "Time To First Paint",
+        ProfilerCategory::TimeToFirstContentfulPaint => "Time To First Contentful Paint",
+        ProfilerCategory::TimeToInteractive => "Time to Interactive",
+        ProfilerCategory::ApplicationHeartbeat => "Application Heartbeat",
     }
 }
 
@@ -173,6 +178,10 @@ pub struct Profiler {
     output: Option<OutputOptions>,
     pub last_msg: Option<ProfilerMsg>,
     trace: Option<TraceDump>,
+    /// The sections reported so far, when writing a Chrome trace.
+    chrome_trace: Option<ChromeTrace>,
+    /// The channels to pass reported sections on to.
+    listeners: Vec<IpcSender<ProfilerSample>>,
 }
 
 impl Profiler {
@@ -191,6 +200,7 @@ impl Profiler {
                 // decide if we need to spawn the timer thread
                 match option {
                     &OutputOptions::FileName(_) |
+                    &OutputOptions::ChromeTrace(_) |
                     &OutputOptions::DB(_, _, _, _) => { /* no timer thread needed */ },
                     &OutputOptions::Stdout(period) => {
                         // Spawn a timer thread
@@ -217,8 +227,10 @@ impl Profiler {
                         profiler.start();
                     }).expect("Thread spawning failed");
                 } else {
-                    // No-op to handle messages when the time profiler is not printing:
+                    // No-op to handle messages when the time profiler is not printing, other than
+                    // passing them on to listeners:
                     thread::Builder::new().name("Time profiler".to_owned()).spawn(move || {
+                        let mut listeners = vec![];
                         loop {
                             match port.recv() {
                                 Err(_) => break,
@@ -226,6 +238,8 @@ impl Profiler {
                                     let _ = chan.send(());
                                     break;
                                 },
+                                Ok(ProfilerMsg::Listen(listener)) => listeners.push(listener),
+                                Ok(ProfilerMsg::Time(k, t, _, _)) => notify_listeners(&mut listeners, k, t),
                                 _ => {}
                             }
                         }
@@ -268,7 +282,8 @@ impl Profiler {
                     let ProfilerChan(ref c) = profiler_chan;
                     if let Err(_) = c.send(ProfilerMsg::Time((ProfilerCategory::ApplicationHeartbeat, None),
                                                              (start_time, end_time),
-                                                             (start_energy, end_energy))) {
+                                                             (start_energy, end_energy),
+                                                             None)) {
                         return;
                     }
                     start_time = end_time;
@@ -281,12 +296,18 @@ impl Profiler {
     }
 
     pub fn new(port: IpcReceiver<ProfilerMsg>, trace: Option<TraceDump>, output: Option<OutputOptions>) -> Profiler {
+        let chrome_trace = match output {
+            Some(OutputOptions::ChromeTrace(_)) => Some(ChromeTrace::new()),
+            _ => None,
+        };
         Profiler {
             port: port,
             buckets: BTreeMap::new(),
             output: output,
             last_msg: None,
             trace: trace,
+            chrome_trace: chrome_trace,
+            listeners: vec![],
         }
     }
 
@@ -304,14 +325,23 @@ impl Profiler {
 
     fn handle_msg(&mut self, msg: ProfilerMsg) -> bool {
         match msg.clone() {
-            ProfilerMsg::Time(k, t, e) => {
+            ProfilerMsg::Time(k, t, e, thread) => {
                 heartbeats::maybe_heartbeat(&k.0, t.0, t.1, e.0, e.1);
                 if let Some(ref mut trace) = self.trace {
                     trace.write_one(&k, t, e);
                 }
+                if let Some(ref mut chrome_trace) = self.chrome_trace {
+                    chrome_trace.add(thread, &k, t);
+                }
+                notify_listeners(&mut self.listeners, k.clone(), t);
                 let ms = (t.1 - t.0) as f64 / 1000000f64;
                 self.find_or_insert(k, ms);
             },
+            ProfilerMsg::Listen(listener) => {
+                self.listeners.push(listener);
+                // Listening is not reported data, so it does not count as the last message.
+                return true;
+            },
             ProfilerMsg::Print => if let Some(ProfilerMsg::Time(..)) = self.last_msg {
                 // only print if more data has arrived since the last printout
                 self.print_buckets();
@@ -428,11 +458,34 @@ impl Profiler {
                 }
 
             },
+            Some(OutputOptions::ChromeTrace(ref filename)) => {
+                if let Some(ref chrome_trace) = self.chrome_trace {
+                    if let Err(e) = chrome_trace.write(filename) {
+                        warn!("Could not write the Chrome trace to {}: {}", filename, e);
+                    }
+                }
+            },
             None => { /* Do nothing if no output option has been set */ },
         };
     }
 }
 
+/// Pass a reported section on to the listeners, forgetting those that hung up.
+fn notify_listeners(listeners: &mut Vec<IpcSender<ProfilerSample>>,
+                    (category, metadata): (ProfilerCategory, Option<TimerMetadata>),
+                    (start, end): (u64, u64)) {
+    if listeners.is_empty() {
+        return;
+    }
+    let sample = ProfilerSample {
+        category: category,
+        metadata: metadata,
+        start: start,
+        end: end,
+    };
+    listeners.retain(|listener| listener.send(sample.clone()).is_ok());
+}
+
 fn enforce_range<T>(min: T, max: T, value: T) -> T where T: Ord {
     assert!(min <= max);
     match value.cmp(&max) {
@@ -176,6 +176,10 @@ pub enum ProfilerMsg {
     /// Triggers printing of the memory profiling metrics.
     Print,
 
+    /// Collects the reports of every registered reporter, and sends them on the given channel.
+    /// Unlike printed reports, their paths are not prefixed with "explicit".
+    CollectReports(IpcSender<Vec<Report>>),
+
     /// Tells the memory profiler to shut down.
     Exit,
 }
@@ -7,8 +7,15 @@ extern crate time as std_time;
 use energy::read_energy_uj;
 use ipc_channel::ipc::IpcSender;
 use self::std_time::precise_time_ns;
-use servo_config::opts;
+use servo_config::opts::{self, OutputOptions};
 use signpost;
+use std::borrow::ToOwned;
+use std::sync::atomic::{ATOMIC_USIZE_INIT, AtomicUsize, Ordering};
+use std::thread;
+
+static NEXT_THREAD_ID: AtomicUsize = ATOMIC_USIZE_INIT;
+
+thread_local!(static THREAD_ID: usize = NEXT_THREAD_ID.fetch_add(1, Ordering::SeqCst));
 
 #[derive(Clone, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct TimerMetadata {
@@ -30,14 +37,44 @@ impl ProfilerChan {
 
 #[derive(Clone, Deserialize, Serialize)]
 pub enum ProfilerMsg {
-    /// Normal message used for reporting time
-    Time((ProfilerCategory, Option<TimerMetadata>), (u64, u64), (u64, u64)),
+    /// Normal message used for reporting time, with the thread that reports it if the profile
+    /// is written as a Chrome trace.
+    Time((ProfilerCategory, Option<TimerMetadata>), (u64, u64), (u64, u64), Option<ProfilerThread>),
     /// Message used to force print the profiling metrics
     Print,
+    /// Pass on every section of time reported from now on, until the receiver hangs up.
+    Listen(IpcSender<ProfilerSample>),
     /// Tells the profiler to shut down.
     Exit(IpcSender<()>),
 }
 
+/// The thread that reported a section of time. Threads are told apart by their id, as several
+/// threads can share a name.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ProfilerThread {
+    pub id: usize,
+    pub name: Option<String>,
+}
+
+impl ProfilerThread {
+    pub fn current() -> ProfilerThread {
+        ProfilerThread {
+            id: THREAD_ID.with(|id| *id),
+            name: thread::current().name().map(ToOwned::to_owned),
+        }
+    }
+}
+
+/// A section of time reported to the profiler, as passed on to its listeners.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ProfilerSample {
+    pub category: ProfilerCategory,
+    pub metadata: Option<TimerMetadata>,
+    /// When the section started and ended, in nanoseconds.
+    pub start: u64,
+    pub end: u64,
+}
+
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub enum ProfilerCategory {
@@ -147,7 +184,13 @@ pub fn send_profile_data(category: ProfilerCategory,
                          end_time: u64,
                          start_energy: u64,
                          end_energy: u64) {
+    // Only Chrome traces have a track per thread.
+    let thread = match opts::get().time_profiling {
+        Some(OutputOptions::ChromeTrace(_)) => Some(ProfilerThread::current()),
+        _ => None,
+    };
     profiler_chan.send(ProfilerMsg::Time((category, meta),
                                          (start_time, end_time),
-                                         (start_energy, end_energy)));
+                                         (start_energy, end_energy),
+                                         thread));
 }
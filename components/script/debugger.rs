@@ -0,0 +1,212 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The devtools debugger, built on SpiderMonkey's Debugger API.
+//!
+//! The Debugger API can only be used from a compartment other than the ones it debugs, so each
+//! script thread that devtools are connected to creates a global of its own, and runs
+//! `resources/debugger.js` in it. That script debugs every window of the thread, and calls back
+//! into `pauseInScriptThread` when script execution pauses.
+
+use devtools_traits::{FrameInfo, PauseInfo, ScopeInfo, SourceInfo, StepType};
+use dom::bindings::conversions::jsstring_to_str;
+use dom::bindings::reflector::DomObject;
+use dom::globalscope::GlobalScope;
+use js::conversions::ToJSValConvertible;
+use js::jsapi::{CallArgs, CompartmentOptions, Evaluate2, HandleValueArray, Heap};
+use js::jsapi::{JSAutoCompartment, JSContext, JSObject, JS_CallFunctionName, JS_ClearPendingException};
+use js::jsapi::{JS_DefineDebuggerObject, JS_DefineFunction, JS_InitStandardClasses};
+use js::jsapi::{JS_NewGlobalObject, JS_WrapObject, MutableHandleValue, OnNewGlobalHookOption};
+use js::jsval::{JSVal, ObjectValue, UInt32Value, UndefinedValue};
+use js::rust::{CompileOptionsWrapper, SIMPLE_GLOBAL_CLASS};
+use libc;
+use script_thread::ScriptThread;
+use serde_json;
+use servo_config::resource_files::read_resource_file;
+use std::ffi::CString;
+use std::ptr;
+
+/// The global `debugger.js` runs in.
+#[derive(JSTraceable)]
+pub struct Debugger {
+    global: Box<Heap<*mut JSObject>>,
+}
+
+impl Debugger {
+    #[allow(unsafe_code)]
+    pub fn new(cx: *mut JSContext) -> Debugger {
+        let debugger = Debugger {
+            global: Box::new(Heap::default()),
+        };
+        unsafe {
+            rooted!(in(cx) let global = JS_NewGlobalObject(cx,
+                                                           &SIMPLE_GLOBAL_CLASS,
+                                                           ptr::null_mut(),
+                                                           OnNewGlobalHookOption::FireOnNewGlobalHook,
+                                                           &CompartmentOptions::default()));
+            assert!(!global.is_null());
+            debugger.global.set(global.get());
+
+            let _ac = JSAutoCompartment::new(cx, global.get());
+            assert!(JS_InitStandardClasses(cx, global.handle()));
+            assert!(JS_DefineDebuggerObject(cx, global.handle()));
+            assert!(!JS_DefineFunction(cx,
+                                       global.handle(),
+                                       b"pauseInScriptThread\0".as_ptr() as *const libc::c_char,
+                                       Some(pause_in_script_thread),
+                                       1,
+                                       0).is_null());
+
+            let source = read_resource_file("debugger.js").expect("Couldn't find debugger.js");
+            let code: Vec<u16> = String::from_utf8_lossy(&source).encode_utf16().collect();
+            let filename = CString::new("debugger.js").unwrap();
+            let options = CompileOptionsWrapper::new(cx, filename.as_ptr(), 1);
+            rooted!(in(cx) let mut rval = UndefinedValue());
+            if !Evaluate2(cx, options.ptr, code.as_ptr(), code.len() as libc::size_t, rval.handle_mut()) {
+                JS_ClearPendingException(cx);
+                warn!("Failed to run debugger.js.");
+            }
+        }
+        debugger
+    }
+
+    /// Call the function with the given name defined by `debugger.js`. The caller must have
+    /// entered the compartment of the debugger global, and rooted the arguments.
+    #[allow(unsafe_code)]
+    unsafe fn call(&self, cx: *mut JSContext, name: &[u8], args: &[JSVal], rval: MutableHandleValue) -> bool {
+        assert!(*name.last().unwrap() == b'\0');
+        rooted!(in(cx) let global = self.global.get());
+        let args = HandleValueArray::from_rooted_slice(args);
+        if !JS_CallFunctionName(cx, global.handle(), name.as_ptr() as *const libc::c_char, &args, rval) {
+            JS_ClearPendingException(cx);
+            warn!("Debugger function {} failed.", String::from_utf8_lossy(&name[..name.len() - 1]));
+            return false;
+        }
+        true
+    }
+
+    /// Call the function with the given name defined by `debugger.js`, and parse the JSON it returns.
+    #[allow(unsafe_code)]
+    unsafe fn call_for_json(&self, cx: *mut JSContext, name: &[u8], args: &[JSVal]) -> Option<String> {
+        rooted!(in(cx) let mut rval = UndefinedValue());
+        if !self.call(cx, name, args, rval.handle_mut()) || !rval.is_string() {
+            return None;
+        }
+        Some(String::from(jsstring_to_str(cx, rval.to_string())))
+    }
+
+    /// Start debugging the scripts of the given global.
+    #[allow(unsafe_code)]
+    pub fn add_debuggee(&self, cx: *mut JSContext, global: &GlobalScope) {
+        unsafe {
+            let _ac = JSAutoCompartment::new(cx, self.global.get());
+            rooted!(in(cx) let mut debuggee = global.reflector().get_jsobject().get());
+            if !JS_WrapObject(cx, debuggee.handle_mut()) {
+                JS_ClearPendingException(cx);
+                return warn!("Failed to wrap debuggee global.");
+            }
+            rooted!(in(cx) let mut rval = UndefinedValue());
+            self.call(cx, b"addDebuggee\0", &[ObjectValue(debuggee.get())], rval.handle_mut());
+        }
+    }
+
+    /// The sources of the scripts run in the given global, read from the debugger when asked for
+    /// rather than copied as each script runs.
+    #[allow(unsafe_code)]
+    pub fn sources(&self, cx: *mut JSContext, global: &GlobalScope) -> Vec<SourceInfo> {
+        let json = unsafe {
+            let _ac = JSAutoCompartment::new(cx, self.global.get());
+            rooted!(in(cx) let mut debuggee = global.reflector().get_jsobject().get());
+            if !JS_WrapObject(cx, debuggee.handle_mut()) {
+                JS_ClearPendingException(cx);
+                warn!("Failed to wrap debuggee global.");
+                return vec![];
+            }
+            self.call_for_json(cx, b"sources\0", &[ObjectValue(debuggee.get())])
+        };
+        json.and_then(|json| serde_json::from_str(&json).ok()).unwrap_or(vec![])
+    }
+
+    /// Set a breakpoint on the given line of the scripts with the given URL, returning whether
+    /// there is code on that line. The breakpoint also applies to scripts loaded later.
+    #[allow(unsafe_code)]
+    pub fn set_breakpoint(&self, cx: *mut JSContext, url: &str, line: u32) -> bool {
+        unsafe {
+            let _ac = JSAutoCompartment::new(cx, self.global.get());
+            rooted!(in(cx) let mut url_value = UndefinedValue());
+            url.to_jsval(cx, url_value.handle_mut());
+            rooted!(in(cx) let mut rval = UndefinedValue());
+            self.call(cx, b"setBreakpoint\0", &[url_value.get(), UInt32Value(line)], rval.handle_mut()) &&
+                rval.is_boolean() && rval.to_boolean()
+        }
+    }
+
+    #[allow(unsafe_code)]
+    pub fn remove_breakpoint(&self, cx: *mut JSContext, url: &str, line: u32) {
+        unsafe {
+            let _ac = JSAutoCompartment::new(cx, self.global.get());
+            rooted!(in(cx) let mut url_value = UndefinedValue());
+            url.to_jsval(cx, url_value.handle_mut());
+            rooted!(in(cx) let mut rval = UndefinedValue());
+            self.call(cx, b"removeBreakpoint\0", &[url_value.get(), UInt32Value(line)], rval.handle_mut());
+        }
+    }
+
+    /// Pause as soon as the next script runs.
+    #[allow(unsafe_code)]
+    pub fn interrupt(&self, cx: *mut JSContext) {
+        unsafe {
+            let _ac = JSAutoCompartment::new(cx, self.global.get());
+            rooted!(in(cx) let mut rval = UndefinedValue());
+            self.call(cx, b"interrupt\0", &[], rval.handle_mut());
+        }
+    }
+
+    /// The stack of the paused script, newest frame first.
+    #[allow(unsafe_code)]
+    pub fn frames(&self, cx: *mut JSContext) -> Vec<FrameInfo> {
+        let json = unsafe {
+            let _ac = JSAutoCompartment::new(cx, self.global.get());
+            self.call_for_json(cx, b"frames\0", &[])
+        };
+        json.and_then(|json| serde_json::from_str(&json).ok()).unwrap_or(vec![])
+    }
+
+    /// The scopes of the frame at the given depth in the stack of the paused script.
+    #[allow(unsafe_code)]
+    pub fn environment(&self, cx: *mut JSContext, depth: u32) -> Option<Vec<ScopeInfo>> {
+        let json = unsafe {
+            let _ac = JSAutoCompartment::new(cx, self.global.get());
+            self.call_for_json(cx, b"environment\0", &[UInt32Value(depth)])
+        };
+        json.and_then(|json| serde_json::from_str(&json).ok()).and_then(|scopes| scopes)
+    }
+}
+
+/// `pauseInScriptThread(info)`: relay the pause described by the JSON-serialized `PauseInfo` to
+/// devtools, and block until they resume the script.
+#[allow(unsafe_code)]
+unsafe extern "C" fn pause_in_script_thread(cx: *mut JSContext, argc: u32, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let info = if args.get(0).is_string() {
+        String::from(jsstring_to_str(cx, args.get(0).to_string()))
+    } else {
+        String::new()
+    };
+    let step = match serde_json::from_str::<PauseInfo>(&info) {
+        Ok(info) => ScriptThread::pause_for_debugger(info),
+        Err(error) => {
+            warn!("Invalid pause info ({}).", error);
+            None
+        },
+    };
+    let step = match step {
+        None => "resume",
+        Some(StepType::In) => "in",
+        Some(StepType::Over) => "over",
+        Some(StepType::Out) => "out",
+    };
+    step.to_jsval(cx, args.rval());
+    true
+}
@@ -2,33 +2,58 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use debugger::Debugger;
 use devtools_traits::{AutoMargins, CachedConsoleMessage, CachedConsoleMessageTypes};
-use devtools_traits::{ComputedNodeLayout, ConsoleAPI, PageError};
-use devtools_traits::{EvaluateJSReply, Modification, NodeInfo, TimelineMarker};
+use devtools_traits::{ComputedNodeLayout, ConsoleAPI, DeclarationInfo, PageError};
+use devtools_traits::{EvaluateJSReply, FrameInfo, JsStackSample, Modification, NodeInfo, RuleInfo, RuleLocation};
+use devtools_traits::{RuleModification, SourceInfo, StyleSheetInfo, TimelineMarker};
 use devtools_traits::TimelineMarkerType;
+use dom::abstractworker::SharedRt;
+use dom::bindings::codegen::Bindings::CSSRuleBinding::CSSRuleMethods;
+use dom::bindings::codegen::Bindings::CSSRuleListBinding::CSSRuleListMethods;
 use dom::bindings::codegen::Bindings::CSSStyleDeclarationBinding::CSSStyleDeclarationMethods;
+use dom::bindings::codegen::Bindings::CSSStyleRuleBinding::CSSStyleRuleMethods;
 use dom::bindings::codegen::Bindings::DOMRectBinding::DOMRectMethods;
-use dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
+use dom::bindings::codegen::Bindings::DocumentBinding::{DocumentMethods, ElementCreationOptions};
 use dom::bindings::codegen::Bindings::ElementBinding::ElementMethods;
+use dom::bindings::codegen::Bindings::HTMLElementBinding::HTMLElementMethods;
+use dom::bindings::codegen::Bindings::NodeBinding::NodeMethods;
+use dom::bindings::codegen::Bindings::StyleSheetBinding::StyleSheetMethods;
 use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use dom::bindings::conversions::{ConversionResult, FromJSValConvertible, jsstring_to_str};
 use dom::bindings::inheritance::Castable;
 use dom::bindings::reflector::DomObject;
 use dom::bindings::root::DomRoot;
 use dom::bindings::str::DOMString;
+use dom::cssstyledeclaration::CSSStyleDeclaration;
+use dom::cssstylerule::CSSStyleRule;
 use dom::document::AnimationFrameCallback;
 use dom::element::Element;
 use dom::globalscope::GlobalScope;
-use dom::node::{Node, window_from_node};
+use dom::htmlelement::HTMLElement;
+use dom::mutationobserver::MutationObserver;
+use dom::node::{Node, document_from_node, window_from_node};
+use dom::stylesheet::StyleSheet;
 use dom::window::Window;
 use ipc_channel::ipc::IpcSender;
-use js::jsapi::{JSAutoCompartment, ObjectClassName};
+use js::JSCLASS_IS_DOMJSCLASS;
+use js::jsapi::{CaptureCurrentStack, GetSavedFrameColumn, GetSavedFrameFunctionDisplayName, GetSavedFrameLine};
+use js::jsapi::{GetSavedFrameParent, GetSavedFrameSource, GetScriptedCallerGlobal, HandleValue};
+use js::jsapi::{JSAutoCompartment, JSContext, JSString, JS_ClearPendingException, ObjectClassName};
+use js::jsapi::SavedFrameSelfHosted;
 use js::jsval::UndefinedValue;
+use js::rust::get_object_class;
 use msg::constellation_msg::PipelineId;
 use script_thread::Documents;
 use std::ffi::CStr;
+use std::ptr;
 use std::str;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 use style::properties::longhands::{margin_bottom, margin_left, margin_right, margin_top};
+use time::precise_time_ns;
 use uuid::Uuid;
 
 
@@ -41,37 +66,43 @@ pub fn handle_evaluate_js(global: &GlobalScope, eval: String, reply: IpcSender<E
         let _ac = JSAutoCompartment::new(cx, globalhandle.get());
         rooted!(in(cx) let mut rval = UndefinedValue());
         global.evaluate_js_on_global_with_result(&eval, rval.handle_mut());
-
-        if rval.is_undefined() {
-            EvaluateJSReply::VoidValue
-        } else if rval.is_boolean() {
-            EvaluateJSReply::BooleanValue(rval.to_boolean())
-        } else if rval.is_double() || rval.is_int32() {
-            EvaluateJSReply::NumberValue(
-                match FromJSValConvertible::from_jsval(cx, rval.handle(), ()) {
-                    Ok(ConversionResult::Success(v)) => v,
-                    _ => unreachable!(),
-                })
-        } else if rval.is_string() {
-            EvaluateJSReply::StringValue(String::from(jsstring_to_str(cx, rval.to_string())))
-        } else if rval.is_null() {
-            EvaluateJSReply::NullValue
-        } else {
-            assert!(rval.is_object());
-
-            rooted!(in(cx) let obj = rval.to_object());
-            let class_name = CStr::from_ptr(ObjectClassName(cx, obj.handle()));
-            let class_name = str::from_utf8(class_name.to_bytes()).unwrap();
-
-            EvaluateJSReply::ActorValue {
-                class: class_name.to_owned(),
-                uuid: Uuid::new_v4().to_string(),
-            }
-        }
+        value_to_reply(cx, rval.handle())
     };
     reply.send(result).unwrap();
 }
 
+/// Serialize a JS value for devtools, which get a new uuid for objects.
+#[allow(unsafe_code)]
+pub unsafe fn value_to_reply(cx: *mut JSContext, rval: HandleValue) -> EvaluateJSReply {
+    if rval.is_undefined() {
+        EvaluateJSReply::VoidValue
+    } else if rval.is_boolean() {
+        EvaluateJSReply::BooleanValue(rval.to_boolean())
+    } else if rval.is_double() || rval.is_int32() {
+        EvaluateJSReply::NumberValue(
+            match FromJSValConvertible::from_jsval(cx, rval, ()) {
+                Ok(ConversionResult::Success(v)) => v,
+                _ => unreachable!(),
+            })
+    } else if rval.is_string() {
+        EvaluateJSReply::StringValue(String::from(jsstring_to_str(cx, rval.to_string())))
+    } else if rval.is_null() {
+        EvaluateJSReply::NullValue
+    } else if rval.is_object() {
+        rooted!(in(cx) let obj = rval.to_object());
+        let class_name = CStr::from_ptr(ObjectClassName(cx, obj.handle()));
+        let class_name = str::from_utf8(class_name.to_bytes()).unwrap();
+
+        EvaluateJSReply::ActorValue {
+            class: class_name.to_owned(),
+            uuid: Uuid::new_v4().to_string(),
+        }
+    } else {
+        // Symbols have no form in the protocol yet.
+        EvaluateJSReply::VoidValue
+    }
+}
+
 pub fn handle_get_root_node(documents: &Documents, pipeline: PipelineId, reply: IpcSender<Option<NodeInfo>>) {
     let info = documents.find_document(pipeline)
         .map(|document| document.upcast::<Node>().summarize());
@@ -227,6 +258,115 @@ pub fn handle_modify_attribute(documents: &Documents,
     }
 }
 
+pub fn handle_wants_mutation_notifications(documents: &Documents, pipeline: PipelineId, send_notifications: bool) {
+    let document = match documents.find_document(pipeline) {
+        None => return warn!("Message sent to closed pipeline {}.", pipeline),
+        Some(document) => document,
+    };
+    let window = document.window();
+    match (window.devtools_mutation_observer(), send_notifications) {
+        (None, true) => {
+            if let Some(devtools_chan) = window.upcast::<GlobalScope>().devtools_chan() {
+                let observer = MutationObserver::observe_for_devtools(window,
+                                                                      document.upcast(),
+                                                                      devtools_chan.clone(),
+                                                                      pipeline);
+                window.set_devtools_mutation_observer(Some(&observer));
+            }
+        },
+        (Some(observer), false) => {
+            observer.disconnect_devtools(document.upcast());
+            window.set_devtools_mutation_observer(None);
+        },
+        _ => {},
+    }
+}
+
+pub fn handle_set_outer_html(documents: &Documents,
+                             pipeline: PipelineId,
+                             node_id: String,
+                             html: String,
+                             reply: IpcSender<bool>) {
+    let done = find_node_by_unique_id(documents, pipeline, &*node_id).map_or(false, |node| {
+        node.downcast::<Element>().map_or(false, |element| element.SetOuterHTML(DOMString::from(html)).is_ok())
+    });
+    reply.send(done).unwrap();
+}
+
+pub fn handle_remove_node(documents: &Documents,
+                          pipeline: PipelineId,
+                          node_id: String,
+                          reply: IpcSender<Result<Option<NodeInfo>, ()>>) {
+    let node = match find_node_by_unique_id(documents, pipeline, &*node_id) {
+        None => return reply.send(Err(())).unwrap(),
+        Some(node) => node,
+    };
+    let next_sibling = node.GetNextSibling();
+    let removed = node.GetParentNode().map_or(false, |parent| parent.RemoveChild(&node).is_ok());
+    if removed {
+        reply.send(Ok(next_sibling.map(|sibling| sibling.summarize()))).unwrap();
+    } else {
+        reply.send(Err(())).unwrap();
+    }
+}
+
+pub fn handle_insert_before(documents: &Documents,
+                            pipeline: PipelineId,
+                            node_id: String,
+                            parent_id: String,
+                            sibling_id: Option<String>,
+                            reply: IpcSender<bool>) {
+    let node = find_node_by_unique_id(documents, pipeline, &*node_id);
+    let parent = find_node_by_unique_id(documents, pipeline, &*parent_id);
+    let sibling = sibling_id.map(|sibling_id| find_node_by_unique_id(documents, pipeline, &*sibling_id));
+    let done = match (node, parent, sibling) {
+        (_, _, Some(None)) => false,
+        (Some(node), Some(parent), sibling) => {
+            let sibling = sibling.and_then(|sibling| sibling);
+            parent.InsertBefore(&node, sibling.as_ref().map(|sibling| &**sibling)).is_ok()
+        },
+        _ => false,
+    };
+    reply.send(done).unwrap();
+}
+
+pub fn handle_edit_tag_name(documents: &Documents,
+                            pipeline: PipelineId,
+                            node_id: String,
+                            tag_name: String,
+                            reply: IpcSender<bool>) {
+    let node = match find_node_by_unique_id(documents, pipeline, &*node_id) {
+        None => return reply.send(false).unwrap(),
+        Some(node) => node,
+    };
+    let (element, parent) = match (node.downcast::<Element>(), node.GetParentNode()) {
+        (Some(element), Some(parent)) => (element, parent),
+        _ => return reply.send(false).unwrap(),
+    };
+    let document = document_from_node(element);
+    let replacement = match document.CreateElementNS(Some(DOMString::from(&**element.namespace())),
+                                                     DOMString::from(tag_name),
+                                                     &ElementCreationOptions { is: None }) {
+        Ok(replacement) => replacement,
+        Err(_) => return reply.send(false).unwrap(),
+    };
+
+    // The new element takes over the attributes and children of the one it replaces.
+    for attr in element.attrs().iter() {
+        replacement.push_new_attribute(attr.local_name().clone(),
+                                       attr.value().clone(),
+                                       attr.name().clone(),
+                                       attr.namespace().clone(),
+                                       attr.prefix().cloned());
+    }
+    while let Some(child) = node.GetFirstChild() {
+        if replacement.upcast::<Node>().AppendChild(&child).is_err() {
+            return reply.send(false).unwrap();
+        }
+    }
+    reply.send(parent.ReplaceChild(replacement.upcast(), &node).is_ok()).unwrap();
+}
+
 pub fn handle_wants_live_notifications(global: &GlobalScope, send_notifications: bool) {
     global.set_devtools_wants_updates(send_notifications);
 }
@@ -263,3 +403,273 @@ pub fn handle_reload(documents: &Documents,
         win.Location().reload_without_origin_check();
     }
 }
+
+pub fn handle_get_sources(documents: &Documents,
+                          debugger: Option<&Debugger>,
+                          pipeline: PipelineId,
+                          reply: IpcSender<Vec<SourceInfo>>) {
+    let sources = match (documents.find_window(pipeline), debugger) {
+        (Some(window), Some(debugger)) => debugger.sources(window.get_cx(), window.upcast()),
+        _ => vec![],
+    };
+    reply.send(sources).unwrap();
+}
+
+pub fn handle_get_style_sheets(documents: &Documents,
+                               pipeline: PipelineId,
+                               reply: IpcSender<Vec<StyleSheetInfo>>) {
+    let sheets = match documents.find_document(pipeline) {
+        None => vec![],
+        Some(document) => (0..document.stylesheet_count()).filter_map(|index| {
+            document.stylesheet_at(index).map(|sheet| StyleSheetInfo {
+                index: index,
+                href: sheet.upcast::<StyleSheet>().GetHref().map(String::from),
+                nodeHref: document.url().as_str().to_owned(),
+                title: sheet.upcast::<StyleSheet>().GetTitle().map(String::from),
+                disabled: sheet.disabled(),
+                ruleCount: sheet.rulelist().Length() as usize,
+            })
+        }).collect(),
+    };
+    reply.send(sheets).unwrap();
+}
+
+pub fn handle_get_style_sheet_text(documents: &Documents,
+                                   pipeline: PipelineId,
+                                   index: usize,
+                                   reply: IpcSender<Option<String>>) {
+    let text = documents.find_document(pipeline)
+        .and_then(|document| document.stylesheet_at(index))
+        .map(|sheet| {
+            let rules = sheet.rulelist();
+            (0..rules.Length()).filter_map(|index| rules.Item(index))
+                               .map(|rule| String::from(rule.CssText()))
+                               .collect::<Vec<_>>()
+                               .join("\n")
+        });
+    reply.send(text).unwrap();
+}
+
+pub fn handle_set_style_sheet_disabled(documents: &Documents,
+                                       pipeline: PipelineId,
+                                       index: usize,
+                                       disabled: bool) {
+    match documents.find_document(pipeline).and_then(|document| document.stylesheet_at(index)) {
+        None => warn!("style sheet {} for pipeline id {} is not found", index, pipeline),
+        Some(sheet) => sheet.set_disabled(disabled),
+    }
+}
+
+/// The declarations of the rule at the given location, and its selector.
+fn find_rule(documents: &Documents,
+             pipeline: PipelineId,
+             location: &RuleLocation)
+             -> Option<(DomRoot<CSSStyleDeclaration>, DOMString)> {
+    match *location {
+        RuleLocation::StyleSheet(sheet, index) => {
+            documents.find_document(pipeline)
+                .and_then(|document| document.stylesheet_at(sheet))
+                .and_then(|sheet| sheet.rulelist().Item(index as u32))
+                .and_then(|rule| rule.downcast::<CSSStyleRule>().map(|rule| (rule.Style(), rule.SelectorText())))
+        }
+        RuleLocation::ElementStyle(ref node_id) => {
+            find_node_by_unique_id(documents, pipeline, node_id)
+                .and_then(|node| node.downcast::<HTMLElement>().map(|element| element.Style()))
+                .map(|style| (style, DOMString::new()))
+        }
+    }
+}
+
+fn summarize_rule(location: RuleLocation, style: &CSSStyleDeclaration, selector: DOMString) -> RuleInfo {
+    let declarations = (0..style.Length()).map(|index| {
+        let name = style.Item(index);
+        DeclarationInfo {
+            value: String::from(style.GetPropertyValue(name.clone())),
+            priority: String::from(style.GetPropertyPriority(name.clone())),
+            name: String::from(name),
+        }
+    }).collect();
+    RuleInfo {
+        location: location,
+        selector: String::from(selector),
+        cssText: String::from(style.CssText()),
+        declarations: declarations,
+    }
+}
+
+pub fn handle_get_applied_rules(documents: &Documents,
+                                pipeline: PipelineId,
+                                node_id: String,
+                                reply: IpcSender<Option<Vec<RuleInfo>>>) {
+    let node = match find_node_by_unique_id(documents, pipeline, &*node_id) {
+        None => return reply.send(None).unwrap(),
+        Some(node) => node,
+    };
+    let element = match node.downcast::<Element>() {
+        None => return reply.send(None).unwrap(),
+        Some(element) => element,
+    };
+    let document = document_from_node(element);
+
+    // The style attribute wins over the style sheets, and later rules over earlier ones.
+    let mut rules = vec![];
+    if let Some(html_element) = element.downcast::<HTMLElement>() {
+        let location = RuleLocation::ElementStyle(node_id.clone());
+        rules.push(summarize_rule(location, &html_element.Style(), DOMString::new()));
+    }
+    for sheet_index in (0..document.stylesheet_count()).rev() {
+        let rule_list = match document.stylesheet_at(sheet_index) {
+            Some(ref sheet) if !sheet.disabled() => sheet.rulelist(),
+            _ => continue,
+        };
+        for rule_index in (0..rule_list.Length()).rev() {
+            let rule = match rule_list.Item(rule_index) {
+                Some(rule) => rule,
+                None => continue,
+            };
+            if let Some(rule) = rule.downcast::<CSSStyleRule>() {
+                let selector = rule.SelectorText();
+                if element.Matches(selector.clone()).unwrap_or(false) {
+                    let location = RuleLocation::StyleSheet(sheet_index, rule_index as usize);
+                    rules.push(summarize_rule(location, &rule.Style(), selector));
+                }
+            }
+        }
+    }
+    reply.send(Some(rules)).unwrap();
+}
+
+pub fn handle_modify_rule(documents: &Documents,
+                          pipeline: PipelineId,
+                          location: RuleLocation,
+                          modifications: Vec<RuleModification>,
+                          reply: IpcSender<Option<RuleInfo>>) {
+    let (style, selector) = match find_rule(documents, pipeline, &location) {
+        None => return reply.send(None).unwrap(),
+        Some(rule) => rule,
+    };
+    for modification in modifications {
+        let name = DOMString::from(modification.name);
+        // Setting or removing the declaration through the CSSOM restyles the page.
+        let _ = match modification.value {
+            Some(value) => style.SetProperty(name, DOMString::from(value), DOMString::from(modification.priority)),
+            None => style.RemoveProperty(name).map(|_| ()),
+        };
+    }
+    reply.send(Some(summarize_rule(location, &style, selector))).unwrap();
+}
+
+pub fn handle_set_rule_text(documents: &Documents,
+                            pipeline: PipelineId,
+                            location: RuleLocation,
+                            text: String,
+                            reply: IpcSender<Option<RuleInfo>>) {
+    let (style, selector) = match find_rule(documents, pipeline, &location) {
+        None => return reply.send(None).unwrap(),
+        Some(rule) => rule,
+    };
+    let _ = style.SetCssText(DOMString::from(text));
+    reply.send(Some(summarize_rule(location, &style, selector))).unwrap();
+}
+
+/// The stack of the script running in `cx`, newest frame first, walked natively so that
+/// neither the debugger nor any script is involved.
+#[allow(unsafe_code)]
+pub fn js_stack(cx: *mut JSContext) -> Vec<FrameInfo> {
+    let mut frames = vec![];
+    unsafe {
+        rooted!(in(cx) let mut frame = ptr::null_mut());
+        if !CaptureCurrentStack(cx, frame.handle_mut(), 0) {
+            JS_ClearPendingException(cx);
+            return frames;
+        }
+        while !frame.is_null() {
+            rooted!(in(cx) let mut source = ptr::null_mut());
+            rooted!(in(cx) let mut name = ptr::null_mut());
+            rooted!(in(cx) let mut parent = ptr::null_mut());
+            let (mut line, mut column) = (0, 0);
+            GetSavedFrameSource(cx, frame.handle(), source.handle_mut(), SavedFrameSelfHosted::Exclude);
+            GetSavedFrameLine(cx, frame.handle(), &mut line, SavedFrameSelfHosted::Exclude);
+            GetSavedFrameColumn(cx, frame.handle(), &mut column, SavedFrameSelfHosted::Exclude);
+            GetSavedFrameFunctionDisplayName(cx, frame.handle(), name.handle_mut(), SavedFrameSelfHosted::Exclude);
+            GetSavedFrameParent(cx, frame.handle(), parent.handle_mut(), SavedFrameSelfHosted::Exclude);
+            let to_string = |string: *mut JSString| {
+                if string.is_null() { None } else { Some(String::from(jsstring_to_str(cx, string))) }
+            };
+            let callee = to_string(name.get());
+            frames.push(FrameInfo {
+                depth: frames.len() as u32,
+                // Saved frames do not tell evaluated code apart from the top level of a script.
+                type_: if callee.is_some() { "call" } else { "global" }.to_owned(),
+                callee: callee,
+                url: to_string(source.get()).unwrap_or_default(),
+                line: line,
+                column: column,
+            });
+            frame.set(parent.get());
+        }
+    }
+    frames
+}
+
+/// Samples the stack of the script running in `cx` if it runs in a window, returning the
+/// pipeline of that window along with it. This only looks at the script's own global, so
+/// that it is safe to call while the script thread is interrupted anywhere.
+#[allow(unsafe_code)]
+pub fn take_js_sample(cx: *mut JSContext) -> Option<(PipelineId, JsStackSample)> {
+    let time = precise_time_ns();
+    let pipeline_id = unsafe {
+        let global = GetScriptedCallerGlobal(cx);
+        // Globals that are not DOM globals, like the debugger's, are not sampled.
+        if global.is_null() || (*get_object_class(global)).flags & JSCLASS_IS_DOMJSCLASS == 0 {
+            return None;
+        }
+        GlobalScope::from_object(global).pipeline_id()
+    };
+    Some((pipeline_id, JsStackSample {
+        time: time,
+        frames: js_stack(cx),
+    }))
+}
+
+/// How often the stacks of the scripts that devtools are profiling are sampled.
+const JS_SAMPLING_INTERVAL_MS: u64 = 10;
+
+/// Interrupts the scripts of a runtime at a regular interval, for its interrupt callback to
+/// sample their stack. It stops once dropped.
+pub struct JsSampler {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+unsafe_no_jsmanaged_fields!(JsSampler);
+
+impl JsSampler {
+    pub fn start(rt: SharedRt) -> JsSampler {
+        let running = Arc::new(AtomicBool::new(true));
+        let thread = {
+            let running = running.clone();
+            thread::Builder::new().name("JsSampler".to_owned()).spawn(move || {
+                while running.load(Ordering::SeqCst) {
+                    rt.request_interrupt();
+                    thread::sleep(Duration::from_millis(JS_SAMPLING_INTERVAL_MS));
+                }
+            }).expect("Thread spawning failed")
+        };
+        JsSampler {
+            running: running,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for JsSampler {
+    fn drop(&mut self) {
+        // The runtime may go away once the sampler is dropped, so wait for the thread to stop
+        // interrupting it.
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -514,6 +514,14 @@ impl<T: DomObject> MutDom<T> {
             DomRoot::from_ref(&*ptr::read(self.val.get()))
         }
     }
+
+    /// Retrieve a copy of the inner `Dom<T>` as `LayoutDom<T>`.
+    /// For use by layout, which can't use safe types like Temporary.
+    #[allow(unrooted_must_root)]
+    pub unsafe fn get_inner_as_layout(&self) -> LayoutDom<T> {
+        debug_assert!(thread_state::get().is_layout());
+        (*self.val.get()).to_layout()
+    }
 }
 
 impl<T: DomObject> MallocSizeOf for MutDom<T> {
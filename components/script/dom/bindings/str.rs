@@ -208,6 +208,203 @@ impl DOMString {
         self.0.truncate(last_non_whitespace);
         let _ = self.0.splice(0..first_non_whitespace, "");
     }
+
+    /// <https://html.spec.whatwg.org/multipage/#valid-floating-point-number>
+    pub fn is_valid_floating_point_number_string(&self) -> bool {
+        let mut input = self.0.as_str();
+        if input.starts_with('-') {
+            input = &input[1..];
+        }
+
+        let (mantissa, exponent) = match input.find(|c| c == 'e' || c == 'E') {
+            Some(index) => (&input[..index], Some(&input[index + 1..])),
+            None => (input, None),
+        };
+        let (integer, fraction) = match mantissa.find('.') {
+            Some(index) => (&mantissa[..index], Some(&mantissa[index + 1..])),
+            None => (mantissa, None),
+        };
+
+        let valid_mantissa = match fraction {
+            Some(fraction) => is_ascii_digits(fraction) && (integer.is_empty() || is_ascii_digits(integer)),
+            None => is_ascii_digits(integer),
+        };
+        let valid_exponent = match exponent {
+            Some(exponent) => {
+                let digits = if exponent.starts_with('-') || exponent.starts_with('+') {
+                    &exponent[1..]
+                } else {
+                    exponent
+                };
+                is_ascii_digits(digits)
+            },
+            None => true,
+        };
+        valid_mantissa && valid_exponent && self.0.parse::<f64>().map_or(false, |value| value.is_finite())
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#valid-simple-colour>
+    pub fn is_valid_simple_color_string(&self) -> bool {
+        self.0.len() == 7 && self.0.starts_with('#') && self.0[1..].chars().all(|c| c.is_digit(16))
+    }
+
+    /// Parses this string as a date, returning the year, month and day.
+    /// <https://html.spec.whatwg.org/multipage/#parse-a-date-string>
+    pub fn parse_date_string(&self) -> Result<(i32, u32, u32), ()> {
+        parse_date_component(&self.0)
+    }
+
+    /// Parses this string as a month, returning the year and month.
+    /// <https://html.spec.whatwg.org/multipage/#parse-a-month-string>
+    pub fn parse_month_string(&self) -> Result<(i32, u32), ()> {
+        parse_month_component(&self.0)
+    }
+
+    /// Parses this string as a week, returning the week-year and week.
+    /// <https://html.spec.whatwg.org/multipage/#parse-a-week-string>
+    pub fn parse_week_string(&self) -> Result<(i32, u32), ()> {
+        let index = self.0.find("-W").ok_or(())?;
+        let year = parse_year(&self.0[..index])?;
+        let week = parse_two_digits(&self.0[index + 2..])?;
+        if week < 1 || week > max_week_in_year(year) {
+            return Err(());
+        }
+        Ok((year, week))
+    }
+
+    /// Parses this string as a time, returning the hour, minute and second.
+    /// <https://html.spec.whatwg.org/multipage/#parse-a-time-string>
+    pub fn parse_time_string(&self) -> Result<(u32, u32, f64), ()> {
+        parse_time_component(&self.0)
+    }
+
+    /// Parses this string as a local date and time.
+    /// <https://html.spec.whatwg.org/multipage/#parse-a-local-date-and-time-string>
+    pub fn parse_local_date_and_time_string(&self) -> Result<((i32, u32, u32), (u32, u32, f64)), ()> {
+        let index = self.0.find(|c| c == 'T' || c == ' ').ok_or(())?;
+        let date = parse_date_component(&self.0[..index])?;
+        let time = parse_time_component(&self.0[index + 1..])?;
+        Ok((date, time))
+    }
+
+    /// Replaces a valid local date and time string with the equivalent valid normalized
+    /// local date and time string.
+    /// <https://html.spec.whatwg.org/multipage/#valid-normalised-local-date-and-time-string>
+    pub fn convert_valid_normalized_local_date_and_time_string(&mut self) -> Result<(), ()> {
+        let ((year, month, day), (hour, minute, second)) = self.parse_local_date_and_time_string()?;
+        let mut normalized = format!("{:04}-{:02}-{:02}T{:02}:{:02}", year, month, day, hour, minute);
+        if second != 0. {
+            let whole_seconds = second.trunc() as u32;
+            normalized.push_str(&format!(":{:02}", whole_seconds));
+            let milliseconds = ((second - second.trunc()) * 1000.).round() as u32;
+            if milliseconds != 0 {
+                let fraction = format!("{:03}", milliseconds);
+                normalized.push('.');
+                normalized.push_str(fraction.trim_right_matches('0'));
+            }
+        }
+        self.0 = normalized;
+        Ok(())
+    }
+}
+
+fn is_ascii_digits(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_digit(10))
+}
+
+// https://html.spec.whatwg.org/multipage/#parse-a-date-component
+fn parse_date_component(value: &str) -> Result<(i32, u32, u32), ()> {
+    let index = value.rfind('-').ok_or(())?;
+    let (year, month) = parse_month_component(&value[..index])?;
+    let day = parse_two_digits(&value[index + 1..])?;
+    if day < 1 || day > max_day_in_month(year, month) {
+        return Err(());
+    }
+    Ok((year, month, day))
+}
+
+// https://html.spec.whatwg.org/multipage/#parse-a-month-component
+fn parse_month_component(value: &str) -> Result<(i32, u32), ()> {
+    let index = value.find('-').ok_or(())?;
+    let year = parse_year(&value[..index])?;
+    let month = parse_two_digits(&value[index + 1..])?;
+    if month < 1 || month > 12 {
+        return Err(());
+    }
+    Ok((year, month))
+}
+
+// https://html.spec.whatwg.org/multipage/#parse-a-time-component
+fn parse_time_component(value: &str) -> Result<(u32, u32, f64), ()> {
+    let mut components = value.split(':');
+    let hour = parse_two_digits(components.next().ok_or(())?)?;
+    let minute = parse_two_digits(components.next().ok_or(())?)?;
+    let second = match components.next() {
+        Some(second) => {
+            let (whole, fraction) = match second.find('.') {
+                Some(index) => (&second[..index], Some(&second[index + 1..])),
+                None => (second, None),
+            };
+            if parse_two_digits(whole)? > 59 {
+                return Err(());
+            }
+            if let Some(fraction) = fraction {
+                if fraction.len() > 3 || !is_ascii_digits(fraction) {
+                    return Err(());
+                }
+            }
+            second.parse::<f64>().map_err(|_| ())?
+        },
+        None => 0.,
+    };
+    if components.next().is_some() || hour > 23 || minute > 59 {
+        return Err(());
+    }
+    Ok((hour, minute, second))
+}
+
+// A valid year is four or more digits representing a number greater than zero.
+fn parse_year(value: &str) -> Result<i32, ()> {
+    if value.len() < 4 || !is_ascii_digits(value) {
+        return Err(());
+    }
+    match value.parse::<i32>() {
+        Ok(year) if year > 0 => Ok(year),
+        _ => Err(()),
+    }
+}
+
+fn parse_two_digits(value: &str) -> Result<u32, ()> {
+    if value.len() != 2 || !is_ascii_digits(value) {
+        return Err(());
+    }
+    value.parse::<u32>().map_err(|_| ())
+}
+
+fn is_leap_year(year: i32) -> bool {
+    year % 400 == 0 || (year % 4 == 0 && year % 100 != 0)
+}
+
+// https://html.spec.whatwg.org/multipage/#number-of-days-in-month-month-of-year-year
+fn max_day_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        _ => 28,
+    }
+}
+
+// https://html.spec.whatwg.org/multipage/#week-number-of-the-last-day
+fn max_week_in_year(year: i32) -> u32 {
+    // The day of the week of January 1st, with Sunday being 0.
+    let previous = year - 1;
+    let january_first = (1 + 5 * (previous % 4) + 4 * (previous % 100) + 6 * (previous % 400)) % 7;
+    match january_first {
+        4 => 53,
+        3 if is_leap_year(year) => 53,
+        _ => 52,
+    }
 }
 
 impl Borrow<str> for DOMString {
@@ -37,7 +37,7 @@ use canvas_traits::webgl::{WebGLChan, WebGLContextShareMode, WebGLError, WebGLPi
 use canvas_traits::webgl::{WebGLReceiver, WebGLSender, WebGLShaderId, WebGLTextureId, WebGLVertexArrayId};
 use canvas_traits::webgl::{WebGLSLVersion, WebGLVersion};
 use cssparser::RGBA;
-use devtools_traits::{CSSError, TimelineMarkerType, WorkerId};
+use devtools_traits::{CSSError, JsStackSample, TimelineMarkerType, WorkerId};
 use dom::abstractworker::SharedRt;
 use dom::bindings::cell::DomRefCell;
 use dom::bindings::error::Error;
@@ -65,9 +65,11 @@ use js::rust::Runtime;
 use metrics::{InteractiveMetrics, InteractiveWindow};
 use msg::constellation_msg::{BrowsingContextId, FrameType, PipelineId, TopLevelBrowsingContextId};
 use net_traits::{Metadata, NetworkError, ReferrerPolicy, ResourceThreads};
+use net_traits::csp::CspList;
 use net_traits::filemanager_thread::RelativePos;
 use net_traits::image::base::{Image, ImageMetadata};
 use net_traits::image_cache::{ImageCache, PendingImageId};
+use net_traits::indexeddb_thread::{DatabaseInfo, IndexedDBError, IndexedDBKey, IndexedDBKeyRange, IndexedDBReply};
 use net_traits::request::{Request, RequestInit};
 use net_traits::response::{Response, ResponseBody};
 use net_traits::response::HttpsState;
@@ -76,6 +78,7 @@ use offscreen_gl_context::GLLimits;
 use parking_lot::RwLock;
 use profile_traits::mem::ProfilerChan as MemProfilerChan;
 use profile_traits::time::ProfilerChan as TimeProfilerChan;
+use regex::Regex;
 use script_layout_interface::OpaqueStyleAndLayoutData;
 use script_layout_interface::reporter::CSSErrorReporter;
 use script_layout_interface::rpc::LayoutRPC;
@@ -109,6 +112,7 @@ use style::stylesheet_set::StylesheetSet;
 use style::stylesheets::{CssRules, FontFaceRule, KeyframesRule, MediaRule, Stylesheet};
 use style::stylesheets::{NamespaceRule, StyleRule, ImportRule, SupportsRule, ViewportRule};
 use style::stylesheets::keyframes_rule::Keyframe;
+use style::stylist::ShadowRootStyles;
 use style::values::specified::Length;
 use time::Duration;
 use uuid::Uuid;
@@ -122,6 +126,7 @@ pub unsafe trait JSTraceable {
 }
 
 unsafe_no_jsmanaged_fields!(CSSError);
+unsafe_no_jsmanaged_fields!(JsStackSample);
 
 unsafe_no_jsmanaged_fields!(&'static Encoding);
 
@@ -345,6 +350,7 @@ unsafe_no_jsmanaged_fields!(ServoUrl, ImmutableOrigin, MutableOrigin);
 unsafe_no_jsmanaged_fields!(Image, ImageMetadata, ImageCache, PendingImageId);
 unsafe_no_jsmanaged_fields!(Metadata);
 unsafe_no_jsmanaged_fields!(NetworkError);
+unsafe_no_jsmanaged_fields!(DatabaseInfo, IndexedDBError, IndexedDBKey, IndexedDBKeyRange, IndexedDBReply);
 unsafe_no_jsmanaged_fields!(Atom, Prefix, LocalName, Namespace, QualName);
 unsafe_no_jsmanaged_fields!(TrustedPromise);
 unsafe_no_jsmanaged_fields!(PropertyDeclarationBlock);
@@ -380,7 +386,9 @@ unsafe_no_jsmanaged_fields!(AttrValue);
 unsafe_no_jsmanaged_fields!(Snapshot);
 unsafe_no_jsmanaged_fields!(PendingRestyle);
 unsafe_no_jsmanaged_fields!(Stylesheet);
+unsafe_no_jsmanaged_fields!(ShadowRootStyles);
 unsafe_no_jsmanaged_fields!(HttpsState);
+unsafe_no_jsmanaged_fields!(CspList);
 unsafe_no_jsmanaged_fields!(Request);
 unsafe_no_jsmanaged_fields!(RequestInit);
 unsafe_no_jsmanaged_fields!(SharedRt);
@@ -419,6 +427,7 @@ unsafe_no_jsmanaged_fields!(WebVRGamepadHand);
 unsafe_no_jsmanaged_fields!(ScriptToConstellationChan);
 unsafe_no_jsmanaged_fields!(InteractiveMetrics);
 unsafe_no_jsmanaged_fields!(InteractiveWindow);
+unsafe_no_jsmanaged_fields!(Regex);
 
 unsafe impl<'a> JSTraceable for &'a str {
     #[inline]
@@ -2,30 +2,68 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use devtools_traits::{ConsoleMessage, LogLevel, ScriptToDevtoolsControlMsg};
+use devtools::{self, value_to_reply};
+use devtools_traits::{ConsoleArgument, ConsoleCounter, ConsoleMessage, ConsoleTimer, EvaluateJSReply};
+use devtools_traits::{LogLevel, ScriptToDevtoolsControlMsg};
+use dom::bindings::conversions::jsid_to_string;
 use dom::bindings::inheritance::Castable;
 use dom::bindings::str::DOMString;
 use dom::globalscope::GlobalScope;
 use dom::workerglobalscope::WorkerGlobalScope;
+use js::jsapi::{GetPropertyKeys, HandleObject, HandleValue, JSContext, JSITER_OWNONLY, JSObject};
+use js::jsapi::{JSPROP_GETTER, JSPROP_SETTER, JS_ClearPendingException, JS_GetOwnPropertyDescriptorById};
+use js::jsapi::PropertyDescriptor;
+use js::rust::IdVector;
 use std::io;
 
+/// The most properties of an object that are previewed.
+const PREVIEW_MAX_PROPERTIES: usize = 100;
+
 // https://developer.mozilla.org/en-US/docs/Web/API/Console
 pub struct Console(());
 
 impl Console {
-    fn send_to_devtools(global: &GlobalScope, level: LogLevel, message: DOMString) {
+    fn send_to_devtools(global: &GlobalScope, message: ConsoleMessage) {
         if let Some(chan) = global.devtools_chan() {
-            let console_message = prepare_message(level, message);
             let worker_id = global.downcast::<WorkerGlobalScope>().map(|worker| {
                 worker.get_worker_id()
             });
             let devtools_message = ScriptToDevtoolsControlMsg::ConsoleAPI(
                 global.pipeline_id(),
-                console_message,
+                message,
                 worker_id);
             chan.send(devtools_message).unwrap();
         }
     }
+
+    /// Print the message on stdout, indented by the groups that are open, and send it to devtools.
+    fn report(global: &GlobalScope, message: ConsoleMessage) {
+        let indent = "  ".repeat(global.console_group_depth());
+        with_stderr_lock(|| {
+            for line in message.message.split('\n') {
+                println!("{}{}", indent, line);
+            }
+            if let Some(ref stack) = message.stacktrace {
+                for frame in stack {
+                    let callee = frame.callee.as_ref().map_or("<anonymous>", |callee| &**callee);
+                    println!("{}    at {} ({}:{}:{})", indent, callee, frame.url, frame.line, frame.column);
+                }
+            }
+        });
+        Self::send_to_devtools(global, message);
+    }
+
+    #[allow(unsafe_code)]
+    unsafe fn log(cx: *mut JSContext, global: &GlobalScope, level: LogLevel, data: Vec<HandleValue>) {
+        let depth = preview_depth(global);
+        let arguments = data.iter().map(|value| console_argument(cx, *value, depth)).collect::<Vec<_>>();
+        Self::report(global, prepare_message(global, level, format_arguments(&arguments), arguments));
+    }
+
+    fn warn(global: &GlobalScope, message: String) {
+        let arguments = vec![string_argument(message.clone())];
+        Self::report(global, prepare_message(global, LogLevel::Warn, message, arguments));
+    }
 }
 
 // In order to avoid interleaving the stdout output of the Console API methods
@@ -39,100 +77,416 @@ fn with_stderr_lock<F>(f: F) where F: FnOnce() {
     f()
 }
 
+#[allow(unsafe_code)]
 impl Console {
-    // https://developer.mozilla.org/en-US/docs/Web/API/Console/log
-    pub fn Log(global: &GlobalScope, messages: Vec<DOMString>) {
-        with_stderr_lock(move || {
-            for message in messages {
-                println!("{}", message);
-                Self::send_to_devtools(global, LogLevel::Log, message);
-            }
-        })
+    // https://console.spec.whatwg.org/#log
+    pub unsafe fn Log(cx: *mut JSContext, global: &GlobalScope, data: Vec<HandleValue>) {
+        Self::log(cx, global, LogLevel::Log, data)
     }
 
-    // https://developer.mozilla.org/en-US/docs/Web/API/Console
-    pub fn Debug(global: &GlobalScope, messages: Vec<DOMString>) {
-        with_stderr_lock(move || {
-            for message in messages {
-                println!("{}", message);
-                Self::send_to_devtools(global, LogLevel::Debug, message);
-            }
-        })
+    // https://console.spec.whatwg.org/#debug
+    pub unsafe fn Debug(cx: *mut JSContext, global: &GlobalScope, data: Vec<HandleValue>) {
+        Self::log(cx, global, LogLevel::Debug, data)
     }
 
-    // https://developer.mozilla.org/en-US/docs/Web/API/Console/info
-    pub fn Info(global: &GlobalScope, messages: Vec<DOMString>) {
-        with_stderr_lock(move || {
-            for message in messages {
-                println!("{}", message);
-                Self::send_to_devtools(global, LogLevel::Info, message);
-            }
-        })
+    // https://console.spec.whatwg.org/#info
+    pub unsafe fn Info(cx: *mut JSContext, global: &GlobalScope, data: Vec<HandleValue>) {
+        Self::log(cx, global, LogLevel::Info, data)
     }
 
-    // https://developer.mozilla.org/en-US/docs/Web/API/Console/warn
-    pub fn Warn(global: &GlobalScope, messages: Vec<DOMString>) {
-        with_stderr_lock(move || {
-            for message in messages {
-                println!("{}", message);
-                Self::send_to_devtools(global, LogLevel::Warn, message);
-            }
-        })
+    // https://console.spec.whatwg.org/#warn
+    pub unsafe fn Warn(cx: *mut JSContext, global: &GlobalScope, data: Vec<HandleValue>) {
+        Self::log(cx, global, LogLevel::Warn, data)
+    }
+
+    // https://console.spec.whatwg.org/#error
+    pub unsafe fn Error(cx: *mut JSContext, global: &GlobalScope, data: Vec<HandleValue>) {
+        Self::log(cx, global, LogLevel::Error, data)
+    }
+
+    // https://console.spec.whatwg.org/#dirxml
+    pub unsafe fn Dirxml(cx: *mut JSContext, global: &GlobalScope, data: Vec<HandleValue>) {
+        Self::log(cx, global, LogLevel::Log, data)
     }
 
-    // https://developer.mozilla.org/en-US/docs/Web/API/Console/error
-    pub fn Error(global: &GlobalScope, messages: Vec<DOMString>) {
-        with_stderr_lock(move || {
-            for message in messages {
-                println!("{}", message);
-                Self::send_to_devtools(global, LogLevel::Error, message);
+    // https://console.spec.whatwg.org/#assert
+    pub unsafe fn Assert(cx: *mut JSContext, global: &GlobalScope, condition: bool, data: Vec<HandleValue>) {
+        if condition {
+            return;
+        }
+        let depth = preview_depth(global);
+        let mut arguments = data.iter().map(|value| console_argument(cx, *value, depth)).collect::<Vec<_>>();
+        let prefixed = match arguments.first_mut() {
+            Some(&mut ConsoleArgument { value: EvaluateJSReply::StringValue(ref mut first), .. }) => {
+                let message = format!("Assertion failed: {}", first);
+                *first = message;
+                true
             }
-        })
+            _ => false,
+        };
+        if !prefixed {
+            arguments.insert(0, string_argument("Assertion failed".to_owned()));
+        }
+        let mut message = prepare_message(global, LogLevel::Assert, format_arguments(&arguments), arguments);
+        message.stacktrace = Some(devtools::js_stack(cx));
+        Self::report(global, message);
     }
 
-    // https://developer.mozilla.org/en-US/docs/Web/API/Console/assert
-    pub fn Assert(global: &GlobalScope, condition: bool, message: Option<DOMString>) {
-        with_stderr_lock(move || {
-            if !condition {
-                let message = message.unwrap_or_else(|| DOMString::from("no message"));
-                println!("Assertion failed: {}", message);
-                Self::send_to_devtools(global, LogLevel::Error, message);
+    // https://console.spec.whatwg.org/#table
+    pub unsafe fn Table(cx: *mut JSContext,
+                        global: &GlobalScope,
+                        tabular_data: HandleValue,
+                        properties: Option<Vec<DOMString>>) {
+        let mut data = console_argument(cx, tabular_data, 2);
+        let table = match data.preview {
+            Some(ref mut rows) => {
+                if let Some(ref columns) = properties {
+                    for row in rows.iter_mut() {
+                        if let Some(ref mut preview) = row.1.preview {
+                            let filtered = columns.iter().filter_map(|column| {
+                                preview.iter().find(|&&(ref name, _)| *name == **column).cloned()
+                            }).collect();
+                            *preview = filtered;
+                        }
+                    }
+                }
+                format_table(rows)
             }
-        })
+            None => return Self::log(cx, global, LogLevel::Log, vec![tabular_data]),
+        };
+        Self::report(global, prepare_message(global, LogLevel::Table, table, vec![data]));
     }
 
-    // https://developer.mozilla.org/en-US/docs/Web/API/Console/time
+    // https://console.spec.whatwg.org/#trace
+    pub unsafe fn Trace(cx: *mut JSContext, global: &GlobalScope, data: Vec<HandleValue>) {
+        let depth = preview_depth(global);
+        let arguments = data.iter().map(|value| console_argument(cx, *value, depth)).collect::<Vec<_>>();
+        let text = if arguments.is_empty() { "console.trace()".to_owned() } else { format_arguments(&arguments) };
+        let mut message = prepare_message(global, LogLevel::Trace, text, arguments);
+        message.stacktrace = Some(devtools::js_stack(cx));
+        Self::report(global, message);
+    }
+
+    // https://console.spec.whatwg.org/#dir
+    pub unsafe fn Dir(cx: *mut JSContext, global: &GlobalScope, item: HandleValue, _options: Option<*mut JSObject>) {
+        let argument = console_argument(cx, item, 2);
+        Self::report(global, prepare_message(global, LogLevel::Dir, format_value(&argument, false), vec![argument]));
+    }
+
+    // https://console.spec.whatwg.org/#count
+    pub fn Count(global: &GlobalScope, label: DOMString) {
+        let count = global.count(label.clone());
+        let mut message = prepare_message(global, LogLevel::Count, format!("{}: {}", label, count), vec![]);
+        message.counter = Some(ConsoleCounter {
+            label: String::from(label),
+            count: count,
+        });
+        Self::report(global, message);
+    }
+
+    // https://console.spec.whatwg.org/#countreset
+    pub fn CountReset(global: &GlobalScope, label: DOMString) {
+        if global.count_reset(&label).is_err() {
+            Self::warn(global, format!("Count for '{}' does not exist", label));
+        }
+    }
+
+    // https://console.spec.whatwg.org/#group
+    pub unsafe fn Group(cx: *mut JSContext, global: &GlobalScope, data: Vec<HandleValue>) {
+        Self::log(cx, global, LogLevel::Group, data);
+        global.push_console_group();
+    }
+
+    // https://console.spec.whatwg.org/#groupcollapsed
+    pub unsafe fn GroupCollapsed(cx: *mut JSContext, global: &GlobalScope, data: Vec<HandleValue>) {
+        Self::log(cx, global, LogLevel::GroupCollapsed, data);
+        global.push_console_group();
+    }
+
+    // https://console.spec.whatwg.org/#groupend
+    pub fn GroupEnd(global: &GlobalScope) {
+        global.pop_console_group();
+        Self::send_to_devtools(global, prepare_message(global, LogLevel::GroupEnd, String::new(), vec![]));
+    }
+
+    // https://console.spec.whatwg.org/#time
     pub fn Time(global: &GlobalScope, label: DOMString) {
-        with_stderr_lock(move || {
-            if let Ok(()) = global.time(label.clone()) {
-                let message = DOMString::from(format!("{}: timer started", label));
-                println!("{}", message);
-                Self::send_to_devtools(global, LogLevel::Log, message);
-            }
-        })
+        if global.time(label.clone()).is_err() {
+            return Self::warn(global, format!("Timer '{}' already exists", label));
+        }
+        let mut message = prepare_message(global, LogLevel::Time, format!("{}: timer started", label), vec![]);
+        message.timer = Some(ConsoleTimer {
+            name: String::from(label),
+            duration: None,
+        });
+        Self::report(global, message);
+    }
+
+    // https://console.spec.whatwg.org/#timelog
+    pub unsafe fn TimeLog(cx: *mut JSContext, global: &GlobalScope, label: DOMString, data: Vec<HandleValue>) {
+        let delta = match global.time_log(&label) {
+            Ok(delta) => delta,
+            Err(()) => return Self::warn(global, format!("Timer '{}' does not exist", label)),
+        };
+        let depth = preview_depth(global);
+        let arguments = data.iter().map(|value| console_argument(cx, *value, depth)).collect::<Vec<_>>();
+        let mut text = format!("{}: {}ms", label, delta);
+        if !arguments.is_empty() {
+            text = format!("{} {}", text, format_arguments(&arguments));
+        }
+        let mut message = prepare_message(global, LogLevel::TimeLog, text, arguments);
+        message.timer = Some(ConsoleTimer {
+            name: String::from(label),
+            duration: Some(delta),
+        });
+        Self::report(global, message);
     }
 
-    // https://developer.mozilla.org/en-US/docs/Web/API/Console/timeEnd
+    // https://console.spec.whatwg.org/#timeend
     pub fn TimeEnd(global: &GlobalScope, label: DOMString) {
-        with_stderr_lock(move || {
-            if let Ok(delta) = global.time_end(&label) {
-                let message = DOMString::from(
-                    format!("{}: {}ms", label, delta)
-                );
-                println!("{}", message);
-                Self::send_to_devtools(global, LogLevel::Log, message);
-            };
-        })
+        let delta = match global.time_end(&label) {
+            Ok(delta) => delta,
+            Err(()) => return Self::warn(global, format!("Timer '{}' does not exist", label)),
+        };
+        let mut message = prepare_message(global, LogLevel::TimeEnd, format!("{}: {}ms", label, delta), vec![]);
+        message.timer = Some(ConsoleTimer {
+            name: String::from(label),
+            duration: Some(delta),
+        });
+        Self::report(global, message);
     }
 }
 
-fn prepare_message(log_level: LogLevel, message: DOMString) -> ConsoleMessage {
-    // TODO: Sending fake values for filename, lineNumber and columnNumber in LogMessage; adjust later
+/// A message located at the running script.
+fn prepare_message(global: &GlobalScope,
+                   log_level: LogLevel,
+                   message: String,
+                   arguments: Vec<ConsoleArgument>)
+                   -> ConsoleMessage {
+    let stack = devtools::js_stack(global.get_cx());
+    let (filename, line, column) = stack.first().map_or((String::new(), 0, 0), |frame| {
+        (frame.url.clone(), frame.line as usize, frame.column as usize)
+    });
     ConsoleMessage {
-        message: String::from(message),
+        message: message,
         logLevel: log_level,
-        filename: "test".to_owned(),
-        lineNumber: 1,
-        columnNumber: 1,
+        filename: filename,
+        lineNumber: line,
+        columnNumber: column,
+        arguments: arguments,
+        counter: None,
+        timer: None,
+        stacktrace: None,
+    }
+}
+
+fn string_argument(value: String) -> ConsoleArgument {
+    ConsoleArgument {
+        value: EvaluateJSReply::StringValue(value),
+        preview: None,
+        is_accessor: false,
+    }
+}
+
+/// How deep to preview the objects passed to console methods that only log them. Previews are
+/// for devtools to show the objects, so they are only made while a client listens.
+fn preview_depth(global: &GlobalScope) -> u32 {
+    if global.live_devtools_updates() { 1 } else { 0 }
+}
+
+/// Describe a value for devtools, previewing objects down to the given depth.
+#[allow(unsafe_code)]
+unsafe fn console_argument(cx: *mut JSContext, value: HandleValue, depth: u32) -> ConsoleArgument {
+    let preview = if value.is_object() && depth > 0 {
+        rooted!(in(cx) let object = value.to_object());
+        Some(own_properties(cx, object.handle(), depth - 1))
+    } else {
+        None
+    };
+    ConsoleArgument {
+        value: value_to_reply(cx, value),
+        preview: preview,
+        is_accessor: false,
+    }
+}
+
+/// The own enumerable properties of an object with a string key. Only the values of data
+/// properties are previewed, since running getters could have side effects.
+#[allow(unsafe_code)]
+unsafe fn own_properties(cx: *mut JSContext, object: HandleObject, depth: u32) -> Vec<(String, ConsoleArgument)> {
+    let ids = IdVector::new(cx);
+    if !GetPropertyKeys(cx, object, JSITER_OWNONLY, ids.get()) {
+        JS_ClearPendingException(cx);
+        return vec![];
+    }
+    let mut properties = vec![];
+    for id in ids.iter().take(PREVIEW_MAX_PROPERTIES) {
+        rooted!(in(cx) let id = *id);
+        let name = match jsid_to_string(cx, id.handle()) {
+            Some(name) => String::from(name),
+            None => continue,
+        };
+        rooted!(in(cx) let mut descriptor = PropertyDescriptor::default());
+        if !JS_GetOwnPropertyDescriptorById(cx, object, id.handle(), descriptor.handle_mut()) {
+            JS_ClearPendingException(cx);
+            continue;
+        }
+        if descriptor.obj.is_null() {
+            continue;
+        }
+        if descriptor.attrs & (JSPROP_GETTER | JSPROP_SETTER) != 0 {
+            properties.push((name, ConsoleArgument {
+                value: EvaluateJSReply::VoidValue,
+                preview: None,
+                is_accessor: true,
+            }));
+            continue;
+        }
+        rooted!(in(cx) let value = descriptor.value);
+        properties.push((name, console_argument(cx, value.handle(), depth)));
+    }
+    properties
+}
+
+fn number_to_string(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_owned()
+    } else if value.is_infinite() {
+        if value < 0. { "-Infinity" } else { "Infinity" }.to_owned()
+    } else if value == 0. && value.is_sign_negative() {
+        "-0".to_owned()
+    } else {
+        value.to_string()
+    }
+}
+
+/// The text of a value, where strings in objects are quoted.
+fn format_value(argument: &ConsoleArgument, nested: bool) -> String {
+    if argument.is_accessor {
+        return "Getter".to_owned();
+    }
+    match argument.value {
+        EvaluateJSReply::VoidValue => "undefined".to_owned(),
+        EvaluateJSReply::NullValue => "null".to_owned(),
+        EvaluateJSReply::BooleanValue(value) => value.to_string(),
+        EvaluateJSReply::NumberValue(value) => number_to_string(value),
+        EvaluateJSReply::StringValue(ref value) if nested => format!("\"{}\"", value),
+        EvaluateJSReply::StringValue(ref value) => value.clone(),
+        EvaluateJSReply::ActorValue { ref class, .. } => {
+            let properties = match argument.preview {
+                Some(ref properties) => properties,
+                None => return format!("[object {}]", class),
+            };
+            if class == "Array" {
+                let items = properties.iter().map(|&(_, ref value)| format_value(value, true)).collect::<Vec<_>>();
+                format!("[{}]", items.join(", "))
+            } else {
+                let properties = properties.iter().map(|&(ref name, ref value)| {
+                    format!("{}: {}", name, format_value(value, true))
+                }).collect::<Vec<_>>();
+                format!("{} {{{}}}", class, properties.join(", "))
+            }
+        }
+    }
+}
+
+/// Format the values of a console call as text, substituting the values for the format
+/// specifiers of a leading string.
+/// https://console.spec.whatwg.org/#formatter
+fn format_arguments(arguments: &[ConsoleArgument]) -> String {
+    let mut rest = arguments.iter();
+    let mut parts = vec![];
+    if let Some(&ConsoleArgument { value: EvaluateJSReply::StringValue(ref format), .. }) = arguments.first() {
+        rest.next();
+        let mut text = String::new();
+        let mut chars = format.chars().peekable();
+        while let Some(c) = chars.next() {
+            let specifier = match chars.peek() {
+                Some(&specifier) if c == '%' && "sdifoOc".contains(specifier) => specifier,
+                _ => {
+                    text.push(c);
+                    continue;
+                }
+            };
+            let argument = match rest.next() {
+                Some(argument) => argument,
+                None => {
+                    text.push(c);
+                    continue;
+                }
+            };
+            chars.next();
+            match (specifier, &argument.value) {
+                // Styles have no meaning on stdout.
+                ('c', _) => {}
+                ('d', &EvaluateJSReply::NumberValue(value)) |
+                ('i', &EvaluateJSReply::NumberValue(value)) => text.push_str(&number_to_string(value.trunc())),
+                ('f', &EvaluateJSReply::NumberValue(value)) => text.push_str(&number_to_string(value)),
+                ('d', _) | ('i', _) | ('f', _) => text.push_str("NaN"),
+                _ => text.push_str(&format_value(argument, false)),
+            }
+        }
+        parts.push(text);
+    }
+    parts.extend(rest.map(|argument| format_value(argument, false)));
+    parts.join(" ")
+}
+
+/// Lay out the rows of `console.table` data as text, with a column for each property of the
+/// rows and one for the rows that are not objects.
+fn format_table(rows: &[(String, ConsoleArgument)]) -> String {
+    let mut columns: Vec<String> = vec![];
+    let mut has_values = false;
+    for &(_, ref row) in rows {
+        match row.preview {
+            Some(ref properties) => {
+                for &(ref name, _) in properties {
+                    if !columns.contains(name) {
+                        columns.push(name.clone());
+                    }
+                }
+            }
+            None => has_values = true,
+        }
+    }
+
+    let mut header = vec!["(index)".to_owned()];
+    header.extend(columns.iter().cloned());
+    if has_values {
+        header.push("Values".to_owned());
+    }
+    let mut lines = vec![header];
+    for &(ref index, ref row) in rows {
+        let mut line = vec![index.clone()];
+        match row.preview {
+            Some(ref properties) => {
+                line.extend(columns.iter().map(|column| {
+                    properties.iter()
+                              .find(|&&(ref name, _)| name == column)
+                              .map_or(String::new(), |&(_, ref value)| format_value(value, true))
+                }));
+                if has_values {
+                    line.push(String::new());
+                }
+            }
+            None => {
+                line.extend(columns.iter().map(|_| String::new()));
+                line.push(format_value(row, true));
+            }
+        }
+        lines.push(line);
+    }
+
+    let widths = (0..lines[0].len()).map(|column| {
+        lines.iter().map(|line| line[column].chars().count()).max().unwrap_or(0)
+    }).collect::<Vec<_>>();
+    let mut text = vec![];
+    for (index, line) in lines.iter().enumerate() {
+        let cells = line.iter().zip(&widths).map(|(cell, &width)| format!("{:1$}", cell, width)).collect::<Vec<_>>();
+        text.push(cells.join(" | ").trim_right().to_owned());
+        if index == 0 {
+            text.push(widths.iter().map(|&width| "-".repeat(width)).collect::<Vec<_>>().join("-+-"));
+        }
     }
+    text.join("\n")
 }
@@ -56,7 +56,9 @@ impl CSSStyleSheet {
                            CSSStyleSheetBinding::Wrap)
     }
 
-    fn rulelist(&self) -> DomRoot<CSSRuleList> {
+    /// The rules of the sheet. Unlike `cssRules`, this does not check that the sheet is
+    /// origin-clean, and is meant for devtools.
+    pub fn rulelist(&self) -> DomRoot<CSSRuleList> {
         self.rulelist.or_init(|| {
             let rules = self.style_stylesheet.contents.rules.clone();
             CSSRuleList::new(
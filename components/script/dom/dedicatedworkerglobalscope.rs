@@ -30,6 +30,7 @@ use js::jsapi::{JSAutoCompartment, JSContext, NullHandleValue};
 use js::jsval::UndefinedValue;
 use msg::constellation_msg::TopLevelBrowsingContextId;
 use net_traits::{IpcSend, load_whole_resource};
+use net_traits::csp::CspList;
 use net_traits::request::{CredentialsMode, Destination, RequestInit};
 use script_runtime::{CommonScriptMsg, ScriptChan, ScriptPort, new_rt_and_cx, Runtime};
 use script_runtime::ScriptThreadEventCategory::WorkerEvent;
@@ -186,6 +187,7 @@ impl DedicatedWorkerGlobalScope {
                 referrer_url: referrer_url,
                 referrer_policy: referrer_policy,
                 origin,
+                csp_list: init.csp_list.clone(),
                 .. RequestInit::default()
             };
 
@@ -202,6 +204,9 @@ impl DedicatedWorkerGlobalScope {
                 }
                 Ok((metadata, bytes)) => (metadata, bytes)
             };
+            let mut init = init;
+            init.csp_list = CspList::for_worker(&init.csp_list, &metadata.final_url,
+                                                metadata.headers.as_ref().map(|headers| &**headers));
             let url = metadata.final_url;
             let source = String::from_utf8_lossy(&bytes);
 
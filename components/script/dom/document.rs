@@ -12,10 +12,14 @@ use dom::bindings::callback::ExceptionHandling;
 use dom::bindings::cell::DomRefCell;
 use dom::bindings::codegen::Bindings::DocumentBinding;
 use dom::bindings::codegen::Bindings::DocumentBinding::{DocumentMethods, DocumentReadyState, ElementCreationOptions};
+use dom::bindings::codegen::Bindings::EventBinding::EventInit;
 use dom::bindings::codegen::Bindings::HTMLIFrameElementBinding::HTMLIFrameElementBinding::HTMLIFrameElementMethods;
 use dom::bindings::codegen::Bindings::NodeBinding::NodeMethods;
 use dom::bindings::codegen::Bindings::NodeFilterBinding::NodeFilter;
 use dom::bindings::codegen::Bindings::PerformanceBinding::PerformanceMethods;
+use dom::bindings::codegen::Bindings::SecurityPolicyViolationEventBinding::SecurityPolicyViolationEventDisposition;
+use dom::bindings::codegen::Bindings::SecurityPolicyViolationEventBinding::SecurityPolicyViolationEventInit;
+use dom::bindings::codegen::Bindings::SelectionBinding::SelectionMethods;
 use dom::bindings::codegen::Bindings::TouchBinding::TouchMethods;
 use dom::bindings::codegen::Bindings::WindowBinding::{FrameRequestCallback, ScrollBehavior, WindowMethods};
 use dom::bindings::codegen::UnionTypes::NodeOrString;
@@ -34,6 +38,7 @@ use dom::comment::Comment;
 use dom::cssstylesheet::CSSStyleSheet;
 use dom::customelementregistry::CustomElementDefinition;
 use dom::customevent::CustomEvent;
+use dom::characterdata::CharacterData;
 use dom::documentfragment::DocumentFragment;
 use dom::documenttype::DocumentType;
 use dom::domimplementation::DOMImplementation;
@@ -58,8 +63,10 @@ use dom::htmlheadelement::HTMLHeadElement;
 use dom::htmlhtmlelement::HTMLHtmlElement;
 use dom::htmliframeelement::HTMLIFrameElement;
 use dom::htmlimageelement::HTMLImageElement;
+use dom::htmlinputelement::HTMLInputElement;
 use dom::htmlmetaelement::HTMLMetaElement;
 use dom::htmlscriptelement::{HTMLScriptElement, ScriptResult};
+use dom::htmltextareaelement::HTMLTextAreaElement;
 use dom::htmltitleelement::HTMLTitleElement;
 use dom::keyboardevent::KeyboardEvent;
 use dom::location::Location;
@@ -75,7 +82,10 @@ use dom::processinginstruction::ProcessingInstruction;
 use dom::progressevent::ProgressEvent;
 use dom::promise::Promise;
 use dom::range::Range;
+use dom::selection::{LayoutSelectionHelpers, Selection};
+use dom::securitypolicyviolationevent::SecurityPolicyViolationEvent;
 use dom::servoparser::ServoParser;
+use dom::shadowroot::ShadowRoot;
 use dom::storageevent::StorageEvent;
 use dom::stylesheetlist::StyleSheetList;
 use dom::text::Text;
@@ -102,12 +112,14 @@ use msg::constellation_msg::{BrowsingContextId, Key, KeyModifiers, KeyState, Top
 use net_traits::{FetchResponseMsg, IpcSend, ReferrerPolicy};
 use net_traits::CookieSource::NonHTTP;
 use net_traits::CoreResourceMsg::{GetCookiesForUrl, SetCookiesForUrl};
+use net_traits::csp::{CheckResult, CspList, InlineCheckType, PolicyDisposition, Violation};
 use net_traits::pub_domains::is_pub_domain;
 use net_traits::request::RequestInit;
 use net_traits::response::HttpsState;
 use num_traits::ToPrimitive;
 use profile_traits::time::{TimerMetadata, TimerMetadataFrameType, TimerMetadataReflowType};
 use script_layout_interface::message::{Msg, NodesFromPointQueryType, ReflowGoal};
+use script_layout_interface::rpc::TextIndexResponse;
 use script_runtime::{CommonScriptMsg, ScriptThreadEventCategory};
 use script_thread::{MainThreadScriptMsg, ScriptThread};
 use script_traits::{AnimationState, DocumentActivity, MouseButton, MouseEventType};
@@ -119,11 +131,13 @@ use servo_config::prefs::PREFS;
 use servo_url::{ImmutableOrigin, MutableOrigin, ServoUrl};
 use std::borrow::ToOwned;
 use std::cell::{Cell, Ref, RefMut};
+use std::cmp;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::collections::hash_map::Entry::{Occupied, Vacant};
 use std::default::Default;
 use std::iter::once;
 use std::mem;
+use std::ops;
 use std::rc::Rc;
 use std::time::{Duration, Instant};
 use style::attr::AttrValue;
@@ -136,8 +150,10 @@ use style::str::{HTML_SPACE_CHARACTERS, split_html_space_chars, str_join};
 use style::stylesheet_set::StylesheetSet;
 use style::stylesheets::{Stylesheet, StylesheetContents, Origin, OriginSet};
 use task_source::TaskSource;
+use textinput::{is_control_key, len_of_first_n_code_units};
 use time;
 use timers::OneshotTimerCallback;
+use unicode_segmentation::UnicodeSegmentation;
 use url::Host;
 use url::percent_encoding::percent_decode;
 use webrender_api::ClipId;
@@ -262,6 +278,13 @@ pub struct Document {
     /// List of stylesheets associated with nodes in this document. |None| if the list needs to be refreshed.
     stylesheets: DomRefCell<StylesheetSet<StyleSheetInDocument>>,
     stylesheet_list: MutNullableDom<StyleSheetList>,
+    /// The shadow roots attached to connected elements of this document, whose
+    /// stylesheets layout needs to flush.
+    shadow_roots: DomRefCell<Vec<Dom<ShadowRoot>>>,
+    /// <https://w3c.github.io/selection-api/#dfn-selection>
+    selection: MutNullableDom<Selection>,
+    /// Whether the user is currently selecting text by dragging the mouse.
+    mouse_selection_in_progress: Cell<bool>,
     ready_state: Cell<DocumentReadyState>,
     /// Whether the DOMContentLoaded event has already been dispatched.
     domcontentloaded_dispatched: Cell<bool>,
@@ -330,6 +353,8 @@ pub struct Document {
     origin: MutableOrigin,
     ///  https://w3c.github.io/webappsec-referrer-policy/#referrer-policy-states
     referrer_policy: Cell<Option<ReferrerPolicy>>,
+    /// <https://w3c.github.io/webappsec-csp/#concept-document-csp-list>
+    csp_list: DomRefCell<CspList>,
     /// <https://html.spec.whatwg.org/multipage/#dom-document-referrer>
     referrer: Option<String>,
     /// <https://html.spec.whatwg.org/multipage/#target-element>
@@ -801,6 +826,9 @@ impl Document {
         if let Some(ref elem) = self.focused.get() {
             let node = elem.upcast::<Node>();
             elem.set_focus_state(false);
+            // The mouseup that would end a selection made with the mouse may never come
+            // once the focus moves away.
+            self.mouse_selection_in_progress.set(false);
             // FIXME: pass appropriate relatedTarget
             self.fire_focus_event(FocusEventType::Blur, node, None);
         }
@@ -847,7 +875,7 @@ impl Document {
     pub fn handle_mouse_event(
         &self,
         js_runtime: *mut JSRuntime,
-        _button: MouseButton,
+        button: MouseButton,
         client_point: Point2D<f32>,
         mouse_event_type: MouseEventType,
         node_address: Option<UntrustedNodeAddress>,
@@ -860,8 +888,15 @@ impl Document {
         };
         debug!("{}: at {:?}", mouse_event_type_string, client_point);
 
-        let el = node_address.and_then(|address| {
-            let node = unsafe { node::from_untrusted_node_address(js_runtime, address) };
+        // Releasing the button ends a selection made with the mouse, wherever it happens.
+        if let MouseEventType::MouseUp = mouse_event_type {
+            self.mouse_selection_in_progress.set(false);
+        }
+
+        let hit_node = node_address.map(|address| {
+            unsafe { node::from_untrusted_node_address(js_runtime, address) }
+        });
+        let el = hit_node.as_ref().and_then(|node| {
             node.inclusive_ancestors()
                 .filter_map(DomRoot::downcast::<Element>)
                 .next()
@@ -919,7 +954,11 @@ impl Document {
                 }
 
                 let target = node.upcast();
-                event.fire(target);
+                let status = event.fire(target);
+
+                if let (MouseButton::Left, EventStatus::NotCanceled) = (button, status) {
+                    self.begin_mouse_selection(hit_node.as_ref().unwrap(), client_point);
+                }
             },
             MouseEventType::MouseUp => {
                 if let Some(a) = activatable {
@@ -933,13 +972,17 @@ impl Document {
 
         if let MouseEventType::Click = mouse_event_type {
             self.commit_focus_transaction(FocusType::Element);
-            self.maybe_fire_dblclick(client_point, node);
+            if self.maybe_fire_dblclick(client_point, node) {
+                self.select_word_at_point(hit_node.as_ref().unwrap(), client_point);
+            }
         }
 
         self.window.reflow(ReflowGoal::Full, ReflowReason::MouseEvent);
     }
 
-    fn maybe_fire_dblclick(&self, click_pos: Point2D<f32>, target: &Node) {
+    /// Fires a `dblclick` event if this click follows another one closely
+    /// enough, and returns whether the default action should happen.
+    fn maybe_fire_dblclick(&self, click_pos: Point2D<f32>, target: &Node) -> bool {
         // https://w3c.github.io/uievents/#event-type-dblclick
         let now = Instant::now();
 
@@ -980,16 +1023,109 @@ impl Document {
                     None,
                     None
                 );
-                event.upcast::<Event>().fire(target.upcast());
+                let status = event.upcast::<Event>().fire(target.upcast());
 
                 // When a double click occurs, self.last_click_info is left as None so that a
                 // third sequential click will not cause another double click.
-                return;
+                return status == EventStatus::NotCanceled;
             }
         }
 
         // Update last_click_info with the time and position of the click.
         *self.last_click_info.borrow_mut() = Some((now, click_pos));
+        false
+    }
+
+    /// Returns the offset of the boundary point in the text node `node` that is
+    /// closest to `client_point`, or `None` if `node` isn't a text node.
+    fn caret_offset_from_point(&self, node: &Node, client_point: Point2D<f32>) -> Option<u32> {
+        if !node.is::<Text>() {
+            return None;
+        }
+        let page_point = Point2D::new(client_point.x + self.window.PageXOffset() as f32,
+                                      client_point.y + self.window.PageYOffset() as f32);
+        // The index is a byte offset into the text of the node, mapped back
+        // from the rendered text whose white space may have been collapsed.
+        let TextIndexResponse(index) = self.window.text_offset_query(node.to_trusted_node_address(), page_point);
+        let data = node.downcast::<CharacterData>().unwrap().data();
+        Some(utf16_len_of_first_n_bytes(&data, index?))
+    }
+
+    /// Starts selecting text from `client_point` with the mouse, which is over `node`.
+    fn begin_mouse_selection(&self, node: &Node, client_point: Point2D<f32>) {
+        let selection = match self.GetSelection() {
+            Some(selection) => selection,
+            None => return,
+        };
+
+        // https://w3c.github.io/selection-api/#selectstart-event
+        let event = node.upcast::<EventTarget>().fire_bubbling_cancelable_event(atom!("selectstart"));
+        if event.get_cancel_state() == EventDefault::Prevented {
+            return;
+        }
+
+        match self.caret_offset_from_point(node, client_point) {
+            Some(offset) => {
+                let _ = selection.Collapse(Some(node), offset);
+                self.mouse_selection_in_progress.set(true);
+            },
+            None => selection.RemoveAllRanges(),
+        }
+    }
+
+    /// Moves the focus of the selection made with the mouse to `client_point`,
+    /// which is over `node`.
+    fn extend_mouse_selection(&self, node: &Node, client_point: Point2D<f32>) {
+        let offset = match self.caret_offset_from_point(node, client_point) {
+            Some(offset) => offset,
+            None => return,
+        };
+        if let Some(selection) = self.selection.get() {
+            let _ = selection.Extend(node, offset);
+            self.window.reflow(ReflowGoal::Full, ReflowReason::MouseEvent);
+        }
+    }
+
+    /// Selects the word at `client_point` in the text node `node`.
+    fn select_word_at_point(&self, node: &Node, client_point: Point2D<f32>) {
+        let offset = match self.caret_offset_from_point(node, client_point) {
+            Some(offset) => offset,
+            None => return,
+        };
+        let selection = match self.GetSelection() {
+            Some(selection) => selection,
+            None => return,
+        };
+
+        let (start, end) = {
+            let data = node.downcast::<CharacterData>().unwrap().data();
+            let byte_offset = len_of_first_n_code_units(&data, offset as usize);
+            let word = data.split_word_bound_indices()
+                .find(|&(index, word)| byte_offset < index + word.len())
+                .or_else(|| data.split_word_bound_indices().last());
+            match word {
+                Some((index, word)) => (utf16_len_of_first_n_bytes(&data, index),
+                                        utf16_len_of_first_n_bytes(&data, index + word.len())),
+                None => return,
+            }
+        };
+        let _ = selection.SetBaseAndExtent(node, start, node, end);
+    }
+
+    /// Copies the selected text to the clipboard, unless the focused element
+    /// is a text control, which handles copying its own selection.
+    fn copy_selection_to_clipboard(&self) {
+        let focus_is_text_control = self.get_focused_element().map_or(false, |element| {
+            element.is::<HTMLInputElement>() || element.is::<HTMLTextAreaElement>()
+        });
+        if focus_is_text_control {
+            return;
+        }
+        if let Some(selection) = self.selection.get() {
+            if !selection.IsCollapsed() {
+                self.send_to_constellation(ScriptMsg::SetClipboardContents(selection.Stringifier().into()));
+            }
+        }
     }
 
     #[allow(unsafe_code)]
@@ -1096,8 +1232,10 @@ impl Document {
             Some(client_point) => client_point,
         };
 
-        let maybe_new_target = node_address.and_then(|address| {
-            let node = unsafe { node::from_untrusted_node_address(js_runtime, address) };
+        let hit_node = node_address.map(|address| {
+            unsafe { node::from_untrusted_node_address(js_runtime, address) }
+        });
+        let maybe_new_target = hit_node.as_ref().and_then(|node| {
             node.inclusive_ancestors()
                 .filter_map(DomRoot::downcast::<Element>)
                 .next()
@@ -1112,6 +1250,10 @@ impl Document {
 
         self.fire_mouse_event(client_point, new_target.upcast(), FireMouseEventType::Move);
 
+        if self.mouse_selection_in_progress.get() {
+            self.extend_mouse_selection(hit_node.as_ref().unwrap(), client_point);
+        }
+
         // Nothing more to do here, mousemove is sent,
         // and the element under the mouse hasn't changed.
         if maybe_new_target == prev_mouse_over_target.get() {
@@ -1384,6 +1526,9 @@ impl Document {
                         }
                     }
                 }
+                Key::C if state == KeyState::Pressed && is_control_key(modifiers) => {
+                    self.copy_selection_to_clipboard();
+                }
                 _ => (),
             }
         }
@@ -1578,8 +1723,9 @@ impl Document {
     }
 
     pub fn fetch_async(&self, load: LoadType,
-                       request: RequestInit,
+                       mut request: RequestInit,
                        fetch_target: IpcSender<FetchResponseMsg>) {
+        request.csp_list = self.get_csp_list().clone();
         let mut loader = self.loader.borrow_mut();
         loader.fetch_async(load, request, fetch_target);
     }
@@ -2048,6 +2194,8 @@ pub trait LayoutDocumentHelpers {
     unsafe fn will_paint(&self);
     unsafe fn quirks_mode(&self) -> QuirksMode;
     unsafe fn style_shared_lock(&self) -> &StyleSharedRwLock;
+    unsafe fn shadow_roots_for_layout(&self) -> Vec<LayoutDom<ShadowRoot>>;
+    unsafe fn selected_text_for_layout(&self, text: LayoutDom<Text>) -> Option<ops::Range<usize>>;
 }
 
 #[allow(unsafe_code)]
@@ -2089,6 +2237,29 @@ impl LayoutDocumentHelpers for LayoutDom<Document> {
     unsafe fn style_shared_lock(&self) -> &StyleSharedRwLock {
         (*self.unsafe_get()).style_shared_lock()
     }
+
+    #[inline]
+    #[allow(unrooted_must_root)]
+    unsafe fn shadow_roots_for_layout(&self) -> Vec<LayoutDom<ShadowRoot>> {
+        (*self.unsafe_get()).shadow_roots.borrow_for_layout().iter().map(|root| root.to_layout()).collect()
+    }
+
+    #[inline]
+    unsafe fn selected_text_for_layout(&self, text: LayoutDom<Text>) -> Option<ops::Range<usize>> {
+        (*self.unsafe_get()).selection.get_inner_as_layout()?.selected_text_for_layout(text)
+    }
+}
+
+/// The length in UTF-16 code units of the first `n` bytes of a string, rounding
+/// `n` down to a character boundary.
+///
+/// If the string is shorter than `n` bytes, returns the length of the whole string.
+fn utf16_len_of_first_n_bytes(text: &str, n: usize) -> u32 {
+    let mut n = cmp::min(n, text.len());
+    while !text.is_char_boundary(n) {
+        n -= 1;
+    }
+    text[..n].chars().map(char::len_utf16).sum::<usize>() as u32
 }
 
 // https://html.spec.whatwg.org/multipage/#is-a-registrable-domain-suffix-of-or-is-equal-to
@@ -2226,6 +2397,9 @@ impl Document {
                 //StyleSharedRwLock::new()
             },
             stylesheets: DomRefCell::new(StylesheetSet::new()),
+            shadow_roots: DomRefCell::new(vec![]),
+            selection: Default::default(),
+            mouse_selection_in_progress: Cell::new(false),
             stylesheet_list: MutNullableDom::new(None),
             ready_state: Cell::new(ready_state),
             domcontentloaded_dispatched: Cell::new(domcontentloaded_dispatched),
@@ -2261,6 +2435,7 @@ impl Document {
             origin: origin,
             referrer: referrer,
             referrer_policy: Cell::new(referrer_policy),
+            csp_list: DomRefCell::new(CspList::new()),
             target_element: MutNullableDom::new(None),
             last_click_info: DomRefCell::new(None),
             ignore_destructive_writes_counter: Default::default(),
@@ -2375,9 +2550,43 @@ impl Document {
         Some(Device::new(MediaType::screen(), viewport_size, device_pixel_ratio))
     }
 
-    /// Remove a stylesheet owned by `owner` from the list of document sheets.
+    /// Updates the text nodes painted as selected, if the selection changed.
+    pub fn flush_selection_for_reflow(&self) {
+        if let Some(selection) = self.selection.get() {
+            selection.flush_for_reflow();
+        }
+    }
+
+    pub fn register_shadow_root(&self, shadow_root: &ShadowRoot) {
+        self.shadow_roots.borrow_mut().push(Dom::from_ref(shadow_root));
+    }
+
+    pub fn unregister_shadow_root(&self, shadow_root: &ShadowRoot) {
+        self.shadow_roots.borrow_mut().retain(|root| &**root != shadow_root);
+    }
+
+    /// Remove a stylesheet owned by `owner` from the list of document sheets,
+    /// or from the sheets of the shadow tree it's in.
     #[allow(unrooted_must_root)] // Owner needs to be rooted already necessarily.
     pub fn remove_stylesheet(&self, owner: &Element, s: &Arc<Stylesheet>) {
+        if let Some(shadow_root) = owner.upcast::<Node>().containing_shadow_root() {
+            if shadow_root.remove_stylesheet(owner, s) {
+                return;
+            }
+        }
+
+        let in_document = self.stylesheets.borrow().iter().any(|(sheet, _origin)| Arc::ptr_eq(&sheet.sheet, s));
+        if !in_document {
+            // The owner has just been removed from a shadow tree, so it can't
+            // find it anymore.
+            for shadow_root in self.shadow_roots.borrow().iter() {
+                if shadow_root.remove_stylesheet(owner, s) {
+                    return;
+                }
+            }
+            return;
+        }
+
         self.window()
             .layout_chan()
             .send(Msg::RemoveStylesheet(s.clone()))
@@ -2406,6 +2615,11 @@ impl Document {
         debug_assert!(owner.as_stylesheet_owner().is_some() ||
                       owner.is::<HTMLMetaElement>(), "Wat");
 
+        if let Some(shadow_root) = owner.upcast::<Node>().containing_shadow_root() {
+            shadow_root.add_stylesheet(owner, sheet);
+            return;
+        }
+
         let mut stylesheets = self.stylesheets.borrow_mut();
         let insertion_point =
             stylesheets
@@ -2544,6 +2758,88 @@ impl Document {
         return self.referrer_policy.get();
     }
 
+    pub fn get_csp_list(&self) -> Ref<CspList> {
+        self.csp_list.borrow()
+    }
+
+    /// Enforce or monitor the given policies for this document, as delivered
+    /// by its response headers or a `<meta http-equiv>` element.
+    pub fn append_csp_list(&self, csp_list: CspList) {
+        self.csp_list.borrow_mut().append(csp_list);
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#should-block-inline>
+    pub fn should_inline_be_blocked(&self,
+                                    check_type: InlineCheckType,
+                                    nonce: Option<&str>,
+                                    source: &str)
+                                    -> bool {
+        let (result, violations) = self.get_csp_list().should_inline_be_blocked(check_type, nonce, source);
+        self.report_csp_violations(violations);
+        result == CheckResult::Blocked
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#can-compile-strings>
+    pub fn is_js_evaluation_allowed(&self) -> bool {
+        let (result, violations) = self.get_csp_list().is_js_evaluation_allowed();
+        self.report_csp_violations(violations);
+        result == CheckResult::Allowed
+    }
+
+    fn fire_security_policy_violation_event(&self, violation: &Violation) {
+        let init = SecurityPolicyViolationEventInit {
+            parent: EventInit {
+                bubbles: true,
+                cancelable: false,
+                composed: false,
+            },
+            documentURI: USVString(self.url().into_string()),
+            referrer: USVString(self.referrer.clone().unwrap_or_default()),
+            blockedURI: USVString(violation.blocked_uri()),
+            violatedDirective: DOMString::from(violation.directive.clone()),
+            effectiveDirective: DOMString::from(violation.directive.clone()),
+            originalPolicy: DOMString::from(violation.policy.clone()),
+            sourceFile: USVString(String::new()),
+            sample: DOMString::from(violation.sample.clone().unwrap_or_default()),
+            disposition: match violation.disposition {
+                PolicyDisposition::Enforce => SecurityPolicyViolationEventDisposition::Enforce,
+                PolicyDisposition::Report => SecurityPolicyViolationEventDisposition::Report,
+            },
+            statusCode: 0,
+            lineNumber: 0,
+            columnNumber: 0,
+        };
+        let event = SecurityPolicyViolationEvent::new(self.window.upcast(),
+                                                      atom!("securitypolicyviolation"),
+                                                      EventBubbles::Bubbles,
+                                                      EventCancelable::NotCancelable,
+                                                      &init);
+        event.upcast::<Event>().fire(self.upcast());
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#report-violation>
+    pub fn report_csp_violations(&self, violations: Vec<Violation>) {
+        let document_url = self.url();
+        let referrer = self.referrer.clone().unwrap_or_default();
+        for violation in violations {
+            warn!("Content Security Policy violation: {} blocked by {} ({})",
+                  violation.blocked_uri(), violation.directive, violation.policy);
+
+            // Step 3.
+            let document = Trusted::new(self);
+            let event_violation = violation.clone();
+            let _ = self.window.dom_manipulation_task_source().queue(
+                task!(fire_security_policy_violation_event: move || {
+                    document.root().fire_security_policy_violation_event(&event_violation);
+                }),
+                self.window.upcast(),
+            );
+
+            // Step 4.
+            self.window.upcast::<GlobalScope>().send_csp_report(&violation, &document_url, &referrer);
+        }
+    }
+
     pub fn set_target_element(&self, node: Option<&Element>) {
         if let Some(ref element) = self.target_element.get() {
             element.set_target_state(false);
@@ -2721,6 +3017,14 @@ impl ProfilerMetadataFactory for Document {
 }
 
 impl DocumentMethods for Document {
+    // https://w3c.github.io/selection-api/#dom-document-getselection
+    fn GetSelection(&self) -> Option<DomRoot<Selection>> {
+        if !self.has_browsing_context {
+            return None;
+        }
+        Some(self.selection.or_init(|| Selection::new(self)))
+    }
+
     // https://drafts.csswg.org/cssom/#dom-document-stylesheets
     fn StyleSheets(&self) -> DomRoot<StyleSheetList> {
         self.stylesheet_list.or_init(|| StyleSheetList::new(&self.window, Dom::from_ref(&self)))
@@ -3017,7 +3321,7 @@ impl DocumentMethods for Document {
     // https://dom.spec.whatwg.org/#dom-document-importnode
     fn ImportNode(&self, node: &Node, deep: bool) -> Fallible<DomRoot<Node>> {
         // Step 1.
-        if node.is::<Document>() {
+        if node.is::<Document>() || node.is::<ShadowRoot>() {
             return Err(Error::NotSupported);
         }
 
@@ -3039,9 +3343,14 @@ impl DocumentMethods for Document {
         }
 
         // Step 2.
-        Node::adopt(node, self);
+        if node.is::<ShadowRoot>() {
+            return Err(Error::HierarchyRequest);
+        }
 
         // Step 3.
+        Node::adopt(node, self);
+
+        // Step 4.
         Ok(DomRoot::from_ref(node))
     }
 
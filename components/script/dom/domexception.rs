@@ -35,6 +35,12 @@ pub enum DOMErrorName {
     TimeoutError = DOMExceptionConstants::TIMEOUT_ERR,
     InvalidNodeTypeError = DOMExceptionConstants::INVALID_NODE_TYPE_ERR,
     DataCloneError = DOMExceptionConstants::DATA_CLONE_ERR,
+    // Error names without a legacy code.
+    ConstraintError,
+    DataError,
+    TransactionInactiveError,
+    ReadOnlyError,
+    VersionError,
 }
 
 #[dom_struct]
@@ -61,7 +67,14 @@ impl DOMException {
 impl DOMExceptionMethods for DOMException {
     // https://heycam.github.io/webidl/#dfn-DOMException
     fn Code(&self) -> u16 {
-        self.code as u16
+        match self.code {
+            DOMErrorName::ConstraintError |
+            DOMErrorName::DataError |
+            DOMErrorName::TransactionInactiveError |
+            DOMErrorName::ReadOnlyError |
+            DOMErrorName::VersionError => 0,
+            code => code as u16,
+        }
     }
 
     // https://heycam.github.io/webidl/#idl-DOMException-error-names
@@ -94,6 +107,14 @@ impl DOMExceptionMethods for DOMException {
             DOMErrorName::InvalidNodeTypeError =>
                 "The supplied node is incorrect or has an incorrect ancestor for this operation.",
             DOMErrorName::DataCloneError => "The object can not be cloned.",
+            DOMErrorName::ConstraintError => "A mutation operation in a transaction failed because a constraint \
+                                              was not satisfied.",
+            DOMErrorName::DataError => "The provided data is inadequate.",
+            DOMErrorName::TransactionInactiveError => "A request was placed against a transaction which is not \
+                                                       active.",
+            DOMErrorName::ReadOnlyError => "The mutating operation was attempted in a read-only transaction.",
+            DOMErrorName::VersionError => "An attempt was made to open a database using a lower version than \
+                                           the existing version.",
         };
 
         DOMString::from(message)
@@ -11,11 +11,12 @@ use dom::bindings::cell::DomRefCell;
 use dom::bindings::codegen::Bindings::AttrBinding::AttrMethods;
 use dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
 use dom::bindings::codegen::Bindings::ElementBinding;
-use dom::bindings::codegen::Bindings::ElementBinding::ElementMethods;
+use dom::bindings::codegen::Bindings::ElementBinding::{ElementMethods, ShadowRootInit};
 use dom::bindings::codegen::Bindings::EventBinding::EventMethods;
 use dom::bindings::codegen::Bindings::FunctionBinding::Function;
 use dom::bindings::codegen::Bindings::HTMLTemplateElementBinding::HTMLTemplateElementMethods;
 use dom::bindings::codegen::Bindings::NodeBinding::NodeMethods;
+use dom::bindings::codegen::Bindings::ShadowRootBinding::{ShadowRootMethods, ShadowRootMode};
 use dom::bindings::codegen::Bindings::WindowBinding::{ScrollBehavior, ScrollToOptions};
 use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use dom::bindings::codegen::UnionTypes::NodeOrString;
@@ -31,6 +32,7 @@ use dom::bindings::xmlname::XMLName::InvalidXMLName;
 use dom::characterdata::CharacterData;
 use dom::create::create_element;
 use dom::customelementregistry::{CallbackReaction, CustomElementDefinition, CustomElementReaction};
+use dom::customelementregistry::is_valid_custom_element_name;
 use dom::document::{Document, LayoutDocumentHelpers};
 use dom::documentfragment::DocumentFragment;
 use dom::domrect::DOMRect;
@@ -56,6 +58,7 @@ use dom::htmllinkelement::HTMLLinkElement;
 use dom::htmlobjectelement::HTMLObjectElement;
 use dom::htmloptgroupelement::HTMLOptGroupElement;
 use dom::htmlselectelement::HTMLSelectElement;
+use dom::htmlslotelement::HTMLSlotElement;
 use dom::htmlstyleelement::HTMLStyleElement;
 use dom::htmltablecellelement::{HTMLTableCellElement, HTMLTableCellElementLayoutHelpers};
 use dom::htmltableelement::{HTMLTableElement, HTMLTableElementLayoutHelpers};
@@ -71,6 +74,7 @@ use dom::node::{document_from_node, window_from_node};
 use dom::nodelist::NodeList;
 use dom::promise::Promise;
 use dom::servoparser::ServoParser;
+use dom::shadowroot::ShadowRoot;
 use dom::text::Text;
 use dom::validation::Validatable;
 use dom::virtualmethods::{VirtualMethods, vtable_for};
@@ -83,6 +87,7 @@ use html5ever::serialize::TraversalScope;
 use html5ever::serialize::TraversalScope::{ChildrenOnly, IncludeNode};
 use js::jsapi::Heap;
 use js::jsval::JSVal;
+use net_traits::csp::InlineCheckType;
 use net_traits::request::CorsSettings;
 use ref_filter_map::ref_filter_map;
 use script_layout_interface::message::ReflowGoal;
@@ -157,6 +162,8 @@ pub struct Element {
     custom_element_definition: DomRefCell<Option<Rc<CustomElementDefinition>>>,
     /// <https://dom.spec.whatwg.org/#concept-element-custom-element-state>
     custom_element_state: Cell<CustomElementState>,
+    /// <https://dom.spec.whatwg.org/#concept-element-shadow-root>
+    shadow_root: MutNullableDom<ShadowRoot>,
 }
 
 impl fmt::Debug for Element {
@@ -272,6 +279,7 @@ impl Element {
             custom_element_reaction_queue: Default::default(),
             custom_element_definition: Default::default(),
             custom_element_state: Cell::new(CustomElementState::Uncustomized),
+            shadow_root: Default::default(),
         }
     }
 
@@ -483,6 +491,7 @@ pub trait LayoutElementHelpers {
     fn get_state_for_layout(&self) -> ElementState;
     fn insert_selector_flags(&self, flags: ElementSelectorFlags);
     fn has_selector_flags(&self, flags: ElementSelectorFlags) -> bool;
+    fn get_shadow_root_for_layout(&self) -> Option<LayoutDom<ShadowRoot>>;
 }
 
 impl LayoutElementHelpers for LayoutDom<Element> {
@@ -912,6 +921,14 @@ impl LayoutElementHelpers for LayoutDom<Element> {
             (*self.unsafe_get()).selector_flags.get().contains(flags)
         }
     }
+
+    #[inline]
+    #[allow(unsafe_code)]
+    fn get_shadow_root_for_layout(&self) -> Option<LayoutDom<ShadowRoot>> {
+        unsafe {
+            (*self.unsafe_get()).shadow_root.get_inner_as_layout()
+        }
+    }
 }
 
 impl Element {
@@ -1609,6 +1626,16 @@ impl ElementMethods for Element {
         self.set_tokenlist_attribute(&local_name!("class"), class);
     }
 
+    // https://dom.spec.whatwg.org/#dom-element-slot
+    fn Slot(&self) -> DOMString {
+        self.get_string_attribute(&local_name!("slot"))
+    }
+
+    // https://dom.spec.whatwg.org/#dom-element-slot
+    fn SetSlot(&self, slot: DOMString) {
+        self.set_string_attribute(&local_name!("slot"), slot);
+    }
+
     // https://dom.spec.whatwg.org/#dom-element-classlist
     fn ClassList(&self) -> DomRoot<DOMTokenList> {
         self.class_list.or_init(|| DOMTokenList::new(self, &local_name!("class")))
@@ -2154,7 +2181,7 @@ impl ElementMethods for Element {
             NodeTypeId::Document(_) => return Err(Error::NoModificationAllowed),
 
             // Step 4.
-            NodeTypeId::DocumentFragment => {
+            NodeTypeId::DocumentFragment(_) => {
                 let body_elem = Element::create(QualName::new(None, ns!(html), local_name!("body")),
                                                 None,
                                                 &context_document,
@@ -2331,6 +2358,59 @@ impl ElementMethods for Element {
         self.insert_adjacent(position, fragment.upcast()).map(|_| ())
     }
 
+    // https://dom.spec.whatwg.org/#dom-element-attachshadow
+    fn AttachShadow(&self, init: &ShadowRootInit) -> Fallible<DomRoot<ShadowRoot>> {
+        // Step 1.
+        if self.namespace != ns!(html) {
+            return Err(Error::NotSupported);
+        }
+
+        // Step 2.
+        match self.local_name() {
+            &local_name!("article") | &local_name!("aside") | &local_name!("blockquote") |
+            &local_name!("body") | &local_name!("div") | &local_name!("footer") |
+            &local_name!("h1") | &local_name!("h2") | &local_name!("h3") |
+            &local_name!("h4") | &local_name!("h5") | &local_name!("h6") |
+            &local_name!("header") | &local_name!("main") | &local_name!("nav") |
+            &local_name!("p") | &local_name!("section") | &local_name!("span") => {},
+            local_name if is_valid_custom_element_name(local_name) => {},
+            _ => return Err(Error::NotSupported),
+        }
+
+        // Step 3.
+        if self.shadow_root.get().is_some() {
+            return Err(Error::NotSupported);
+        }
+
+        // Steps 4-5.
+        let shadow_root = ShadowRoot::new(self, init.mode);
+        let node = self.upcast::<Node>();
+        shadow_root.upcast::<Node>().set_flag(NodeFlags::IS_IN_DOC, node.is_in_doc());
+        self.shadow_root.set(Some(&shadow_root));
+        if node.is_in_doc() {
+            let doc = document_from_node(self);
+            doc.register_shadow_root(&shadow_root);
+            // The children of the host aren't rendered anymore.
+            doc.content_and_heritage_changed(node);
+        }
+
+        // Step 6.
+        Ok(shadow_root)
+    }
+
+    // https://dom.spec.whatwg.org/#dom-element-shadowroot
+    fn GetShadowRoot(&self) -> Option<DomRoot<ShadowRoot>> {
+        match self.shadow_root.get() {
+            Some(ref shadow_root) if shadow_root.Mode() == ShadowRootMode::Closed => None,
+            shadow_root => shadow_root,
+        }
+    }
+
+    // https://dom.spec.whatwg.org/#dom-slotable-assignedslot
+    fn GetAssignedSlot(&self) -> Option<DomRoot<HTMLSlotElement>> {
+        self.upcast::<Node>().find_a_slot(true)
+    }
+
     // check-tidy: no specs after this line
     fn EnterFormalActivationState(&self) -> ErrorResult {
         match self.as_maybe_activatable() {
@@ -2394,7 +2474,7 @@ impl VirtualMethods for Element {
                             _ => false,
                         };
 
-                        let block = if is_declaration {
+                        if is_declaration {
                             let mut value = AttrValue::String(String::new());
                             attr.swap_value(&mut value);
                             let (serialization, block) = match value {
@@ -2403,17 +2483,20 @@ impl VirtualMethods for Element {
                             };
                             let mut value = AttrValue::String(serialization);
                             attr.swap_value(&mut value);
-                            block
+                            Some(block)
+                        } else if doc.should_inline_be_blocked(InlineCheckType::StyleAttribute,
+                                                               None,
+                                                               &attr.value()) {
+                            // https://html.spec.whatwg.org/multipage/#the-style-attribute
+                            None
                         } else {
                             let win = window_from_node(self);
-                            Arc::new(doc.style_shared_lock().wrap(parse_style_attribute(
+                            Some(Arc::new(doc.style_shared_lock().wrap(parse_style_attribute(
                                 &attr.value(),
                                 &doc.base_url(),
                                 win.css_error_reporter(),
-                                doc.quirks_mode())))
-                        };
-
-                        Some(block)
+                                doc.quirks_mode()))))
+                        }
                     }
                     AttributeMutation::Removed => {
                         None
@@ -2430,7 +2513,7 @@ impl VirtualMethods for Element {
                             None
                         }
                     });
-                if node.is_in_doc() {
+                if node.is_in_doc() && !node.is_in_shadow_tree() {
                     let value = attr.value().as_atom().clone();
                     match mutation {
                         AttributeMutation::Set(old_value) => {
@@ -2450,6 +2533,13 @@ impl VirtualMethods for Element {
                     }
                 }
             },
+            &local_name!("slot") => {
+                // https://dom.spec.whatwg.org/#slotable-name
+                if let Some(slot) = node.assigned_slot() {
+                    slot.assign_slotables();
+                }
+                node.assign_a_slot();
+            },
             _ => {
                 // FIXME(emilio): This is pretty dubious, and should be done in
                 // the relevant super-classes.
@@ -2488,8 +2578,13 @@ impl VirtualMethods for Element {
         }
 
         let doc = document_from_node(self);
-        if let Some(ref value) = *self.id_attribute.borrow() {
-            doc.register_named_element(self, value.clone());
+        if !self.upcast::<Node>().is_in_shadow_tree() {
+            if let Some(ref value) = *self.id_attribute.borrow() {
+                doc.register_named_element(self, value.clone());
+            }
+        }
+        if let Some(shadow_root) = self.shadow_root() {
+            doc.register_shadow_root(&shadow_root);
         }
         // This is used for layout optimization.
         doc.increment_dom_count();
@@ -2511,8 +2606,13 @@ impl VirtualMethods for Element {
         if fullscreen.r() == Some(self) {
             doc.exit_fullscreen();
         }
-        if let Some(ref value) = *self.id_attribute.borrow() {
-            doc.unregister_named_element(self, value.clone());
+        if !self.upcast::<Node>().is_in_shadow_tree() {
+            if let Some(ref value) = *self.id_attribute.borrow() {
+                doc.unregister_named_element(self, value.clone());
+            }
+        }
+        if let Some(shadow_root) = self.shadow_root() {
+            doc.unregister_shadow_root(&shadow_root);
         }
         // This is used for layout optimization.
         doc.decrement_dom_count();
@@ -2683,7 +2783,9 @@ impl<'a> SelectorsElement for DomRoot<Element> {
             NonTSPseudoClass::Indeterminate |
             NonTSPseudoClass::ReadWrite |
             NonTSPseudoClass::PlaceholderShown |
-            NonTSPseudoClass::Target =>
+            NonTSPseudoClass::Target |
+            NonTSPseudoClass::Valid |
+            NonTSPseudoClass::Invalid =>
                 Element::state(self).contains(pseudo_class.state_flag()),
         }
     }
@@ -2977,9 +3079,12 @@ impl Element {
 
     /// <https://dom.spec.whatwg.org/#connected>
     pub fn is_connected(&self) -> bool {
-        let node = self.upcast::<Node>();
-        let root = node.GetRootNode();
-        root.is::<Document>()
+        self.upcast::<Node>().is_in_doc()
+    }
+
+    /// <https://dom.spec.whatwg.org/#concept-element-shadow-root>
+    pub fn shadow_root(&self) -> Option<DomRoot<ShadowRoot>> {
+        self.shadow_root.get()
     }
 }
 
@@ -7,6 +7,7 @@ use dom::bindings::callback::ExceptionHandling;
 use dom::bindings::cell::DomRefCell;
 use dom::bindings::codegen::Bindings::EventBinding;
 use dom::bindings::codegen::Bindings::EventBinding::{EventConstants, EventMethods};
+use dom::bindings::codegen::Bindings::ShadowRootBinding::{ShadowRootMethods, ShadowRootMode};
 use dom::bindings::error::Fallible;
 use dom::bindings::inheritance::Castable;
 use dom::bindings::refcounted::Trusted;
@@ -16,12 +17,18 @@ use dom::bindings::str::DOMString;
 use dom::document::Document;
 use dom::eventtarget::{CompiledEventListener, EventTarget, ListenerPhase};
 use dom::globalscope::GlobalScope;
+use dom::htmlslotelement::HTMLSlotElement;
+use dom::idbrequest::IDBRequest;
+use dom::idbtransaction::IDBTransaction;
 use dom::node::Node;
+use dom::shadowroot::ShadowRoot;
+use dom::uievent::UIEvent;
 use dom::virtualmethods::vtable_for;
 use dom::window::Window;
 use dom_struct::dom_struct;
 use servo_atoms::Atom;
 use std::cell::Cell;
+use std::cmp;
 use std::default::Default;
 use task::TaskOnce;
 use time;
@@ -31,6 +38,8 @@ pub struct Event {
     reflector_: Reflector,
     current_target: MutNullableDom<EventTarget>,
     target: MutNullableDom<EventTarget>,
+    /// <https://dom.spec.whatwg.org/#event-path>, starting at the target.
+    path: DomRefCell<Vec<Dom<EventTarget>>>,
     type_: DomRefCell<Atom>,
     phase: Cell<EventPhase>,
     canceled: Cell<EventDefault>,
@@ -38,6 +47,7 @@ pub struct Event {
     stop_immediate: Cell<bool>,
     cancelable: Cell<bool>,
     bubbles: Cell<bool>,
+    composed: Cell<bool>,
     trusted: Cell<bool>,
     dispatching: Cell<bool>,
     initialized: Cell<bool>,
@@ -50,6 +60,7 @@ impl Event {
             reflector_: Reflector::new(),
             current_target: Default::default(),
             target: Default::default(),
+            path: Default::default(),
             type_: DomRefCell::new(atom!("")),
             phase: Cell::new(EventPhase::None),
             canceled: Cell::new(EventDefault::Allowed),
@@ -57,6 +68,7 @@ impl Event {
             stop_immediate: Cell::new(false),
             cancelable: Cell::new(false),
             bubbles: Cell::new(false),
+            composed: Cell::new(false),
             trusted: Cell::new(false),
             dispatching: Cell::new(false),
             initialized: Cell::new(false),
@@ -84,7 +96,9 @@ impl Event {
                        init: &EventBinding::EventInit) -> Fallible<DomRoot<Event>> {
         let bubbles = EventBubbles::from(init.bubbles);
         let cancelable = EventCancelable::from(init.cancelable);
-        Ok(Event::new(global, Atom::from(type_), bubbles, cancelable))
+        let event = Event::new(global, Atom::from(type_), bubbles, cancelable);
+        event.composed.set(init.composed);
+        Ok(event)
     }
 
     pub fn init_event(&self, type_: Atom, bubbles: bool, cancelable: bool) {
@@ -133,23 +147,55 @@ impl Event {
         // Step 3. The "invoke" algorithm is only used on `target` separately,
         // so we don't put it in the path.
         rooted_vec!(let mut event_path);
+        // The target of the event as seen from each object in the path, that
+        // is, retargeted against it.
+        rooted_vec!(let mut event_path_targets);
+        let event_target = target_override.unwrap_or(target);
 
         // Step 4.
+        let mut clear_targets = false;
         if let Some(target_node) = target.downcast::<Node>() {
-            for ancestor in target_node.ancestors() {
-                event_path.push(Dom::from_ref(ancestor.upcast::<EventTarget>()));
+            clear_targets = target_node.is_in_shadow_tree();
+            let mut parent = self.get_the_parent(target_node, target_node);
+            while let Some(node) = parent {
+                let retargeted = retarget(target_node, Some(&node));
+                event_path.push(Dom::from_ref(node.upcast::<EventTarget>()));
+                event_path_targets.push(Dom::from_ref(retargeted.upcast::<EventTarget>()));
+                parent = self.get_the_parent(target_node, &node);
             }
             let top_most_ancestor_or_target =
                 DomRoot::from_ref(event_path.r().last().cloned().unwrap_or(target));
             if let Some(document) = DomRoot::downcast::<Document>(top_most_ancestor_or_target) {
                 if self.type_() != atom!("load") && document.browsing_context().is_some() {
+                    let retargeted = retarget(target_node, None);
                     event_path.push(Dom::from_ref(document.window().upcast()));
+                    event_path_targets.push(Dom::from_ref(retargeted.upcast::<EventTarget>()));
                 }
             }
+        } else if let Some(request) = target.downcast::<IDBRequest>() {
+            // https://w3c.github.io/IndexedDB/#ref-for-get-the-parent
+            if let Some(transaction) = request.transaction() {
+                event_path.push(Dom::from_ref(transaction.upcast::<EventTarget>()));
+                event_path.push(Dom::from_ref(transaction.db().upcast::<EventTarget>()));
+            }
+        } else if let Some(transaction) = target.downcast::<IDBTransaction>() {
+            event_path.push(Dom::from_ref(transaction.db().upcast::<EventTarget>()));
+        }
+        while event_path_targets.len() < event_path.len() {
+            event_path_targets.push(Dom::from_ref(event_target));
+        }
+
+        {
+            let mut path = self.path.borrow_mut();
+            path.push(Dom::from_ref(target));
+            path.extend(event_path.iter().cloned());
         }
 
         // Steps 5-9. In a separate function to short-circuit various things easily.
-        dispatch_to_listeners(self, target, event_path.r());
+        dispatch_to_listeners(self, target, event_target, event_path.r(), event_path_targets.r());
+
+        // Listeners on the path may have seen a retargeted target.
+        self.target.set(Some(event_target));
 
         // Default action.
         if let Some(target) = self.GetTarget() {
@@ -161,11 +207,42 @@ impl Event {
 
         // Step 10-12.
         self.clear_dispatching_flags();
+        self.path.borrow_mut().clear();
+
+        // Step 13. Don't expose nodes of a shadow tree through the target
+        // after dispatch.
+        if clear_targets {
+            self.target.set(None);
+        }
 
         // Step 14.
         self.status()
     }
 
+    /// <https://dom.spec.whatwg.org/#get-the-parent> for nodes, where `target`
+    /// is the node the event is being dispatched to.
+    fn get_the_parent(&self, target: &Node, node: &Node) -> Option<DomRoot<Node>> {
+        if let Some(slot) = node.assigned_slot() {
+            return Some(DomRoot::upcast(slot));
+        }
+        if let Some(shadow_root) = node.downcast::<ShadowRoot>() {
+            let is_target_root = target.containing_shadow_root().r() == Some(shadow_root);
+            if !self.composed() && is_target_root {
+                return None;
+            }
+            return Some(DomRoot::upcast(shadow_root.host()));
+        }
+        node.GetParentNode()
+    }
+
+    /// Whether this event propagates across shadow tree boundaries.
+    ///
+    /// Events dispatched by the user agent for user interaction are always
+    /// composed: <https://w3c.github.io/uievents/#events-composed>
+    pub fn composed(&self) -> bool {
+        self.composed.get() || (self.trusted.get() && self.is::<UIEvent>())
+    }
+
     pub fn status(&self) -> EventStatus {
         match self.DefaultPrevented() {
             true => EventStatus::Canceled,
@@ -242,6 +319,72 @@ impl EventMethods for Event {
         self.current_target.get()
     }
 
+    // https://dom.spec.whatwg.org/#dom-event-composedpath
+    fn ComposedPath(&self) -> Vec<DomRoot<EventTarget>> {
+        // Steps 1-3.
+        let path = self.path.borrow();
+        let current_target = match self.current_target.get() {
+            Some(current_target) if !path.is_empty() => current_target,
+            _ => return vec![],
+        };
+
+        // Steps 4-6.
+        let mut current_target_index = 0;
+        let mut current_target_hidden_subtree_level = 0i32;
+
+        // Steps 7-8.
+        for (index, item) in path.iter().enumerate().rev() {
+            if is_root_of_closed_tree(item) {
+                current_target_hidden_subtree_level += 1;
+            }
+            if **item == *current_target {
+                current_target_index = index;
+                break;
+            }
+            if is_slot_in_closed_tree(item) {
+                current_target_hidden_subtree_level -= 1;
+            }
+        }
+
+        // Steps 9-11.
+        let mut composed_path = vec![];
+        let mut current_hidden_level = current_target_hidden_subtree_level;
+        let mut max_hidden_level = current_target_hidden_subtree_level;
+        for item in path[..current_target_index].iter().rev() {
+            if is_root_of_closed_tree(item) {
+                current_hidden_level += 1;
+            }
+            if current_hidden_level <= max_hidden_level {
+                composed_path.push(DomRoot::from_ref(&**item));
+            }
+            if is_slot_in_closed_tree(item) {
+                current_hidden_level -= 1;
+                max_hidden_level = cmp::min(max_hidden_level, current_hidden_level);
+            }
+        }
+        composed_path.reverse();
+        composed_path.push(current_target);
+
+        // Steps 12-14.
+        current_hidden_level = current_target_hidden_subtree_level;
+        max_hidden_level = current_target_hidden_subtree_level;
+        for item in &path[current_target_index + 1..] {
+            if is_slot_in_closed_tree(item) {
+                current_hidden_level += 1;
+            }
+            if current_hidden_level <= max_hidden_level {
+                composed_path.push(DomRoot::from_ref(&**item));
+            }
+            if is_root_of_closed_tree(item) {
+                current_hidden_level -= 1;
+                max_hidden_level = cmp::min(max_hidden_level, current_hidden_level);
+            }
+        }
+
+        // Step 15.
+        composed_path
+    }
+
     // https://dom.spec.whatwg.org/#dom-event-defaultprevented
     fn DefaultPrevented(&self) -> bool {
         self.canceled.get() == EventDefault::Prevented
@@ -275,6 +418,11 @@ impl EventMethods for Event {
         self.cancelable.get()
     }
 
+    // https://dom.spec.whatwg.org/#dom-event-composed
+    fn Composed(&self) -> bool {
+        self.composed()
+    }
+
     // https://dom.spec.whatwg.org/#dom-event-timestamp
     fn TimeStamp(&self) -> u64 {
         self.timestamp
@@ -410,9 +558,43 @@ impl TaskOnce for SimpleEventTask {
     }
 }
 
+/// <https://dom.spec.whatwg.org/#retarget>, where `b` is `None` for targets
+/// that aren't nodes.
+fn retarget(a: &Node, b: Option<&Node>) -> DomRoot<Node> {
+    let mut a = DomRoot::from_ref(a);
+    loop {
+        let shadow_root = match a.containing_shadow_root() {
+            Some(shadow_root) => shadow_root,
+            None => return a,
+        };
+        if b.map_or(false, |b| shadow_root.upcast::<Node>().is_shadow_including_inclusive_ancestor_of(b)) {
+            return a;
+        }
+        a = DomRoot::upcast(shadow_root.host());
+    }
+}
+
+/// Whether `target` is a shadow root whose mode is "closed".
+fn is_root_of_closed_tree(target: &EventTarget) -> bool {
+    target.downcast::<ShadowRoot>().map_or(false, |shadow_root| shadow_root.Mode() == ShadowRootMode::Closed)
+}
+
+/// Whether `target` is a slot whose root is a shadow root whose mode is "closed".
+fn is_slot_in_closed_tree(target: &EventTarget) -> bool {
+    target.downcast::<HTMLSlotElement>().map_or(false, |slot| {
+        slot.upcast::<Node>().containing_shadow_root().map_or(false, |shadow_root| {
+            shadow_root.Mode() == ShadowRootMode::Closed
+        })
+    })
+}
+
 // See dispatch_event.
 // https://dom.spec.whatwg.org/#concept-event-dispatch
-fn dispatch_to_listeners(event: &Event, target: &EventTarget, event_path: &[&EventTarget]) {
+fn dispatch_to_listeners(event: &Event,
+                         target: &EventTarget,
+                         event_target: &EventTarget,
+                         event_path: &[&EventTarget],
+                         event_path_targets: &[&EventTarget]) {
     assert!(!event.stop_propagation.get());
     assert!(!event.stop_immediate.get());
 
@@ -431,7 +613,8 @@ fn dispatch_to_listeners(event: &Event, target: &EventTarget, event_path: &[&Eve
     event.phase.set(EventPhase::Capturing);
 
     // Step 6.
-    for object in event_path.iter().rev() {
+    for (object, object_target) in event_path.iter().zip(event_path_targets).rev() {
+        event.target.set(Some(object_target));
         invoke(window.r(), object, event, Some(ListenerPhase::Capturing));
         if event.stop_propagation.get() {
             return;
@@ -444,6 +627,7 @@ fn dispatch_to_listeners(event: &Event, target: &EventTarget, event_path: &[&Eve
     event.phase.set(EventPhase::AtTarget);
 
     // Step 8.
+    event.target.set(Some(event_target));
     invoke(window.r(), target, event, None);
     if event.stop_propagation.get() {
         return;
@@ -459,7 +643,8 @@ fn dispatch_to_listeners(event: &Event, target: &EventTarget, event_path: &[&Eve
     event.phase.set(EventPhase::Bubbling);
 
     // Step 9.2.
-    for object in event_path {
+    for (object, object_target) in event_path.iter().zip(event_path_targets) {
+        event.target.set(Some(object_target));
         invoke(window.r(), object, event, Some(ListenerPhase::Bubbling));
         if event.stop_propagation.get() {
             return;
@@ -528,6 +713,7 @@ impl Default for EventBinding::EventInit {
         EventBinding::EventInit {
             bubbles: false,
             cancelable: false,
+            composed: false,
         }
     }
 }
@@ -25,6 +25,7 @@ use js::jsval::UndefinedValue;
 use mime::{Mime, TopLevel, SubLevel};
 use net_traits::{CoreResourceMsg, FetchChannels, FetchMetadata};
 use net_traits::{FetchResponseMsg, FetchResponseListener, NetworkError};
+use net_traits::csp::Violation;
 use net_traits::request::{CacheMode, CorsSettings, CredentialsMode};
 use net_traits::request::{RequestInit, RequestMode};
 use network_listener::{NetworkListener, PreInvoke};
@@ -390,6 +391,10 @@ impl FetchResponseListener for EventSourceContext {
         }
         self.reestablish_the_connection();
     }
+
+    fn process_csp_violations(&mut self, violations: Vec<Violation>) {
+        self.event_source.root().global().report_csp_violations(violations);
+    }
 }
 
 impl PreInvoke for EventSourceContext {
@@ -456,6 +461,7 @@ impl EventSource {
             } else {
                 CredentialsMode::Include
             },
+            csp_list: global.get_csp_list(),
             ..RequestInit::default()
         };
         // Step 10
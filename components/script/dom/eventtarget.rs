@@ -35,6 +35,7 @@ use fnv::FnvHasher;
 use js::jsapi::{CompileFunction, JS_GetFunctionObject, JSAutoCompartment};
 use js::rust::{AutoObjectVectorWrapper, CompileOptionsWrapper};
 use libc::{c_char, size_t};
+use net_traits::csp::InlineCheckType;
 use servo_atoms::Atom;
 use servo_url::ServoUrl;
 use std::collections::HashMap;
@@ -386,6 +387,11 @@ impl EventTarget {
             return None;
         }
 
+        // https://w3c.github.io/webappsec-csp/#should-block-inline
+        if document.should_inline_be_blocked(InlineCheckType::ScriptAttribute, None, &handler.source) {
+            return None;
+        }
+
         // Step 1.3
         let body: Vec<u16> = handler.source.encode_utf16().collect();
 
@@ -23,7 +23,9 @@ use dom::window::Window;
 use dom::workerglobalscope::WorkerGlobalScope;
 use dom::workletglobalscope::WorkletGlobalScope;
 use dom_struct::dom_struct;
-use ipc_channel::ipc::IpcSender;
+use hyper::header::Headers;
+use hyper::method::Method;
+use ipc_channel::ipc::{self, IpcSender};
 use js::{JSCLASS_IS_DOMJSCLASS, JSCLASS_IS_GLOBAL};
 use js::glue::{IsWrapper, UnwrapObject};
 use js::jsapi::{CurrentGlobalOrNull, GetGlobalForObjectCrossCompartment};
@@ -35,7 +37,9 @@ use js::rust::{CompileOptionsWrapper, Runtime, get_object_class};
 use libc;
 use microtask::{Microtask, MicrotaskQueue};
 use msg::constellation_msg::PipelineId;
-use net_traits::{CoreResourceThread, ResourceThreads, IpcSend};
+use net_traits::{CoreResourceMsg, CoreResourceThread, FetchChannels, ResourceThreads, IpcSend};
+use net_traits::csp::{CheckResult, CspList, Violation};
+use net_traits::request::{CredentialsMode, Destination, RequestInit, RequestMode};
 use profile_traits::{mem, time};
 use script_runtime::{CommonScriptMsg, ScriptChan, ScriptPort};
 use script_thread::{MainThreadScriptChan, ScriptThread};
@@ -71,6 +75,12 @@ pub struct GlobalScope {
     /// Timers used by the Console API.
     console_timers: DomRefCell<HashMap<DOMString, u64>>,
 
+    /// Counters used by the Console API.
+    console_counters: DomRefCell<HashMap<DOMString, u32>>,
+
+    /// How many Console API groups are open.
+    console_group_depth: Cell<usize>,
+
     /// For providing instructions to an optional devtools server.
     #[ignore_malloc_size_of = "channels are hard"]
     devtools_chan: Option<IpcSender<ScriptToDevtoolsControlMsg>>,
@@ -132,6 +142,8 @@ impl GlobalScope {
             pipeline_id,
             devtools_wants_updates: Default::default(),
             console_timers: DomRefCell::new(Default::default()),
+            console_counters: DomRefCell::new(Default::default()),
+            console_group_depth: Default::default(),
             devtools_chan,
             mem_profiler_chan,
             time_profiler_chan,
@@ -224,12 +236,43 @@ impl GlobalScope {
         }
     }
 
+    pub fn time_log(&self, label: &str) -> Result<u64, ()> {
+        self.console_timers.borrow().get(label).ok_or(()).map(|start| {
+            timestamp_in_ms(get_time()) - start
+        })
+    }
+
     pub fn time_end(&self, label: &str) -> Result<u64, ()> {
         self.console_timers.borrow_mut().remove(label).ok_or(()).map(|start| {
             timestamp_in_ms(get_time()) - start
         })
     }
 
+    /// Increment the Console API counter for the given label, returning its new count.
+    pub fn count(&self, label: DOMString) -> u32 {
+        let mut counters = self.console_counters.borrow_mut();
+        let count = counters.entry(label).or_insert(0);
+        *count += 1;
+        *count
+    }
+
+    pub fn count_reset(&self, label: &str) -> Result<(), ()> {
+        self.console_counters.borrow_mut().get_mut(label).ok_or(()).map(|count| *count = 0)
+    }
+
+    /// The number of Console API groups that are open.
+    pub fn console_group_depth(&self) -> usize {
+        self.console_group_depth.get()
+    }
+
+    pub fn push_console_group(&self) {
+        self.console_group_depth.set(self.console_group_depth.get() + 1);
+    }
+
+    pub fn pop_console_group(&self) {
+        self.console_group_depth.set(self.console_group_depth.get().saturating_sub(1));
+    }
+
     /// Get an `&IpcSender<ScriptToDevtoolsControlMsg>` to send messages
     /// to the devtools thread when available.
     pub fn devtools_chan(&self) -> Option<&IpcSender<ScriptToDevtoolsControlMsg>> {
@@ -298,6 +341,74 @@ impl GlobalScope {
         unreachable!();
     }
 
+    /// The CSP list governing fetches made by this global.
+    ///
+    /// Worklets are not given a policy yet, so they are unrestricted.
+    pub fn get_csp_list(&self) -> CspList {
+        if let Some(window) = self.downcast::<Window>() {
+            return window.Document().get_csp_list().clone();
+        }
+        if let Some(worker) = self.downcast::<WorkerGlobalScope>() {
+            return worker.get_csp_list().clone();
+        }
+        CspList::new()
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#can-compile-strings>
+    pub fn is_js_evaluation_allowed(&self) -> bool {
+        if let Some(window) = self.downcast::<Window>() {
+            return window.Document().is_js_evaluation_allowed();
+        }
+        if let Some(worker) = self.downcast::<WorkerGlobalScope>() {
+            let (result, violations) = worker.get_csp_list().is_js_evaluation_allowed();
+            worker.report_csp_violations(violations);
+            return result == CheckResult::Allowed;
+        }
+        true
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#report-violation>
+    pub fn report_csp_violations(&self, violations: Vec<Violation>) {
+        if let Some(window) = self.downcast::<Window>() {
+            return window.Document().report_csp_violations(violations);
+        }
+        if let Some(worker) = self.downcast::<WorkerGlobalScope>() {
+            worker.report_csp_violations(violations);
+        }
+    }
+
+    /// Sends the report of `violation` to each of its report URIs, resolved
+    /// against `url`.
+    ///
+    /// <https://w3c.github.io/webappsec-csp/#report-violation> (step 4)
+    pub fn send_csp_report(&self, violation: &Violation, url: &ServoUrl, referrer: &str) {
+        let body = violation.report_body(url, referrer, 0);
+        for report_uri in &violation.report_uris {
+            let report_url = match url.join(report_uri) {
+                Ok(report_url) => report_url,
+                Err(_) => continue,
+            };
+            let mut headers = Headers::new();
+            headers.set_raw("content-type", vec![b"application/csp-report".to_vec()]);
+            let request = RequestInit {
+                method: Method::Post,
+                url: report_url,
+                headers: headers,
+                body: Some(body.clone()),
+                destination: Destination::Report,
+                mode: RequestMode::NoCors,
+                credentials_mode: CredentialsMode::CredentialsSameOrigin,
+                origin: self.origin().immutable().clone(),
+                pipeline_id: Some(self.pipeline_id()),
+                .. RequestInit::default()
+            };
+            // Nobody is interested in the response to a report.
+            let (action_sender, _) = ipc::channel().unwrap();
+            let _ = self.resource_threads().sender().send(
+                CoreResourceMsg::Fetch(request, FetchChannels::ResponseMsg(action_sender)));
+        }
+    }
+
     /// Extract a `Window`, panic if the global object is not a `Window`.
     pub fn as_window(&self) -> &Window {
         self.downcast::<Window>().expect("expected a Window scope")
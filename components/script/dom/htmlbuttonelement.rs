@@ -21,7 +21,7 @@ use dom::htmlformelement::HTMLFormElement;
 use dom::node::{Node, UnbindContext, document_from_node, window_from_node};
 use dom::nodelist::NodeList;
 use dom::validation::Validatable;
-use dom::validitystate::{ValidityState, ValidationFlags};
+use dom::validitystate::ValidityState;
 use dom::virtualmethods::VirtualMethods;
 use dom_struct::dom_struct;
 use html5ever::{LocalName, Prefix};
@@ -43,6 +43,7 @@ pub struct HTMLButtonElement {
     htmlelement: HTMLElement,
     button_type: Cell<ButtonType>,
     form_owner: MutNullableDom<HTMLFormElement>,
+    validity_state: MutNullableDom<ValidityState>,
 }
 
 impl HTMLButtonElement {
@@ -51,10 +52,12 @@ impl HTMLButtonElement {
                      document: &Document) -> HTMLButtonElement {
         HTMLButtonElement {
             htmlelement:
-                HTMLElement::new_inherited_with_state(ElementState::IN_ENABLED_STATE,
+                HTMLElement::new_inherited_with_state(ElementState::IN_ENABLED_STATE |
+                                                      ElementState::IN_VALID_STATE,
                                                       local_name, prefix, document),
             button_type: Cell::new(ButtonType::Submit),
             form_owner: Default::default(),
+            validity_state: Default::default(),
         }
     }
 
@@ -69,12 +72,6 @@ impl HTMLButtonElement {
 }
 
 impl HTMLButtonElementMethods for HTMLButtonElement {
-    // https://html.spec.whatwg.org/multipage/#dom-cva-validity
-    fn Validity(&self) -> DomRoot<ValidityState> {
-        let window = window_from_node(self);
-        ValidityState::new(&window, self.upcast())
-    }
-
     // https://html.spec.whatwg.org/multipage/#dom-fe-disabled
     make_bool_getter!(Disabled, "disabled");
 
@@ -137,6 +134,36 @@ impl HTMLButtonElementMethods for HTMLButtonElement {
     // https://html.spec.whatwg.org/multipage/#dom-button-value
     make_setter!(SetValue, "value");
 
+    // https://html.spec.whatwg.org/multipage/#dom-cva-willvalidate
+    fn WillValidate(&self) -> bool {
+        self.is_instance_validatable()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-validity
+    fn Validity(&self) -> DomRoot<ValidityState> {
+        self.validity_state()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-validationmessage
+    fn ValidationMessage(&self) -> DOMString {
+        self.validation_message()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-checkvalidity
+    fn CheckValidity(&self) -> bool {
+        self.check_validity()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-reportvalidity
+    fn ReportValidity(&self) -> bool {
+        self.report_validity()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-setcustomvalidity
+    fn SetCustomValidity(&self, error: DOMString) {
+        self.validity_state().set_custom_error_message(error);
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-lfe-labels
     fn Labels(&self) -> DomRoot<NodeList> {
         self.upcast::<HTMLElement>().labels()
@@ -220,6 +247,8 @@ impl VirtualMethods for HTMLButtonElement {
             }
             _ => {},
         }
+
+        self.update_validity_state();
     }
 
     fn bind_to_tree(&self, tree_in_doc: bool) {
@@ -228,6 +257,7 @@ impl VirtualMethods for HTMLButtonElement {
         }
 
         self.upcast::<Element>().check_ancestors_disabled_state_for_form_control();
+        self.update_validity_state();
     }
 
     fn unbind_from_tree(&self, context: &UnbindContext) {
@@ -240,6 +270,7 @@ impl VirtualMethods for HTMLButtonElement {
         } else {
             el.check_disabled_attribute();
         }
+        self.update_validity_state();
     }
 }
 
@@ -258,13 +289,18 @@ impl FormControl for HTMLButtonElement {
 }
 
 impl Validatable for HTMLButtonElement {
-    fn is_instance_validatable(&self) -> bool {
-        true
+    fn as_element(&self) -> &Element {
+        self.upcast()
+    }
+
+    fn validity_state(&self) -> DomRoot<ValidityState> {
+        self.validity_state.or_init(|| ValidityState::new(&window_from_node(self), self.upcast()))
     }
-    fn validate(&self, validate_flags: ValidationFlags) -> bool {
-        if validate_flags.is_empty() {}
-        // Need more flag check for different validation types later
-        true
+
+    // https://html.spec.whatwg.org/multipage/#candidate-for-constraint-validation
+    fn is_instance_validatable(&self) -> bool {
+        // https://html.spec.whatwg.org/multipage/#the-button-element:barred-from-constraint-validation
+        self.button_type.get() == ButtonType::Submit && !self.upcast::<Element>().disabled_state()
     }
 }
 
@@ -39,7 +39,7 @@ use dom::htmlselectelement::HTMLSelectElement;
 use dom::htmltextareaelement::HTMLTextAreaElement;
 use dom::node::{Node, NodeFlags, UnbindContext, VecPreOrderInsertionHelper};
 use dom::node::{document_from_node, window_from_node};
-use dom::validitystate::ValidationFlags;
+use dom::validation::Validatable;
 use dom::virtualmethods::VirtualMethods;
 use dom_struct::dom_struct;
 use encoding_rs::{Encoding, UTF_8};
@@ -154,6 +154,16 @@ impl HTMLFormElementMethods for HTMLFormElement {
     // https://html.spec.whatwg.org/multipage/#dom-fs-target
     make_setter!(SetTarget, "target");
 
+    // https://html.spec.whatwg.org/multipage/#dom-form-checkvalidity
+    fn CheckValidity(&self) -> bool {
+        self.static_validation().is_ok()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-form-reportvalidity
+    fn ReportValidity(&self) -> bool {
+        self.interactive_validation().is_ok()
+    }
+
     // https://html.spec.whatwg.org/multipage/#the-form-element:concept-form-submit
     fn Submit(&self) {
         self.submit(SubmittedFrom::FromForm, FormSubmitter::FormElement(self));
@@ -302,8 +312,6 @@ impl HTMLFormElement {
            !submitter.no_validate(self)
         {
             if self.interactive_validation().is_err() {
-                // TODO: Implement event handlers on all form control elements
-                self.upcast::<EventTarget>().fire_event(atom!("invalid"));
                 return;
             }
         }
@@ -459,42 +467,31 @@ impl HTMLFormElement {
     /// <https://html.spec.whatwg.org/multipage/#interactively-validate-the-constraints>
     fn interactive_validation(&self) -> Result<(), ()> {
         // Step 1-3
-        let _unhandled_invalid_controls = match self.static_validation() {
+        let unhandled_invalid_controls = match self.static_validation() {
             Ok(()) => return Ok(()),
             Err(err) => err
         };
-        // TODO: Report the problems with the constraints of at least one of
-        //       the elements given in unhandled invalid controls to the user
         // Step 4
+        if let Some(first_control) = unhandled_invalid_controls.first() {
+            first_control.as_validatable().report_problems();
+        }
+        // Step 5
         Err(())
     }
 
     /// Statitically validate the constraints of form elements
     /// <https://html.spec.whatwg.org/multipage/#statically-validate-the-constraints>
     fn static_validation(&self) -> Result<(), Vec<FormSubmittableElement>> {
-        let node = self.upcast::<Node>();
-        // FIXME(#3553): This is an incorrect way of getting controls owned by the
-        //               form, refactor this when html5ever's form owner PR lands
         // Step 1-3
-        let invalid_controls = node.traverse_preorder().filter_map(|field| {
-            if let Some(el) = field.downcast::<Element>() {
-                if el.disabled_state() {
-                    None
-                } else {
-                    let validatable = match el.as_maybe_validatable() {
-                        Some(v) => v,
-                        None => return None
-                    };
-                    if !validatable.is_instance_validatable() {
-                        None
-                    } else if validatable.validate(ValidationFlags::empty()) {
-                        None
-                    } else {
-                        Some(FormSubmittableElement::from_element(&el))
-                    }
-                }
-            } else {
+        let invalid_controls = self.controls.borrow().iter().filter_map(|field| {
+            let validatable = match field.as_maybe_validatable() {
+                Some(v) => v,
+                None => return None
+            };
+            if !validatable.is_instance_validatable() || validatable.is_valid() {
                 None
+            } else {
+                Some(FormSubmittableElement::from_element(field))
             }
         }).collect::<Vec<FormSubmittableElement>>();
         // Step 4
@@ -742,6 +739,16 @@ impl FormSubmittableElement {
         }
     }
 
+    fn as_validatable(&self) -> &Validatable {
+        match *self {
+            FormSubmittableElement::ButtonElement(ref button) => &**button as &Validatable,
+            FormSubmittableElement::InputElement(ref input) => &**input as &Validatable,
+            FormSubmittableElement::ObjectElement(ref object) => &**object as &Validatable,
+            FormSubmittableElement::SelectElement(ref select) => &**select as &Validatable,
+            FormSubmittableElement::TextAreaElement(ref textarea) => &**textarea as &Validatable
+        }
+    }
+
     fn from_element(element: &Element) -> FormSubmittableElement {
         if let Some(input) = element.downcast::<HTMLInputElement>() {
             FormSubmittableElement::InputElement(DomRoot::from_ref(&input))
@@ -41,6 +41,7 @@ use ipc_channel::ipc;
 use js::jsapi::{JSAutoCompartment, JSContext, MutableHandleValue};
 use js::jsval::{NullValue, UndefinedValue};
 use msg::constellation_msg::{FrameType, BrowsingContextId, PipelineId, TopLevelBrowsingContextId, TraversalDirection};
+use net_traits::csp::InlineCheckType;
 use net_traits::response::HttpsState;
 use script_layout_interface::message::ReflowGoal;
 use script_thread::ScriptThread;
@@ -142,6 +143,11 @@ impl HTMLIFrameElement {
         if let Some(ref mut load_data) = load_data {
             let is_javascript = load_data.url.scheme() == "javascript";
             if is_javascript {
+                // https://w3c.github.io/webappsec-csp/#should-block-navigation-request
+                let url = load_data.url.clone();
+                if document.should_inline_be_blocked(InlineCheckType::Navigation, None, url.as_str()) {
+                    return;
+                }
                 let window_proxy = self.GetContentWindow();
                 if let Some(window_proxy) = window_proxy {
                     ScriptThread::eval_js_url(&window_proxy.global(), load_data);
@@ -42,11 +42,12 @@ use ipc_channel::ipc;
 use ipc_channel::router::ROUTER;
 use microtask::{Microtask, MicrotaskRunnable};
 use net_traits::{FetchResponseListener, FetchMetadata, NetworkError, FetchResponseMsg};
+use net_traits::csp::Violation;
 use net_traits::image::base::{Image, ImageMetadata};
 use net_traits::image_cache::{CanRequestImages, ImageCache, ImageOrMetadataAvailable};
 use net_traits::image_cache::{ImageResponder, ImageResponse, ImageState, PendingImageId};
 use net_traits::image_cache::UsePlaceholder;
-use net_traits::request::RequestInit;
+use net_traits::request::{Destination, RequestInit};
 use network_listener::{NetworkListener, PreInvoke};
 use num_traits::ToPrimitive;
 use script_thread::ScriptThread;
@@ -141,6 +142,8 @@ struct ImageContext {
     status: Result<(), NetworkError>,
     /// The cache ID for this request.
     id: PendingImageId,
+    /// The document that requested the image.
+    doc: Trusted<Document>,
 }
 
 impl FetchResponseListener for ImageContext {
@@ -183,6 +186,10 @@ impl FetchResponseListener for ImageContext {
             self.id,
             FetchResponseMsg::ProcessResponseEOF(response));
     }
+
+    fn process_csp_violations(&mut self, violations: Vec<Violation>) {
+        self.doc.root().report_csp_violations(violations);
+    }
 }
 
 impl PreInvoke for ImageContext {}
@@ -260,6 +267,7 @@ impl HTMLImageElement {
             image_cache: window.image_cache(),
             status: Ok(()),
             id: id,
+            doc: Trusted::new(&document),
         }));
 
         let (action_sender, action_receiver) = ipc::channel().unwrap();
@@ -274,8 +282,10 @@ impl HTMLImageElement {
 
         let request = RequestInit {
             url: img_url.clone(),
+            destination: Destination::Image,
             origin: document.origin().immutable().clone(),
             pipeline_id: Some(document.global().pipeline_id()),
+            csp_list: document.get_csp_list().clone(),
             .. RequestInit::default()
         };
 
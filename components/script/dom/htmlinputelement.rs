@@ -3,6 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use caseless::compatibility_caseless_match_str;
+use cssparser::RGBA;
 use dom::activation::{Activatable, ActivationSource, synthetic_click_activation};
 use dom::attr::Attr;
 use dom::bindings::cell::DomRefCell;
@@ -13,6 +14,7 @@ use dom::bindings::codegen::Bindings::HTMLInputElementBinding::HTMLInputElementM
 use dom::bindings::codegen::Bindings::KeyboardEventBinding::KeyboardEventMethods;
 use dom::bindings::error::{Error, ErrorResult};
 use dom::bindings::inheritance::Castable;
+use dom::bindings::nonnull::NonNullJSObjectPtr;
 use dom::bindings::root::{Dom, DomRoot, LayoutDom, MutNullableDom, RootedReference};
 use dom::bindings::str::DOMString;
 use dom::document::Document;
@@ -32,25 +34,30 @@ use dom::node::{Node, NodeDamage, UnbindContext};
 use dom::node::{document_from_node, window_from_node};
 use dom::nodelist::NodeList;
 use dom::validation::Validatable;
-use dom::validitystate::ValidationFlags;
+use dom::validitystate::{ValidationFlags, ValidityState};
 use dom::virtualmethods::VirtualMethods;
 use dom_struct::dom_struct;
 use html5ever::{LocalName, Prefix};
 use ipc_channel::ipc::channel;
+use js::jsapi::{ClippedTime, DateGetMsecSinceEpoch, JSContext, JSObject, NewDateObject, ObjectIsDate};
 use mime_guess;
 use net_traits::{CoreResourceMsg, IpcSend};
 use net_traits::blob_url_store::get_blob_origin;
 use net_traits::filemanager_thread::{FileManagerThreadMsg, FilterPattern};
+use regex::Regex;
+use script_layout_interface::HTMLInputWidget;
 use script_layout_interface::rpc::TextIndexResponse;
 use script_traits::ScriptToConstellationChan;
 use servo_atoms::Atom;
+use servo_url::ServoUrl;
 use std::borrow::ToOwned;
 use std::cell::Cell;
+use std::f64;
 use std::mem;
 use std::ops::Range;
-use style::attr::AttrValue;
+use style::attr::{AttrValue, parse_double};
 use style::element_state::ElementState;
-use style::str::split_commas;
+use style::str::{HTML_SPACE_CHARACTERS, split_commas};
 use textinput::{Direction, Selection, SelectionDirection, TextInput};
 use textinput::KeyReaction::{DispatchInput, Nothing, RedrawSelection, TriggerDefaultAction};
 use textinput::Lines::Single;
@@ -58,6 +65,14 @@ use textinput::Lines::Single;
 const DEFAULT_SUBMIT_VALUE: &'static str = "Submit";
 const DEFAULT_RESET_VALUE: &'static str = "Reset";
 const PASSWORD_REPLACEMENT_CHAR: char = '●';
+// https://html.spec.whatwg.org/multipage/#valid-e-mail-address
+const VALID_EMAIL_ADDRESS_REGEX: &'static str = concat!(
+    r"^[a-zA-Z0-9.!#$%&'*+/=?^_`{|}~-]+@[a-zA-Z0-9](?:[a-zA-Z0-9-]{0,61}[a-zA-Z0-9])?",
+    r"(?:\.[a-zA-Z0-9](?:[a-zA-Z0-9-]{0,61}[a-zA-Z0-9])?)*$");
+// Tolerance for floating point error when checking for a step mismatch.
+const STEP_MISMATCH_EPSILON: f64 = 1e-9;
+const MS_PER_DAY: f64 = 86400000.;
+const DEFAULT_COLOR_VALUE: &'static str = "#000000";
 
 #[derive(Clone, Copy, JSTraceable, PartialEq)]
 #[allow(dead_code)]
@@ -67,11 +82,58 @@ enum InputType {
     InputReset,
     InputButton,
     InputText,
+    InputHidden,
+    InputSearch,
+    InputTel,
+    InputUrl,
+    InputEmail,
+    InputPassword,
+    InputDate,
+    InputMonth,
+    InputWeek,
+    InputTime,
+    InputDatetimeLocal,
+    InputNumber,
+    InputRange,
+    InputColor,
     InputFile,
     InputImage,
     InputCheckbox,
     InputRadio,
-    InputPassword
+}
+
+impl InputType {
+    // Whether the value is edited as text. These are also the types the readonly
+    // attribute applies to.
+    fn is_textual(&self) -> bool {
+        match *self {
+            InputType::InputText |
+            InputType::InputSearch |
+            InputType::InputTel |
+            InputType::InputUrl |
+            InputType::InputEmail |
+            InputType::InputPassword |
+            InputType::InputDate |
+            InputType::InputMonth |
+            InputType::InputWeek |
+            InputType::InputTime |
+            InputType::InputDatetimeLocal |
+            InputType::InputNumber => true,
+            _ => false,
+        }
+    }
+
+    // Whether the value is a date, a time, or both.
+    fn is_date_or_time(&self) -> bool {
+        match *self {
+            InputType::InputDate |
+            InputType::InputMonth |
+            InputType::InputWeek |
+            InputType::InputTime |
+            InputType::InputDatetimeLocal => true,
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -82,6 +144,12 @@ enum ValueMode {
     Filename,
 }
 
+#[derive(Clone, Copy)]
+enum StepDirection {
+    Up,
+    Down,
+}
+
 #[dom_struct]
 pub struct HTMLInputElement {
     htmlelement: HTMLElement,
@@ -97,9 +165,16 @@ pub struct HTMLInputElement {
     activation_state: DomRefCell<InputActivationState>,
     // https://html.spec.whatwg.org/multipage/#concept-input-value-dirty-flag
     value_dirty: Cell<bool>,
+    // Whether the value was last changed by a user edit, as opposed to a script.
+    value_changed_by_user: Cell<bool>,
+    // The compiled pattern regular expression, once compiled since the pattern
+    // attribute last changed. The inner value is None if the pattern is invalid.
+    #[ignore_malloc_size_of = "Regex"]
+    compiled_pattern: DomRefCell<Option<Option<Regex>>>,
 
     filelist: MutNullableDom<FileList>,
     form_owner: MutNullableDom<HTMLFormElement>,
+    validity_state: MutNullableDom<ValidityState>,
 }
 
 #[derive(JSTraceable)]
@@ -139,7 +214,8 @@ impl HTMLInputElement {
         HTMLInputElement {
             htmlelement:
                 HTMLElement::new_inherited_with_state(ElementState::IN_ENABLED_STATE |
-                                                      ElementState::IN_READ_WRITE_STATE,
+                                                      ElementState::IN_READ_WRITE_STATE |
+                                                      ElementState::IN_VALID_STATE,
                                                       local_name, prefix, document),
             input_type: Cell::new(InputType::InputText),
             placeholder: DomRefCell::new(DOMString::new()),
@@ -156,8 +232,11 @@ impl HTMLInputElement {
                                                       SelectionDirection::None)),
             activation_state: DomRefCell::new(InputActivationState::new()),
             value_dirty: Cell::new(false),
+            value_changed_by_user: Cell::new(false),
+            compiled_pattern: DomRefCell::new(None),
             filelist: MutNullableDom::new(None),
             form_owner: Default::default(),
+            validity_state: Default::default(),
         }
     }
 
@@ -182,12 +261,12 @@ impl HTMLInputElement {
             InputType::InputSubmit |
             InputType::InputReset |
             InputType::InputButton |
-            InputType::InputImage => ValueMode::Default,
+            InputType::InputImage |
+            InputType::InputHidden => ValueMode::Default,
             InputType::InputCheckbox |
             InputType::InputRadio => ValueMode::DefaultOn,
-            InputType::InputPassword |
-            InputType::InputText => ValueMode::Value,
             InputType::InputFile => ValueMode::Filename,
+            _ => ValueMode::Value,
         }
     }
 }
@@ -203,6 +282,8 @@ pub trait LayoutHTMLInputElementHelpers {
     unsafe fn checked_state_for_layout(self) -> bool;
     #[allow(unsafe_code)]
     unsafe fn indeterminate_state_for_layout(self) -> bool;
+    #[allow(unsafe_code)]
+    unsafe fn widget_for_layout(self) -> Option<HTMLInputWidget>;
 }
 
 #[allow(unsafe_code)]
@@ -225,6 +306,7 @@ impl LayoutHTMLInputElementHelpers for LayoutDom<HTMLInputElement> {
         match (*self.unsafe_get()).input_type.get() {
             InputType::InputCheckbox | InputType::InputRadio => String::new(),
             InputType::InputFile | InputType::InputImage => String::new(),
+            InputType::InputHidden | InputType::InputRange | InputType::InputColor => String::new(),
             InputType::InputButton => get_raw_attr_value(self, ""),
             InputType::InputSubmit => get_raw_attr_value(self, DEFAULT_SUBMIT_VALUE),
             InputType::InputReset => get_raw_attr_value(self, DEFAULT_RESET_VALUE),
@@ -274,7 +356,7 @@ impl LayoutHTMLInputElementHelpers for LayoutDom<HTMLInputElement> {
                 let bytes_per_char = PASSWORD_REPLACEMENT_CHAR.len_utf8();
                 Some(char_start * bytes_per_char .. char_end * bytes_per_char)
             }
-            InputType::InputText => Some(textinput.get_absolute_selection_range()),
+            input_type if input_type.is_textual() => Some(textinput.get_absolute_selection_range()),
             _ => None
         }
     }
@@ -290,6 +372,40 @@ impl LayoutHTMLInputElementHelpers for LayoutDom<HTMLInputElement> {
     unsafe fn indeterminate_state_for_layout(self) -> bool {
         self.upcast::<Element>().get_state_for_layout().contains(ElementState::IN_INDETERMINATE_STATE)
     }
+
+    #[allow(unrooted_must_root)]
+    #[allow(unsafe_code)]
+    unsafe fn widget_for_layout(self) -> Option<HTMLInputWidget> {
+        #[allow(unsafe_code)]
+        unsafe fn get_numeric_attr_value(input: LayoutDom<HTMLInputElement>, name: &LocalName, default: f64) -> f64 {
+            let elem = input.upcast::<Element>();
+            (*elem.unsafe_get())
+                .get_attr_val_for_layout(&ns!(), name)
+                .and_then(|value| parse_double(value).ok())
+                .unwrap_or(default)
+        }
+
+        // The value has already been sanitized, so it only needs to be parsed here.
+        let value = get_raw_textinput_value(self);
+        match (*self.unsafe_get()).input_type.get() {
+            InputType::InputRange => {
+                let min = get_numeric_attr_value(self, &local_name!("min"), 0.);
+                let max = get_numeric_attr_value(self, &local_name!("max"), 100.);
+                let value = parse_double(&value).unwrap_or(min);
+                let fraction = if max > min { ((value - min) / (max - min)).max(0.).min(1.) } else { 0. };
+                Some(HTMLInputWidget::Range(fraction as f32))
+            },
+            InputType::InputColor => {
+                let color = if value.is_valid_simple_color_string() {
+                    u32::from_str_radix(&value[1..], 16).unwrap_or(0)
+                } else {
+                    0
+                };
+                Some(HTMLInputWidget::Color(RGBA::new((color >> 16) as u8, (color >> 8) as u8, color as u8, 255)))
+            },
+            _ => None,
+        }
+    }
 }
 
 impl HTMLInputElementMethods for HTMLInputElement {
@@ -414,6 +530,7 @@ impl HTMLInputElementMethods for HTMLInputElement {
                 let old_value = mem::replace(self.textinput.borrow_mut().single_line_content_mut(), value);
                 // Step 3.
                 self.value_dirty.set(true);
+                self.value_changed_by_user.set(false);
                 // Step 4.
                 self.sanitize_value();
                 // Step 5.
@@ -438,10 +555,82 @@ impl HTMLInputElementMethods for HTMLInputElement {
         }
 
         self.value_changed.set(true);
+        self.update_validity_state();
         self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
         Ok(())
     }
 
+    #[allow(unsafe_code)]
+    // https://html.spec.whatwg.org/multipage/#dom-input-valueasdate
+    unsafe fn GetValueAsDate(&self, cx: *mut JSContext) -> Option<NonNullJSObjectPtr> {
+        let time = match self.convert_string_to_date(&self.Value()) {
+            Some(time) => time,
+            None => return None,
+        };
+        rooted!(in(cx) let date = NewDateObject(cx, ClippedTime { t: time }));
+        Some(NonNullJSObjectPtr::new_unchecked(date.get()))
+    }
+
+    #[allow(unsafe_code)]
+    // https://html.spec.whatwg.org/multipage/#dom-input-valueasdate
+    unsafe fn SetValueAsDate(&self, cx: *mut JSContext, value: *mut JSObject) -> ErrorResult {
+        // Step 1.
+        if !self.does_value_as_date_apply() {
+            return Err(Error::InvalidState);
+        }
+
+        // Steps 2-3.
+        if value.is_null() {
+            return self.SetValue(DOMString::new());
+        }
+        rooted!(in(cx) let object = value);
+        let mut is_date = false;
+        if !ObjectIsDate(cx, object.handle(), &mut is_date) {
+            return Err(Error::JSFailed);
+        }
+        if !is_date {
+            return Err(Error::Type("Value is not a Date.".to_owned()));
+        }
+        let mut time = 0.;
+        if !DateGetMsecSinceEpoch(cx, object.handle(), &mut time) {
+            return Err(Error::JSFailed);
+        }
+
+        // Step 4.
+        let value = if time.is_nan() {
+            DOMString::new()
+        } else {
+            self.convert_date_to_string(time).unwrap_or_default()
+        };
+        self.SetValue(value)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-input-valueasnumber
+    fn ValueAsNumber(&self) -> f64 {
+        self.convert_string_to_number(&self.Value()).unwrap_or(f64::NAN)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-input-valueasnumber
+    fn SetValueAsNumber(&self, value: f64) -> ErrorResult {
+        // Step 1.
+        if value.is_infinite() {
+            return Err(Error::Type("Value is infinite.".to_owned()));
+        }
+
+        // Step 2.
+        if !self.does_value_as_number_apply() {
+            return Err(Error::InvalidState);
+        }
+
+        // Steps 3-4.
+        let value = if value.is_nan() {
+            DOMString::new()
+        } else {
+            self.convert_number_to_string(value).unwrap_or_default()
+        };
+        self.SetValue(value)
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-input-defaultvalue
     make_getter!(DefaultValue, "value");
 
@@ -547,6 +736,16 @@ impl HTMLInputElementMethods for HTMLInputElement {
     // https://html.spec.whatwg.org/multipage/#dom-input-step
     make_setter!(SetStep, "step");
 
+    // https://html.spec.whatwg.org/multipage/#dom-input-stepup
+    fn StepUp(&self, n: i32) -> ErrorResult {
+        self.step_up_or_down(n, StepDirection::Up)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-input-stepdown
+    fn StepDown(&self, n: i32) -> ErrorResult {
+        self.step_up_or_down(n, StepDirection::Down)
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-input-indeterminate
     fn Indeterminate(&self) -> bool {
         self.upcast::<Element>().state().contains(ElementState::IN_INDETERMINATE_STATE)
@@ -557,9 +756,39 @@ impl HTMLInputElementMethods for HTMLInputElement {
         self.upcast::<Element>().set_state(ElementState::IN_INDETERMINATE_STATE, val)
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-cva-willvalidate
+    fn WillValidate(&self) -> bool {
+        self.is_instance_validatable()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-validity
+    fn Validity(&self) -> DomRoot<ValidityState> {
+        self.validity_state()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-validationmessage
+    fn ValidationMessage(&self) -> DOMString {
+        self.validation_message()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-checkvalidity
+    fn CheckValidity(&self) -> bool {
+        self.check_validity()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-reportvalidity
+    fn ReportValidity(&self) -> bool {
+        self.report_validity()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-setcustomvalidity
+    fn SetCustomValidity(&self, error: DOMString) {
+        self.validity_state().set_custom_error_message(error);
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-lfe-labels
     fn Labels(&self) -> DomRoot<NodeList> {
-        if self.type_() == atom!("hidden") {
+        if self.input_type.get() == InputType::InputHidden {
             let window = window_from_node(self);
             NodeList::empty(&window)
         } else {
@@ -756,6 +985,12 @@ impl HTMLInputElement {
                                     self.radio_group_name().as_ref());
         }
 
+        if self.input_type.get() == InputType::InputRadio {
+            self.update_radio_group_validity_state();
+        } else {
+            self.update_validity_state();
+        }
+
         self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
         //TODO: dispatch change event
     }
@@ -782,12 +1017,20 @@ impl HTMLInputElement {
             .expect("Failed to reset input value to default.");
         self.value_dirty.set(false);
         self.value_changed.set(false);
+        self.update_validity_state();
         self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
     }
 
     fn update_placeholder_shown_state(&self) {
+        // https://html.spec.whatwg.org/multipage/#attr-input-placeholder
         match self.input_type.get() {
-            InputType::InputText | InputType::InputPassword => {},
+            InputType::InputText |
+            InputType::InputSearch |
+            InputType::InputUrl |
+            InputType::InputTel |
+            InputType::InputEmail |
+            InputType::InputPassword |
+            InputType::InputNumber => {},
             _ => return,
         }
         let has_placeholder = !self.placeholder.borrow().is_empty();
@@ -853,6 +1096,7 @@ impl HTMLInputElement {
         } else {
             let filelist = FileList::new(&window, files);
             self.filelist.set(Some(&filelist));
+            self.update_validity_state();
 
             target.fire_bubbling_event(atom!("input"));
             target.fire_bubbling_event(atom!("change"));
@@ -861,19 +1105,106 @@ impl HTMLInputElement {
 
     // https://html.spec.whatwg.org/multipage/#value-sanitization-algorithm
     fn sanitize_value(&self) {
-        match self.type_() {
-            atom!("text") | atom!("search") | atom!("tel") | atom!("password") => {
-                self.textinput.borrow_mut().single_line_content_mut().strip_newlines();
+        let mut value = self.textinput.borrow().single_line_content().clone();
+        match self.input_type.get() {
+            InputType::InputText | InputType::InputSearch | InputType::InputTel | InputType::InputPassword => {
+                value.strip_newlines();
             }
-            atom!("url") => {
-                let mut textinput = self.textinput.borrow_mut();
-                let content = textinput.single_line_content_mut();
-                content.strip_newlines();
-                content.strip_leading_and_trailing_ascii_whitespace();
+            InputType::InputUrl => {
+                value.strip_newlines();
+                value.strip_leading_and_trailing_ascii_whitespace();
             }
-            // TODO: Implement more value sanitization algorithms for different types of inputs
+            // https://html.spec.whatwg.org/multipage/#e-mail-state-(type=email):value-sanitization-algorithm
+            // https://html.spec.whatwg.org/multipage/#e-mail-state-(type=email):value-sanitization-algorithm-2
+            InputType::InputEmail => {
+                if self.Multiple() {
+                    let addresses = value.split(',')
+                                         .map(|address| address.trim_matches(HTML_SPACE_CHARACTERS))
+                                         .collect::<Vec<_>>()
+                                         .join(",");
+                    value = DOMString::from(addresses);
+                } else {
+                    value.strip_newlines();
+                    value.strip_leading_and_trailing_ascii_whitespace();
+                }
+            }
+            // https://html.spec.whatwg.org/multipage/#date-state-(type=date):value-sanitization-algorithm
+            InputType::InputDate => if value.parse_date_string().is_err() {
+                value.clear();
+            },
+            // https://html.spec.whatwg.org/multipage/#month-state-(type=month):value-sanitization-algorithm
+            InputType::InputMonth => if value.parse_month_string().is_err() {
+                value.clear();
+            },
+            // https://html.spec.whatwg.org/multipage/#week-state-(type=week):value-sanitization-algorithm
+            InputType::InputWeek => if value.parse_week_string().is_err() {
+                value.clear();
+            },
+            // https://html.spec.whatwg.org/multipage/#time-state-(type=time):value-sanitization-algorithm
+            InputType::InputTime => if value.parse_time_string().is_err() {
+                value.clear();
+            },
+            // https://html.spec.whatwg.org/multipage/#local-date-and-time-state-(type=datetime-local):value-sanitization-algorithm
+            InputType::InputDatetimeLocal => if value.convert_valid_normalized_local_date_and_time_string().is_err() {
+                value.clear();
+            },
+            // https://html.spec.whatwg.org/multipage/#number-state-(type=number):value-sanitization-algorithm
+            InputType::InputNumber => if !value.is_valid_floating_point_number_string() {
+                value.clear();
+            },
+            // https://html.spec.whatwg.org/multipage/#range-state-(type=range):value-sanitization-algorithm
+            InputType::InputRange => value = self.sanitize_range_value(&value),
+            // https://html.spec.whatwg.org/multipage/#color-state-(type=color):value-sanitization-algorithm
+            InputType::InputColor => if value.is_valid_simple_color_string() {
+                value = DOMString::from(value.to_ascii_lowercase());
+            } else {
+                value = DOMString::from(DEFAULT_COLOR_VALUE);
+            },
             _ => ()
         }
+
+        if *self.textinput.borrow().single_line_content() != value {
+            self.textinput.borrow_mut().set_content(value);
+        }
+    }
+
+    // A range control always has a valid value within its range that matches its step.
+    fn sanitize_range_value(&self, value: &DOMString) -> DOMString {
+        let minimum = self.minimum().unwrap_or(0.);
+        let maximum = self.maximum().unwrap_or(100.);
+
+        // https://html.spec.whatwg.org/multipage/#range-state-(type=range):concept-input-value-default-range
+        let mut number = if value.is_valid_floating_point_number_string() {
+            self.convert_string_to_number(value).unwrap_or(minimum)
+        } else if maximum < minimum {
+            minimum
+        } else {
+            minimum + (maximum - minimum) / 2.
+        };
+
+        // https://html.spec.whatwg.org/multipage/#range-state-(type=range):suffering-from-an-underflow-2
+        // https://html.spec.whatwg.org/multipage/#range-state-(type=range):suffering-from-an-overflow-2
+        if number < minimum {
+            number = minimum;
+        } else if number > maximum {
+            number = if maximum < minimum { minimum } else { maximum };
+        }
+
+        // https://html.spec.whatwg.org/multipage/#range-state-(type=range):suffering-from-a-step-mismatch
+        if let Some(step) = self.allowed_value_step() {
+            let step_base = self.step_base();
+            let steps = (number - step_base) / step;
+            if (steps - steps.round()).abs() > STEP_MISMATCH_EPSILON {
+                // Round to the nearest value that matches the step, preferring the larger
+                // one when it is in range.
+                number = step_base + (steps + 0.5).floor() * step;
+                if number > maximum {
+                    number -= step;
+                }
+            }
+        }
+
+        self.convert_number_to_string(number).unwrap_or_default()
     }
 
     fn selection_direction(&self) -> SelectionDirection {
@@ -899,6 +1230,523 @@ impl HTMLInputElement {
 
         self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
     }
+
+    // https://html.spec.whatwg.org/multipage/#radio-button-group
+    fn radio_group_members(&self) -> Vec<DomRoot<HTMLInputElement>> {
+        let group = self.radio_group_name();
+        let owner = self.form_owner();
+        let doc = document_from_node(self);
+        let members = doc.upcast::<Node>().query_selector_iter(DOMString::from("input[type=radio]")).unwrap()
+            .filter_map(DomRoot::downcast::<HTMLInputElement>)
+            .filter(|r| in_same_group(&r, owner.r(), group.as_ref()) && self != &**r)
+            .collect();
+        members
+    }
+
+    // Whether a radio button suffers from being missing depends on the other
+    // members of its group, so their validity has to be updated together.
+    fn update_radio_group_validity_state(&self) {
+        self.update_validity_state();
+        for member in self.radio_group_members() {
+            member.update_validity_state();
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#the-readonly-attribute
+    fn does_readonly_apply(&self) -> bool {
+        self.input_type.get().is_textual()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#the-pattern-attribute
+    fn does_pattern_apply(&self) -> bool {
+        match self.input_type.get() {
+            InputType::InputText |
+            InputType::InputSearch |
+            InputType::InputUrl |
+            InputType::InputTel |
+            InputType::InputEmail |
+            InputType::InputPassword => true,
+            _ => false,
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#the-maxlength-and-minlength-attributes
+    fn does_minmaxlength_apply(&self) -> bool {
+        match self.input_type.get() {
+            InputType::InputText |
+            InputType::InputSearch |
+            InputType::InputUrl |
+            InputType::InputTel |
+            InputType::InputEmail |
+            InputType::InputPassword => true,
+            _ => false,
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-input-valueasnumber
+    // https://html.spec.whatwg.org/multipage/#dom-input-stepup
+    fn does_value_as_number_apply(&self) -> bool {
+        let input_type = self.input_type.get();
+        input_type.is_date_or_time() || input_type == InputType::InputNumber || input_type == InputType::InputRange
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-input-valueasdate
+    fn does_value_as_date_apply(&self) -> bool {
+        match self.input_type.get() {
+            InputType::InputDate |
+            InputType::InputMonth |
+            InputType::InputWeek |
+            InputType::InputTime => true,
+            _ => false,
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#suffering-from-being-missing
+    fn suffers_from_being_missing(&self, value: &DOMString) -> bool {
+        match self.input_type.get() {
+            // https://html.spec.whatwg.org/multipage/#checkbox-state-(type=checkbox):suffering-from-being-missing
+            InputType::InputCheckbox => self.Required() && !self.Checked(),
+            // https://html.spec.whatwg.org/multipage/#radio-button-state-(type=radio):suffering-from-being-missing
+            InputType::InputRadio => {
+                if self.Checked() {
+                    return false;
+                }
+                let members = self.radio_group_members();
+                !members.iter().any(|member| member.Checked()) &&
+                    (self.Required() || members.iter().any(|member| member.Required()))
+            },
+            // https://html.spec.whatwg.org/multipage/#file-upload-state-(type=file):suffering-from-being-missing
+            InputType::InputFile => {
+                self.Required() && self.filelist.get().map_or(true, |files| files.Length() == 0)
+            },
+            // https://html.spec.whatwg.org/multipage/#the-required-attribute:suffering-from-being-missing
+            _ => self.does_readonly_apply() && self.Required() && self.is_mutable() && value.is_empty(),
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#suffering-from-a-type-mismatch
+    fn suffers_from_type_mismatch(&self, value: &DOMString) -> bool {
+        if value.is_empty() {
+            return false;
+        }
+
+        match self.input_type.get() {
+            // https://html.spec.whatwg.org/multipage/#e-mail-state-(type=email):suffering-from-a-type-mismatch
+            InputType::InputEmail => {
+                if self.Multiple() {
+                    value.split(',')
+                         .any(|address| !is_valid_email_address(address.trim_matches(HTML_SPACE_CHARACTERS)))
+                } else {
+                    !is_valid_email_address(value)
+                }
+            },
+            // https://html.spec.whatwg.org/multipage/#url-state-(type=url):suffering-from-a-type-mismatch
+            InputType::InputUrl => ServoUrl::parse(value).is_err(),
+            _ => false,
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#suffering-from-a-pattern-mismatch
+    fn suffers_from_pattern_mismatch(&self, value: &DOMString) -> bool {
+        if value.is_empty() || !self.does_pattern_apply() {
+            return false;
+        }
+
+        let pattern = match self.upcast::<Element>().get_attribute(&ns!(), &local_name!("pattern")) {
+            Some(attr) => attr,
+            None => return false,
+        };
+
+        let mut compiled_pattern = self.compiled_pattern.borrow_mut();
+        // https://html.spec.whatwg.org/multipage/#compiled-pattern-regular-expression
+        // FIXME: The pattern should be compiled as a JavaScript regular expression
+        // with the "u" flag; this only differs for the more exotic syntax.
+        let regex = compiled_pattern.get_or_insert_with(|| Regex::new(&format!("^(?:{})$", &**pattern.value())).ok());
+        let regex = match *regex {
+            Some(ref regex) => regex,
+            // An invalid pattern does not impose any constraint.
+            None => return false,
+        };
+
+        if self.Multiple() && self.input_type.get() == InputType::InputEmail {
+            value.split(',').any(|address| !regex.is_match(address.trim_matches(HTML_SPACE_CHARACTERS)))
+        } else {
+            !regex.is_match(value)
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#suffering-from-being-too-long
+    // https://html.spec.whatwg.org/multipage/#suffering-from-being-too-short
+    fn suffers_from_length_issues(&self, value: &DOMString) -> ValidationFlags {
+        // Only dirty values that were last changed by a user edit are checked.
+        if value.is_empty() || !self.value_dirty.get() || !self.value_changed_by_user.get() ||
+           !self.does_minmaxlength_apply() {
+            return ValidationFlags::empty();
+        }
+
+        let mut failed_flags = ValidationFlags::empty();
+        let value_len = value.encode_utf16().count() as i32;
+        let max_length = self.MaxLength();
+        if max_length != DEFAULT_MAX_LENGTH && value_len > max_length {
+            failed_flags.insert(ValidationFlags::TOO_LONG);
+        }
+        let min_length = self.MinLength();
+        if min_length != DEFAULT_MIN_LENGTH && value_len < min_length {
+            failed_flags.insert(ValidationFlags::TOO_SHORT);
+        }
+        failed_flags
+    }
+
+    // https://html.spec.whatwg.org/multipage/#suffering-from-an-underflow
+    // https://html.spec.whatwg.org/multipage/#suffering-from-an-overflow
+    // https://html.spec.whatwg.org/multipage/#suffering-from-a-step-mismatch
+    fn suffers_from_range_issues(&self, value: &DOMString) -> ValidationFlags {
+        let value = match self.convert_string_to_number(value) {
+            Some(value) => value,
+            None => return ValidationFlags::empty(),
+        };
+
+        let mut failed_flags = ValidationFlags::empty();
+        if self.minimum().map_or(false, |min| value < min) {
+            failed_flags.insert(ValidationFlags::RANGE_UNDERFLOW);
+        }
+        if self.maximum().map_or(false, |max| value > max) {
+            failed_flags.insert(ValidationFlags::RANGE_OVERFLOW);
+        }
+        if let Some(step) = self.allowed_value_step() {
+            let steps = (value - self.step_base()) / step;
+            if (steps - steps.round()).abs() > STEP_MISMATCH_EPSILON {
+                failed_flags.insert(ValidationFlags::STEP_MISMATCH);
+            }
+        }
+        failed_flags
+    }
+
+    // https://html.spec.whatwg.org/multipage/#concept-input-value-string-number
+    fn convert_string_to_number(&self, value: &str) -> Option<f64> {
+        let value = DOMString::from(value);
+        match self.input_type.get() {
+            InputType::InputNumber | InputType::InputRange => parse_double(&value).ok(),
+            InputType::InputDate => {
+                value.parse_date_string().ok()
+                     .map(|(year, month, day)| days_from_civil(year, month, day) as f64 * MS_PER_DAY)
+            },
+            InputType::InputMonth => {
+                value.parse_month_string().ok()
+                     .map(|(year, month)| ((year as i64 - 1970) * 12 + month as i64 - 1) as f64)
+            },
+            InputType::InputWeek => {
+                value.parse_week_string().ok()
+                     .map(|(year, week)| days_from_week(year, week) as f64 * MS_PER_DAY)
+            },
+            InputType::InputTime => value.parse_time_string().ok().map(ms_from_time),
+            InputType::InputDatetimeLocal => {
+                value.parse_local_date_and_time_string().ok()
+                     .map(|((year, month, day), time)| {
+                         days_from_civil(year, month, day) as f64 * MS_PER_DAY + ms_from_time(time)
+                     })
+            },
+            _ => None,
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#concept-input-value-number-string
+    fn convert_number_to_string(&self, value: f64) -> Option<DOMString> {
+        let input_type = self.input_type.get();
+        if input_type.is_date_or_time() && !is_valid_time_value(value) {
+            return None;
+        }
+
+        let string = match input_type {
+            // https://html.spec.whatwg.org/multipage/#best-representation-of-the-number-as-a-floating-point-number
+            InputType::InputNumber | InputType::InputRange => {
+                Some(if value == 0. { "0".to_owned() } else { value.to_string() })
+            },
+            InputType::InputDate => {
+                let (year, month, day) = civil_from_days(days_from_ms(value));
+                format_date(year, month, day)
+            },
+            InputType::InputMonth => {
+                let months = value.floor() as i64;
+                let years = if months < 0 { (months - 11) / 12 } else { months / 12 };
+                format_month(1970 + years, (months - years * 12 + 1) as u32)
+            },
+            InputType::InputWeek => {
+                // The week belongs to the year its Thursday is in.
+                let thursday = days_from_ms(value) - weekday_from_days(days_from_ms(value)) + 3;
+                let (year, _, _) = civil_from_days(thursday);
+                let week = (thursday - days_from_civil(year as i32, 1, 1)) / 7 + 1;
+                if year < 1 { None } else { Some(format!("{:04}-W{:02}", year, week)) }
+            },
+            InputType::InputTime => Some(format_time(value)),
+            InputType::InputDatetimeLocal => {
+                let (year, month, day) = civil_from_days(days_from_ms(value));
+                format_date(year, month, day).map(|date| format!("{}T{}", date, format_time(value)))
+            },
+            _ => None,
+        };
+        string.map(DOMString::from)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#concept-input-value-string-date
+    fn convert_string_to_date(&self, value: &str) -> Option<f64> {
+        match self.input_type.get() {
+            InputType::InputDate | InputType::InputWeek | InputType::InputTime => self.convert_string_to_number(value),
+            // Unlike its number, the date of a month is the start of its first day.
+            InputType::InputMonth => {
+                DOMString::from(value).parse_month_string().ok()
+                    .map(|(year, month)| days_from_civil(year, month, 1) as f64 * MS_PER_DAY)
+            },
+            _ => None,
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#concept-input-value-date-string
+    fn convert_date_to_string(&self, value: f64) -> Option<DOMString> {
+        match self.input_type.get() {
+            InputType::InputDate | InputType::InputWeek | InputType::InputTime => self.convert_number_to_string(value),
+            InputType::InputMonth if is_valid_time_value(value) => {
+                let (year, month, _) = civil_from_days(days_from_ms(value));
+                format_month(year, month).map(DOMString::from)
+            },
+            _ => None,
+        }
+    }
+
+    fn numeric_attribute(&self, name: &LocalName) -> Option<f64> {
+        self.upcast::<Element>()
+            .get_attribute(&ns!(), name)
+            .and_then(|attr| self.convert_string_to_number(&attr.value()))
+    }
+
+    // https://html.spec.whatwg.org/multipage/#concept-input-min
+    fn minimum(&self) -> Option<f64> {
+        match self.numeric_attribute(&local_name!("min")) {
+            Some(min) => Some(min),
+            // https://html.spec.whatwg.org/multipage/#range-state-(type=range):concept-input-min-default
+            None if self.input_type.get() == InputType::InputRange => Some(0.),
+            None => None,
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#concept-input-max
+    fn maximum(&self) -> Option<f64> {
+        match self.numeric_attribute(&local_name!("max")) {
+            Some(max) => Some(max),
+            // https://html.spec.whatwg.org/multipage/#range-state-(type=range):concept-input-max-default
+            None if self.input_type.get() == InputType::InputRange => Some(100.),
+            None => None,
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#concept-input-step
+    fn allowed_value_step(&self) -> Option<f64> {
+        // https://html.spec.whatwg.org/multipage/#concept-input-step-default
+        // https://html.spec.whatwg.org/multipage/#concept-input-step-scale
+        let (default_step, step_scale_factor) = match self.input_type.get() {
+            InputType::InputNumber | InputType::InputRange | InputType::InputMonth => (1., 1.),
+            InputType::InputDate => (1., MS_PER_DAY),
+            InputType::InputWeek => (1., 7. * MS_PER_DAY),
+            InputType::InputTime | InputType::InputDatetimeLocal => (60., 1000.),
+            _ => return None,
+        };
+
+        let step = match self.upcast::<Element>().get_attribute(&ns!(), &local_name!("step")) {
+            Some(attr) => {
+                let value = attr.value();
+                if value.eq_ignore_ascii_case("any") {
+                    return None;
+                }
+                parse_double(&value).ok().and_then(|step| if step > 0. { Some(step) } else { None })
+            },
+            None => None,
+        };
+
+        // Dates, months and weeks can only be stepped by a whole number of them.
+        let step = match self.input_type.get() {
+            InputType::InputDate | InputType::InputMonth | InputType::InputWeek => {
+                step.map(|step| step.round().max(1.))
+            },
+            _ => step,
+        };
+
+        Some(step.unwrap_or(default_step) * step_scale_factor)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#concept-input-min-zero
+    fn step_base(&self) -> f64 {
+        self.numeric_attribute(&local_name!("min"))
+            .or_else(|| self.numeric_attribute(&local_name!("value")))
+            .unwrap_or_else(|| match self.input_type.get() {
+                // https://html.spec.whatwg.org/multipage/#week-state-(type=week):concept-input-step-default-base
+                InputType::InputWeek => -259200000.,
+                _ => 0.,
+            })
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-input-stepup
+    // https://html.spec.whatwg.org/multipage/#dom-input-stepdown
+    fn step_up_or_down(&self, n: i32, direction: StepDirection) -> ErrorResult {
+        // Step 1.
+        if !self.does_value_as_number_apply() {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 2.
+        let step = match self.allowed_value_step() {
+            Some(step) => step,
+            None => return Err(Error::InvalidState),
+        };
+        let step_base = self.step_base();
+        let (minimum, maximum) = (self.minimum(), self.maximum());
+
+        if let (Some(min), Some(max)) = (minimum, maximum) {
+            // Step 3.
+            if max < min {
+                return Ok(());
+            }
+            // Step 4.
+            if step_base + ((min - step_base) / step).ceil() * step > max {
+                return Ok(());
+            }
+        }
+
+        // Steps 5-6.
+        let mut value = self.convert_string_to_number(&self.Value()).unwrap_or(0.);
+        let value_before_stepping = value;
+
+        // Step 7.
+        let steps = (value - step_base) / step;
+        if (steps - steps.round()).abs() > STEP_MISMATCH_EPSILON {
+            value = match direction {
+                StepDirection::Up => step_base + steps.ceil() * step,
+                StepDirection::Down => step_base + steps.floor() * step,
+            };
+        } else {
+            let delta = step * n as f64;
+            value += match direction {
+                StepDirection::Up => delta,
+                StepDirection::Down => -delta,
+            };
+        }
+
+        // Step 8.
+        if let Some(min) = minimum {
+            if value < min {
+                value = step_base + ((min - step_base) / step).ceil() * step;
+            }
+        }
+
+        // Step 9.
+        if let Some(max) = maximum {
+            if value > max {
+                value = step_base + ((max - step_base) / step).floor() * step;
+            }
+        }
+
+        // Step 10.
+        match direction {
+            StepDirection::Up if value < value_before_stepping => return Ok(()),
+            StepDirection::Down if value > value_before_stepping => return Ok(()),
+            _ => {},
+        }
+
+        // Step 11.
+        self.SetValue(self.convert_number_to_string(value).unwrap_or_default())
+    }
+}
+
+// https://html.spec.whatwg.org/multipage/#valid-e-mail-address
+fn is_valid_email_address(address: &str) -> bool {
+    lazy_static! {
+        static ref VALID_EMAIL_ADDRESS: Regex = Regex::new(VALID_EMAIL_ADDRESS_REGEX).unwrap();
+    }
+    VALID_EMAIL_ADDRESS.is_match(address)
+}
+
+// Whether a number of milliseconds since the epoch is in the range of a `Date`.
+fn is_valid_time_value(value: f64) -> bool {
+    value.is_finite() && value.abs() <= 8.64e15
+}
+
+fn days_from_ms(value: f64) -> i64 {
+    (value / MS_PER_DAY).floor() as i64
+}
+
+// The number of days between 1970-01-01 and a date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i32, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year as i64 - 1 } else { year as i64 };
+    let era = (if year >= 0 { year } else { year - 399 }) / 400;
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+// The inverse of `days_from_civil`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = (if days >= 0 { days } else { days - 146096 }) / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = (if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 }) as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// The day of the week, with Monday being 0.
+fn weekday_from_days(days: i64) -> i64 {
+    // 1970-01-01 was a Thursday.
+    ((days % 7) + 7 + 3) % 7
+}
+
+// The first day of a week, which is the Monday of the week containing January 4th.
+fn days_from_week(year: i32, week: u32) -> i64 {
+    let january_fourth = days_from_civil(year, 1, 4);
+    january_fourth - weekday_from_days(january_fourth) + (week as i64 - 1) * 7
+}
+
+fn ms_from_time((hour, minute, second): (u32, u32, f64)) -> f64 {
+    ((hour * 60 + minute) * 60) as f64 * 1000. + second * 1000.
+}
+
+// https://html.spec.whatwg.org/multipage/#valid-date-string
+fn format_date(year: i64, month: u32, day: u32) -> Option<String> {
+    if year < 1 {
+        return None;
+    }
+    Some(format!("{:04}-{:02}-{:02}", year, month, day))
+}
+
+// https://html.spec.whatwg.org/multipage/#valid-month-string
+fn format_month(year: i64, month: u32) -> Option<String> {
+    if year < 1 {
+        return None;
+    }
+    Some(format!("{:04}-{:02}", year, month))
+}
+
+// The shortest valid time string for the time of day of a number of milliseconds.
+// https://html.spec.whatwg.org/multipage/#valid-time-string
+fn format_time(value: f64) -> String {
+    let milliseconds = (value - (value / MS_PER_DAY).floor() * MS_PER_DAY) as u64;
+    let (hour, minute) = (milliseconds / 3600000, milliseconds / 60000 % 60);
+    let (second, millisecond) = (milliseconds / 1000 % 60, milliseconds % 1000);
+
+    let mut time = format!("{:02}:{:02}", hour, minute);
+    if second != 0 || millisecond != 0 {
+        time.push_str(&format!(":{:02}", second));
+    }
+    if millisecond != 0 {
+        let fraction = format!("{:03}", millisecond);
+        time.push('.');
+        time.push_str(fraction.trim_right_matches('0'));
+    }
+    time
 }
 
 impl VirtualMethods for HTMLInputElement {
@@ -924,7 +1772,7 @@ impl VirtualMethods for HTMLInputElement {
                 el.set_enabled_state(!disabled_state);
                 el.check_ancestors_disabled_state_for_form_control();
 
-                if self.input_type.get() == InputType::InputText {
+                if self.input_type.get().is_textual() {
                     let read_write = !(self.ReadOnly() || el.disabled_state());
                     el.set_read_write_state(read_write);
                 }
@@ -958,6 +1806,19 @@ impl VirtualMethods for HTMLInputElement {
                             &atom!("radio") => InputType::InputRadio,
                             &atom!("checkbox") => InputType::InputCheckbox,
                             &atom!("password") => InputType::InputPassword,
+                            &atom!("hidden") => InputType::InputHidden,
+                            &atom!("search") => InputType::InputSearch,
+                            &atom!("tel") => InputType::InputTel,
+                            &atom!("url") => InputType::InputUrl,
+                            &atom!("email") => InputType::InputEmail,
+                            &atom!("date") => InputType::InputDate,
+                            &atom!("month") => InputType::InputMonth,
+                            &atom!("week") => InputType::InputWeek,
+                            &atom!("time") => InputType::InputTime,
+                            &atom!("datetime-local") => InputType::InputDatetimeLocal,
+                            &atom!("number") => InputType::InputNumber,
+                            &atom!("range") => InputType::InputRange,
+                            &atom!("color") => InputType::InputColor,
                             _ => InputType::InputText,
                         };
 
@@ -965,7 +1826,7 @@ impl VirtualMethods for HTMLInputElement {
                         let (old_value_mode, old_idl_value) = (self.value_mode(), self.Value());
                         self.input_type.set(new_type);
 
-                        if new_type == InputType::InputText {
+                        if new_type.is_textual() {
                             let read_write = !(self.ReadOnly() || el.disabled_state());
                             el.set_read_write_state(read_write);
                         } else {
@@ -1035,12 +1896,21 @@ impl VirtualMethods for HTMLInputElement {
                 let value = mutation.new_value(attr).map(|value| (**value).to_owned());
                 self.textinput.borrow_mut().set_content(
                     value.map_or(DOMString::new(), DOMString::from));
+                self.sanitize_value();
                 self.update_placeholder_shown_state();
             },
+            // The value of a range control depends on its range and step.
+            &local_name!("min") | &local_name!("max") | &local_name!("step")
+                if self.input_type.get() == InputType::InputRange => {
+                self.sanitize_value();
+            },
             &local_name!("name") if self.input_type.get() == InputType::InputRadio => {
                 self.radio_group_updated(
                     mutation.new_value(attr).as_ref().map(|name| name.as_atom()));
             },
+            &local_name!("pattern") => {
+                *self.compiled_pattern.borrow_mut() = None;
+            },
             &local_name!("maxlength") => {
                 match *attr.value() {
                     AttrValue::Int(_, value) => {
@@ -1076,7 +1946,7 @@ impl VirtualMethods for HTMLInputElement {
                 }
                 self.update_placeholder_shown_state();
             },
-            &local_name!("readonly") if self.input_type.get() == InputType::InputText => {
+            &local_name!("readonly") if self.input_type.get().is_textual() => {
                 let el = self.upcast::<Element>();
                 match mutation {
                     AttributeMutation::Set(_) => {
@@ -1092,6 +1962,12 @@ impl VirtualMethods for HTMLInputElement {
             },
             _ => {},
         }
+
+        match (attr.local_name(), self.input_type.get()) {
+            (&local_name!("name"), InputType::InputRadio) |
+            (&local_name!("required"), InputType::InputRadio) => self.update_radio_group_validity_state(),
+            _ => self.update_validity_state(),
+        }
     }
 
     fn parse_plain_attribute(&self, name: &LocalName, value: DOMString) -> AttrValue {
@@ -1112,6 +1988,7 @@ impl VirtualMethods for HTMLInputElement {
         }
 
         self.upcast::<Element>().check_ancestors_disabled_state_for_form_control();
+        self.update_validity_state();
     }
 
     fn unbind_from_tree(&self, context: &UnbindContext) {
@@ -1124,6 +2001,7 @@ impl VirtualMethods for HTMLInputElement {
         } else {
             el.check_disabled_attribute();
         }
+        self.update_validity_state();
     }
 
     fn handle_event(&self, event: &Event) {
@@ -1138,8 +2016,7 @@ impl VirtualMethods for HTMLInputElement {
             //TODO: set the editing position for text inputs
 
             document_from_node(self).request_focus(self.upcast());
-            if (self.input_type.get() == InputType::InputText ||
-                self.input_type.get() == InputType::InputPassword) &&
+            if self.input_type.get().is_textual() &&
                 // Check if we display a placeholder. Layout doesn't know about this.
                 !self.textinput.borrow().is_empty() {
                     if let Some(mouse_event) = event.downcast::<MouseEvent>() {
@@ -1162,8 +2039,7 @@ impl VirtualMethods for HTMLInputElement {
                     }
                 }
         } else if event.type_() == atom!("keydown") && !event.DefaultPrevented() &&
-            (self.input_type.get() == InputType::InputText ||
-             self.input_type.get() == InputType::InputPassword) {
+            self.input_type.get().is_textual() {
                 if let Some(keyevent) = event.downcast::<KeyboardEvent>() {
                     // This can't be inlined, as holding on to textinput.borrow_mut()
                     // during self.implicit_submission will cause a panic.
@@ -1177,7 +2053,10 @@ impl VirtualMethods for HTMLInputElement {
                         },
                         DispatchInput => {
                             self.value_changed.set(true);
+                            self.value_dirty.set(true);
+                            self.value_changed_by_user.set(true);
                             self.update_placeholder_shown_state();
+                            self.update_validity_state();
                             self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
                             event.mark_as_handled();
                         }
@@ -1189,8 +2068,7 @@ impl VirtualMethods for HTMLInputElement {
                     }
                 }
         } else if event.type_() == atom!("keypress") && !event.DefaultPrevented() &&
-            (self.input_type.get() == InputType::InputText ||
-             self.input_type.get() == InputType::InputPassword) {
+            self.input_type.get().is_textual() {
                 if event.IsTrusted() {
                     let window = window_from_node(self);
                     let _ = window.user_interaction_task_source()
@@ -1219,13 +2097,53 @@ impl FormControl for HTMLInputElement {
 }
 
 impl Validatable for HTMLInputElement {
+    fn as_element(&self) -> &Element {
+        self.upcast()
+    }
+
+    fn validity_state(&self) -> DomRoot<ValidityState> {
+        self.validity_state.or_init(|| ValidityState::new(&window_from_node(self), self.upcast()))
+    }
+
+    // https://html.spec.whatwg.org/multipage/#candidate-for-constraint-validation
     fn is_instance_validatable(&self) -> bool {
-        // https://html.spec.whatwg.org/multipage/#candidate-for-constraint-validation
-        true
+        match self.input_type.get() {
+            // https://html.spec.whatwg.org/multipage/#hidden-state-(type=hidden)
+            // https://html.spec.whatwg.org/multipage/#reset-button-state-(type=reset)
+            // https://html.spec.whatwg.org/multipage/#button-state-(type=button)
+            InputType::InputHidden | InputType::InputReset | InputType::InputButton => false,
+            // https://html.spec.whatwg.org/multipage/#the-readonly-attribute:barred-from-constraint-validation
+            _ if self.does_readonly_apply() && self.ReadOnly() => false,
+            _ => !self.upcast::<Element>().disabled_state(),
+        }
     }
-    fn validate(&self, _validate_flags: ValidationFlags) -> bool {
-        // call stub methods defined in validityState.rs file here according to the flags set in validate_flags
-        true
+
+    fn perform_validation(&self, validate_flags: ValidationFlags) -> ValidationFlags {
+        let mut failed_flags = ValidationFlags::empty();
+        let value = self.Value();
+
+        if validate_flags.contains(ValidationFlags::VALUE_MISSING) &&
+           self.suffers_from_being_missing(&value) {
+            failed_flags.insert(ValidationFlags::VALUE_MISSING);
+        }
+        if validate_flags.contains(ValidationFlags::TYPE_MISMATCH) &&
+           self.suffers_from_type_mismatch(&value) {
+            failed_flags.insert(ValidationFlags::TYPE_MISMATCH);
+        }
+        if validate_flags.contains(ValidationFlags::PATTERN_MISMATCH) &&
+           self.suffers_from_pattern_mismatch(&value) {
+            failed_flags.insert(ValidationFlags::PATTERN_MISMATCH);
+        }
+        if validate_flags.intersects(ValidationFlags::TOO_LONG | ValidationFlags::TOO_SHORT) {
+            failed_flags.insert(validate_flags & self.suffers_from_length_issues(&value));
+        }
+        if validate_flags.intersects(ValidationFlags::RANGE_UNDERFLOW |
+                                     ValidationFlags::RANGE_OVERFLOW |
+                                     ValidationFlags::STEP_MISMATCH) {
+            failed_flags.insert(validate_flags & self.suffers_from_range_issues(&value));
+        }
+
+        failed_flags
     }
 }
 
@@ -37,6 +37,7 @@ use ipc_channel::router::ROUTER;
 use microtask::{Microtask, MicrotaskRunnable};
 use mime::{Mime, SubLevel, TopLevel};
 use net_traits::{FetchResponseListener, FetchMetadata, Metadata, NetworkError};
+use net_traits::csp::Violation;
 use net_traits::request::{CredentialsMode, Destination, RequestInit};
 use network_listener::{NetworkListener, PreInvoke};
 use script_thread::ScriptThread;
@@ -599,6 +600,7 @@ impl HTMLMediaElement {
                     pipeline_id: Some(self.global().pipeline_id()),
                     referrer_url: Some(document.url()),
                     referrer_policy: document.get_referrer_policy(),
+                    csp_list: document.get_csp_list().clone(),
                     .. RequestInit::default()
                 };
 
@@ -1090,6 +1092,10 @@ impl FetchResponseListener for HTMLMediaElementContext {
             elem.queue_dedicated_media_source_failure_steps();
         }
     }
+
+    fn process_csp_violations(&mut self, violations: Vec<Violation>) {
+        document_from_node(&*self.elem.root()).report_csp_violations(violations);
+    }
 }
 
 impl PreInvoke for HTMLMediaElementContext {
@@ -19,9 +19,11 @@ use dom::node::{Node, UnbindContext, document_from_node, window_from_node};
 use dom::virtualmethods::VirtualMethods;
 use dom_struct::dom_struct;
 use html5ever::{LocalName, Prefix};
+use net_traits::csp::{CspList, PolicyDisposition, PolicySource};
 use parking_lot::RwLock;
 use servo_arc::Arc;
 use servo_config::prefs::PREFS;
+use std::cell::Cell;
 use std::sync::atomic::AtomicBool;
 use style::attr::AttrValue;
 use style::media_queries::MediaList;
@@ -34,6 +36,9 @@ pub struct HTMLMetaElement {
     #[ignore_malloc_size_of = "Arc"]
     stylesheet: DomRefCell<Option<Arc<Stylesheet>>>,
     cssom_stylesheet: MutNullableDom<CSSStyleSheet>,
+    /// Whether this element's policy was already added to the document's CSP
+    /// list, which happens at most once.
+    csp_applied: Cell<bool>,
 }
 
 impl HTMLMetaElement {
@@ -44,6 +49,7 @@ impl HTMLMetaElement {
             htmlelement: HTMLElement::new_inherited(local_name, prefix, document),
             stylesheet: DomRefCell::new(None),
             cssom_stylesheet: MutNullableDom::new(None),
+            csp_applied: Cell::new(false),
         }
     }
 
@@ -87,6 +93,39 @@ impl HTMLMetaElement {
                 self.apply_referrer();
             }
         }
+
+        if let Some(http_equiv) = element.get_attribute(&ns!(), &local_name!("http-equiv")).r() {
+            let http_equiv = http_equiv.value().to_ascii_lowercase();
+            if http_equiv.trim_matches(HTML_SPACE_CHARACTERS) == "content-security-policy" {
+                self.apply_content_security_policy();
+            }
+        }
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#attr-meta-http-equiv-content-security-policy>
+    fn apply_content_security_policy(&self) {
+        // Step 1.
+        let in_head = self.upcast::<Node>().GetParentElement().map_or(false, |parent| parent.is::<HTMLHeadElement>());
+        if !in_head {
+            return;
+        }
+
+        // Step 2.
+        let element = self.upcast::<Element>();
+        let content = match element.get_attribute(&ns!(), &local_name!("content")) {
+            Some(content) => content,
+            None => return,
+        };
+        if content.value().is_empty() {
+            return;
+        }
+
+        // Steps 3-5.
+        if self.csp_applied.replace(true) {
+            return;
+        }
+        let csp_list = CspList::parse(&content.value(), PolicySource::Meta, PolicyDisposition::Enforce);
+        document_from_node(self).append_csp_list(csp_list);
     }
 
     fn apply_viewport(&self) {
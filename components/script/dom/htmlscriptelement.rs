@@ -29,6 +29,7 @@ use ipc_channel::ipc;
 use ipc_channel::router::ROUTER;
 use js::jsval::UndefinedValue;
 use net_traits::{FetchMetadata, FetchResponseListener, Metadata, NetworkError};
+use net_traits::csp::{InlineCheckType, Violation};
 use net_traits::request::{CorsSettings, CredentialsMode, Destination, RequestInit, RequestMode};
 use network_listener::{NetworkListener, PreInvoke};
 use servo_atoms::Atom;
@@ -220,6 +221,10 @@ impl FetchResponseListener for ScriptContext {
 
         document.finish_load(LoadType::Script(self.url.clone()));
     }
+
+    fn process_csp_violations(&mut self, violations: Vec<Violation>) {
+        document_from_node(&*self.elem.root()).report_csp_violations(violations);
+    }
 }
 
 impl PreInvoke for ScriptContext {}
@@ -229,6 +234,7 @@ fn fetch_a_classic_script(script: &HTMLScriptElement,
                           kind: ExternalScriptKind,
                           url: ServoUrl,
                           cors_setting: Option<CorsSettings>,
+                          cryptographic_nonce: String,
                           integrity_metadata: String,
                           character_encoding: &'static Encoding) {
     let doc = document_from_node(script);
@@ -254,6 +260,7 @@ fn fetch_a_classic_script(script: &HTMLScriptElement,
         referrer_url: Some(doc.url()),
         referrer_policy: doc.get_referrer_policy(),
         integrity_metadata: integrity_metadata,
+        cryptographic_nonce_metadata: cryptographic_nonce,
         .. RequestInit::default()
     };
 
@@ -340,7 +347,12 @@ impl HTMLScriptElement {
 
         // TODO: Step 11: nomodule content attribute
 
-        // TODO(#4577): Step 12: CSP.
+        // Step 12.
+        let nonce = element.get_string_attribute(&local_name!("nonce"));
+        if !element.has_attribute(&local_name!("src")) &&
+           doc.should_inline_be_blocked(InlineCheckType::Script, Some(&*nonce), &text) {
+            return;
+        }
 
         // Step 13.
         let for_attribute = element.get_attribute(&ns!(), &local_name!("for"));
@@ -372,7 +384,7 @@ impl HTMLScriptElement {
 
         // TODO: Step 16: Module script credentials mode.
 
-        // TODO: Step 17: Nonce.
+        // Step 17: Nonce, computed in step 12.
 
         // Step 18: Integrity metadata.
         let im_attribute = element.get_attribute(&ns!(), &local_name!("integrity"));
@@ -427,7 +439,13 @@ impl HTMLScriptElement {
             };
 
             // Step 21.6.
-            fetch_a_classic_script(self, kind, url, cors_setting, integrity_metadata.to_owned(), encoding);
+            fetch_a_classic_script(self,
+                                   kind,
+                                   url,
+                                   cors_setting,
+                                   nonce.into(),
+                                   integrity_metadata.to_owned(),
+                                   encoding);
 
             // Step 23.
             match kind {
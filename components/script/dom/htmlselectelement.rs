@@ -14,7 +14,7 @@ use dom::bindings::codegen::UnionTypes::HTMLElementOrLong;
 use dom::bindings::codegen::UnionTypes::HTMLOptionElementOrHTMLOptGroupElement;
 //use dom::bindings::error::ErrorResult;
 use dom::bindings::inheritance::Castable;
-use dom::bindings::root::{DomRoot, MutNullableDom};
+use dom::bindings::root::{DomRoot, MutNullableDom, RootedReference};
 use dom::bindings::str::DOMString;
 use dom::document::Document;
 use dom::element::{AttributeMutation, Element};
@@ -63,6 +63,7 @@ pub struct HTMLSelectElement {
     htmlelement: HTMLElement,
     options: MutNullableDom<HTMLOptionsCollection>,
     form_owner: MutNullableDom<HTMLFormElement>,
+    validity_state: MutNullableDom<ValidityState>,
 }
 
 static DEFAULT_SELECT_SIZE: u32 = 0;
@@ -73,10 +74,12 @@ impl HTMLSelectElement {
                      document: &Document) -> HTMLSelectElement {
         HTMLSelectElement {
             htmlelement:
-                HTMLElement::new_inherited_with_state(ElementState::IN_ENABLED_STATE,
+                HTMLElement::new_inherited_with_state(ElementState::IN_ENABLED_STATE |
+                                                      ElementState::IN_VALID_STATE,
                                                       local_name, prefix, document),
                 options: Default::default(),
                 form_owner: Default::default(),
+                validity_state: Default::default(),
         }
     }
 
@@ -117,6 +120,7 @@ impl HTMLSelectElement {
     // https://html.spec.whatwg.org/multipage/#ask-for-a-reset
     pub fn ask_for_reset(&self) {
         if self.Multiple() {
+            self.update_validity_state();
             return;
         }
 
@@ -143,6 +147,8 @@ impl HTMLSelectElement {
                 }
             }
         }
+
+        self.update_validity_state();
     }
 
     pub fn push_form_data(&self, data_set: &mut Vec<FormDatum>) {
@@ -185,15 +191,59 @@ impl HTMLSelectElement {
              self.Size()
          }
      }
+
+    // https://html.spec.whatwg.org/multipage/#placeholder-label-option
+    fn placeholder_label_option(&self) -> Option<DomRoot<HTMLOptionElement>> {
+        if !self.Required() || self.Multiple() || self.display_size() != 1 {
+            return None;
+        }
+
+        match self.list_of_options().next() {
+            Some(option) => {
+                let parent = option.upcast::<Node>().GetParentNode();
+                if option.Value().is_empty() && parent.r() == Some(self.upcast::<Node>()) {
+                    Some(option)
+                } else {
+                    None
+                }
+            },
+            None => None,
+        }
+    }
 }
 
 impl HTMLSelectElementMethods for HTMLSelectElement {
+    // https://html.spec.whatwg.org/multipage/#dom-cva-willvalidate
+    fn WillValidate(&self) -> bool {
+        self.is_instance_validatable()
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-cva-validity
     fn Validity(&self) -> DomRoot<ValidityState> {
-        let window = window_from_node(self);
-        ValidityState::new(&window, self.upcast())
+        self.validity_state()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-validationmessage
+    fn ValidationMessage(&self) -> DOMString {
+        self.validation_message()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-checkvalidity
+    fn CheckValidity(&self) -> bool {
+        self.check_validity()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-reportvalidity
+    fn ReportValidity(&self) -> bool {
+        self.report_validity()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-setcustomvalidity
+    fn SetCustomValidity(&self, error: DOMString) {
+        self.validity_state().set_custom_error_message(error);
     }
 
+
     // Note: this function currently only exists for union.html.
     // https://html.spec.whatwg.org/multipage/#dom-select-add
     fn Add(&self, _element: HTMLOptionElementOrHTMLOptGroupElement, _before: Option<HTMLElementOrLong>) {
@@ -222,6 +272,12 @@ impl HTMLSelectElementMethods for HTMLSelectElement {
     // https://html.spec.whatwg.org/multipage/#dom-fe-name
     make_setter!(SetName, "name");
 
+    // https://html.spec.whatwg.org/multipage/#dom-select-required
+    make_bool_getter!(Required, "required");
+
+    // https://html.spec.whatwg.org/multipage/#dom-select-required
+    make_bool_setter!(SetRequired, "required");
+
     // https://html.spec.whatwg.org/multipage/#dom-select-size
     make_uint_getter!(Size, "size", DEFAULT_SELECT_SIZE);
 
@@ -311,6 +367,7 @@ impl HTMLSelectElementMethods for HTMLSelectElement {
         for opt in opt_iter {
             opt.set_selectedness(false);
         }
+        self.update_validity_state();
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-select-selectedindex
@@ -337,6 +394,7 @@ impl HTMLSelectElementMethods for HTMLSelectElement {
                 opt.set_selectedness(false);
             }
         }
+        self.update_validity_state();
     }
 }
 
@@ -367,6 +425,8 @@ impl VirtualMethods for HTMLSelectElement {
             },
             _ => {},
         }
+
+        self.update_validity_state();
     }
 
     fn bind_to_tree(&self, tree_in_doc: bool) {
@@ -375,6 +435,7 @@ impl VirtualMethods for HTMLSelectElement {
         }
 
         self.upcast::<Element>().check_ancestors_disabled_state_for_form_control();
+        self.update_validity_state();
     }
 
     fn unbind_from_tree(&self, context: &UnbindContext) {
@@ -387,6 +448,7 @@ impl VirtualMethods for HTMLSelectElement {
         } else {
             el.check_disabled_attribute();
         }
+        self.update_validity_state();
     }
 
     fn parse_plain_attribute(&self, local_name: &LocalName, value: DOMString) -> AttrValue {
@@ -412,13 +474,34 @@ impl FormControl for HTMLSelectElement {
 }
 
 impl Validatable for HTMLSelectElement {
+    fn as_element(&self) -> &Element {
+        self.upcast()
+    }
+
+    fn validity_state(&self) -> DomRoot<ValidityState> {
+        self.validity_state.or_init(|| ValidityState::new(&window_from_node(self), self.upcast()))
+    }
+
+    // https://html.spec.whatwg.org/multipage/#candidate-for-constraint-validation
     fn is_instance_validatable(&self) -> bool {
-        true
+        !self.upcast::<Element>().disabled_state()
     }
-    fn validate(&self, validate_flags: ValidationFlags) -> bool {
-        if validate_flags.is_empty() {}
-        // Need more flag check for different validation types later
-        true
+
+    fn perform_validation(&self, validate_flags: ValidationFlags) -> ValidationFlags {
+        let mut failed_flags = ValidationFlags::empty();
+
+        // https://html.spec.whatwg.org/multipage/#the-select-element:suffering-from-being-missing
+        if validate_flags.contains(ValidationFlags::VALUE_MISSING) && self.Required() {
+            let placeholder = self.placeholder_label_option();
+            let selected_option = self.list_of_options()
+                .filter(|option| option.Selected())
+                .find(|option| placeholder.as_ref() != Some(option));
+            if selected_option.is_none() {
+                failed_flags.insert(ValidationFlags::VALUE_MISSING);
+            }
+        }
+
+        failed_flags
     }
 }
 
@@ -0,0 +1,198 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::attr::Attr;
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::HTMLSlotElementBinding::{self, AssignedNodesOptions, HTMLSlotElementMethods};
+use dom::bindings::inheritance::Castable;
+use dom::bindings::root::{Dom, DomRoot, LayoutDom};
+use dom::bindings::str::DOMString;
+use dom::document::Document;
+use dom::element::{AttributeMutation, Element};
+use dom::htmlelement::HTMLElement;
+use dom::mutationobserver::MutationObserver;
+use dom::node::{Node, document_from_node};
+use dom::shadowroot::ShadowRoot;
+use dom::text::Text;
+use dom::virtualmethods::VirtualMethods;
+use dom_struct::dom_struct;
+use html5ever::{LocalName, Prefix};
+use script_thread::ScriptThread;
+
+// https://html.spec.whatwg.org/multipage/#the-slot-element
+#[dom_struct]
+pub struct HTMLSlotElement {
+    htmlelement: HTMLElement,
+    /// <https://dom.spec.whatwg.org/#slot-assigned-nodes>
+    assigned_nodes: DomRefCell<Vec<Dom<Node>>>,
+}
+
+impl HTMLSlotElement {
+    fn new_inherited(local_name: LocalName, prefix: Option<Prefix>, document: &Document) -> HTMLSlotElement {
+        HTMLSlotElement {
+            htmlelement: HTMLElement::new_inherited(local_name, prefix, document),
+            assigned_nodes: DomRefCell::new(vec![]),
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn new(local_name: LocalName,
+               prefix: Option<Prefix>,
+               document: &Document) -> DomRoot<HTMLSlotElement> {
+        Node::reflect_node(Box::new(HTMLSlotElement::new_inherited(local_name, prefix, document)),
+                           document,
+                           HTMLSlotElementBinding::Wrap)
+    }
+
+    pub fn has_no_assigned_nodes(&self) -> bool {
+        self.assigned_nodes.borrow().is_empty()
+    }
+
+    pub fn assigned_nodes(&self) -> Vec<DomRoot<Node>> {
+        self.assigned_nodes.borrow().iter().map(|node| DomRoot::from_ref(&**node)).collect()
+    }
+
+    /// <https://dom.spec.whatwg.org/#find-slotables>
+    fn find_slotables(&self) -> Vec<DomRoot<Node>> {
+        // Steps 1-3.
+        let shadow_root = match self.upcast::<Node>().containing_shadow_root() {
+            Some(shadow_root) => shadow_root,
+            None => return vec![],
+        };
+        // Steps 4-5.
+        let host = shadow_root.host();
+        host.upcast::<Node>()
+            .children()
+            .filter(|child| child.is::<Element>() || child.is::<Text>())
+            .filter(|child| child.find_a_slot(false).map_or(false, |slot| &*slot == self))
+            .collect()
+    }
+
+    /// <https://dom.spec.whatwg.org/#find-flattened-slotables>
+    fn find_flattened_slotables(&self) -> Vec<DomRoot<Node>> {
+        // Steps 1-2.
+        if !self.upcast::<Node>().is_in_shadow_tree() {
+            return vec![];
+        }
+        // Steps 3-4.
+        let mut slotables = self.assigned_nodes();
+        if slotables.is_empty() {
+            slotables = self.upcast::<Node>()
+                .children()
+                .filter(|child| child.is::<Element>() || child.is::<Text>())
+                .collect();
+        }
+        // Step 5.
+        let mut result = vec![];
+        for node in slotables {
+            match node.downcast::<HTMLSlotElement>() {
+                Some(slot) if node.is_in_shadow_tree() => result.extend(slot.find_flattened_slotables()),
+                _ => result.push(node.clone()),
+            }
+        }
+        result
+    }
+
+    /// <https://dom.spec.whatwg.org/#assign-slotables>
+    pub fn assign_slotables(&self) {
+        // Step 1.
+        let slotables = self.find_slotables();
+        // Step 2.
+        let changed = {
+            let assigned_nodes = self.assigned_nodes.borrow();
+            slotables.len() != assigned_nodes.len() ||
+                slotables.iter().zip(assigned_nodes.iter()).any(|(a, b)| &**a != &**b)
+        };
+        if !changed {
+            return;
+        }
+        self.signal_a_slot_change();
+        // Step 3.
+        for node in self.assigned_nodes.borrow().iter() {
+            if node.assigned_slot().map_or(false, |slot| &*slot == self) {
+                node.set_assigned_slot(None);
+            }
+        }
+        *self.assigned_nodes.borrow_mut() = slotables.iter().map(|node| Dom::from_ref(&**node)).collect();
+        // Step 4.
+        for node in &slotables {
+            node.set_assigned_slot(Some(self));
+        }
+
+        // The flat tree of the host changed, so it needs to be laid out again.
+        if let Some(shadow_root) = self.upcast::<Node>().containing_shadow_root() {
+            let host = shadow_root.host();
+            if host.upcast::<Node>().is_in_doc() {
+                document_from_node(self).content_and_heritage_changed(host.upcast());
+            }
+        }
+    }
+
+    /// <https://dom.spec.whatwg.org/#assign-slotables-for-a-tree>
+    pub fn assign_slotables_for_a_tree(root: &Node) {
+        for slot in root.traverse_preorder().filter_map(DomRoot::downcast::<HTMLSlotElement>) {
+            slot.assign_slotables();
+        }
+    }
+
+    /// <https://dom.spec.whatwg.org/#signal-a-slot-change>
+    pub fn signal_a_slot_change(&self) {
+        // Step 1.
+        ScriptThread::add_signal_slot(self);
+        // Step 2.
+        MutationObserver::queue_mutation_observer_compound_microtask();
+    }
+}
+
+impl HTMLSlotElementMethods for HTMLSlotElement {
+    // https://html.spec.whatwg.org/multipage/#dom-slot-name
+    make_getter!(Name, "name");
+
+    // https://html.spec.whatwg.org/multipage/#dom-slot-name
+    make_setter!(SetName, "name");
+
+    // https://html.spec.whatwg.org/multipage/#dom-slot-assignednodes
+    fn AssignedNodes(&self, options: &AssignedNodesOptions) -> Vec<DomRoot<Node>> {
+        if options.flatten {
+            self.find_flattened_slotables()
+        } else {
+            self.assigned_nodes()
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-slot-assignedelements
+    fn AssignedElements(&self, options: &AssignedNodesOptions) -> Vec<DomRoot<Element>> {
+        self.AssignedNodes(options).into_iter().filter_map(DomRoot::downcast::<Element>).collect()
+    }
+}
+
+impl VirtualMethods for HTMLSlotElement {
+    fn super_type(&self) -> Option<&VirtualMethods> {
+        Some(self.upcast::<HTMLElement>() as &VirtualMethods)
+    }
+
+    fn attribute_mutated(&self, attr: &Attr, mutation: AttributeMutation) {
+        self.super_type().unwrap().attribute_mutated(attr, mutation);
+        if attr.local_name() == &local_name!("name") {
+            // https://dom.spec.whatwg.org/#slot-name
+            let root = self.upcast::<Node>().inclusive_ancestors().last().unwrap();
+            if root.is::<ShadowRoot>() {
+                HTMLSlotElement::assign_slotables_for_a_tree(&root);
+            }
+        }
+    }
+}
+
+#[allow(unsafe_code)]
+pub trait LayoutHTMLSlotElementHelpers {
+    unsafe fn first_assigned_node_for_layout(&self) -> Option<LayoutDom<Node>>;
+}
+
+#[allow(unsafe_code)]
+impl LayoutHTMLSlotElementHelpers for LayoutDom<HTMLSlotElement> {
+    #[inline]
+    unsafe fn first_assigned_node_for_layout(&self) -> Option<LayoutDom<Node>> {
+        (*self.unsafe_get()).assigned_nodes.borrow_for_layout().first().map(|node| node.to_layout())
+    }
+}
@@ -20,6 +20,7 @@ use dom::virtualmethods::VirtualMethods;
 use dom_struct::dom_struct;
 use html5ever::{LocalName, Prefix};
 use net_traits::ReferrerPolicy;
+use net_traits::csp::InlineCheckType;
 use servo_arc::Arc;
 use std::cell::Cell;
 use style::media_queries::parse_media_query_list;
@@ -84,6 +85,14 @@ impl HTMLStyleElement {
         };
 
         let data = node.GetTextContent().expect("Element.textContent must be a string");
+
+        // https://html.spec.whatwg.org/multipage/#update-a-style-block
+        // Step 5.
+        let nonce = element.get_string_attribute(&local_name!("nonce"));
+        if doc.should_inline_be_blocked(InlineCheckType::Style, Some(&*nonce), &data) {
+            return;
+        }
+
         let url = window.get_url();
         let context = CssParserContext::new_for_cssom(&url,
                                                       Some(CssRuleType::Media),
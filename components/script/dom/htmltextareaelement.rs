@@ -24,6 +24,7 @@ use dom::node::{ChildrenMutation, Node, NodeDamage, UnbindContext};
 use dom::node::{document_from_node, window_from_node};
 use dom::nodelist::NodeList;
 use dom::validation::Validatable;
+use dom::validitystate::{ValidationFlags, ValidityState};
 use dom::virtualmethods::VirtualMethods;
 use dom_struct::dom_struct;
 use html5ever::{LocalName, Prefix};
@@ -43,7 +44,10 @@ pub struct HTMLTextAreaElement {
     placeholder: DomRefCell<DOMString>,
     // https://html.spec.whatwg.org/multipage/#concept-textarea-dirty
     value_changed: Cell<bool>,
+    // Whether the value was last changed by a user edit, as opposed to a script.
+    value_changed_by_user: Cell<bool>,
     form_owner: MutNullableDom<HTMLFormElement>,
+    validity_state: MutNullableDom<ValidityState>,
 }
 
 pub trait LayoutHTMLTextAreaElementHelpers {
@@ -104,6 +108,9 @@ static DEFAULT_COLS: u32 = 20;
 // https://html.spec.whatwg.org/multipage/#attr-textarea-rows-value
 static DEFAULT_ROWS: u32 = 2;
 
+static DEFAULT_MAX_LENGTH: i32 = -1;
+static DEFAULT_MIN_LENGTH: i32 = -1;
+
 impl HTMLTextAreaElement {
     fn new_inherited(local_name: LocalName,
                      prefix: Option<Prefix>,
@@ -112,13 +119,16 @@ impl HTMLTextAreaElement {
         HTMLTextAreaElement {
             htmlelement:
                 HTMLElement::new_inherited_with_state(ElementState::IN_ENABLED_STATE |
-                                                      ElementState::IN_READ_WRITE_STATE,
+                                                      ElementState::IN_READ_WRITE_STATE |
+                                                      ElementState::IN_VALID_STATE,
                                                       local_name, prefix, document),
             placeholder: DomRefCell::new(DOMString::new()),
             textinput: DomRefCell::new(TextInput::new(
                     Lines::Multiple, DOMString::new(), chan, None, None, SelectionDirection::None)),
             value_changed: Cell::new(false),
+            value_changed_by_user: Cell::new(false),
             form_owner: Default::default(),
+            validity_state: Default::default(),
         }
     }
 
@@ -161,6 +171,18 @@ impl HTMLTextAreaElementMethods for HTMLTextAreaElement {
         self.form_owner()
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-textarea-maxlength
+    make_int_getter!(MaxLength, "maxlength", DEFAULT_MAX_LENGTH);
+
+    // https://html.spec.whatwg.org/multipage/#dom-textarea-maxlength
+    make_limited_int_setter!(SetMaxLength, "maxlength", DEFAULT_MAX_LENGTH);
+
+    // https://html.spec.whatwg.org/multipage/#dom-textarea-minlength
+    make_int_getter!(MinLength, "minlength", DEFAULT_MIN_LENGTH);
+
+    // https://html.spec.whatwg.org/multipage/#dom-textarea-minlength
+    make_limited_int_setter!(SetMinLength, "minlength", DEFAULT_MIN_LENGTH);
+
     // https://html.spec.whatwg.org/multipage/#attr-fe-name
     make_getter!(Name, "name");
 
@@ -228,10 +250,42 @@ impl HTMLTextAreaElementMethods for HTMLTextAreaElement {
         // TODO move the cursor to the end of the field
         self.textinput.borrow_mut().set_content(value);
         self.value_changed.set(true);
+        self.value_changed_by_user.set(false);
+        self.update_validity_state();
 
         self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-cva-willvalidate
+    fn WillValidate(&self) -> bool {
+        self.is_instance_validatable()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-validity
+    fn Validity(&self) -> DomRoot<ValidityState> {
+        self.validity_state()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-validationmessage
+    fn ValidationMessage(&self) -> DOMString {
+        self.validation_message()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-checkvalidity
+    fn CheckValidity(&self) -> bool {
+        self.check_validity()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-reportvalidity
+    fn ReportValidity(&self) -> bool {
+        self.report_validity()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-setcustomvalidity
+    fn SetCustomValidity(&self, error: DOMString) {
+        self.validity_state().set_custom_error_message(error);
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-lfe-labels
     fn Labels(&self) -> DomRoot<NodeList> {
         self.upcast::<HTMLElement>().labels()
@@ -294,6 +348,12 @@ impl HTMLTextAreaElement {
         self.SetValue(self.DefaultValue());
         self.value_changed.set(false);
     }
+
+    // https://html.spec.whatwg.org/multipage/#concept-fe-mutable
+    fn is_mutable(&self) -> bool {
+        // https://html.spec.whatwg.org/multipage/#the-textarea-element:concept-fe-mutable
+        !(self.upcast::<Element>().disabled_state() || self.ReadOnly())
+    }
 }
 
 
@@ -346,11 +406,27 @@ impl VirtualMethods for HTMLTextAreaElement {
                     }
                 }
             },
+            local_name!("maxlength") => {
+                let max_length = mutation.new_value(attr).and_then(|value| match *value {
+                    AttrValue::Int(_, length) if length >= 0 => Some(length as usize),
+                    _ => None,
+                });
+                self.textinput.borrow_mut().max_length = max_length;
+            },
+            local_name!("minlength") => {
+                let min_length = mutation.new_value(attr).and_then(|value| match *value {
+                    AttrValue::Int(_, length) if length >= 0 => Some(length as usize),
+                    _ => None,
+                });
+                self.textinput.borrow_mut().min_length = min_length;
+            },
             local_name!("form") => {
                 self.form_attribute_mutated(mutation);
             },
             _ => {},
         }
+
+        self.update_validity_state();
     }
 
     fn bind_to_tree(&self, tree_in_doc: bool) {
@@ -359,12 +435,15 @@ impl VirtualMethods for HTMLTextAreaElement {
         }
 
         self.upcast::<Element>().check_ancestors_disabled_state_for_form_control();
+        self.update_validity_state();
     }
 
     fn parse_plain_attribute(&self, name: &LocalName, value: DOMString) -> AttrValue {
         match *name {
             local_name!("cols") => AttrValue::from_limited_u32(value.into(), DEFAULT_COLS),
             local_name!("rows") => AttrValue::from_limited_u32(value.into(), DEFAULT_ROWS),
+            local_name!("maxlength") => AttrValue::from_limited_i32(value.into(), DEFAULT_MAX_LENGTH),
+            local_name!("minlength") => AttrValue::from_limited_i32(value.into(), DEFAULT_MIN_LENGTH),
             _ => self.super_type().unwrap().parse_plain_attribute(name, value),
         }
     }
@@ -379,6 +458,7 @@ impl VirtualMethods for HTMLTextAreaElement {
         } else {
             el.check_disabled_attribute();
         }
+        self.update_validity_state();
     }
 
     fn children_changed(&self, mutation: &ChildrenMutation) {
@@ -409,7 +489,9 @@ impl VirtualMethods for HTMLTextAreaElement {
                     KeyReaction::TriggerDefaultAction => (),
                     KeyReaction::DispatchInput => {
                         self.value_changed.set(true);
+                        self.value_changed_by_user.set(true);
                         self.update_placeholder_shown_state();
+                        self.update_validity_state();
                         self.upcast::<Node>().dirty(NodeDamage::OtherNodeDamage);
                         event.mark_as_handled();
                     }
@@ -455,5 +537,48 @@ impl FormControl for HTMLTextAreaElement {
     }
 }
 
+impl Validatable for HTMLTextAreaElement {
+    fn as_element(&self) -> &Element {
+        self.upcast()
+    }
+
+    fn validity_state(&self) -> DomRoot<ValidityState> {
+        self.validity_state.or_init(|| ValidityState::new(&window_from_node(self), self.upcast()))
+    }
+
+    // https://html.spec.whatwg.org/multipage/#candidate-for-constraint-validation
+    fn is_instance_validatable(&self) -> bool {
+        // https://html.spec.whatwg.org/multipage/#the-textarea-element:barred-from-constraint-validation
+        !self.upcast::<Element>().disabled_state() && !self.ReadOnly()
+    }
+
+    fn perform_validation(&self, validate_flags: ValidationFlags) -> ValidationFlags {
+        let mut failed_flags = ValidationFlags::empty();
+        let value = self.Value();
+
+        // https://html.spec.whatwg.org/multipage/#the-textarea-element:suffering-from-being-missing
+        if validate_flags.contains(ValidationFlags::VALUE_MISSING) &&
+           self.Required() && self.is_mutable() && value.is_empty() {
+            failed_flags.insert(ValidationFlags::VALUE_MISSING);
+        }
 
-impl Validatable for HTMLTextAreaElement {}
+        // https://html.spec.whatwg.org/multipage/#suffering-from-being-too-long
+        // https://html.spec.whatwg.org/multipage/#suffering-from-being-too-short
+        // Only values that were last changed by a user edit are checked.
+        if !value.is_empty() && self.value_changed_by_user.get() {
+            let value_len = value.encode_utf16().count() as i32;
+            let max_length = self.MaxLength();
+            if validate_flags.contains(ValidationFlags::TOO_LONG) &&
+               max_length != DEFAULT_MAX_LENGTH && value_len > max_length {
+                failed_flags.insert(ValidationFlags::TOO_LONG);
+            }
+            let min_length = self.MinLength();
+            if validate_flags.contains(ValidationFlags::TOO_SHORT) &&
+               min_length != DEFAULT_MIN_LENGTH && value_len < min_length {
+                failed_flags.insert(ValidationFlags::TOO_SHORT);
+            }
+        }
+
+        failed_flags
+    }
+}
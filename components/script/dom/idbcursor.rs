@@ -0,0 +1,341 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::IDBCursorBinding;
+use dom::bindings::codegen::Bindings::IDBCursorBinding::IDBCursorDirection;
+use dom::bindings::codegen::Bindings::IDBCursorBinding::IDBCursorMethods;
+use dom::bindings::codegen::UnionTypes::IDBObjectStoreOrIDBIndex;
+use dom::bindings::error::{Error, ErrorResult, Fallible};
+use dom::bindings::reflector::{DomObject, Reflector, reflect_dom_object};
+use dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use dom::globalscope::GlobalScope;
+use dom::idbindex::IDBIndex;
+use dom::idbobjectstore::IDBObjectStore;
+use dom::idbrequest::{IDBRequest, IDBRequestKind, IDBRequestSource};
+use dom_struct::dom_struct;
+use indexed_db::{convert_value_to_key, extract_key, key_to_jsval};
+use js::jsapi::{HandleValue, Heap, JSContext};
+use js::jsval::{JSVal, UndefinedValue};
+use net_traits::indexeddb_thread::{IndexedDBCursorDirection, IndexedDBKey, IndexedDBKeyRange};
+use net_traits::indexeddb_thread::{IndexedDBOperation, IndexedDBRecord};
+use std::cell::Cell;
+
+#[dom_struct]
+pub struct IDBCursor {
+    reflector_: Reflector,
+    object_store: Dom<IDBObjectStore>,
+    index: Option<Dom<IDBIndex>>,
+    direction: IDBCursorDirection,
+    range: IndexedDBKeyRange,
+    /// Whether the cursor only iterates over keys, for `openKeyCursor`
+    key_only: bool,
+    request: MutNullableDom<IDBRequest>,
+    /// <https://w3c.github.io/IndexedDB/#cursor-key>
+    key: DomRefCell<Option<IndexedDBKey>>,
+    /// <https://w3c.github.io/IndexedDB/#cursor-primary-key>
+    primary_key: DomRefCell<Option<IndexedDBKey>>,
+    /// <https://w3c.github.io/IndexedDB/#cursor-got-value-flag>
+    got_value: Cell<bool>,
+    value: Heap<JSVal>,
+}
+
+impl IDBCursor {
+    pub fn new_inherited(object_store: &IDBObjectStore,
+                         index: Option<&IDBIndex>,
+                         range: IndexedDBKeyRange,
+                         direction: IDBCursorDirection,
+                         key_only: bool)
+                         -> IDBCursor {
+        IDBCursor {
+            reflector_: Reflector::new(),
+            object_store: Dom::from_ref(object_store),
+            index: index.map(Dom::from_ref),
+            direction: direction,
+            range: range,
+            key_only: key_only,
+            request: Default::default(),
+            key: DomRefCell::new(None),
+            primary_key: DomRefCell::new(None),
+            got_value: Cell::new(false),
+            value: Heap::default(),
+        }
+    }
+
+    pub fn new(global: &GlobalScope,
+               object_store: &IDBObjectStore,
+               index: Option<&IDBIndex>,
+               range: IndexedDBKeyRange,
+               direction: IDBCursorDirection,
+               key_only: bool)
+               -> DomRoot<IDBCursor> {
+        reflect_dom_object(Box::new(IDBCursor::new_inherited(object_store, index, range, direction, key_only)),
+                           global,
+                           IDBCursorBinding::Wrap)
+    }
+
+    pub fn object_store(&self) -> DomRoot<IDBObjectStore> {
+        DomRoot::from_ref(&*self.object_store)
+    }
+
+    pub fn set_request(&self, request: &IDBRequest) {
+        self.request.set(Some(request));
+    }
+
+    pub fn value(&self) -> JSVal {
+        self.value.get()
+    }
+
+    /// Moves the cursor to a record the storage thread found.
+    pub fn set_record(&self, record: IndexedDBRecord) {
+        if !self.key_only {
+            let global = self.global();
+            rooted!(in(global.get_cx()) let mut value = UndefinedValue());
+            self.object_store.read_value(record.value, &record.primary_key, value.handle_mut());
+            self.value.set(value.get());
+        }
+        *self.key.borrow_mut() = Some(record.key);
+        *self.primary_key.borrow_mut() = Some(record.primary_key);
+        self.got_value.set(true);
+    }
+
+    /// The operation moving the cursor past its current position, to `key` and
+    /// `primary_key` if given, `count` times
+    pub fn iterate(&self,
+                   key: Option<IndexedDBKey>,
+                   primary_key: Option<IndexedDBKey>,
+                   count: u32)
+                   -> IndexedDBOperation {
+        let position = match (self.key.borrow().clone(), self.primary_key.borrow().clone()) {
+            (Some(key), Some(primary_key)) => Some((key, primary_key)),
+            _ => None,
+        };
+        IndexedDBOperation::Iterate {
+            source: match self.index {
+                Some(ref index) => index.source(),
+                None => self.object_store.source(None),
+            },
+            range: self.range.clone(),
+            direction: match self.direction {
+                IDBCursorDirection::Next => IndexedDBCursorDirection::Next,
+                IDBCursorDirection::Nextunique => IndexedDBCursorDirection::NextUnique,
+                IDBCursorDirection::Prev => IndexedDBCursorDirection::Prev,
+                IDBCursorDirection::Prevunique => IndexedDBCursorDirection::PrevUnique,
+            },
+            position: position,
+            key: key,
+            primary_key: primary_key,
+            count: count,
+        }
+    }
+
+    /// Checks shared by the methods that move the cursor or modify its record
+    fn check_source(&self) -> ErrorResult {
+        match self.index {
+            Some(ref index) => index.check_active()?,
+            None => {
+                self.object_store.check_active()?;
+            },
+        }
+        if !self.got_value.get() {
+            return Err(Error::InvalidState);
+        }
+        Ok(())
+    }
+
+    /// Re-runs the cursor's request, to move it to its next record.
+    fn move_cursor(&self, key: Option<IndexedDBKey>, primary_key: Option<IndexedDBKey>, count: u32) {
+        self.got_value.set(false);
+        let request = self.request.get().expect("Cursor without a request");
+        let operation = self.iterate(key, primary_key, count);
+        self.object_store.transaction().add_request(&request, IDBRequestKind::Cursor, operation);
+    }
+
+    fn is_forward(&self) -> bool {
+        match self.direction {
+            IDBCursorDirection::Next | IDBCursorDirection::Nextunique => true,
+            IDBCursorDirection::Prev | IDBCursorDirection::Prevunique => false,
+        }
+    }
+}
+
+impl IDBCursorMethods for IDBCursor {
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-source
+    fn Source(&self) -> IDBObjectStoreOrIDBIndex {
+        match self.index {
+            Some(ref index) => IDBObjectStoreOrIDBIndex::IDBIndex(DomRoot::from_ref(&**index)),
+            None => IDBObjectStoreOrIDBIndex::IDBObjectStore(self.object_store()),
+        }
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-direction
+    fn Direction(&self) -> IDBCursorDirection {
+        self.direction
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-key
+    unsafe fn Key(&self, cx: *mut JSContext) -> JSVal {
+        rooted!(in(cx) let mut key = UndefinedValue());
+        if let Some(ref position) = *self.key.borrow() {
+            key_to_jsval(cx, position, key.handle_mut());
+        }
+        key.get()
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-primarykey
+    unsafe fn PrimaryKey(&self, cx: *mut JSContext) -> JSVal {
+        rooted!(in(cx) let mut key = UndefinedValue());
+        if let Some(ref position) = *self.primary_key.borrow() {
+            key_to_jsval(cx, position, key.handle_mut());
+        }
+        key.get()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-request
+    fn Request(&self) -> DomRoot<IDBRequest> {
+        self.request.get().expect("Cursor without a request")
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-advance
+    fn Advance(&self, count: u32) -> ErrorResult {
+        // Step 1.
+        if count == 0 {
+            return Err(Error::Type("The count must not be 0".to_owned()));
+        }
+
+        // Steps 2-5.
+        self.check_source()?;
+
+        // Steps 6-9.
+        self.move_cursor(None, None, count);
+        Ok(())
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-continue
+    unsafe fn Continue(&self, cx: *mut JSContext, key: HandleValue) -> ErrorResult {
+        // Steps 1-4.
+        self.check_source()?;
+
+        // Step 5.
+        let key = if key.is_undefined() {
+            None
+        } else {
+            let key = convert_value_to_key(cx, key, &mut vec![])?;
+            if let Some(ref position) = *self.key.borrow() {
+                if (self.is_forward() && key <= *position) || (!self.is_forward() && key >= *position) {
+                    return Err(Error::Data);
+                }
+            }
+            Some(key)
+        };
+
+        // Steps 6-9.
+        self.move_cursor(key, None, 1);
+        Ok(())
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-continueprimarykey
+    unsafe fn ContinuePrimaryKey(&self,
+                                 cx: *mut JSContext,
+                                 key: HandleValue,
+                                 primary_key: HandleValue)
+                                 -> ErrorResult {
+        // Steps 1-4.
+        self.check_source()?;
+
+        // Steps 5-6.
+        if self.index.is_none() {
+            return Err(Error::InvalidAccess);
+        }
+        match self.direction {
+            IDBCursorDirection::Next | IDBCursorDirection::Prev => {},
+            IDBCursorDirection::Nextunique | IDBCursorDirection::Prevunique => return Err(Error::InvalidAccess),
+        }
+
+        // Steps 8-11.
+        let key = convert_value_to_key(cx, key, &mut vec![])?;
+        let primary_key = convert_value_to_key(cx, primary_key, &mut vec![])?;
+        let position = (self.key.borrow().clone(), self.primary_key.borrow().clone());
+        if let (Some(position), Some(object_store_position)) = position {
+            let before = if self.is_forward() {
+                key < position || (key == position && primary_key <= object_store_position)
+            } else {
+                key > position || (key == position && primary_key >= object_store_position)
+            };
+            if before {
+                return Err(Error::Data);
+            }
+        }
+
+        // Steps 12-15.
+        self.move_cursor(Some(key), Some(primary_key), 1);
+        Ok(())
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-update
+    unsafe fn Update(&self, cx: *mut JSContext, value: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-2.
+        let info = self.object_store.check_active()?;
+        if self.object_store.transaction().is_read_only() {
+            return Err(Error::ReadOnly);
+        }
+
+        // Steps 3-4.
+        if self.index.as_ref().map_or(false, |index| index.info().is_none()) || !self.got_value.get() {
+            return Err(Error::InvalidState);
+        }
+        if self.key_only {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 7.
+        let primary_key = self.primary_key.borrow().clone();
+        if let Some(ref key_path) = info.key_path {
+            if extract_key(cx, value, key_path)? != primary_key {
+                return Err(Error::Data);
+            }
+        }
+
+        // Step 8.
+        self.object_store.store(cx,
+                                &info,
+                                IDBRequestSource::Cursor(Dom::from_ref(self)),
+                                value,
+                                primary_key,
+                                false)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-delete
+    fn Delete(&self) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-2.
+        self.object_store.check_active()?;
+        if self.object_store.transaction().is_read_only() {
+            return Err(Error::ReadOnly);
+        }
+
+        // Steps 3-5.
+        if self.index.as_ref().map_or(false, |index| index.info().is_none()) || !self.got_value.get() {
+            return Err(Error::InvalidState);
+        }
+        if self.key_only {
+            return Err(Error::InvalidState);
+        }
+        let primary_key = match *self.primary_key.borrow() {
+            Some(ref primary_key) => primary_key.clone(),
+            None => return Err(Error::InvalidState),
+        };
+
+        // Step 6.
+        let operation = IndexedDBOperation::Delete(self.object_store.name().to_string(),
+                                                   IndexedDBKeyRange::only(primary_key));
+        Ok(self.object_store.make_request(IDBRequestSource::Cursor(Dom::from_ref(self)),
+                                          IDBRequestKind::Undefined,
+                                          operation))
+    }
+}
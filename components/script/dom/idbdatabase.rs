@@ -0,0 +1,271 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::IDBDatabaseBinding;
+use dom::bindings::codegen::Bindings::IDBDatabaseBinding::IDBDatabaseMethods;
+use dom::bindings::codegen::Bindings::IDBDatabaseBinding::IDBObjectStoreParameters;
+use dom::bindings::codegen::Bindings::IDBTransactionBinding::IDBTransactionMethods;
+use dom::bindings::codegen::Bindings::IDBTransactionBinding::IDBTransactionMode;
+use dom::bindings::codegen::UnionTypes::StringOrStringSequence;
+use dom::bindings::error::{Error, ErrorResult, Fallible};
+use dom::bindings::inheritance::Castable;
+use dom::bindings::reflector::{DomObject, reflect_dom_object};
+use dom::bindings::root::{DomRoot, MutNullableDom};
+use dom::bindings::str::DOMString;
+use dom::domstringlist::DOMStringList;
+use dom::event::{Event, EventBubbles, EventCancelable};
+use dom::eventtarget::EventTarget;
+use dom::globalscope::GlobalScope;
+use dom::idbobjectstore::IDBObjectStore;
+use dom::idbtransaction::IDBTransaction;
+use dom::idbversionchangeevent::IDBVersionChangeEvent;
+use dom_struct::dom_struct;
+use indexed_db::{is_valid_key_path, key_path_from_idl};
+use net_traits::IpcSend;
+use net_traits::indexeddb_thread::{DatabaseInfo, IndexedDBOperation, IndexedDBThreadMsg, KeyPath, ObjectStoreInfo};
+use std::cell::Cell;
+
+#[dom_struct]
+pub struct IDBDatabase {
+    eventtarget: EventTarget,
+    name: DOMString,
+    /// The version and object stores of the database, as seen by this connection
+    info: DomRefCell<DatabaseInfo>,
+    /// The id of the connection in the storage thread
+    connection: u64,
+    /// <https://w3c.github.io/IndexedDB/#connection-close-pending-flag>
+    close_pending: Cell<bool>,
+    upgrade_transaction: MutNullableDom<IDBTransaction>,
+}
+
+impl IDBDatabase {
+    fn new_inherited(name: DOMString, info: DatabaseInfo, connection: u64) -> IDBDatabase {
+        IDBDatabase {
+            eventtarget: EventTarget::new_inherited(),
+            name: name,
+            info: DomRefCell::new(info),
+            connection: connection,
+            close_pending: Cell::new(false),
+            upgrade_transaction: Default::default(),
+        }
+    }
+
+    pub fn new(global: &GlobalScope, name: DOMString, info: DatabaseInfo, connection: u64) -> DomRoot<IDBDatabase> {
+        reflect_dom_object(Box::new(IDBDatabase::new_inherited(name, info, connection)),
+                           global,
+                           IDBDatabaseBinding::Wrap)
+    }
+
+    /// Fires a versionchange event, as another connection is upgrading or deleting the
+    /// database. Step 10.2 of <https://w3c.github.io/IndexedDB/#open-a-database>.
+    pub fn version_change(&self, old_version: u64, new_version: Option<u64>) {
+        if self.close_pending.get() {
+            return;
+        }
+        let event = IDBVersionChangeEvent::new(&self.global(),
+                                               atom!("versionchange"),
+                                               EventBubbles::DoesNotBubble,
+                                               EventCancelable::NotCancelable,
+                                               old_version,
+                                               new_version);
+        event.upcast::<Event>().fire(self.upcast());
+    }
+
+    pub fn info(&self) -> DatabaseInfo {
+        self.info.borrow().clone()
+    }
+
+    pub fn set_info(&self, info: DatabaseInfo) {
+        *self.info.borrow_mut() = info;
+    }
+
+    pub fn set_version(&self, version: u64) {
+        self.info.borrow_mut().version = version;
+    }
+
+    pub fn object_store_info(&self, name: &str) -> Option<ObjectStoreInfo> {
+        self.info.borrow().object_store(name).cloned()
+    }
+
+    /// Updates the schema of an object store, during an upgrade transaction.
+    pub fn set_object_store_info(&self, store: ObjectStoreInfo) {
+        let mut info = self.info.borrow_mut();
+        info.object_stores.retain(|existing| existing.name != store.name);
+        info.object_stores.push(store);
+    }
+
+    pub fn object_store_names(&self) -> Vec<DOMString> {
+        let mut names: Vec<DOMString> = self.info.borrow().object_stores.iter()
+            .map(|store| DOMString::from(store.name.clone()))
+            .collect();
+        names.sort();
+        names
+    }
+
+    pub fn set_upgrade_transaction(&self, transaction: Option<&IDBTransaction>) {
+        self.upgrade_transaction.set(transaction);
+    }
+
+    /// The upgrade transaction, if it is running and active
+    fn active_upgrade_transaction(&self) -> Fallible<DomRoot<IDBTransaction>> {
+        let transaction = match self.upgrade_transaction.get() {
+            Some(transaction) => transaction,
+            None => return Err(Error::InvalidState),
+        };
+        if !transaction.is_active() {
+            return Err(Error::TransactionInactive);
+        }
+        Ok(transaction)
+    }
+}
+
+impl IDBDatabaseMethods for IDBDatabase {
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-name
+    fn Name(&self) -> DOMString {
+        self.name.clone()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-version
+    fn Version(&self) -> u64 {
+        self.info.borrow().version
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-objectstorenames
+    fn ObjectStoreNames(&self) -> DomRoot<DOMStringList> {
+        DOMStringList::new(&self.global(), self.object_store_names())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-transaction
+    fn Transaction(&self,
+                   store_names: StringOrStringSequence,
+                   mode: IDBTransactionMode)
+                   -> Fallible<DomRoot<IDBTransaction>> {
+        // Step 1.
+        if self.upgrade_transaction.get().is_some() {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 2.
+        if self.close_pending.get() {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 3.
+        let mut scope = match store_names {
+            StringOrStringSequence::String(name) => vec![name],
+            StringOrStringSequence::StringSequence(names) => names,
+        };
+        scope.sort();
+        scope.dedup();
+
+        // Step 4.
+        {
+            let info = self.info.borrow();
+            if scope.iter().any(|name| info.object_store(name).is_none()) {
+                return Err(Error::NotFound);
+            }
+        }
+
+        // Step 5.
+        if scope.is_empty() {
+            return Err(Error::InvalidAccess);
+        }
+
+        // Step 6.
+        if mode == IDBTransactionMode::Versionchange {
+            return Err(Error::Type("Transactions cannot be created in versionchange mode".to_owned()));
+        }
+
+        // Steps 7-9.
+        Ok(IDBTransaction::new(&self.global(), self, mode, scope))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-close
+    fn Close(&self) {
+        // https://w3c.github.io/IndexedDB/#close-a-database-connection
+        if self.close_pending.replace(true) {
+            return;
+        }
+        // The storage thread runs the upgrades waiting for this connection to close once
+        // its transactions are finished.
+        let thread = IpcSend::<IndexedDBThreadMsg>::sender(self.global().resource_threads());
+        let _ = thread.send(IndexedDBThreadMsg::CloseConnection(self.connection));
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-createobjectstore
+    fn CreateObjectStore(&self,
+                         name: DOMString,
+                         options: &IDBObjectStoreParameters)
+                         -> Fallible<DomRoot<IDBObjectStore>> {
+        // Steps 2-4.
+        let transaction = self.active_upgrade_transaction()?;
+
+        // Steps 5-6.
+        let key_path = options.keyPath.as_ref().map(key_path_from_idl);
+        if let Some(ref key_path) = key_path {
+            if !is_valid_key_path(key_path) {
+                return Err(Error::Syntax);
+            }
+        }
+
+        // Step 7.
+        if self.info.borrow().object_store(&name).is_some() {
+            return Err(Error::Constraint);
+        }
+
+        // Step 8.
+        let auto_increment = options.autoIncrement;
+        match key_path {
+            Some(KeyPath::String(ref path)) if auto_increment && path.is_empty() => {
+                return Err(Error::InvalidAccess);
+            },
+            Some(KeyPath::Sequence(_)) if auto_increment => return Err(Error::InvalidAccess),
+            _ => {},
+        }
+
+        // Steps 9-10.
+        let info = ObjectStoreInfo {
+            name: String::from(name.clone()),
+            key_path: key_path,
+            auto_increment: auto_increment,
+            indexes: vec![],
+        };
+        self.set_object_store_info(info.clone());
+        transaction.change_schema(IndexedDBOperation::CreateObjectStore(info));
+        transaction.ObjectStore(name)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-deleteobjectstore
+    fn DeleteObjectStore(&self, name: DOMString) -> ErrorResult {
+        // Steps 2-4.
+        let transaction = self.active_upgrade_transaction()?;
+
+        // Step 5.
+        if self.info.borrow().object_store(&name).is_none() {
+            return Err(Error::NotFound);
+        }
+
+        // Steps 6-7.
+        if let Ok(store) = transaction.ObjectStore(name.clone()) {
+            store.set_deleted();
+        }
+        transaction.remove_object_store(&name);
+        self.info.borrow_mut().object_stores.retain(|store| *store.name != *name);
+        transaction.change_schema(IndexedDBOperation::DeleteObjectStore(name.into()));
+        Ok(())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-onabort
+    event_handler!(abort, GetOnabort, SetOnabort);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-onclose
+    event_handler!(close, GetOnclose, SetOnclose);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-onerror
+    event_handler!(error, GetOnerror, SetOnerror);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-onversionchange
+    event_handler!(versionchange, GetOnversionchange, SetOnversionchange);
+}
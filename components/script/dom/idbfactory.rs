@@ -0,0 +1,123 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::IDBFactoryBinding;
+use dom::bindings::codegen::Bindings::IDBFactoryBinding::IDBFactoryMethods;
+use dom::bindings::error::{Error, Fallible};
+use dom::bindings::refcounted::Trusted;
+use dom::bindings::reflector::{DomObject, Reflector, reflect_dom_object};
+use dom::bindings::root::DomRoot;
+use dom::bindings::str::DOMString;
+use dom::globalscope::GlobalScope;
+use dom::idbopendbrequest::IDBOpenDBRequest;
+use dom_struct::dom_struct;
+use indexed_db::{compare_keys, convert_value_to_key, route_from_indexeddb_thread, send_to_indexeddb_thread};
+use ipc_channel::ipc;
+use js::jsapi::{HandleValue, JSContext};
+use net_traits::IpcSend;
+use net_traits::indexeddb_thread::{IndexedDBConnectionEvent, IndexedDBOpenEvent, IndexedDBThreadMsg};
+
+#[dom_struct]
+pub struct IDBFactory {
+    reflector_: Reflector,
+}
+
+impl IDBFactory {
+    fn new_inherited() -> IDBFactory {
+        IDBFactory {
+            reflector_: Reflector::new(),
+        }
+    }
+
+    pub fn new(global: &GlobalScope) -> DomRoot<IDBFactory> {
+        reflect_dom_object(Box::new(IDBFactory::new_inherited()),
+                           global,
+                           IDBFactoryBinding::Wrap)
+    }
+}
+
+impl IDBFactoryMethods for IDBFactory {
+    // https://w3c.github.io/IndexedDB/#dom-idbfactory-open
+    fn Open(&self, name: DOMString, version: Option<u64>) -> Fallible<DomRoot<IDBOpenDBRequest>> {
+        // Step 1.
+        if version == Some(0) {
+            return Err(Error::Type("The version must not be 0".to_owned()));
+        }
+
+        // Steps 2-3.
+        let global = self.global();
+        let origin = global.origin().immutable().clone();
+        if !origin.is_tuple() {
+            return Err(Error::Security);
+        }
+
+        // Steps 4-5.
+        let request = IDBOpenDBRequest::new(&global);
+        let thread = IpcSend::<IndexedDBThreadMsg>::sender(global.resource_threads());
+        let database_name = String::from(name.clone());
+        let (sender, receiver) = ipc::channel().unwrap();
+        let trusted = Trusted::new(&*request);
+        let cancel_thread = thread.clone();
+        route_from_indexeddb_thread(&global, receiver, move |event: IndexedDBOpenEvent| {
+            trusted.root().process_open_event(name.clone(), event);
+        }, move |event| {
+            // Nothing can use a connection opened for a global that went away, so it must not
+            // keep other connections from upgrading the database.
+            match event {
+                IndexedDBOpenEvent::Opened(connection, _) => {
+                    let _ = cancel_thread.send(IndexedDBThreadMsg::CloseConnection(connection));
+                },
+                IndexedDBOpenEvent::Upgrade { connection, transaction, .. } => {
+                    let (sender, _) = ipc::channel().unwrap();
+                    let _ = cancel_thread.send(IndexedDBThreadMsg::Abort(sender, transaction));
+                    let _ = cancel_thread.send(IndexedDBThreadMsg::CloseConnection(connection));
+                },
+                IndexedDBOpenEvent::Blocked(..) | IndexedDBOpenEvent::Error(_) => {},
+            }
+        });
+        let (events_sender, events_receiver) = ipc::channel().unwrap();
+        let trusted = Trusted::new(&*request);
+        let cancel_thread = thread.clone();
+        route_from_indexeddb_thread(&global, events_receiver, move |event: IndexedDBConnectionEvent| {
+            trusted.root().process_connection_event(event);
+        }, move |event| {
+            let IndexedDBConnectionEvent::VersionChange { connection, .. } = event;
+            let _ = cancel_thread.send(IndexedDBThreadMsg::CloseConnection(connection));
+        });
+        let _ = thread.send(IndexedDBThreadMsg::Open(sender, origin, database_name, version, events_sender));
+
+        // Step 6.
+        Ok(request)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbfactory-deletedatabase
+    fn DeleteDatabase(&self, name: DOMString) -> Fallible<DomRoot<IDBOpenDBRequest>> {
+        // Steps 1-2.
+        let global = self.global();
+        let origin = global.origin().immutable().clone();
+        if !origin.is_tuple() {
+            return Err(Error::Security);
+        }
+
+        // Steps 3-4.
+        let request = IDBOpenDBRequest::new(&global);
+        let trusted = Trusted::new(&*request);
+        send_to_indexeddb_thread(&global, |sender| {
+            IndexedDBThreadMsg::DeleteDatabase(sender, origin, name.into())
+        }, move |version: u64| {
+            trusted.root().process_delete(version);
+        });
+
+        // Step 5.
+        Ok(request)
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbfactory-cmp
+    unsafe fn Cmp(&self, cx: *mut JSContext, first: HandleValue, second: HandleValue) -> Fallible<i16> {
+        let first = convert_value_to_key(cx, first, &mut vec![])?;
+        let second = convert_value_to_key(cx, second, &mut vec![])?;
+        Ok(compare_keys(&first, &second))
+    }
+}
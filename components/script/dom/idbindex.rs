@@ -0,0 +1,211 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::IDBCursorBinding::IDBCursorDirection;
+use dom::bindings::codegen::Bindings::IDBIndexBinding;
+use dom::bindings::codegen::Bindings::IDBIndexBinding::IDBIndexMethods;
+use dom::bindings::error::{Error, Fallible};
+use dom::bindings::reflector::{Reflector, reflect_dom_object};
+use dom::bindings::root::{Dom, DomRoot};
+use dom::bindings::str::DOMString;
+use dom::globalscope::GlobalScope;
+use dom::idbobjectstore::IDBObjectStore;
+use dom::idbrequest::{IDBRequest, IDBRequestKind, IDBRequestSource};
+use dom_struct::dom_struct;
+use indexed_db::{convert_value_to_key_range, key_path_to_jsval};
+use js::jsapi::{HandleValue, JSContext};
+use js::jsval::{JSVal, UndefinedValue};
+use net_traits::indexeddb_thread::{IndexInfo, IndexedDBKeyRange, IndexedDBOperation, IndexedDBSource};
+use std::cell::Cell;
+
+#[dom_struct]
+pub struct IDBIndex {
+    reflector_: Reflector,
+    name: DOMString,
+    object_store: Dom<IDBObjectStore>,
+    /// Whether the index was deleted by the upgrade transaction
+    deleted: Cell<bool>,
+}
+
+impl IDBIndex {
+    fn new_inherited(object_store: &IDBObjectStore, name: DOMString) -> IDBIndex {
+        IDBIndex {
+            reflector_: Reflector::new(),
+            name: name,
+            object_store: Dom::from_ref(object_store),
+            deleted: Cell::new(false),
+        }
+    }
+
+    pub fn new(global: &GlobalScope, object_store: &IDBObjectStore, name: DOMString) -> DomRoot<IDBIndex> {
+        reflect_dom_object(Box::new(IDBIndex::new_inherited(object_store, name)),
+                           global,
+                           IDBIndexBinding::Wrap)
+    }
+
+    pub fn object_store(&self) -> DomRoot<IDBObjectStore> {
+        DomRoot::from_ref(&*self.object_store)
+    }
+
+    pub fn set_deleted(&self) {
+        self.deleted.set(true);
+    }
+
+    /// The schema of the index, or `None` once it or its object store has been deleted
+    pub fn info(&self) -> Option<IndexInfo> {
+        if self.deleted.get() {
+            return None;
+        }
+        self.object_store.info().and_then(|info| {
+            info.indexes.into_iter().find(|index| *index.name == *self.name)
+        })
+    }
+
+    pub fn source(&self) -> IndexedDBSource {
+        self.object_store.source(Some(&self.name))
+    }
+
+    /// Checks that requests can be made against the index.
+    pub fn check_active(&self) -> Fallible<()> {
+        if self.info().is_none() {
+            return Err(Error::InvalidState);
+        }
+        self.object_store.check_active().map(|_| ())
+    }
+
+    /// A request reading the records of the index
+    #[allow(unsafe_code)]
+    unsafe fn read_request(&self,
+                           cx: *mut JSContext,
+                           query: HandleValue,
+                           null_disallowed: bool,
+                           kind: IDBRequestKind,
+                           operation: fn(IndexedDBSource, IndexedDBKeyRange) -> IndexedDBOperation)
+                           -> Fallible<DomRoot<IDBRequest>> {
+        self.check_active()?;
+        let range = convert_value_to_key_range(cx, query, null_disallowed)?;
+        Ok(self.object_store.make_request(IDBRequestSource::Index(Dom::from_ref(self)),
+                                          kind,
+                                          operation(self.source(), range)))
+    }
+
+    #[allow(unsafe_code)]
+    unsafe fn get_all(&self,
+                      cx: *mut JSContext,
+                      query: HandleValue,
+                      count: Option<u32>,
+                      kind: IDBRequestKind)
+                      -> Fallible<DomRoot<IDBRequest>> {
+        self.check_active()?;
+        let range = convert_value_to_key_range(cx, query, false)?;
+        Ok(self.object_store.make_request(IDBRequestSource::Index(Dom::from_ref(self)),
+                                          kind,
+                                          IndexedDBOperation::GetAll(self.source(), range, count)))
+    }
+
+    #[allow(unsafe_code)]
+    unsafe fn open_cursor(&self,
+                          cx: *mut JSContext,
+                          query: HandleValue,
+                          direction: IDBCursorDirection,
+                          key_only: bool)
+                          -> Fallible<DomRoot<IDBRequest>> {
+        self.check_active()?;
+        let range = convert_value_to_key_range(cx, query, false)?;
+        Ok(self.object_store.open_cursor(IDBRequestSource::Index(Dom::from_ref(self)),
+                                         Some(self),
+                                         range,
+                                         direction,
+                                         key_only))
+    }
+}
+
+impl IDBIndexMethods for IDBIndex {
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-name
+    fn Name(&self) -> DOMString {
+        self.name.clone()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-objectstore
+    fn ObjectStore(&self) -> DomRoot<IDBObjectStore> {
+        self.object_store()
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-keypath
+    unsafe fn KeyPath(&self, cx: *mut JSContext) -> Fallible<JSVal> {
+        rooted!(in(cx) let mut key_path = UndefinedValue());
+        let info = self.info();
+        key_path_to_jsval(cx, info.as_ref().map(|info| &info.key_path), key_path.handle_mut());
+        Ok(key_path.get())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-multientry
+    fn MultiEntry(&self) -> bool {
+        self.info().map_or(false, |info| info.multi_entry)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-unique
+    fn Unique(&self) -> bool {
+        self.info().map_or(false, |info| info.unique)
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-get
+    unsafe fn Get(&self, cx: *mut JSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.read_request(cx, query, true, IDBRequestKind::Value, IndexedDBOperation::Get)
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-getkey
+    unsafe fn GetKey(&self, cx: *mut JSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.read_request(cx, query, true, IDBRequestKind::PrimaryKey, IndexedDBOperation::Get)
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-getall
+    unsafe fn GetAll(&self,
+                     cx: *mut JSContext,
+                     query: HandleValue,
+                     count: Option<u32>)
+                     -> Fallible<DomRoot<IDBRequest>> {
+        self.get_all(cx, query, count, IDBRequestKind::Values)
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-getallkeys
+    unsafe fn GetAllKeys(&self,
+                         cx: *mut JSContext,
+                         query: HandleValue,
+                         count: Option<u32>)
+                         -> Fallible<DomRoot<IDBRequest>> {
+        self.get_all(cx, query, count, IDBRequestKind::PrimaryKeys)
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-count
+    unsafe fn Count(&self, cx: *mut JSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.read_request(cx, query, false, IDBRequestKind::Count, IndexedDBOperation::Count)
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-opencursor
+    unsafe fn OpenCursor(&self,
+                         cx: *mut JSContext,
+                         query: HandleValue,
+                         direction: IDBCursorDirection)
+                         -> Fallible<DomRoot<IDBRequest>> {
+        self.open_cursor(cx, query, direction, false)
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-openkeycursor
+    unsafe fn OpenKeyCursor(&self,
+                            cx: *mut JSContext,
+                            query: HandleValue,
+                            direction: IDBCursorDirection)
+                            -> Fallible<DomRoot<IDBRequest>> {
+        self.open_cursor(cx, query, direction, true)
+    }
+}
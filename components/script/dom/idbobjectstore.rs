@@ -0,0 +1,520 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::IDBCursorBinding::IDBCursorDirection;
+use dom::bindings::codegen::Bindings::IDBObjectStoreBinding;
+use dom::bindings::codegen::Bindings::IDBObjectStoreBinding::IDBIndexParameters;
+use dom::bindings::codegen::Bindings::IDBObjectStoreBinding::IDBObjectStoreMethods;
+use dom::bindings::codegen::UnionTypes::StringOrStringSequence;
+use dom::bindings::error::{Error, ErrorResult, Fallible};
+use dom::bindings::reflector::{DomObject, Reflector, reflect_dom_object};
+use dom::bindings::root::{Dom, DomRoot};
+use dom::bindings::str::DOMString;
+use dom::bindings::structuredclone::StructuredCloneData;
+use dom::domstringlist::DOMStringList;
+use dom::globalscope::GlobalScope;
+use dom::idbcursor::IDBCursor;
+use dom::idbcursorwithvalue::IDBCursorWithValue;
+use dom::idbindex::IDBIndex;
+use dom::idbrequest::{IDBRequest, IDBRequestKind, IDBRequestSource};
+use dom::idbtransaction::IDBTransaction;
+use dom_struct::dom_struct;
+use indexed_db::{convert_value_to_key, convert_value_to_key_range, could_inject_key, extract_index_keys};
+use indexed_db::{extract_key, inject_key_into_value, is_valid_key_path, key_path_from_idl, key_path_to_jsval};
+use indexed_db::send_to_indexeddb_thread_sync;
+use js::jsapi::{HandleValue, JSContext, MutableHandleValue};
+use js::jsval::{JSVal, UndefinedValue};
+use net_traits::indexeddb_thread::{IndexInfo, IndexedDBKey, IndexedDBKeyRange, IndexedDBOperation};
+use net_traits::indexeddb_thread::{IndexedDBReply, IndexedDBSource, IndexedDBThreadMsg, KeyPath, ObjectStoreInfo};
+use std::cell::Cell;
+use std::collections::HashMap;
+
+#[dom_struct]
+pub struct IDBObjectStore {
+    reflector_: Reflector,
+    name: DOMString,
+    transaction: Dom<IDBTransaction>,
+    /// Whether the object store was deleted by the upgrade transaction
+    deleted: Cell<bool>,
+    indexes: DomRefCell<HashMap<DOMString, Dom<IDBIndex>>>,
+}
+
+impl IDBObjectStore {
+    fn new_inherited(transaction: &IDBTransaction, name: DOMString) -> IDBObjectStore {
+        IDBObjectStore {
+            reflector_: Reflector::new(),
+            name: name,
+            transaction: Dom::from_ref(transaction),
+            deleted: Cell::new(false),
+            indexes: DomRefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn new(global: &GlobalScope, transaction: &IDBTransaction, name: DOMString) -> DomRoot<IDBObjectStore> {
+        reflect_dom_object(Box::new(IDBObjectStore::new_inherited(transaction, name)),
+                           global,
+                           IDBObjectStoreBinding::Wrap)
+    }
+
+    pub fn name(&self) -> &DOMString {
+        &self.name
+    }
+
+    pub fn transaction(&self) -> DomRoot<IDBTransaction> {
+        DomRoot::from_ref(&*self.transaction)
+    }
+
+    pub fn set_deleted(&self) {
+        self.deleted.set(true);
+    }
+
+    /// The schema of the object store, or `None` once it has been deleted
+    pub fn info(&self) -> Option<ObjectStoreInfo> {
+        if self.deleted.get() {
+            return None;
+        }
+        self.transaction.db().object_store_info(&self.name)
+    }
+
+    /// Checks that requests can be made against the object store, returning its schema.
+    pub fn check_active(&self) -> Fallible<ObjectStoreInfo> {
+        let info = match self.info() {
+            Some(info) => info,
+            None => return Err(Error::InvalidState),
+        };
+        if !self.transaction.is_active() {
+            return Err(Error::TransactionInactive);
+        }
+        Ok(info)
+    }
+
+    /// Like `check_active`, for requests that modify the object store
+    fn check_writable(&self) -> Fallible<ObjectStoreInfo> {
+        let info = self.check_active()?;
+        if self.transaction.is_read_only() {
+            return Err(Error::ReadOnly);
+        }
+        Ok(info)
+    }
+
+    pub fn source(&self, index: Option<&DOMString>) -> IndexedDBSource {
+        IndexedDBSource {
+            object_store: self.name.to_string(),
+            index: index.map(|index| index.to_string()),
+        }
+    }
+
+    /// Creates a request against `source`, and runs `operation` for it in the transaction.
+    #[allow(unrooted_must_root)]
+    pub fn make_request(&self,
+                        source: IDBRequestSource,
+                        kind: IDBRequestKind,
+                        operation: IndexedDBOperation)
+                        -> DomRoot<IDBRequest> {
+        let request = IDBRequest::new(&self.global(), source, &self.transaction);
+        self.transaction.add_request(&request, kind, operation);
+        request
+    }
+
+    /// Opens a cursor over the records of the object store, or of one of its indexes.
+    #[allow(unrooted_must_root)]
+    pub fn open_cursor(&self,
+                       source: IDBRequestSource,
+                       index: Option<&IDBIndex>,
+                       range: IndexedDBKeyRange,
+                       direction: IDBCursorDirection,
+                       key_only: bool)
+                       -> DomRoot<IDBRequest> {
+        let global = self.global();
+        let cursor = if key_only {
+            IDBCursor::new(&global, self, index, range.clone(), direction, true)
+        } else {
+            DomRoot::upcast(IDBCursorWithValue::new(&global, self, index, range.clone(), direction))
+        };
+        let request = IDBRequest::new(&global, source, &self.transaction);
+        request.set_cursor(&cursor);
+        cursor.set_request(&request);
+        self.transaction.add_request(&request, IDBRequestKind::Cursor, cursor.iterate(None, None, 1));
+        request
+    }
+
+    /// Reads a value stored in the object store, injecting the key it was stored under if
+    /// the key was generated.
+    #[allow(unsafe_code)]
+    pub fn read_value(&self, value: Vec<u8>, primary_key: &IndexedDBKey, rval: MutableHandleValue) {
+        let global = self.global();
+        StructuredCloneData::Vector(value).read(&global, rval);
+        if let Some(ObjectStoreInfo { key_path: Some(KeyPath::String(path)), auto_increment: true, .. }) =
+                self.transaction.db().object_store_info(&self.name) {
+            unsafe {
+                let _ = inject_key_into_value(global.get_cx(), rval.handle(), primary_key, &path);
+            }
+        }
+    }
+
+    /// The index keys of a value, for every index of the object store
+    #[allow(unsafe_code)]
+    unsafe fn index_keys(cx: *mut JSContext,
+                         info: &ObjectStoreInfo,
+                         value: HandleValue)
+                         -> Fallible<Vec<(String, Vec<IndexedDBKey>)>> {
+        let mut index_keys = vec![];
+        for index in &info.indexes {
+            let keys = extract_index_keys(cx, value, &index.key_path, index.multi_entry)?;
+            index_keys.push((index.name.clone(), keys));
+        }
+        Ok(index_keys)
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#add-or-put>, from step 7 on
+    #[allow(unrooted_must_root, unsafe_code)]
+    pub unsafe fn store(&self,
+                        cx: *mut JSContext,
+                        info: &ObjectStoreInfo,
+                        source: IDBRequestSource,
+                        value: HandleValue,
+                        key: Option<IndexedDBKey>,
+                        no_overwrite: bool)
+                        -> Fallible<DomRoot<IDBRequest>> {
+        // Step 10.
+        let clone = StructuredCloneData::write(cx, value)?.move_to_arraybuffer();
+
+        // Step 11.
+        let key = match info.key_path {
+            Some(ref key_path) => {
+                match extract_key(cx, value, key_path)? {
+                    Some(key) => Some(key),
+                    None => {
+                        if !info.auto_increment {
+                            return Err(Error::Data);
+                        }
+                        let path = match *key_path {
+                            KeyPath::String(ref path) => path,
+                            KeyPath::Sequence(_) => return Err(Error::Data),
+                        };
+                        if !could_inject_key(cx, value, path)? {
+                            return Err(Error::Data);
+                        }
+                        None
+                    },
+                }
+            },
+            None => key,
+        };
+
+        // Steps 12-13.
+        let index_keys = IDBObjectStore::index_keys(cx, info, value)?;
+        let operation = IndexedDBOperation::Put {
+            object_store: info.name.clone(),
+            key: key,
+            value: clone,
+            index_keys: index_keys,
+            no_overwrite: no_overwrite,
+        };
+        Ok(self.make_request(source, IDBRequestKind::Key, operation))
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#add-or-put>
+    #[allow(unsafe_code)]
+    unsafe fn add_or_put(&self,
+                         cx: *mut JSContext,
+                         value: HandleValue,
+                         key: HandleValue,
+                         no_overwrite: bool)
+                         -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-6.
+        let info = self.check_writable()?;
+
+        // Steps 7-8.
+        if info.key_path.is_some() && !key.is_undefined() {
+            return Err(Error::Data);
+        }
+        if info.key_path.is_none() && !info.auto_increment && key.is_undefined() {
+            return Err(Error::Data);
+        }
+
+        // Step 9.
+        let key = if key.is_undefined() {
+            None
+        } else {
+            Some(convert_value_to_key(cx, key, &mut vec![])?)
+        };
+
+        self.store(cx, &info, IDBRequestSource::ObjectStore(Dom::from_ref(self)), value, key, no_overwrite)
+    }
+
+    /// A request reading the records of the object store
+    #[allow(unsafe_code)]
+    unsafe fn read_request(&self,
+                           cx: *mut JSContext,
+                           query: HandleValue,
+                           null_disallowed: bool,
+                           kind: IDBRequestKind,
+                           operation: fn(IndexedDBSource, IndexedDBKeyRange) -> IndexedDBOperation)
+                           -> Fallible<DomRoot<IDBRequest>> {
+        self.check_active()?;
+        let range = convert_value_to_key_range(cx, query, null_disallowed)?;
+        Ok(self.make_request(IDBRequestSource::ObjectStore(Dom::from_ref(self)),
+                             kind,
+                             operation(self.source(None), range)))
+    }
+}
+
+impl IDBObjectStoreMethods for IDBObjectStore {
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-name
+    fn Name(&self) -> DOMString {
+        self.name.clone()
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-keypath
+    unsafe fn KeyPath(&self, cx: *mut JSContext) -> Fallible<JSVal> {
+        rooted!(in(cx) let mut key_path = UndefinedValue());
+        let info = self.transaction.db().object_store_info(&self.name);
+        key_path_to_jsval(cx, info.as_ref().and_then(|info| info.key_path.as_ref()), key_path.handle_mut());
+        Ok(key_path.get())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-indexnames
+    fn IndexNames(&self) -> DomRoot<DOMStringList> {
+        let mut names: Vec<DOMString> = self.info().map_or(vec![], |info| {
+            info.indexes.into_iter().map(|index| DOMString::from(index.name)).collect()
+        });
+        names.sort();
+        DOMStringList::new(&self.global(), names)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-transaction
+    fn Transaction(&self) -> DomRoot<IDBTransaction> {
+        self.transaction()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-autoincrement
+    fn AutoIncrement(&self) -> bool {
+        self.transaction.db().object_store_info(&self.name).map_or(false, |info| info.auto_increment)
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-put
+    unsafe fn Put(&self, cx: *mut JSContext, value: HandleValue, key: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.add_or_put(cx, value, key, false)
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-add
+    unsafe fn Add(&self, cx: *mut JSContext, value: HandleValue, key: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.add_or_put(cx, value, key, true)
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-delete
+    unsafe fn Delete(&self, cx: *mut JSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.check_writable()?;
+        let range = convert_value_to_key_range(cx, query, true)?;
+        Ok(self.make_request(IDBRequestSource::ObjectStore(Dom::from_ref(self)),
+                             IDBRequestKind::Undefined,
+                             IndexedDBOperation::Delete(self.name.to_string(), range)))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-clear
+    fn Clear(&self) -> Fallible<DomRoot<IDBRequest>> {
+        self.check_writable()?;
+        Ok(self.make_request(IDBRequestSource::ObjectStore(Dom::from_ref(self)),
+                             IDBRequestKind::Undefined,
+                             IndexedDBOperation::Clear(self.name.to_string())))
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-get
+    unsafe fn Get(&self, cx: *mut JSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.read_request(cx, query, true, IDBRequestKind::Value, IndexedDBOperation::Get)
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-getkey
+    unsafe fn GetKey(&self, cx: *mut JSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.read_request(cx, query, true, IDBRequestKind::PrimaryKey, IndexedDBOperation::Get)
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-getall
+    unsafe fn GetAll(&self,
+                     cx: *mut JSContext,
+                     query: HandleValue,
+                     count: Option<u32>)
+                     -> Fallible<DomRoot<IDBRequest>> {
+        self.check_active()?;
+        let range = convert_value_to_key_range(cx, query, false)?;
+        Ok(self.make_request(IDBRequestSource::ObjectStore(Dom::from_ref(self)),
+                             IDBRequestKind::Values,
+                             IndexedDBOperation::GetAll(self.source(None), range, count)))
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-getallkeys
+    unsafe fn GetAllKeys(&self,
+                         cx: *mut JSContext,
+                         query: HandleValue,
+                         count: Option<u32>)
+                         -> Fallible<DomRoot<IDBRequest>> {
+        self.check_active()?;
+        let range = convert_value_to_key_range(cx, query, false)?;
+        Ok(self.make_request(IDBRequestSource::ObjectStore(Dom::from_ref(self)),
+                             IDBRequestKind::PrimaryKeys,
+                             IndexedDBOperation::GetAll(self.source(None), range, count)))
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-count
+    unsafe fn Count(&self, cx: *mut JSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.read_request(cx, query, false, IDBRequestKind::Count, IndexedDBOperation::Count)
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-opencursor
+    unsafe fn OpenCursor(&self,
+                         cx: *mut JSContext,
+                         query: HandleValue,
+                         direction: IDBCursorDirection)
+                         -> Fallible<DomRoot<IDBRequest>> {
+        self.check_active()?;
+        let range = convert_value_to_key_range(cx, query, false)?;
+        Ok(self.open_cursor(IDBRequestSource::ObjectStore(Dom::from_ref(self)), None, range, direction, false))
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-openkeycursor
+    unsafe fn OpenKeyCursor(&self,
+                            cx: *mut JSContext,
+                            query: HandleValue,
+                            direction: IDBCursorDirection)
+                            -> Fallible<DomRoot<IDBRequest>> {
+        self.check_active()?;
+        let range = convert_value_to_key_range(cx, query, false)?;
+        Ok(self.open_cursor(IDBRequestSource::ObjectStore(Dom::from_ref(self)), None, range, direction, true))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-index
+    fn Index(&self, name: DOMString) -> Fallible<DomRoot<IDBIndex>> {
+        // Steps 3-4.
+        let info = match self.info() {
+            Some(ref info) if !self.transaction.is_finished() => info.clone(),
+            _ => return Err(Error::InvalidState),
+        };
+
+        // Step 5.
+        if !info.indexes.iter().any(|index| *index.name == *name) {
+            return Err(Error::NotFound);
+        }
+
+        // Step 6.
+        let mut indexes = self.indexes.borrow_mut();
+        let index = indexes.entry(name.clone()).or_insert_with(|| {
+            Dom::from_ref(&*IDBIndex::new(&self.global(), self, name))
+        });
+        Ok(DomRoot::from_ref(&**index))
+    }
+
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-createindex
+    fn CreateIndex(&self,
+                   name: DOMString,
+                   key_path: StringOrStringSequence,
+                   options: &IDBIndexParameters)
+                   -> Fallible<DomRoot<IDBIndex>> {
+        // Steps 2-6.
+        if !self.transaction.is_upgrade() {
+            return Err(Error::InvalidState);
+        }
+        let mut info = match self.info() {
+            Some(info) => info,
+            None => return Err(Error::InvalidState),
+        };
+        if !self.transaction.is_active() {
+            return Err(Error::TransactionInactive);
+        }
+
+        // Step 7.
+        if info.indexes.iter().any(|index| *index.name == *name) {
+            return Err(Error::Constraint);
+        }
+
+        // Step 8.
+        let key_path = key_path_from_idl(&key_path);
+        if !is_valid_key_path(&key_path) {
+            return Err(Error::Syntax);
+        }
+
+        // Steps 9-10.
+        if let KeyPath::Sequence(_) = key_path {
+            if options.multiEntry {
+                return Err(Error::InvalidAccess);
+            }
+        }
+
+        // Steps 11-13.
+        let index = IndexInfo {
+            name: name.to_string(),
+            key_path: key_path,
+            unique: options.unique,
+            multi_entry: options.multiEntry,
+        };
+
+        // The storage thread cannot look into values, so the keys of the existing records
+        // are computed here. The upgrade transaction holds the database exclusively, so the
+        // records read are the ones the new index is created from.
+        let transaction_id = self.transaction.id();
+        let operation = IndexedDBOperation::GetAll(self.source(None), IndexedDBKeyRange::unbounded(), None);
+        let records = match send_to_indexeddb_thread_sync(&self.global(), |sender| {
+            IndexedDBThreadMsg::Operation(sender, transaction_id, operation)
+        }) {
+            Some(Ok(IndexedDBReply::Records(records))) => records,
+            _ => vec![],
+        };
+        let global = self.global();
+        let cx = global.get_cx();
+        let mut keys = vec![];
+        for record in records {
+            rooted!(in(cx) let mut value = UndefinedValue());
+            self.read_value(record.value, &record.primary_key, value.handle_mut());
+            let index_keys = unsafe { extract_index_keys(cx, value.handle(), &index.key_path, index.multi_entry)? };
+            keys.push((record.primary_key, index_keys));
+        }
+
+        info.indexes.push(index.clone());
+        self.transaction.db().set_object_store_info(info);
+        self.transaction.change_schema(IndexedDBOperation::CreateIndex(self.name.to_string(), index, keys));
+        self.Index(name)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-deleteindex
+    fn DeleteIndex(&self, name: DOMString) -> ErrorResult {
+        // Steps 2-6.
+        if !self.transaction.is_upgrade() {
+            return Err(Error::InvalidState);
+        }
+        let mut info = match self.info() {
+            Some(info) => info,
+            None => return Err(Error::InvalidState),
+        };
+        if !self.transaction.is_active() {
+            return Err(Error::TransactionInactive);
+        }
+
+        // Step 7.
+        if !info.indexes.iter().any(|index| *index.name == *name) {
+            return Err(Error::NotFound);
+        }
+
+        // Steps 8-10.
+        if let Some(index) = self.indexes.borrow_mut().remove(&name) {
+            index.set_deleted();
+        }
+        info.indexes.retain(|index| *index.name != *name);
+        self.transaction.db().set_object_store_info(info);
+        self.transaction.change_schema(IndexedDBOperation::DeleteIndex(self.name.to_string(), name.into()));
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::IDBOpenDBRequestBinding;
+use dom::bindings::codegen::Bindings::IDBOpenDBRequestBinding::IDBOpenDBRequestMethods;
+use dom::bindings::inheritance::Castable;
+use dom::bindings::reflector::{DomObject, reflect_dom_object};
+use dom::bindings::root::{DomRoot, MutNullableDom};
+use dom::bindings::str::DOMString;
+use dom::domexception::{DOMErrorName, DOMException};
+use dom::event::{Event, EventBubbles, EventCancelable};
+use dom::eventtarget::EventTarget;
+use dom::globalscope::GlobalScope;
+use dom::idbdatabase::IDBDatabase;
+use dom::idbrequest::IDBRequest;
+use dom::idbtransaction::IDBTransaction;
+use dom::idbversionchangeevent::IDBVersionChangeEvent;
+use dom_struct::dom_struct;
+use js::jsapi::HandleValue;
+use js::jsval::ObjectValue;
+use net_traits::IpcSend;
+use net_traits::indexeddb_thread::{DatabaseInfo, IndexedDBConnectionEvent, IndexedDBOpenEvent, IndexedDBThreadMsg};
+
+#[dom_struct]
+pub struct IDBOpenDBRequest {
+    request: IDBRequest,
+    /// The connection this request opened
+    connection: MutNullableDom<IDBDatabase>,
+}
+
+impl IDBOpenDBRequest {
+    fn new_inherited() -> IDBOpenDBRequest {
+        IDBOpenDBRequest {
+            request: IDBRequest::new_inherited(None, None),
+            connection: Default::default(),
+        }
+    }
+
+    pub fn new(global: &GlobalScope) -> DomRoot<IDBOpenDBRequest> {
+        reflect_dom_object(Box::new(IDBOpenDBRequest::new_inherited()),
+                           global,
+                           IDBOpenDBRequestBinding::Wrap)
+    }
+
+    /// Steps 5 and on of <https://w3c.github.io/IndexedDB/#open-a-database>, run as the
+    /// storage thread processes the request. The version check and the upgrade are made
+    /// there, so that no other request can change the version in between.
+    pub fn process_open_event(&self, name: DOMString, event: IndexedDBOpenEvent) {
+        let global = self.global();
+        match event {
+            IndexedDBOpenEvent::Blocked(old_version, new_version) => {
+                let event = IDBVersionChangeEvent::new(&global,
+                                                       atom!("blocked"),
+                                                       EventBubbles::DoesNotBubble,
+                                                       EventCancelable::NotCancelable,
+                                                       old_version,
+                                                       Some(new_version));
+                event.upcast::<Event>().fire(self.upcast());
+            },
+            IndexedDBOpenEvent::Error(error) => {
+                self.request.set_error_from_reply(error);
+                self.upcast::<EventTarget>().fire_bubbling_cancelable_event(atom!("error"));
+            },
+            IndexedDBOpenEvent::Opened(connection, info) => {
+                self.open_connection(name, connection, info);
+                self.upcast::<EventTarget>().fire_event(atom!("success"));
+            },
+            IndexedDBOpenEvent::Upgrade { connection, transaction, version, info } => {
+                // https://w3c.github.io/IndexedDB/#upgrade-a-database
+                let old_version = info.version;
+                let database = self.open_connection(name, connection, info);
+                let transaction = IDBTransaction::new_upgrade(&global, &database, transaction, version, self);
+                self.request.set_transaction(Some(&transaction));
+                let event = IDBVersionChangeEvent::new(&global,
+                                                       atom!("upgradeneeded"),
+                                                       EventBubbles::DoesNotBubble,
+                                                       EventCancelable::NotCancelable,
+                                                       old_version,
+                                                       Some(version));
+                transaction.dispatch_while_active(event.upcast::<Event>(), self.upcast());
+            },
+        }
+    }
+
+    fn open_connection(&self, name: DOMString, connection: u64, info: DatabaseInfo) -> DomRoot<IDBDatabase> {
+        let global = self.global();
+        let database = IDBDatabase::new(&global, name, info, connection);
+        rooted!(in(global.get_cx()) let result = ObjectValue(database.reflector().get_jsobject().get()));
+        self.request.set_result(result.handle());
+        self.connection.set(Some(&database));
+        database
+    }
+
+    /// Handles an event the storage thread sent to the connection this request opened.
+    pub fn process_connection_event(&self, event: IndexedDBConnectionEvent) {
+        let IndexedDBConnectionEvent::VersionChange { connection, old_version, new_version } = event;
+        if let Some(database) = self.connection.get() {
+            database.version_change(old_version, new_version);
+        }
+        // The request that sent the event waits for it to be handled before reporting it is
+        // blocked by the connections that stayed open.
+        let thread = IpcSend::<IndexedDBThreadMsg>::sender(self.global().resource_threads());
+        let _ = thread.send(IndexedDBThreadMsg::VersionChangeFired(connection));
+    }
+
+    /// Finishes opening the database once its upgrade transaction is finished.
+    pub fn upgrade_finished(&self, aborted: bool) {
+        self.request.set_transaction(None);
+        if aborted {
+            self.request.set_error(&DOMException::new(&self.global(), DOMErrorName::AbortError));
+            self.upcast::<EventTarget>().fire_bubbling_cancelable_event(atom!("error"));
+        } else {
+            self.upcast::<EventTarget>().fire_event(atom!("success"));
+        }
+    }
+
+    /// Steps 4 and on of <https://w3c.github.io/IndexedDB/#dom-idbfactory-deletedatabase>
+    pub fn process_delete(&self, version: u64) {
+        self.request.set_result(HandleValue::undefined());
+        let event = IDBVersionChangeEvent::new(&self.global(),
+                                               atom!("success"),
+                                               EventBubbles::DoesNotBubble,
+                                               EventCancelable::NotCancelable,
+                                               version,
+                                               None);
+        event.upcast::<Event>().fire(self.upcast());
+    }
+}
+
+impl IDBOpenDBRequestMethods for IDBOpenDBRequest {
+    // https://w3c.github.io/IndexedDB/#dom-idbopendbrequest-onblocked
+    event_handler!(blocked, GetOnblocked, SetOnblocked);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbopendbrequest-onupgradeneeded
+    event_handler!(upgradeneeded, GetOnupgradeneeded, SetOnupgradeneeded);
+}
@@ -0,0 +1,266 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::IDBRequestBinding;
+use dom::bindings::codegen::Bindings::IDBRequestBinding::IDBRequestMethods;
+use dom::bindings::codegen::Bindings::IDBRequestBinding::IDBRequestReadyState;
+use dom::bindings::codegen::UnionTypes::IDBObjectStoreOrIDBIndexOrIDBCursor;
+use dom::bindings::conversions::ToJSValConvertible;
+use dom::bindings::error::{Error, Fallible};
+use dom::bindings::reflector::{DomObject, reflect_dom_object};
+use dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use dom::domexception::{DOMErrorName, DOMException};
+use dom::eventtarget::EventTarget;
+use dom::globalscope::GlobalScope;
+use dom::idbcursor::IDBCursor;
+use dom::idbindex::IDBIndex;
+use dom::idbobjectstore::IDBObjectStore;
+use dom::idbtransaction::IDBTransaction;
+use dom_struct::dom_struct;
+use indexed_db::key_to_jsval;
+use js::jsapi::{HandleValue, Heap, JSContext, JS_NewArrayObject1, JS_SetElement};
+use js::jsval::{JSVal, NullValue, ObjectValue, UndefinedValue};
+use net_traits::indexeddb_thread::{IndexedDBError, IndexedDBKey, IndexedDBReply, IndexedDBResult};
+use std::cell::Cell;
+
+/// The object a request was made against
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+pub enum IDBRequestSource {
+    ObjectStore(Dom<IDBObjectStore>),
+    Index(Dom<IDBIndex>),
+    Cursor(Dom<IDBCursor>),
+}
+
+/// How the reply to a request becomes its result
+#[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum IDBRequestKind {
+    /// The result is undefined
+    Undefined,
+    /// The result is the key the storage thread replied with
+    Key,
+    /// The result is the value of the record found, or undefined
+    Value,
+    /// The result is the primary key of the record found, or undefined
+    PrimaryKey,
+    /// The result is an array of the values of the records found
+    Values,
+    /// The result is an array of the primary keys of the records found
+    PrimaryKeys,
+    /// The result is the number of records found
+    Count,
+    /// The result is the request's cursor, or null once the cursor has run out of records
+    Cursor,
+}
+
+#[dom_struct]
+pub struct IDBRequest {
+    eventtarget: EventTarget,
+    result: Heap<JSVal>,
+    error: MutNullableDom<DOMException>,
+    source: Option<IDBRequestSource>,
+    transaction: MutNullableDom<IDBTransaction>,
+    ready_state: Cell<IDBRequestReadyState>,
+    kind: Cell<IDBRequestKind>,
+    /// The cursor iterated by this request, for requests made by `openCursor` and `openKeyCursor`
+    cursor: MutNullableDom<IDBCursor>,
+    /// The reply of the storage thread, kept until the requests before this one are done
+    reply: DomRefCell<Option<IndexedDBResult<IndexedDBReply>>>,
+}
+
+impl IDBRequest {
+    #[allow(unrooted_must_root)]
+    pub fn new_inherited(source: Option<IDBRequestSource>, transaction: Option<&IDBTransaction>) -> IDBRequest {
+        IDBRequest {
+            eventtarget: EventTarget::new_inherited(),
+            result: Heap::default(),
+            error: Default::default(),
+            source: source,
+            transaction: MutNullableDom::new(transaction),
+            ready_state: Cell::new(IDBRequestReadyState::Pending),
+            kind: Cell::new(IDBRequestKind::Undefined),
+            cursor: Default::default(),
+            reply: DomRefCell::new(None),
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn new(global: &GlobalScope,
+               source: IDBRequestSource,
+               transaction: &IDBTransaction)
+               -> DomRoot<IDBRequest> {
+        reflect_dom_object(Box::new(IDBRequest::new_inherited(Some(source), Some(transaction))),
+                           global,
+                           IDBRequestBinding::Wrap)
+    }
+
+    pub fn transaction(&self) -> Option<DomRoot<IDBTransaction>> {
+        self.transaction.get()
+    }
+
+    pub fn set_transaction(&self, transaction: Option<&IDBTransaction>) {
+        self.transaction.set(transaction);
+    }
+
+    pub fn set_cursor(&self, cursor: &IDBCursor) {
+        self.cursor.set(Some(cursor));
+    }
+
+    /// Marks the request as pending again, until the reply to its new operation is processed.
+    pub fn set_pending(&self, kind: IDBRequestKind) {
+        self.ready_state.set(IDBRequestReadyState::Pending);
+        self.kind.set(kind);
+        *self.reply.borrow_mut() = None;
+    }
+
+    pub fn set_reply(&self, reply: IndexedDBResult<IndexedDBReply>) {
+        *self.reply.borrow_mut() = Some(reply);
+    }
+
+    pub fn has_reply(&self) -> bool {
+        self.reply.borrow().is_some()
+    }
+
+    pub fn take_reply(&self) -> Option<IndexedDBResult<IndexedDBReply>> {
+        self.reply.borrow_mut().take()
+    }
+
+    /// The object store the records of this request belong to
+    pub fn object_store(&self) -> Option<DomRoot<IDBObjectStore>> {
+        match self.source {
+            Some(IDBRequestSource::ObjectStore(ref store)) => Some(DomRoot::from_ref(&**store)),
+            Some(IDBRequestSource::Index(ref index)) => Some(index.object_store()),
+            Some(IDBRequestSource::Cursor(ref cursor)) => Some(cursor.object_store()),
+            None => None,
+        }
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#request-done-flag>
+    pub fn set_result(&self, result: HandleValue) {
+        self.result.set(result.get());
+        self.error.set(None);
+        self.ready_state.set(IDBRequestReadyState::Done);
+    }
+
+    pub fn set_error(&self, error: &DOMException) {
+        self.result.set(UndefinedValue());
+        self.error.set(Some(error));
+        self.ready_state.set(IDBRequestReadyState::Done);
+    }
+
+    pub fn set_error_from_reply(&self, error: IndexedDBError) {
+        let name = match error {
+            IndexedDBError::Constraint => DOMErrorName::ConstraintError,
+            IndexedDBError::Data => DOMErrorName::DataError,
+            IndexedDBError::NotFound => DOMErrorName::NotFoundError,
+            IndexedDBError::Version => DOMErrorName::VersionError,
+            IndexedDBError::Abort => DOMErrorName::AbortError,
+        };
+        self.set_error(&DOMException::new(&self.global(), name));
+    }
+
+    /// Converts the reply of the storage thread into the result of this request.
+    #[allow(unsafe_code)]
+    pub fn set_result_from_reply(&self, reply: IndexedDBReply) {
+        let global = self.global();
+        let cx = global.get_cx();
+        rooted!(in(cx) let mut result = UndefinedValue());
+        unsafe {
+            match (self.kind.get(), reply) {
+                (IDBRequestKind::Key, IndexedDBReply::Key(key)) => {
+                    key_to_jsval(cx, &key, result.handle_mut());
+                },
+                (IDBRequestKind::Value, IndexedDBReply::Record(Some(record))) => {
+                    if let Some(store) = self.object_store() {
+                        store.read_value(record.value, &record.primary_key, result.handle_mut());
+                    }
+                },
+                (IDBRequestKind::PrimaryKey, IndexedDBReply::Record(Some(record))) => {
+                    key_to_jsval(cx, &record.primary_key, result.handle_mut());
+                },
+                (IDBRequestKind::Values, IndexedDBReply::Records(records)) => {
+                    rooted!(in(cx) let array = JS_NewArrayObject1(cx, records.len()));
+                    if let Some(store) = self.object_store() {
+                        for (index, record) in records.into_iter().enumerate() {
+                            rooted!(in(cx) let mut value = UndefinedValue());
+                            store.read_value(record.value, &record.primary_key, value.handle_mut());
+                            assert!(JS_SetElement(cx, array.handle(), index as u32, value.handle()));
+                        }
+                    }
+                    result.set(ObjectValue(array.get()));
+                },
+                (IDBRequestKind::PrimaryKeys, IndexedDBReply::Records(records)) => {
+                    let keys = records.into_iter().map(|record| record.primary_key).collect();
+                    key_to_jsval(cx, &IndexedDBKey::Array(keys), result.handle_mut());
+                },
+                (IDBRequestKind::Count, IndexedDBReply::Count(count)) => {
+                    (count as f64).to_jsval(cx, result.handle_mut());
+                },
+                (IDBRequestKind::Cursor, IndexedDBReply::Record(record)) => {
+                    let cursor = self.cursor.get().expect("Cursor request without a cursor");
+                    match record {
+                        Some(record) => {
+                            cursor.set_record(record);
+                            result.set(ObjectValue(cursor.reflector().get_jsobject().get()));
+                        },
+                        None => result.set(NullValue()),
+                    }
+                },
+                _ => {},
+            }
+        }
+        self.set_result(result.handle());
+    }
+}
+
+impl IDBRequestMethods for IDBRequest {
+    #[allow(unsafe_code)]
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-result
+    unsafe fn Result(&self, _cx: *mut JSContext) -> Fallible<JSVal> {
+        if self.ready_state.get() == IDBRequestReadyState::Pending {
+            return Err(Error::InvalidState);
+        }
+        Ok(self.result.get())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-error
+    fn GetError(&self) -> Fallible<Option<DomRoot<DOMException>>> {
+        if self.ready_state.get() == IDBRequestReadyState::Pending {
+            return Err(Error::InvalidState);
+        }
+        Ok(self.error.get())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-source
+    fn GetSource(&self) -> Option<IDBObjectStoreOrIDBIndexOrIDBCursor> {
+        self.source.as_ref().map(|source| match *source {
+            IDBRequestSource::ObjectStore(ref store) => {
+                IDBObjectStoreOrIDBIndexOrIDBCursor::IDBObjectStore(DomRoot::from_ref(&**store))
+            },
+            IDBRequestSource::Index(ref index) => {
+                IDBObjectStoreOrIDBIndexOrIDBCursor::IDBIndex(DomRoot::from_ref(&**index))
+            },
+            IDBRequestSource::Cursor(ref cursor) => {
+                IDBObjectStoreOrIDBIndexOrIDBCursor::IDBCursor(DomRoot::from_ref(&**cursor))
+            },
+        })
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-transaction
+    fn GetTransaction(&self) -> Option<DomRoot<IDBTransaction>> {
+        self.transaction.get()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-readystate
+    fn ReadyState(&self) -> IDBRequestReadyState {
+        self.ready_state.get()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-onsuccess
+    event_handler!(success, GetOnsuccess, SetOnsuccess);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-onerror
+    event_handler!(error, GetOnerror, SetOnerror);
+}
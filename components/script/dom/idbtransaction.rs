@@ -0,0 +1,418 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::IDBDatabaseBinding::IDBDatabaseMethods;
+use dom::bindings::codegen::Bindings::IDBRequestBinding::IDBRequestMethods;
+use dom::bindings::codegen::Bindings::IDBTransactionBinding;
+use dom::bindings::codegen::Bindings::IDBTransactionBinding::IDBTransactionMethods;
+use dom::bindings::codegen::Bindings::IDBTransactionBinding::IDBTransactionMode;
+use dom::bindings::error::{Error, ErrorResult, Fallible};
+use dom::bindings::inheritance::Castable;
+use dom::bindings::refcounted::Trusted;
+use dom::bindings::reflector::{DomObject, reflect_dom_object};
+use dom::bindings::root::{Dom, DomRoot, MutNullableDom, RootedReference};
+use dom::bindings::str::DOMString;
+use dom::domexception::{DOMErrorName, DOMException};
+use dom::domstringlist::DOMStringList;
+use dom::event::{Event, EventBubbles, EventCancelable, EventStatus};
+use dom::eventtarget::EventTarget;
+use dom::globalscope::GlobalScope;
+use dom::idbdatabase::IDBDatabase;
+use dom::idbobjectstore::IDBObjectStore;
+use dom::idbopendbrequest::IDBOpenDBRequest;
+use dom::idbrequest::{IDBRequest, IDBRequestKind};
+use dom_struct::dom_struct;
+use indexed_db::{send_to_indexeddb_thread, send_to_indexeddb_thread_sync};
+use net_traits::indexeddb_thread::{DatabaseInfo, IndexedDBOperation, IndexedDBReply};
+use net_traits::indexeddb_thread::{IndexedDBResult, IndexedDBThreadMsg, IndexedDBTransactionMode};
+use std::cell::Cell;
+use std::collections::{HashMap, VecDeque};
+use task_source::TaskSource;
+
+/// <https://w3c.github.io/IndexedDB/#transaction-lifetime-concept>
+#[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+enum TransactionState {
+    Active,
+    Inactive,
+    Committing,
+    Finished,
+}
+
+#[dom_struct]
+pub struct IDBTransaction {
+    eventtarget: EventTarget,
+    /// The id of the transaction in the storage thread
+    id: u64,
+    db: Dom<IDBDatabase>,
+    mode: IDBTransactionMode,
+    scope: Vec<DOMString>,
+    state: Cell<TransactionState>,
+    /// Whether the storage thread was asked to commit the transaction
+    commit_sent: Cell<bool>,
+    /// The requests whose success or error events were not fired yet, in order
+    requests: DomRefCell<VecDeque<Dom<IDBRequest>>>,
+    error: MutNullableDom<DOMException>,
+    object_stores: DomRefCell<HashMap<DOMString, Dom<IDBObjectStore>>>,
+    /// The open request of an upgrade transaction
+    open_request: MutNullableDom<IDBOpenDBRequest>,
+    /// The schema the database had before an upgrade transaction, restored if it is aborted
+    old_info: DomRefCell<Option<DatabaseInfo>>,
+}
+
+impl IDBTransaction {
+    fn new_inherited(id: u64,
+                     db: &IDBDatabase,
+                     mode: IDBTransactionMode,
+                     scope: Vec<DOMString>,
+                     open_request: Option<&IDBOpenDBRequest>)
+                     -> IDBTransaction {
+        IDBTransaction {
+            eventtarget: EventTarget::new_inherited(),
+            id: id,
+            db: Dom::from_ref(db),
+            mode: mode,
+            scope: scope,
+            state: Cell::new(TransactionState::Active),
+            commit_sent: Cell::new(false),
+            requests: DomRefCell::new(VecDeque::new()),
+            error: Default::default(),
+            object_stores: DomRefCell::new(HashMap::new()),
+            open_request: MutNullableDom::new(open_request),
+            old_info: DomRefCell::new(None),
+        }
+    }
+
+    fn new_with_id(global: &GlobalScope,
+                   id: u64,
+                   db: &IDBDatabase,
+                   mode: IDBTransactionMode,
+                   scope: Vec<DOMString>,
+                   open_request: Option<&IDBOpenDBRequest>)
+                   -> DomRoot<IDBTransaction> {
+        let transaction = IDBTransaction::new_inherited(id, db, mode, scope, open_request);
+        let transaction = reflect_dom_object(Box::new(transaction), global, IDBTransactionBinding::Wrap);
+
+        // The transaction is only active during the task it was created in, and whenever
+        // the events of its requests are dispatched.
+        let trusted = Trusted::new(&*transaction);
+        let _ = global.networking_task_source().queue(task!(deactivate_transaction: move || {
+            trusted.root().deactivate();
+        }), global);
+        transaction
+    }
+
+    pub fn new(global: &GlobalScope,
+               db: &IDBDatabase,
+               mode: IDBTransactionMode,
+               scope: Vec<DOMString>)
+               -> DomRoot<IDBTransaction> {
+        let origin = global.origin().immutable().clone();
+        let name = String::from(db.Name());
+        let net_mode = match mode {
+            IDBTransactionMode::Readonly => IndexedDBTransactionMode::ReadOnly,
+            IDBTransactionMode::Readwrite => IndexedDBTransactionMode::ReadWrite,
+            IDBTransactionMode::Versionchange => IndexedDBTransactionMode::VersionChange,
+        };
+        let id = send_to_indexeddb_thread_sync(global, |sender| {
+            IndexedDBThreadMsg::CreateTransaction(sender, origin, name, net_mode)
+        }).unwrap_or(0);
+        IDBTransaction::new_with_id(global, id, db, mode, scope, None)
+    }
+
+    /// Creates the upgrade transaction `id` of `open_request`, which the storage thread
+    /// started to change the version of `db`.
+    pub fn new_upgrade(global: &GlobalScope,
+                       db: &IDBDatabase,
+                       id: u64,
+                       version: u64,
+                       open_request: &IDBOpenDBRequest)
+                       -> DomRoot<IDBTransaction> {
+        let scope = db.object_store_names();
+        let transaction = IDBTransaction::new_with_id(global,
+                                                      id,
+                                                      db,
+                                                      IDBTransactionMode::Versionchange,
+                                                      scope,
+                                                      Some(open_request));
+        *transaction.old_info.borrow_mut() = Some(db.info());
+        db.set_version(version);
+        db.set_upgrade_transaction(Some(&transaction));
+        transaction
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn db(&self) -> DomRoot<IDBDatabase> {
+        DomRoot::from_ref(&*self.db)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.state.get() == TransactionState::Active
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state.get() == TransactionState::Finished
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.mode == IDBTransactionMode::Readonly
+    }
+
+    pub fn is_upgrade(&self) -> bool {
+        self.mode == IDBTransactionMode::Versionchange
+    }
+
+    /// Forgets the object store handle of a store deleted by this upgrade transaction.
+    pub fn remove_object_store(&self, name: &DOMString) {
+        self.object_stores.borrow_mut().remove(name);
+    }
+
+    fn deactivate(&self) {
+        if self.state.get() == TransactionState::Active {
+            self.state.set(TransactionState::Inactive);
+        }
+        self.maybe_commit();
+    }
+
+    /// Dispatches an event with the transaction active, as done for the events of its requests.
+    pub fn dispatch_while_active(&self, event: &Event, target: &EventTarget) -> EventStatus {
+        if self.state.get() == TransactionState::Inactive {
+            self.state.set(TransactionState::Active);
+        }
+        let status = event.fire(target);
+        if self.state.get() == TransactionState::Active {
+            self.state.set(TransactionState::Inactive);
+        }
+        status
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#asynchronously-execute-a-request>
+    pub fn add_request(&self, request: &IDBRequest, kind: IDBRequestKind, operation: IndexedDBOperation) {
+        request.set_pending(kind);
+        self.requests.borrow_mut().push_back(Dom::from_ref(request));
+
+        let transaction = Trusted::new(self);
+        let trusted_request = Trusted::new(request);
+        send_to_indexeddb_thread(&self.global(), |sender| {
+            IndexedDBThreadMsg::Operation(sender, self.id, operation)
+        }, move |reply: IndexedDBResult<IndexedDBReply>| {
+            trusted_request.root().set_reply(reply);
+            transaction.root().process_requests();
+        });
+    }
+
+    /// Runs an operation changing the schema of the database. Those are not requests, but
+    /// the transaction is aborted if one of them fails.
+    pub fn change_schema(&self, operation: IndexedDBOperation) {
+        let transaction = Trusted::new(self);
+        send_to_indexeddb_thread(&self.global(), |sender| {
+            IndexedDBThreadMsg::Operation(sender, self.id, operation)
+        }, move |reply: IndexedDBResult<IndexedDBReply>| {
+            if reply.is_err() {
+                let transaction = transaction.root();
+                let error = DOMException::new(&transaction.global(), DOMErrorName::AbortError);
+                transaction.abort(Some(&error));
+            }
+        });
+    }
+
+    /// Fires the events of the requests whose reply arrived, in the order the requests were
+    /// made, then commits the transaction if it has no requests left.
+    fn process_requests(&self) {
+        loop {
+            if self.state.get() == TransactionState::Finished {
+                return;
+            }
+            let request = {
+                let requests = self.requests.borrow();
+                match requests.front() {
+                    Some(request) if request.has_reply() => DomRoot::from_ref(&**request),
+                    _ => break,
+                }
+            };
+            self.requests.borrow_mut().pop_front();
+            match request.take_reply() {
+                Some(Ok(reply)) => {
+                    // https://w3c.github.io/IndexedDB/#fire-a-success-event
+                    request.set_result_from_reply(reply);
+                    let event = Event::new(&self.global(),
+                                           atom!("success"),
+                                           EventBubbles::DoesNotBubble,
+                                           EventCancelable::NotCancelable);
+                    self.dispatch_while_active(&event, request.upcast());
+                },
+                Some(Err(error)) => {
+                    // https://w3c.github.io/IndexedDB/#fire-an-error-event
+                    request.set_error_from_reply(error);
+                    let event = Event::new(&self.global(),
+                                           atom!("error"),
+                                           EventBubbles::Bubbles,
+                                           EventCancelable::Cancelable);
+                    if self.dispatch_while_active(&event, request.upcast()) == EventStatus::NotCanceled {
+                        let error = request.GetError().ok().and_then(|error| error);
+                        self.abort(error.r());
+                    }
+                },
+                None => {},
+            }
+        }
+        self.maybe_commit();
+    }
+
+    /// Commits the transaction once it is inactive or was asked to commit, and all of its
+    /// requests are done.
+    fn maybe_commit(&self) {
+        match self.state.get() {
+            TransactionState::Inactive | TransactionState::Committing => {},
+            TransactionState::Active | TransactionState::Finished => return,
+        }
+        if self.commit_sent.get() || !self.requests.borrow().is_empty() {
+            return;
+        }
+        self.state.set(TransactionState::Committing);
+        self.commit_sent.set(true);
+
+        // https://w3c.github.io/IndexedDB/#commit-a-transaction
+        let transaction = Trusted::new(self);
+        send_to_indexeddb_thread(&self.global(), |sender| {
+            IndexedDBThreadMsg::Commit(sender, self.id)
+        }, move |result: IndexedDBResult<()>| {
+            let transaction = transaction.root();
+            if result.is_err() {
+                let error = DOMException::new(&transaction.global(), DOMErrorName::AbortError);
+                return transaction.abort(Some(&error));
+            }
+            if transaction.state.get() == TransactionState::Finished {
+                return;
+            }
+            transaction.state.set(TransactionState::Finished);
+            transaction.upcast::<EventTarget>().fire_event(atom!("complete"));
+            if let Some(open_request) = transaction.open_request.get() {
+                transaction.db.set_upgrade_transaction(None);
+                open_request.upgrade_finished(false);
+            }
+        });
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#abort-a-transaction>
+    pub fn abort(&self, error: Option<&DOMException>) {
+        if self.state.get() == TransactionState::Finished {
+            return;
+        }
+        self.state.set(TransactionState::Finished);
+        self.error.set(error);
+
+        if let Some(info) = self.old_info.borrow_mut().take() {
+            // https://w3c.github.io/IndexedDB/#abort-an-upgrade-transaction
+            self.db.set_info(info);
+            self.db.set_upgrade_transaction(None);
+            self.db.Close();
+        }
+
+        let transaction = Trusted::new(self);
+        send_to_indexeddb_thread(&self.global(), |sender| {
+            IndexedDBThreadMsg::Abort(sender, self.id)
+        }, move |()| {
+            let transaction = transaction.root();
+            let global = transaction.global();
+            let requests: Vec<DomRoot<IDBRequest>> = transaction.requests.borrow_mut()
+                .drain(..)
+                .map(|request| DomRoot::from_ref(&*request))
+                .collect();
+            for request in requests {
+                request.set_error(&DOMException::new(&global, DOMErrorName::AbortError));
+                request.upcast::<EventTarget>().fire_bubbling_cancelable_event(atom!("error"));
+            }
+            transaction.upcast::<EventTarget>().fire_bubbling_event(atom!("abort"));
+            if let Some(open_request) = transaction.open_request.get() {
+                open_request.upgrade_finished(true);
+            }
+        });
+    }
+}
+
+impl IDBTransactionMethods for IDBTransaction {
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-objectstorenames
+    fn ObjectStoreNames(&self) -> DomRoot<DOMStringList> {
+        if self.is_upgrade() {
+            return self.db.ObjectStoreNames();
+        }
+        let mut names = self.scope.clone();
+        names.sort();
+        DOMStringList::new(&self.global(), names)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-mode
+    fn Mode(&self) -> IDBTransactionMode {
+        self.mode
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-db
+    fn Db(&self) -> DomRoot<IDBDatabase> {
+        self.db()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-error
+    fn GetError(&self) -> Option<DomRoot<DOMException>> {
+        self.error.get()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-objectstore
+    fn ObjectStore(&self, name: DOMString) -> Fallible<DomRoot<IDBObjectStore>> {
+        // Step 1.
+        if self.is_finished() {
+            return Err(Error::InvalidState);
+        }
+
+        // Step 2.
+        let in_scope = if self.is_upgrade() {
+            self.db.info().object_store(&name).is_some()
+        } else {
+            self.scope.contains(&name)
+        };
+        if !in_scope {
+            return Err(Error::NotFound);
+        }
+
+        // Step 3.
+        let mut object_stores = self.object_stores.borrow_mut();
+        let store = object_stores.entry(name.clone()).or_insert_with(|| {
+            Dom::from_ref(&*IDBObjectStore::new(&self.global(), self, name))
+        });
+        Ok(DomRoot::from_ref(&**store))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-commit
+    fn Commit(&self) -> ErrorResult {
+        if self.state.get() != TransactionState::Active {
+            return Err(Error::InvalidState);
+        }
+        self.state.set(TransactionState::Committing);
+        self.maybe_commit();
+        Ok(())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-abort
+    fn Abort(&self) -> ErrorResult {
+        match self.state.get() {
+            TransactionState::Committing | TransactionState::Finished => Err(Error::InvalidState),
+            TransactionState::Active | TransactionState::Inactive => {
+                self.abort(None);
+                Ok(())
+            },
+        }
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-onabort
+    event_handler!(abort, GetOnabort, SetOnabort);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-oncomplete
+    event_handler!(complete, GetOncomplete, SetOncomplete);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-onerror
+    event_handler!(error, GetOnerror, SetOnerror);
+}
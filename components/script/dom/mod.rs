@@ -275,6 +275,7 @@ pub mod dompointreadonly;
 pub mod domquad;
 pub mod domrect;
 pub mod domrectreadonly;
+pub mod domstringlist;
 pub mod domstringmap;
 pub mod domtokenlist;
 pub mod element;
@@ -353,6 +354,7 @@ pub mod htmlprogresselement;
 pub mod htmlquoteelement;
 pub mod htmlscriptelement;
 pub mod htmlselectelement;
+pub mod htmlslotelement;
 pub mod htmlsourceelement;
 pub mod htmlspanelement;
 pub mod htmlstyleelement;
@@ -372,6 +374,17 @@ pub mod htmltrackelement;
 pub mod htmlulistelement;
 pub mod htmlunknownelement;
 pub mod htmlvideoelement;
+pub mod idbcursor;
+pub mod idbcursorwithvalue;
+pub mod idbdatabase;
+pub mod idbfactory;
+pub mod idbindex;
+pub mod idbkeyrange;
+pub mod idbobjectstore;
+pub mod idbopendbrequest;
+pub mod idbrequest;
+pub mod idbtransaction;
+pub mod idbversionchangeevent;
 pub mod imagedata;
 pub mod inputevent;
 pub mod keyboardevent;
@@ -418,11 +431,14 @@ pub mod range;
 pub mod request;
 pub mod response;
 pub mod screen;
+pub mod securitypolicyviolationevent;
+pub mod selection;
 pub mod serviceworker;
 pub mod serviceworkercontainer;
 pub mod serviceworkerglobalscope;
 pub mod serviceworkerregistration;
 pub mod servoparser;
+pub mod shadowroot;
 pub mod storage;
 pub mod storageevent;
 pub mod stylepropertymapreadonly;
@@ -2,6 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use devtools_traits::ScriptToDevtoolsControlMsg;
 use dom::bindings::callback::ExceptionHandling;
 use dom::bindings::cell::DomRefCell;
 use dom::bindings::codegen::Bindings::MutationObserverBinding;
@@ -9,15 +10,19 @@ use dom::bindings::codegen::Bindings::MutationObserverBinding::MutationCallback;
 use dom::bindings::codegen::Bindings::MutationObserverBinding::MutationObserverBinding::MutationObserverMethods;
 use dom::bindings::codegen::Bindings::MutationObserverBinding::MutationObserverInit;
 use dom::bindings::error::{Error, Fallible};
+use dom::bindings::inheritance::Castable;
 use dom::bindings::reflector::{Reflector, reflect_dom_object};
 use dom::bindings::root::DomRoot;
 use dom::bindings::str::DOMString;
+use dom::eventtarget::EventTarget;
 use dom::mutationrecord::MutationRecord;
 use dom::node::Node;
 use dom::window::Window;
 use dom_struct::dom_struct;
 use html5ever::{Namespace, LocalName};
+use ipc_channel::ipc::IpcSender;
 use microtask::Microtask;
+use msg::constellation_msg::PipelineId;
 use script_thread::ScriptThread;
 use std::rc::Rc;
 
@@ -25,10 +30,19 @@ use std::rc::Rc;
 pub struct MutationObserver {
     reflector_: Reflector,
     #[ignore_malloc_size_of = "can't measure Rc values"]
-    callback: Rc<MutationCallback>,
+    listener: MutationListener,
     record_queue: DomRefCell<Vec<DomRoot<MutationRecord>>>,
 }
 
+/// Who is notified of the mutations an observer records.
+#[derive(JSTraceable)]
+enum MutationListener {
+    /// The callback a page created the observer with.
+    Callback(Rc<MutationCallback>),
+    /// Devtools following the DOM tree of the given pipeline.
+    Devtools(IpcSender<ScriptToDevtoolsControlMsg>, PipelineId),
+}
+
 pub enum Mutation<'a> {
     Attribute { name: LocalName, namespace: Namespace, old_value: DOMString },
     ChildList { added: Option<&'a [&'a Node]>, removed: Option<&'a [&'a Node]>,
@@ -53,25 +67,57 @@ pub struct ObserverOptions {
 }
 
 impl MutationObserver {
-    fn new(global: &Window, callback: Rc<MutationCallback>) -> DomRoot<MutationObserver> {
-        let boxed_observer = Box::new(MutationObserver::new_inherited(callback));
+    fn new(global: &Window, listener: MutationListener) -> DomRoot<MutationObserver> {
+        let boxed_observer = Box::new(MutationObserver::new_inherited(listener));
         reflect_dom_object(boxed_observer, global, MutationObserverBinding::Wrap)
     }
 
-    fn new_inherited(callback: Rc<MutationCallback>) -> MutationObserver {
+    fn new_inherited(listener: MutationListener) -> MutationObserver {
         MutationObserver {
             reflector_: Reflector::new(),
-            callback: callback,
+            listener: listener,
             record_queue: DomRefCell::new(vec![]),
         }
     }
 
     pub fn Constructor(global: &Window, callback: Rc<MutationCallback>) -> Fallible<DomRoot<MutationObserver>> {
-        let observer = MutationObserver::new(global, callback);
+        let observer = MutationObserver::new(global, MutationListener::Callback(callback));
         ScriptThread::add_mutation_observer(&*observer);
         Ok(observer)
     }
 
+    /// Create an observer of every change to the tree of `root` that reports them to devtools,
+    /// until `disconnect_devtools` is called.
+    pub fn observe_for_devtools(global: &Window,
+                                root: &Node,
+                                devtools_chan: IpcSender<ScriptToDevtoolsControlMsg>,
+                                pipeline: PipelineId) -> DomRoot<MutationObserver> {
+        let observer = MutationObserver::new(global, MutationListener::Devtools(devtools_chan, pipeline));
+        ScriptThread::add_mutation_observer(&*observer);
+        root.registered_mutation_observers().push(RegisteredObserver {
+            observer: DomRoot::from_ref(&*observer),
+            options: ObserverOptions {
+                attribute_old_value: false,
+                attributes: true,
+                character_data: false,
+                character_data_old_value: false,
+                child_list: true,
+                subtree: true,
+                attribute_filter: vec![],
+            },
+        });
+        observer
+    }
+
+    /// Stop an observer created by `observe_for_devtools` for the same `root`.
+    pub fn disconnect_devtools(&self, root: &Node) {
+        root.registered_mutation_observers().retain(|registered| {
+            &*registered.observer as *const MutationObserver != self as *const MutationObserver
+        });
+        self.record_queue.borrow_mut().clear();
+        ScriptThread::remove_mutation_observer(self);
+    }
+
     /// <https://dom.spec.whatwg.org/#queue-a-mutation-observer-compound-microtask>
     pub fn queue_mutation_observer_compound_microtask() {
         // Step 1
@@ -90,17 +136,29 @@ impl MutationObserver {
         ScriptThread::set_mutation_observer_compound_microtask_queued(false);
         // Step 2
         let notify_list = ScriptThread::get_mutation_observers();
-        // TODO: steps 3-4 (slots)
+        // Steps 3-4
+        let signal_list = ScriptThread::take_signal_slots();
         // Step 5
         for mo in &notify_list {
             let queue: Vec<DomRoot<MutationRecord>> = mo.record_queue.borrow().clone();
             mo.record_queue.borrow_mut().clear();
             // TODO: Step 5.3 Remove all transient registered observers whose observer is mo.
             if !queue.is_empty() {
-                let _ = mo.callback.Call_(&**mo, queue, &**mo, ExceptionHandling::Report);
+                match mo.listener {
+                    MutationListener::Callback(ref callback) => {
+                        let _ = callback.Call_(&**mo, queue, &**mo, ExceptionHandling::Report);
+                    },
+                    MutationListener::Devtools(ref devtools_chan, pipeline) => {
+                        let mutations = queue.iter().map(|record| record.summarize()).collect();
+                        let _ = devtools_chan.send(ScriptToDevtoolsControlMsg::Mutations(pipeline, mutations));
+                    },
+                }
             }
         }
-        // TODO: Step 6 (slot signals)
+        // Step 6
+        for slot in signal_list {
+            slot.upcast::<EventTarget>().fire_bubbling_event(atom!("slotchange"));
+        }
     }
 
     /// <https://dom.spec.whatwg.org/#queueing-a-mutation-record>
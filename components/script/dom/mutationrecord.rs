@@ -2,11 +2,17 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use devtools_traits::NodeMutation;
+use dom::bindings::codegen::Bindings::ElementBinding::ElementMethods;
 use dom::bindings::codegen::Bindings::MutationRecordBinding::MutationRecordBinding;
 use dom::bindings::codegen::Bindings::MutationRecordBinding::MutationRecordBinding::MutationRecordMethods;
+use dom::bindings::codegen::Bindings::NodeBinding::NodeMethods;
+use dom::bindings::codegen::Bindings::NodeListBinding::NodeListMethods;
+use dom::bindings::inheritance::Castable;
 use dom::bindings::reflector::{Reflector, reflect_dom_object};
 use dom::bindings::root::{Dom, DomRoot, MutNullableDom};
 use dom::bindings::str::DOMString;
+use dom::element::Element;
 use dom::node::{Node, window_from_node};
 use dom::nodelist::NodeList;
 use dom_struct::dom_struct;
@@ -89,6 +95,35 @@ impl MutationRecord {
             prev_sibling: prev_sibling.map(Dom::from_ref),
         }
     }
+
+    /// The mutation as reported to devtools, with the values the tree has now.
+    pub fn summarize(&self) -> NodeMutation {
+        let target = self.target.unique_id();
+        match self.attribute_name {
+            Some(ref name) => NodeMutation::Attribute {
+                target: target,
+                name: String::from(name.clone()),
+                namespace: self.attribute_namespace.clone().map(String::from),
+                value: self.target.downcast::<Element>().and_then(|element| {
+                    element.GetAttributeNS(self.attribute_namespace.clone(), name.clone())
+                }).map(String::from),
+            },
+            None => {
+                let added = self.AddedNodes();
+                let removed = self.RemovedNodes();
+                NodeMutation::ChildList {
+                    target: target,
+                    added: (0..added.Length()).filter_map(|index| added.Item(index))
+                                              .map(|node| node.summarize())
+                                              .collect(),
+                    removed: (0..removed.Length()).filter_map(|index| removed.Item(index))
+                                                  .map(|node| node.unique_id())
+                                                  .collect(),
+                    numChildren: self.target.ChildNodes().Length() as usize,
+                }
+            }
+        }
+    }
 }
 
 impl MutationRecordMethods for MutationRecord {
@@ -12,14 +12,15 @@ use dom::bindings::codegen::Bindings::CharacterDataBinding::CharacterDataMethods
 use dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
 use dom::bindings::codegen::Bindings::ElementBinding::ElementMethods;
 use dom::bindings::codegen::Bindings::HTMLCollectionBinding::HTMLCollectionMethods;
-use dom::bindings::codegen::Bindings::NodeBinding::{NodeConstants, NodeMethods};
+use dom::bindings::codegen::Bindings::NodeBinding::{GetRootNodeOptions, NodeConstants, NodeMethods};
 use dom::bindings::codegen::Bindings::NodeListBinding::NodeListMethods;
 use dom::bindings::codegen::Bindings::ProcessingInstructionBinding::ProcessingInstructionMethods;
+use dom::bindings::codegen::Bindings::ShadowRootBinding::{ShadowRootMethods, ShadowRootMode};
 use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use dom::bindings::codegen::UnionTypes::NodeOrString;
 use dom::bindings::conversions::{self, DerivedFrom};
 use dom::bindings::error::{Error, ErrorResult, Fallible};
-use dom::bindings::inheritance::{Castable, CharacterDataTypeId, ElementTypeId};
+use dom::bindings::inheritance::{Castable, CharacterDataTypeId, DocumentFragmentTypeId, ElementTypeId};
 use dom::bindings::inheritance::{EventTargetTypeId, HTMLElementTypeId, NodeTypeId};
 use dom::bindings::inheritance::{SVGElementTypeId, SVGGraphicsElementTypeId};
 use dom::bindings::reflector::{DomObject, reflect_dom_object};
@@ -29,10 +30,10 @@ use dom::bindings::xmlname::namespace_from_domstring;
 use dom::characterdata::{CharacterData, LayoutCharacterDataHelpers};
 use dom::cssstylesheet::CSSStyleSheet;
 use dom::customelementregistry::{CallbackReaction, try_upgrade_element};
-use dom::document::{Document, DocumentSource, HasBrowsingContext, IsHTMLDocument};
+use dom::document::{Document, DocumentSource, HasBrowsingContext, IsHTMLDocument, LayoutDocumentHelpers};
 use dom::documentfragment::DocumentFragment;
 use dom::documenttype::DocumentType;
-use dom::element::{CustomElementCreationMode, Element, ElementCreator};
+use dom::element::{CustomElementCreationMode, Element, ElementCreator, LayoutElementHelpers};
 use dom::eventtarget::EventTarget;
 use dom::globalscope::GlobalScope;
 use dom::htmlbodyelement::HTMLBodyElement;
@@ -44,12 +45,14 @@ use dom::htmlimageelement::{HTMLImageElement, LayoutHTMLImageElementHelpers};
 use dom::htmlinputelement::{HTMLInputElement, LayoutHTMLInputElementHelpers};
 use dom::htmllinkelement::HTMLLinkElement;
 use dom::htmlmetaelement::HTMLMetaElement;
+use dom::htmlslotelement::{HTMLSlotElement, LayoutHTMLSlotElementHelpers};
 use dom::htmlstyleelement::HTMLStyleElement;
 use dom::htmltextareaelement::{HTMLTextAreaElement, LayoutHTMLTextAreaElementHelpers};
 use dom::mutationobserver::{Mutation, MutationObserver, RegisteredObserver};
 use dom::nodelist::NodeList;
 use dom::processinginstruction::ProcessingInstruction;
 use dom::range::WeakRangeVec;
+use dom::shadowroot::{LayoutShadowRootHelpers, ShadowRoot};
 use dom::svgsvgelement::{SVGSVGElement, LayoutSVGSVGElementHelpers};
 use dom::text::Text;
 use dom::virtualmethods::{VirtualMethods, vtable_for};
@@ -62,7 +65,7 @@ use libc::{self, c_void, uintptr_t};
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use msg::constellation_msg::{BrowsingContextId, PipelineId};
 use ref_slice::ref_slice;
-use script_layout_interface::{HTMLCanvasData, OpaqueStyleAndLayoutData, SVGSVGData};
+use script_layout_interface::{HTMLCanvasData, HTMLInputWidget, OpaqueStyleAndLayoutData, SVGSVGData};
 use script_layout_interface::{LayoutElementType, LayoutNodeType, TrustedNodeAddress};
 use script_layout_interface::message::Msg;
 use script_thread::ScriptThread;
@@ -142,6 +145,9 @@ pub struct Node {
     /// Registered observers for this node.
     mutation_observers: DomRefCell<Vec<RegisteredObserver>>,
 
+    /// <https://dom.spec.whatwg.org/#slotable-assigned-slot>
+    assigned_slot: MutNullableDom<HTMLSlotElement>,
+
     unique_id: UniqueId,
 }
 
@@ -167,7 +173,8 @@ bitflags! {
         /// Whether any ancestor is a fragmentation container
         const CAN_BE_FRAGMENTED = 1 << 4;
 
-        // There's a free bit here.
+        /// Whether the root of this node is a shadow root.
+        const IS_IN_SHADOW_TREE = 1 << 5;
 
         #[doc = "Specifies whether the parser has set an associated form owner for \
                  this element. Only applicable for form-associatable elements."]
@@ -181,6 +188,9 @@ bitflags! {
 
         /// Whether this element has already handled the stored snapshot.
         const HANDLED_SNAPSHOT = 1 << 8;
+
+        /// Whether this text node is painted as part of the document's selection.
+        const IS_IN_SELECTION = 1 << 9;
     }
 }
 
@@ -260,7 +270,11 @@ impl Node {
         self.children_count.set(self.children_count.get() + 1);
 
         let parent_in_doc = self.is_in_doc();
+        let parent_in_shadow_tree = self.is_in_shadow_tree();
         for node in new_child.traverse_preorder() {
+            node.set_flag(NodeFlags::IS_IN_SHADOW_TREE, parent_in_shadow_tree);
+        }
+        for node in new_child.traverse_shadow_including_preorder() {
             node.set_flag(NodeFlags::IS_IN_DOC, parent_in_doc);
             // Out-of-document elements never have the descendants flag set.
             debug_assert!(!node.get_flag(NodeFlags::HAS_DIRTY_DESCENDANTS));
@@ -299,13 +313,14 @@ impl Node {
         child.parent_node.set(None);
         self.children_count.set(self.children_count.get() - 1);
 
-        for node in child.traverse_preorder() {
+        for node in child.traverse_shadow_including_preorder() {
             // Out-of-document elements never have the descendants flag set.
             node.set_flag(NodeFlags::IS_IN_DOC | NodeFlags::HAS_DIRTY_DESCENDANTS |
-                          NodeFlags::HAS_SNAPSHOT | NodeFlags::HANDLED_SNAPSHOT,
+                          NodeFlags::HAS_SNAPSHOT | NodeFlags::HANDLED_SNAPSHOT |
+                          NodeFlags::IS_IN_SELECTION,
                           false);
         }
-        for node in child.traverse_preorder() {
+        for node in child.traverse_shadow_including_preorder() {
             // This needs to be in its own loop, because unbind_from_tree may
             // rely on the state of IS_IN_DOC of the context node's descendants,
             // e.g. when removing a <form>.
@@ -316,6 +331,11 @@ impl Node {
                 ScriptThread::enqueue_callback_reaction(&*element, CallbackReaction::Disconnected, None);
             }
         }
+        // This is done after unbinding, so that elements removed from a shadow
+        // tree can still tell they weren't in the document's id map.
+        for node in child.traverse_preorder() {
+            node.set_flag(NodeFlags::IS_IN_SHADOW_TREE, false);
+        }
     }
 
     pub fn to_untrusted_node_address(&self) -> UntrustedNodeAddress {
@@ -425,6 +445,104 @@ impl Node {
         self.flags.get().contains(NodeFlags::IS_IN_DOC)
     }
 
+    pub fn is_in_shadow_tree(&self) -> bool {
+        self.flags.get().contains(NodeFlags::IS_IN_SHADOW_TREE)
+    }
+
+    /// Returns the shadow root that is the root of this node, if any.
+    pub fn containing_shadow_root(&self) -> Option<DomRoot<ShadowRoot>> {
+        if !self.is_in_shadow_tree() {
+            return None;
+        }
+        self.inclusive_ancestors().last().and_then(DomRoot::downcast)
+    }
+
+    /// Iterates over this node and its ancestors, going from shadow roots to
+    /// their hosts.
+    pub fn shadow_including_inclusive_ancestors(&self) -> impl Iterator<Item=DomRoot<Node>> {
+        SimpleNodeIterator {
+            current: Some(DomRoot::from_ref(self)),
+            next_node: |n| match n.downcast::<ShadowRoot>() {
+                Some(shadow_root) => Some(DomRoot::upcast(shadow_root.host())),
+                None => n.GetParentNode(),
+            },
+        }
+    }
+
+    /// <https://dom.spec.whatwg.org/#concept-shadow-including-inclusive-ancestor>
+    pub fn is_shadow_including_inclusive_ancestor_of(&self, node: &Node) -> bool {
+        node.shadow_including_inclusive_ancestors().any(|ancestor| &*ancestor == self)
+    }
+
+    /// <https://dom.spec.whatwg.org/#slotable-assigned-slot>
+    pub fn assigned_slot(&self) -> Option<DomRoot<HTMLSlotElement>> {
+        self.assigned_slot.get()
+    }
+
+    pub fn set_assigned_slot(&self, slot: Option<&HTMLSlotElement>) {
+        self.assigned_slot.set(slot);
+    }
+
+    /// The name of this node as a slotable, that is, the value of its `slot`
+    /// attribute if it's an element, and the empty string otherwise.
+    fn slotable_name(&self) -> DOMString {
+        self.downcast::<Element>()
+            .map_or(DOMString::new(), |element| element.get_string_attribute(&local_name!("slot")))
+    }
+
+    /// <https://dom.spec.whatwg.org/#find-a-slot>
+    pub fn find_a_slot(&self, open: bool) -> Option<DomRoot<HTMLSlotElement>> {
+        // Steps 1-3.
+        let shadow_root = self.GetParentNode()?.downcast::<Element>()?.shadow_root()?;
+        // Step 4.
+        if open && shadow_root.Mode() != ShadowRootMode::Open {
+            return None;
+        }
+        // Step 5.
+        let name = self.slotable_name();
+        shadow_root.upcast::<Node>()
+            .traverse_preorder()
+            .filter_map(DomRoot::downcast::<HTMLSlotElement>)
+            .find(|slot| slot.Name() == name)
+    }
+
+    /// <https://dom.spec.whatwg.org/#assign-a-slot>
+    pub fn assign_a_slot(&self) {
+        if let Some(slot) = self.find_a_slot(false) {
+            slot.assign_slotables();
+        }
+    }
+
+    /// Whether this node is a slot that, in the flat tree, is replaced by its
+    /// assigned nodes, or by its children if it has none.
+    pub fn is_flat_tree_slot(&self) -> bool {
+        self.is_in_shadow_tree() && self.is::<HTMLSlotElement>()
+    }
+
+    /// Returns the parent of this node in the flat tree, which is the one that
+    /// gets rendered: the children of shadow hosts are replaced by their
+    /// shadow tree, and the slots in it by the nodes assigned to them.
+    pub fn flat_tree_parent(&self) -> Option<DomRoot<Node>> {
+        if let Some(shadow_root) = self.downcast::<ShadowRoot>() {
+            return Some(DomRoot::upcast(shadow_root.host()));
+        }
+        let mut node = DomRoot::from_ref(self);
+        loop {
+            if let Some(slot) = node.assigned_slot() {
+                node = DomRoot::upcast(slot);
+                continue;
+            }
+            let parent = node.GetParentNode()?;
+            if let Some(shadow_root) = parent.downcast::<ShadowRoot>() {
+                return Some(DomRoot::upcast(shadow_root.host()));
+            }
+            if !parent.is_flat_tree_slot() {
+                return Some(parent);
+            }
+            node = parent;
+        }
+    }
+
     /// Returns the type ID of this node.
     pub fn type_id(&self) -> NodeTypeId {
         match *self.eventtarget.type_id() {
@@ -487,12 +605,20 @@ impl Node {
     pub fn note_dirty_descendants(&self) {
         debug_assert!(self.is_in_doc());
 
-        for ancestor in self.inclusive_ancestors() {
+        // Layout traverses the flat tree, so that's where the flag needs to
+        // be propagated, skipping the nodes that aren't part of it.
+        let mut current = if self.is::<ShadowRoot>() || self.is_flat_tree_slot() {
+            self.flat_tree_parent()
+        } else {
+            Some(DomRoot::from_ref(self))
+        };
+        while let Some(ancestor) = current {
             if ancestor.get_flag(NodeFlags::HAS_DIRTY_DESCENDANTS) {
                 return;
             }
 
             ancestor.set_flag(NodeFlags::HAS_DIRTY_DESCENDANTS, true);
+            current = ancestor.flat_tree_parent();
         }
     }
 
@@ -520,8 +646,17 @@ impl Node {
         }
 
         match self.type_id() {
-            NodeTypeId::CharacterData(CharacterDataTypeId::Text) =>
-                self.parent_node.get().unwrap().downcast::<Element>().unwrap().restyle(damage),
+            NodeTypeId::CharacterData(CharacterDataTypeId::Text) |
+            NodeTypeId::DocumentFragment(DocumentFragmentTypeId::ShadowRoot) => {
+                if let Some(parent) = self.flat_tree_parent().and_then(DomRoot::downcast::<Element>) {
+                    parent.restyle(damage);
+                }
+            },
+            NodeTypeId::Element(_) if self.is_flat_tree_slot() => {
+                if let Some(parent) = self.flat_tree_parent().and_then(DomRoot::downcast::<Element>) {
+                    parent.restyle(damage);
+                }
+            },
             NodeTypeId::Element(_) =>
                 self.downcast::<Element>().unwrap().restyle(damage),
             _ => {},
@@ -538,6 +673,14 @@ impl Node {
         TreeIterator::new(self)
     }
 
+    /// Iterates over this node and all its shadow-including descendants, in
+    /// shadow-including preorder.
+    pub fn traverse_shadow_including_preorder(&self) -> ShadowIncludingTreeIterator {
+        ShadowIncludingTreeIterator {
+            stack: vec![TreeIterator::new(self)],
+        }
+    }
+
     pub fn inclusively_following_siblings(&self) -> impl Iterator<Item=DomRoot<Node>> {
         SimpleNodeIterator {
             current: Some(DomRoot::from_ref(self)),
@@ -1013,6 +1156,12 @@ pub trait LayoutNodeHelpers {
     unsafe fn prev_sibling_ref(&self) -> Option<LayoutDom<Node>>;
     unsafe fn next_sibling_ref(&self) -> Option<LayoutDom<Node>>;
 
+    unsafe fn assigned_slot_ref(&self) -> Option<LayoutDom<Node>>;
+    unsafe fn is_flat_tree_slot_for_layout(&self) -> bool;
+    unsafe fn flat_tree_parent_ref(&self) -> Option<LayoutDom<Node>>;
+    unsafe fn flat_tree_first_child_ref(&self) -> Option<LayoutDom<Node>>;
+    unsafe fn flat_tree_next_sibling_ref(&self) -> Option<LayoutDom<Node>>;
+
     unsafe fn owner_doc_for_layout(&self) -> LayoutDom<Document>;
 
     unsafe fn is_element_for_layout(&self) -> bool;
@@ -1030,11 +1179,59 @@ pub trait LayoutNodeHelpers {
     fn image_url(&self) -> Option<ServoUrl>;
     fn canvas_data(&self) -> Option<HTMLCanvasData>;
     fn svg_data(&self) -> Option<SVGSVGData>;
+    fn input_widget(&self) -> Option<HTMLInputWidget>;
     fn iframe_browsing_context_id(&self) -> Option<BrowsingContextId>;
     fn iframe_pipeline_id(&self) -> Option<PipelineId>;
     fn opaque(&self) -> OpaqueNode;
 }
 
+/// Returns the node that comes after `node` in the flat tree, without
+/// replacing slots by their contents.
+#[allow(unsafe_code)]
+unsafe fn unexpanded_flat_tree_next_sibling(node: LayoutDom<Node>) -> Option<LayoutDom<Node>> {
+    let mut node = node;
+    loop {
+        if let Some(slot) = node.assigned_slot_ref() {
+            // Assigned nodes are in tree order, so the next one is a following
+            // sibling, and once they're exhausted we continue after the slot.
+            let mut sibling = node.next_sibling_ref();
+            while let Some(next) = sibling {
+                if next.assigned_slot_ref() == Some(slot) {
+                    return Some(next);
+                }
+                sibling = next.next_sibling_ref();
+            }
+            node = slot;
+            continue;
+        }
+        if let Some(next) = node.next_sibling_ref() {
+            return Some(next);
+        }
+        // The fallback content of a slot is followed by what follows the slot.
+        let parent = node.parent_node_ref()?;
+        if !parent.is_flat_tree_slot_for_layout() {
+            return None;
+        }
+        node = parent;
+    }
+}
+
+/// Replaces `node`, if it's a slot in a shadow tree, by the first node it
+/// renders, that is, its first assigned node, or its first child if it has
+/// none. Empty slots are skipped altogether.
+#[allow(unsafe_code)]
+unsafe fn expand_flat_tree_slots(node: LayoutDom<Node>) -> Option<LayoutDom<Node>> {
+    let mut node = node;
+    while node.is_flat_tree_slot_for_layout() {
+        let slot = node.downcast::<HTMLSlotElement>().unwrap();
+        node = match slot.first_assigned_node_for_layout().or_else(|| node.first_child_ref()) {
+            Some(child) => child,
+            None => unexpanded_flat_tree_next_sibling(node)?,
+        };
+    }
+    Some(node)
+}
+
 impl LayoutNodeHelpers for LayoutDom<Node> {
     #[inline]
     #[allow(unsafe_code)]
@@ -1078,6 +1275,55 @@ impl LayoutNodeHelpers for LayoutDom<Node> {
         (*self.unsafe_get()).next_sibling.get_inner_as_layout()
     }
 
+    #[inline]
+    #[allow(unsafe_code)]
+    unsafe fn assigned_slot_ref(&self) -> Option<LayoutDom<Node>> {
+        (*self.unsafe_get()).assigned_slot.get_inner_as_layout().map(|slot| slot.upcast())
+    }
+
+    #[inline]
+    #[allow(unsafe_code)]
+    unsafe fn is_flat_tree_slot_for_layout(&self) -> bool {
+        self.get_flag(NodeFlags::IS_IN_SHADOW_TREE) && (*self.unsafe_get()).is::<HTMLSlotElement>()
+    }
+
+    #[allow(unsafe_code)]
+    unsafe fn flat_tree_parent_ref(&self) -> Option<LayoutDom<Node>> {
+        if let Some(shadow_root) = self.downcast::<ShadowRoot>() {
+            return Some(shadow_root.get_host_for_layout().upcast());
+        }
+        let mut node = *self;
+        loop {
+            if let Some(slot) = node.assigned_slot_ref() {
+                node = slot;
+                continue;
+            }
+            let parent = node.parent_node_ref()?;
+            if let Some(shadow_root) = parent.downcast::<ShadowRoot>() {
+                return Some(shadow_root.get_host_for_layout().upcast());
+            }
+            if !parent.is_flat_tree_slot_for_layout() {
+                return Some(parent);
+            }
+            node = parent;
+        }
+    }
+
+    #[allow(unsafe_code)]
+    unsafe fn flat_tree_first_child_ref(&self) -> Option<LayoutDom<Node>> {
+        let shadow_root = self.downcast::<Element>().and_then(|element| element.get_shadow_root_for_layout());
+        let first_child = match shadow_root {
+            Some(shadow_root) => shadow_root.upcast::<Node>().first_child_ref(),
+            None => self.first_child_ref(),
+        };
+        expand_flat_tree_slots(first_child?)
+    }
+
+    #[allow(unsafe_code)]
+    unsafe fn flat_tree_next_sibling_ref(&self) -> Option<LayoutDom<Node>> {
+        expand_flat_tree_slots(unexpanded_flat_tree_next_sibling(*self)?)
+    }
+
     #[inline]
     #[allow(unsafe_code)]
     unsafe fn owner_doc_for_layout(&self) -> LayoutDom<Document> {
@@ -1159,6 +1405,12 @@ impl LayoutNodeHelpers for LayoutDom<Node> {
             return unsafe { input.selection_for_layout() };
         }
 
+        if let Some(text) = self.downcast::<Text>() {
+            if unsafe { self.get_flag(NodeFlags::IS_IN_SELECTION) } {
+                return unsafe { self.owner_doc_for_layout().selected_text_for_layout(text) };
+            }
+        }
+
         None
     }
 
@@ -1181,6 +1433,12 @@ impl LayoutNodeHelpers for LayoutDom<Node> {
             .map(|svg| svg.data())
     }
 
+    #[allow(unsafe_code)]
+    fn input_widget(&self) -> Option<HTMLInputWidget> {
+        self.downcast::<HTMLInputElement>()
+            .and_then(|input| unsafe { input.widget_for_layout() })
+    }
+
     fn iframe_browsing_context_id(&self) -> Option<BrowsingContextId> {
         let iframe_element = self.downcast::<HTMLIFrameElement>()
             .expect("not an iframe element!");
@@ -1359,6 +1617,38 @@ impl Iterator for TreeIterator {
     }
 }
 
+/// An iterator over a node and its shadow-including descendants, where the
+/// shadow root of an element comes right after it, before its children.
+///
+/// <https://dom.spec.whatwg.org/#concept-shadow-including-tree-order>
+pub struct ShadowIncludingTreeIterator {
+    stack: Vec<TreeIterator>,
+}
+
+impl Iterator for ShadowIncludingTreeIterator {
+    type Item = DomRoot<Node>;
+
+    fn next(&mut self) -> Option<DomRoot<Node>> {
+        loop {
+            let next = match self.stack.last_mut() {
+                Some(iterator) => iterator.next(),
+                None => return None,
+            };
+            match next {
+                Some(node) => {
+                    if let Some(shadow_root) = node.downcast::<Element>().and_then(Element::shadow_root) {
+                        self.stack.push(TreeIterator::new(shadow_root.upcast()));
+                    }
+                    return Some(node);
+                },
+                None => {
+                    self.stack.pop();
+                },
+            }
+        }
+    }
+}
+
 /// Specifies whether children must be recursively cloned or not.
 #[derive(Clone, Copy, MallocSizeOf, PartialEq)]
 pub enum CloneChildrenFlag {
@@ -1410,6 +1700,8 @@ impl Node {
 
             mutation_observers: Default::default(),
 
+            assigned_slot: Default::default(),
+
             unique_id: UniqueId::new(),
         }
     }
@@ -1423,15 +1715,15 @@ impl Node {
         // Step 3.
         if &*old_doc != document {
             // Step 3.1.
-            for descendant in node.traverse_preorder() {
+            for descendant in node.traverse_shadow_including_preorder() {
                 descendant.set_owner_doc(document);
             }
-            for descendant in node.traverse_preorder().filter_map(|d| d.as_custom_element()) {
+            for descendant in node.traverse_shadow_including_preorder().filter_map(|d| d.as_custom_element()) {
                 // Step 3.2.
                 ScriptThread::enqueue_callback_reaction(&*descendant,
                     CallbackReaction::Adopted(old_doc.clone(), DomRoot::from_ref(document)), None);
             }
-            for descendant in node.traverse_preorder() {
+            for descendant in node.traverse_shadow_including_preorder() {
                 // Step 3.3.
                 vtable_for(&descendant).adopting_steps(&old_doc);
             }
@@ -1445,13 +1737,13 @@ impl Node {
         // Step 1.
         match parent.type_id() {
             NodeTypeId::Document(_) |
-            NodeTypeId::DocumentFragment |
+            NodeTypeId::DocumentFragment(_) |
             NodeTypeId::Element(..) => (),
             _ => return Err(Error::HierarchyRequest)
         }
 
         // Step 2.
-        if node.is_inclusive_ancestor_of(parent) {
+        if node.is_shadow_including_inclusive_ancestor_of(parent) {
             return Err(Error::HierarchyRequest);
         }
 
@@ -1474,7 +1766,7 @@ impl Node {
                     return Err(Error::HierarchyRequest);
                 }
             },
-            NodeTypeId::DocumentFragment |
+            NodeTypeId::DocumentFragment(_) |
             NodeTypeId::Element(_) |
             NodeTypeId::CharacterData(CharacterDataTypeId::ProcessingInstruction) |
             NodeTypeId::CharacterData(CharacterDataTypeId::Comment) => (),
@@ -1485,7 +1777,7 @@ impl Node {
         if parent.is::<Document>() {
             match node.type_id() {
                 // Step 6.1
-                NodeTypeId::DocumentFragment => {
+                NodeTypeId::DocumentFragment(_) => {
                     // Step 6.1.1(b)
                     if node.children()
                            .any(|c| c.is::<Text>())
@@ -1602,7 +1894,7 @@ impl Node {
             }
         }
         rooted_vec!(let mut new_nodes);
-        let new_nodes = if let NodeTypeId::DocumentFragment = node.type_id() {
+        let new_nodes = if let NodeTypeId::DocumentFragment(_) = node.type_id() {
             // Step 3.
             new_nodes.extend(node.children().map(|kid| Dom::from_ref(&*kid)));
             // Step 4.
@@ -1639,8 +1931,24 @@ impl Node {
         for kid in new_nodes {
             // Step 7.1.
             parent.add_child(*kid, child);
+            // Step 7.2.
+            let parent_is_shadow_host = parent.downcast::<Element>().map_or(false, |e| e.shadow_root().is_some());
+            if parent_is_shadow_host && (kid.is::<Element>() || kid.is::<Text>()) {
+                kid.assign_a_slot();
+            }
+            // Step 7.3.
+            if parent.is_flat_tree_slot() &&
+               parent.downcast::<HTMLSlotElement>().unwrap().has_no_assigned_nodes() {
+                parent.downcast::<HTMLSlotElement>().unwrap().signal_a_slot_change();
+            }
+            // Step 7.4.
+            if let Some(shadow_root) = kid.containing_shadow_root() {
+                if kid.traverse_preorder().any(|node| node.is::<HTMLSlotElement>()) {
+                    HTMLSlotElement::assign_slotables_for_a_tree(shadow_root.upcast());
+                }
+            }
             // Step 7.7.
-            for descendant in kid.traverse_preorder().filter_map(DomRoot::downcast::<Element>) {
+            for descendant in kid.traverse_shadow_including_preorder().filter_map(DomRoot::downcast::<Element>) {
                 // Step 7.7.2.
                 if descendant.is_connected() {
                     if descendant.get_custom_element_definition().is_some() {
@@ -1678,7 +1986,7 @@ impl Node {
         // Step 3.
         rooted_vec!(let mut added_nodes);
         let added_nodes = if let Some(node) = node.as_ref() {
-            if let NodeTypeId::DocumentFragment = node.type_id() {
+            if let NodeTypeId::DocumentFragment(_) = node.type_id() {
                 added_nodes.extend(node.children().map(|child| Dom::from_ref(&*child)));
                 added_nodes.r()
             } else {
@@ -1750,9 +2058,27 @@ impl Node {
         // Step 8.
         let old_next_sibling = node.GetNextSibling();
         // Steps 9-10 are handled in unbind_from_tree.
+        let old_root = parent.inclusive_ancestors().last().unwrap();
+        let old_slot = node.assigned_slot();
         parent.remove_child(node, cached_index);
-        // Step 11. transient registered observers
+        // Step 11.
+        if let Some(slot) = old_slot {
+            slot.assign_slotables();
+        }
         // Step 12.
+        if parent.is_flat_tree_slot() {
+            let parent_slot = parent.downcast::<HTMLSlotElement>().unwrap();
+            if parent_slot.has_no_assigned_nodes() {
+                parent_slot.signal_a_slot_change();
+            }
+        }
+        // Step 13.
+        if node.traverse_preorder().any(|node| node.is::<HTMLSlotElement>()) {
+            HTMLSlotElement::assign_slotables_for_a_tree(&old_root);
+            HTMLSlotElement::assign_slotables_for_a_tree(node);
+        }
+        // Step 14. transient registered observers
+        // Step 15.
         if let SuppressObserver::Unsuppressed = suppress_observers {
             vtable_for(&parent).children_changed(
                 &ChildrenMutation::replace(old_previous_sibling.r(),
@@ -1790,7 +2116,7 @@ impl Node {
                                                 &document);
                 DomRoot::upcast::<Node>(doctype)
             },
-            NodeTypeId::DocumentFragment => {
+            NodeTypeId::DocumentFragment(_) => {
                 let doc_fragment = DocumentFragment::new(&document);
                 DomRoot::upcast::<Node>(doc_fragment)
             },
@@ -1913,7 +2239,7 @@ impl Node {
                     .GetDocumentElement().as_ref()
                     .map_or(ns!(), |elem| elem.locate_namespace(prefix))
             },
-            NodeTypeId::DocumentType | NodeTypeId::DocumentFragment => ns!(),
+            NodeTypeId::DocumentType | NodeTypeId::DocumentFragment(_) => ns!(),
             _ => {
                 node.GetParentElement().as_ref()
                     .map_or(ns!(), |elem| elem.locate_namespace(prefix))
@@ -1936,7 +2262,7 @@ impl NodeMethods for Node {
                 NodeConstants::DOCUMENT_NODE,
             NodeTypeId::DocumentType =>
                 NodeConstants::DOCUMENT_TYPE_NODE,
-            NodeTypeId::DocumentFragment =>
+            NodeTypeId::DocumentFragment(_) =>
                 NodeConstants::DOCUMENT_FRAGMENT_NODE,
             NodeTypeId::Element(_) =>
                 NodeConstants::ELEMENT_NODE,
@@ -1957,7 +2283,7 @@ impl NodeMethods for Node {
             NodeTypeId::DocumentType => {
                 self.downcast::<DocumentType>().unwrap().name().clone()
             },
-            NodeTypeId::DocumentFragment => DOMString::from("#document-fragment"),
+            NodeTypeId::DocumentFragment(_) => DOMString::from("#document-fragment"),
             NodeTypeId::Document(_) => DOMString::from("#document")
         }
     }
@@ -1973,14 +2299,18 @@ impl NodeMethods for Node {
             NodeTypeId::CharacterData(..) |
             NodeTypeId::Element(..) |
             NodeTypeId::DocumentType |
-            NodeTypeId::DocumentFragment => Some(self.owner_doc()),
+            NodeTypeId::DocumentFragment(_) => Some(self.owner_doc()),
             NodeTypeId::Document(_) => None
         }
     }
 
     // https://dom.spec.whatwg.org/#dom-node-getrootnode
-    fn GetRootNode(&self) -> DomRoot<Node> {
-        self.inclusive_ancestors().last().unwrap()
+    fn GetRootNode(&self, options: &GetRootNodeOptions) -> DomRoot<Node> {
+        if options.composed {
+            self.shadow_including_inclusive_ancestors().last().unwrap()
+        } else {
+            self.inclusive_ancestors().last().unwrap()
+        }
     }
 
     // https://dom.spec.whatwg.org/#dom-node-parentnode
@@ -2042,7 +2372,7 @@ impl NodeMethods for Node {
     // https://dom.spec.whatwg.org/#dom-node-textcontent
     fn GetTextContent(&self) -> Option<DOMString> {
         match self.type_id() {
-            NodeTypeId::DocumentFragment |
+            NodeTypeId::DocumentFragment(_) |
             NodeTypeId::Element(..) => {
                 let content = Node::collect_text_contents(self.traverse_preorder());
                 Some(content)
@@ -2062,7 +2392,7 @@ impl NodeMethods for Node {
     fn SetTextContent(&self, value: Option<DOMString>) {
         let value = value.unwrap_or_default();
         match self.type_id() {
-            NodeTypeId::DocumentFragment |
+            NodeTypeId::DocumentFragment(_) |
             NodeTypeId::Element(..) => {
                 // Step 1-2.
                 let node = if value.is_empty() {
@@ -2098,7 +2428,7 @@ impl NodeMethods for Node {
         // Step 1.
         match self.type_id() {
             NodeTypeId::Document(_) |
-            NodeTypeId::DocumentFragment |
+            NodeTypeId::DocumentFragment(_) |
             NodeTypeId::Element(..) => (),
             _ => return Err(Error::HierarchyRequest)
         }
@@ -2126,7 +2456,7 @@ impl NodeMethods for Node {
         if self.is::<Document>() {
             match node.type_id() {
                 // Step 6.1
-                NodeTypeId::DocumentFragment => {
+                NodeTypeId::DocumentFragment(_) => {
                     // Step 6.1.1(b)
                     if node.children()
                            .any(|c| c.is::<Text>())
@@ -2207,7 +2537,7 @@ impl NodeMethods for Node {
 
         // Step 12.
         rooted_vec!(let mut nodes);
-        let nodes = if node.type_id() == NodeTypeId::DocumentFragment {
+        let nodes = if node.is::<DocumentFragment>() {
             nodes.extend(node.children().map(|node| Dom::from_ref(&*node)));
             nodes.r()
         } else {
@@ -2268,12 +2598,18 @@ impl NodeMethods for Node {
     }
 
     // https://dom.spec.whatwg.org/#dom-node-clonenode
-    fn CloneNode(&self, deep: bool) -> DomRoot<Node> {
-        Node::clone(self, None, if deep {
+    fn CloneNode(&self, deep: bool) -> Fallible<DomRoot<Node>> {
+        // Step 1.
+        if self.is::<ShadowRoot>() {
+            return Err(Error::NotSupported);
+        }
+
+        // Step 2.
+        Ok(Node::clone(self, None, if deep {
             CloneChildrenFlag::CloneChildren
         } else {
             CloneChildrenFlag::DoNotCloneChildren
-        })
+        }))
     }
 
     // https://dom.spec.whatwg.org/#dom-node-isequalnode
@@ -2461,7 +2797,7 @@ impl NodeMethods for Node {
                     element.lookup_prefix(namespace)
                 })
             },
-            NodeTypeId::DocumentType | NodeTypeId::DocumentFragment => None,
+            NodeTypeId::DocumentType | NodeTypeId::DocumentFragment(_) => None,
             _ => {
                 self.GetParentElement().and_then(|element| {
                     element.lookup_prefix(namespace)
@@ -14,7 +14,7 @@ use dom::bindings::error::{Error, ErrorResult, Fallible};
 use dom::bindings::inheritance::{CharacterDataTypeId, NodeTypeId};
 use dom::bindings::inheritance::Castable;
 use dom::bindings::reflector::{Reflector, reflect_dom_object};
-use dom::bindings::root::{Dom, DomRoot, MutDom, RootedReference};
+use dom::bindings::root::{Dom, DomRoot, LayoutDom, MutDom, MutNullableDom, RootedReference};
 use dom::bindings::str::DOMString;
 use dom::bindings::trace::JSTraceable;
 use dom::bindings::weakref::{WeakRef, WeakRefVec};
@@ -24,6 +24,7 @@ use dom::documentfragment::DocumentFragment;
 use dom::element::Element;
 use dom::htmlscriptelement::HTMLScriptElement;
 use dom::node::{Node, UnbindContext};
+use dom::selection::Selection;
 use dom::text::Text;
 use dom::window::Window;
 use dom_struct::dom_struct;
@@ -37,6 +38,8 @@ pub struct Range {
     reflector_: Reflector,
     start: BoundaryPoint,
     end: BoundaryPoint,
+    /// The selection this range is associated with, if any.
+    selection: MutNullableDom<Selection>,
 }
 
 impl Range {
@@ -46,6 +49,7 @@ impl Range {
             reflector_: Reflector::new(),
             start: BoundaryPoint::new(start_container, start_offset),
             end: BoundaryPoint::new(end_container, end_offset),
+            selection: Default::default(),
         }
     }
 
@@ -146,6 +150,9 @@ impl Range {
             }
         }
         self.start.set(node, offset);
+        if let Some(selection) = self.selection.get() {
+            selection.range_changed();
+        }
     }
 
     // https://dom.spec.whatwg.org/#concept-range-bp-set
@@ -160,10 +167,13 @@ impl Range {
             }
         }
         self.end.set(node, offset);
+        if let Some(selection) = self.selection.get() {
+            selection.range_changed();
+        }
     }
 
     // https://dom.spec.whatwg.org/#dom-range-comparepointnode-offset
-    fn compare_point(&self, node: &Node, offset: u32) -> Fallible<Ordering> {
+    pub fn compare_point(&self, node: &Node, offset: u32) -> Fallible<Ordering> {
         let start_node = self.StartContainer();
         let start_node_root = start_node.inclusive_ancestors().last().unwrap();
         let node_root = node.inclusive_ancestors().last().unwrap();
@@ -190,6 +200,35 @@ impl Range {
         // Step 6.
         Ok(Ordering::Equal)
     }
+
+    pub fn set_selection(&self, selection: Option<&Selection>) {
+        self.selection.set(selection);
+    }
+
+    /// The text nodes that are contained or partially contained in this range,
+    /// in tree order.
+    pub fn text_nodes(&self) -> Vec<DomRoot<Text>> {
+        let start_node = self.StartContainer();
+        let end_node = self.EndContainer();
+
+        let mut text_nodes = vec![];
+        if let Some(text) = start_node.downcast::<Text>() {
+            text_nodes.push(DomRoot::from_ref(text));
+        }
+        if start_node == end_node {
+            return text_nodes;
+        }
+
+        let ancestor = self.CommonAncestorContainer();
+        text_nodes.extend(start_node.following_nodes(&ancestor)
+                                    .filter_map(DomRoot::downcast::<Text>)
+                                    .filter(|text| self.contains(text.upcast())));
+
+        if let Some(text) = end_node.downcast::<Text>() {
+            text_nodes.push(DomRoot::from_ref(text));
+        }
+        text_nodes
+    }
 }
 
 impl RangeMethods for Range {
@@ -470,7 +509,7 @@ impl RangeMethods for Range {
                 fragment.upcast::<Node>().AppendChild(&clone)?;
             } else {
                 // Step 14.1.
-                let clone = child.CloneNode(false);
+                let clone = child.CloneNode(false)?;
                 // Step 14.2.
                 fragment.upcast::<Node>().AppendChild(&clone)?;
                 // Step 14.3.
@@ -489,7 +528,7 @@ impl RangeMethods for Range {
         // Step 15.
         for child in contained_children {
             // Step 15.1.
-            let clone = child.CloneNode(true);
+            let clone = child.CloneNode(true)?;
             // Step 15.2.
             fragment.upcast::<Node>().AppendChild(&clone)?;
         }
@@ -505,7 +544,7 @@ impl RangeMethods for Range {
                 fragment.upcast::<Node>().AppendChild(&clone)?;
             } else {
                 // Step 17.1.
-                let clone = child.CloneNode(false);
+                let clone = child.CloneNode(false)?;
                 // Step 17.2.
                 fragment.upcast::<Node>().AppendChild(&clone)?;
                 // Step 17.3.
@@ -545,7 +584,7 @@ impl RangeMethods for Range {
         if end_node == start_node {
             if let Some(end_data) = end_node.downcast::<CharacterData>() {
                 // Step 4.1.
-                let clone = end_node.CloneNode(true);
+                let clone = end_node.CloneNode(true)?;
                 // Step 4.2.
                 let text = end_data.SubstringData(start_offset, end_offset - start_offset);
                 clone.downcast::<CharacterData>().unwrap().SetData(text.unwrap());
@@ -581,7 +620,7 @@ impl RangeMethods for Range {
             if let Some(start_data) = child.downcast::<CharacterData>() {
                 assert!(child == start_node);
                 // Step 15.1.
-                let clone = start_node.CloneNode(true);
+                let clone = start_node.CloneNode(true)?;
                 // Step 15.2.
                 let text = start_data.SubstringData(start_offset,
                                                     start_node.len() - start_offset);
@@ -594,7 +633,7 @@ impl RangeMethods for Range {
                                             DOMString::new())?;
             } else {
                 // Step 16.1.
-                let clone = child.CloneNode(false);
+                let clone = child.CloneNode(false)?;
                 // Step 16.2.
                 fragment.upcast::<Node>().AppendChild(&clone)?;
                 // Step 16.3.
@@ -619,7 +658,7 @@ impl RangeMethods for Range {
             if let Some(end_data) = child.downcast::<CharacterData>() {
                 assert!(child == end_node);
                 // Step 18.1.
-                let clone = end_node.CloneNode(true);
+                let clone = end_node.CloneNode(true)?;
                 // Step 18.2.
                 let text = end_data.SubstringData(0, end_offset);
                 clone.downcast::<CharacterData>().unwrap().SetData(text.unwrap());
@@ -629,7 +668,7 @@ impl RangeMethods for Range {
                 end_data.ReplaceData(0, end_offset, DOMString::new())?;
             } else {
                 // Step 19.1.
-                let clone = child.CloneNode(false);
+                let clone = child.CloneNode(false)?;
                 // Step 19.2.
                 fragment.upcast::<Node>().AppendChild(&clone)?;
                 // Step 19.3.
@@ -725,7 +764,7 @@ impl RangeMethods for Range {
             reference_node.r().map_or(parent.len(), |node| node.index());
 
         // Step 11
-        let new_offset = new_offset + if node.type_id() == NodeTypeId::DocumentFragment {
+        let new_offset = new_offset + if node.is::<DocumentFragment>() {
             node.len()
         } else {
             1
@@ -837,7 +876,7 @@ impl RangeMethods for Range {
         match new_parent.type_id() {
             NodeTypeId::Document(_) |
             NodeTypeId::DocumentType |
-            NodeTypeId::DocumentFragment => return Err(Error::InvalidNodeType),
+            NodeTypeId::DocumentFragment(_) => return Err(Error::InvalidNodeType),
             _ => ()
         }
 
@@ -906,7 +945,7 @@ impl RangeMethods for Range {
         let node = self.StartContainer();
         let owner_doc = node.owner_doc();
         let element = match node.type_id() {
-            NodeTypeId::Document(_) | NodeTypeId::DocumentFragment => None,
+            NodeTypeId::Document(_) | NodeTypeId::DocumentFragment(_) => None,
             NodeTypeId::Element(_) => Some(DomRoot::downcast::<Element>(node).unwrap()),
             NodeTypeId::CharacterData(CharacterDataTypeId::Comment) |
             NodeTypeId::CharacterData(CharacterDataTypeId::Text) => node.GetParentElement(),
@@ -933,6 +972,27 @@ impl RangeMethods for Range {
     }
 }
 
+#[allow(unsafe_code)]
+pub trait LayoutRangeHelpers {
+    unsafe fn start_for_layout(&self) -> (LayoutDom<Node>, u32);
+    unsafe fn end_for_layout(&self) -> (LayoutDom<Node>, u32);
+}
+
+#[allow(unsafe_code)]
+impl LayoutRangeHelpers for LayoutDom<Range> {
+    #[inline]
+    unsafe fn start_for_layout(&self) -> (LayoutDom<Node>, u32) {
+        let start = &(*self.unsafe_get()).start;
+        (start.node.get_inner_as_layout(), start.offset.get())
+    }
+
+    #[inline]
+    unsafe fn end_for_layout(&self) -> (LayoutDom<Node>, u32) {
+        let end = &(*self.unsafe_get()).end;
+        (end.node.get_inner_as_layout(), end.offset.get())
+    }
+}
+
 #[derive(DenyPublicFields, JSTraceable, MallocSizeOf)]
 #[must_root]
 pub struct BoundaryPoint {
@@ -0,0 +1,452 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::RangeBinding::RangeMethods;
+use dom::bindings::codegen::Bindings::SelectionBinding::{self, SelectionMethods};
+use dom::bindings::error::{Error, ErrorResult, Fallible};
+use dom::bindings::inheritance::Castable;
+use dom::bindings::refcounted::Trusted;
+use dom::bindings::reflector::{Reflector, reflect_dom_object};
+use dom::bindings::root::{Dom, DomRoot, LayoutDom, MutNullableDom};
+use dom::bindings::str::DOMString;
+use dom::characterdata::{CharacterData, LayoutCharacterDataHelpers};
+use dom::document::Document;
+use dom::eventtarget::EventTarget;
+use dom::globalscope::GlobalScope;
+use dom::node::{Node, NodeDamage, NodeFlags};
+use dom::range::{LayoutRangeHelpers, Range};
+use dom::text::Text;
+use dom_struct::dom_struct;
+use script_traits::ScriptMsg;
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::ops;
+use task_source::TaskSource;
+use textinput::len_of_first_n_code_units;
+
+/// <https://w3c.github.io/selection-api/#dfn-direction>
+#[derive(Clone, Copy, JSTraceable, MallocSizeOf, PartialEq)]
+enum Direction {
+    Forwards,
+    Backwards,
+    Directionless,
+}
+
+// https://w3c.github.io/selection-api/#selection-interface
+#[dom_struct]
+pub struct Selection {
+    reflector_: Reflector,
+    document: Dom<Document>,
+    range: MutNullableDom<Range>,
+    direction: Cell<Direction>,
+    /// Whether a task to fire `selectionchange` at the document is already queued.
+    has_pending_selectionchange: Cell<bool>,
+    /// Whether the text nodes painted as selected need to be recomputed before
+    /// the next reflow.
+    needs_repaint: Cell<bool>,
+    /// The text nodes that have the `IS_IN_SELECTION` flag set.
+    painted_nodes: DomRefCell<Vec<Dom<Node>>>,
+}
+
+impl Selection {
+    fn new_inherited(document: &Document) -> Selection {
+        Selection {
+            reflector_: Reflector::new(),
+            document: Dom::from_ref(document),
+            range: MutNullableDom::new(None),
+            direction: Cell::new(Direction::Directionless),
+            has_pending_selectionchange: Cell::new(false),
+            needs_repaint: Cell::new(false),
+            painted_nodes: DomRefCell::new(vec![]),
+        }
+    }
+
+    pub fn new(document: &Document) -> DomRoot<Selection> {
+        reflect_dom_object(Box::new(Selection::new_inherited(document)),
+                           document.window(),
+                           SelectionBinding::Wrap)
+    }
+
+    /// Whether `node`'s root is the document associated with this selection.
+    fn is_in_document(&self, node: &Node) -> bool {
+        &*node.inclusive_ancestors().last().unwrap() == self.document.upcast::<Node>()
+    }
+
+    fn set_range(&self, range: Option<&Range>, direction: Direction) {
+        if let Some(old_range) = self.range.get() {
+            old_range.set_selection(None);
+        }
+        if let Some(range) = range {
+            range.set_selection(Some(self));
+        }
+        self.range.set(range);
+        self.direction.set(direction);
+        self.range_changed();
+    }
+
+    /// Called whenever the range of this selection, or one of its boundary
+    /// points, changed.
+    pub fn range_changed(&self) {
+        self.needs_repaint.set(true);
+        self.queue_selectionchange();
+    }
+
+    /// <https://w3c.github.io/selection-api/#selectionchange-event>
+    fn queue_selectionchange(&self) {
+        if self.has_pending_selectionchange.get() {
+            return;
+        }
+        self.has_pending_selectionchange.set(true);
+
+        let window = self.document.window();
+        let selection = Trusted::new(self);
+        let _ = window.user_interaction_task_source().queue(
+            task!(fire_selectionchange_event: move || {
+                let selection = selection.root();
+                selection.has_pending_selectionchange.set(false);
+                selection.document.upcast::<EventTarget>().fire_event(atom!("selectionchange"));
+                selection.notify_embedder();
+            }),
+            window.upcast(),
+        );
+    }
+
+    /// Let the embedder know about the new selected text, so that it can be copied.
+    fn notify_embedder(&self) {
+        let text = String::from(self.Stringifier());
+        self.document.window().upcast::<GlobalScope>()
+            .script_to_constellation_chan()
+            .send(ScriptMsg::SelectionChanged(text))
+            .unwrap();
+    }
+
+    /// Update the text nodes that layout paints as selected, if the selection
+    /// changed since the last reflow.
+    pub fn flush_for_reflow(&self) {
+        if !self.needs_repaint.get() {
+            return;
+        }
+        self.needs_repaint.set(false);
+
+        let mut painted_nodes = self.painted_nodes.borrow_mut();
+        for node in painted_nodes.drain(..) {
+            node.set_flag(NodeFlags::IS_IN_SELECTION, false);
+            node.dirty(NodeDamage::OtherNodeDamage);
+        }
+
+        let range = match self.range.get() {
+            Some(range) => range,
+            None => return,
+        };
+        if range.Collapsed() {
+            return;
+        }
+        for text in range.text_nodes() {
+            let node = text.upcast::<Node>();
+            node.set_flag(NodeFlags::IS_IN_SELECTION, true);
+            node.dirty(NodeDamage::OtherNodeDamage);
+            painted_nodes.push(Dom::from_ref(node));
+        }
+    }
+}
+
+impl SelectionMethods for Selection {
+    // https://w3c.github.io/selection-api/#dom-selection-anchornode
+    fn GetAnchorNode(&self) -> Option<DomRoot<Node>> {
+        self.range.get().map(|range| match self.direction.get() {
+            Direction::Backwards => range.EndContainer(),
+            _ => range.StartContainer(),
+        })
+    }
+
+    // https://w3c.github.io/selection-api/#dom-selection-anchoroffset
+    fn AnchorOffset(&self) -> u32 {
+        self.range.get().map_or(0, |range| match self.direction.get() {
+            Direction::Backwards => range.EndOffset(),
+            _ => range.StartOffset(),
+        })
+    }
+
+    // https://w3c.github.io/selection-api/#dom-selection-focusnode
+    fn GetFocusNode(&self) -> Option<DomRoot<Node>> {
+        self.range.get().map(|range| match self.direction.get() {
+            Direction::Backwards => range.StartContainer(),
+            _ => range.EndContainer(),
+        })
+    }
+
+    // https://w3c.github.io/selection-api/#dom-selection-focusoffset
+    fn FocusOffset(&self) -> u32 {
+        self.range.get().map_or(0, |range| match self.direction.get() {
+            Direction::Backwards => range.StartOffset(),
+            _ => range.EndOffset(),
+        })
+    }
+
+    // https://w3c.github.io/selection-api/#dom-selection-iscollapsed
+    fn IsCollapsed(&self) -> bool {
+        self.range.get().map_or(true, |range| range.Collapsed())
+    }
+
+    // https://w3c.github.io/selection-api/#dom-selection-rangecount
+    fn RangeCount(&self) -> u32 {
+        if self.range.get().is_some() { 1 } else { 0 }
+    }
+
+    // https://w3c.github.io/selection-api/#dom-selection-type
+    fn Type(&self) -> DOMString {
+        DOMString::from(match self.range.get() {
+            None => "None",
+            Some(ref range) if range.Collapsed() => "Caret",
+            Some(_) => "Range",
+        })
+    }
+
+    // https://w3c.github.io/selection-api/#dom-selection-getrangeat
+    fn GetRangeAt(&self, index: u32) -> Fallible<DomRoot<Range>> {
+        match self.range.get() {
+            Some(range) if index == 0 => Ok(range),
+            _ => Err(Error::IndexSize),
+        }
+    }
+
+    // https://w3c.github.io/selection-api/#dom-selection-addrange
+    fn AddRange(&self, range: &Range) {
+        // Step 1.
+        if !self.is_in_document(&range.StartContainer()) {
+            return;
+        }
+        // Step 2.
+        if self.range.get().is_some() {
+            return;
+        }
+        // Step 3.
+        self.set_range(Some(range), Direction::Forwards);
+    }
+
+    // https://w3c.github.io/selection-api/#dom-selection-removerange
+    fn RemoveRange(&self, range: &Range) -> ErrorResult {
+        match self.range.get() {
+            Some(ref selected) if &**selected == range => {
+                self.set_range(None, Direction::Directionless);
+                Ok(())
+            },
+            _ => Err(Error::NotFound),
+        }
+    }
+
+    // https://w3c.github.io/selection-api/#dom-selection-removeallranges
+    fn RemoveAllRanges(&self) {
+        if self.range.get().is_some() {
+            self.set_range(None, Direction::Directionless);
+        }
+    }
+
+    // https://w3c.github.io/selection-api/#dom-selection-empty
+    fn Empty(&self) {
+        self.RemoveAllRanges()
+    }
+
+    // https://w3c.github.io/selection-api/#dom-selection-collapse
+    fn Collapse(&self, node: Option<&Node>, offset: u32) -> ErrorResult {
+        // Step 1.
+        let node = match node {
+            Some(node) => node,
+            None => {
+                self.RemoveAllRanges();
+                return Ok(());
+            },
+        };
+        // Step 2.
+        if node.is_doctype() {
+            return Err(Error::InvalidNodeType);
+        }
+        // Step 3.
+        if offset > node.len() {
+            return Err(Error::IndexSize);
+        }
+        // Step 4.
+        if !self.is_in_document(node) {
+            return Ok(());
+        }
+        // Steps 5-6.
+        let range = Range::new(&self.document, node, offset, node, offset);
+        self.set_range(Some(&range), Direction::Directionless);
+        Ok(())
+    }
+
+    // https://w3c.github.io/selection-api/#dom-selection-setposition
+    fn SetPosition(&self, node: Option<&Node>, offset: u32) -> ErrorResult {
+        self.Collapse(node, offset)
+    }
+
+    // https://w3c.github.io/selection-api/#dom-selection-collapsetostart
+    fn CollapseToStart(&self) -> ErrorResult {
+        // Step 1.
+        let range = self.range.get().ok_or(Error::InvalidState)?;
+        // Steps 2-3.
+        let (node, offset) = (range.StartContainer(), range.StartOffset());
+        let range = Range::new(&self.document, &node, offset, &node, offset);
+        self.set_range(Some(&range), Direction::Directionless);
+        Ok(())
+    }
+
+    // https://w3c.github.io/selection-api/#dom-selection-collapsetoend
+    fn CollapseToEnd(&self) -> ErrorResult {
+        // Step 1.
+        let range = self.range.get().ok_or(Error::InvalidState)?;
+        // Steps 2-3.
+        let (node, offset) = (range.EndContainer(), range.EndOffset());
+        let range = Range::new(&self.document, &node, offset, &node, offset);
+        self.set_range(Some(&range), Direction::Directionless);
+        Ok(())
+    }
+
+    // https://w3c.github.io/selection-api/#dom-selection-extend
+    fn Extend(&self, node: &Node, offset: u32) -> ErrorResult {
+        // Step 1.
+        if !self.is_in_document(node) {
+            return Ok(());
+        }
+        // Step 2.
+        if self.range.get().is_none() {
+            return Err(Error::InvalidState);
+        }
+        // Steps 3-4.
+        let anchor_node = self.GetAnchorNode().unwrap();
+        let anchor_offset = self.AnchorOffset();
+        // Steps 5-7.
+        let range = Range::new(&self.document, &anchor_node, anchor_offset, &anchor_node, anchor_offset);
+        let direction = match range.compare_point(node, offset)? {
+            Ordering::Less => {
+                range.SetStart(node, offset)?;
+                Direction::Backwards
+            },
+            _ => {
+                range.SetEnd(node, offset)?;
+                Direction::Forwards
+            },
+        };
+        // Steps 8-9.
+        self.set_range(Some(&range), direction);
+        Ok(())
+    }
+
+    // https://w3c.github.io/selection-api/#dom-selection-setbaseandextent
+    fn SetBaseAndExtent(&self,
+                        anchor_node: &Node,
+                        anchor_offset: u32,
+                        focus_node: &Node,
+                        focus_offset: u32)
+                        -> ErrorResult {
+        // Step 1.
+        if anchor_offset > anchor_node.len() || focus_offset > focus_node.len() {
+            return Err(Error::IndexSize);
+        }
+        if anchor_node.is_doctype() || focus_node.is_doctype() {
+            return Err(Error::InvalidNodeType);
+        }
+        // Step 2.
+        if !self.is_in_document(anchor_node) || !self.is_in_document(focus_node) {
+            return Ok(());
+        }
+        // Steps 3-6.
+        let range = Range::new(&self.document, anchor_node, anchor_offset, anchor_node, anchor_offset);
+        let direction = match range.compare_point(focus_node, focus_offset)? {
+            Ordering::Less => {
+                range.SetStart(focus_node, focus_offset)?;
+                Direction::Backwards
+            },
+            _ => {
+                range.SetEnd(focus_node, focus_offset)?;
+                Direction::Forwards
+            },
+        };
+        // Steps 7-8.
+        self.set_range(Some(&range), direction);
+        Ok(())
+    }
+
+    // https://w3c.github.io/selection-api/#dom-selection-selectallchildren
+    fn SelectAllChildren(&self, node: &Node) -> ErrorResult {
+        // Step 1.
+        if node.is_doctype() {
+            return Err(Error::InvalidNodeType);
+        }
+        // Step 2.
+        if !self.is_in_document(node) {
+            return Ok(());
+        }
+        // Steps 3-6.
+        let range = Range::new(&self.document, node, 0, node, node.children_count());
+        self.set_range(Some(&range), Direction::Forwards);
+        Ok(())
+    }
+
+    // https://w3c.github.io/selection-api/#dom-selection-deletefromdocument
+    fn DeleteFromDocument(&self) -> ErrorResult {
+        match self.range.get() {
+            Some(range) => range.DeleteContents(),
+            None => Ok(()),
+        }
+    }
+
+    // https://w3c.github.io/selection-api/#dom-selection-containsnode
+    fn ContainsNode(&self, node: &Node, allow_partial_containment: bool) -> bool {
+        // Step 1.
+        if !self.is_in_document(node) {
+            return false;
+        }
+        let range = match self.range.get() {
+            Some(range) => range,
+            None => return false,
+        };
+        // Steps 2-3.
+        let (start, end) = match (range.compare_point(node, 0), range.compare_point(node, node.len())) {
+            (Ok(start), Ok(end)) => (start, end),
+            _ => return false,
+        };
+        if allow_partial_containment {
+            start != Ordering::Greater && end != Ordering::Less
+        } else {
+            start == Ordering::Equal && end == Ordering::Equal
+        }
+    }
+
+    // https://w3c.github.io/selection-api/#dom-selection-stringifier
+    fn Stringifier(&self) -> DOMString {
+        self.range.get().map_or_else(DOMString::new, |range| range.Stringifier())
+    }
+}
+
+#[allow(unsafe_code)]
+pub trait LayoutSelectionHelpers {
+    unsafe fn selected_text_for_layout(&self, text: LayoutDom<Text>) -> Option<ops::Range<usize>>;
+}
+
+#[allow(unsafe_code)]
+impl LayoutSelectionHelpers for LayoutDom<Selection> {
+    /// The byte range of the data of `text` that is selected, if any.
+    unsafe fn selected_text_for_layout(&self, text: LayoutDom<Text>) -> Option<ops::Range<usize>> {
+        let range = (*self.unsafe_get()).range.get_inner_as_layout()?;
+        let data = text.upcast::<CharacterData>().data_for_layout();
+        let node = text.upcast::<Node>();
+
+        let (start_node, start_offset) = range.start_for_layout();
+        let start = if start_node == node {
+            len_of_first_n_code_units(data, start_offset as usize)
+        } else {
+            0
+        };
+        let (end_node, end_offset) = range.end_for_layout();
+        let end = if end_node == node {
+            len_of_first_n_code_units(data, end_offset as usize)
+        } else {
+            data.len()
+        };
+
+        if start < end { Some(start..end) } else { None }
+    }
+}
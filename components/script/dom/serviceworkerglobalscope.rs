@@ -23,6 +23,7 @@ use ipc_channel::router::ROUTER;
 use js::jsapi::{JS_SetInterruptCallback, JSAutoCompartment, JSContext};
 use js::jsval::UndefinedValue;
 use net_traits::{load_whole_resource, IpcSend, CustomResponseMediator};
+use net_traits::csp::CspList;
 use net_traits::request::{CredentialsMode, Destination, RequestInit};
 use script_runtime::{CommonScriptMsg, ScriptChan, new_rt_and_cx, Runtime};
 use script_traits::{TimerEvent, WorkerGlobalScopeInit, ScopeThings, ServiceWorkerMsg, WorkerScriptLoadOrigin};
@@ -146,7 +147,7 @@ impl ServiceWorkerGlobalScope {
                             swmanager_sender: IpcSender<ServiceWorkerMsg>,
                             scope_url: ServoUrl) {
         let ScopeThings { script_url,
-                          init,
+                          mut init,
                           worker_load_origin,
                           .. } = scope_things;
 
@@ -168,6 +169,7 @@ impl ServiceWorkerGlobalScope {
                 referrer_url: referrer_url,
                 referrer_policy: referrer_policy,
                 origin,
+                csp_list: init.csp_list.clone(),
                 .. RequestInit::default()
             };
 
@@ -178,6 +180,8 @@ impl ServiceWorkerGlobalScope {
                     return;
                 }
                 Ok((metadata, bytes)) => {
+                    init.csp_list = CspList::for_worker(&init.csp_list, &metadata.final_url,
+                                                        metadata.headers.as_ref().map(|headers| &**headers));
                     (metadata.final_url, String::from_utf8(bytes).unwrap())
                 }
             };
@@ -0,0 +1,146 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::cell::DomRefCell;
+use dom::bindings::codegen::Bindings::ShadowRootBinding::{self, ShadowRootMethods, ShadowRootMode};
+use dom::bindings::inheritance::Castable;
+use dom::bindings::root::{Dom, DomRoot, LayoutDom};
+use dom::documentfragment::DocumentFragment;
+use dom::element::Element;
+use dom::node::{Node, NodeFlags, document_from_node};
+use dom_struct::dom_struct;
+use servo_arc::Arc;
+use style::context::QuirksMode;
+use style::media_queries::Device;
+use style::shared_lock::SharedRwLockReadGuard;
+use style::stylesheets::{DocumentStyleSheet, Origin, Stylesheet};
+use style::stylist::{CascadeData, ShadowRootStyles};
+
+// https://dom.spec.whatwg.org/#interface-shadowroot
+#[dom_struct]
+pub struct ShadowRoot {
+    document_fragment: DocumentFragment,
+    /// <https://dom.spec.whatwg.org/#concept-documentfragment-host>
+    host: Dom<Element>,
+    /// <https://dom.spec.whatwg.org/#shadowroot-mode>
+    mode: ShadowRootMode,
+    /// The owners of the stylesheets in `author_styles`, in the same order.
+    stylesheet_owners: DomRefCell<Vec<Dom<Element>>>,
+    /// The stylesheets of this shadow tree, and the rules in them. These are
+    /// flushed by layout before styling the shadow tree.
+    author_styles: DomRefCell<ShadowRootStyles>,
+}
+
+impl ShadowRoot {
+    fn new_inherited(host: &Element, mode: ShadowRootMode) -> ShadowRoot {
+        let document_fragment = DocumentFragment::new_inherited(&document_from_node(host));
+        document_fragment.upcast::<Node>().set_flag(NodeFlags::IS_IN_SHADOW_TREE, true);
+        ShadowRoot {
+            document_fragment: document_fragment,
+            host: Dom::from_ref(host),
+            mode: mode,
+            stylesheet_owners: DomRefCell::new(vec![]),
+            author_styles: DomRefCell::new(ShadowRootStyles::new()),
+        }
+    }
+
+    pub fn new(host: &Element, mode: ShadowRootMode) -> DomRoot<ShadowRoot> {
+        let document = document_from_node(host);
+        Node::reflect_node(Box::new(ShadowRoot::new_inherited(host, mode)),
+                           &document,
+                           ShadowRootBinding::Wrap)
+    }
+
+    pub fn host(&self) -> DomRoot<Element> {
+        DomRoot::from_ref(&self.host)
+    }
+
+    /// Add a stylesheet owned by `owner`, which must be in this shadow tree,
+    /// in the correct tree position.
+    #[allow(unrooted_must_root)] // Owner needs to be rooted already necessarily.
+    pub fn add_stylesheet(&self, owner: &Element, sheet: Arc<Stylesheet>) {
+        let mut owners = self.stylesheet_owners.borrow_mut();
+        let mut styles = self.author_styles.borrow_mut();
+        let insertion_point = owners.iter().position(|other| {
+            owner.upcast::<Node>().is_before(other.upcast())
+        });
+
+        let document = document_from_node(self);
+        let guard = document.style_shared_lock().read();
+        let sheet = DocumentStyleSheet(sheet);
+        match insertion_point {
+            Some(index) => {
+                let before = styles.stylesheets.get(Origin::Author, index).unwrap().clone();
+                styles.stylesheets.insert_stylesheet_before(None, sheet, before, &guard);
+                owners.insert(index, Dom::from_ref(owner));
+            },
+            None => {
+                styles.stylesheets.append_stylesheet(None, sheet, &guard);
+                owners.push(Dom::from_ref(owner));
+            },
+        }
+    }
+
+    /// Remove a stylesheet owned by `owner`, and return whether it was in
+    /// this shadow tree.
+    pub fn remove_stylesheet(&self, owner: &Element, sheet: &Arc<Stylesheet>) -> bool {
+        let mut styles = self.author_styles.borrow_mut();
+        let index = styles.stylesheets
+            .iter()
+            .position(|(s, _origin)| Arc::ptr_eq(&s.0, sheet));
+        let index = match index {
+            Some(index) => index,
+            None => return false,
+        };
+        debug_assert!(&*self.stylesheet_owners.borrow()[index] == owner);
+        self.stylesheet_owners.borrow_mut().remove(index);
+
+        let guard = sheet.shared_lock.read();
+        styles.stylesheets.remove_stylesheet(None, DocumentStyleSheet(sheet.clone()), &guard);
+        true
+    }
+}
+
+impl ShadowRootMethods for ShadowRoot {
+    // https://dom.spec.whatwg.org/#dom-shadowroot-mode
+    fn Mode(&self) -> ShadowRootMode {
+        self.mode
+    }
+
+    // https://dom.spec.whatwg.org/#dom-shadowroot-host
+    fn Host(&self) -> DomRoot<Element> {
+        self.host()
+    }
+}
+
+#[allow(unsafe_code)]
+pub trait LayoutShadowRootHelpers {
+    unsafe fn get_host_for_layout(&self) -> LayoutDom<Element>;
+    unsafe fn get_style_data_for_layout<'a>(&self) -> &'a CascadeData;
+    unsafe fn flush_stylesheets_for_layout(&self,
+                                           device: &Device,
+                                           quirks_mode: QuirksMode,
+                                           guard: &SharedRwLockReadGuard) -> bool;
+}
+
+#[allow(unsafe_code)]
+impl LayoutShadowRootHelpers for LayoutDom<ShadowRoot> {
+    #[inline]
+    unsafe fn get_host_for_layout(&self) -> LayoutDom<Element> {
+        (*self.unsafe_get()).host.to_layout()
+    }
+
+    #[inline]
+    unsafe fn get_style_data_for_layout<'a>(&self) -> &'a CascadeData {
+        &(*self.unsafe_get()).author_styles.borrow_for_layout().data
+    }
+
+    #[inline]
+    unsafe fn flush_stylesheets_for_layout(&self,
+                                           device: &Device,
+                                           quirks_mode: QuirksMode,
+                                           guard: &SharedRwLockReadGuard) -> bool {
+        (*self.unsafe_get()).author_styles.borrow_mut_for_layout().flush(device, quirks_mode, guard)
+    }
+}
@@ -1,9 +1,131 @@
 /* This Source Code Form is subject to the terms of the Mozilla Public
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
-use dom::validitystate::ValidationFlags;
+use dom::bindings::codegen::Bindings::EventBinding::EventMethods;
+use dom::bindings::codegen::Bindings::HTMLElementBinding::HTMLElementMethods;
+use dom::bindings::inheritance::Castable;
+use dom::bindings::root::DomRoot;
+use dom::bindings::str::DOMString;
+use dom::element::Element;
+use dom::eventtarget::EventTarget;
+use dom::htmlelement::HTMLElement;
+use dom::validitystate::{ValidationFlags, ValidityState};
+use style::element_state::ElementState;
 
+/// Trait for elements with constraint validation support
 pub trait Validatable {
-    fn is_instance_validatable(&self) -> bool { true }
-    fn validate(&self, _validate_flags: ValidationFlags) -> bool { true }
+    fn as_element(&self) -> &Element;
+
+    /// <https://html.spec.whatwg.org/multipage/#dom-cva-validity>
+    fn validity_state(&self) -> DomRoot<ValidityState>;
+
+    /// <https://html.spec.whatwg.org/multipage/#candidate-for-constraint-validation>
+    fn is_instance_validatable(&self) -> bool;
+
+    /// Check the element-specific constraints selected by `validate_flags`,
+    /// returning the ones the element suffers from.
+    fn perform_validation(&self, _validate_flags: ValidationFlags) -> ValidationFlags {
+        ValidationFlags::empty()
+    }
+
+    /// Like `perform_validation`, but also takes the custom validity error
+    /// message into account.
+    fn validate(&self, validate_flags: ValidationFlags) -> ValidationFlags {
+        let mut failed_flags = self.perform_validation(validate_flags);
+        if validate_flags.contains(ValidationFlags::CUSTOM_ERROR) &&
+           !self.validity_state().custom_error_message().is_empty() {
+            failed_flags.insert(ValidationFlags::CUSTOM_ERROR);
+        }
+        failed_flags
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#concept-fv-valid>
+    fn is_valid(&self) -> bool {
+        self.validate(ValidationFlags::all()).is_empty()
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#check-validity-steps>
+    fn check_validity(&self) -> bool {
+        if !self.is_instance_validatable() || self.is_valid() {
+            return true;
+        }
+
+        self.as_element().upcast::<EventTarget>().fire_cancelable_event(atom!("invalid"));
+        false
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#report-validity-steps>
+    fn report_validity(&self) -> bool {
+        if !self.is_instance_validatable() || self.is_valid() {
+            return true;
+        }
+
+        let event = self.as_element().upcast::<EventTarget>().fire_cancelable_event(atom!("invalid"));
+        if !event.DefaultPrevented() {
+            self.report_problems();
+        }
+        false
+    }
+
+    /// Report the problems with the constraints of this element to the user.
+    /// There is no validation UI yet, so this focuses the element and logs
+    /// the validation message.
+    fn report_problems(&self) {
+        if let Some(html_element) = self.as_element().downcast::<HTMLElement>() {
+            html_element.Focus();
+        }
+        warn!("Invalid form control: {}", self.validation_message());
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#dom-cva-validationmessage>
+    fn validation_message(&self) -> DOMString {
+        if !self.is_instance_validatable() {
+            return DOMString::new();
+        }
+
+        let failed_flags = self.validate(ValidationFlags::all());
+        if failed_flags.contains(ValidationFlags::CUSTOM_ERROR) {
+            return self.validity_state().custom_error_message();
+        }
+        DOMString::from(default_validation_message(failed_flags))
+    }
+
+    /// Update the `:valid` and `:invalid` state of the element. Elements
+    /// barred from constraint validation match neither.
+    fn update_validity_state(&self) {
+        let (valid, invalid) = if self.is_instance_validatable() {
+            let valid = self.is_valid();
+            (valid, !valid)
+        } else {
+            (false, false)
+        };
+
+        let element = self.as_element();
+        element.set_state(ElementState::IN_VALID_STATE, valid);
+        element.set_state(ElementState::IN_INVALID_STATE, invalid);
+    }
+}
+
+fn default_validation_message(failed_flags: ValidationFlags) -> &'static str {
+    if failed_flags.contains(ValidationFlags::VALUE_MISSING) {
+        "Please fill out this field."
+    } else if failed_flags.contains(ValidationFlags::TYPE_MISMATCH) {
+        "Please enter a value of the expected type."
+    } else if failed_flags.contains(ValidationFlags::PATTERN_MISMATCH) {
+        "Please match the requested format."
+    } else if failed_flags.contains(ValidationFlags::TOO_LONG) {
+        "Please shorten this text."
+    } else if failed_flags.contains(ValidationFlags::TOO_SHORT) {
+        "Please lengthen this text."
+    } else if failed_flags.contains(ValidationFlags::RANGE_UNDERFLOW) {
+        "Please select a value that is no less than the minimum."
+    } else if failed_flags.contains(ValidationFlags::RANGE_OVERFLOW) {
+        "Please select a value that is no more than the maximum."
+    } else if failed_flags.contains(ValidationFlags::STEP_MISMATCH) {
+        "Please select a valid value."
+    } else if failed_flags.contains(ValidationFlags::BAD_INPUT) {
+        "Please enter a valid value."
+    } else {
+        ""
+    }
 }
@@ -2,31 +2,18 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use dom::bindings::cell::DomRefCell;
 use dom::bindings::codegen::Bindings::ValidityStateBinding;
 use dom::bindings::codegen::Bindings::ValidityStateBinding::ValidityStateMethods;
 use dom::bindings::reflector::{Reflector, reflect_dom_object};
 use dom::bindings::root::{Dom, DomRoot};
+use dom::bindings::str::DOMString;
 use dom::element::Element;
+use dom::validation::Validatable;
 use dom::window::Window;
 use dom_struct::dom_struct;
 
 // https://html.spec.whatwg.org/multipage/#validity-states
-#[derive(JSTraceable, MallocSizeOf)]
-#[allow(dead_code)]
-pub enum ValidityStatus {
-    ValueMissing,
-    TypeMismatch,
-    PatternMismatch,
-    TooLong,
-    TooShort,
-    RangeUnderflow,
-    RangeOverflow,
-    StepMismatch,
-    BadInput,
-    CustomError,
-    Valid
-}
-
 bitflags!{
     pub struct ValidationFlags: u32 {
         const VALUE_MISSING    = 0b0000000001;
@@ -47,16 +34,16 @@ bitflags!{
 pub struct ValidityState {
     reflector_: Reflector,
     element: Dom<Element>,
-    state: ValidityStatus
+    // https://html.spec.whatwg.org/multipage/#custom-validity-error-message
+    custom_error_message: DomRefCell<DOMString>,
 }
 
-
 impl ValidityState {
     fn new_inherited(element: &Element) -> ValidityState {
         ValidityState {
             reflector_: Reflector::new(),
             element: Dom::from_ref(element),
-            state: ValidityStatus::Valid
+            custom_error_message: DomRefCell::new(DOMString::new()),
         }
     }
 
@@ -65,61 +52,85 @@ impl ValidityState {
                            window,
                            ValidityStateBinding::Wrap)
     }
+
+    // https://html.spec.whatwg.org/multipage/#custom-validity-error-message
+    pub fn custom_error_message(&self) -> DOMString {
+        self.custom_error_message.borrow().clone()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-cva-setcustomvalidity
+    pub fn set_custom_error_message(&self, error: DOMString) {
+        *self.custom_error_message.borrow_mut() = error;
+        if let Some(validatable) = self.element.as_maybe_validatable() {
+            validatable.update_validity_state();
+        }
+    }
+
+    /// Returns which of the constraints in `validate_flags` the element
+    /// suffers from.
+    fn invalid_flags(&self, validate_flags: ValidationFlags) -> ValidationFlags {
+        match self.element.as_maybe_validatable() {
+            Some(validatable) => validatable.validate(validate_flags),
+            None if validate_flags.contains(ValidationFlags::CUSTOM_ERROR) &&
+                    !self.custom_error_message.borrow().is_empty() => ValidationFlags::CUSTOM_ERROR,
+            None => ValidationFlags::empty(),
+        }
+    }
 }
 
 impl ValidityStateMethods for ValidityState {
     // https://html.spec.whatwg.org/multipage/#dom-validitystate-valuemissing
     fn ValueMissing(&self) -> bool {
-        false
+        !self.invalid_flags(ValidationFlags::VALUE_MISSING).is_empty()
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-validitystate-typemismatch
     fn TypeMismatch(&self) -> bool {
-        false
+        !self.invalid_flags(ValidationFlags::TYPE_MISMATCH).is_empty()
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-validitystate-patternmismatch
     fn PatternMismatch(&self) -> bool {
-        false
+        !self.invalid_flags(ValidationFlags::PATTERN_MISMATCH).is_empty()
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-validitystate-toolong
     fn TooLong(&self) -> bool {
-        false
+        !self.invalid_flags(ValidationFlags::TOO_LONG).is_empty()
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-validitystate-tooshort
     fn TooShort(&self) -> bool {
-        false
+        !self.invalid_flags(ValidationFlags::TOO_SHORT).is_empty()
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-validitystate-rangeunderflow
     fn RangeUnderflow(&self) -> bool {
-        false
+        !self.invalid_flags(ValidationFlags::RANGE_UNDERFLOW).is_empty()
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-validitystate-rangeoverflow
     fn RangeOverflow(&self) -> bool {
-        false
+        !self.invalid_flags(ValidationFlags::RANGE_OVERFLOW).is_empty()
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-validitystate-stepmismatch
     fn StepMismatch(&self) -> bool {
-        false
+        !self.invalid_flags(ValidationFlags::STEP_MISMATCH).is_empty()
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-validitystate-badinput
     fn BadInput(&self) -> bool {
-        false
+        !self.invalid_flags(ValidationFlags::BAD_INPUT).is_empty()
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-validitystate-customerror
     fn CustomError(&self) -> bool {
-        false
+        !self.invalid_flags(ValidationFlags::CUSTOM_ERROR).is_empty()
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-validitystate-valid
     fn Valid(&self) -> bool {
-        false
+        self.invalid_flags(ValidationFlags::all()).is_empty()
     }
 }
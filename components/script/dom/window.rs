@@ -36,14 +36,17 @@ use dom::event::Event;
 use dom::globalscope::GlobalScope;
 use dom::history::History;
 use dom::htmliframeelement::build_mozbrowser_custom_event;
+use dom::idbfactory::IDBFactory;
 use dom::location::Location;
 use dom::mediaquerylist::{MediaQueryList, WeakMediaQueryListVec};
 use dom::messageevent::MessageEvent;
+use dom::mutationobserver::MutationObserver;
 use dom::navigator::Navigator;
 use dom::node::{Node, NodeDamage, document_from_node, from_untrusted_node_address};
 use dom::performance::Performance;
 use dom::promise::Promise;
 use dom::screen::Screen;
+use dom::selection::Selection;
 use dom::storage::Storage;
 use dom::testrunner::TestRunner;
 use dom::windowproxy::WindowProxy;
@@ -79,6 +82,7 @@ use script_thread::{ImageCacheMsg, MainThreadScriptChan, MainThreadScriptMsg};
 use script_thread::{ScriptThread, SendableMainThreadScriptChan};
 use script_traits::{ConstellationControlMsg, DocumentState, LoadData, MozBrowserEvent};
 use script_traits::{ScriptToConstellationChan, ScriptMsg, ScrollState, TimerEvent, TimerEventId};
+use script_traits::{SimpleDialog, SimpleDialogResponse};
 use script_traits::{TimerSchedulerMsg, UntrustedNodeAddress, WindowSizeData, WindowSizeType};
 use script_traits::webdriver_msg::{WebDriverJSError, WebDriverJSResult};
 use selectors::attr::CaseSensitivity;
@@ -118,7 +122,7 @@ use task_source::user_interaction::UserInteractionTaskSource;
 use time;
 use timers::{IsInterval, TimerCallback};
 #[cfg(any(target_os = "macos", target_os = "linux", target_os = "windows"))]
-use tinyfiledialogs::{self, MessageBoxIcon};
+use tinyfiledialogs::{self, MessageBoxIcon, YesNo};
 use url::Position;
 use webdriver_handlers::jsval_to_webdriver;
 use webrender_api::{ClipId, DocumentId};
@@ -184,6 +188,7 @@ pub struct Window {
     history: MutNullableDom<History>,
     custom_element_registry: MutNullableDom<CustomElementRegistry>,
     performance: MutNullableDom<Performance>,
+    indexed_db: MutNullableDom<IDBFactory>,
     navigation_start: Cell<u64>,
     navigation_start_precise: Cell<u64>,
     screen: MutNullableDom<Screen>,
@@ -197,6 +202,9 @@ pub struct Window {
     #[ignore_malloc_size_of = "channels are hard"]
     devtools_marker_sender: DomRefCell<Option<IpcSender<Option<TimelineMarker>>>>,
 
+    /// The observer reporting the changes to the DOM tree to devtools, while they follow it.
+    devtools_mutation_observer: MutNullableDom<MutationObserver>,
+
     /// Pending resize event, if any.
     resize_event: Cell<Option<(WindowSizeData, WindowSizeType)>>,
 
@@ -448,6 +456,37 @@ fn display_alert_dialog(_message: &str) {
     // tinyfiledialogs not supported on Android
 }
 
+#[cfg(any(target_os = "macos", target_os = "linux", target_os = "windows"))]
+fn display_confirm_dialog(message: &str) -> bool {
+    if opts::get().headless {
+        return false;
+    }
+    match tinyfiledialogs::message_box_yes_no("Confirm", message, MessageBoxIcon::Question, YesNo::No) {
+        YesNo::Yes => true,
+        YesNo::No => false,
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+fn display_confirm_dialog(_message: &str) -> bool {
+    // tinyfiledialogs not supported on Android
+    false
+}
+
+#[cfg(any(target_os = "macos", target_os = "linux", target_os = "windows"))]
+fn display_prompt_dialog(message: &str, default: &str) -> Option<String> {
+    if opts::get().headless {
+        return None;
+    }
+    tinyfiledialogs::input_box("Prompt", message, default)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+fn display_prompt_dialog(_message: &str, _default: &str) -> Option<String> {
+    // tinyfiledialogs not supported on Android
+    None
+}
+
 // https://html.spec.whatwg.org/multipage/#atob
 pub fn base64_btoa(input: DOMString) -> Fallible<DOMString> {
     // "The btoa() method must throw an InvalidCharacterError exception if
@@ -534,12 +573,27 @@ impl WindowMethods for Window {
             stderr.flush().unwrap();
         }
 
-        let (sender, receiver) = ipc::channel().unwrap();
-        self.send_to_constellation(ScriptMsg::Alert(s.to_string(), sender));
+        match self.show_simple_dialog(SimpleDialog::Alert(s.to_string())) {
+            SimpleDialogResponse::Display => display_alert_dialog(&s),
+            SimpleDialogResponse::Accept(_) | SimpleDialogResponse::Dismiss => {},
+        }
+    }
 
-        let should_display_alert_dialog = receiver.recv().unwrap();
-        if should_display_alert_dialog {
-            display_alert_dialog(&s);
+    // https://html.spec.whatwg.org/multipage/#dom-confirm
+    fn Confirm(&self, message: DOMString) -> bool {
+        match self.show_simple_dialog(SimpleDialog::Confirm(message.to_string())) {
+            SimpleDialogResponse::Display => display_confirm_dialog(&message),
+            SimpleDialogResponse::Accept(_) => true,
+            SimpleDialogResponse::Dismiss => false,
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-prompt
+    fn Prompt(&self, message: DOMString, default: DOMString) -> Option<DOMString> {
+        match self.show_simple_dialog(SimpleDialog::Prompt(message.to_string(), default.to_string())) {
+            SimpleDialogResponse::Display => display_prompt_dialog(&message, &default).map(DOMString::from),
+            SimpleDialogResponse::Accept(value) => Some(DOMString::from(value)),
+            SimpleDialogResponse::Dismiss => None,
         }
     }
 
@@ -716,6 +770,11 @@ impl WindowMethods for Window {
         })
     }
 
+    // https://w3c.github.io/IndexedDB/#dom-windoworworkerglobalscope-indexeddb
+    fn IndexedDB(&self) -> DomRoot<IDBFactory> {
+        self.indexed_db.or_init(|| IDBFactory::new(self.upcast()))
+    }
+
     // https://html.spec.whatwg.org/multipage/#globaleventhandlers
     global_event_handlers!();
 
@@ -823,6 +882,11 @@ impl WindowMethods for Window {
         }
     }
 
+    // https://w3c.github.io/selection-api/#dom-window-getselection
+    fn GetSelection(&self) -> Option<DomRoot<Selection>> {
+        self.Document().GetSelection()
+    }
+
     // https://drafts.csswg.org/cssom/#dom-window-getcomputedstyle
     fn GetComputedStyle(&self,
                         element: &Element,
@@ -1318,6 +1382,10 @@ impl Window {
     pub fn reflow(&self, reflow_goal: ReflowGoal, reason: ReflowReason) -> bool {
         let for_display = reflow_goal == ReflowGoal::Full;
 
+        // Dirty the text nodes whose selected state changed, so that they
+        // get repainted.
+        self.Document().flush_selection_for_reflow();
+
         let mut issued_reflow = false;
         if !for_display || self.Document().needs_reflow() {
             issued_reflow = self.force_reflow(reflow_goal, reason);
@@ -1491,6 +1559,15 @@ impl Window {
         self.layout_rpc.text_index()
     }
 
+    /// Returns the byte offset into the data of the text node `node` of the character
+    /// closest to `point`, in page coordinates.
+    pub fn text_offset_query(&self, node: TrustedNodeAddress, point: Point2D<f32>) -> TextIndexResponse {
+        if !self.reflow(ReflowGoal::TextOffsetQuery(node, point), ReflowReason::Query) {
+            return TextIndexResponse(None);
+        }
+        self.layout_rpc.text_index()
+    }
+
     #[allow(unsafe_code)]
     pub fn init_window_proxy(&self, window_proxy: &WindowProxy) {
         assert!(self.window_proxy.get().is_none());
@@ -1675,6 +1752,14 @@ impl Window {
         }
     }
 
+    pub fn devtools_mutation_observer(&self) -> Option<DomRoot<MutationObserver>> {
+        self.devtools_mutation_observer.get()
+    }
+
+    pub fn set_devtools_mutation_observer(&self, observer: Option<&MutationObserver>) {
+        self.devtools_mutation_observer.set(observer);
+    }
+
     pub fn set_webdriver_script_chan(&self, chan: Option<IpcSender<WebDriverJSResult>>) {
         *self.webdriver_script_chan.borrow_mut() = chan;
     }
@@ -1743,6 +1828,13 @@ impl Window {
             .unwrap();
     }
 
+    /// Ask the constellation to show a simple dialog, blocking until it has been answered.
+    fn show_simple_dialog(&self, dialog: SimpleDialog) -> SimpleDialogResponse {
+        let (sender, receiver) = ipc::channel().unwrap();
+        self.send_to_constellation(ScriptMsg::ShowSimpleDialog(dialog, sender));
+        receiver.recv().unwrap_or(SimpleDialogResponse::Dismiss)
+    }
+
     pub fn webrender_document(&self) -> DocumentId {
         self.webrender_document
     }
@@ -1820,6 +1912,7 @@ impl Window {
             window_proxy: Default::default(),
             document: Default::default(),
             performance: Default::default(),
+            indexed_db: Default::default(),
             navigation_start: Cell::new(navigation_start),
             navigation_start_precise: Cell::new(navigation_start_precise),
             screen: Default::default(),
@@ -1842,6 +1935,7 @@ impl Window {
             current_state: Cell::new(WindowState::Alive),
             devtools_marker_sender: Default::default(),
             devtools_markers: Default::default(),
+            devtools_mutation_observer: Default::default(),
             webdriver_script_chan: Default::default(),
             ignore_further_async_events: Default::default(),
             error_reporter,
@@ -1901,6 +1995,7 @@ fn debug_reflow_events(id: PipelineId, reflow_goal: &ReflowGoal, reason: &Reflow
         ReflowGoal::OffsetParentQuery(_n) => "\tOffsetParentQuery",
         ReflowGoal::MarginStyleQuery(_n) => "\tMarginStyleQuery",
         ReflowGoal::TextIndexQuery(..) => "\tTextIndexQuery",
+        ReflowGoal::TextOffsetQuery(..) => "\tTextOffsetQuery",
         ReflowGoal::TickAnimations => "\tTickAnimations",
     });
 
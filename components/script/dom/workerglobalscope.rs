@@ -17,6 +17,7 @@ use dom::bindings::trace::RootedTraceableBox;
 use dom::crypto::Crypto;
 use dom::dedicatedworkerglobalscope::DedicatedWorkerGlobalScope;
 use dom::globalscope::GlobalScope;
+use dom::idbfactory::IDBFactory;
 use dom::performance::Performance;
 use dom::promise::Promise;
 use dom::serviceworkerglobalscope::ServiceWorkerGlobalScope;
@@ -31,6 +32,7 @@ use js::jsval::UndefinedValue;
 use js::panic::maybe_resume_unwind;
 use msg::constellation_msg::PipelineId;
 use net_traits::{IpcSend, load_whole_resource};
+use net_traits::csp::{CspList, Violation};
 use net_traits::request::{CredentialsMode, Destination, RequestInit as NetRequestInit};
 use script_runtime::{CommonScriptMsg, ScriptChan, ScriptPort, get_reports, Runtime};
 use script_traits::{TimerEvent, TimerEventId};
@@ -61,6 +63,7 @@ pub fn prepare_workerscope_init(global: &GlobalScope,
             worker_id: global.get_next_worker_id(),
             pipeline_id: global.pipeline_id(),
             origin: global.origin().immutable().clone(),
+            csp_list: global.get_csp_list(),
         };
 
     init
@@ -92,6 +95,9 @@ pub struct WorkerGlobalScope {
 
     navigation_start_precise: u64,
     performance: MutNullableDom<Performance>,
+    indexed_db: MutNullableDom<IDBFactory>,
+    /// <https://w3c.github.io/webappsec-csp/#global-object-csp-list>
+    csp_list: CspList,
 }
 
 impl WorkerGlobalScope {
@@ -117,6 +123,7 @@ impl WorkerGlobalScope {
                 Default::default(),
             ),
             worker_id: init.worker_id,
+            csp_list: init.csp_list,
             worker_url,
             closing,
             runtime,
@@ -126,6 +133,7 @@ impl WorkerGlobalScope {
             from_devtools_receiver,
             navigation_start_precise: precise_time_ns(),
             performance: Default::default(),
+            indexed_db: Default::default(),
         }
     }
 
@@ -170,6 +178,21 @@ impl WorkerGlobalScope {
     pub fn pipeline_id(&self) -> PipelineId {
         self.globalscope.pipeline_id()
     }
+
+    pub fn get_csp_list(&self) -> &CspList {
+        &self.csp_list
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#report-violation>
+    pub fn report_csp_violations(&self, violations: Vec<Violation>) {
+        for violation in violations {
+            warn!("Content Security Policy violation in worker: {} blocked by {} ({})",
+                  violation.blocked_uri(), violation.directive, violation.policy);
+            // TODO: Step 3, fire a securitypolicyviolation event at the worker.
+            // Step 4.
+            self.upcast::<GlobalScope>().send_csp_report(&violation, &self.worker_url, "");
+        }
+    }
 }
 
 impl WorkerGlobalScopeMethods for WorkerGlobalScope {
@@ -211,6 +234,7 @@ impl WorkerGlobalScopeMethods for WorkerGlobalScope {
                 pipeline_id: Some(self.upcast::<GlobalScope>().pipeline_id()),
                 referrer_url: None,
                 referrer_policy: None,
+                csp_list: self.csp_list.clone(),
                 .. NetRequestInit::default()
             };
             let (url, source) = match load_whole_resource(request,
@@ -327,6 +351,11 @@ impl WorkerGlobalScopeMethods for WorkerGlobalScope {
                              self.navigation_start_precise)
         })
     }
+
+    // https://w3c.github.io/IndexedDB/#dom-windoworworkerglobalscope-indexeddb
+    fn IndexedDB(&self) -> DomRoot<IDBFactory> {
+        self.indexed_db.or_init(|| IDBFactory::new(self.upcast()))
+    }
 }
 
 
@@ -24,6 +24,7 @@ use js::jsapi::JSAutoCompartment;
 use net_traits::{FetchChannels, FetchResponseListener, NetworkError};
 use net_traits::{FilteredMetadata, FetchMetadata, Metadata};
 use net_traits::CoreResourceMsg::Fetch as NetTraitsFetch;
+use net_traits::csp::Violation;
 use net_traits::request::{Request as NetTraitsRequest, ServiceWorkersMode};
 use net_traits::request::RequestInit as NetTraitsRequestInit;
 use network_listener::{NetworkListener, PreInvoke};
@@ -82,6 +83,7 @@ pub fn Fetch(global: &GlobalScope, input: RequestInfo, init: RootedTraceableBox<
         Ok(r) => r.get_request(),
     };
     let mut request_init = request_init_from_request(request);
+    request_init.csp_list = global.get_csp_list();
 
     // Step 3
     if global.downcast::<ServiceWorkerGlobalScope>().is_some() {
@@ -182,6 +184,10 @@ impl FetchResponseListener for FetchContext {
         // TODO
         // ... trailerObject is not supported in Servo yet.
     }
+
+    fn process_csp_violations(&mut self, violations: Vec<Violation>) {
+        self.response_object.root().global().report_csp_violations(violations);
+    }
 }
 
 fn fill_headers_with_metadata(r: DomRoot<Response>, m: Metadata) {
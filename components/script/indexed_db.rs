@@ -0,0 +1,449 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Helpers shared by the IndexedDB interfaces: conversions between JS values and keys,
+//! key paths, and talking to the IndexedDB storage thread.
+
+use dom::bindings::codegen::UnionTypes::StringOrStringSequence;
+use dom::bindings::conversions::{ToJSValConvertible, jsstring_to_str, root_from_handlevalue};
+use dom::bindings::error::{Error, Fallible};
+use dom::bindings::str::DOMString;
+use dom::bindings::utils::{get_dictionary_property, set_dictionary_property};
+use dom::globalscope::GlobalScope;
+use dom::idbkeyrange::IDBKeyRange;
+use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
+use ipc_channel::router::ROUTER;
+use js::jsapi::{ClippedTime, DateGetMsecSinceEpoch, HandleValue, JSContext, JSObject};
+use js::jsapi::{JS_GetArrayLength, JS_GetElement, JS_IsArrayObject, JS_NewArrayObject1};
+use js::jsapi::{JS_NewPlainObject, JS_SetElement, MutableHandleValue, NewDateObject, ObjectIsDate};
+use js::jsval::{NullValue, ObjectValue, UndefinedValue};
+use js::typedarray::{ArrayBuffer, ArrayBufferView, CreateWith};
+use net_traits::IpcSend;
+use net_traits::indexeddb_thread::{IndexedDBKey, IndexedDBKeyRange, IndexedDBThreadMsg, KeyPath};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::ptr;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic;
+use task_source::TaskSource;
+
+/// Sends a message to the IndexedDB thread, and runs `callback` with its reply in a task.
+pub fn send_to_indexeddb_thread<T, M, F>(global: &GlobalScope, message: M, callback: F)
+    where T: for<'de> Deserialize<'de> + Serialize + Send + 'static,
+          M: FnOnce(IpcSender<T>) -> IndexedDBThreadMsg,
+          F: FnOnce(T) + Send + 'static,
+{
+    let (sender, receiver) = ipc::channel().unwrap();
+    // FIXME: IndexedDB tasks should be queued on the database access task source.
+    let task_source = global.networking_task_source();
+    let canceller = global.task_canceller();
+    let mut callback = Some(callback);
+    ROUTER.add_route(receiver.to_opaque(), Box::new(move |message| {
+        let reply: T = match message.to() {
+            Ok(reply) => reply,
+            Err(_) => return,
+        };
+        if let Some(callback) = callback.take() {
+            let _ = task_source.queue_with_canceller(task!(indexeddb_reply: move || {
+                callback(reply);
+            }), &canceller);
+        }
+    }));
+    let thread = IpcSend::<IndexedDBThreadMsg>::sender(global.resource_threads());
+    let _ = thread.send(message(sender));
+}
+
+/// Runs `callback` in a task for every message the IndexedDB thread sends on `receiver`.
+/// Messages that arrive once the global can no longer run tasks are given to `cancelled`
+/// instead, on the router thread.
+pub fn route_from_indexeddb_thread<T, F, C>(global: &GlobalScope, receiver: IpcReceiver<T>, callback: F, cancelled: C)
+    where T: for<'de> Deserialize<'de> + Serialize + Send + 'static,
+          F: FnMut(T) + Send + 'static,
+          C: Fn(T) + Send + 'static,
+{
+    // FIXME: IndexedDB tasks should be queued on the database access task source.
+    let task_source = global.networking_task_source();
+    let canceller = global.task_canceller();
+    let callback = Arc::new(Mutex::new(callback));
+    ROUTER.add_route(receiver.to_opaque(), Box::new(move |message| {
+        let message: T = match message.to() {
+            Ok(message) => message,
+            Err(_) => return,
+        };
+        let is_cancelled = canceller.cancelled.as_ref().map_or(false, |cancelled| {
+            cancelled.load(atomic::Ordering::SeqCst)
+        });
+        if is_cancelled {
+            return cancelled(message);
+        }
+        let callback = callback.clone();
+        let _ = task_source.queue_with_canceller(task!(indexeddb_message: move || {
+            let mut callback = callback.lock().unwrap();
+            (*callback)(message);
+        }), &canceller);
+    }));
+}
+
+/// Sends a message to the IndexedDB thread and waits for its reply. Only used where the
+/// storage thread is known to answer right away.
+pub fn send_to_indexeddb_thread_sync<T, M>(global: &GlobalScope, message: M) -> Option<T>
+    where T: for<'de> Deserialize<'de> + Serialize,
+          M: FnOnce(IpcSender<T>) -> IndexedDBThreadMsg,
+{
+    let (sender, receiver) = ipc::channel().unwrap();
+    let thread = IpcSend::<IndexedDBThreadMsg>::sender(global.resource_threads());
+    if thread.send(message(sender)).is_err() {
+        return None;
+    }
+    receiver.recv().ok()
+}
+
+/// <https://w3c.github.io/IndexedDB/#convert-a-value-to-a-key>
+#[allow(unsafe_code)]
+pub unsafe fn convert_value_to_key(cx: *mut JSContext,
+                                   input: HandleValue,
+                                   seen: &mut Vec<*mut JSObject>)
+                                   -> Fallible<IndexedDBKey> {
+    if input.is_number() {
+        let number = input.to_number();
+        if number.is_nan() {
+            return Err(Error::Data);
+        }
+        return Ok(IndexedDBKey::Number(number));
+    }
+    if input.is_string() {
+        return Ok(IndexedDBKey::String(String::from(jsstring_to_str(cx, input.to_string()))));
+    }
+    if !input.is_object() {
+        return Err(Error::Data);
+    }
+
+    rooted!(in(cx) let object = input.to_object());
+    let mut is_date = false;
+    if !ObjectIsDate(cx, object.handle(), &mut is_date) {
+        return Err(Error::JSFailed);
+    }
+    if is_date {
+        let mut time = 0.;
+        if !DateGetMsecSinceEpoch(cx, object.handle(), &mut time) {
+            return Err(Error::JSFailed);
+        }
+        if time.is_nan() {
+            return Err(Error::Data);
+        }
+        return Ok(IndexedDBKey::Date(time));
+    }
+
+    typedarray!(in(cx) let buffer: ArrayBuffer = object.get());
+    if let Ok(mut buffer) = buffer {
+        return Ok(IndexedDBKey::Binary(buffer.as_slice().to_vec()));
+    }
+    typedarray!(in(cx) let view: ArrayBufferView = object.get());
+    if let Ok(mut view) = view {
+        return Ok(IndexedDBKey::Binary(view.as_slice().to_vec()));
+    }
+
+    let mut is_array = false;
+    if !JS_IsArrayObject(cx, input, &mut is_array) {
+        return Err(Error::JSFailed);
+    }
+    if !is_array || seen.contains(&object.get()) {
+        return Err(Error::Data);
+    }
+    let mut length = 0;
+    if !JS_GetArrayLength(cx, object.handle(), &mut length) {
+        return Err(Error::JSFailed);
+    }
+    seen.push(object.get());
+    let mut keys = Vec::with_capacity(length as usize);
+    for index in 0..length {
+        rooted!(in(cx) let mut entry = UndefinedValue());
+        if !JS_GetElement(cx, object.handle(), index, entry.handle_mut()) {
+            return Err(Error::JSFailed);
+        }
+        keys.push(convert_value_to_key(cx, entry.handle(), seen)?);
+    }
+    seen.pop();
+    Ok(IndexedDBKey::Array(keys))
+}
+
+/// <https://w3c.github.io/IndexedDB/#convert-a-value-to-a-multientry-key>
+#[allow(unsafe_code)]
+unsafe fn convert_value_to_multi_entry_keys(cx: *mut JSContext, input: HandleValue) -> Fallible<Vec<IndexedDBKey>> {
+    let mut is_array = false;
+    if !JS_IsArrayObject(cx, input, &mut is_array) {
+        return Err(Error::JSFailed);
+    }
+    if !is_array {
+        return convert_value_to_key(cx, input, &mut vec![]).map(|key| vec![key]);
+    }
+    rooted!(in(cx) let object = input.to_object());
+    let mut length = 0;
+    if !JS_GetArrayLength(cx, object.handle(), &mut length) {
+        return Err(Error::JSFailed);
+    }
+    let mut keys: Vec<IndexedDBKey> = vec![];
+    for index in 0..length {
+        rooted!(in(cx) let mut entry = UndefinedValue());
+        if !JS_GetElement(cx, object.handle(), index, entry.handle_mut()) {
+            return Err(Error::JSFailed);
+        }
+        // Invalid subkeys are skipped, and duplicates are removed.
+        match convert_value_to_key(cx, entry.handle(), &mut vec![object.get()]) {
+            Ok(key) => {
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            },
+            Err(Error::Data) => {},
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(keys)
+}
+
+/// <https://w3c.github.io/IndexedDB/#convert-a-key-to-a-value>
+#[allow(unsafe_code)]
+pub unsafe fn key_to_jsval(cx: *mut JSContext, key: &IndexedDBKey, rval: MutableHandleValue) {
+    match *key {
+        IndexedDBKey::Number(number) => number.to_jsval(cx, rval),
+        IndexedDBKey::String(ref string) => string.to_jsval(cx, rval),
+        IndexedDBKey::Date(time) => {
+            rooted!(in(cx) let date = NewDateObject(cx, ClippedTime { t: time }));
+            rval.set(ObjectValue(date.get()));
+        },
+        IndexedDBKey::Binary(ref bytes) => {
+            rooted!(in(cx) let mut buffer = ptr::null_mut::<JSObject>());
+            assert!(ArrayBuffer::create(cx, CreateWith::Slice(bytes), buffer.handle_mut()).is_ok());
+            rval.set(ObjectValue(buffer.get()));
+        },
+        IndexedDBKey::Array(ref keys) => {
+            rooted!(in(cx) let array = JS_NewArrayObject1(cx, keys.len()));
+            for (index, key) in keys.iter().enumerate() {
+                rooted!(in(cx) let mut entry = UndefinedValue());
+                key_to_jsval(cx, key, entry.handle_mut());
+                assert!(JS_SetElement(cx, array.handle(), index as u32, entry.handle()));
+            }
+            rval.set(ObjectValue(array.get()));
+        },
+    }
+}
+
+/// <https://w3c.github.io/IndexedDB/#convert-a-value-to-a-key-range>
+#[allow(unsafe_code)]
+pub unsafe fn convert_value_to_key_range(cx: *mut JSContext,
+                                         input: HandleValue,
+                                         null_disallowed: bool)
+                                         -> Fallible<IndexedDBKeyRange> {
+    if let Ok(range) = root_from_handlevalue::<IDBKeyRange>(input) {
+        return Ok(range.inner().clone());
+    }
+    if input.is_undefined() || input.is_null() {
+        if null_disallowed {
+            return Err(Error::Data);
+        }
+        return Ok(IndexedDBKeyRange::unbounded());
+    }
+    convert_value_to_key(cx, input, &mut vec![]).map(IndexedDBKeyRange::only)
+}
+
+/// <https://w3c.github.io/IndexedDB/#compare-two-keys>, as the integer `cmp()` returns
+pub fn compare_keys(first: &IndexedDBKey, second: &IndexedDBKey) -> i16 {
+    match first.cmp(second) {
+        Ordering::Less => -1,
+        Ordering::Equal => 0,
+        Ordering::Greater => 1,
+    }
+}
+
+pub fn key_path_from_idl(key_path: &StringOrStringSequence) -> KeyPath {
+    match *key_path {
+        StringOrStringSequence::String(ref path) => KeyPath::String(path.to_string()),
+        StringOrStringSequence::StringSequence(ref paths) => {
+            KeyPath::Sequence(paths.iter().map(|path| path.to_string()).collect())
+        },
+    }
+}
+
+#[allow(unsafe_code)]
+pub unsafe fn key_path_to_jsval(cx: *mut JSContext, key_path: Option<&KeyPath>, rval: MutableHandleValue) {
+    match key_path {
+        Some(&KeyPath::String(ref path)) => path.to_jsval(cx, rval),
+        Some(&KeyPath::Sequence(ref paths)) => {
+            let paths: Vec<DOMString> = paths.iter().map(|path| DOMString::from(path.clone())).collect();
+            paths.to_jsval(cx, rval)
+        },
+        None => rval.set(NullValue()),
+    }
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c == '$' || c == '_' || c.is_alphabetic()
+}
+
+fn is_identifier_part(c: char) -> bool {
+    is_identifier_start(c) || c.is_numeric() || c == '\u{200C}' || c == '\u{200D}'
+}
+
+/// <https://w3c.github.io/IndexedDB/#valid-key-path>
+pub fn is_valid_key_path(key_path: &KeyPath) -> bool {
+    fn is_valid_string(path: &str) -> bool {
+        path.is_empty() || path.split('.').all(|identifier| {
+            let mut chars = identifier.chars();
+            match chars.next() {
+                Some(first) => is_identifier_start(first) && chars.all(is_identifier_part),
+                None => false,
+            }
+        })
+    }
+    match *key_path {
+        KeyPath::String(ref path) => is_valid_string(path),
+        KeyPath::Sequence(ref paths) => !paths.is_empty() && paths.iter().all(|path| is_valid_string(path)),
+    }
+}
+
+/// <https://w3c.github.io/IndexedDB/#evaluate-a-key-path-on-a-value>
+///
+/// Returns `Ok(false)` on failure, that is when the value has no property at the key path.
+#[allow(unsafe_code)]
+pub unsafe fn evaluate_key_path_on_value(cx: *mut JSContext,
+                                         value: HandleValue,
+                                         key_path: &KeyPath,
+                                         rval: MutableHandleValue)
+                                         -> Fallible<bool> {
+    let path = match *key_path {
+        KeyPath::String(ref path) => path,
+        KeyPath::Sequence(ref paths) => {
+            rooted!(in(cx) let array = JS_NewArrayObject1(cx, paths.len()));
+            for (index, path) in paths.iter().enumerate() {
+                rooted!(in(cx) let mut entry = UndefinedValue());
+                if !evaluate_key_path_on_value(cx, value, &KeyPath::String(path.clone()), entry.handle_mut())? {
+                    return Ok(false);
+                }
+                if !JS_SetElement(cx, array.handle(), index as u32, entry.handle()) {
+                    return Err(Error::JSFailed);
+                }
+            }
+            rval.set(ObjectValue(array.get()));
+            return Ok(true);
+        },
+    };
+
+    rval.set(value.get());
+    if path.is_empty() {
+        return Ok(true);
+    }
+    for identifier in path.split('.') {
+        rooted!(in(cx) let current = rval.get());
+        if current.is_string() && identifier == "length" {
+            let length = jsstring_to_str(cx, current.to_string()).encode_utf16().count();
+            (length as f64).to_jsval(cx, rval);
+            continue;
+        }
+        if !current.is_object() {
+            return Ok(false);
+        }
+        rooted!(in(cx) let object = current.to_object());
+        match get_dictionary_property(cx, object.handle(), identifier, rval) {
+            Ok(true) => {},
+            Ok(false) => return Ok(false),
+            Err(()) => return Err(Error::JSFailed),
+        }
+    }
+    Ok(true)
+}
+
+/// <https://w3c.github.io/IndexedDB/#extract-a-key-from-a-value-using-a-key-path>
+///
+/// Returns `Ok(None)` on failure, and `Err(Error::Data)` if the value at the key path is
+/// not a valid key.
+#[allow(unsafe_code)]
+pub unsafe fn extract_key(cx: *mut JSContext,
+                          value: HandleValue,
+                          key_path: &KeyPath)
+                          -> Fallible<Option<IndexedDBKey>> {
+    rooted!(in(cx) let mut result = UndefinedValue());
+    if !evaluate_key_path_on_value(cx, value, key_path, result.handle_mut())? {
+        return Ok(None);
+    }
+    convert_value_to_key(cx, result.handle(), &mut vec![]).map(Some)
+}
+
+/// The keys a value is stored under in an index. Values that cannot be indexed get no keys.
+#[allow(unsafe_code)]
+pub unsafe fn extract_index_keys(cx: *mut JSContext,
+                                 value: HandleValue,
+                                 key_path: &KeyPath,
+                                 multi_entry: bool)
+                                 -> Fallible<Vec<IndexedDBKey>> {
+    rooted!(in(cx) let mut result = UndefinedValue());
+    if !evaluate_key_path_on_value(cx, value, key_path, result.handle_mut())? {
+        return Ok(vec![]);
+    }
+    let keys = if multi_entry {
+        convert_value_to_multi_entry_keys(cx, result.handle())
+    } else {
+        convert_value_to_key(cx, result.handle(), &mut vec![]).map(|key| vec![key])
+    };
+    match keys {
+        Err(Error::Data) => Ok(vec![]),
+        keys => keys,
+    }
+}
+
+/// <https://w3c.github.io/IndexedDB/#check-that-a-key-could-be-injected-into-a-value>
+#[allow(unsafe_code)]
+pub unsafe fn could_inject_key(cx: *mut JSContext, value: HandleValue, key_path: &str) -> Fallible<bool> {
+    let identifiers: Vec<&str> = key_path.split('.').collect();
+    rooted!(in(cx) let mut current = value.get());
+    for identifier in &identifiers[..identifiers.len() - 1] {
+        if !current.is_object() {
+            return Ok(false);
+        }
+        rooted!(in(cx) let object = current.to_object());
+        match get_dictionary_property(cx, object.handle(), identifier, current.handle_mut()) {
+            Ok(true) => {},
+            Ok(false) => return Ok(true),
+            Err(()) => return Err(Error::JSFailed),
+        }
+    }
+    Ok(current.is_object())
+}
+
+/// <https://w3c.github.io/IndexedDB/#inject-a-key-into-a-value-using-a-key-path>
+///
+/// Values are stored without the keys generated for them, so this runs when they are read.
+#[allow(unsafe_code)]
+pub unsafe fn inject_key_into_value(cx: *mut JSContext,
+                                    value: HandleValue,
+                                    key: &IndexedDBKey,
+                                    key_path: &str)
+                                    -> Fallible<()> {
+    let identifiers: Vec<&str> = key_path.split('.').collect();
+    if !value.is_object() {
+        return Ok(());
+    }
+    rooted!(in(cx) let mut object = value.to_object());
+    for identifier in &identifiers[..identifiers.len() - 1] {
+        rooted!(in(cx) let mut next = UndefinedValue());
+        match get_dictionary_property(cx, object.handle(), identifier, next.handle_mut()) {
+            Ok(true) => {},
+            Ok(false) => {
+                next.set(ObjectValue(JS_NewPlainObject(cx)));
+                if set_dictionary_property(cx, object.handle(), identifier, next.handle()).is_err() {
+                    return Err(Error::JSFailed);
+                }
+            },
+            Err(()) => return Err(Error::JSFailed),
+        }
+        if !next.is_object() {
+            return Ok(());
+        }
+        object.set(next.to_object());
+    }
+    rooted!(in(cx) let mut key_value = UndefinedValue());
+    key_to_jsval(cx, key, key_value.handle_mut());
+    let last = identifiers[identifiers.len() - 1];
+    set_dictionary_property(cx, object.handle(), last, key_value.handle()).map_err(|()| Error::JSFailed)
+}
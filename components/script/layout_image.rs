@@ -73,6 +73,7 @@ pub fn fetch_image_for_layout(url: ServoUrl,
         origin: document.origin().immutable().clone(),
         destination: Destination::Image,
         pipeline_id: Some(document.global().pipeline_id()),
+        csp_list: document.get_csp_list().clone(),
         .. FetchRequestInit::default()
     };
 
@@ -113,11 +113,13 @@ mod task;
 
 mod body;
 pub mod clipboard_provider;
+mod debugger;
 mod devtools;
 pub mod document_loader;
 #[macro_use]
 mod dom;
 pub mod fetch;
+mod indexed_db;
 mod layout_image;
 mod mem;
 mod microtask;
@@ -149,6 +151,7 @@ pub mod layout_exports {
     pub use dom::element::{Element, LayoutElementHelpers, RawLayoutElementHelpers};
     pub use dom::node::NodeFlags;
     pub use dom::node::{LayoutNodeHelpers, Node};
+    pub use dom::shadowroot::{LayoutShadowRootHelpers, ShadowRoot};
     pub use dom::text::Text;
 }
 
@@ -19,6 +19,7 @@ use js::jsapi::{JSGCInvocationKind, JSGCStatus, JS_AddExtraGCRootsTracer, JS_Set
 use js::jsapi::{JSGCMode, JSGCParamKey, JS_SetGCParameter, JS_SetGlobalJitCompilerOption};
 use js::jsapi::{JSJitCompilerOption, JS_SetOffthreadIonCompilationEnabled, JS_SetParallelParsingEnabled};
 use js::jsapi::{JSObject, RuntimeOptionsRef, SetPreserveWrapperCallback, SetEnqueuePromiseJobCallback};
+use js::jsapi::{JSSecurityCallbacks, JS_SetSecurityCallbacks};
 use js::panic::wrap_panic;
 use js::rust::Runtime as RustRuntime;
 use microtask::{EnqueuedPromiseCallback, Microtask};
@@ -122,6 +123,20 @@ unsafe extern "C" fn enqueue_job(cx: *mut JSContext,
     }), false)
 }
 
+/// SM callback deciding whether strings may be compiled as code, as with
+/// `eval()` and `new Function()`.
+#[allow(unsafe_code)]
+unsafe extern "C" fn content_security_policy_allows(cx: *mut JSContext) -> bool {
+    wrap_panic(AssertUnwindSafe(|| {
+        GlobalScope::from_context(cx).is_js_evaluation_allowed()
+    }), false)
+}
+
+static SECURITY_CALLBACKS: JSSecurityCallbacks = JSSecurityCallbacks {
+    contentSecurityPolicyAllows: Some(content_security_policy_allows),
+    subsumes: None,
+};
+
 #[derive(JSTraceable)]
 pub struct Runtime(RustRuntime);
 
@@ -162,6 +177,8 @@ pub unsafe fn new_rt_and_cx() -> Runtime {
 
     SetEnqueuePromiseJobCallback(runtime.rt(), Some(enqueue_job), ptr::null_mut());
 
+    JS_SetSecurityCallbacks(runtime.rt(), &SECURITY_CALLBACKS);
+
     set_gc_zeal_options(runtime.rt());
 
     // Enable or disable the JITs.
@@ -19,11 +19,13 @@
 
 use bluetooth_traits::BluetoothRequest;
 use canvas_traits::webgl::WebGLPipeline;
-use devtools;
-use devtools_traits::{DevtoolScriptControlMsg, DevtoolsPageInfo};
-use devtools_traits::{ScriptToDevtoolsControlMsg, WorkerId};
+use debugger::Debugger;
+use devtools::{self, JsSampler};
+use devtools_traits::{DevtoolScriptControlMsg, DevtoolsPageInfo, JsStackSample, PauseInfo};
+use devtools_traits::{ScriptToDevtoolsControlMsg, StepType, WorkerId};
 use devtools_traits::CSSError;
 use document_loader::DocumentLoader;
+use dom::abstractworker::SharedRt;
 use dom::bindings::cell::DomRefCell;
 use dom::bindings::codegen::Bindings::CSSStyleDeclarationBinding::CSSStyleDeclarationMethods;
 use dom::bindings::codegen::Bindings::DocumentBinding::{DocumentMethods, DocumentReadyState};
@@ -47,6 +49,7 @@ use dom::event::{Event, EventBubbles, EventCancelable};
 use dom::globalscope::GlobalScope;
 use dom::htmlanchorelement::HTMLAnchorElement;
 use dom::htmliframeelement::{HTMLIFrameElement, NavigationType};
+use dom::htmlslotelement::HTMLSlotElement;
 use dom::mutationobserver::MutationObserver;
 use dom::node::{Node, NodeDamage, window_from_node, from_untrusted_node_address};
 use dom::performanceentry::PerformanceEntry;
@@ -69,7 +72,8 @@ use hyper_serde::Serde;
 use ipc_channel::ipc::{self, IpcSender};
 use ipc_channel::router::ROUTER;
 use js::glue::GetWindowProxyClass;
-use js::jsapi::{JSAutoCompartment, JSContext, JS_SetWrapObjectCallbacks};
+use js::jsapi::{JSAutoCompartment, JSContext, JSInterruptCallback, JS_SetInterruptCallback};
+use js::jsapi::JS_SetWrapObjectCallbacks;
 use js::jsapi::{JSTracer, SetWindowProxyClass};
 use js::jsval::UndefinedValue;
 use malloc_size_of::MallocSizeOfOps;
@@ -79,6 +83,7 @@ use microtask::{MicrotaskQueue, Microtask};
 use msg::constellation_msg::{BrowsingContextId, FrameType, PipelineId, PipelineNamespace, TopLevelBrowsingContextId};
 use net_traits::{FetchMetadata, FetchResponseListener, FetchResponseMsg};
 use net_traits::{Metadata, NetworkError, ReferrerPolicy, ResourceThreads};
+use net_traits::csp::{CspList, InlineCheckType};
 use net_traits::image_cache::{ImageCache, PendingImageResponse};
 use net_traits::request::{CredentialsMode, Destination, RedirectMode, RequestInit};
 use net_traits::storage_thread::StorageType;
@@ -131,6 +136,9 @@ pub type ImageCacheMsg = (PipelineId, PendingImageResponse);
 
 thread_local!(static SCRIPT_THREAD_ROOT: Cell<Option<*const ScriptThread>> = Cell::new(None));
 
+/// The interrupt callback that the one sampling script stacks replaced, and calls in turn.
+thread_local!(static PREVIOUS_INTERRUPT_CALLBACK: Cell<JSInterruptCallback> = Cell::new(None));
+
 pub unsafe fn trace_thread(tr: *mut JSTracer) {
     SCRIPT_THREAD_ROOT.with(|root| {
         if let Some(script_thread) = root.get() {
@@ -456,6 +464,17 @@ pub struct ScriptThread {
     /// no such server exists.
     devtools_port: Receiver<DevtoolScriptControlMsg>,
     devtools_sender: IpcSender<DevtoolScriptControlMsg>,
+    /// The devtools debugger, if there is a devtools server.
+    debugger: Option<Debugger>,
+    /// The stacks sampled so far for the pipelines whose scripts devtools are profiling.
+    js_samples: DomRefCell<HashMap<PipelineId, Vec<JsStackSample>>>,
+    /// Samples are queued here by the interrupt callback, which may run while anything is
+    /// borrowed, until `collect_js_samples` files them in `js_samples`.
+    js_sample_sender: Sender<(PipelineId, JsStackSample)>,
+    js_sample_port: Receiver<(PipelineId, JsStackSample)>,
+    /// Interrupts script while any pipeline is being profiled. It must stop before the
+    /// runtime goes away, so it is dropped before `js_runtime`.
+    js_sampler: DomRefCell<Option<JsSampler>>,
 
     /// The JavaScript runtime.
     js_runtime: Rc<Runtime>,
@@ -481,6 +500,9 @@ pub struct ScriptThread {
     /// The unit of related similar-origin browsing contexts' list of MutationObserver objects
     mutation_observers: DomRefCell<Vec<Dom<MutationObserver>>>,
 
+    /// <https://dom.spec.whatwg.org/#signal-slot-list>
+    signal_slots: DomRefCell<Vec<Dom<HTMLSlotElement>>>,
+
     /// A handle to the webgl thread
     webgl_chan: WebGLPipeline,
 
@@ -622,6 +644,15 @@ impl ScriptThread {
         })
     }
 
+    pub fn remove_mutation_observer(observer: &MutationObserver) {
+        SCRIPT_THREAD_ROOT.with(|root| {
+            let script_thread = unsafe { &*root.get().unwrap() };
+            script_thread.mutation_observers
+                .borrow_mut()
+                .retain(|o| &**o as *const MutationObserver != observer as *const MutationObserver);
+        })
+    }
+
     pub fn get_mutation_observers() -> Vec<DomRoot<MutationObserver>> {
         SCRIPT_THREAD_ROOT.with(|root| {
             let script_thread = unsafe { &*root.get().unwrap() };
@@ -629,6 +660,23 @@ impl ScriptThread {
         })
     }
 
+    pub fn add_signal_slot(slot: &HTMLSlotElement) {
+        SCRIPT_THREAD_ROOT.with(|root| {
+            let script_thread = unsafe { &*root.get().unwrap() };
+            let mut signal_slots = script_thread.signal_slots.borrow_mut();
+            if !signal_slots.iter().any(|s| &**s == slot) {
+                signal_slots.push(Dom::from_ref(slot));
+            }
+        })
+    }
+
+    pub fn take_signal_slots() -> Vec<DomRoot<HTMLSlotElement>> {
+        SCRIPT_THREAD_ROOT.with(|root| {
+            let script_thread = unsafe { &*root.get().unwrap() };
+            script_thread.signal_slots.borrow_mut().drain(..).map(|s| DomRoot::from_ref(&*s)).collect()
+        })
+    }
+
     pub fn mark_document_with_no_blocked_loads(doc: &Document) {
         SCRIPT_THREAD_ROOT.with(|root| {
             let script_thread = unsafe { &*root.get().unwrap() };
@@ -808,6 +856,8 @@ impl ScriptThread {
         let (ipc_devtools_sender, ipc_devtools_receiver) = ipc::channel().unwrap();
         let devtools_port = ROUTER.route_ipc_receiver_to_new_mpsc_receiver(ipc_devtools_receiver);
 
+        let (js_sample_sender, js_sample_port) = channel();
+
         let (timer_event_chan, timer_event_port) = channel();
 
         // Ask the router to proxy IPC messages from the control port to us.
@@ -815,6 +865,12 @@ impl ScriptThread {
 
         let boxed_script_sender = Box::new(MainThreadScriptChan(chan.clone()));
 
+        let debugger = if state.devtools_chan.is_some() {
+            Some(Debugger::new(runtime.cx()))
+        } else {
+            None
+        };
+
         let (image_cache_channel, image_cache_port) = channel();
 
         ScriptThread {
@@ -852,6 +908,11 @@ impl ScriptThread {
             devtools_chan: state.devtools_chan,
             devtools_port: devtools_port,
             devtools_sender: ipc_devtools_sender,
+            debugger: debugger,
+            js_samples: DomRefCell::new(HashMap::new()),
+            js_sample_sender: js_sample_sender,
+            js_sample_port: js_sample_port,
+            js_sampler: DomRefCell::new(None),
 
             js_runtime: Rc::new(runtime),
             topmost_mouse_over_target: MutNullableDom::new(Default::default()),
@@ -869,6 +930,8 @@ impl ScriptThread {
 
             mutation_observers: Default::default(),
 
+            signal_slots: Default::default(),
+
             layout_to_constellation_chan: state.layout_to_constellation_chan,
 
             webgl_chan: state.webgl_chan,
@@ -1413,6 +1476,16 @@ impl ScriptThread {
                     None => return warn!("Message sent to closed pipeline {}.", id),
                 }
             },
+            DevtoolScriptControlMsg::WantsMutationNotifications(id, to_send) =>
+                devtools::handle_wants_mutation_notifications(&*documents, id, to_send),
+            DevtoolScriptControlMsg::SetOuterHTML(id, node_id, html, reply) =>
+                devtools::handle_set_outer_html(&*documents, id, node_id, html, reply),
+            DevtoolScriptControlMsg::RemoveNode(id, node_id, reply) =>
+                devtools::handle_remove_node(&*documents, id, node_id, reply),
+            DevtoolScriptControlMsg::InsertBefore(id, node_id, parent_id, sibling_id, reply) =>
+                devtools::handle_insert_before(&*documents, id, node_id, parent_id, sibling_id, reply),
+            DevtoolScriptControlMsg::EditTagName(id, node_id, tag_name, reply) =>
+                devtools::handle_edit_tag_name(&*documents, id, node_id, tag_name, reply),
             DevtoolScriptControlMsg::SetTimelineMarkers(id, marker_types, reply) =>
                 devtools::handle_set_timeline_markers(&*documents, id, marker_types, reply),
             DevtoolScriptControlMsg::DropTimelineMarkers(id, marker_types) =>
@@ -1421,6 +1494,131 @@ impl ScriptThread {
                 devtools::handle_request_animation_frame(&*documents, id, name),
             DevtoolScriptControlMsg::Reload(id) =>
                 devtools::handle_reload(&*documents, id),
+            DevtoolScriptControlMsg::GetSources(id, reply) =>
+                devtools::handle_get_sources(&*documents, self.debugger.as_ref(), id, reply),
+            DevtoolScriptControlMsg::SetBreakpoint(_, url, line, reply) => {
+                let found = self.debugger.as_ref().map_or(false, |debugger| {
+                    debugger.set_breakpoint(self.get_cx(), &url, line)
+                });
+                let _ = reply.send(found);
+            },
+            DevtoolScriptControlMsg::RemoveBreakpoint(_, url, line) => {
+                if let Some(ref debugger) = self.debugger {
+                    debugger.remove_breakpoint(self.get_cx(), &url, line);
+                }
+            },
+            DevtoolScriptControlMsg::Interrupt(_) => {
+                if let Some(ref debugger) = self.debugger {
+                    debugger.interrupt(self.get_cx());
+                }
+            },
+            // Resuming is handled by the paused event loop; there is nothing to resume otherwise.
+            DevtoolScriptControlMsg::Resume(..) => {},
+            DevtoolScriptControlMsg::GetFrames(_, reply) => {
+                let frames = self.debugger.as_ref().map_or(vec![], |debugger| debugger.frames(self.get_cx()));
+                let _ = reply.send(frames);
+            },
+            DevtoolScriptControlMsg::GetEnvironment(_, depth, reply) => {
+                let scopes = self.debugger.as_ref().and_then(|debugger| {
+                    debugger.environment(self.get_cx(), depth)
+                });
+                let _ = reply.send(scopes);
+            },
+            DevtoolScriptControlMsg::GetStyleSheets(id, reply) =>
+                devtools::handle_get_style_sheets(&*documents, id, reply),
+            DevtoolScriptControlMsg::GetStyleSheetText(id, index, reply) =>
+                devtools::handle_get_style_sheet_text(&*documents, id, index, reply),
+            DevtoolScriptControlMsg::SetStyleSheetDisabled(id, index, disabled) =>
+                devtools::handle_set_style_sheet_disabled(&*documents, id, index, disabled),
+            DevtoolScriptControlMsg::GetAppliedRules(id, node_id, reply) =>
+                devtools::handle_get_applied_rules(&*documents, id, node_id, reply),
+            DevtoolScriptControlMsg::ModifyRule(id, location, modifications, reply) =>
+                devtools::handle_modify_rule(&*documents, id, location, modifications, reply),
+            DevtoolScriptControlMsg::SetRuleText(id, location, text, reply) =>
+                devtools::handle_set_rule_text(&*documents, id, location, text, reply),
+            DevtoolScriptControlMsg::StartJsSampling(id) => self.handle_start_js_sampling(id),
+            DevtoolScriptControlMsg::StopJsSampling(id, reply) => self.handle_stop_js_sampling(id, reply),
+        }
+    }
+
+    /// Sample the stack of the running script. This runs wherever script was interrupted, so
+    /// the sample is only queued, to be filed by `collect_js_samples`.
+    pub fn sample_js_stack(cx: *mut JSContext) {
+        SCRIPT_THREAD_ROOT.with(|root| {
+            if let Some(script_thread) = root.get() {
+                let script_thread = unsafe { &*script_thread };
+                if let Some(sample) = devtools::take_js_sample(cx) {
+                    let _ = script_thread.js_sample_sender.send(sample);
+                }
+            }
+        })
+    }
+
+    /// File the queued stack samples under the pipelines being profiled, and drop the others.
+    fn collect_js_samples(&self) {
+        let mut js_samples = self.js_samples.borrow_mut();
+        for (id, sample) in self.js_sample_port.try_iter() {
+            if let Some(samples) = js_samples.get_mut(&id) {
+                samples.push(sample);
+            }
+        }
+    }
+
+    fn handle_start_js_sampling(&self, id: PipelineId) {
+        self.collect_js_samples();
+        self.js_samples.borrow_mut().entry(id).or_insert_with(Vec::new);
+        let mut sampler = self.js_sampler.borrow_mut();
+        if sampler.is_none() {
+            let previous = unsafe { JS_SetInterruptCallback(self.js_runtime.rt(), Some(interrupt_callback)) };
+            PREVIOUS_INTERRUPT_CALLBACK.with(|callback| callback.set(previous));
+            *sampler = Some(JsSampler::start(SharedRt::new(&self.js_runtime)));
+        }
+    }
+
+    /// Stop sampling the stack of the scripts of the given pipeline, returning the samples.
+    fn stop_js_sampling(&self, id: PipelineId) -> Vec<JsStackSample> {
+        self.collect_js_samples();
+        let samples = self.js_samples.borrow_mut().remove(&id).unwrap_or_default();
+        if self.js_samples.borrow().is_empty() && self.js_sampler.borrow_mut().take().is_some() {
+            let previous = PREVIOUS_INTERRUPT_CALLBACK.with(|callback| callback.replace(None));
+            unsafe {
+                JS_SetInterruptCallback(self.js_runtime.rt(), previous);
+            }
+        }
+        samples
+    }
+
+    fn handle_stop_js_sampling(&self, id: PipelineId, reply: IpcSender<Vec<JsStackSample>>) {
+        let _ = reply.send(self.stop_js_sampling(id));
+    }
+
+    /// Relay a pause of script execution to devtools, and block until they resume it.
+    /// Returns how far to run the script before pausing again.
+    pub fn pause_for_debugger(info: PauseInfo) -> Option<StepType> {
+        SCRIPT_THREAD_ROOT.with(|root| {
+            let script_thread = unsafe { &*root.get().unwrap() };
+            script_thread.run_paused_event_loop(info)
+        })
+    }
+
+    fn run_paused_event_loop(&self, info: PauseInfo) -> Option<StepType> {
+        let chan = match self.devtools_chan {
+            Some(ref chan) => chan,
+            None => return None,
+        };
+        let pipeline_id = GlobalScope::entry().pipeline_id();
+        if chan.send(ScriptToDevtoolsControlMsg::Paused(pipeline_id, info)).is_err() {
+            return None;
+        }
+
+        // Only devtools messages are handled while paused, so that no other script runs.
+        loop {
+            match self.devtools_port.recv() {
+                Ok(DevtoolScriptControlMsg::Resume(_, step)) => return step,
+                Ok(msg) => self.handle_msg_from_devtools(msg),
+                // Devtools are gone, and cannot resume the script, so it carries on.
+                Err(_) => return None,
+            }
         }
     }
 
@@ -1436,12 +1634,32 @@ impl ScriptThread {
         match msg {
             WebDriverScriptCommand::AddCookie(params, reply) =>
                 webdriver_handlers::handle_add_cookie(&*documents, pipeline_id, params, reply),
+            WebDriverScriptCommand::DeleteCookie(name, reply) =>
+                webdriver_handlers::handle_delete_cookie(&*documents, pipeline_id, name, reply),
+            WebDriverScriptCommand::DeleteCookies(reply) =>
+                webdriver_handlers::handle_delete_cookies(&*documents, pipeline_id, reply),
+            WebDriverScriptCommand::ElementClear(element_id, reply) =>
+                webdriver_handlers::handle_element_clear(&*documents, pipeline_id, element_id, reply),
+            WebDriverScriptCommand::ElementClick(element_id, reply) =>
+                webdriver_handlers::handle_element_click(&*documents, pipeline_id, element_id, reply),
             WebDriverScriptCommand::ExecuteScript(script, reply) =>
                 webdriver_handlers::handle_execute_script(&*documents, pipeline_id, script, reply),
             WebDriverScriptCommand::FindElementCSS(selector, reply) =>
                 webdriver_handlers::handle_find_element_css(&*documents, pipeline_id, selector, reply),
             WebDriverScriptCommand::FindElementsCSS(selector, reply) =>
                 webdriver_handlers::handle_find_elements_css(&*documents, pipeline_id, selector, reply),
+            WebDriverScriptCommand::FindElementElementCSS(selector, element_id, reply) =>
+                webdriver_handlers::handle_find_element_element_css(&*documents,
+                                                                    pipeline_id,
+                                                                    selector,
+                                                                    element_id,
+                                                                    reply),
+            WebDriverScriptCommand::FindElementElementsCSS(selector, element_id, reply) =>
+                webdriver_handlers::handle_find_element_elements_css(&*documents,
+                                                                     pipeline_id,
+                                                                     selector,
+                                                                     element_id,
+                                                                     reply),
             WebDriverScriptCommand::FocusElement(element_id, reply) =>
                 webdriver_handlers::handle_focus_element(&*documents, pipeline_id, element_id, reply),
             WebDriverScriptCommand::GetActiveElement(reply) =>
@@ -1456,12 +1674,25 @@ impl ScriptThread {
                 webdriver_handlers::handle_get_attribute(&*documents, pipeline_id, node_id, name, reply),
             WebDriverScriptCommand::GetElementCSS(node_id, name, reply) =>
                 webdriver_handlers::handle_get_css(&*documents, pipeline_id, node_id, name, reply),
+            WebDriverScriptCommand::GetElementInViewCenterPoint(element_id, reply) =>
+                webdriver_handlers::handle_get_element_in_view_center_point(&*documents,
+                                                                            pipeline_id,
+                                                                            element_id,
+                                                                            reply),
+            WebDriverScriptCommand::GetElementProperty(node_id, name, reply) =>
+                webdriver_handlers::handle_get_property(&*documents, pipeline_id, node_id, name, reply),
             WebDriverScriptCommand::GetElementRect(node_id, reply) =>
                 webdriver_handlers::handle_get_rect(&*documents, pipeline_id, node_id, reply),
             WebDriverScriptCommand::GetElementText(node_id, reply) =>
                 webdriver_handlers::handle_get_text(&*documents, pipeline_id, node_id, reply),
             WebDriverScriptCommand::GetBrowsingContextId(webdriver_frame_id, reply) =>
                 webdriver_handlers::handle_get_browsing_context_id(&*documents, pipeline_id, webdriver_frame_id, reply),
+            WebDriverScriptCommand::GetFrameOffset(browsing_context_id, reply) =>
+                webdriver_handlers::handle_get_frame_offset(&*documents, pipeline_id, browsing_context_id, reply),
+            WebDriverScriptCommand::GetViewportSize(reply) =>
+                webdriver_handlers::handle_get_viewport_size(&*documents, pipeline_id, reply),
+            WebDriverScriptCommand::GetPageSource(reply) =>
+                webdriver_handlers::handle_get_page_source(&*documents, pipeline_id, reply),
             WebDriverScriptCommand::GetUrl(reply) =>
                 webdriver_handlers::handle_get_url(&*documents, pipeline_id, reply),
             WebDriverScriptCommand::IsEnabled(element_id, reply) =>
@@ -1585,7 +1816,6 @@ impl ScriptThread {
 
     fn collect_reports(&self, reports_chan: ReportsChan) {
         let mut path_seg = String::from("url(");
-        let mut dom_tree_size = 0;
         let mut reports = vec![];
         // Servo uses vanilla jemalloc, which doesn't have a
         // malloc_enclosing_size_of function.
@@ -1594,6 +1824,7 @@ impl ScriptThread {
         for (_, document) in self.documents.borrow().iter() {
             let current_url = document.url();
 
+            let mut dom_tree_size = 0;
             for child in document.upcast::<Node>().traverse_preorder() {
                 dom_tree_size += malloc_size_of_including_self(&mut ops, &*child);
             }
@@ -1843,6 +2074,7 @@ impl ScriptThread {
         debug!("Exiting pipeline {}.", id);
 
         self.closed_pipelines.borrow_mut().insert(id);
+        self.stop_js_sampling(id);
 
         // Check if the exit message is for an in progress load.
         let idx = self.incomplete_loads.borrow().iter().position(|load| {
@@ -1959,6 +2191,7 @@ impl ScriptThread {
             parent: EventInit {
                 bubbles: true,
                 cancelable: false,
+                composed: false,
             },
             propertyName: DOMString::from(name),
             elapsedTime: Finite::new(duration as f32).unwrap(),
@@ -2217,6 +2450,9 @@ impl ScriptThread {
                                      referrer,
                                      referrer_policy);
         document.set_ready_state(DocumentReadyState::Loading);
+        if let Some(ref headers) = metadata.headers {
+            document.append_csp_list(CspList::from_headers(headers));
+        }
 
         self.documents.borrow_mut().insert(incomplete.pipeline_id, &*document);
 
@@ -2228,6 +2464,9 @@ impl ScriptThread {
 
         // Notify devtools that a new script global exists.
         self.notify_devtools(document.Title(), final_url.clone(), (incomplete.pipeline_id, None));
+        if let Some(ref debugger) = self.debugger {
+            debugger.add_debuggee(self.get_cx(), window.upcast());
+        }
 
         let parse_input = DOMString::new();
 
@@ -2453,6 +2692,11 @@ impl ScriptThread {
         if is_javascript {
             let window = self.documents.borrow().find_window(parent_pipeline_id);
             if let Some(window) = window {
+                // https://w3c.github.io/webappsec-csp/#should-block-navigation-request
+                let url = load_data.url.clone();
+                if window.Document().should_inline_be_blocked(InlineCheckType::Navigation, None, url.as_str()) {
+                    return;
+                }
                 ScriptThread::eval_js_url(window.upcast::<GlobalScope>(), &mut load_data);
             }
         }
@@ -2689,6 +2933,16 @@ impl Drop for ScriptThread {
     }
 }
 
+/// Set while devtools are profiling script, to sample its stack whenever it is interrupted.
+unsafe extern "C" fn interrupt_callback(cx: *mut JSContext) -> bool {
+    ScriptThread::sample_js_stack(cx);
+    // Whoever else interrupts the script decides whether it carries on running.
+    match PREVIOUS_INTERRUPT_CALLBACK.with(|callback| callback.get()) {
+        Some(previous) => previous(cx),
+        None => true,
+    }
+}
+
 fn dom_last_modified(tm: &Tm) -> String {
     tm.to_local().strftime("%m/%d/%Y %H:%M:%S").unwrap().to_string()
 }
@@ -20,6 +20,7 @@ use hyper_serde::Serde;
 use ipc_channel::ipc;
 use ipc_channel::router::ROUTER;
 use net_traits::{FetchResponseListener, FetchMetadata, FilteredMetadata, Metadata, NetworkError, ReferrerPolicy};
+use net_traits::csp::Violation;
 use net_traits::request::{CorsSettings, CredentialsMode, Destination, RequestInit, RequestMode};
 use network_listener::{NetworkListener, PreInvoke};
 use parking_lot::RwLock;
@@ -110,6 +111,10 @@ impl FetchResponseListener for StylesheetContext {
         self.data.append(&mut payload);
     }
 
+    fn process_csp_violations(&mut self, violations: Vec<Violation>) {
+        self.document.root().report_csp_violations(violations);
+    }
+
     fn process_response_eof(&mut self, status: Result<(), NetworkError>) {
         let elem = self.elem.root();
         let document = self.document.root();
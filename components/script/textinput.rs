@@ -112,12 +112,12 @@ pub enum Direction {
 /// Was the keyboard event accompanied by the standard control modifier,
 /// i.e. cmd on Mac OS or ctrl on other platforms.
 #[cfg(target_os = "macos")]
-fn is_control_key(mods: KeyModifiers) -> bool {
+pub fn is_control_key(mods: KeyModifiers) -> bool {
     mods.contains(KeyModifiers::SUPER) && !mods.contains(KeyModifiers::CONTROL | KeyModifiers::ALT)
 }
 
 #[cfg(not(target_os = "macos"))]
-fn is_control_key(mods: KeyModifiers) -> bool {
+pub fn is_control_key(mods: KeyModifiers) -> bool {
     mods.contains(KeyModifiers::CONTROL) && !mods.contains(KeyModifiers::SUPER | KeyModifiers::ALT)
 }
 
@@ -134,7 +134,7 @@ fn len_of_first_n_chars(text: &str, n: usize) -> usize {
 /// The length in bytes of the first n code units a string when encoded in UTF-16.
 ///
 /// If the string is fewer than n code units, returns the length of the whole string.
-fn len_of_first_n_code_units(text: &str, n: usize) -> usize {
+pub fn len_of_first_n_code_units(text: &str, n: usize) -> usize {
     let mut utf8_len = 0;
     let mut utf16_len = 0;
     for c in text.chars() {
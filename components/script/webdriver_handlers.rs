@@ -4,38 +4,49 @@
 
 use cookie_rs::Cookie;
 use dom::bindings::codegen::Bindings::CSSStyleDeclarationBinding::CSSStyleDeclarationMethods;
+use dom::bindings::codegen::Bindings::DOMRectReadOnlyBinding::DOMRectReadOnlyMethods;
 use dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
 use dom::bindings::codegen::Bindings::ElementBinding::ElementMethods;
 use dom::bindings::codegen::Bindings::HTMLElementBinding::HTMLElementMethods;
 use dom::bindings::codegen::Bindings::HTMLInputElementBinding::HTMLInputElementMethods;
 use dom::bindings::codegen::Bindings::HTMLOptionElementBinding::HTMLOptionElementMethods;
+use dom::bindings::codegen::Bindings::HTMLTextAreaElementBinding::HTMLTextAreaElementMethods;
 use dom::bindings::codegen::Bindings::NodeBinding::NodeMethods;
-use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
+use dom::bindings::codegen::Bindings::WindowBinding::{ScrollBehavior, WindowMethods};
 use dom::bindings::conversions::{ConversionResult, FromJSValConvertible, StringificationBehavior};
+use dom::bindings::conversions::get_property_jsval;
 use dom::bindings::inheritance::Castable;
+use dom::bindings::num::Finite;
+use dom::bindings::reflector::DomObject;
 use dom::bindings::root::DomRoot;
 use dom::bindings::str::DOMString;
+use dom::domrectreadonly::DOMRectReadOnly;
 use dom::element::Element;
+use dom::eventtarget::EventTarget;
 use dom::globalscope::GlobalScope;
+use dom::htmldatalistelement::HTMLDataListElement;
 use dom::htmlelement::HTMLElement;
 use dom::htmliframeelement::HTMLIFrameElement;
 use dom::htmlinputelement::HTMLInputElement;
 use dom::htmloptionelement::HTMLOptionElement;
-use dom::node::{Node, window_from_node};
+use dom::htmlselectelement::HTMLSelectElement;
+use dom::htmltextareaelement::HTMLTextAreaElement;
+use dom::node::{Node, document_from_node, window_from_node};
 use euclid::{Point2D, Rect, Size2D};
 use hyper_serde::Serde;
 use ipc_channel::ipc::{self, IpcSender};
-use js::jsapi::{HandleValue, JSContext};
+use js::jsapi::{HandleValue, JSAutoCompartment, JSContext};
 use js::jsval::UndefinedValue;
 use msg::constellation_msg::BrowsingContextId;
 use msg::constellation_msg::PipelineId;
 use net_traits::CookieSource::{HTTP, NonHTTP};
-use net_traits::CoreResourceMsg::{GetCookiesDataForUrl, SetCookieForUrl};
+use net_traits::CoreResourceMsg::{GetCookiesDataForUrl, SetCookieForUrl, SetCookiesForUrl};
 use net_traits::IpcSend;
 use script_thread::Documents;
 use script_traits::webdriver_msg::{WebDriverFrameId, WebDriverJSError, WebDriverJSResult, WebDriverJSValue};
-use script_traits::webdriver_msg::WebDriverCookieError;
+use script_traits::webdriver_msg::{WebDriverCookieError, WebDriverElementError};
 use servo_url::ServoUrl;
+use time::Duration;
 
 fn find_node_by_unique_id(documents: &Documents,
                           pipeline: PipelineId,
@@ -132,6 +143,39 @@ pub fn handle_get_browsing_context_id(documents: &Documents,
     reply.send(result).unwrap()
 }
 
+/// The origin of the content box of the iframe of a child browsing context, which is where
+/// its viewport is, relative to the viewport of the document.
+pub fn handle_get_frame_offset(documents: &Documents,
+                               pipeline: PipelineId,
+                               browsing_context_id: BrowsingContextId,
+                               reply: IpcSender<Result<Point2D<f32>, ()>>) {
+    let iframe = documents.find_document(pipeline).and_then(|document| document.find_iframe(browsing_context_id));
+    reply.send(match iframe {
+        Some(iframe) => {
+            let element = iframe.upcast::<Element>();
+            let style = window_from_node(element).GetComputedStyle(element, None);
+            let padding = |property: &str| {
+                let value = String::from(style.GetPropertyValue(DOMString::from(property)));
+                value.trim_right_matches("px").parse::<f64>().unwrap_or(0.)
+            };
+            let rect = element.GetBoundingClientRect();
+            let rect = rect.upcast::<DOMRectReadOnly>();
+            Ok(Point2D::new((rect.X() + element.ClientLeft() as f64 + padding("padding-left")) as f32,
+                            (rect.Y() + element.ClientTop() as f64 + padding("padding-top")) as f32))
+        },
+        None => Err(()),
+    }).unwrap();
+}
+
+pub fn handle_get_viewport_size(documents: &Documents,
+                                pipeline: PipelineId,
+                                reply: IpcSender<Result<Size2D<f32>, ()>>) {
+    reply.send(match documents.find_window(pipeline) {
+        Some(window) => Ok(Size2D::new(window.InnerWidth() as f32, window.InnerHeight() as f32)),
+        None => Err(()),
+    }).unwrap();
+}
+
 pub fn handle_find_element_css(documents: &Documents, pipeline: PipelineId, selector: String,
                                reply: IpcSender<Result<Option<String>, ()>>) {
     let node_id = documents.find_document(pipeline)
@@ -141,6 +185,38 @@ pub fn handle_find_element_css(documents: &Documents, pipeline: PipelineId, sele
     reply.send(node_id).unwrap();
 }
 
+pub fn handle_find_element_element_css(documents: &Documents,
+                                       pipeline: PipelineId,
+                                       selector: String,
+                                       element_id: String,
+                                       reply: IpcSender<Result<Option<String>, WebDriverElementError>>) {
+    let node_id = find_node_by_unique_id(documents, pipeline, element_id)
+        .and_then(|node| DomRoot::downcast::<Element>(node))
+        .ok_or(WebDriverElementError::StaleElementReference)
+        .and_then(|element| {
+            element.QuerySelector(DOMString::from(selector))
+                .map_err(|_| WebDriverElementError::InvalidSelector)
+        })
+        .map(|node| node.map(|x| x.upcast::<Node>().unique_id()));
+    reply.send(node_id).unwrap();
+}
+
+pub fn handle_find_element_elements_css(documents: &Documents,
+                                        pipeline: PipelineId,
+                                        selector: String,
+                                        element_id: String,
+                                        reply: IpcSender<Result<Vec<String>, WebDriverElementError>>) {
+    let node_ids = find_node_by_unique_id(documents, pipeline, element_id)
+        .and_then(|node| DomRoot::downcast::<Element>(node))
+        .ok_or(WebDriverElementError::StaleElementReference)
+        .and_then(|element| {
+            element.QuerySelectorAll(DOMString::from(selector))
+                .map_err(|_| WebDriverElementError::InvalidSelector)
+        })
+        .map(|nodes| nodes.iter().map(|x| x.upcast::<Node>().unique_id()).collect());
+    reply.send(node_ids).unwrap();
+}
+
 pub fn handle_find_elements_css(documents: &Documents,
                                 pipeline: PipelineId,
                                 selector: String,
@@ -217,6 +293,46 @@ pub fn handle_get_cookie(documents: &Documents,
     reply.send(cookies.into_iter().filter(|c| c.name() == &*name).collect()).unwrap();
 }
 
+/// Deletes the cookies visible to the document with the given name, or all of them,
+/// by replacing them with cookies that have already expired.
+fn delete_cookies(documents: &Documents, pipeline: PipelineId, name: Option<String>) {
+    let document = match documents.find_document(pipeline) {
+        Some(document) => document,
+        None => return,
+    };
+    let url = document.url();
+    let resource_threads = document.window().upcast::<GlobalScope>().resource_threads();
+
+    let (sender, receiver) = ipc::channel().unwrap();
+    let _ = resource_threads.send(GetCookiesDataForUrl(url.clone(), sender, HTTP));
+    let expired_cookies = receiver.recv().unwrap().into_iter()
+        .filter(|cookie| name.as_ref().map_or(true, |name| cookie.name() == &**name))
+        .map(|cookie| {
+            let mut cookie = cookie.into_inner();
+            cookie.set_max_age(Duration::seconds(0));
+            Serde(cookie)
+        })
+        .collect();
+    let _ = resource_threads.send(SetCookiesForUrl(url, expired_cookies, HTTP));
+}
+
+// https://w3c.github.io/webdriver/webdriver-spec.html#delete-cookie
+pub fn handle_delete_cookie(documents: &Documents,
+                            pipeline: PipelineId,
+                            name: String,
+                            reply: IpcSender<()>) {
+    delete_cookies(documents, pipeline, Some(name));
+    reply.send(()).unwrap();
+}
+
+// https://w3c.github.io/webdriver/webdriver-spec.html#delete-all-cookies
+pub fn handle_delete_cookies(documents: &Documents,
+                             pipeline: PipelineId,
+                             reply: IpcSender<()>) {
+    delete_cookies(documents, pipeline, None);
+    reply.send(()).unwrap();
+}
+
 // https://w3c.github.io/webdriver/webdriver-spec.html#add-cookie
 pub fn handle_add_cookie(documents: &Documents,
                          pipeline: PipelineId,
@@ -301,6 +417,21 @@ pub fn handle_get_rect(documents: &Documents,
     }).unwrap();
 }
 
+// https://w3c.github.io/webdriver/webdriver-spec.html#get-page-source
+pub fn handle_get_page_source(documents: &Documents,
+                              pipeline: PipelineId,
+                              reply: IpcSender<Result<String, ()>>) {
+    reply.send(match documents.find_document(pipeline) {
+        Some(document) => {
+            let source = document.GetDocumentElement()
+                .and_then(|element| element.GetOuterHTML().ok())
+                .map_or(String::new(), String::from);
+            Ok(source)
+        },
+        None => Err(()),
+    }).unwrap();
+}
+
 pub fn handle_get_text(documents: &Documents,
                        pipeline: PipelineId,
                        node_id: String,
@@ -339,6 +470,30 @@ pub fn handle_get_attribute(documents: &Documents,
     }).unwrap();
 }
 
+// https://w3c.github.io/webdriver/webdriver-spec.html#get-element-property
+#[allow(unsafe_code)]
+pub fn handle_get_property(documents: &Documents,
+                           pipeline: PipelineId,
+                           node_id: String,
+                           name: String,
+                           reply: IpcSender<Result<WebDriverJSValue, ()>>) {
+    reply.send(match find_node_by_unique_id(documents, pipeline, node_id) {
+        Some(node) => {
+            let window = window_from_node(&*node);
+            let cx = window.get_cx();
+            let object = node.reflector().get_jsobject();
+            let _ac = JSAutoCompartment::new(cx, object.get());
+            rooted!(in(cx) let mut property = UndefinedValue());
+            match unsafe { get_property_jsval(cx, object, &name, property.handle_mut()) } {
+                // TODO: return objects and elements once WebDriverJSValue can represent them.
+                Ok(()) => Ok(unsafe { jsval_to_webdriver(cx, property.handle()) }.unwrap_or(WebDriverJSValue::Null)),
+                Err(_) => Ok(WebDriverJSValue::Null),
+            }
+        },
+        None => Err(()),
+    }).unwrap();
+}
+
 pub fn handle_get_css(documents: &Documents,
                       pipeline: PipelineId,
                       node_id: String,
@@ -401,3 +556,197 @@ pub fn handle_is_selected(documents: &Documents,
         None => Err(())
     }).unwrap();
 }
+
+/// <https://w3c.github.io/webdriver/webdriver-spec.html#dfn-container>
+fn element_container(element: &Element) -> DomRoot<Element> {
+    if element.is::<HTMLOptionElement>() {
+        let container = element.upcast::<Node>().ancestors().find(|node| {
+            node.is::<HTMLSelectElement>() || node.is::<HTMLDataListElement>()
+        });
+        if let Some(container) = container {
+            return DomRoot::downcast::<Element>(container).unwrap();
+        }
+    }
+    DomRoot::from_ref(element)
+}
+
+/// <https://w3c.github.io/webdriver/webdriver-spec.html#dfn-scrolls-into-view>
+fn scroll_into_view(element: &Element) {
+    let window = window_from_node(element);
+    let rect = element.GetBoundingClientRect();
+    let rect = rect.upcast::<DOMRectReadOnly>();
+    let width = window.InnerWidth() as f64;
+    let height = window.InnerHeight() as f64;
+
+    // Align the element with the end of the viewport in the block direction,
+    // and with its nearest edge in the inline direction.
+    let dx = if rect.Left() < 0. {
+        rect.Left()
+    } else if rect.Right() > width {
+        (rect.Right() - width).min(rect.Left())
+    } else {
+        0.
+    };
+    let dy = if rect.Bottom() > height || rect.Top() < 0. {
+        rect.Bottom() - height
+    } else {
+        0.
+    };
+    if dx != 0. || dy != 0. {
+        window.scroll(window.ScrollX() as f64 + dx,
+                      window.ScrollY() as f64 + dy,
+                      ScrollBehavior::Instant);
+    }
+}
+
+/// <https://w3c.github.io/webdriver/webdriver-spec.html#dfn-in-view-center-point>
+fn in_view_center_point(element: &Element) -> Option<Point2D<i64>> {
+    let window = window_from_node(element);
+
+    // Step 1.
+    let rects = element.GetClientRects();
+    let rect = match rects.first() {
+        Some(rect) => rect.upcast::<DOMRectReadOnly>(),
+        None => return None,
+    };
+
+    // Steps 2-5.
+    let left = rect.Left().min(rect.Right()).max(0.);
+    let right = rect.Left().max(rect.Right()).min(window.InnerWidth() as f64);
+    let top = rect.Top().min(rect.Bottom()).max(0.);
+    let bottom = rect.Top().max(rect.Bottom()).min(window.InnerHeight() as f64);
+    if left > right || top > bottom {
+        return None;
+    }
+
+    // Steps 6-8.
+    Some(Point2D::new(((left + right) / 2.).floor() as i64,
+                      ((top + bottom) / 2.).floor() as i64))
+}
+
+/// <https://w3c.github.io/webdriver/webdriver-spec.html#dfn-obscuring>
+fn is_obscured(element: &Element, point: Point2D<i64>) -> bool {
+    let document = document_from_node(element);
+    let elements = document.ElementsFromPoint(Finite::wrap(point.x as f64), Finite::wrap(point.y as f64));
+    match elements.first() {
+        Some(topmost) => !element.upcast::<Node>().is_inclusive_ancestor_of(topmost.upcast()),
+        None => true,
+    }
+}
+
+pub fn handle_element_click(documents: &Documents,
+                            pipeline: PipelineId,
+                            element_id: String,
+                            reply: IpcSender<Result<Option<Point2D<i64>>, WebDriverElementError>>) {
+    let node = match find_node_by_unique_id(documents, pipeline, element_id) {
+        Some(node) => node,
+        None => return reply.send(Err(WebDriverElementError::StaleElementReference)).unwrap(),
+    };
+    let element = match node.downcast::<Element>() {
+        Some(element) => element,
+        None => return reply.send(Err(WebDriverElementError::StaleElementReference)).unwrap(),
+    };
+
+    // https://w3c.github.io/webdriver/webdriver-spec.html#element-click
+    // Step 4.
+    let is_file_input = node.downcast::<HTMLInputElement>()
+        .map_or(false, |input| input.type_() == atom!("file"));
+    if is_file_input {
+        return reply.send(Err(WebDriverElementError::InvalidArgument)).unwrap();
+    }
+
+    // Step 5.
+    let container = element_container(element);
+    scroll_into_view(&container);
+
+    // Steps 6-7.
+    let point = match in_view_center_point(&container) {
+        Some(point) => point,
+        None => return reply.send(Err(WebDriverElementError::ElementNotInteractable)).unwrap(),
+    };
+
+    // Step 8.
+    if is_obscured(&container, point) {
+        return reply.send(Err(WebDriverElementError::ElementClickIntercepted)).unwrap();
+    }
+
+    // Step 9. Options are selected here; for other elements the WebDriver server
+    // synthesizes mouse events at the returned point.
+    if let Some(option) = node.downcast::<HTMLOptionElement>() {
+        if !element.disabled_state() {
+            option.SetSelected(true);
+            let target = container.upcast::<EventTarget>();
+            target.fire_bubbling_event(atom!("input"));
+            target.fire_bubbling_event(atom!("change"));
+        }
+        return reply.send(Ok(None)).unwrap();
+    }
+
+    reply.send(Ok(Some(point))).unwrap();
+}
+
+pub fn handle_element_clear(documents: &Documents,
+                            pipeline: PipelineId,
+                            element_id: String,
+                            reply: IpcSender<Result<(), WebDriverElementError>>) {
+    let node = match find_node_by_unique_id(documents, pipeline, element_id) {
+        Some(node) => node,
+        None => return reply.send(Err(WebDriverElementError::StaleElementReference)).unwrap(),
+    };
+    let element = match node.downcast::<Element>() {
+        Some(element) => element,
+        None => return reply.send(Err(WebDriverElementError::StaleElementReference)).unwrap(),
+    };
+
+    // https://w3c.github.io/webdriver/webdriver-spec.html#element-clear
+    // Step 4. Only mutable form controls holding text are editable here.
+    let is_editable = if let Some(input) = node.downcast::<HTMLInputElement>() {
+        let clearable = match &*input.type_() {
+            "checkbox" | "radio" | "submit" | "reset" | "button" | "image" | "hidden" => false,
+            _ => true,
+        };
+        clearable && !element.disabled_state() && !input.ReadOnly()
+    } else if let Some(textarea) = node.downcast::<HTMLTextAreaElement>() {
+        !element.disabled_state() && !textarea.ReadOnly()
+    } else {
+        false
+    };
+    if !is_editable {
+        return reply.send(Err(WebDriverElementError::InvalidElementState)).unwrap();
+    }
+
+    // Step 5.
+    scroll_into_view(element);
+
+    // Step 6.
+    if in_view_center_point(element).is_none() {
+        return reply.send(Err(WebDriverElementError::ElementNotInteractable)).unwrap();
+    }
+
+    // Step 7.
+    let html_element = node.downcast::<HTMLElement>().unwrap();
+    html_element.Focus();
+    if let Some(input) = node.downcast::<HTMLInputElement>() {
+        let _ = input.SetValue(DOMString::new());
+    } else if let Some(textarea) = node.downcast::<HTMLTextAreaElement>() {
+        textarea.SetValue(DOMString::new());
+    }
+    html_element.Blur();
+
+    reply.send(Ok(())).unwrap();
+}
+
+pub fn handle_get_element_in_view_center_point(documents: &Documents,
+                                               pipeline: PipelineId,
+                                               element_id: String,
+                                               reply: IpcSender<Result<Option<Point2D<i64>>, ()>>) {
+    reply.send(match find_node_by_unique_id(documents, pipeline, element_id) {
+        Some(node) => {
+            match node.downcast::<Element>() {
+                Some(element) => Ok(in_view_center_point(element)),
+                None => Err(()),
+            }
+        },
+        None => Err(()),
+    }).unwrap();
+}
@@ -42,6 +42,7 @@ pub mod wrapper_traits;
 
 use atomic_refcell::AtomicRefCell;
 use canvas_traits::canvas::CanvasMsg;
+use cssparser::RGBA;
 use ipc_channel::ipc::IpcSender;
 use libc::c_void;
 use net_traits::image_cache::PendingImageId;
@@ -138,6 +139,15 @@ pub struct SVGSVGData {
     pub height: u32,
 }
 
+/// An `<input>` element that is rendered as a widget rather than as text.
+#[derive(Clone, Copy, Debug)]
+pub enum HTMLInputWidget {
+    /// A range control, with the position of its thumb as a fraction of the track.
+    Range(f32),
+    /// A color well showing the selected color.
+    Color(RGBA),
+}
+
 /// The address of a node known to be valid. These are sent from script to layout.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct TrustedNodeAddress(pub *const c_void);
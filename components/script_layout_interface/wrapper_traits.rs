@@ -5,6 +5,7 @@
 #![allow(unsafe_code)]
 
 use HTMLCanvasData;
+use HTMLInputWidget;
 use LayoutNodeType;
 use OpaqueStyleAndLayoutData;
 use SVGSVGData;
@@ -254,6 +255,9 @@ pub trait ThreadSafeLayoutNode: Clone + Copy + Debug + GetLayoutData + NodeInfo
 
     fn svg_data(&self) -> Option<SVGSVGData>;
 
+    /// If this is an `<input>` element rendered as a widget, returns how to paint it.
+    fn input_widget(&self) -> Option<HTMLInputWidget>;
+
     /// If this node is an iframe element, returns its browsing context ID. If this node is
     /// not an iframe element, fails. Returns None if there is no nested browsing context.
     fn iframe_browsing_context_id(&self) -> Option<BrowsingContextId>;
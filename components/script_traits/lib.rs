@@ -51,6 +51,7 @@ use libc::c_void;
 use msg::constellation_msg::{BrowsingContextId, TopLevelBrowsingContextId, FrameType, Key, KeyModifiers, KeyState};
 use msg::constellation_msg::{PipelineId, PipelineNamespaceId, TraversalDirection};
 use net_traits::{FetchResponseMsg, ReferrerPolicy, ResourceThreads};
+use net_traits::csp::CspList;
 use net_traits::image::base::Image;
 use net_traits::image::base::PixelFormat;
 use net_traits::image_cache::ImageCache;
@@ -75,6 +76,7 @@ use webvr_traits::{WebVREvent, WebVRMsg};
 
 pub use script_msg::{LayoutMsg, ScriptMsg, EventResult, LogEntry};
 pub use script_msg::{ServiceWorkerMsg, ScopeThings, SWManagerMsg, SWManagerSenders, DOMMessage};
+pub use script_msg::{SimpleDialog, SimpleDialogResponse};
 
 /// The address of a node. Layout sends these back. They must be validated via
 /// `from_untrusted_node_address` before they can be used, because we do not trust layout.
@@ -749,8 +751,32 @@ pub enum WindowSizeType {
 pub enum WebDriverCommandMsg {
     /// Get the window size.
     GetWindowSize(TopLevelBrowsingContextId, IpcSender<WindowSizeData>),
+    /// Get the outer size and position of the window containing the top-level browsing context.
+    GetWindowRect(TopLevelBrowsingContextId, IpcSender<(Size2D<u32>, Point2D<i32>)>),
+    /// Move the window containing the top-level browsing context.
+    SetWindowPosition(TopLevelBrowsingContextId, Point2D<i32>),
+    /// Get the size of the screen available to the window containing the top-level browsing context.
+    GetScreenAvailSize(TopLevelBrowsingContextId, IpcSender<Size2D<u32>>),
+    /// Get the IDs of all the top-level browsing contexts.
+    GetTopLevelBrowsingContexts(IpcSender<Vec<TopLevelBrowsingContextId>>),
     /// Load a URL in the top-level browsing context with the given ID.
     LoadUrl(TopLevelBrowsingContextId, LoadData, IpcSender<LoadStatus>),
+    /// Act as if a mouse button changed state at the given point, in CSS pixels
+    /// relative to the viewport of the top-level browsing context. The point was
+    /// translated from the viewport of the given browsing context.
+    MouseButtonAction(TopLevelBrowsingContextId, BrowsingContextId, MouseEventType, MouseButton, f32, f32),
+    /// Act as if the mouse was moved to the given point, in CSS pixels relative
+    /// to the viewport of the top-level browsing context. The point was
+    /// translated from the viewport of the given browsing context.
+    MouseMoveAction(TopLevelBrowsingContextId, BrowsingContextId, f32, f32),
+    /// Get the simple dialog open in the top-level browsing context, if any.
+    GetSimpleDialog(TopLevelBrowsingContextId, IpcSender<Option<SimpleDialog>>),
+    /// Set the value that the prompt open in the top-level browsing context returns
+    /// when it is accepted.
+    SetSimpleDialogText(TopLevelBrowsingContextId, String),
+    /// Accept (true) or dismiss (false) the simple dialog open in the top-level
+    /// browsing context.
+    CloseSimpleDialog(TopLevelBrowsingContextId, bool),
     /// Refresh the top-level browsing context with the given ID.
     Refresh(TopLevelBrowsingContextId, IpcSender<LoadStatus>),
     /// Pass a webdriver command to the script thread of the current pipeline
@@ -802,7 +828,7 @@ pub enum ConstellationMsg {
     NewBrowser(ServoUrl, IpcSender<TopLevelBrowsingContextId>),
     /// Close a top level browsing context.
     CloseBrowser(TopLevelBrowsingContextId),
-    /// Make browser visible.
+    /// Make browser visible, and give it focus.
     SelectBrowser(TopLevelBrowsingContextId),
     /// Forward an event to the script task of the given pipeline.
     ForwardEvent(PipelineId, CompositorEvent),
@@ -833,6 +859,8 @@ pub struct WorkerGlobalScopeInit {
     pub pipeline_id: PipelineId,
     /// The origin
     pub origin: ImmutableOrigin,
+    /// The CSP list of the creator, replaced by that of the worker script once it is loaded
+    pub csp_list: CspList,
 }
 
 /// Common entities representing a network load origin
@@ -66,6 +66,40 @@ pub enum LogEntry {
     Warn(String),
 }
 
+/// A simple dialog opened by script.
+/// <https://html.spec.whatwg.org/multipage/#simple-dialogs>
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum SimpleDialog {
+    /// `window.alert()`, with its message.
+    Alert(String),
+    /// `window.confirm()`, with its message.
+    Confirm(String),
+    /// `window.prompt()`, with its message and default value.
+    Prompt(String, String),
+}
+
+impl SimpleDialog {
+    /// The message shown by the dialog.
+    pub fn message(&self) -> &str {
+        match *self {
+            SimpleDialog::Alert(ref message) |
+            SimpleDialog::Confirm(ref message) |
+            SimpleDialog::Prompt(ref message, _) => message,
+        }
+    }
+}
+
+/// How a simple dialog was answered.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum SimpleDialogResponse {
+    /// Nothing answered the dialog on script's behalf, so it should be shown to the user.
+    Display,
+    /// The dialog was accepted, with the value entered for prompts.
+    Accept(String),
+    /// The dialog was dismissed.
+    Dismiss,
+}
+
 /// Messages from the script to the constellation.
 #[derive(Deserialize, Serialize)]
 pub enum ScriptMsg {
@@ -112,6 +146,8 @@ pub enum ScriptMsg {
     NewFavicon(ServoUrl),
     /// Status message to be displayed in the chrome, eg. a link URL on mouseover.
     NodeStatus(Option<String>),
+    /// The selection of the document changed; the string is the newly selected text.
+    SelectionChanged(String),
     /// Notification that this iframe should be removed.
     /// Returns a list of pipelines which were closed.
     RemoveIFrame(BrowsingContextId, IpcSender<Vec<PipelineId>>),
@@ -131,8 +167,8 @@ pub enum ScriptMsg {
     SetDocumentState(DocumentState),
     /// Update the pipeline Url, which can change after redirections.
     SetFinalUrl(ServoUrl),
-    /// Check if an alert dialog box should be presented
-    Alert(String, IpcSender<bool>),
+    /// Show a simple dialog, and wait for it to be answered.
+    ShowSimpleDialog(SimpleDialog, IpcSender<SimpleDialogResponse>),
     /// Set title of current page
     /// <https://html.spec.whatwg.org/multipage/#document.title>
     SetTitle(Option<String>),
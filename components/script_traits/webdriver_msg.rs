@@ -5,7 +5,7 @@
 #![allow(missing_docs)]
 
 use cookie_rs::Cookie;
-use euclid::Rect;
+use euclid::{Point2D, Rect, Size2D};
 use hyper_serde::Serde;
 use ipc_channel::ipc::IpcSender;
 use msg::constellation_msg::BrowsingContextId;
@@ -18,20 +18,34 @@ pub enum WebDriverScriptCommand {
                 serialize_with = "::hyper_serde::serialize")]
               Cookie<'static>,
               IpcSender<Result<(), WebDriverCookieError>>),
+    DeleteCookie(String, IpcSender<()>),
+    DeleteCookies(IpcSender<()>),
+    ElementClear(String, IpcSender<Result<(), WebDriverElementError>>),
+    ElementClick(String, IpcSender<Result<Option<Point2D<i64>>, WebDriverElementError>>),
     ExecuteScript(String, IpcSender<WebDriverJSResult>),
     ExecuteAsyncScript(String, IpcSender<WebDriverJSResult>),
     FindElementCSS(String, IpcSender<Result<Option<String>, ()>>),
+    FindElementElementCSS(String, String, IpcSender<Result<Option<String>, WebDriverElementError>>),
     FindElementsCSS(String, IpcSender<Result<Vec<String>, ()>>),
+    FindElementElementsCSS(String, String, IpcSender<Result<Vec<String>, WebDriverElementError>>),
     FocusElement(String, IpcSender<Result<(), ()>>),
     GetActiveElement(IpcSender<Option<String>>),
     GetCookie(String, IpcSender<Vec<Serde<Cookie<'static>>>>),
     GetCookies(IpcSender<Vec<Serde<Cookie<'static>>>>),
     GetElementAttribute(String, String, IpcSender<Result<Option<String>, ()>>),
     GetElementCSS(String, String, IpcSender<Result<String, ()>>),
+    GetElementInViewCenterPoint(String, IpcSender<Result<Option<Point2D<i64>>, ()>>),
+    GetElementProperty(String, String, IpcSender<Result<WebDriverJSValue, ()>>),
     GetElementRect(String, IpcSender<Result<Rect<f64>, ()>>),
     GetElementTagName(String, IpcSender<Result<String, ()>>),
     GetElementText(String, IpcSender<Result<String, ()>>),
+    GetPageSource(IpcSender<Result<String, ()>>),
     GetBrowsingContextId(WebDriverFrameId, IpcSender<Result<BrowsingContextId, ()>>),
+    /// Get the origin of the viewport of a child browsing context, relative to the viewport
+    /// of the browsing context the command is sent to.
+    GetFrameOffset(BrowsingContextId, IpcSender<Result<Point2D<f32>, ()>>),
+    /// Get the size of the viewport of the browsing context the command is sent to.
+    GetViewportSize(IpcSender<Result<Size2D<f32>, ()>>),
     GetUrl(IpcSender<ServoUrl>),
     IsEnabled(String, IpcSender<Result<bool, ()>>),
     IsSelected(String, IpcSender<Result<bool, ()>>),
@@ -44,6 +58,17 @@ pub enum WebDriverCookieError {
     UnableToSetCookie,
 }
 
+/// Errors raised by the commands operating on an element.
+#[derive(Deserialize, Serialize)]
+pub enum WebDriverElementError {
+    StaleElementReference,
+    InvalidArgument,
+    InvalidSelector,
+    InvalidElementState,
+    ElementNotInteractable,
+    ElementClickIntercepted,
+}
+
 #[derive(Deserialize, Serialize)]
 pub enum WebDriverJSValue {
     Undefined,
@@ -306,6 +306,20 @@ fn complex_selector_specificity<Impl>(mut iter: slice::Iter<Component<Impl>>)
                     simple_selector_specificity(&ss, specificity);
                 }
             }
+            Component::Host(ref argument) => {
+                specificity.class_like_selectors += 1;
+                if let Some(ref argument) = *argument {
+                    for ss in argument.iter() {
+                        simple_selector_specificity(&ss, specificity);
+                    }
+                }
+            }
+            Component::Slotted(ref argument) => {
+                specificity.element_selectors += 1;
+                for ss in argument.iter() {
+                    simple_selector_specificity(&ss, specificity);
+                }
+            }
         }
     }
 
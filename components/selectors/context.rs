@@ -106,6 +106,14 @@ where
     /// See https://drafts.csswg.org/selectors-4/#scope-pseudo
     pub scope_element: Option<OpaqueElement>,
 
+    /// The shadow host whose shadow tree the selectors being matched belong
+    /// to, if any.
+    ///
+    /// This is what `:host` matches, and it's what `::slotted()` requires the
+    /// assigned slot to be in the shadow tree of. Ancestor combinators don't
+    /// go past it.
+    pub current_host: Option<OpaqueElement>,
+
     /// The current nesting level of selectors that we're matching.
     pub nesting_level: usize,
 
@@ -155,6 +163,7 @@ where
             relevant_link_found: false,
             classes_and_ids_case_sensitivity: quirks_mode.classes_and_ids_case_sensitivity(),
             scope_element: None,
+            current_host: None,
             nesting_level: 0,
             pseudo_element_matching_fn: None,
             _impl: ::std::marker::PhantomData,
@@ -466,6 +466,8 @@ fn matches_hover_and_active_quirk<Impl: SelectorImpl>(
             Component::Class(_) |
             Component::PseudoElement(_) |
             Component::Negation(_) |
+            Component::Host(_) |
+            Component::Slotted(_) |
             Component::FirstChild |
             Component::LastChild |
             Component::OnlyChild |
@@ -494,6 +496,7 @@ enum Rightmost {
 fn next_element_for_combinator<E>(
     element: &E,
     combinator: Combinator,
+    context: &MatchingContext<E::Impl>,
 ) -> Option<E>
 where
     E: Element,
@@ -508,11 +511,30 @@ where
             if element.blocks_ancestor_combinators() {
                 return None;
             }
-            element.parent_element()
+            match context.current_host {
+                None => element.parent_element(),
+                Some(ref host) => {
+                    // The selectors of a shadow tree can reach its host
+                    // through ancestor combinators, but nothing above it.
+                    if *host == element.opaque() {
+                        return None;
+                    }
+                    match element.parent_element() {
+                        Some(parent) => Some(parent),
+                        None if element.parent_node_is_shadow_root() => {
+                            element.containing_shadow_host()
+                        }
+                        None => None,
+                    }
+                }
+            }
         }
         Combinator::PseudoElement => {
             element.pseudo_element_originating_element()
         }
+        Combinator::SlotAssignment => {
+            element.assigned_slot()
+        }
     }
 }
 
@@ -537,12 +559,23 @@ where
     let matches_all_simple_selectors = {
         let matches_hover_and_active_quirk =
             matches_hover_and_active_quirk(&selector_iter, context, rightmost);
+        // The shadow host is featureless for the selectors of its own shadow
+        // tree, only `:host` can match it.
+        let is_featureless_host = context.current_host.as_ref().map_or(false, |host| {
+            *host == element.opaque()
+        });
         let mut local_context =
             LocalMatchingContext {
                 shared: context,
                 matches_hover_and_active_quirk,
             };
         selector_iter.all(|simple| {
+            if is_featureless_host && !matches!(*simple, Component::Host(..) |
+                                                         Component::ExplicitUniversalType |
+                                                         Component::ExplicitAnyNamespace |
+                                                         Component::DefaultNamespace(..)) {
+                return false;
+            }
             matches_simple_selector(
                 simple,
                 element,
@@ -577,12 +610,13 @@ where
         }
         Combinator::Child |
         Combinator::Descendant |
-        Combinator::PseudoElement => {
+        Combinator::PseudoElement |
+        Combinator::SlotAssignment => {
             SelectorMatchingResult::NotMatchedGlobally
         }
     };
 
-    let mut next_element = next_element_for_combinator(element, combinator);
+    let mut next_element = next_element_for_combinator(element, combinator, context);
 
     loop {
         let element = match next_element {
@@ -609,6 +643,7 @@ where
             // Upgrade the failure status to
             // NotMatchedAndRestartFromClosestDescendant.
             (_, Combinator::PseudoElement) |
+            (_, Combinator::SlotAssignment) |
             (_, Combinator::Child) => {
                 return SelectorMatchingResult::NotMatchedAndRestartFromClosestDescendant;
             }
@@ -630,7 +665,7 @@ where
             _ => {},
         }
 
-        next_element = next_element_for_combinator(&element, combinator);
+        next_element = next_element_for_combinator(&element, combinator, context);
     }
 }
 
@@ -797,9 +832,55 @@ where
             context.shared.nesting_level -= 1;
             result
         }
+        Component::Host(ref argument) => {
+            let is_host = context.shared.current_host.as_ref().map_or(false, |host| {
+                *host == element.opaque()
+            });
+            match *argument {
+                Some(ref argument) if is_host => {
+                    matches_inner_compound_selector(argument, element, context, relevant_link, flags_setter)
+                }
+                _ => is_host,
+            }
+        }
+        Component::Slotted(ref argument) => {
+            let slot_host = element.assigned_slot().and_then(|slot| slot.containing_shadow_host());
+            let assigned_to_current_host = match (slot_host, context.shared.current_host.as_ref()) {
+                (Some(slot_host), Some(current_host)) => slot_host.opaque() == *current_host,
+                _ => false,
+            };
+            assigned_to_current_host &&
+            matches_inner_compound_selector(argument, element, context, relevant_link, flags_setter)
+        }
     }
 }
 
+/// Matches the compound selector argument of `:host()` and `::slotted()`.
+fn matches_inner_compound_selector<E, F>(
+    compound: &[Component<E::Impl>],
+    element: &E,
+    context: &mut LocalMatchingContext<E::Impl>,
+    relevant_link: &RelevantLinkStatus,
+    flags_setter: &mut F,
+) -> bool
+where
+    E: Element,
+    F: FnMut(&E, ElementSelectorFlags),
+{
+    context.shared.nesting_level += 1;
+    let result = compound.iter().all(|ss| {
+        matches_simple_selector(
+            ss,
+            element,
+            context,
+            relevant_link,
+            flags_setter,
+        )
+    });
+    context.shared.nesting_level -= 1;
+    result
+}
+
 #[inline(always)]
 fn select_name<'a, T>(is_html: bool, local_name: &'a T, local_name_lower: &'a T) -> &'a T {
     if is_html {
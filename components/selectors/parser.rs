@@ -55,6 +55,7 @@ pub enum SelectorParseErrorKind<'i> {
     EmptySelector,
     DanglingCombinator,
     NonSimpleSelectorInNegation,
+    NonCompoundSelector,
     UnexpectedTokenInAttributeSelector(Token<'i>),
     PseudoElementExpectedColon(Token<'i>),
     PseudoElementExpectedIdent(Token<'i>),
@@ -165,6 +166,16 @@ pub trait Parser<'i> {
         Err(arguments.new_custom_error(SelectorParseErrorKind::UnsupportedPseudoClassOrElement(name)))
     }
 
+    /// Whether to parse the `:host` pseudo-class.
+    fn parse_host(&self) -> bool {
+        false
+    }
+
+    /// Whether to parse the `::slotted()` pseudo-element.
+    fn parse_slotted(&self) -> bool {
+        false
+    }
+
     fn default_namespace(&self) -> Option<<Self::Impl as SelectorImpl>::NamespaceUrl> {
         None
     }
@@ -325,8 +336,10 @@ impl<Impl: SelectorImpl> SelectorMethods for Component<Impl> {
         }
 
         match *self {
-            Negation(ref negated) => {
-                for component in negated.iter() {
+            Negation(ref list) |
+            Host(Some(ref list)) |
+            Slotted(ref list) => {
+                for component in list.iter() {
                     if !component.visit(visitor) {
                         return false;
                     }
@@ -420,6 +433,13 @@ impl<Impl: SelectorImpl> Selector<Impl> {
         None
     }
 
+    /// Whether this selector's rightmost compound selector is a `::slotted()`
+    /// pseudo-element.
+    #[inline]
+    pub fn is_slotted(&self) -> bool {
+        matches!(self.iter_raw_match_order().next(), Some(&Component::Slotted(..)))
+    }
+
     /// Whether this selector (pseudo-element part excluded) matches every element.
     ///
     /// Used for "pre-computed" pseudo-elements in components/style/stylist.rs
@@ -630,6 +650,11 @@ pub enum Combinator {
     /// combinator for this, we will need to fix up the way hashes are computed
     /// for revalidation selectors.
     PseudoElement,
+    /// A dummy combinator we use to the left of `::slotted()`.
+    ///
+    /// It serializes as the empty string, and goes from a slotted element to
+    /// the slot it's assigned to.
+    SlotAssignment,
 }
 
 impl Combinator {
@@ -699,6 +724,10 @@ pub enum Component<Impl: SelectorImpl> {
     // and what the consumers of those APIs should do about the presence of
     // combinators in negation.
     Negation(Box<[Component<Impl>]>),
+    /// The `:host` pseudo-class, optionally taking a compound selector:
+    ///
+    /// https://drafts.csswg.org/css-scoping/#host-selector
+    Host(Option<Box<[Component<Impl>]>>),
     FirstChild, LastChild, OnlyChild,
     Root,
     Empty,
@@ -712,6 +741,11 @@ pub enum Component<Impl: SelectorImpl> {
     OnlyOfType,
     NonTSPseudoClass(Impl::NonTSPseudoClass),
     PseudoElement(Impl::PseudoElement),
+    /// The `::slotted()` pseudo-element. It is always the rightmost compound
+    /// selector, to the right of a `Combinator::SlotAssignment`:
+    ///
+    /// https://drafts.csswg.org/css-scoping/#slotted-pseudo
+    Slotted(Box<[Component<Impl>]>),
 }
 
 impl<Impl: SelectorImpl> Component<Impl> {
@@ -846,7 +880,8 @@ impl<Impl: SelectorImpl> ToCss for Selector<Impl> {
                         // contains the pseudo element selector as
                         // well--Combinator::PseudoElement doesn't exist in the
                         // spec.
-                        (Some(&&Component::Combinator(Combinator::PseudoElement)), _) => (),
+                        (Some(&&Component::Combinator(Combinator::PseudoElement)), _) |
+                        (Some(&&Component::Combinator(Combinator::SlotAssignment)), _) => (),
                         (_, &Component::ExplicitUniversalType) => {
                             // Iterate over everything so we serialize the namespace
                             // too.
@@ -913,7 +948,8 @@ impl ToCss for Combinator {
             Combinator::Descendant => dest.write_str(" "),
             Combinator::NextSibling => dest.write_str(" + "),
             Combinator::LaterSibling => dest.write_str(" ~ "),
-            Combinator::PseudoElement => Ok(()),
+            Combinator::PseudoElement |
+            Combinator::SlotAssignment => Ok(()),
         }
     }
 }
@@ -993,6 +1029,21 @@ impl<Impl: SelectorImpl> ToCss for Component<Impl> {
                 }
                 dest.write_str(")")
             }
+            Host(None) => dest.write_str(":host"),
+            Host(Some(ref arg)) => {
+                dest.write_str(":host(")?;
+                for component in arg.iter() {
+                    component.to_css(dest)?;
+                }
+                dest.write_str(")")
+            }
+            Slotted(ref arg) => {
+                dest.write_str("::slotted(")?;
+                for component in arg.iter() {
+                    component.to_css(dest)?;
+                }
+                dest.write_str(")")
+            }
 
             FirstChild => dest.write_str(":first-child"),
             LastChild => dest.write_str(":last-child"),
@@ -1094,8 +1145,8 @@ where
     let mut has_pseudo_element;
     'outer_loop: loop {
         // Parse a sequence of simple selectors.
-        has_pseudo_element = match parse_compound_selector(parser, input, &mut builder)? {
-            Some(has_pseudo_element) => has_pseudo_element,
+        let (parsed_pseudo_element, slotted) = match parse_compound_selector(parser, input, &mut builder)? {
+            Some(result) => result,
             None => {
                 return Err(input.new_custom_error(if builder.has_combinators() {
                     SelectorParseErrorKind::DanglingCombinator
@@ -1104,7 +1155,8 @@ where
                 }))
             }
         };
-        if has_pseudo_element {
+        has_pseudo_element = parsed_pseudo_element;
+        if has_pseudo_element || slotted {
             break;
         }
 
@@ -1235,6 +1287,7 @@ where
 enum SimpleSelectorParseResult<Impl: SelectorImpl> {
     SimpleSelector(Component<Impl>),
     PseudoElement(Impl::PseudoElement),
+    SlottedPseudo(Box<[Component<Impl>]>),
 }
 
 #[derive(Debug)]
@@ -1547,7 +1600,8 @@ where
             None => {
                 return Err(input.new_custom_error(SelectorParseErrorKind::EmptyNegation));
             },
-            Some(SimpleSelectorParseResult::PseudoElement(_)) => {
+            Some(SimpleSelectorParseResult::PseudoElement(_)) |
+            Some(SimpleSelectorParseResult::SlottedPseudo(_)) => {
                 return Err(input.new_custom_error(SelectorParseErrorKind::NonSimpleSelectorInNegation));
             }
         }
@@ -1557,6 +1611,49 @@ where
     Ok(Component::Negation(sequence.into_vec().into_boxed_slice()))
 }
 
+/// Parses the compound selector argument of `:host()` and `::slotted()`.
+fn parse_inner_compound_selector<'i, 't, P, Impl>(
+    parser: &P,
+    input: &mut CssParser<'i, 't>,
+) -> Result<Box<[Component<Impl>]>, ParseError<'i, P::Error>>
+where
+    P: Parser<'i, Impl=Impl>,
+    Impl: SelectorImpl,
+{
+    let mut sequence = SmallVec::<[Component<Impl>; 4]>::new();
+
+    input.skip_whitespace();
+
+    let mut empty = true;
+    if !parse_type_selector(parser, input, &mut sequence)? {
+        if let Some(url) = parser.default_namespace() {
+            sequence.push(Component::DefaultNamespace(url));
+        }
+    } else {
+        empty = false;
+    }
+
+    loop {
+        match parse_one_simple_selector(parser, input, /* inside_negation = */ false)? {
+            None => break,
+            Some(SimpleSelectorParseResult::SimpleSelector(s)) => {
+                sequence.push(s);
+                empty = false;
+            }
+            Some(SimpleSelectorParseResult::PseudoElement(_)) |
+            Some(SimpleSelectorParseResult::SlottedPseudo(_)) => {
+                return Err(input.new_custom_error(SelectorParseErrorKind::NonCompoundSelector));
+            }
+        }
+    }
+
+    if empty {
+        return Err(input.new_custom_error(SelectorParseErrorKind::EmptySelector));
+    }
+
+    Ok(sequence.into_vec().into_boxed_slice())
+}
+
 /// simple_selector_sequence
 /// : [ type_selector | universal ] [ HASH | class | attrib | pseudo | negation ]*
 /// | [ HASH | class | attrib | pseudo | negation ]+
@@ -1564,12 +1661,13 @@ where
 /// `Err(())` means invalid selector.
 /// `Ok(None)` is an empty selector
 ///
-/// The boolean represent whether a pseudo-element has been parsed.
+/// The booleans represent whether a pseudo-element and a `::slotted()`
+/// pseudo-element have been parsed, respectively.
 fn parse_compound_selector<'i, 't, P, Impl>(
     parser: &P,
     input: &mut CssParser<'i, 't>,
     builder: &mut SelectorBuilder<Impl>,
-) -> Result<Option<bool>, ParseError<'i, P::Error>>
+) -> Result<Option<(bool, bool)>, ParseError<'i, P::Error>>
 where
     P: Parser<'i, Impl=Impl>,
     Impl: SelectorImpl,
@@ -1589,6 +1687,7 @@ where
     }
 
     let mut pseudo = false;
+    let mut slotted = false;
     loop {
         match parse_one_simple_selector(parser, input, /* inside_negation = */ false)? {
             None => break,
@@ -1596,6 +1695,17 @@ where
                 builder.push_simple_selector(s);
                 empty = false
             }
+            Some(SimpleSelectorParseResult::SlottedPseudo(inner)) => {
+                if !builder.is_empty() {
+                    builder.push_combinator(Combinator::SlotAssignment);
+                }
+
+                builder.push_simple_selector(Component::Slotted(inner));
+
+                slotted = true;
+                empty = false;
+                break
+            }
             Some(SimpleSelectorParseResult::PseudoElement(p)) => {
                 // Try to parse state to its right. There are only 3 allowable
                 // state selectors that can go on pseudo-elements.
@@ -1651,7 +1761,7 @@ where
         // An empty selector is invalid.
         Ok(None)
     } else {
-        Ok(Some(pseudo))
+        Ok(Some((pseudo, slotted)))
     }
 }
 
@@ -1678,6 +1788,11 @@ where
             }
             return parse_negation(parser, input)
         },
+        "host" => {
+            if parser.parse_host() {
+                return Ok(Component::Host(Some(parse_inner_compound_selector(parser, input)?)))
+            }
+        },
         _ => {}
     }
     P::parse_non_ts_functional_pseudo_class(parser, name, input)
@@ -1762,6 +1877,12 @@ where
             let is_pseudo_element = !is_single_colon ||
                 P::is_pseudo_element_allows_single_colon(&name);
             if is_pseudo_element {
+                if is_functional && parser.parse_slotted() && name.eq_ignore_ascii_case("slotted") {
+                    let inner = input.parse_nested_block(|input| {
+                        parse_inner_compound_selector(parser, input)
+                    })?;
+                    return Ok(Some(SimpleSelectorParseResult::SlottedPseudo(inner)))
+                }
                 let pseudo_element = if is_functional {
                     input.parse_nested_block(|input| {
                         P::parse_functional_pseudo_element(parser, name, input)
@@ -1797,6 +1918,9 @@ where
     P: Parser<'i, Impl=Impl>,
     Impl: SelectorImpl
 {
+    if parser.parse_host() && name.eq_ignore_ascii_case("host") {
+        return Ok(Component::Host(None))
+    }
     (match_ignore_ascii_case! { &name,
         "first-child" => Ok(Component::FirstChild),
         "last-child"  => Ok(Component::LastChild),
@@ -1989,6 +2113,14 @@ pub mod tests {
             Err(location.new_custom_error(SelectorParseErrorKind::UnsupportedPseudoClassOrElement(name)))
         }
 
+        fn parse_host(&self) -> bool {
+            true
+        }
+
+        fn parse_slotted(&self) -> bool {
+            true
+        }
+
         fn default_namespace(&self) -> Option<DummyAtom> {
             self.default_ns.clone()
         }
@@ -2305,6 +2437,46 @@ pub mod tests {
                 Component::PseudoElement(PseudoElement::After),
             ), specificity(0, 0, 2) | HAS_PSEUDO_BIT)
         ))));
+        assert_eq!(parse(":host"), Ok(SelectorList::from_vec(vec!(
+            Selector::from_vec(vec!(
+                Component::Host(None),
+            ), specificity(0, 1, 0))
+        ))));
+        assert_eq!(parse(":host(.foo)"), Ok(SelectorList::from_vec(vec!(
+            Selector::from_vec(vec!(
+                Component::Host(Some(vec![
+                    Component::Class(DummyAtom::from("foo")),
+                ].into_boxed_slice())),
+            ), specificity(0, 2, 0))
+        ))));
+        assert_eq!(parse(":host > div"), Ok(SelectorList::from_vec(vec!(
+            Selector::from_vec(vec!(
+                Component::Host(None),
+                Component::Combinator(Combinator::Child),
+                Component::LocalName(LocalName {
+                    name: DummyAtom::from("div"),
+                    lower_name: DummyAtom::from("div") }),
+            ), specificity(0, 1, 1))
+        ))));
+        assert_eq!(parse("div::slotted(span.foo)"), Ok(SelectorList::from_vec(vec!(
+            Selector::from_vec(vec!(
+                Component::LocalName(LocalName {
+                    name: DummyAtom::from("div"),
+                    lower_name: DummyAtom::from("div") }),
+                Component::Combinator(Combinator::SlotAssignment),
+                Component::Slotted(vec![
+                    Component::LocalName(LocalName {
+                        name: DummyAtom::from("span"),
+                        lower_name: DummyAtom::from("span") }),
+                    Component::Class(DummyAtom::from("foo")),
+                ].into_boxed_slice()),
+            ), specificity(0, 1, 3))
+        ))));
+        assert!(parse(":host(div > span)").is_err());
+        assert!(parse(":host()").is_err());
+        assert!(parse("::slotted()").is_err());
+        assert!(parse("::slotted(span) div").is_err());
+        assert!(parse("::slotted(::before)").is_err());
         assert_eq!(parse("#d1 > .ok"), Ok(SelectorList::from_vec(vec!(
             Selector::from_vec(vec!(
                 Component::ID(DummyAtom::from("d1")),
@@ -118,4 +118,19 @@ pub trait Element: Sized + Clone + Debug {
     fn blocks_ancestor_combinators(&self) -> bool {
         false
     }
+
+    /// Whether the parent node of this element is a shadow root.
+    fn parent_node_is_shadow_root(&self) -> bool {
+        false
+    }
+
+    /// The shadow host of the shadow tree this element is in, if any.
+    fn containing_shadow_host(&self) -> Option<Self> {
+        None
+    }
+
+    /// The slot this element is assigned to, if any.
+    fn assigned_slot(&self) -> Option<Self> {
+        None
+    }
 }
@@ -80,6 +80,7 @@ use constellation::{Constellation, InitialConstellationState, UnprivilegedPipeli
 use constellation::{FromCompositorLogger, FromScriptLogger};
 #[cfg(all(not(target_os = "windows"), not(target_os = "ios")))]
 use constellation::content_process_sandbox_profile;
+use devtools::har::HarRecorder;
 use env_logger::Logger as EnvLogger;
 #[cfg(all(not(target_os = "windows"), not(target_os = "ios")))]
 use gaol::sandbox::{ChildSandbox, ChildSandboxMethods};
@@ -124,7 +125,8 @@ pub use msg::constellation_msg::TopLevelBrowsingContextId as BrowserId;
 pub struct Servo<Window: WindowMethods + 'static> {
     compositor: IOCompositor<Window>,
     constellation_chan: Sender<ConstellationMsg>,
-    embedder_receiver: EmbedderReceiver
+    embedder_receiver: EmbedderReceiver,
+    har_recorder: Option<HarRecorder>,
 }
 
 impl<Window> Servo<Window> where Window: WindowMethods + 'static {
@@ -147,12 +149,24 @@ impl<Window> Servo<Window> where Window: WindowMethods + 'static {
         let time_profiler_chan = profile_time::Profiler::create(&opts.time_profiling,
                                                                 opts.time_profiler_trace_path.clone());
         let mem_profiler_chan = profile_mem::Profiler::create(opts.mem_profiler_period);
+        // The URLs that the devtools network monitor blocks requests to.
+        let blocked_urls = devtools_traits::BlockedUrls::default();
+        // The clients that want to be sent response bodies.
+        let network_listeners = devtools_traits::NetworkListeners::default();
+        let devtools_chan = opts.devtools_port.map(|port| {
+            devtools::start_server(port, mem_profiler_chan.clone(), time_profiler_chan.clone(),
+                                   blocked_urls.clone(), network_listeners.clone())
+        });
         let debugger_chan = opts.debugger_port.map(|port| {
-            debugger::start_server(port)
+            debugger::start_server(port, devtools_chan.clone(), network_listeners.clone())
         });
-        let devtools_chan = opts.devtools_port.map(|port| {
-            devtools::start_server(port)
+        // The debugger sees devtools messages before passing them on to the devtools server.
+        let devtools_chan = debugger_chan.as_ref().map(|chan| chan.devtools_chan()).or(devtools_chan);
+        let har_recorder = opts.har_file.as_ref().map(|path| {
+            HarRecorder::start(PathBuf::from(path), devtools_chan.clone(), &network_listeners)
         });
+        // So does the HAR recorder, which records the network activity that net reports.
+        let devtools_chan = har_recorder.as_ref().map(|recorder| recorder.devtools_chan()).or(devtools_chan);
 
         let mut resource_path = resources_dir_path().unwrap();
         resource_path.push("shaders");
@@ -221,6 +235,8 @@ impl<Window> Servo<Window> where Window: WindowMethods + 'static {
                                                                     mem_profiler_chan.clone(),
                                                                     debugger_chan,
                                                                     devtools_chan,
+                                                                    blocked_urls,
+                                                                    network_listeners,
                                                                     supports_clipboard,
                                                                     &mut webrender,
                                                                     webrender_document,
@@ -253,6 +269,7 @@ impl<Window> Servo<Window> where Window: WindowMethods + 'static {
             compositor: compositor,
             constellation_chan: constellation_chan,
             embedder_receiver: embedder_receiver,
+            har_recorder: har_recorder,
         }
     }
 
@@ -357,6 +374,10 @@ impl<Window> Servo<Window> where Window: WindowMethods + 'static {
                     warn!("Sending CloseBrowser message to constellation failed ({}).", e);
                 }
             }
+
+            WindowEvent::SaveHar => {
+                self.save_har();
+            }
         }
     }
 
@@ -441,6 +462,11 @@ impl<Window> Servo<Window> where Window: WindowMethods + 'static {
                     self.compositor.window.head_parsed(top_level_browsing_context, );
                 },
 
+                (EmbedderMsg::SelectionChanged(top_level_browsing_context, text),
+                 ShutdownState::NotShuttingDown) => {
+                    self.compositor.window.selection_changed(top_level_browsing_context, text);
+                },
+
                 (EmbedderMsg::HistoryChanged(top_level_browsing_context, entries, current),
                  ShutdownState::NotShuttingDown) => {
                     self.compositor.window.history_changed(top_level_browsing_context, entries, current);
@@ -498,8 +524,17 @@ impl<Window> Servo<Window> where Window: WindowMethods + 'static {
         }).expect("Failed to set logger.")
     }
 
+    fn save_har(&self) {
+        if let Some(ref recorder) = self.har_recorder {
+            if let Err(e) = recorder.save() {
+                warn!("Saving HAR file failed ({}).", e);
+            }
+        }
+    }
+
     pub fn deinit(self) {
         self.compositor.deinit();
+        self.save_har();
     }
 }
 
@@ -535,6 +570,8 @@ fn create_constellation(user_agent: Cow<'static, str>,
                         mem_profiler_chan: mem::ProfilerChan,
                         debugger_chan: Option<debugger::Sender>,
                         devtools_chan: Option<Sender<devtools_traits::DevtoolsControlMsg>>,
+                        blocked_urls: devtools_traits::BlockedUrls,
+                        network_listeners: devtools_traits::NetworkListeners,
                         supports_clipboard: bool,
                         webrender: &mut webrender::Renderer,
                         webrender_document: webrender_api::DocumentId,
@@ -546,6 +583,8 @@ fn create_constellation(user_agent: Cow<'static, str>,
     let (public_resource_threads, private_resource_threads) =
         new_resource_threads(user_agent,
                              devtools_chan.clone(),
+                             blocked_urls,
+                             network_listeners,
                              time_profiler_chan.clone(),
                              config_dir);
     let font_cache_thread = FontCacheThread::new(public_resource_threads.sender(),
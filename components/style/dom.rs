@@ -31,7 +31,7 @@ use std::fmt;
 use std::fmt::Debug;
 use std::hash::Hash;
 use std::ops::Deref;
-use stylist::Stylist;
+use stylist::{CascadeData, Stylist};
 use traversal_flags::TraversalFlags;
 
 /// An opaque handle to a node, which, unlike UnsafeNode, cannot be transformed
@@ -393,8 +393,8 @@ pub trait TElement
     /// The style scope of this element is a node that represents which rules
     /// apply to the element.
     ///
-    /// In Servo, where we don't know about Shadow DOM or XBL, the style scope
-    /// is always the document.
+    /// By default, this is the document. Servo overrides this to return the
+    /// shadow root for elements in a shadow tree.
     fn style_scope(&self) -> Self::ConcreteNode {
         self.as_node().owner_doc().as_node()
     }
@@ -777,6 +777,24 @@ pub trait TElement
         false
     }
 
+    /// Returns the cascade data for the stylesheets of the shadow root attached
+    /// to this element, if it's a shadow host.
+    fn shadow_root_cascade_data<'a>(&self) -> Option<&'a CascadeData>
+    where
+        Self: 'a,
+    {
+        None
+    }
+
+    /// Returns the cascade data for the stylesheets of the shadow tree this
+    /// element is in, if any.
+    fn containing_shadow_cascade_data<'a>(&self) -> Option<&'a CascadeData>
+    where
+        Self: 'a,
+    {
+        self.containing_shadow_host().and_then(|host| host.shadow_root_cascade_data())
+    }
+
     /// Gets the current existing CSS transitions, by |property, end value| pairs in a FnvHashMap.
     #[cfg(feature = "gecko")]
     fn get_css_transitions_info(&self)
@@ -26,8 +26,9 @@
         """.split()
         webkit_prefixed_values = "flex inline-flex".split()
         values += webkit_prefixed_values
+        values += "grid inline-grid".split()
         if product == "gecko":
-            values += """grid inline-grid ruby ruby-base ruby-base-container
+            values += """ruby ruby-base ruby-base-container
                 ruby-text ruby-text-container contents flow-root -webkit-box
                 -webkit-inline-box -moz-box -moz-inline-box -moz-grid -moz-inline-grid
                 -moz-grid-group -moz-grid-line -moz-stack -moz-inline-stack -moz-deck
@@ -47,10 +48,8 @@
                 matches!(*self,
                          T::flex
                          | T::inline_flex
-                         % if product == "gecko":
                          | T::grid
                          | T::inline_grid
-                         % endif
                 )
             }
 
@@ -117,9 +116,9 @@
                     // Values that have a corresponding block-outside version.
                     T::inline_table => T::table,
                     T::inline_flex => T::flex,
+                    T::inline_grid => T::grid,
 
                     % if product == "gecko":
-                    T::inline_grid => T::grid,
                     T::_webkit_inline_box => T::_webkit_box,
                     % endif
 
@@ -130,9 +129,9 @@
                     % endif
 
                     // These are not changed by blockification.
-                    T::none | T::block | T::flex | T::list_item | T::table => *self,
+                    T::none | T::block | T::flex | T::grid | T::list_item | T::table => *self,
                     % if product == "gecko":
-                    T::contents | T::flow_root | T::grid | T::_webkit_box => *self,
+                    T::contents | T::flow_root | T::_webkit_box => *self,
                     % endif
 
                     // Everything else becomes block.
@@ -191,10 +190,21 @@
     /// Parse a display value.
     pub fn parse<'i, 't>(_context: &ParserContext, input: &mut Parser<'i, 't>)
                          -> Result<SpecifiedValue, ParseError<'i>> {
+        % if product == "servo":
+        use servo_config::prefs::PREFS;
+        % endif
         try_match_ident_ignore_ascii_case! { input,
             % for value in values:
                 "${value}" => {
+                    % if product == "servo" and value in ["grid", "inline-grid"]:
+                    if !PREFS.get("layout.grid.enabled").as_boolean().unwrap_or(false) {
+                        Err(())
+                    } else {
+                        Ok(computed_value::T::${to_rust_ident(value)})
+                    }
+                    % else:
                     Ok(computed_value::T::${to_rust_ident(value)})
+                    % endif
                 },
             % endfor
             % for value in webkit_prefixed_values:
@@ -257,8 +257,8 @@ ${helpers.predefined_type("object-position",
                               "computed::NonNegativeLengthOrPercentage::zero()",
                               spec="https://drafts.csswg.org/css-grid/#propdef-grid-%s-gap" % kind,
                               animation_value_type="NonNegativeLengthOrPercentage",
-                              gecko_pref="layout.css.grid.enabled",
-                              products="gecko")}
+                              servo_pref="layout.grid.enabled",
+                              gecko_pref="layout.css.grid.enabled")}
 
     % for range in ["start", "end"]:
         ${helpers.predefined_type("grid-%s-%s" % (kind, range),
@@ -266,7 +266,7 @@ ${helpers.predefined_type("object-position",
                                   "Default::default()",
                                   animation_value_type="discrete",
                                   spec="https://drafts.csswg.org/css-grid/#propdef-grid-%s-%s" % (kind, range),
-                                  products="gecko",
+                                  servo_pref="layout.grid.enabled",
                                   gecko_pref="layout.css.grid.enabled",
                                   boxed=True)}
     % endfor
@@ -278,16 +278,16 @@ ${helpers.predefined_type("object-position",
                               "Default::default()",
                               animation_value_type="discrete",
                               spec="https://drafts.csswg.org/css-grid/#propdef-grid-auto-%ss" % kind,
-                              products="gecko",
+                              servo_pref="layout.grid.enabled",
                               gecko_pref="layout.css.grid.enabled",
                               boxed=True)}
 
     ${helpers.predefined_type("grid-template-%ss" % kind,
                               "GridTemplateComponent",
                               "specified::GenericGridTemplateComponent::None",
-                              products="gecko",
                               spec="https://drafts.csswg.org/css-grid/#propdef-grid-template-%ss" % kind,
                               boxed=True,
+                              servo_pref="layout.grid.enabled",
                               gecko_pref="layout.css.grid.enabled",
                               animation_value_type="discrete")}
 
@@ -295,7 +295,7 @@ ${helpers.predefined_type("object-position",
 
 <%helpers:longhand name="grid-auto-flow"
         spec="https://drafts.csswg.org/css-grid/#propdef-grid-auto-flow"
-        products="gecko"
+        servo_pref="layout.grid.enabled"
         gecko_pref="layout.css.grid.enabled"
         animation_value_type="discrete">
     use std::fmt;
@@ -418,8 +418,8 @@ ${helpers.predefined_type("object-position",
 
 <%helpers:longhand name="grid-template-areas"
         spec="https://drafts.csswg.org/css-grid/#propdef-grid-template-areas"
-        products="gecko"
         animation_value_type="discrete"
+        servo_pref="layout.grid.enabled"
         gecko_pref="layout.css.grid.enabled"
         boxed="True">
     use hash::FnvHashMap;
@@ -444,16 +444,14 @@ ${helpers.predefined_type("object-position",
         SpecifiedValue::parse(context, input)
     }
 
-    #[cfg_attr(feature = "gecko", derive(MallocSizeOf))]
-    #[derive(Clone, Debug, PartialEq)]
+    #[derive(Clone, Debug, MallocSizeOf, PartialEq)]
     pub struct TemplateAreas {
         pub areas: Box<[NamedArea]>,
         pub strings: Box<[Box<str>]>,
         pub width: u32,
     }
 
-    #[cfg_attr(feature = "gecko", derive(MallocSizeOf))]
-    #[derive(Clone, Debug, PartialEq)]
+    #[derive(Clone, Debug, MallocSizeOf, PartialEq)]
     pub struct NamedArea {
         pub name: Box<str>,
         pub rows: Range<u32>,
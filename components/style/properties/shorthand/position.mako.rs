@@ -103,9 +103,9 @@
 </%helpers:shorthand>
 
 <%helpers:shorthand name="grid-gap" sub_properties="grid-row-gap grid-column-gap"
+                    servo_pref="layout.grid.enabled"
                     gecko_pref="layout.css.grid.enabled"
-                    spec="https://drafts.csswg.org/css-grid/#propdef-grid-gap"
-                    products="gecko">
+                    spec="https://drafts.csswg.org/css-grid/#propdef-grid-gap">
   use properties::longhands::{grid_row_gap, grid_column_gap};
 
   pub fn parse_value<'i, 't>(context: &ParserContext, input: &mut Parser<'i, 't>)
@@ -135,9 +135,9 @@
 
 % for kind in ["row", "column"]:
 <%helpers:shorthand name="grid-${kind}" sub_properties="grid-${kind}-start grid-${kind}-end"
+                    servo_pref="layout.grid.enabled"
                     gecko_pref="layout.css.grid.enabled"
-                    spec="https://drafts.csswg.org/css-grid/#propdef-grid-${kind}"
-                    products="gecko">
+                    spec="https://drafts.csswg.org/css-grid/#propdef-grid-${kind}">
     use values::specified::GridLine;
     use parser::Parse;
 
@@ -175,10 +175,10 @@
 % endfor
 
 <%helpers:shorthand name="grid-area"
+                    servo_pref="layout.grid.enabled"
                     gecko_pref="layout.css.grid.enabled"
                     sub_properties="grid-row-start grid-row-end grid-column-start grid-column-end"
-                    spec="https://drafts.csswg.org/css-grid/#propdef-grid-area"
-                    products="gecko">
+                    spec="https://drafts.csswg.org/css-grid/#propdef-grid-area">
     use values::specified::GridLine;
     use parser::Parse;
 
@@ -241,10 +241,10 @@
 </%helpers:shorthand>
 
 <%helpers:shorthand name="grid-template"
+                    servo_pref="layout.grid.enabled"
                     gecko_pref="layout.css.grid.enabled"
                     sub_properties="grid-template-rows grid-template-columns grid-template-areas"
-                    spec="https://drafts.csswg.org/css-grid/#propdef-grid-template"
-                    products="gecko">
+                    spec="https://drafts.csswg.org/css-grid/#propdef-grid-template">
     use parser::Parse;
     use properties::longhands::grid_template_areas::TemplateAreas;
     use values::{Either, None_};
@@ -463,11 +463,11 @@
 </%helpers:shorthand>
 
 <%helpers:shorthand name="grid"
+                    servo_pref="layout.grid.enabled"
                     gecko_pref="layout.css.grid.enabled"
                     sub_properties="grid-template-rows grid-template-columns grid-template-areas
                                     grid-auto-rows grid-auto-columns grid-auto-flow"
-                    spec="https://drafts.csswg.org/css-grid/#propdef-grid"
-                    products="gecko">
+                    spec="https://drafts.csswg.org/css-grid/#propdef-grid">
     use parser::Parse;
     use properties::longhands::{grid_auto_columns, grid_auto_rows, grid_auto_flow};
     use properties::longhands::grid_auto_flow::computed_value::{AutoFlow, T as SpecifiedAutoFlow};
@@ -253,7 +253,13 @@ fn compute_damage(old: &ComputedValues, new: &ComputedValues) -> ServoRestyleDam
         get_position.flex_basis,
         get_position.flex_grow,
         get_position.flex_shrink,
-        get_position.align_self
+        get_position.align_self,
+        get_position.grid_template_rows, get_position.grid_template_columns,
+        get_position.grid_template_areas, get_position.grid_auto_rows,
+        get_position.grid_auto_columns, get_position.grid_auto_flow,
+        get_position.grid_row_start, get_position.grid_row_end,
+        get_position.grid_column_start, get_position.grid_column_end,
+        get_position.grid_row_gap, get_position.grid_column_gap
     ]) || add_if_not_equal!(old, new, damage,
                             [ServoRestyleDamage::REPAINT, ServoRestyleDamage::REPOSITION,
                             ServoRestyleDamage::STORE_OVERFLOW, ServoRestyleDamage::REFLOW_OUT_OF_FLOW],
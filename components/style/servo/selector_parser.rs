@@ -261,6 +261,7 @@ pub enum NonTSPseudoClass {
     Fullscreen,
     Hover,
     Indeterminate,
+    Invalid,
     Lang(PseudoClassStringArg),
     Link,
     PlaceholderShown,
@@ -269,6 +270,7 @@ pub enum NonTSPseudoClass {
     ServoNonZeroBorder,
     ServoCaseSensitiveTypeAttr(Atom),
     Target,
+    Valid,
     Visited,
 }
 
@@ -299,12 +301,14 @@ impl ToCss for NonTSPseudoClass {
             Fullscreen => ":fullscreen",
             Hover => ":hover",
             Indeterminate => ":indeterminate",
+            Invalid => ":invalid",
             Link => ":link",
             PlaceholderShown => ":placeholder-shown",
             ReadWrite => ":read-write",
             ReadOnly => ":read-only",
             ServoNonZeroBorder => ":-servo-nonzero-border",
             Target => ":target",
+            Valid => ":valid",
             Visited => ":visited",
             Lang(_) |
             ServoCaseSensitiveTypeAttr(_) => unreachable!(),
@@ -341,6 +345,8 @@ impl NonTSPseudoClass {
             ReadOnly | ReadWrite => ElementState::IN_READ_WRITE_STATE,
             PlaceholderShown => ElementState::IN_PLACEHOLDER_SHOWN_STATE,
             Target => ElementState::IN_TARGET_STATE,
+            Valid => ElementState::IN_VALID_STATE,
+            Invalid => ElementState::IN_INVALID_STATE,
 
             AnyLink |
             Lang(_) |
@@ -411,11 +417,13 @@ impl<'a, 'i> ::selectors::Parser<'i> for SelectorParser<'a> {
             "fullscreen" => Fullscreen,
             "hover" => Hover,
             "indeterminate" => Indeterminate,
+            "invalid" => Invalid,
             "link" => Link,
             "placeholder-shown" => PlaceholderShown,
             "read-write" => ReadWrite,
             "read-only" => ReadOnly,
             "target" => Target,
+            "valid" => Valid,
             "visited" => Visited,
             "-servo-nonzero-border" => {
                 if !self.in_user_agent_stylesheet() {
@@ -538,6 +546,14 @@ impl<'a, 'i> ::selectors::Parser<'i> for SelectorParser<'a> {
         Ok(pseudo_element)
     }
 
+    fn parse_host(&self) -> bool {
+        true
+    }
+
+    fn parse_slotted(&self) -> bool {
+        true
+    }
+
     fn default_namespace(&self) -> Option<Namespace> {
         self.namespaces.default.as_ref().map(|&(ref ns, _)| ns.clone())
     }
@@ -74,6 +74,7 @@ use matching::MatchMethods;
 use owning_ref::OwningHandle;
 use properties::ComputedValues;
 use rule_tree::StrongRuleNode;
+use selectors::Element;
 use selectors::NthIndexCache;
 use selectors::matching::{ElementSelectorFlags, VisitedHandlingMode};
 use servo_arc::{Arc, NonZeroPtrMut};
@@ -673,6 +674,20 @@ impl<E: TElement> StyleSharingCache<E> {
             return None;
         }
 
+        // Shadow hosts match the rules of their own shadow tree, and slotted
+        // elements the rules of the shadow tree of their slot, none of which
+        // show up in the revalidation selectors.
+        if target.element.shadow_root_cascade_data().is_some() ||
+           candidate.element.shadow_root_cascade_data().is_some() {
+            trace!("Miss: Shadow host");
+            return None;
+        }
+
+        if target.element.assigned_slot() != candidate.element.assigned_slot() {
+            trace!("Miss: Different slots");
+            return None;
+        }
+
         if *target.get_local_name() != *candidate.element.get_local_name() {
             trace!("Miss: Local Name");
             return None;
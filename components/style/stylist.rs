@@ -25,6 +25,7 @@ use properties::{AnimationRules, PropertyDeclarationBlock};
 use rule_tree::{CascadeLevel, RuleTree, StrongRuleNode, StyleSource};
 use selector_map::{PrecomputedHashMap, SelectorMap, SelectorMapEntry};
 use selector_parser::{SelectorImpl, PerPseudoElementMap, PseudoElement};
+use selectors::Element as SelectorsElement;
 use selectors::NthIndexCache;
 use selectors::attr::NamespaceConstraint;
 use selectors::bloom::{BloomFilter, NonCountingBloomFilter};
@@ -1318,11 +1319,18 @@ impl Stylist {
         });
 
         if rule_hash_target.matches_user_and_author_rules() && !only_default_rules {
-            // Gecko skips author normal rules if cutting off inheritance.
-            // See nsStyleSet::FileRules().
-            if !cut_off_inheritance {
-                // Step 3c: Author normal rules.
-                if let Some(map) = self.cascade_data.author.borrow_for_pseudo(pseudo_element) {
+            // The rules of shadow trees are matched without the bloom filter,
+            // since it's built from the flat tree, which doesn't contain the
+            // slots whose assigned nodes are rendered in their place.
+            let bloom_filter = context.bloom_filter.take();
+            let current_host = context.current_host.take();
+
+            // Step 3c: Shadow DOM rules from inner trees, that is, the `:host`
+            // rules of our own shadow root, and the `::slotted()` rules from
+            // the shadow tree of the slot we're assigned to.
+            if let Some(data) = rule_hash_target.shadow_root_cascade_data() {
+                if let Some(map) = data.borrow_for_pseudo(pseudo_element) {
+                    context.current_host = Some(rule_hash_target.opaque());
                     map.get_all_matching_rules(
                         element,
                         &rule_hash_target,
@@ -1333,9 +1341,71 @@ impl Stylist {
                         CascadeLevel::AuthorNormal
                     );
                 }
+            }
+
+            if pseudo_element.is_none() {
+                let slot_host =
+                    rule_hash_target.assigned_slot().and_then(|slot| slot.containing_shadow_host());
+                if let Some(slot_host) = slot_host {
+                    if let Some(data) = slot_host.shadow_root_cascade_data() {
+                        context.current_host = Some(slot_host.opaque());
+                        data.slotted_rules.get_all_matching_rules(
+                            element,
+                            &rule_hash_target,
+                            applicable_declarations,
+                            context,
+                            self.quirks_mode,
+                            flags_setter,
+                            CascadeLevel::AuthorNormal
+                        );
+                    }
+                }
+            }
+
+            // Gecko skips author normal rules if cutting off inheritance.
+            // See nsStyleSet::FileRules().
+            if !cut_off_inheritance {
+                // Step 3d: Author normal rules, which for elements in a shadow
+                // tree are the ones from its stylesheets.
+                match rule_hash_target.containing_shadow_host() {
+                    Some(host) => {
+                        let map = host.shadow_root_cascade_data()
+                            .and_then(|data| data.borrow_for_pseudo(pseudo_element));
+                        if let Some(map) = map {
+                            context.current_host = Some(host.opaque());
+                            map.get_all_matching_rules(
+                                element,
+                                &rule_hash_target,
+                                applicable_declarations,
+                                context,
+                                self.quirks_mode,
+                                flags_setter,
+                                CascadeLevel::AuthorNormal
+                            );
+                        }
+                    }
+                    None => {
+                        context.bloom_filter = bloom_filter;
+                        context.current_host = None;
+                        if let Some(map) = self.cascade_data.author.borrow_for_pseudo(pseudo_element) {
+                            map.get_all_matching_rules(
+                                element,
+                                &rule_hash_target,
+                                applicable_declarations,
+                                context,
+                                self.quirks_mode,
+                                flags_setter,
+                                CascadeLevel::AuthorNormal
+                            );
+                        }
+                    }
+                }
             } else {
                 debug!("skipping author normal rules due to cut off inheritance");
             }
+
+            context.bloom_filter = bloom_filter;
+            context.current_host = current_host;
         } else {
             debug!("skipping author normal rules");
         }
@@ -1420,6 +1490,13 @@ impl Stylist {
             }
         }
 
+        let shadow_rules_may_contain =
+            element.containing_shadow_cascade_data()
+                .map_or(false, |data| data.mapped_ids.might_contain_hash(hash));
+        if shadow_rules_may_contain {
+            return true;
+        }
+
         let mut xbl_rules_may_contain = false;
 
         element.each_xbl_stylist(|stylist| {
@@ -1485,6 +1562,30 @@ impl Stylist {
             );
         }
 
+        if let Some(host) = element.containing_shadow_host() {
+            if let Some(data) = host.shadow_root_cascade_data() {
+                matching_context.bloom_filter = None;
+                matching_context.current_host = Some(host.opaque());
+                data.selectors_for_cache_revalidation.lookup(
+                    element,
+                    self.quirks_mode,
+                    |selector_and_hashes| {
+                        results.push(matches_selector(
+                            &selector_and_hashes.selector,
+                            selector_and_hashes.selector_offset,
+                            None,
+                            &element,
+                            &mut matching_context,
+                            flags_setter
+                        ));
+                        true
+                    }
+                );
+                matching_context.bloom_filter = bloom;
+                matching_context.current_host = None;
+            }
+        }
+
         element.each_xbl_stylist(|stylist| {
             stylist.cascade_data.author.selectors_for_cache_revalidation.lookup(
                 element,
@@ -1850,10 +1951,15 @@ impl<'a> SelectorVisitor for StylistSelectorVisitor<'a> {
 /// `InvalidationData`? That'd make `clear_cascade_data()` clearer.
 #[cfg_attr(feature = "servo", derive(MallocSizeOf))]
 #[derive(Debug)]
-struct CascadeData {
+pub struct CascadeData {
     /// Rules from stylesheets at this `CascadeData`'s origin.
     element_map: SelectorMap<Rule>,
 
+    /// Rules whose selectors have a `::slotted()` pseudo-element. These only
+    /// ever match in shadow root stylesheets, against the elements assigned to
+    /// the slots of the shadow tree.
+    slotted_rules: SelectorMap<Rule>,
+
     /// Rules from stylesheets at this `CascadeData`'s origin that correspond
     /// to a given pseudo-element.
     ///
@@ -1928,6 +2034,7 @@ impl CascadeData {
     fn new() -> Self {
         Self {
             element_map: SelectorMap::new(),
+            slotted_rules: SelectorMap::new(),
             pseudos_map: PerPseudoElementMap::default(),
             animations: Default::default(),
             extra_data: ExtraStyleData::default(),
@@ -2035,6 +2142,7 @@ impl CascadeData {
 
                                 continue;
                             }
+                            None if selector.is_slotted() => &mut self.slotted_rules,
                             None => &mut self.element_map,
                             Some(pseudo) => {
                                 self.pseudos_map
@@ -2236,6 +2344,7 @@ impl CascadeData {
     /// Clears the cascade data, but not the invalidation data.
     fn clear_cascade_data(&mut self) {
         self.element_map.clear();
+        self.slotted_rules.clear();
         self.pseudos_map.clear();
         self.animations.clear();
         self.extra_data.clear();
@@ -2260,6 +2369,7 @@ impl CascadeData {
     #[cfg(feature = "gecko")]
     pub fn add_size_of(&self, ops: &mut MallocSizeOfOps, sizes: &mut ServoStyleSetSizes) {
         sizes.mElementAndPseudosMaps += self.element_map.size_of(ops);
+        sizes.mElementAndPseudosMaps += self.slotted_rules.size_of(ops);
 
         for elem in self.pseudos_map.iter() {
             if let Some(ref elem) = *elem {
@@ -2284,6 +2394,60 @@ impl Default for CascadeData {
     }
 }
 
+/// The stylesheets of a shadow root, and the cascade data built from them.
+///
+/// This lives in the DOM, and is flushed by layout before styling.
+#[cfg(feature = "servo")]
+#[derive(MallocSizeOf)]
+pub struct ShadowRootStyles {
+    /// The stylesheets in the shadow tree, in tree order.
+    pub stylesheets: StylesheetSet<StylistSheet>,
+    /// The rules from the stylesheets above.
+    pub data: CascadeData,
+}
+
+#[cfg(feature = "servo")]
+impl ShadowRootStyles {
+    /// Creates an empty set of shadow root styles.
+    pub fn new() -> Self {
+        ShadowRootStyles {
+            stylesheets: StylesheetSet::new(),
+            data: CascadeData::new(),
+        }
+    }
+
+    /// Rebuilds the cascade data if the stylesheets have changed since the
+    /// last flush, and returns whether they did.
+    ///
+    /// There is no invalidation for shadow root stylesheets, so the callers
+    /// are expected to restyle the whole shadow tree when this returns true.
+    pub fn flush(
+        &mut self,
+        device: &Device,
+        quirks_mode: QuirksMode,
+        guard: &SharedRwLockReadGuard,
+    ) -> bool {
+        if !self.stylesheets.has_changed() {
+            return false;
+        }
+
+        self.stylesheets.flush_without_invalidation();
+        self.data.clear();
+        for (sheet, _origin) in self.stylesheets.iter() {
+            self.data.add_stylesheet(
+                device,
+                quirks_mode,
+                sheet,
+                guard,
+                SheetRebuildKind::Full,
+                None,
+            ).unwrap_or_else(|_| warn!("OOM in ShadowRootStyles::flush"));
+        }
+
+        true
+    }
+}
+
 /// A rule, that wraps a style rule, but represents a single selector of the
 /// rule.
 #[derive(Clone, Debug, MallocSizeOf)]
@@ -0,0 +1,531 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The state machine behind the WebDriver actions API.
+//!
+//! <https://w3c.github.io/webdriver/webdriver-spec.html#actions>
+
+use Handler;
+use euclid::{Point2D, Size2D};
+use ipc_channel::ipc;
+use keys::key_from_char;
+use msg::constellation_msg::{BrowsingContextId, KeyModifiers, KeyState};
+use script_traits::{ConstellationMsg, MouseButton, MouseEventType, WebDriverCommandMsg};
+use script_traits::webdriver_msg::{WebDriverFrameId, WebDriverScriptCommand};
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+use std::thread;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+use webdriver::actions::{ActionSequence, ActionsType, GeneralAction, KeyAction, KeyActionItem};
+use webdriver::actions::{NullActionItem, PointerAction, PointerActionItem, PointerMoveAction};
+use webdriver::actions::{PointerOrigin, PointerType};
+use webdriver::common::{Nullable, WebElement};
+use webdriver::error::{ErrorStatus, WebDriverError, WebDriverResult};
+
+/// How often intermediate mouse move events are dispatched while a pointer move
+/// with a duration is in progress.
+const POINTER_MOVE_INTERVAL: u64 = 17;
+
+/// <https://w3c.github.io/webdriver/webdriver-spec.html#dfn-key-input-state>
+pub struct KeyInputState {
+    pressed: HashSet<char>,
+    alt: bool,
+    ctrl: bool,
+    meta: bool,
+    shift: bool,
+}
+
+impl KeyInputState {
+    fn new() -> KeyInputState {
+        KeyInputState {
+            pressed: HashSet::new(),
+            alt: false,
+            ctrl: false,
+            meta: false,
+            shift: false,
+        }
+    }
+
+    fn modifiers(&self) -> KeyModifiers {
+        let mut modifiers = KeyModifiers::empty();
+        if self.alt {
+            modifiers.insert(KeyModifiers::ALT);
+        }
+        if self.ctrl {
+            modifiers.insert(KeyModifiers::CONTROL);
+        }
+        if self.meta {
+            modifiers.insert(KeyModifiers::SUPER);
+        }
+        if self.shift {
+            modifiers.insert(KeyModifiers::SHIFT);
+        }
+        modifiers
+    }
+
+    /// Updates the modifier flags if the key value is a modifier key.
+    fn set_modifier(&mut self, value: char, pressed: bool) {
+        match value {
+            '\u{E008}' | '\u{E050}' => self.shift = pressed,
+            '\u{E009}' | '\u{E051}' => self.ctrl = pressed,
+            '\u{E00A}' | '\u{E052}' => self.alt = pressed,
+            '\u{E03D}' | '\u{E053}' => self.meta = pressed,
+            _ => {},
+        }
+    }
+}
+
+/// <https://w3c.github.io/webdriver/webdriver-spec.html#dfn-pointer-input-state>
+pub struct PointerInputState {
+    /// The pressed buttons, along with the point where each was pressed, so that
+    /// releasing a button without moving the pointer also produces a click.
+    pressed: HashMap<u64, Point2D<i64>>,
+    x: i64,
+    y: i64,
+}
+
+impl PointerInputState {
+    pub fn new() -> PointerInputState {
+        PointerInputState {
+            pressed: HashMap::new(),
+            x: 0,
+            y: 0,
+        }
+    }
+}
+
+/// <https://w3c.github.io/webdriver/webdriver-spec.html#dfn-input-source-state>
+pub enum InputSourceState {
+    Null,
+    Key(KeyInputState),
+    Pointer(PointerInputState),
+}
+
+/// An entry of the input cancel list, undoing an action that was dispatched.
+///
+/// <https://w3c.github.io/webdriver/webdriver-spec.html#dfn-input-cancel-list>
+pub enum CancelAction {
+    KeyUp(String, char),
+    PointerUp(String, u64),
+}
+
+/// A single action of a tick.
+enum ActionItem<'a> {
+    Null(&'a NullActionItem),
+    Key(&'a KeyActionItem),
+    Pointer(&'a PointerActionItem),
+}
+
+fn mouse_button(button: u64) -> Option<MouseButton> {
+    match button {
+        0 => Some(MouseButton::Left),
+        1 => Some(MouseButton::Middle),
+        2 => Some(MouseButton::Right),
+        _ => None,
+    }
+}
+
+fn pointer_move_duration(action: &PointerMoveAction) -> u64 {
+    match action.duration {
+        Nullable::Value(duration) => duration,
+        Nullable::Null => 0,
+    }
+}
+
+impl Handler {
+    /// <https://w3c.github.io/webdriver/webdriver-spec.html#dfn-extract-an-action-sequence>
+    fn extract_an_action_sequence<'a>(&mut self,
+                                      sequences: &'a [ActionSequence])
+                                      -> WebDriverResult<Vec<Vec<(String, ActionItem<'a>)>>> {
+        let mut actions_by_tick: Vec<Vec<(String, ActionItem<'a>)>> = vec![];
+        for sequence in sequences {
+            let id = sequence.id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
+            let items: Vec<ActionItem> = match sequence.actions {
+                ActionsType::Null(ref items) => {
+                    self.input_source(&id, InputSourceState::Null)?;
+                    items.iter().map(ActionItem::Null).collect()
+                },
+                ActionsType::Key(ref items) => {
+                    self.input_source(&id, InputSourceState::Key(KeyInputState::new()))?;
+                    items.iter().map(ActionItem::Key).collect()
+                },
+                ActionsType::Pointer(ref parameters, ref items) => {
+                    // Servo only synthesizes mouse events.
+                    if parameters.pointer_type != PointerType::Mouse {
+                        return Err(WebDriverError::new(ErrorStatus::UnsupportedOperation,
+                                                       "Only mouse pointers are supported"));
+                    }
+                    self.input_source(&id, InputSourceState::Pointer(PointerInputState::new()))?;
+                    items.iter().map(ActionItem::Pointer).collect()
+                },
+            };
+            for (tick, item) in items.into_iter().enumerate() {
+                if actions_by_tick.len() <= tick {
+                    actions_by_tick.push(vec![]);
+                }
+                actions_by_tick[tick].push((id.clone(), item));
+            }
+        }
+        Ok(actions_by_tick)
+    }
+
+    /// Creates the input source with the given id, or checks that the existing
+    /// one is of the same type.
+    fn input_source(&mut self, id: &str, state: InputSourceState) -> WebDriverResult<()> {
+        let session = self.session_mut()?;
+        if let Some(existing) = session.input_state_table.get(id) {
+            return match (existing, &state) {
+                (&InputSourceState::Null, &InputSourceState::Null) |
+                (&InputSourceState::Key(_), &InputSourceState::Key(_)) |
+                (&InputSourceState::Pointer(_), &InputSourceState::Pointer(_)) => Ok(()),
+                _ => Err(WebDriverError::new(ErrorStatus::InvalidArgument,
+                                             "Input source type does not match existing input source")),
+            };
+        }
+        session.input_state_table.insert(id.to_owned(), state);
+        Ok(())
+    }
+
+    /// <https://w3c.github.io/webdriver/webdriver-spec.html#dfn-dispatch-actions>
+    pub fn dispatch_actions(&mut self, sequences: &[ActionSequence]) -> WebDriverResult<()> {
+        let actions_by_tick = self.extract_an_action_sequence(sequences)?;
+        for tick_actions in actions_by_tick {
+            let tick_start = Instant::now();
+            let tick_duration = tick_actions.iter().map(|&(_, ref item)| {
+                match *item {
+                    ActionItem::Null(&NullActionItem::General(GeneralAction::Pause(ref pause))) |
+                    ActionItem::Key(&KeyActionItem::General(GeneralAction::Pause(ref pause))) |
+                    ActionItem::Pointer(&PointerActionItem::General(GeneralAction::Pause(ref pause))) => {
+                        pause.duration
+                    },
+                    ActionItem::Pointer(&PointerActionItem::Pointer(PointerAction::Move(ref action))) => {
+                        pointer_move_duration(action)
+                    },
+                    _ => 0,
+                }
+            }).max().unwrap_or(0);
+
+            for &(ref id, ref item) in &tick_actions {
+                self.dispatch_tick_action(id, item, tick_start)?;
+            }
+
+            // Wait until the tick duration has elapsed before moving on to the next tick.
+            let elapsed = tick_start.elapsed();
+            let tick_duration = Duration::from_millis(tick_duration);
+            if elapsed < tick_duration {
+                thread::sleep(tick_duration - elapsed);
+            }
+        }
+        Ok(())
+    }
+
+    fn dispatch_tick_action(&mut self, id: &str, item: &ActionItem, tick_start: Instant) -> WebDriverResult<()> {
+        match *item {
+            ActionItem::Key(&KeyActionItem::Key(KeyAction::Down(ref action))) => {
+                self.dispatch_keydown_action(id, action.value)
+            },
+            ActionItem::Key(&KeyActionItem::Key(KeyAction::Up(ref action))) => {
+                self.dispatch_keyup_action(id, action.value)
+            },
+            ActionItem::Pointer(&PointerActionItem::Pointer(PointerAction::Down(ref action))) => {
+                self.dispatch_pointerdown_action(id, action.button)
+            },
+            ActionItem::Pointer(&PointerActionItem::Pointer(PointerAction::Up(ref action))) => {
+                self.dispatch_pointerup_action(id, action.button)
+            },
+            ActionItem::Pointer(&PointerActionItem::Pointer(PointerAction::Move(ref action))) => {
+                self.dispatch_pointermove_action(id, action, tick_start)
+            },
+            // Pauses only contribute to the tick duration, and mice cannot be cancelled.
+            _ => Ok(()),
+        }
+    }
+
+    fn key_input_state(&mut self, id: &str) -> WebDriverResult<&mut KeyInputState> {
+        match self.session_mut()?.input_state_table.get_mut(id) {
+            Some(&mut InputSourceState::Key(ref mut state)) => Ok(state),
+            _ => Err(WebDriverError::new(ErrorStatus::UnknownError, "Missing key input source")),
+        }
+    }
+
+    fn pointer_input_state(&mut self, id: &str) -> WebDriverResult<&mut PointerInputState> {
+        match self.session_mut()?.input_state_table.get_mut(id) {
+            Some(&mut InputSourceState::Pointer(ref mut state)) => Ok(state),
+            _ => Err(WebDriverError::new(ErrorStatus::UnknownError, "Missing pointer input source")),
+        }
+    }
+
+    fn send_key_event(&self, value: char, modifiers: KeyModifiers, state: KeyState) -> WebDriverResult<()> {
+        // Keys without a Servo equivalent still update the input state, but
+        // cannot be dispatched.
+        if let Some((key, with_shift)) = key_from_char(&value) {
+            let modifiers = if with_shift { modifiers | KeyModifiers::SHIFT } else { modifiers };
+            let browsing_context_id = self.session()?.browsing_context_id;
+            let cmd_msg = WebDriverCommandMsg::SendKeys(browsing_context_id, vec![(key, modifiers, state)]);
+            self.constellation_chan.send(ConstellationMsg::WebDriverCommand(cmd_msg)).unwrap();
+        }
+        Ok(())
+    }
+
+    /// <https://w3c.github.io/webdriver/webdriver-spec.html#dfn-dispatch-a-keydown-action>
+    fn dispatch_keydown_action(&mut self, id: &str, value: char) -> WebDriverResult<()> {
+        let modifiers = {
+            let state = self.key_input_state(id)?;
+            if !state.pressed.insert(value) {
+                return Ok(());
+            }
+            state.set_modifier(value, true);
+            state.modifiers()
+        };
+        self.session_mut()?.input_cancel_list.push(CancelAction::KeyUp(id.to_owned(), value));
+        self.send_key_event(value, modifiers, KeyState::Pressed)
+    }
+
+    /// <https://w3c.github.io/webdriver/webdriver-spec.html#dfn-dispatch-a-keyup-action>
+    fn dispatch_keyup_action(&mut self, id: &str, value: char) -> WebDriverResult<()> {
+        let modifiers = {
+            let state = self.key_input_state(id)?;
+            if !state.pressed.remove(&value) {
+                return Ok(());
+            }
+            state.set_modifier(value, false);
+            state.modifiers()
+        };
+        self.send_key_event(value, modifiers, KeyState::Released)
+    }
+
+    /// <https://w3c.github.io/webdriver/webdriver-spec.html#dfn-dispatch-a-pointerdown-action>
+    pub fn dispatch_pointerdown_action(&mut self, id: &str, button: u64) -> WebDriverResult<()> {
+        let (x, y) = {
+            let state = self.pointer_input_state(id)?;
+            if state.pressed.contains_key(&button) {
+                return Ok(());
+            }
+            state.pressed.insert(button, Point2D::new(state.x, state.y));
+            (state.x, state.y)
+        };
+        self.session_mut()?.input_cancel_list.push(CancelAction::PointerUp(id.to_owned(), button));
+        if let Some(button) = mouse_button(button) {
+            let point = self.top_level_point(Point2D::new(x, y))?;
+            self.send_mouse_button_action(MouseEventType::MouseDown, button, point)?;
+        }
+        Ok(())
+    }
+
+    /// <https://w3c.github.io/webdriver/webdriver-spec.html#dfn-dispatch-a-pointerup-action>
+    pub fn dispatch_pointerup_action(&mut self, id: &str, button: u64) -> WebDriverResult<()> {
+        let (x, y, pressed_at) = {
+            let state = self.pointer_input_state(id)?;
+            match state.pressed.remove(&button) {
+                Some(pressed_at) => (state.x, state.y, pressed_at),
+                None => return Ok(()),
+            }
+        };
+        if let Some(button) = mouse_button(button) {
+            let point = self.top_level_point(Point2D::new(x, y))?;
+            self.send_mouse_button_action(MouseEventType::MouseUp, button, point)?;
+            if pressed_at == Point2D::new(x, y) {
+                self.send_mouse_button_action(MouseEventType::Click, button, point)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// <https://w3c.github.io/webdriver/webdriver-spec.html#dfn-dispatch-a-pointermove-action>
+    fn dispatch_pointermove_action(&mut self,
+                                   id: &str,
+                                   action: &PointerMoveAction,
+                                   tick_start: Instant)
+                                   -> WebDriverResult<()> {
+        let (start_x, start_y) = {
+            let state = self.pointer_input_state(id)?;
+            (state.x, state.y)
+        };
+        let x_offset = match action.x {
+            Nullable::Value(x) => x,
+            Nullable::Null => 0,
+        };
+        let y_offset = match action.y {
+            Nullable::Value(y) => y,
+            Nullable::Null => 0,
+        };
+
+        // Steps 1-6.
+        let (x, y) = match action.origin {
+            PointerOrigin::Viewport => (x_offset, y_offset),
+            PointerOrigin::Pointer => (start_x + x_offset, start_y + y_offset),
+            PointerOrigin::Element(ref element) => {
+                let center = self.element_in_view_center_point(element)?;
+                (center.x + x_offset, center.y + y_offset)
+            },
+        };
+
+        // Step 7.
+        let viewport = self.viewport_size()?;
+        if x < 0 || y < 0 || x as f32 > viewport.width || y as f32 > viewport.height {
+            return Err(WebDriverError::new(ErrorStatus::MoveTargetOutOfBounds,
+                                           "Move target is outside of the viewport"));
+        }
+
+        // Steps 8-10.
+        // FIXME: the spec performs moves with a duration in parallel with the
+        // other actions of the tick; here they run one after the other.
+        self.perform_pointer_move(id, pointer_move_duration(action), Point2D::new(start_x, start_y),
+                                  Point2D::new(x, y), tick_start)
+    }
+
+    /// <https://w3c.github.io/webdriver/webdriver-spec.html#dfn-perform-a-pointer-move>
+    pub fn perform_pointer_move(&mut self,
+                                id: &str,
+                                duration: u64,
+                                start: Point2D<i64>,
+                                target: Point2D<i64>,
+                                tick_start: Instant)
+                                -> WebDriverResult<()> {
+        let offset = self.browsing_context_offset()?;
+        let (top_level_browsing_context_id, browsing_context_id) = {
+            let session = self.session()?;
+            (session.top_level_browsing_context_id, session.browsing_context_id)
+        };
+        loop {
+            let elapsed = tick_start.elapsed();
+            let elapsed = elapsed.as_secs() * 1000 + (elapsed.subsec_nanos() / 1_000_000) as u64;
+            let last = duration == 0 || elapsed >= duration;
+            let point = if last {
+                target
+            } else {
+                let ratio = elapsed as f64 / duration as f64;
+                Point2D::new(start.x + ((target.x - start.x) as f64 * ratio) as i64,
+                             start.y + ((target.y - start.y) as f64 * ratio) as i64)
+            };
+
+            let moved = {
+                let state = self.pointer_input_state(id)?;
+                let moved = state.x != point.x || state.y != point.y;
+                state.x = point.x;
+                state.y = point.y;
+                moved
+            };
+            if moved {
+                let cmd_msg = WebDriverCommandMsg::MouseMoveAction(top_level_browsing_context_id,
+                                                                   browsing_context_id,
+                                                                   point.x as f32 + offset.x,
+                                                                   point.y as f32 + offset.y);
+                self.constellation_chan.send(ConstellationMsg::WebDriverCommand(cmd_msg)).unwrap();
+            }
+
+            if last {
+                return Ok(());
+            }
+            thread::sleep(Duration::from_millis(cmp::min(POINTER_MOVE_INTERVAL, duration - elapsed)));
+        }
+    }
+
+    /// <https://w3c.github.io/webdriver/webdriver-spec.html#dfn-release-actions>
+    pub fn release_actions(&mut self) -> WebDriverResult<()> {
+        let cancel_list = {
+            let session = self.session_mut()?;
+            session.input_cancel_list.drain(..).collect::<Vec<_>>()
+        };
+        for action in cancel_list.into_iter().rev() {
+            // Input sources removed since, like the one used to click elements,
+            // have nothing to undo.
+            let exists = match action {
+                CancelAction::KeyUp(ref id, _) | CancelAction::PointerUp(ref id, _) => {
+                    self.session()?.input_state_table.contains_key(id)
+                },
+            };
+            if !exists {
+                continue;
+            }
+            match action {
+                CancelAction::KeyUp(id, value) => self.dispatch_keyup_action(&id, value)?,
+                CancelAction::PointerUp(id, button) => self.dispatch_pointerup_action(&id, button)?,
+            }
+        }
+        self.session_mut()?.input_state_table.clear();
+        Ok(())
+    }
+
+    fn send_mouse_button_action(&self,
+                                event_type: MouseEventType,
+                                button: MouseButton,
+                                point: Point2D<f32>)
+                                -> WebDriverResult<()> {
+        let session = self.session()?;
+        let cmd_msg = WebDriverCommandMsg::MouseButtonAction(session.top_level_browsing_context_id,
+                                                             session.browsing_context_id,
+                                                             event_type, button, point.x, point.y);
+        self.constellation_chan.send(ConstellationMsg::WebDriverCommand(cmd_msg)).unwrap();
+        Ok(())
+    }
+
+    /// Translate a point in the viewport of the current browsing context to the
+    /// viewport of its top-level browsing context, where the compositor hit-tests.
+    fn top_level_point(&self, point: Point2D<i64>) -> WebDriverResult<Point2D<f32>> {
+        let offset = self.browsing_context_offset()?;
+        Ok(Point2D::new(point.x as f32 + offset.x, point.y as f32 + offset.y))
+    }
+
+    /// The origin of the viewport of the current browsing context relative to the viewport
+    /// of its top-level browsing context, summed up over each of its ancestor frames.
+    fn browsing_context_offset(&self) -> WebDriverResult<Point2D<f32>> {
+        let (top_level_browsing_context_id, mut browsing_context_id) = {
+            let session = self.session()?;
+            (BrowsingContextId::from(session.top_level_browsing_context_id), session.browsing_context_id)
+        };
+        let mut offset = Point2D::zero();
+        while browsing_context_id != top_level_browsing_context_id {
+            let (sender, receiver) = ipc::channel().unwrap();
+            let cmd = WebDriverScriptCommand::GetBrowsingContextId(WebDriverFrameId::Parent, sender);
+            self.script_command(browsing_context_id, cmd);
+            let parent_id = match receiver.recv() {
+                Ok(Ok(parent_id)) => parent_id,
+                _ => return Err(WebDriverError::new(ErrorStatus::NoSuchFrame, "Frame does not exist")),
+            };
+
+            let (sender, receiver) = ipc::channel().unwrap();
+            let cmd = WebDriverScriptCommand::GetFrameOffset(browsing_context_id, sender);
+            self.script_command(parent_id, cmd);
+            let frame_offset = match receiver.recv() {
+                Ok(Ok(frame_offset)) => frame_offset,
+                _ => return Err(WebDriverError::new(ErrorStatus::NoSuchFrame, "Frame does not exist")),
+            };
+
+            offset = Point2D::new(offset.x + frame_offset.x, offset.y + frame_offset.y);
+            browsing_context_id = parent_id;
+        }
+        Ok(offset)
+    }
+
+    fn script_command(&self, browsing_context_id: BrowsingContextId, cmd: WebDriverScriptCommand) {
+        let cmd_msg = WebDriverCommandMsg::ScriptCommand(browsing_context_id, cmd);
+        self.constellation_chan.send(ConstellationMsg::WebDriverCommand(cmd_msg)).unwrap();
+    }
+
+    /// The size of the viewport of the current browsing context, which pointer
+    /// moves are relative to.
+    fn viewport_size(&self) -> WebDriverResult<Size2D<f32>> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        self.browsing_context_script_command(WebDriverScriptCommand::GetViewportSize(sender))?;
+        match receiver.recv() {
+            Ok(Ok(size)) => Ok(size),
+            _ => Err(WebDriverError::new(ErrorStatus::NoSuchFrame, "Frame does not exist")),
+        }
+    }
+
+    fn element_in_view_center_point(&self, element: &WebElement) -> WebDriverResult<Point2D<i64>> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd = WebDriverScriptCommand::GetElementInViewCenterPoint(element.id.clone(), sender);
+        self.browsing_context_script_command(cmd)?;
+        match receiver.recv().unwrap() {
+            Ok(Some(point)) => Ok(point),
+            Ok(None) => Err(WebDriverError::new(ErrorStatus::MoveTargetOutOfBounds,
+                                                "Element is not in view")),
+            Err(_) => Err(WebDriverError::new(ErrorStatus::StaleElementReference,
+                                              "Unable to find element in document")),
+        }
+    }
+}
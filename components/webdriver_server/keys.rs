@@ -11,7 +11,7 @@ use msg::constellation_msg::{Key, KeyState, KeyModifiers};
 /// entirely clear on how to deal with characters outside this
 /// range. Returns None if no key corresponding to the character is
 /// matched.
-fn key_from_char(key_string: &char) -> Option<(Key, bool)> {
+pub fn key_from_char(key_string: &char) -> Option<(Key, bool)> {
     match *key_string {
         ' ' => Some((Key::Space, false)),
         '\'' => Some((Key::Apostrophe, true)),
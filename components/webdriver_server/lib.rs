@@ -25,10 +25,12 @@ extern crate servo_url;
 extern crate uuid;
 extern crate webdriver;
 
+mod actions;
 mod keys;
 
-use euclid::Size2D;
-use hyper::method::Method::{self, Post};
+use actions::{CancelAction, InputSourceState, PointerInputState};
+use euclid::{Point2D, Size2D};
+use hyper::method::Method::{self, Get, Post};
 use image::{DynamicImage, ImageFormat, RgbImage};
 use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
 use keys::keycodes_to_keys;
@@ -36,21 +38,23 @@ use msg::constellation_msg::{BrowsingContextId, TopLevelBrowsingContextId, Trave
 use net_traits::image::base::PixelFormat;
 use regex::Captures;
 use rustc_serialize::json::{Json, ToJson};
-use script_traits::{ConstellationMsg, LoadData, WebDriverCommandMsg};
-use script_traits::webdriver_msg::{LoadStatus, WebDriverCookieError, WebDriverFrameId};
+use script_traits::{ConstellationMsg, LoadData, SimpleDialog, WebDriverCommandMsg, WindowSizeData};
+use script_traits::webdriver_msg::{LoadStatus, WebDriverCookieError, WebDriverElementError, WebDriverFrameId};
 use script_traits::webdriver_msg::{WebDriverJSError, WebDriverJSResult, WebDriverScriptCommand};
 use servo_config::prefs::{PREFS, PrefValue};
 use servo_url::ServoUrl;
 use std::borrow::ToOwned;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
+use std::i32;
 use std::net::{SocketAddr, SocketAddrV4};
 use std::sync::mpsc::Sender;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
-use webdriver::command::{AddCookieParameters, GetParameters, JavascriptCommandParameters};
-use webdriver::command::{LocatorParameters, Parameters};
-use webdriver::command::{SendKeysParameters, SwitchToFrameParameters, TimeoutsParameters};
+use webdriver::command::{ActionsParameters, AddCookieParameters, GetParameters, JavascriptCommandParameters};
+use webdriver::command::{LocatorParameters, NewSessionParameters, Parameters};
+use webdriver::command::{SendKeysParameters, SwitchToFrameParameters, SwitchToWindowParameters};
+use webdriver::command::TimeoutsParameters;
 use webdriver::command::{WebDriverCommand, WebDriverExtensionCommand, WebDriverMessage};
 use webdriver::command::WindowSizeParameters;
 use webdriver::common::{Date, LocatorStrategy, Nullable, WebElement};
@@ -64,7 +68,11 @@ use webdriver::server::{self, Session, WebDriverHandler};
 fn extension_routes() -> Vec<(Method, &'static str, ServoExtensionRoute)> {
     return vec![(Post, "/session/{sessionId}/servo/prefs/get", ServoExtensionRoute::GetPrefs),
                 (Post, "/session/{sessionId}/servo/prefs/set", ServoExtensionRoute::SetPrefs),
-                (Post, "/session/{sessionId}/servo/prefs/reset", ServoExtensionRoute::ResetPrefs)]
+                (Post, "/session/{sessionId}/servo/prefs/reset", ServoExtensionRoute::ResetPrefs),
+                // The following commands are not known to the webdriver crate yet.
+                (Post, "/session/{sessionId}/window/new", ServoExtensionRoute::NewWindow),
+                (Get, "/session/{sessionId}/window/rect", ServoExtensionRoute::GetWindowRect),
+                (Post, "/session/{sessionId}/window/rect", ServoExtensionRoute::SetWindowRect)]
 }
 
 fn cookie_msg_to_cookie(cookie: cookie_rs::Cookie) -> Cookie {
@@ -88,6 +96,23 @@ fn cookie_msg_to_cookie(cookie: cookie_rs::Cookie) -> Cookie {
     }
 }
 
+fn element_error_to_webdriver_error(error: WebDriverElementError) -> WebDriverError {
+    match error {
+        WebDriverElementError::StaleElementReference =>
+            WebDriverError::new(ErrorStatus::StaleElementReference, "Unable to find element in document"),
+        WebDriverElementError::InvalidArgument =>
+            WebDriverError::new(ErrorStatus::InvalidArgument, "Element cannot be interacted with"),
+        WebDriverElementError::InvalidSelector =>
+            WebDriverError::new(ErrorStatus::InvalidSelector, "Invalid selector"),
+        WebDriverElementError::InvalidElementState =>
+            WebDriverError::new(ErrorStatus::InvalidElementState, "Element is not editable"),
+        WebDriverElementError::ElementNotInteractable =>
+            WebDriverError::new(ErrorStatus::ElementNotInteractable, "Element is not in view"),
+        WebDriverElementError::ElementClickIntercepted =>
+            WebDriverError::new(ErrorStatus::ElementClickIntercepted, "Element is obscured by another element"),
+    }
+}
+
 pub fn start_server(port: u16, constellation_chan: Sender<ConstellationMsg>) {
     let handler = Handler::new(constellation_chan);
     thread::Builder::new().name("WebdriverHttpServer".to_owned()).spawn(move || {
@@ -99,6 +124,40 @@ pub fn start_server(port: u16, constellation_chan: Sender<ConstellationMsg>) {
     }).expect("Thread spawning failed");
 }
 
+/// How user prompts that are open when a command is run get handled.
+/// <https://w3c.github.io/webdriver/webdriver-spec.html#dfn-unhandled-prompt-behavior>
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum UnhandledPromptBehavior {
+    Dismiss,
+    Accept,
+    DismissAndNotify,
+    AcceptAndNotify,
+    Ignore,
+}
+
+impl UnhandledPromptBehavior {
+    fn from_str(value: &str) -> Option<UnhandledPromptBehavior> {
+        match value {
+            "dismiss" => Some(UnhandledPromptBehavior::Dismiss),
+            "accept" => Some(UnhandledPromptBehavior::Accept),
+            "dismiss and notify" => Some(UnhandledPromptBehavior::DismissAndNotify),
+            "accept and notify" => Some(UnhandledPromptBehavior::AcceptAndNotify),
+            "ignore" => Some(UnhandledPromptBehavior::Ignore),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match *self {
+            UnhandledPromptBehavior::Dismiss => "dismiss",
+            UnhandledPromptBehavior::Accept => "accept",
+            UnhandledPromptBehavior::DismissAndNotify => "dismiss and notify",
+            UnhandledPromptBehavior::AcceptAndNotify => "accept and notify",
+            UnhandledPromptBehavior::Ignore => "ignore",
+        }
+    }
+}
+
 /// Represents the current WebDriver session and holds relevant session state.
 struct WebDriverSession {
     id: Uuid,
@@ -115,6 +174,15 @@ struct WebDriverSession {
     /// Time to wait for the element location strategy when retrieving elements, and when
     /// waiting for an element to become interactable.
     implicit_wait_timeout: Option<u64>,
+
+    /// The state of the input sources used by the actions API, by input source id.
+    input_state_table: HashMap<String, InputSourceState>,
+
+    /// The actions to undo when the actions are released.
+    input_cancel_list: Vec<CancelAction>,
+
+    /// What to do with a user prompt that is open when a command is run.
+    unhandled_prompt_behavior: UnhandledPromptBehavior,
 }
 
 impl WebDriverSession {
@@ -130,6 +198,11 @@ impl WebDriverSession {
             script_timeout: Some(30_000),
             load_timeout: Some(300_000),
             implicit_wait_timeout: Some(0),
+
+            input_state_table: HashMap::new(),
+            input_cancel_list: vec![],
+
+            unhandled_prompt_behavior: UnhandledPromptBehavior::DismissAndNotify,
         }
     }
 }
@@ -145,6 +218,9 @@ enum ServoExtensionRoute {
     GetPrefs,
     SetPrefs,
     ResetPrefs,
+    NewWindow,
+    GetWindowRect,
+    SetWindowRect,
 }
 
 impl WebDriverExtensionRoute for ServoExtensionRoute {
@@ -166,6 +242,12 @@ impl WebDriverExtensionRoute for ServoExtensionRoute {
                 let parameters: GetPrefsParameters = Parameters::from_json(&body_data)?;
                 ServoExtensionCommand::ResetPrefs(parameters)
             }
+            ServoExtensionRoute::NewWindow => ServoExtensionCommand::NewWindow,
+            ServoExtensionRoute::GetWindowRect => ServoExtensionCommand::GetWindowRect,
+            ServoExtensionRoute::SetWindowRect => {
+                let parameters: WindowRectParameters = Parameters::from_json(&body_data)?;
+                ServoExtensionCommand::SetWindowRect(parameters)
+            }
         };
         Ok(WebDriverCommand::Extension(command))
     }
@@ -176,6 +258,9 @@ enum ServoExtensionCommand {
     GetPrefs(GetPrefsParameters),
     SetPrefs(SetPrefsParameters),
     ResetPrefs(GetPrefsParameters),
+    NewWindow,
+    GetWindowRect,
+    SetWindowRect(WindowRectParameters),
 }
 
 impl WebDriverExtensionCommand for ServoExtensionCommand {
@@ -184,10 +269,62 @@ impl WebDriverExtensionCommand for ServoExtensionCommand {
             ServoExtensionCommand::GetPrefs(ref x) => Some(x.to_json()),
             ServoExtensionCommand::SetPrefs(ref x) => Some(x.to_json()),
             ServoExtensionCommand::ResetPrefs(ref x) => Some(x.to_json()),
+            ServoExtensionCommand::NewWindow => None,
+            ServoExtensionCommand::GetWindowRect => None,
+            ServoExtensionCommand::SetWindowRect(ref x) => Some(x.to_json()),
         }
     }
 }
 
+/// <https://w3c.github.io/webdriver/webdriver-spec.html#set-window-rect>
+#[derive(Clone, PartialEq)]
+struct WindowRectParameters {
+    x: Option<i32>,
+    y: Option<i32>,
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+impl Parameters for WindowRectParameters {
+    fn from_json(body: &Json) -> WebDriverResult<WindowRectParameters> {
+        let data = body.as_object().ok_or(
+            WebDriverError::new(ErrorStatus::InvalidArgument,
+                                "Message body was not an object"))?;
+        // The spec only allows coordinates that fit in a signed 32-bit integer and dimensions
+        // that are not negative.
+        let integer = |name: &str, min: i64| -> WebDriverResult<Option<i64>> {
+            match data.get(name) {
+                None | Some(&Json::Null) => Ok(None),
+                Some(value) => match value.as_i64() {
+                    Some(x) if x >= min && x <= i32::MAX as i64 => Ok(Some(x)),
+                    _ => Err(WebDriverError::new(ErrorStatus::InvalidArgument,
+                                                 format!("{} was not an integer between {} and {}",
+                                                         name, min, i32::MAX))),
+                },
+            }
+        };
+        let coordinate = |name: &str| integer(name, i32::MIN as i64).map(|x| x.map(|x| x as i32));
+        let dimension = |name: &str| integer(name, 0).map(|x| x.map(|x| x as u32));
+        Ok(WindowRectParameters {
+            x: coordinate("x")?,
+            y: coordinate("y")?,
+            width: dimension("width")?,
+            height: dimension("height")?,
+        })
+    }
+}
+
+impl ToJson for WindowRectParameters {
+    fn to_json(&self) -> Json {
+        let mut data = BTreeMap::new();
+        data.insert("x".to_owned(), self.x.to_json());
+        data.insert("y".to_owned(), self.y.to_json());
+        data.insert("width".to_owned(), self.width.to_json());
+        data.insert("height".to_owned(), self.height.to_json());
+        Json::Object(data)
+    }
+}
+
 #[derive(Clone, PartialEq)]
 struct GetPrefsParameters {
     prefs: Vec<String>
@@ -307,16 +444,27 @@ impl Handler {
         }
     }
 
-    fn handle_new_session(&mut self) -> WebDriverResult<WebDriverResponse> {
+    fn handle_new_session(&mut self, parameters: &NewSessionParameters) -> WebDriverResult<WebDriverResponse> {
         debug!("new session");
         if self.session.is_none() {
             let top_level_browsing_context_id = self.focus_top_level_browsing_context_id()?;
             let browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
-            let session = WebDriverSession::new(browsing_context_id, top_level_browsing_context_id);
+            let mut session = WebDriverSession::new(browsing_context_id, top_level_browsing_context_id);
+            // Required capabilities take precedence over desired ones.
+            let unhandled_prompt_behavior = parameters.required.get("unhandledPromptBehavior")
+                .or_else(|| parameters.desired.get("unhandledPromptBehavior"));
+            if let Some(value) = unhandled_prompt_behavior {
+                session.unhandled_prompt_behavior = value.as_string()
+                    .and_then(UnhandledPromptBehavior::from_str)
+                    .ok_or(WebDriverError::new(ErrorStatus::InvalidArgument,
+                                               "Invalid unhandledPromptBehavior capability"))?;
+            }
             let mut capabilities = BTreeMap::new();
             capabilities.insert("browserName".to_owned(), "servo".to_json());
             capabilities.insert("browserVersion".to_owned(), "0.0.1".to_json());
             capabilities.insert("acceptInsecureCerts".to_owned(), false.to_json());
+            capabilities.insert("unhandledPromptBehavior".to_owned(),
+                                session.unhandled_prompt_behavior.as_str().to_json());
             let response = NewSessionResponse::new(session.id.to_string(), Json::Object(capabilities));
             debug!("new session created {}.", session.id);
             self.session = Some(session);
@@ -329,6 +477,9 @@ impl Handler {
     }
 
     fn handle_delete_session(&mut self) -> WebDriverResult<WebDriverResponse> {
+        if self.session.is_some() {
+            let _ = self.release_actions();
+        }
         self.session = None;
         Ok(WebDriverResponse::Void)
     }
@@ -407,8 +558,16 @@ impl Handler {
     }
 
     fn handle_set_window_size(&self, params: &WindowSizeParameters) -> WebDriverResult<WebDriverResponse> {
-        let (sender, receiver) = ipc::channel().unwrap();
         let size = Size2D::new(params.width as u32, params.height as u32);
+        let window_size = self.set_window_size(size)?;
+        let vp = window_size.initial_viewport;
+        let window_size_response = WindowSizeResponse::new(vp.width as u64, vp.height as u64);
+        Ok(WebDriverResponse::WindowSize(window_size_response))
+    }
+
+    /// Resize the window, waiting until the resize happened or timed out.
+    fn set_window_size(&self, size: Size2D<u32>) -> WebDriverResult<WindowSizeData> {
+        let (sender, receiver) = ipc::channel().unwrap();
         let top_level_browsing_context_id = self.session()?.top_level_browsing_context_id;
         let cmd_msg = WebDriverCommandMsg::SetWindowSize(top_level_browsing_context_id, size, sender.clone());
 
@@ -424,10 +583,68 @@ impl Handler {
             constellation_chan.send(ConstellationMsg::WebDriverCommand(cmd_msg)).unwrap();
         });
 
-        let window_size = receiver.recv().unwrap();
-        let vp = window_size.initial_viewport;
-        let window_size_response = WindowSizeResponse::new(vp.width as u64, vp.height as u64);
-        Ok(WebDriverResponse::WindowSize(window_size_response))
+        Ok(receiver.recv().unwrap())
+    }
+
+    fn window_rect(&self) -> WebDriverResult<(Size2D<u32>, Point2D<i32>)> {
+        let top_level_browsing_context_id = self.session()?.top_level_browsing_context_id;
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd_msg = WebDriverCommandMsg::GetWindowRect(top_level_browsing_context_id, sender);
+        self.constellation_chan.send(ConstellationMsg::WebDriverCommand(cmd_msg)).unwrap();
+        Ok(receiver.recv().unwrap())
+    }
+
+    fn window_rect_response(&self) -> WebDriverResult<WebDriverResponse> {
+        let (size, origin) = self.window_rect()?;
+        let rect = WindowRectParameters {
+            x: Some(origin.x),
+            y: Some(origin.y),
+            width: Some(size.width),
+            height: Some(size.height),
+        };
+        Ok(WebDriverResponse::Generic(ValueResponse::new(rect.to_json())))
+    }
+
+    fn set_window_position(&self, origin: Point2D<i32>) -> WebDriverResult<()> {
+        let top_level_browsing_context_id = self.session()?.top_level_browsing_context_id;
+        let cmd_msg = WebDriverCommandMsg::SetWindowPosition(top_level_browsing_context_id, origin);
+        self.constellation_chan.send(ConstellationMsg::WebDriverCommand(cmd_msg)).unwrap();
+        Ok(())
+    }
+
+    // https://w3c.github.io/webdriver/webdriver-spec.html#get-window-rect
+    fn handle_get_window_rect(&self) -> WebDriverResult<WebDriverResponse> {
+        self.window_rect_response()
+    }
+
+    // https://w3c.github.io/webdriver/webdriver-spec.html#set-window-rect
+    fn handle_set_window_rect(&self, params: &WindowRectParameters) -> WebDriverResult<WebDriverResponse> {
+        // A dimension or coordinate that is left out keeps its current value.
+        let (size, origin) = self.window_rect()?;
+        if params.width.is_some() || params.height.is_some() {
+            let width = params.width.unwrap_or(size.width);
+            let height = params.height.unwrap_or(size.height);
+            self.set_window_size(Size2D::new(width, height))?;
+        }
+        if params.x.is_some() || params.y.is_some() {
+            let x = params.x.unwrap_or(origin.x);
+            let y = params.y.unwrap_or(origin.y);
+            self.set_window_position(Point2D::new(x, y))?;
+        }
+        self.window_rect_response()
+    }
+
+    // https://w3c.github.io/webdriver/webdriver-spec.html#maximize-window
+    fn handle_maximize_window(&self) -> WebDriverResult<WebDriverResponse> {
+        let top_level_browsing_context_id = self.session()?.top_level_browsing_context_id;
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd_msg = WebDriverCommandMsg::GetScreenAvailSize(top_level_browsing_context_id, sender);
+        self.constellation_chan.send(ConstellationMsg::WebDriverCommand(cmd_msg)).unwrap();
+        let screen_size = receiver.recv().unwrap();
+
+        self.set_window_position(Point2D::zero())?;
+        self.set_window_size(screen_size)?;
+        self.window_rect_response()
     }
 
     fn handle_is_enabled(&self, element: &WebElement) -> WebDriverResult<WebDriverResponse> {
@@ -488,20 +705,81 @@ impl Handler {
         Ok(WebDriverResponse::Generic(ValueResponse::new(value.to_json())))
     }
 
+    fn top_level_browsing_context_ids(&self) -> Vec<TopLevelBrowsingContextId> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd_msg = WebDriverCommandMsg::GetTopLevelBrowsingContexts(sender);
+        self.constellation_chan.send(ConstellationMsg::WebDriverCommand(cmd_msg)).unwrap();
+        receiver.recv().unwrap()
+    }
+
+    fn verify_top_level_browsing_context_is_open(&self) -> WebDriverResult<()> {
+        let top_level_browsing_context_id = self.session()?.top_level_browsing_context_id;
+        if self.top_level_browsing_context_ids().contains(&top_level_browsing_context_id) {
+            Ok(())
+        } else {
+            Err(WebDriverError::new(ErrorStatus::NoSuchWindow, "The current window was closed"))
+        }
+    }
+
     fn handle_window_handle(&self) -> WebDriverResult<WebDriverResponse> {
-        // For now we assume there's only one window so just use the session
-        // id as the window id
-        let handle = self.session.as_ref().unwrap().id.to_string();
+        // The window handle is the id of the top-level browsing context.
+        let handle = self.session()?.top_level_browsing_context_id.to_string();
         Ok(WebDriverResponse::Generic(ValueResponse::new(handle.to_json())))
     }
 
     fn handle_window_handles(&self) -> WebDriverResult<WebDriverResponse> {
-        // For now we assume there's only one window so just use the session
-        // id as the window id
-        let handles = vec![self.session.as_ref().unwrap().id.to_string().to_json()];
+        let handles: Vec<Json> = self.top_level_browsing_context_ids().iter()
+            .map(|id| id.to_string().to_json())
+            .collect();
         Ok(WebDriverResponse::Generic(ValueResponse::new(handles.to_json())))
     }
 
+    // https://w3c.github.io/webdriver/webdriver-spec.html#close-window
+    fn handle_close_window(&mut self) -> WebDriverResult<WebDriverResponse> {
+        let top_level_browsing_context_id = self.session()?.top_level_browsing_context_id;
+        self.constellation_chan.send(ConstellationMsg::CloseBrowser(top_level_browsing_context_id)).unwrap();
+
+        let handles: Vec<Json> = self.top_level_browsing_context_ids().iter()
+            .map(|id| id.to_string().to_json())
+            .collect();
+        if handles.is_empty() {
+            // Closing the last window ends the session.
+            self.session = None;
+        }
+        Ok(WebDriverResponse::Generic(ValueResponse::new(handles.to_json())))
+    }
+
+    // https://w3c.github.io/webdriver/webdriver-spec.html#switch-to-window
+    fn handle_switch_to_window(&mut self, parameters: &SwitchToWindowParameters) -> WebDriverResult<WebDriverResponse> {
+        let top_level_browsing_context_id = self.top_level_browsing_context_ids().into_iter()
+            .find(|id| id.to_string() == parameters.handle)
+            .ok_or(WebDriverError::new(ErrorStatus::NoSuchWindow, "No window with that handle"))?;
+
+        {
+            let session = self.session_mut()?;
+            session.top_level_browsing_context_id = top_level_browsing_context_id;
+            session.browsing_context_id = BrowsingContextId::from(top_level_browsing_context_id);
+        }
+
+        let msg = ConstellationMsg::SelectBrowser(top_level_browsing_context_id);
+        self.constellation_chan.send(msg).unwrap();
+        Ok(WebDriverResponse::Void)
+    }
+
+    // https://w3c.github.io/webdriver/webdriver-spec.html#new-window
+    fn handle_new_window(&self) -> WebDriverResult<WebDriverResponse> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let url = ServoUrl::parse("about:blank").unwrap();
+        self.constellation_chan.send(ConstellationMsg::NewBrowser(url, sender)).unwrap();
+        let top_level_browsing_context_id = receiver.recv().unwrap();
+
+        // Every top-level browsing context shares the one native window, so they are all tabs.
+        let mut result = BTreeMap::new();
+        result.insert("handle".to_owned(), top_level_browsing_context_id.to_string().to_json());
+        result.insert("type".to_owned(), "tab".to_json());
+        Ok(WebDriverResponse::Generic(ValueResponse::new(Json::Object(result))))
+    }
+
     fn handle_find_element(&self, parameters: &LocatorParameters) -> WebDriverResult<WebDriverResponse> {
         if parameters.using != LocatorStrategy::CSSSelector {
             return Err(WebDriverError::new(ErrorStatus::UnsupportedOperation,
@@ -581,6 +859,55 @@ impl Handler {
         }
     }
 
+    // https://w3c.github.io/webdriver/webdriver-spec.html#find-element-from-element
+    fn handle_find_element_element(&self,
+                                   element: &WebElement,
+                                   parameters: &LocatorParameters)
+                                   -> WebDriverResult<WebDriverResponse> {
+        if parameters.using != LocatorStrategy::CSSSelector {
+            return Err(WebDriverError::new(ErrorStatus::UnsupportedOperation,
+                                           "Unsupported locator strategy"))
+        }
+
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd = WebDriverScriptCommand::FindElementElementCSS(parameters.value.clone(),
+                                                                element.id.clone(),
+                                                                sender);
+        self.browsing_context_script_command(cmd)?;
+        match receiver.recv().unwrap() {
+            Ok(value) => {
+                let value_resp = value.map(|x| WebElement::new(x).to_json()).to_json();
+                Ok(WebDriverResponse::Generic(ValueResponse::new(value_resp)))
+            }
+            Err(error) => Err(element_error_to_webdriver_error(error)),
+        }
+    }
+
+    // https://w3c.github.io/webdriver/webdriver-spec.html#find-elements-from-element
+    fn handle_find_element_elements(&self,
+                                    element: &WebElement,
+                                    parameters: &LocatorParameters)
+                                    -> WebDriverResult<WebDriverResponse> {
+        if parameters.using != LocatorStrategy::CSSSelector {
+            return Err(WebDriverError::new(ErrorStatus::UnsupportedOperation,
+                                           "Unsupported locator strategy"))
+        }
+
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd = WebDriverScriptCommand::FindElementElementsCSS(parameters.value.clone(),
+                                                                 element.id.clone(),
+                                                                 sender);
+        self.browsing_context_script_command(cmd)?;
+        match receiver.recv().unwrap() {
+            Ok(value) => {
+                let resp_value: Vec<Json> = value.into_iter().map(
+                    |x| WebElement::new(x).to_json()).collect();
+                Ok(WebDriverResponse::Generic(ValueResponse::new(resp_value.to_json())))
+            }
+            Err(error) => Err(element_error_to_webdriver_error(error)),
+        }
+    }
+
     // https://w3c.github.io/webdriver/webdriver-spec.html#get-element-rect
     fn handle_element_rect(&self, element: &WebElement) -> WebDriverResult<WebDriverResponse> {
         let (sender, receiver) = ipc::channel().unwrap();
@@ -638,6 +965,18 @@ impl Handler {
         }
     }
 
+    // https://w3c.github.io/webdriver/webdriver-spec.html#get-element-property
+    fn handle_element_property(&self, element: &WebElement, name: &str) -> WebDriverResult<WebDriverResponse> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd = WebDriverScriptCommand::GetElementProperty(element.id.clone(), name.to_owned(), sender);
+        self.browsing_context_script_command(cmd)?;
+        match receiver.recv().unwrap() {
+            Ok(value) => Ok(WebDriverResponse::Generic(ValueResponse::new(value.to_json()))),
+            Err(_) => Err(WebDriverError::new(ErrorStatus::StaleElementReference,
+                                              "Unable to find element in document"))
+        }
+    }
+
     fn handle_element_css(&self, element: &WebElement, name: &str) -> WebDriverResult<WebDriverResponse> {
         let (sender, receiver) = ipc::channel().unwrap();
         let cmd = WebDriverScriptCommand::GetElementCSS(element.id.clone(), name.to_owned(), sender);
@@ -671,6 +1010,24 @@ impl Handler {
         Ok(WebDriverResponse::Cookie(CookieResponse::new(response)))
     }
 
+    // https://w3c.github.io/webdriver/webdriver-spec.html#delete-cookie
+    fn handle_delete_cookie(&self, name: &str) -> WebDriverResult<WebDriverResponse> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd = WebDriverScriptCommand::DeleteCookie(name.to_owned(), sender);
+        self.browsing_context_script_command(cmd)?;
+        receiver.recv().unwrap();
+        Ok(WebDriverResponse::Void)
+    }
+
+    // https://w3c.github.io/webdriver/webdriver-spec.html#delete-all-cookies
+    fn handle_delete_cookies(&self) -> WebDriverResult<WebDriverResponse> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd = WebDriverScriptCommand::DeleteCookies(sender);
+        self.browsing_context_script_command(cmd)?;
+        receiver.recv().unwrap();
+        Ok(WebDriverResponse::Void)
+    }
+
     fn handle_add_cookie(&self, params: &AddCookieParameters) -> WebDriverResult<WebDriverResponse> {
         let (sender, receiver) = ipc::channel().unwrap();
 
@@ -699,6 +1056,85 @@ impl Handler {
         }
     }
 
+    // https://w3c.github.io/webdriver/webdriver-spec.html#get-page-source
+    fn handle_get_page_source(&self) -> WebDriverResult<WebDriverResponse> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd = WebDriverScriptCommand::GetPageSource(sender);
+        self.browsing_context_script_command(cmd)?;
+        match receiver.recv().unwrap() {
+            Ok(source) => Ok(WebDriverResponse::Generic(ValueResponse::new(source.to_json()))),
+            Err(_) => Err(WebDriverError::new(ErrorStatus::NoSuchWindow,
+                                              "Unable to find the document of the browsing context"))
+        }
+    }
+
+    fn simple_dialog(&self) -> WebDriverResult<SimpleDialog> {
+        let top_level_browsing_context_id = self.session()?.top_level_browsing_context_id;
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd_msg = WebDriverCommandMsg::GetSimpleDialog(top_level_browsing_context_id, sender);
+        self.constellation_chan.send(ConstellationMsg::WebDriverCommand(cmd_msg)).unwrap();
+        receiver.recv().unwrap().ok_or(WebDriverError::new(ErrorStatus::NoSuchAlert, "No user prompt is open"))
+    }
+
+    fn close_simple_dialog(&self, accept: bool) -> WebDriverResult<()> {
+        let top_level_browsing_context_id = self.session()?.top_level_browsing_context_id;
+        let cmd_msg = WebDriverCommandMsg::CloseSimpleDialog(top_level_browsing_context_id, accept);
+        self.constellation_chan.send(ConstellationMsg::WebDriverCommand(cmd_msg)).unwrap();
+        Ok(())
+    }
+
+    // https://w3c.github.io/webdriver/webdriver-spec.html#dfn-handle-any-user-prompts
+    fn handle_any_user_prompts(&self) -> WebDriverResult<()> {
+        let dialog = match self.simple_dialog() {
+            Ok(dialog) => dialog,
+            Err(_) => return Ok(()),
+        };
+        let behavior = self.session()?.unhandled_prompt_behavior;
+        match behavior {
+            UnhandledPromptBehavior::Dismiss | UnhandledPromptBehavior::DismissAndNotify =>
+                self.close_simple_dialog(false)?,
+            UnhandledPromptBehavior::Accept | UnhandledPromptBehavior::AcceptAndNotify =>
+                self.close_simple_dialog(true)?,
+            UnhandledPromptBehavior::Ignore => {},
+        }
+        match behavior {
+            UnhandledPromptBehavior::Dismiss | UnhandledPromptBehavior::Accept => Ok(()),
+            _ => Err(WebDriverError::new(ErrorStatus::UnexpectedAlertOpen,
+                                         format!("Unexpected user prompt open: {}", dialog.message()))),
+        }
+    }
+
+    fn handle_dismiss_alert(&self) -> WebDriverResult<WebDriverResponse> {
+        self.simple_dialog()?;
+        self.close_simple_dialog(false)?;
+        Ok(WebDriverResponse::Void)
+    }
+
+    fn handle_accept_alert(&self) -> WebDriverResult<WebDriverResponse> {
+        self.simple_dialog()?;
+        self.close_simple_dialog(true)?;
+        Ok(WebDriverResponse::Void)
+    }
+
+    fn handle_get_alert_text(&self) -> WebDriverResult<WebDriverResponse> {
+        let dialog = self.simple_dialog()?;
+        Ok(WebDriverResponse::Generic(ValueResponse::new(dialog.message().to_json())))
+    }
+
+    fn handle_send_alert_text(&self, keys: &SendKeysParameters) -> WebDriverResult<WebDriverResponse> {
+        match self.simple_dialog()? {
+            SimpleDialog::Prompt(..) => {},
+            SimpleDialog::Alert(_) | SimpleDialog::Confirm(_) =>
+                return Err(WebDriverError::new(ErrorStatus::ElementNotInteractable,
+                                               "User prompt does not accept text")),
+        }
+        let top_level_browsing_context_id = self.session()?.top_level_browsing_context_id;
+        let text = keys.value.iter().collect();
+        let cmd_msg = WebDriverCommandMsg::SetSimpleDialogText(top_level_browsing_context_id, text);
+        self.constellation_chan.send(ConstellationMsg::WebDriverCommand(cmd_msg)).unwrap();
+        Ok(WebDriverResponse::Void)
+    }
+
     fn handle_set_timeouts(&mut self,
                            parameters: &TimeoutsParameters)
                            -> WebDriverResult<WebDriverResponse> {
@@ -791,6 +1227,53 @@ impl Handler {
         Ok(WebDriverResponse::Void)
     }
 
+    // https://w3c.github.io/webdriver/webdriver-spec.html#element-click
+    fn handle_element_click(&mut self, element: &WebElement) -> WebDriverResult<WebDriverResponse> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd = WebDriverScriptCommand::ElementClick(element.id.clone(), sender);
+        self.browsing_context_script_command(cmd)?;
+        let point = match receiver.recv().unwrap() {
+            Ok(Some(point)) => point,
+            // Options are selected by the script thread, without any mouse events.
+            Ok(None) => return Ok(WebDriverResponse::Void),
+            Err(error) => return Err(element_error_to_webdriver_error(error)),
+        };
+
+        // Step 9. Click at the in-view center point with a mouse input source of
+        // its own, which is removed again afterwards.
+        let id = Uuid::new_v4().to_string();
+        self.session_mut()?.input_state_table.insert(id.clone(),
+                                                     InputSourceState::Pointer(PointerInputState::new()));
+        let result = self.perform_pointer_move(&id, 0, Point2D::new(0, 0), point, Instant::now());
+        let result = result.and_then(|_| self.dispatch_pointerdown_action(&id, 0));
+        let result = result.and_then(|_| self.dispatch_pointerup_action(&id, 0));
+        self.session_mut()?.input_state_table.remove(&id);
+        result.map(|_| WebDriverResponse::Void)
+    }
+
+    // https://w3c.github.io/webdriver/webdriver-spec.html#element-clear
+    fn handle_element_clear(&self, element: &WebElement) -> WebDriverResult<WebDriverResponse> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd = WebDriverScriptCommand::ElementClear(element.id.clone(), sender);
+        self.browsing_context_script_command(cmd)?;
+        match receiver.recv().unwrap() {
+            Ok(()) => Ok(WebDriverResponse::Void),
+            Err(error) => Err(element_error_to_webdriver_error(error)),
+        }
+    }
+
+    // https://w3c.github.io/webdriver/webdriver-spec.html#perform-actions
+    fn handle_perform_actions(&mut self, parameters: &ActionsParameters) -> WebDriverResult<WebDriverResponse> {
+        self.dispatch_actions(&parameters.actions)?;
+        Ok(WebDriverResponse::Void)
+    }
+
+    // https://w3c.github.io/webdriver/webdriver-spec.html#release-actions
+    fn handle_release_actions(&mut self) -> WebDriverResult<WebDriverResponse> {
+        self.release_actions()?;
+        Ok(WebDriverResponse::Void)
+    }
+
     fn handle_take_screenshot(&self) -> WebDriverResult<WebDriverResponse> {
         let mut img = None;
         let top_level_id = self.session()?.top_level_browsing_context_id;
@@ -874,14 +1357,46 @@ impl WebDriverHandler<ServoExtensionRoute> for Handler {
             }
         }
 
+        // Most commands need the current window to still be open.
         match msg.command {
-            WebDriverCommand::NewSession(_) => self.handle_new_session(),
+            WebDriverCommand::NewSession(_) |
+            WebDriverCommand::DeleteSession |
+            WebDriverCommand::SetTimeouts(_) |
+            WebDriverCommand::GetWindowHandles |
+            WebDriverCommand::SwitchToWindow(_) |
+            WebDriverCommand::Extension(ServoExtensionCommand::GetPrefs(_)) |
+            WebDriverCommand::Extension(ServoExtensionCommand::SetPrefs(_)) |
+            WebDriverCommand::Extension(ServoExtensionCommand::ResetPrefs(_)) => {},
+            _ => self.verify_top_level_browsing_context_is_open()?,
+        }
+
+        // Commands that interact with the page first need to deal with any open user prompt.
+        match msg.command {
+            WebDriverCommand::NewSession(_) |
+            WebDriverCommand::DeleteSession |
+            WebDriverCommand::SetTimeouts(_) |
+            WebDriverCommand::GetWindowHandle |
+            WebDriverCommand::GetWindowHandles |
+            WebDriverCommand::SwitchToWindow(_) |
+            WebDriverCommand::DismissAlert |
+            WebDriverCommand::AcceptAlert |
+            WebDriverCommand::GetAlertText |
+            WebDriverCommand::SendAlertText(_) |
+            WebDriverCommand::Extension(ServoExtensionCommand::GetPrefs(_)) |
+            WebDriverCommand::Extension(ServoExtensionCommand::SetPrefs(_)) |
+            WebDriverCommand::Extension(ServoExtensionCommand::ResetPrefs(_)) => {},
+            _ => self.handle_any_user_prompts()?,
+        }
+
+        match msg.command {
+            WebDriverCommand::NewSession(ref parameters) => self.handle_new_session(parameters),
             WebDriverCommand::DeleteSession => self.handle_delete_session(),
             WebDriverCommand::AddCookie(ref parameters) => self.handle_add_cookie(parameters),
             WebDriverCommand::Get(ref parameters) => self.handle_get(parameters),
             WebDriverCommand::GetCurrentUrl => self.handle_current_url(),
             WebDriverCommand::GetWindowSize => self.handle_window_size(),
             WebDriverCommand::SetWindowSize(ref size) => self.handle_set_window_size(size),
+            WebDriverCommand::MaximizeWindow => self.handle_maximize_window(),
             WebDriverCommand::IsEnabled(ref element) => self.handle_is_enabled(element),
             WebDriverCommand::IsSelected(ref element) => self.handle_is_selected(element),
             WebDriverCommand::GoBack => self.handle_go_back(),
@@ -890,24 +1405,43 @@ impl WebDriverHandler<ServoExtensionRoute> for Handler {
             WebDriverCommand::GetTitle => self.handle_title(),
             WebDriverCommand::GetWindowHandle => self.handle_window_handle(),
             WebDriverCommand::GetWindowHandles => self.handle_window_handles(),
+            WebDriverCommand::CloseWindow => self.handle_close_window(),
+            WebDriverCommand::SwitchToWindow(ref parameters) => self.handle_switch_to_window(parameters),
             WebDriverCommand::SwitchToFrame(ref parameters) => self.handle_switch_to_frame(parameters),
             WebDriverCommand::SwitchToParentFrame => self.handle_switch_to_parent_frame(),
             WebDriverCommand::FindElement(ref parameters) => self.handle_find_element(parameters),
             WebDriverCommand::FindElements(ref parameters) => self.handle_find_elements(parameters),
+            WebDriverCommand::FindElementElement(ref element, ref parameters) =>
+                self.handle_find_element_element(element, parameters),
+            WebDriverCommand::FindElementElements(ref element, ref parameters) =>
+                self.handle_find_element_elements(element, parameters),
             WebDriverCommand::GetNamedCookie(ref name) => self.handle_get_cookie(name),
             WebDriverCommand::GetCookies => self.handle_get_cookies(),
+            WebDriverCommand::DeleteCookie(ref name) => self.handle_delete_cookie(name),
+            WebDriverCommand::DeleteCookies => self.handle_delete_cookies(),
+            WebDriverCommand::GetPageSource => self.handle_get_page_source(),
             WebDriverCommand::GetActiveElement => self.handle_active_element(),
             WebDriverCommand::GetElementRect(ref element) => self.handle_element_rect(element),
             WebDriverCommand::GetElementText(ref element) => self.handle_element_text(element),
             WebDriverCommand::GetElementTagName(ref element) => self.handle_element_tag_name(element),
             WebDriverCommand::GetElementAttribute(ref element, ref name) =>
                 self.handle_element_attribute(element, name),
+            WebDriverCommand::GetElementProperty(ref element, ref name) =>
+                self.handle_element_property(element, name),
             WebDriverCommand::GetCSSValue(ref element, ref name) =>
                 self.handle_element_css(element, name),
             WebDriverCommand::ExecuteScript(ref x) => self.handle_execute_script(x),
             WebDriverCommand::ExecuteAsyncScript(ref x) => self.handle_execute_async_script(x),
             WebDriverCommand::ElementSendKeys(ref element, ref keys) =>
                 self.handle_element_send_keys(element, keys),
+            WebDriverCommand::ElementClick(ref element) => self.handle_element_click(element),
+            WebDriverCommand::ElementClear(ref element) => self.handle_element_clear(element),
+            WebDriverCommand::PerformActions(ref x) => self.handle_perform_actions(x),
+            WebDriverCommand::ReleaseActions => self.handle_release_actions(),
+            WebDriverCommand::DismissAlert => self.handle_dismiss_alert(),
+            WebDriverCommand::AcceptAlert => self.handle_accept_alert(),
+            WebDriverCommand::GetAlertText => self.handle_get_alert_text(),
+            WebDriverCommand::SendAlertText(ref keys) => self.handle_send_alert_text(keys),
             WebDriverCommand::SetTimeouts(ref x) => self.handle_set_timeouts(x),
             WebDriverCommand::TakeScreenshot => self.handle_take_screenshot(),
             WebDriverCommand::Extension(ref extension) => {
@@ -915,6 +1449,9 @@ impl WebDriverHandler<ServoExtensionRoute> for Handler {
                     ServoExtensionCommand::GetPrefs(ref x) => self.handle_get_prefs(x),
                     ServoExtensionCommand::SetPrefs(ref x) => self.handle_set_prefs(x),
                     ServoExtensionCommand::ResetPrefs(ref x) => self.handle_reset_prefs(x),
+                    ServoExtensionCommand::NewWindow => self.handle_new_window(),
+                    ServoExtensionCommand::GetWindowRect => self.handle_get_window_rect(),
+                    ServoExtensionCommand::SetWindowRect(ref x) => self.handle_set_window_rect(x),
                 }
             }
             _ => Err(WebDriverError::new(ErrorStatus::UnsupportedOperation,
@@ -923,6 +1460,9 @@ impl WebDriverHandler<ServoExtensionRoute> for Handler {
     }
 
     fn delete_session(&mut self, _session: &Option<Session>) {
+        if self.session.is_some() {
+            let _ = self.release_actions();
+        }
         // Servo doesn't support multiple sessions, so we exit on session deletion
         let _ = self.constellation_chan.send(ConstellationMsg::Exit);
         self.session = None;
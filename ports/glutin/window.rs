@@ -32,6 +32,7 @@ use servo_config::resource_files;
 use servo_geometry::DeviceIndependentPixel;
 use servo_url::ServoUrl;
 use std::cell::{Cell, RefCell};
+use std::cmp;
 #[cfg(any(target_os = "linux", target_os = "macos"))]
 use std::ffi::CString;
 #[cfg(any(target_os = "linux", target_os = "macos"))]
@@ -81,6 +82,9 @@ const LINE_HEIGHT: f32 = 38.0;
 
 const MULTISAMPLES: u16 = 16;
 
+/// The largest width or height OSMesa can render to.
+const MAX_HEADLESS_SIZE: u32 = 16384;
+
 #[cfg(target_os = "macos")]
 fn builder_with_platform_options(mut builder: glutin::WindowBuilder) -> glutin::WindowBuilder {
     if opts::get().headless || opts::get().output_file.is_some() {
@@ -99,16 +103,22 @@ fn builder_with_platform_options(builder: glutin::WindowBuilder) -> glutin::Wind
 
 #[cfg(any(target_os = "linux", target_os = "macos"))]
 struct HeadlessContext {
-    width: u32,
-    height: u32,
-    _context: osmesa_sys::OSMesaContext,
-    _buffer: Vec<u32>,
+    width: Cell<u32>,
+    height: Cell<u32>,
+    /// The size of the imaginary screen, which is the size the context was created with.
+    screen_size: Size2D<u32>,
+    position: Cell<Point2D<i32>>,
+    context: osmesa_sys::OSMesaContext,
+    buffer: RefCell<Vec<u32>>,
 }
 
 #[cfg(not(any(target_os = "linux", target_os = "macos")))]
 struct HeadlessContext {
-    width: u32,
-    height: u32,
+    width: Cell<u32>,
+    height: Cell<u32>,
+    /// The size of the imaginary screen, which is the size the context was created with.
+    screen_size: Size2D<u32>,
+    position: Cell<Point2D<i32>>,
 }
 
 impl HeadlessContext {
@@ -142,19 +152,71 @@ impl HeadlessContext {
         };
 
         HeadlessContext {
-            width: width,
-            height: height,
-            _context: context,
-            _buffer: buffer,
+            width: Cell::new(width),
+            height: Cell::new(height),
+            screen_size: Size2D::new(width, height),
+            position: Cell::new(Point2D::zero()),
+            context: context,
+            buffer: RefCell::new(buffer),
         }
     }
 
     #[cfg(not(any(target_os = "linux", target_os = "macos")))]
     fn new(width: u32, height: u32) -> HeadlessContext {
         HeadlessContext {
-            width: width,
-            height: height,
+            width: Cell::new(width),
+            height: Cell::new(height),
+            screen_size: Size2D::new(width, height),
+            position: Cell::new(Point2D::zero()),
+        }
+    }
+
+    fn size(&self) -> Size2D<u32> {
+        Size2D::new(self.width.get(), self.height.get())
+    }
+
+    /// Clamp a requested size to what fits on the imaginary screen and what OSMesa accepts.
+    fn clamp_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let max_width = cmp::max(cmp::min(self.screen_size.width, MAX_HEADLESS_SIZE), 1);
+        let max_height = cmp::max(cmp::min(self.screen_size.height, MAX_HEADLESS_SIZE), 1);
+        (cmp::max(cmp::min(width, max_width), 1), cmp::max(cmp::min(height, max_height), 1))
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    fn resize(&self, width: u32, height: u32) {
+        let (width, height) = self.clamp_size(width, height);
+        let length = match width.checked_mul(height) {
+            Some(length) => length as usize,
+            None => {
+                warn!("Headless context size {}x{} is too large.", width, height);
+                return;
+            }
+        };
+        let mut buffer = vec![0; length];
+
+        let ret = unsafe {
+            osmesa_sys::OSMesaMakeCurrent(self.context,
+                                          buffer.as_mut_ptr() as *mut _,
+                                          gl::UNSIGNED_BYTE,
+                                          width as i32,
+                                          height as i32)
+        };
+        if ret == 0 {
+            // Keep rendering into the old buffer, which OSMesa still uses.
+            error!("Failed to resize the headless context to {}x{}.", width, height);
+            return;
         }
+
+        *self.buffer.borrow_mut() = buffer;
+        self.width.set(width);
+        self.height.set(height);
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    fn resize(&self, width: u32, height: u32) {
+        let (width, height) = self.clamp_size(width, height);
+        self.width.set(width);
+        self.height.set(height);
     }
 
     #[cfg(any(target_os = "linux", target_os = "macos"))]
@@ -990,7 +1052,7 @@ impl WindowMethods for Window {
                 DeviceUintSize::new(width, height) * scale_factor
             }
             WindowKind::Headless(ref context) => {
-                DeviceUintSize::new(context.width, context.height)
+                DeviceUintSize::from_untyped(&context.size())
             }
         }
     }
@@ -1009,7 +1071,7 @@ impl WindowMethods for Window {
                 TypedSize2D::new(width as f32, height as f32)
             }
             WindowKind::Headless(ref context) => {
-                TypedSize2D::new(context.width as f32, context.height as f32)
+                TypedSize2D::from_untyped(&context.size().to_f32())
             }
         }
     }
@@ -1026,8 +1088,7 @@ impl WindowMethods for Window {
                 (size, origin)
             }
             WindowKind::Headless(ref context) => {
-                let size = TypedSize2D::new(context.width, context.height);
-                (size, Point2D::zero())
+                (context.size(), context.position.get())
             }
         }
 
@@ -1040,7 +1101,7 @@ impl WindowMethods for Window {
                 Size2D::new(width, height)
             }
             WindowKind::Headless(ref context) => {
-                Size2D::new(context.width, context.height)
+                context.screen_size
             }
         }
     }
@@ -1053,7 +1114,7 @@ impl WindowMethods for Window {
                 Size2D::new(width, height)
             }
             WindowKind::Headless(ref context) => {
-                Size2D::new(context.width, context.height)
+                context.screen_size
             }
         }
     }
@@ -1067,7 +1128,11 @@ impl WindowMethods for Window {
             WindowKind::Window(ref window) => {
                 window.set_inner_size(size.width as u32, size.height as u32)
             }
-            WindowKind::Headless(..) => {}
+            WindowKind::Headless(ref context) => {
+                // There is no native window to report the new size, so queue the resize ourselves.
+                context.resize(size.width, size.height);
+                self.event_queue.borrow_mut().push(WindowEvent::Resize);
+            }
         }
     }
 
@@ -1076,7 +1141,9 @@ impl WindowMethods for Window {
             WindowKind::Window(ref window) => {
                 window.set_position(point.x, point.y)
             }
-            WindowKind::Headless(..) => {}
+            WindowKind::Headless(ref context) => {
+                context.position.set(point);
+            }
         }
     }
 
@@ -1344,6 +1411,9 @@ impl WindowMethods for Window {
                     self.event_queue.borrow_mut().push(WindowEvent::Quit);
                 }
             }
+            (KeyModifiers::CONTROL, None, Key::F9) => {
+                self.event_queue.borrow_mut().push(WindowEvent::SaveHar);
+            }
             (KeyModifiers::CONTROL, None, Key::F10) => {
                 let event = WindowEvent::ToggleWebRenderDebug(WebRenderDebugOption::RenderTargetDebug);
                 self.event_queue.borrow_mut().push(event);
@@ -0,0 +1,241 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// Runs in a global of its own in every script thread that devtools are connected to, and debugs
+// the windows of that thread through SpiderMonkey's Debugger API. Servo calls the functions
+// defined here, and defines the native `pauseInScriptThread(info)` function, which blocks until
+// devtools resume the script and returns one of "resume", "in", "over" or "out".
+
+"use strict";
+
+const dbg = new Debugger();
+
+// The breakpoints that are set, by "url:line", including the ones in scripts not yet loaded.
+const breakpoints = new Map();
+
+// The frame that is paused, if any.
+let pausedFrame = null;
+
+function location(frame) {
+    if (!frame.script) {
+        return { url: "", line: 0, column: 0 };
+    }
+    const offsetLocation = frame.script.getOffsetLocation(frame.offset);
+    return {
+        url: frame.script.url || "",
+        line: offsetLocation.lineNumber,
+        column: offsetLocation.columnNumber,
+    };
+}
+
+function describeFrame(frame, depth) {
+    const where = location(frame);
+    return {
+        depth: depth,
+        type: frame.type,
+        callee: frame.callee ? (frame.callee.displayName || frame.callee.name || null) : null,
+        url: where.url,
+        line: where.line,
+        column: where.column,
+    };
+}
+
+// Converts a debuggee value to the serialized form of `devtools_traits::EvaluateJSReply`.
+function describeValue(value) {
+    if (value instanceof Debugger.Object) {
+        return { ActorValue: { class: value.class, uuid: "debugger-" + Math.random().toString(16).slice(2) } };
+    }
+    if (value === undefined || (typeof value === "object" && value !== null)) {
+        // Optimized out and uninitialized bindings are reported as undefined.
+        return "VoidValue";
+    }
+    if (value === null) {
+        return "NullValue";
+    }
+    switch (typeof value) {
+        case "boolean":
+            return { BooleanValue: value };
+        case "number":
+            return { NumberValue: value };
+        case "string":
+            return { StringValue: value };
+        default:
+            return { StringValue: String(value) };
+    }
+}
+
+function clearStepping() {
+    dbg.onEnterFrame = undefined;
+    for (let frame = dbg.getNewestFrame(); frame; frame = frame.older) {
+        frame.onStep = undefined;
+        frame.onPop = undefined;
+    }
+}
+
+function pauseOnStep() {
+    return pause(this, "resumeLimit");
+}
+
+function startStepping(frame, step) {
+    if (step === "in") {
+        dbg.onEnterFrame = newFrame => pause(newFrame, "resumeLimit");
+    }
+    if (step === "in" || step === "over") {
+        const startLine = location(frame).line;
+        frame.onStep = function() {
+            if (this === frame && location(this).line === startLine) {
+                return undefined;
+            }
+            return pause(this, "resumeLimit");
+        };
+    }
+    // Whatever the step, returning from the frame pauses in its caller.
+    frame.onPop = function() {
+        if (this.older) {
+            this.older.onStep = pauseOnStep;
+        }
+        return undefined;
+    };
+}
+
+function pause(frame, why) {
+    clearStepping();
+    pausedFrame = frame;
+    const info = JSON.stringify({ why: why, frame: describeFrame(frame, 0) });
+    let step;
+    try {
+        step = pauseInScriptThread(info);
+    } finally {
+        pausedFrame = null;
+    }
+    if (step !== "resume") {
+        startStepping(frame, step);
+    }
+    // Carry on running the debuggee.
+    return undefined;
+}
+
+function breakpointHandler(key) {
+    return {
+        hit: frame => breakpoints.has(key) ? pause(frame, "breakpoint") : undefined,
+    };
+}
+
+function setBreakpointInScript(script, breakpoint) {
+    let found = false;
+    for (const offset of script.getLineOffsets(breakpoint.line)) {
+        script.setBreakpoint(offset, breakpoint.handler);
+        found = true;
+    }
+    for (const child of script.getChildScripts()) {
+        found = setBreakpointInScript(child, breakpoint) || found;
+    }
+    return found;
+}
+
+dbg.onNewScript = function(script) {
+    for (const breakpoint of breakpoints.values()) {
+        if (breakpoint.url === script.url) {
+            setBreakpointInScript(script, breakpoint);
+        }
+    }
+};
+
+dbg.onDebuggerStatement = frame => pause(frame, "debuggerStatement");
+
+function addDebuggee(global) {
+    dbg.addDebuggee(global);
+}
+
+function setBreakpoint(url, line) {
+    const key = url + ":" + line;
+    let breakpoint = breakpoints.get(key);
+    if (!breakpoint) {
+        breakpoint = { url: url, line: line, handler: breakpointHandler(key) };
+        breakpoints.set(key, breakpoint);
+    }
+    let found = false;
+    for (const script of dbg.findScripts({ url: url, line: line })) {
+        for (const offset of script.getLineOffsets(line)) {
+            script.setBreakpoint(offset, breakpoint.handler);
+            found = true;
+        }
+    }
+    return found;
+}
+
+function removeBreakpoint(url, line) {
+    const key = url + ":" + line;
+    const breakpoint = breakpoints.get(key);
+    if (!breakpoint) {
+        return;
+    }
+    breakpoints.delete(key);
+    for (const script of dbg.findScripts({ url: url })) {
+        script.clearBreakpoint(breakpoint.handler);
+    }
+}
+
+// The sources of the scripts run in the given debuggee global, in the serialized form of
+// `devtools_traits::SourceInfo`. Code evaluated on behalf of devtools has no URL, and is left
+// out along with the code created by `eval` and `Function`.
+function sources(global) {
+    const result = new Map();
+    for (const script of dbg.findScripts({ global: global })) {
+        const source = script.source;
+        if (!source.url || source.introductionType === "eval" || source.introductionType === "Function") {
+            continue;
+        }
+        const info = result.get(source);
+        if (info) {
+            info.startLine = Math.min(info.startLine, script.startLine);
+        } else {
+            result.set(source, { url: source.url, text: source.text, startLine: script.startLine });
+        }
+    }
+    return JSON.stringify(Array.from(result.values()));
+}
+
+function interrupt() {
+    dbg.onEnterFrame = frame => pause(frame, "interrupted");
+}
+
+function describeStack(newestFrame) {
+    const result = [];
+    let depth = 0;
+    for (let frame = newestFrame; frame; frame = frame.older) {
+        result.push(describeFrame(frame, depth));
+        depth++;
+    }
+    return JSON.stringify(result);
+}
+
+function frames() {
+    return describeStack(pausedFrame);
+}
+
+function environment(depth) {
+    let frame = pausedFrame;
+    while (frame && depth > 0) {
+        frame = frame.older;
+        depth--;
+    }
+    if (!frame || !frame.environment) {
+        return JSON.stringify(null);
+    }
+    const scopes = [];
+    // The global scope holds every property of the window, so it is left out.
+    for (let env = frame.environment; env && env.parent; env = env.parent) {
+        const bindings = [];
+        for (const name of env.names()) {
+            bindings.push({ name: name, value: describeValue(env.getVariable(name)) });
+        }
+        scopes.push({
+            type: env.type,
+            callee: env.callee ? (env.callee.displayName || env.callee.name || null) : null,
+            bindings: bindings,
+        });
+    }
+    return JSON.stringify(scopes);
+}
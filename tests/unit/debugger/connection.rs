@@ -0,0 +1,70 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use debugger::{is_allowed_origin, is_local_host, remote_object};
+use devtools_traits::EvaluateJSReply;
+use std::f64;
+
+#[test]
+fn test_local_hosts() {
+    assert!(is_local_host(Some(b"localhost")));
+    assert!(is_local_host(Some(b"LocalHost:6000")));
+    assert!(is_local_host(Some(b"127.0.0.1")));
+    assert!(is_local_host(Some(b"127.0.0.1:6000")));
+    assert!(is_local_host(Some(b"[::1]")));
+    assert!(is_local_host(Some(b"[::1]:6000")));
+}
+
+#[test]
+fn test_remote_hosts() {
+    assert!(!is_local_host(None));
+    assert!(!is_local_host(Some(b"")));
+    assert!(!is_local_host(Some(b"example.com")));
+    assert!(!is_local_host(Some(b"localhost.example.com:6000")));
+    assert!(!is_local_host(Some(b"127.0.0.2:6000")));
+    assert!(!is_local_host(Some(b"[::2]:6000")));
+    assert!(!is_local_host(Some(b"\xff\xfe")));
+}
+
+#[test]
+fn test_allowed_origins() {
+    assert!(is_allowed_origin(None));
+    assert!(is_allowed_origin(Some(b"devtools://devtools")));
+    assert!(is_allowed_origin(Some(b"chrome-devtools://devtools")));
+
+    assert!(!is_allowed_origin(Some(b"")));
+    assert!(!is_allowed_origin(Some(b"null")));
+    assert!(!is_allowed_origin(Some(b"http://localhost:6000")));
+    assert!(!is_allowed_origin(Some(b"https://example.com")));
+    assert!(!is_allowed_origin(Some(b"devtools://devtools.example.com")));
+}
+
+#[test]
+fn test_remote_object_primitives() {
+    assert_eq!(remote_object(EvaluateJSReply::VoidValue), json!({ "type": "undefined" }));
+    assert_eq!(remote_object(EvaluateJSReply::NullValue),
+               json!({ "type": "object", "subtype": "null", "value": null }));
+    assert_eq!(remote_object(EvaluateJSReply::BooleanValue(true)), json!({ "type": "boolean", "value": true }));
+    assert_eq!(remote_object(EvaluateJSReply::StringValue("servo".to_owned())),
+               json!({ "type": "string", "value": "servo" }));
+    assert_eq!(remote_object(EvaluateJSReply::ActorValue { class: "Window".to_owned(), uuid: "1".to_owned() }),
+               json!({ "type": "object", "className": "Window", "description": "Window", "objectId": "1" }));
+}
+
+#[test]
+fn test_remote_object_numbers() {
+    assert_eq!(remote_object(EvaluateJSReply::NumberValue(1.5)),
+               json!({ "type": "number", "value": 1.5, "description": "1.5" }));
+    assert_eq!(remote_object(EvaluateJSReply::NumberValue(0.)),
+               json!({ "type": "number", "value": 0., "description": "0" }));
+
+    // JSON has no representation for these numbers.
+    for &(number, unserializable) in &[(f64::NAN, "NaN"),
+                                       (f64::INFINITY, "Infinity"),
+                                       (f64::NEG_INFINITY, "-Infinity"),
+                                       (-0., "-0")] {
+        assert_eq!(remote_object(EvaluateJSReply::NumberValue(number)),
+                   json!({ "type": "number", "unserializableValue": unserializable, "description": unserializable }));
+    }
+}
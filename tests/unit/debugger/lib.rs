@@ -0,0 +1,17 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+extern crate debugger;
+extern crate devtools_traits;
+extern crate ipc_channel;
+extern crate msg;
+#[macro_use]
+extern crate serde_json;
+extern crate servo_url;
+extern crate ws;
+
+#[cfg(test)]
+mod connection;
+#[cfg(test)]
+mod server;
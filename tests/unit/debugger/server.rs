@@ -0,0 +1,92 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use debugger::{self, Sender};
+use devtools_traits::{DevtoolsControlMsg, DevtoolsPageInfo, NetworkListeners, ScriptToDevtoolsControlMsg};
+use ipc_channel::ipc;
+use msg::constellation_msg::TEST_PIPELINE_ID;
+use serde_json::{self, Value};
+use servo_url::ServoUrl;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{Receiver, channel};
+use std::thread;
+use std::time::Duration;
+use ws::{self, CloseCode};
+
+/// Start a server on a free port, and wait for it to accept connections.
+fn start_server() -> (u16, Sender, Receiver<DevtoolsControlMsg>) {
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let (devtools_chan, devtools_port) = channel();
+    let server = debugger::start_server(port, Some(devtools_chan), NetworkListeners::default());
+    for _ in 0..100 {
+        if TcpStream::connect(("127.0.0.1", port)).is_ok() {
+            return (port, server, devtools_port);
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    panic!("The debugger server didn't start.");
+}
+
+#[test]
+fn test_get_targets() {
+    let (port, server, devtools_port) = start_server();
+
+    let (script_chan, _script_port) = ipc::channel().unwrap();
+    let page_info = DevtoolsPageInfo {
+        title: "Test page".to_owned(),
+        url: ServoUrl::parse("http://example.com/").unwrap(),
+    };
+    let message = ScriptToDevtoolsControlMsg::NewGlobal((TEST_PIPELINE_ID, None), script_chan, page_info);
+    server.devtools_chan().send(DevtoolsControlMsg::FromScript(message)).unwrap();
+    // The server passes messages on once it has seen them.
+    devtools_port.recv().unwrap();
+
+    let (reply_chan, reply_port) = channel();
+    ws::connect(format!("ws://127.0.0.1:{}", port), |out: ws::Sender| {
+        out.send(json!({ "id": 1, "method": "Target.getTargets" }).to_string()).unwrap();
+        let reply_chan = reply_chan.clone();
+        move |message: ws::Message| {
+            reply_chan.send(message.into_text().unwrap()).unwrap();
+            out.close(CloseCode::Normal)
+        }
+    }).unwrap();
+
+    let reply: Value = serde_json::from_str(&reply_port.recv().unwrap()).unwrap();
+    assert_eq!(reply, json!({
+        "id": 1,
+        "result": {
+            "targetInfos": [{
+                "targetId": TEST_PIPELINE_ID.to_string(),
+                "type": "page",
+                "title": "Test page",
+                "url": "http://example.com/",
+                "attached": false,
+            }],
+        },
+    }));
+
+    debugger::shutdown_server(&server);
+}
+
+#[test]
+fn test_handshake_from_page_is_refused() {
+    let (port, server, _devtools_port) = start_server();
+
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    write!(stream, "GET / HTTP/1.1\r\n\
+                    Host: 127.0.0.1:{}\r\n\
+                    Upgrade: websocket\r\n\
+                    Connection: Upgrade\r\n\
+                    Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                    Sec-WebSocket-Version: 13\r\n\
+                    Origin: http://example.com\r\n\
+                    \r\n", port).unwrap();
+
+    let mut status = String::new();
+    BufReader::new(stream).read_line(&mut status).unwrap();
+    assert!(status.starts_with("HTTP/1.1 403"), "unexpected response {:?}", status);
+
+    debugger::shutdown_server(&server);
+}
@@ -19,7 +19,7 @@ fn test_transform_compress_none() {
     let mode = CompressionMode::CompressNone;
     for &test in test_strs.iter() {
         let mut trimmed_str = String::new();
-        transform_text(test, mode, true, &mut trimmed_str);
+        transform_text(test, mode, true, &mut trimmed_str, &mut vec![]);
         assert_eq!(trimmed_str, test)
     }
 }
@@ -52,7 +52,7 @@ fn test_transform_discard_newline() {
     let mode = CompressionMode::DiscardNewline;
     for &(test, oracle) in test_strs.iter() {
         let mut trimmed_str = String::new();
-        transform_text(test, mode, true, &mut trimmed_str);
+        transform_text(test, mode, true, &mut trimmed_str, &mut vec![]);
         assert_eq!(trimmed_str, oracle)
     }
 }
@@ -85,7 +85,7 @@ fn test_transform_compress_whitespace() {
     let mode = CompressionMode::CompressWhitespace;
     for &(test, oracle) in test_strs.iter() {
         let mut trimmed_str = String::new();
-        transform_text(test, mode, true, &mut trimmed_str);
+        transform_text(test, mode, true, &mut trimmed_str, &mut vec![]);
         assert_eq!(&*trimmed_str, oracle)
     }
 }
@@ -118,7 +118,7 @@ fn test_transform_compress_whitespace_newline() {
     let mode = CompressionMode::CompressWhitespaceNewline;
     for &(test, oracle) in test_strs.iter() {
         let mut trimmed_str = String::new();
-        transform_text(test, mode, true, &mut trimmed_str);
+        transform_text(test, mode, true, &mut trimmed_str, &mut vec![]);
         assert_eq!(&*trimmed_str, oracle)
     }
 }
@@ -154,7 +154,17 @@ fn test_transform_compress_whitespace_newline_no_incoming() {
     let mode = CompressionMode::CompressWhitespaceNewline;
     for &(test, oracle) in test_strs.iter() {
         let mut trimmed_str = String::new();
-        transform_text(test, mode, false, &mut trimmed_str);
+        transform_text(test, mode, false, &mut trimmed_str, &mut vec![]);
         assert_eq!(trimmed_str, oracle)
     }
 }
+
+#[test]
+fn test_transform_records_original_offsets() {
+    let mode = CompressionMode::CompressWhitespaceNewline;
+    let mut trimmed_str = String::from("a");
+    let mut original_offsets = vec![];
+    transform_text("  b \n c", mode, false, &mut trimmed_str, &mut original_offsets);
+    assert_eq!(trimmed_str, "a b c");
+    assert_eq!(original_offsets, vec![(1, 0), (2, 2), (3, 3), (4, 6)]);
+}
@@ -177,6 +177,20 @@ fn test_insecure_cookies_cannot_evict_secure_cookie() {
     assert_eq!(storage.cookies_for_url(&url, source).unwrap(), "foo4=bar; foo3=bar; foo4=value; foo=bar; foo2=bar");
 }
 
+#[test]
+fn test_expired_cookie_removes_existing_cookie() {
+    let mut storage = CookieStorage::new(5);
+    let url = ServoUrl::parse("http://home.example.org:8888/cookie-parser?0001").unwrap();
+    let source = CookieSource::HTTP;
+
+    add_cookie_to_storage(&mut storage, &url, "foo=bar");
+    add_cookie_to_storage(&mut storage, &url, "foo2=bar");
+    assert_eq!(storage.cookies_for_url(&url, source).unwrap(), "foo=bar; foo2=bar");
+
+    add_cookie_to_storage(&mut storage, &url, "foo=bar; Max-Age=0");
+    assert_eq!(storage.cookies_for_url(&url, source).unwrap(), "foo2=bar");
+}
+
 #[test]
 fn test_secure_cookies_eviction() {
     let mut storage = CookieStorage::new(5);
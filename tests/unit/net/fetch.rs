@@ -2,8 +2,8 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use {DEFAULT_USER_AGENT, new_fetch_context, fetch, make_server};
-use devtools_traits::DevtoolsControlMsg;
+use {DECLINING_CREDENTIALS_PROMPT, DEFAULT_USER_AGENT, new_fetch_context, fetch, make_server};
+use devtools_traits::{BlockedUrls, DevtoolsControlMsg, NetworkListeners};
 use devtools_traits::HttpRequest as DevtoolsHttpRequest;
 use devtools_traits::HttpResponse as DevtoolsHttpResponse;
 use fetch_with_context;
@@ -23,7 +23,7 @@ use hyper::status::StatusCode;
 use hyper::uri::RequestUri;
 use hyper_openssl;
 use msg::constellation_msg::TEST_PIPELINE_ID;
-use net::connector::create_ssl_client;
+use net::connector::{ProxyConfig, create_ssl_client};
 use net::fetch::cors_cache::CorsCache;
 use net::fetch::methods::FetchContext;
 use net::filemanager_thread::FileManager;
@@ -32,6 +32,7 @@ use net::test::HttpState;
 use net_traits::IncludeSubdomains;
 use net_traits::NetworkError;
 use net_traits::ReferrerPolicy;
+use net_traits::csp::{CspList, PolicyDisposition, PolicySource};
 use net_traits::request::{Destination, Origin, RedirectMode, Referrer, Request, RequestMode};
 use net_traits::response::{CacheState, Response, ResponseBody, ResponseType};
 use servo_config::resource_files::resources_dir_path;
@@ -77,6 +78,34 @@ fn test_fetch_on_bad_port_is_network_error() {
     assert!(fetch_error == &NetworkError::Internal("Request attempted on bad port".into()))
 }
 
+#[test]
+fn test_fetch_blocked_by_content_security_policy() {
+    let url = ServoUrl::parse("http://www.example.org/image.png").unwrap();
+    let origin = Origin::Origin(ServoUrl::parse("http://example.com").unwrap().origin());
+    let mut request = Request::new(url, Some(origin), None);
+    request.referrer = Referrer::NoReferrer;
+    request.destination = Destination::Image;
+    request.csp_list = CspList::parse("img-src 'self'", PolicySource::Header, PolicyDisposition::Enforce);
+    let fetch_response = fetch(&mut request, None);
+    assert!(fetch_response.is_network_error());
+    let fetch_error = fetch_response.get_network_error().unwrap();
+    assert!(fetch_error == &NetworkError::Internal("Blocked by Content Security Policy".into()))
+}
+
+#[test]
+fn test_fetch_blocked_by_devtools() {
+    let url = ServoUrl::parse("http://www.example.org/ads/banner.png").unwrap();
+    let origin = Origin::Origin(url.origin());
+    let mut request = Request::new(url, Some(origin), None);
+    request.referrer = Referrer::NoReferrer;
+    let context = new_fetch_context(None);
+    context.blocked_urls.set(vec!["example.org/*/banner".to_owned()]);
+    let fetch_response = fetch_with_context(&mut request, &context);
+    assert!(fetch_response.is_network_error());
+    let fetch_error = fetch_response.get_network_error().unwrap();
+    assert!(fetch_error == &NetworkError::Internal("Blocked by devtools".into()))
+}
+
 #[test]
 fn test_fetch_response_body_matches_const_message() {
     static MESSAGE: &'static [u8] = b"Hello World!";
@@ -534,9 +563,11 @@ fn test_fetch_with_hsts() {
     let ssl_client = create_ssl_client(&ca_file);
 
     let context =  FetchContext {
-        state: Arc::new(HttpState::new(ssl_client)),
+        state: Arc::new(HttpState::new(ssl_client, ProxyConfig::default(), DECLINING_CREDENTIALS_PROMPT)),
         user_agent: DEFAULT_USER_AGENT.into(),
         devtools_chan: None,
+        blocked_urls: BlockedUrls::default(),
+        network_listeners: NetworkListeners::default(),
         filemanager: FileManager::new(),
     };
 
@@ -967,6 +998,8 @@ fn test_fetch_with_devtools() {
         status: Some((200, b"OK".to_vec())),
         body: None,
         pipeline_id: TEST_PIPELINE_ID,
+        security_info: None,
+        from_cache: false,
     };
 
     assert_eq!(devhttprequest, httprequest);
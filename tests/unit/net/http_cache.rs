@@ -0,0 +1,57 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use hyper::header::{CacheControl, CacheDirective};
+use net::http_cache::HttpCache;
+use net_traits::request::Request;
+use net_traits::response::{Response, ResponseBody};
+use servo_url::ServoUrl;
+use std::sync::RwLock;
+use time;
+
+fn request_and_response(url: &str, body: ResponseBody) -> (Request, Response) {
+    let url = ServoUrl::parse(url).unwrap();
+    let request = Request::new(url.clone(), None, None);
+    let mut response = Response::new(url);
+    response.headers.set(CacheControl(vec![CacheDirective::MaxAge(3600)]));
+    *response.body.lock().unwrap() = body;
+    (request, response)
+}
+
+fn is_cached(cache: &RwLock<HttpCache>, request: &Request) -> bool {
+    HttpCache::construct_response(cache, request).is_some()
+}
+
+#[test]
+fn test_evicts_least_recently_used_response() {
+    let cache = RwLock::new(HttpCache::with_max_size(20));
+    let (request_a, response_a) = request_and_response("http://example.com/a", ResponseBody::Done(vec![0; 10]));
+    let (request_b, response_b) = request_and_response("http://example.com/b", ResponseBody::Done(vec![0; 10]));
+    let (request_c, response_c) = request_and_response("http://example.com/c", ResponseBody::Done(vec![0; 10]));
+
+    cache.write().unwrap().store(&request_a, &response_a, time::now_utc());
+    cache.write().unwrap().store(&request_b, &response_b, time::now_utc());
+    // Using a makes b the least recently used response.
+    assert!(is_cached(&cache, &request_a));
+    cache.write().unwrap().store(&request_c, &response_c, time::now_utc());
+
+    assert!(is_cached(&cache, &request_a));
+    assert!(!is_cached(&cache, &request_b));
+    assert!(is_cached(&cache, &request_c));
+}
+
+#[test]
+fn test_evicts_once_body_is_received() {
+    let cache = RwLock::new(HttpCache::with_max_size(20));
+    let (request_a, response_a) = request_and_response("http://example.com/a", ResponseBody::Done(vec![0; 10]));
+    let (request_b, response_b) = request_and_response("http://example.com/b", ResponseBody::Receiving(vec![]));
+
+    cache.write().unwrap().store(&request_a, &response_a, time::now_utc());
+    cache.write().unwrap().store(&request_b, &response_b, time::now_utc());
+    *response_b.body.lock().unwrap() = ResponseBody::Done(vec![0; 15]);
+    cache.write().unwrap().body_finished(&response_b.body, false);
+
+    assert!(!is_cached(&cache, &request_a));
+    assert!(is_cached(&cache, &request_b));
+}
@@ -28,18 +28,21 @@ extern crate url;
 #[cfg(test)] mod mime_classifier;
 #[cfg(test)] mod resource_thread;
 #[cfg(test)] mod hsts;
+#[cfg(test)] mod http_cache;
 #[cfg(test)] mod http_loader;
 #[cfg(test)] mod filemanager_thread;
 #[cfg(test)] mod subresource_integrity;
 
-use devtools_traits::DevtoolsControlMsg;
+use devtools_traits::{BlockedUrls, DevtoolsControlMsg, NetworkListeners};
 use hyper::server::{Handler, Listening, Server};
-use net::connector::create_ssl_client;
+use net::connector::{Proxy, ProxyConfig, create_ssl_client};
 use net::fetch::cors_cache::CorsCache;
 use net::fetch::methods::{self, FetchContext};
 use net::filemanager_thread::FileManager;
-use net::test::HttpState;
+use net::resource_thread::AuthCacheEntry;
+use net::test::{HttpState, ProxyCredentialsPrompt};
 use net_traits::FetchTaskTarget;
+use net_traits::csp::Violation;
 use net_traits::request::Request;
 use net_traits::response::Response;
 use servo_config::resource_files::resources_dir_path;
@@ -49,6 +52,17 @@ use std::sync::mpsc::{Sender, channel};
 
 const DEFAULT_USER_AGENT: &'static str = "Such Browser. Very Layout. Wow.";
 
+/// Declines to give credentials to proxies, without showing any UI.
+struct DecliningCredentialsPrompt;
+
+impl ProxyCredentialsPrompt for DecliningCredentialsPrompt {
+    fn prompt_for_credentials(&self, _: &Proxy) -> Option<AuthCacheEntry> {
+        None
+    }
+}
+
+const DECLINING_CREDENTIALS_PROMPT: &'static DecliningCredentialsPrompt = &DecliningCredentialsPrompt;
+
 struct FetchResponseCollector {
     sender: Sender<Response>,
 }
@@ -56,10 +70,17 @@ struct FetchResponseCollector {
 fn new_fetch_context(dc: Option<Sender<DevtoolsControlMsg>>) -> FetchContext {
     let ca_file = resources_dir_path().unwrap().join("certs");
     let ssl_client = create_ssl_client(&ca_file);
+    // Whoever gave us a devtools channel listens to all of the network events.
+    let network_listeners = NetworkListeners::default();
+    if dc.is_some() {
+        network_listeners.add();
+    }
     FetchContext {
-        state: Arc::new(HttpState::new(ssl_client)),
+        state: Arc::new(HttpState::new(ssl_client, ProxyConfig::default(), DECLINING_CREDENTIALS_PROMPT)),
         user_agent: DEFAULT_USER_AGENT.into(),
         devtools_chan: dc,
+        blocked_urls: BlockedUrls::default(),
+        network_listeners: network_listeners,
         filemanager: FileManager::new(),
     }
 }
@@ -72,6 +93,7 @@ impl FetchTaskTarget for FetchResponseCollector {
     fn process_response_eof(&mut self, response: &Response) {
         let _ = self.sender.send(response.clone());
     }
+    fn process_csp_violations(&mut self, _: &Request, _: Vec<Violation>) {}
 }
 
 fn fetch(request: &mut Request, dc: Option<Sender<DevtoolsControlMsg>>) -> Response {
@@ -2,12 +2,22 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use devtools_traits::{BlockedUrls, NetworkListeners};
 use ipc_channel::ipc;
-use net::resource_thread::new_core_resource_thread;
+use net::resource_thread::{new_core_resource_thread, new_resource_threads};
 use net::test::parse_hostsfile;
-use net_traits::CoreResourceMsg;
+use net_traits::{CoreResourceMsg, IpcSend};
+use net_traits::indexeddb_thread::{IndexInfo, IndexedDBConnectionEvent, IndexedDBCursorDirection, IndexedDBKey};
+use net_traits::indexeddb_thread::{IndexedDBKeyRange, IndexedDBOpenEvent, IndexedDBOperation, IndexedDBReply};
+use net_traits::indexeddb_thread::{IndexedDBResult, IndexedDBSource, IndexedDBThreadMsg, IndexedDBTransactionMode};
+use net_traits::indexeddb_thread::{KeyPath, ObjectStoreInfo};
+use net_traits::storage_thread::{StorageThreadMsg, StorageType};
 use profile_traits::time::ProfilerChan;
+use servo_url::ServoUrl;
+use std::env;
+use std::fs;
 use std::net::IpAddr;
+use std::path::PathBuf;
 
 fn ip(s: &str) -> IpAddr {
     s.parse().unwrap()
@@ -18,11 +28,312 @@ fn test_exit() {
     let (tx, _rx) = ipc::channel().unwrap();
     let (sender, receiver) = ipc::channel().unwrap();
     let (resource_thread, _private_resource_thread) = new_core_resource_thread(
-        "".into(), None, ProfilerChan(tx), None);
+        "".into(), None, BlockedUrls::default(), NetworkListeners::default(), ProfilerChan(tx), None);
     resource_thread.send(CoreResourceMsg::Exit(sender)).unwrap();
     receiver.recv().unwrap();
 }
 
+#[test]
+fn test_clear_cache() {
+    let (tx, _rx) = ipc::channel().unwrap();
+    let (sender, receiver) = ipc::channel().unwrap();
+    let (resource_thread, _private_resource_thread) = new_core_resource_thread(
+        "".into(), None, BlockedUrls::default(), NetworkListeners::default(), ProfilerChan(tx), None);
+    resource_thread.send(CoreResourceMsg::ClearCache(sender)).unwrap();
+    receiver.recv().unwrap();
+}
+
+fn new_storage_thread(config_dir: Option<PathBuf>) -> ipc::IpcSender<StorageThreadMsg> {
+    let (tx, _rx) = ipc::channel().unwrap();
+    let (resource_threads, _private_resource_threads) =
+        new_resource_threads("".into(), None, BlockedUrls::default(), NetworkListeners::default(),
+                             ProfilerChan(tx), config_dir);
+    IpcSend::<StorageThreadMsg>::sender(&resource_threads)
+}
+
+fn set_item(storage: &ipc::IpcSender<StorageThreadMsg>, url: &ServoUrl, name: &str, value: &str)
+            -> Result<(bool, Option<String>), ()> {
+    let (sender, receiver) = ipc::channel().unwrap();
+    storage.send(StorageThreadMsg::SetItem(sender, url.clone(), StorageType::Local,
+                                           name.to_owned(), value.to_owned())).unwrap();
+    receiver.recv().unwrap()
+}
+
+fn get_item(storage: &ipc::IpcSender<StorageThreadMsg>, url: &ServoUrl, name: &str) -> Option<String> {
+    let (sender, receiver) = ipc::channel().unwrap();
+    storage.send(StorageThreadMsg::GetItem(sender, url.clone(), StorageType::Local, name.to_owned())).unwrap();
+    receiver.recv().unwrap()
+}
+
+#[test]
+fn test_local_storage_persists_across_restarts() {
+    let config_dir = env::temp_dir().join("servo_local_storage_test");
+    let _ = fs::remove_dir_all(&config_dir);
+    fs::create_dir_all(&config_dir).unwrap();
+    let url = ServoUrl::parse("https://example.com/page.html").unwrap();
+
+    let storage = new_storage_thread(Some(config_dir.clone()));
+    assert_eq!(set_item(&storage, &url, "login", "alice"), Ok((true, None)));
+    let (sender, receiver) = ipc::channel().unwrap();
+    storage.send(StorageThreadMsg::Exit(sender)).unwrap();
+    receiver.recv().unwrap();
+
+    let storage = new_storage_thread(Some(config_dir.clone()));
+    assert_eq!(get_item(&storage, &url, "login"), Some("alice".to_owned()));
+    let (sender, receiver) = ipc::channel().unwrap();
+    storage.send(StorageThreadMsg::Exit(sender)).unwrap();
+    receiver.recv().unwrap();
+
+    let _ = fs::remove_dir_all(&config_dir);
+}
+
+#[test]
+fn test_local_storage_quota() {
+    let storage = new_storage_thread(None);
+    let url = ServoUrl::parse("https://example.com/").unwrap();
+    let big_value: String = ::std::iter::repeat('x').take(5 * 1024 * 1024).collect();
+    assert_eq!(set_item(&storage, &url, "big", &big_value), Err(()));
+    assert_eq!(set_item(&storage, &url, "small", "value"), Ok((true, None)));
+    assert_eq!(get_item(&storage, &url, "big"), None);
+}
+
+#[test]
+fn test_list_and_clear_storage_origins() {
+    let storage = new_storage_thread(None);
+    let first = ServoUrl::parse("https://example.com/").unwrap();
+    let second = ServoUrl::parse("http://example.org:8000/").unwrap();
+    set_item(&storage, &first, "a", "1").unwrap();
+    set_item(&storage, &second, "bc", "23").unwrap();
+
+    let (sender, receiver) = ipc::channel().unwrap();
+    storage.send(StorageThreadMsg::Origins(sender, StorageType::Local)).unwrap();
+    assert_eq!(receiver.recv().unwrap(), vec![("http://example.org:8000".to_owned(), 4),
+                                              ("https://example.com".to_owned(), 2)]);
+
+    let (sender, receiver) = ipc::channel().unwrap();
+    storage.send(StorageThreadMsg::ClearOrigin(sender, "https://example.com".to_owned(), StorageType::Local))
+           .unwrap();
+    assert!(receiver.recv().unwrap());
+    assert_eq!(get_item(&storage, &first, "a"), None);
+    assert_eq!(get_item(&storage, &second, "bc"), Some("23".to_owned()));
+
+    let (sender, receiver) = ipc::channel().unwrap();
+    storage.send(StorageThreadMsg::Origins(sender, StorageType::Session)).unwrap();
+    assert!(receiver.recv().unwrap().is_empty());
+}
+
+fn new_indexeddb_thread() -> ipc::IpcSender<IndexedDBThreadMsg> {
+    let (tx, _rx) = ipc::channel().unwrap();
+    let (resource_threads, _private_resource_threads) =
+        new_resource_threads("".into(), None, BlockedUrls::default(), NetworkListeners::default(),
+                             ProfilerChan(tx), None);
+    IpcSend::<IndexedDBThreadMsg>::sender(&resource_threads)
+}
+
+fn run_operation(indexeddb: &ipc::IpcSender<IndexedDBThreadMsg>, transaction: u64, operation: IndexedDBOperation)
+                 -> IndexedDBResult<IndexedDBReply> {
+    let (sender, receiver) = ipc::channel().unwrap();
+    indexeddb.send(IndexedDBThreadMsg::Operation(sender, transaction, operation)).unwrap();
+    receiver.recv().unwrap()
+}
+
+/// Opens the "library" database, returning the receivers of the events of the request and of
+/// the connection.
+fn open_library(indexeddb: &ipc::IpcSender<IndexedDBThreadMsg>, url: &ServoUrl, version: Option<u64>)
+                -> (ipc::IpcReceiver<IndexedDBOpenEvent>, ipc::IpcReceiver<IndexedDBConnectionEvent>) {
+    let (sender, receiver) = ipc::channel().unwrap();
+    let (events_sender, events_receiver) = ipc::channel().unwrap();
+    indexeddb.send(IndexedDBThreadMsg::Open(sender, url.origin(), "library".to_owned(), version, events_sender))
+             .unwrap();
+    (receiver, events_receiver)
+}
+
+fn commit(indexeddb: &ipc::IpcSender<IndexedDBThreadMsg>, transaction: u64) {
+    let (sender, receiver) = ipc::channel().unwrap();
+    indexeddb.send(IndexedDBThreadMsg::Commit(sender, transaction)).unwrap();
+    assert_eq!(receiver.recv().unwrap(), Ok(()));
+}
+
+/// Creates a database with a "books" object store, in an upgrade transaction that is left running.
+/// Returns the connection and the transaction.
+fn create_books_store(indexeddb: &ipc::IpcSender<IndexedDBThreadMsg>, url: &ServoUrl) -> (u64, u64) {
+    let (receiver, _events) = open_library(indexeddb, url, Some(1));
+    let (connection, transaction) = match receiver.recv().unwrap() {
+        IndexedDBOpenEvent::Upgrade { connection, transaction, version: 1, info } => {
+            assert_eq!(info.version, 0);
+            (connection, transaction)
+        },
+        event => panic!("Opening a new database did not upgrade it: {:?}", event),
+    };
+    let store = ObjectStoreInfo {
+        name: "books".to_owned(),
+        key_path: None,
+        auto_increment: true,
+        indexes: vec![],
+    };
+    assert!(run_operation(indexeddb, transaction, IndexedDBOperation::CreateObjectStore(store)).is_ok());
+    let put = IndexedDBOperation::Put {
+        object_store: "books".to_owned(),
+        key: None,
+        value: vec![1, 2, 3],
+        index_keys: vec![],
+        no_overwrite: true,
+    };
+    match run_operation(indexeddb, transaction, put) {
+        Ok(IndexedDBReply::Key(key)) => assert_eq!(key, IndexedDBKey::Number(1.)),
+        _ => panic!("Storing a record did not reply with its generated key"),
+    }
+    (connection, transaction)
+}
+
+#[test]
+fn test_indexeddb_committed_records_are_readable() {
+    let indexeddb = new_indexeddb_thread();
+    let url = ServoUrl::parse("https://example.com/").unwrap();
+    let (_, transaction) = create_books_store(&indexeddb, &url);
+    commit(&indexeddb, transaction);
+
+    let (receiver, _events) = open_library(&indexeddb, &url, None);
+    match receiver.recv().unwrap() {
+        IndexedDBOpenEvent::Opened(_, info) => {
+            assert_eq!(info.version, 1);
+            assert!(info.object_store("books").is_some());
+        },
+        event => panic!("Opening an existing database did not open it: {:?}", event),
+    }
+
+    let (sender, receiver) = ipc::channel().unwrap();
+    indexeddb.send(IndexedDBThreadMsg::CreateTransaction(sender, url.origin(), "library".to_owned(),
+                                                         IndexedDBTransactionMode::ReadOnly))
+             .unwrap();
+    let transaction = receiver.recv().unwrap();
+    let source = IndexedDBSource {
+        object_store: "books".to_owned(),
+        index: None,
+    };
+    let get = IndexedDBOperation::Get(source, IndexedDBKeyRange::only(IndexedDBKey::Number(1.)));
+    match run_operation(&indexeddb, transaction, get) {
+        Ok(IndexedDBReply::Record(Some(record))) => assert_eq!(record.value, vec![1, 2, 3]),
+        _ => panic!("The stored record was not found"),
+    }
+}
+
+#[test]
+fn test_indexeddb_abort_reverts_upgrade() {
+    let indexeddb = new_indexeddb_thread();
+    let url = ServoUrl::parse("https://example.org/").unwrap();
+    let (connection, transaction) = create_books_store(&indexeddb, &url);
+    let (sender, receiver) = ipc::channel().unwrap();
+    indexeddb.send(IndexedDBThreadMsg::Abort(sender, transaction)).unwrap();
+    receiver.recv().unwrap();
+    indexeddb.send(IndexedDBThreadMsg::CloseConnection(connection)).unwrap();
+
+    let (receiver, _events) = open_library(&indexeddb, &url, None);
+    match receiver.recv().unwrap() {
+        IndexedDBOpenEvent::Upgrade { info, .. } => assert_eq!(info.version, 0),
+        event => panic!("The aborted upgrade still created the database: {:?}", event),
+    }
+}
+
+#[test]
+fn test_indexeddb_upgrade_waits_for_other_connections() {
+    let indexeddb = new_indexeddb_thread();
+    let url = ServoUrl::parse("https://example.net/").unwrap();
+    let (first, transaction) = create_books_store(&indexeddb, &url);
+    commit(&indexeddb, transaction);
+
+    let (receiver, second_events) = open_library(&indexeddb, &url, Some(1));
+    let second = match receiver.recv().unwrap() {
+        IndexedDBOpenEvent::Opened(connection, _) => connection,
+        event => panic!("Opening at the current version did not open the database: {:?}", event),
+    };
+
+    // Both connections are asked to close, and the upgrade is blocked until they have
+    // handled that and closed.
+    let (receiver, _events) = open_library(&indexeddb, &url, Some(2));
+    match second_events.recv().unwrap() {
+        IndexedDBConnectionEvent::VersionChange { connection, old_version: 1, new_version: Some(2) } => {
+            assert_eq!(connection, second)
+        },
+        event => panic!("The open connection was not sent a versionchange event: {:?}", event),
+    }
+    indexeddb.send(IndexedDBThreadMsg::CloseConnection(first)).unwrap();
+    indexeddb.send(IndexedDBThreadMsg::VersionChangeFired(second)).unwrap();
+    match receiver.recv().unwrap() {
+        IndexedDBOpenEvent::Blocked(1, 2) => {},
+        event => panic!("The upgrade was not blocked by the open connection: {:?}", event),
+    }
+    indexeddb.send(IndexedDBThreadMsg::CloseConnection(second)).unwrap();
+    match receiver.recv().unwrap() {
+        IndexedDBOpenEvent::Upgrade { version: 2, info, .. } => assert_eq!(info.version, 1),
+        event => panic!("The upgrade did not start once connections closed: {:?}", event),
+    }
+}
+
+#[test]
+fn test_indexeddb_cursor_iterates_index() {
+    let indexeddb = new_indexeddb_thread();
+    let url = ServoUrl::parse("https://example.edu/").unwrap();
+    let (receiver, _events) = open_library(&indexeddb, &url, Some(1));
+    let transaction = match receiver.recv().unwrap() {
+        IndexedDBOpenEvent::Upgrade { transaction, .. } => transaction,
+        event => panic!("Opening a new database did not upgrade it: {:?}", event),
+    };
+    let store = ObjectStoreInfo {
+        name: "books".to_owned(),
+        key_path: None,
+        auto_increment: false,
+        indexes: vec![IndexInfo {
+            name: "author".to_owned(),
+            key_path: KeyPath::String("author".to_owned()),
+            unique: false,
+            multi_entry: false,
+        }],
+    };
+    assert!(run_operation(&indexeddb, transaction, IndexedDBOperation::CreateObjectStore(store)).is_ok());
+    for &(key, author) in &[(1., "b"), (2., "a"), (3., "b"), (4., "c")] {
+        let put = IndexedDBOperation::Put {
+            object_store: "books".to_owned(),
+            key: Some(IndexedDBKey::Number(key)),
+            value: vec![],
+            index_keys: vec![("author".to_owned(), vec![IndexedDBKey::String(author.to_owned())])],
+            no_overwrite: true,
+        };
+        assert!(run_operation(&indexeddb, transaction, put).is_ok());
+    }
+
+    let entry = |author: &str, key: f64| (IndexedDBKey::String(author.to_owned()), IndexedDBKey::Number(key));
+    let iterate = |direction, position, key: Option<&str>, primary_key: Option<f64>, count| {
+        let iterate = IndexedDBOperation::Iterate {
+            source: IndexedDBSource {
+                object_store: "books".to_owned(),
+                index: Some("author".to_owned()),
+            },
+            range: IndexedDBKeyRange::unbounded(),
+            direction: direction,
+            position: position,
+            key: key.map(|key| IndexedDBKey::String(key.to_owned())),
+            primary_key: primary_key.map(IndexedDBKey::Number),
+            count: count,
+        };
+        match run_operation(&indexeddb, transaction, iterate) {
+            Ok(IndexedDBReply::Record(record)) => record.map(|record| (record.key, record.primary_key)),
+            _ => panic!("Iterating a cursor failed"),
+        }
+    };
+
+    assert_eq!(iterate(IndexedDBCursorDirection::Next, None, None, None, 1), Some(entry("a", 2.)));
+    assert_eq!(iterate(IndexedDBCursorDirection::Next, None, None, None, 2), Some(entry("b", 1.)));
+    assert_eq!(iterate(IndexedDBCursorDirection::Next, Some(entry("b", 1.)), None, None, 1), Some(entry("b", 3.)));
+    assert_eq!(iterate(IndexedDBCursorDirection::Next, Some(entry("a", 2.)), Some("b"), Some(2.), 1),
+               Some(entry("b", 3.)));
+    assert_eq!(iterate(IndexedDBCursorDirection::NextUnique, Some(entry("a", 2.)), None, None, 2),
+               Some(entry("c", 4.)));
+    assert_eq!(iterate(IndexedDBCursorDirection::Prev, Some(entry("b", 3.)), None, None, 1), Some(entry("b", 1.)));
+    assert_eq!(iterate(IndexedDBCursorDirection::PrevUnique, None, None, None, 2), Some(entry("b", 1.)));
+    assert_eq!(iterate(IndexedDBCursorDirection::Prev, None, Some("a"), None, 2), None);
+}
+
 #[test]
 fn test_parse_hostsfile() {
     let mock_hosts_file_content = "127.0.0.1 foo.bar.com\n127.0.0.2 servo.test.server";
@@ -0,0 +1,115 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use hyper::header::Headers;
+use net_traits::csp::{CheckResult, CspList, InlineCheckType, PolicyDisposition, PolicySource};
+use net_traits::csp::ViolationResource;
+use net_traits::request::{Destination, Origin, Request};
+use servo_url::ServoUrl;
+
+fn enforced(policy: &str) -> CspList {
+    CspList::parse(policy, PolicySource::Header, PolicyDisposition::Enforce)
+}
+
+fn check_request(list: &CspList, url: &str, destination: Destination) -> CheckResult {
+    let origin = ServoUrl::parse("https://example.com/index.html").unwrap().origin();
+    let mut request = Request::new(ServoUrl::parse(url).unwrap(), Some(Origin::Origin(origin)), None);
+    request.destination = destination;
+    list.should_request_be_blocked(&request).0
+}
+
+#[test]
+fn test_parse_policy_list() {
+    let list = enforced("script-src 'self' https://cdn.example.com; SCRIPT-SRC 'none';;, img-src *");
+    assert_eq!(list.0.len(), 2);
+    let policy = &list.0[0];
+    assert_eq!(policy.directives.len(), 1);
+    assert_eq!(policy.directives[0].name, "script-src");
+    assert_eq!(policy.directives[0].value, vec!["'self'".to_owned(), "https://cdn.example.com".to_owned()]);
+    assert_eq!(list.0[1].text, "img-src *");
+}
+
+#[test]
+fn test_parse_meta_policy_ignores_header_only_directives() {
+    let list = CspList::parse("default-src 'self'; report-uri /csp", PolicySource::Meta, PolicyDisposition::Enforce);
+    assert_eq!(list.0[0].directives.len(), 1);
+    assert_eq!(list.0[0].directives[0].name, "default-src");
+}
+
+#[test]
+fn test_parse_headers() {
+    let mut headers = Headers::new();
+    headers.set_raw("Content-Security-Policy", vec![b"img-src 'none'".to_vec()]);
+    headers.set_raw("Content-Security-Policy-Report-Only", vec![b"script-src 'self'".to_vec()]);
+    let list = CspList::from_headers(&headers);
+    assert_eq!(list.0.len(), 2);
+    assert_eq!(list.0[0].disposition, PolicyDisposition::Enforce);
+    assert_eq!(list.0[1].disposition, PolicyDisposition::Report);
+}
+
+#[test]
+fn test_request_matches_source_expressions() {
+    let list = enforced("default-src 'self'; img-src https://*.example.org:* data:; script-src example.net/js/");
+    assert_eq!(check_request(&list, "https://example.com/a.css", Destination::Style), CheckResult::Allowed);
+    assert_eq!(check_request(&list, "https://other.com/a.css", Destination::Style), CheckResult::Blocked);
+    assert_eq!(check_request(&list, "https://img.example.org:8443/a.png", Destination::Image),
+               CheckResult::Allowed);
+    assert_eq!(check_request(&list, "https://example.org/a.png", Destination::Image), CheckResult::Blocked);
+    assert_eq!(check_request(&list, "data:image/png,", Destination::Image), CheckResult::Allowed);
+    assert_eq!(check_request(&list, "https://example.net/js/app.js", Destination::Script), CheckResult::Allowed);
+    assert_eq!(check_request(&list, "https://example.net/app.js", Destination::Script), CheckResult::Blocked);
+    assert_eq!(check_request(&list, "http://example.net/js/app.js", Destination::Script), CheckResult::Blocked);
+}
+
+#[test]
+fn test_report_only_policy_reports_without_blocking() {
+    let list = CspList::parse("img-src 'none'; report-uri /report", PolicySource::Header, PolicyDisposition::Report);
+    let origin = ServoUrl::parse("https://example.com/").unwrap().origin();
+    let url = ServoUrl::parse("https://example.com/a.png").unwrap();
+    let mut request = Request::new(url.clone(), Some(Origin::Origin(origin)), None);
+    request.destination = Destination::Image;
+    let (result, violations) = list.should_request_be_blocked(&request);
+    assert_eq!(result, CheckResult::Allowed);
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].directive, "img-src");
+    assert_eq!(violations[0].resource, ViolationResource::Url(url));
+    assert_eq!(violations[0].report_uris, vec!["/report".to_owned()]);
+}
+
+#[test]
+fn test_inline_checks() {
+    let list = enforced("script-src 'nonce-abc' 'unsafe-inline' 'report-sample'; style-src 'unsafe-inline'");
+    let (result, violations) = list.should_inline_be_blocked(InlineCheckType::Script, None, "alert(1)");
+    assert_eq!(result, CheckResult::Blocked);
+    assert_eq!(violations[0].sample, Some("alert(1)".to_owned()));
+    assert_eq!(list.should_inline_be_blocked(InlineCheckType::Script, Some("abc"), "").0, CheckResult::Allowed);
+    assert_eq!(list.should_inline_be_blocked(InlineCheckType::ScriptAttribute, Some("abc"), "").0,
+               CheckResult::Blocked);
+    assert_eq!(list.should_inline_be_blocked(InlineCheckType::Style, None, "").0, CheckResult::Allowed);
+}
+
+#[test]
+fn test_hash_sources() {
+    let check = |policy: &str, check_type: InlineCheckType, source: &str| {
+        enforced(policy).should_inline_be_blocked(check_type, None, source).0
+    };
+    let hash = "'sha256-bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF+pI='";
+    let policy = format!("script-src {}", hash);
+    assert_eq!(check(&policy, InlineCheckType::Script, "alert(1)"), CheckResult::Allowed);
+    assert_eq!(check(&policy, InlineCheckType::Script, "alert(2)"), CheckResult::Blocked);
+    // Attributes only match hashes along with 'unsafe-hashes'.
+    assert_eq!(check(&policy, InlineCheckType::ScriptAttribute, "alert(1)"), CheckResult::Blocked);
+    let policy = format!("script-src 'unsafe-hashes' {}", hash);
+    assert_eq!(check(&policy, InlineCheckType::ScriptAttribute, "alert(1)"), CheckResult::Allowed);
+    // The base64url alphabet is accepted as well.
+    let policy = "script-src 'sha256-bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF-pI='";
+    assert_eq!(check(policy, InlineCheckType::Script, "alert(1)"), CheckResult::Allowed);
+}
+
+#[test]
+fn test_eval_check() {
+    assert_eq!(enforced("default-src 'self'").is_js_evaluation_allowed().0, CheckResult::Blocked);
+    assert_eq!(enforced("script-src 'unsafe-eval'").is_js_evaluation_allowed().0, CheckResult::Allowed);
+    assert_eq!(enforced("img-src 'none'").is_js_evaluation_allowed().0, CheckResult::Allowed);
+}
@@ -2,8 +2,11 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+extern crate hyper;
 extern crate net_traits;
+extern crate servo_url;
 
+#[cfg(test)] mod csp;
 #[cfg(test)] mod image;
 #[cfg(test)] mod pub_domains;
 
@@ -0,0 +1,97 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use profile::chrome_trace::ChromeTrace;
+use profile_traits::time::{ProfilerCategory, ProfilerThread, TimerMetadata, TimerMetadataFrameType};
+use profile_traits::time::TimerMetadataReflowType;
+use serde_json::{self, Value};
+
+fn thread(id: usize, name: &str) -> Option<ProfilerThread> {
+    Some(ProfilerThread { id: id, name: Some(name.to_owned()) })
+}
+
+fn thread_names(events: &[Value]) -> Vec<&str> {
+    events.iter()
+          .filter(|event| event["ph"] == "M" && event["name"] == "thread_name")
+          .map(|event| event["args"]["name"].as_str().unwrap())
+          .collect()
+}
+
+fn phases(events: &[Value], tid: u64) -> Vec<(String, String, f64)> {
+    events.iter()
+          .filter(|event| event["tid"] == tid && event["ph"] != "M")
+          .map(|event| {
+              (event["ph"].as_str().unwrap().to_owned(),
+               event["name"].as_str().unwrap().to_owned(),
+               event["ts"].as_f64().unwrap())
+          })
+          .collect()
+}
+
+#[test]
+fn test_chrome_trace_nests_sections() {
+    let mut trace = ChromeTrace::new();
+    // Inner sections are reported before the sections they are nested in.
+    trace.add(thread(7, "Layout"), &(ProfilerCategory::LayoutStyleRecalc, None), (2000, 3000));
+    trace.add(thread(7, "Layout"), &(ProfilerCategory::LayoutMain, None), (3000, 5000));
+    trace.add(thread(7, "Layout"), &(ProfilerCategory::LayoutPerform, None), (1000, 6000));
+    trace.add(thread(3, "Script"), &(ProfilerCategory::ScriptEvaluate, None), (1500, 2500));
+
+    let events = trace.events();
+    assert_eq!(phases(&events, 0), vec![
+        ("B".to_owned(), "Layout".to_owned(), 0.),
+        ("B".to_owned(), "Style Recalc".to_owned(), 1.),
+        ("E".to_owned(), "Style Recalc".to_owned(), 2.),
+        ("B".to_owned(), "Primary Layout Pass".to_owned(), 2.),
+        ("E".to_owned(), "Primary Layout Pass".to_owned(), 4.),
+        ("E".to_owned(), "Layout".to_owned(), 5.),
+    ]);
+    assert_eq!(phases(&events, 1), vec![
+        ("B".to_owned(), "Script JS Evaluate".to_owned(), 0.5),
+        ("E".to_owned(), "Script JS Evaluate".to_owned(), 1.5),
+    ]);
+
+    assert_eq!(thread_names(&events), vec!["Layout", "Script"]);
+}
+
+#[test]
+fn test_chrome_trace_keys_threads_by_id() {
+    let mut trace = ChromeTrace::new();
+    trace.add(thread(1, "Worker"), &(ProfilerCategory::ScriptEvaluate, None), (0, 1000));
+    trace.add(thread(2, "Worker"), &(ProfilerCategory::ScriptParseHTML, None), (500, 1500));
+    trace.add(thread(1, "Worker"), &(ProfilerCategory::ScriptEvaluate, None), (2000, 3000));
+    trace.add(None, &(ProfilerCategory::ApplicationHeartbeat, None), (0, 3000));
+
+    let events = trace.events();
+    assert_eq!(thread_names(&events), vec!["Worker", "Worker", "Unnamed"]);
+    assert_eq!(phases(&events, 0).len(), 4);
+    assert_eq!(phases(&events, 1), vec![
+        ("B".to_owned(), "Script Parse HTML".to_owned(), 0.5),
+        ("E".to_owned(), "Script Parse HTML".to_owned(), 1.5),
+    ]);
+    assert_eq!(phases(&events, 2).len(), 2);
+}
+
+#[test]
+fn test_chrome_trace_metadata() {
+    let mut trace = ChromeTrace::new();
+    let metadata = TimerMetadata {
+        url: "http://example.com/".to_owned(),
+        iframe: TimerMetadataFrameType::IFrame,
+        incremental: TimerMetadataReflowType::FirstReflow,
+    };
+    trace.add(None, &(ProfilerCategory::LayoutPerform, Some(metadata)), (0, 1000));
+
+    let mut output = vec![];
+    trace.write_to(&mut output).unwrap();
+    let output: Value = serde_json::from_slice(&output).unwrap();
+    let events = output["traceEvents"].as_array().unwrap();
+    assert_eq!(events[0]["args"]["name"], "Unnamed");
+    assert_eq!(events[1]["ph"], "B");
+    assert_eq!(events[1]["args"]["url"], "http://example.com/");
+    assert_eq!(events[1]["args"]["iframe"], true);
+    assert_eq!(events[1]["args"]["incremental"], false);
+    assert_eq!(events[2]["ph"], "E");
+    assert!(events[2].get("args").is_none());
+}
@@ -5,7 +5,12 @@
 extern crate ipc_channel;
 extern crate profile;
 extern crate profile_traits;
+extern crate serde_json;
 extern crate servo_allocator;
 
+#[cfg(test)]
+mod chrome_trace;
+#[cfg(test)]
+mod mem;
 #[cfg(test)]
 mod time;
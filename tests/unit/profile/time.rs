@@ -4,7 +4,7 @@
 
 use ipc_channel::ipc;
 use profile::time;
-use profile_traits::time::ProfilerMsg;
+use profile_traits::time::{ProfilerCategory, ProfilerMsg};
 
 #[test]
 fn time_profiler_smoke_test() {
@@ -16,6 +16,23 @@ fn time_profiler_smoke_test() {
     assert!(true, "Can tell the profiler thread to exit");
 }
 
+#[test]
+fn time_profiler_listen_test() {
+    let chan = time::Profiler::create(&None, None);
+    let (listener, samples) = ipc::channel().unwrap();
+    chan.send(ProfilerMsg::Listen(listener));
+    chan.send(ProfilerMsg::Time((ProfilerCategory::LayoutPerform, None), (1000, 3000), (0, 0), None));
+
+    let sample = samples.recv().unwrap();
+    assert_eq!(sample.category, ProfilerCategory::LayoutPerform);
+    assert!(sample.metadata.is_none());
+    assert_eq!((sample.start, sample.end), (1000, 3000));
+
+    let (ipcchan, ipcport) = ipc::channel().unwrap();
+    chan.send(ProfilerMsg::Exit(ipcchan));
+    ipcport.recv().unwrap();
+}
+
 #[test]
 fn time_profiler_stats_test() {
     let even_data = vec![1.234, 3.24567, 3.54578, 5.0, 5.324, 7.345,
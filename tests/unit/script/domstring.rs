@@ -0,0 +1,60 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use script::test::DOMString;
+
+#[test]
+fn test_floating_point_number_strings() {
+    assert!(DOMString::from("1").is_valid_floating_point_number_string());
+    assert!(DOMString::from("-1.5e3").is_valid_floating_point_number_string());
+    assert!(!DOMString::from("+1").is_valid_floating_point_number_string());
+    assert!(!DOMString::from("1.").is_valid_floating_point_number_string());
+    assert!(!DOMString::from("").is_valid_floating_point_number_string());
+}
+
+#[test]
+fn test_simple_color_strings() {
+    assert!(DOMString::from("#00ff7F").is_valid_simple_color_string());
+    assert!(!DOMString::from("#fff").is_valid_simple_color_string());
+    assert!(!DOMString::from("red").is_valid_simple_color_string());
+}
+
+#[test]
+fn test_date_strings() {
+    assert_eq!(DOMString::from("2016-02-29").parse_date_string(), Ok((2016, 2, 29)));
+    assert!(DOMString::from("2015-02-29").parse_date_string().is_err());
+    assert!(DOMString::from("0000-01-01").parse_date_string().is_err());
+    assert!(DOMString::from("16-01-01").parse_date_string().is_err());
+    assert_eq!(DOMString::from("2017-12").parse_month_string(), Ok((2017, 12)));
+    assert!(DOMString::from("2017-13").parse_month_string().is_err());
+}
+
+#[test]
+fn test_week_strings() {
+    assert_eq!(DOMString::from("2015-W53").parse_week_string(), Ok((2015, 53)));
+    assert!(DOMString::from("2017-W53").parse_week_string().is_err());
+    assert!(DOMString::from("2017-w01").parse_week_string().is_err());
+}
+
+#[test]
+fn test_time_strings() {
+    assert_eq!(DOMString::from("23:59").parse_time_string(), Ok((23, 59, 0.)));
+    assert_eq!(DOMString::from("00:00:01.5").parse_time_string(), Ok((0, 0, 1.5)));
+    assert!(DOMString::from("24:00").parse_time_string().is_err());
+    assert!(DOMString::from("12:00:").parse_time_string().is_err());
+}
+
+#[test]
+fn test_normalize_local_date_and_time_strings() {
+    let mut value = DOMString::from("2017-01-01 10:00:00.100");
+    assert!(value.convert_valid_normalized_local_date_and_time_string().is_ok());
+    assert_eq!(&*value, "2017-01-01T10:00:00.1");
+
+    let mut value = DOMString::from("2017-01-01T10:00:00");
+    assert!(value.convert_valid_normalized_local_date_and_time_string().is_ok());
+    assert_eq!(&*value, "2017-01-01T10:00");
+
+    let mut value = DOMString::from("2017-01-01");
+    assert!(value.convert_valid_normalized_local_date_and_time_string().is_err());
+}
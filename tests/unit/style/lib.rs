@@ -31,6 +31,7 @@ mod media_queries;
 mod parsing;
 mod properties;
 mod rule_tree;
+mod shadow_dom;
 mod size_of;
 #[path = "../stylo/specified_values.rs"]
 mod specified_values;
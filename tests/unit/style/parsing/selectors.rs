@@ -28,3 +28,21 @@ fn test_selectors() {
     assert_roundtrip!(parse_selector, "* > *");
     assert_roundtrip!(parse_selector, "*|* + *", "* + *");
 }
+
+#[test]
+fn test_shadow_dom_selectors() {
+    assert_roundtrip!(parse_selector, ":host");
+    assert_roundtrip!(parse_selector, ":host(.foo)");
+    assert_roundtrip!(parse_selector, ":HOST(div)", ":host(div)");
+    assert_roundtrip!(parse_selector, ":host > div");
+    assert_roundtrip!(parse_selector, "::slotted(span)");
+    assert_roundtrip!(parse_selector, "div::slotted(span.foo)");
+    assert_roundtrip!(parse_selector, ":host > slot::slotted(*)");
+
+    for invalid in &[":host()", ":host(div > span)", ":host(::before)",
+                     "::slotted()", "::slotted(span) div", "::slotted(span)::before",
+                     "::slotted(div span)", ":not(::slotted(span))"] {
+        let mut input = ParserInput::new(invalid);
+        assert!(parse_selector(&mut Parser::new(&mut input)).is_err(), "{} should not parse", invalid);
+    }
+}
@@ -0,0 +1,255 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use selectors::{Element, OpaqueElement};
+use selectors::attr::{AttrSelectorOperation, CaseSensitivity, NamespaceConstraint};
+use selectors::matching::{self, ElementSelectorFlags, MatchingContext, MatchingMode};
+use selectors::matching::{QuirksMode, RelevantLinkStatus};
+use servo_atoms::Atom;
+use style::{LocalName, Namespace};
+use style::selector_parser::{NonTSPseudoClass, PseudoElement, SelectorImpl, SelectorParser};
+
+/// A node of a mock tree, with just enough state to match `:host` and
+/// `::slotted()`.
+#[derive(Debug)]
+struct MockNode {
+    local_name: LocalName,
+    namespace: Namespace,
+    class: Option<Atom>,
+    parent: Option<usize>,
+    /// Whether `parent` is the shadow root of `host` rather than an element.
+    parent_is_shadow_root: bool,
+    host: Option<usize>,
+    slot: Option<usize>,
+}
+
+impl MockNode {
+    fn new(local_name: LocalName, class: Option<&str>) -> Self {
+        MockNode {
+            local_name: local_name,
+            namespace: ns!(html),
+            class: class.map(Atom::from),
+            parent: None,
+            parent_is_shadow_root: false,
+            host: None,
+            slot: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct MockElement<'a> {
+    tree: &'a [MockNode],
+    index: usize,
+}
+
+impl<'a> MockElement<'a> {
+    fn node(&self) -> &'a MockNode {
+        &self.tree[self.index]
+    }
+
+    fn get(&self, index: Option<usize>) -> Option<Self> {
+        index.map(|index| MockElement { tree: self.tree, index: index })
+    }
+}
+
+impl<'a> Element for MockElement<'a> {
+    type Impl = SelectorImpl;
+
+    fn opaque(&self) -> OpaqueElement {
+        OpaqueElement::new(self.node())
+    }
+
+    fn parent_element(&self) -> Option<Self> {
+        if self.node().parent_is_shadow_root {
+            return None;
+        }
+        self.get(self.node().parent)
+    }
+
+    fn first_child_element(&self) -> Option<Self> {
+        None
+    }
+
+    fn last_child_element(&self) -> Option<Self> {
+        None
+    }
+
+    fn prev_sibling_element(&self) -> Option<Self> {
+        None
+    }
+
+    fn next_sibling_element(&self) -> Option<Self> {
+        None
+    }
+
+    fn is_html_element_in_html_document(&self) -> bool {
+        true
+    }
+
+    fn get_local_name(&self) -> &LocalName {
+        &self.node().local_name
+    }
+
+    fn get_namespace(&self) -> &Namespace {
+        &self.node().namespace
+    }
+
+    fn attr_matches(&self,
+                    _ns: &NamespaceConstraint<&Namespace>,
+                    _local_name: &LocalName,
+                    _operation: &AttrSelectorOperation<&String>)
+                    -> bool {
+        false
+    }
+
+    fn match_non_ts_pseudo_class<F>(
+        &self,
+        _pc: &NonTSPseudoClass,
+        _context: &mut MatchingContext<SelectorImpl>,
+        _relevant_link: &RelevantLinkStatus,
+        _flags_setter: &mut F,
+    ) -> bool
+    where
+        F: FnMut(&Self, ElementSelectorFlags),
+    {
+        false
+    }
+
+    fn match_pseudo_element(&self, _pe: &PseudoElement, _context: &mut MatchingContext<SelectorImpl>) -> bool {
+        false
+    }
+
+    fn is_link(&self) -> bool {
+        false
+    }
+
+    fn has_id(&self, _id: &Atom, _case_sensitivity: CaseSensitivity) -> bool {
+        false
+    }
+
+    fn has_class(&self, name: &Atom, _case_sensitivity: CaseSensitivity) -> bool {
+        self.node().class.as_ref() == Some(name)
+    }
+
+    fn is_empty(&self) -> bool {
+        !self.tree.iter().any(|node| node.parent == Some(self.index))
+    }
+
+    fn is_root(&self) -> bool {
+        self.node().parent.is_none()
+    }
+
+    fn parent_node_is_shadow_root(&self) -> bool {
+        self.node().parent_is_shadow_root
+    }
+
+    fn containing_shadow_host(&self) -> Option<Self> {
+        self.get(self.node().host)
+    }
+
+    fn assigned_slot(&self) -> Option<Self> {
+        self.get(self.node().slot)
+    }
+}
+
+const OUTER: usize = 0;
+const HOST: usize = 1;
+const INNER: usize = 2;
+const SLOT: usize = 3;
+const SLOTTED: usize = 4;
+
+/// Builds the following tree:
+///
+/// ```html
+/// <div class="outer">
+///   <div class="host">
+///     #shadow-root
+///       <p class="inner"><slot></slot></p>
+///     <span class="foo"></span>
+///   </div>
+/// </div>
+/// ```
+fn mock_tree() -> Vec<MockNode> {
+    let mut host = MockNode::new(local_name!("div"), Some("host"));
+    host.parent = Some(OUTER);
+
+    let mut inner = MockNode::new(local_name!("p"), Some("inner"));
+    inner.parent = Some(HOST);
+    inner.parent_is_shadow_root = true;
+    inner.host = Some(HOST);
+
+    let mut slot = MockNode::new(local_name!("slot"), None);
+    slot.parent = Some(INNER);
+    slot.host = Some(HOST);
+
+    let mut slotted = MockNode::new(local_name!("span"), Some("foo"));
+    slotted.parent = Some(HOST);
+    slotted.slot = Some(SLOT);
+
+    vec![MockNode::new(local_name!("div"), Some("outer")), host, inner, slot, slotted]
+}
+
+fn matches(tree: &[MockNode], selector: &str, index: usize, current_host: Option<usize>) -> bool {
+    let selectors = SelectorParser::parse_author_origin_no_namespace(selector).unwrap();
+    let element = MockElement { tree: tree, index: index };
+    let mut context = MatchingContext::new(MatchingMode::Normal, None, None, QuirksMode::NoQuirks);
+    context.current_host = current_host.map(|host| OpaqueElement::new(&tree[host]));
+    matching::matches_selector_list(&selectors, &element, &mut context)
+}
+
+#[test]
+fn test_host_matching() {
+    let tree = mock_tree();
+
+    assert!(matches(&tree, ":host", HOST, Some(HOST)));
+    assert!(!matches(&tree, ":host", HOST, None));
+    assert!(!matches(&tree, ":host", INNER, Some(HOST)));
+    assert!(!matches(&tree, ":host", OUTER, Some(HOST)));
+
+    assert!(matches(&tree, ":host(.host)", HOST, Some(HOST)));
+    assert!(matches(&tree, ":host(div)", HOST, Some(HOST)));
+    assert!(!matches(&tree, ":host(.outer)", HOST, Some(HOST)));
+
+    // The host is featureless for the selectors of its shadow tree.
+    assert!(!matches(&tree, ".host", HOST, Some(HOST)));
+    assert!(!matches(&tree, "div", HOST, Some(HOST)));
+    assert!(matches(&tree, ".host", HOST, None));
+}
+
+#[test]
+fn test_shadow_tree_ancestor_combinators() {
+    let tree = mock_tree();
+
+    assert!(matches(&tree, ":host > p", INNER, Some(HOST)));
+    assert!(matches(&tree, ":host slot", SLOT, Some(HOST)));
+    assert!(matches(&tree, ":host(.host) > .inner > slot", SLOT, Some(HOST)));
+
+    // Ancestor combinators stop at the host, and only `:host` matches it.
+    assert!(!matches(&tree, "div p", INNER, Some(HOST)));
+    assert!(!matches(&tree, ".outer p", INNER, Some(HOST)));
+    assert!(!matches(&tree, ".outer :host > p", INNER, Some(HOST)));
+}
+
+#[test]
+fn test_slotted_matching() {
+    let tree = mock_tree();
+
+    assert!(matches(&tree, "::slotted(span)", SLOTTED, Some(HOST)));
+    assert!(matches(&tree, "::slotted(.foo)", SLOTTED, Some(HOST)));
+    assert!(matches(&tree, "::slotted(*)", SLOTTED, Some(HOST)));
+    assert!(!matches(&tree, "::slotted(div)", SLOTTED, Some(HOST)));
+
+    // Only elements assigned to a slot of the current shadow tree match.
+    assert!(!matches(&tree, "::slotted(span)", SLOTTED, None));
+    assert!(!matches(&tree, "::slotted(span)", SLOTTED, Some(OUTER)));
+    assert!(!matches(&tree, "::slotted(p)", INNER, Some(HOST)));
+
+    // The compound selector to the left of `::slotted()` matches the slot.
+    assert!(matches(&tree, "slot::slotted(span)", SLOTTED, Some(HOST)));
+    assert!(matches(&tree, ".inner > slot::slotted(.foo)", SLOTTED, Some(HOST)));
+    assert!(matches(&tree, ":host slot::slotted(span)", SLOTTED, Some(HOST)));
+    assert!(!matches(&tree, "div::slotted(span)", SLOTTED, Some(HOST)));
+    assert!(!matches(&tree, ".outer slot::slotted(span)", SLOTTED, Some(HOST)));
+}
@@ -19,6 +19,7 @@ test_interfaces([
   "CustomEvent",
   "DedicatedWorkerGlobalScope",
   "DOMException",
+  "DOMStringList",
   "ErrorEvent",
   "Event",
   "EventSource",
@@ -30,6 +31,17 @@ test_interfaces([
   "FormData",
   "Headers",
   "History",
+  "IDBCursor",
+  "IDBCursorWithValue",
+  "IDBDatabase",
+  "IDBFactory",
+  "IDBIndex",
+  "IDBKeyRange",
+  "IDBObjectStore",
+  "IDBOpenDBRequest",
+  "IDBRequest",
+  "IDBTransaction",
+  "IDBVersionChangeEvent",
   "ImageData",
   "MessageEvent",
   "Performance",
@@ -42,6 +54,7 @@ test_interfaces([
   "ProgressEvent",
   "Request",
   "Response",
+  "SecurityPolicyViolationEvent",
   "TextDecoder",
   "TextEncoder",
   "URL",